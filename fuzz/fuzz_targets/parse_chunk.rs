@@ -0,0 +1,7 @@
+#![no_main]
+use anvil_region::parse_chunk_payload;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|bytes: &[u8]| {
+    let _ = parse_chunk_payload(bytes);
+});
@@ -0,0 +1,17 @@
+#![no_main]
+use anvil_region::{parse_region_bytes, AnvilRegion};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+fuzz_target!(|bytes: &[u8]| {
+    if let Ok(chunks) = parse_region_bytes(bytes) {
+        for (_, raw_chunk) in chunks {
+            let _ = raw_chunk.decode();
+        }
+    }
+
+    // Opening a region must not panic either, whatever the header says.
+    if let Ok(mut region) = AnvilRegion::new(Cursor::new(bytes.to_vec())) {
+        let _ = region.reserve_sectors(1);
+    }
+});
@@ -0,0 +1,67 @@
+use crate::{AnvilChunkMetadata, AnvilRegionHeader, Compression, REGION_CHUNKS};
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+impl<'a> Arbitrary<'a> for AnvilChunkMetadata {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        // Sector indexes are stored in 3 bytes of the header.
+        let sector_index = u.int_in_range(0..=0xFF_FFFF)?;
+
+        Ok(AnvilChunkMetadata::new(
+            sector_index,
+            u.arbitrary()?,
+            u.arbitrary()?,
+        ))
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (8, Some(8))
+    }
+}
+
+impl<'a> Arbitrary<'a> for AnvilRegionHeader {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut chunks_metadata = [AnvilChunkMetadata::default(); REGION_CHUNKS];
+
+        for metadata in chunks_metadata.iter_mut() {
+            *metadata = u.arbitrary()?;
+        }
+
+        Ok(AnvilRegionHeader { chunks_metadata })
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        let (min, max) = AnvilChunkMetadata::size_hint(depth);
+
+        (min * REGION_CHUNKS, max.map(|max| max * REGION_CHUNKS))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Compression {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(*u.choose(&[
+            Compression::Gzip,
+            Compression::Zlib,
+            Compression::Uncompressed,
+        ])?)
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (1, Some(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arbitrary_header() {
+        let bytes = [0xFF; 8 * REGION_CHUNKS];
+        let mut u = Unstructured::new(&bytes);
+        let header = AnvilRegionHeader::arbitrary(&mut u).unwrap();
+
+        let metadata = header.get_metadata(31, 31);
+        assert!(metadata.sector_index() <= 0xFF_FFFF);
+        assert_eq!(metadata.sectors(), 255);
+    }
+}
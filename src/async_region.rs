@@ -0,0 +1,318 @@
+use crate::{
+    anvil_region, current_timestamp, sector_padding, sectors_required, AnvilChunkMetadata,
+    AnvilRegionHeader, ChunkLoadError, ChunkPayload, ChunkSaveError, Compression, RawChunk,
+    CHUNK_MAXIMUM_BYTES_LENGTH, DEFAULT_COMPRESSION_LEVEL, REGION_CHUNKS,
+    REGION_HEADER_BYTES_LENGTH, REGION_SECTOR_BYTES_LENGTH,
+};
+use byteorder::{BigEndian, ByteOrder};
+use futures::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+use nbt::CompoundTag;
+use std::io;
+use std::io::SeekFrom;
+
+/// Region read and written through the `futures::io` traits, so it runs on
+/// any async runtime: async-std and smol files implement them, tokio files
+/// do through the compatibility layer of `tokio-util`.
+///
+/// Chunks are compressed and decompressed on the calling task, only the
+/// file I/O is asynchronous. Chunks which no longer fit their sectors are
+/// appended to the end of the file, as with `SectorAllocation::AppendOnly`,
+/// use `AnvilRegion::compact` to reclaim the space.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::AsyncRegion;
+/// use futures::executor::block_on;
+/// use futures::io::Cursor;
+/// use nbt::CompoundTag;
+///
+/// block_on(async {
+///     let mut region = AsyncRegion::new(Cursor::new(Vec::new())).await.unwrap();
+///     region.write_chunk(1, 2, CompoundTag::new()).await.unwrap();
+///
+///     assert!(region.read_chunk(1, 2).await.is_ok());
+///     assert!(region.read_chunk(2, 1).await.is_err());
+/// });
+/// ```
+pub struct AsyncRegion<F> {
+    file: F,
+    chunks_metadata: [AnvilChunkMetadata; REGION_CHUNKS],
+    compression: Compression,
+    compression_level: u32,
+}
+
+impl<F: AsyncRead + AsyncSeek + Unpin> AsyncRegion<F> {
+    /// Reads the header of the region. Files shorter than the header are
+    /// read as regions without chunks.
+    pub async fn new(mut file: F) -> Result<Self, io::Error> {
+        let file_length = file.seek(SeekFrom::End(0)).await?;
+        let mut chunks_metadata = [AnvilChunkMetadata::default(); REGION_CHUNKS];
+
+        if file_length >= REGION_HEADER_BYTES_LENGTH {
+            let mut header = vec![0; REGION_HEADER_BYTES_LENGTH as usize];
+            file.seek(SeekFrom::Start(0)).await?;
+            file.read_exact(&mut header).await?;
+            chunks_metadata = anvil_region::read_header(&mut header.as_slice())?;
+        }
+
+        Ok(AsyncRegion {
+            file,
+            chunks_metadata,
+            compression: Compression::default(),
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+        })
+    }
+
+    /// Sets the compression and compression level, from 0 to 9, of written
+    /// chunks.
+    pub fn with_compression(mut self, compression: Compression, compression_level: u32) -> Self {
+        self.compression = compression;
+        self.compression_level = compression_level.min(9);
+        self
+    }
+
+    /// Consumes the region, returning the underlying file.
+    pub fn into_inner(self) -> F {
+        self.file
+    }
+
+    /// Returns chunk metadata at specified coordinates.
+    pub fn get_metadata(&self, chunk_x: u8, chunk_z: u8) -> AnvilChunkMetadata {
+        self.chunks_metadata[anvil_region::metadata_index(chunk_x, chunk_z)]
+    }
+
+    /// Header of the region, as read and updated by the writes.
+    pub fn header(&self) -> AnvilRegionHeader {
+        AnvilRegionHeader {
+            chunks_metadata: self.chunks_metadata,
+        }
+    }
+
+    pub async fn read_chunk(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+    ) -> Result<CompoundTag, ChunkLoadError> {
+        self.read_chunk_payload(chunk_x, chunk_z).await
+    }
+
+    /// Reads the chunk decoded as `P`.
+    pub async fn read_chunk_payload<P: ChunkPayload>(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+    ) -> Result<P, ChunkLoadError> {
+        self.read_chunk_raw(chunk_x, chunk_z)
+            .await?
+            .decode_payload()
+    }
+
+    /// Reads the chunk data without decompressing it.
+    pub async fn read_chunk_raw(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+    ) -> Result<RawChunk, ChunkLoadError> {
+        let metadata = self.get_metadata(chunk_x, chunk_z);
+
+        if metadata.is_empty() {
+            return Err(ChunkLoadError::ChunkNotFound { chunk_x, chunk_z });
+        }
+
+        let seek_offset = metadata.sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+        // Length doesn't count its own 4 bytes.
+        let maximum_length = metadata.sectors as u32 * REGION_SECTOR_BYTES_LENGTH as u32 - 4;
+
+        let mut chunk_header = [0u8; 5];
+        self.file.seek(SeekFrom::Start(seek_offset)).await?;
+        self.file.read_exact(&mut chunk_header).await?;
+
+        let length = BigEndian::read_u32(&chunk_header[..4]);
+
+        if length > maximum_length {
+            return Err(ChunkLoadError::LengthExceedsMaximum {
+                length,
+                maximum_length,
+            });
+        }
+
+        if length == 0 {
+            return Err(ChunkLoadError::EmptyChunkData);
+        }
+
+        let mut data = vec![0; length as usize - 1];
+        self.file.read_exact(&mut data).await?;
+
+        Ok(RawChunk::new(chunk_header[4], data))
+    }
+}
+
+impl<F: AsyncRead + AsyncWrite + AsyncSeek + Unpin> AsyncRegion<F> {
+    /// Writes chunk data, stamping it with the current time.
+    pub async fn write_chunk<P: ChunkPayload>(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        let raw_chunk = RawChunk::encode(
+            &chunk_compound_tag,
+            self.compression,
+            self.compression_level,
+        )?;
+
+        self.write_chunk_raw(chunk_x, chunk_z, &raw_chunk, current_timestamp())
+            .await
+    }
+
+    /// Writes already compressed chunk data as is, stamping it with the given
+    /// last modification time in seconds since the Unix epoch.
+    pub async fn write_chunk_raw(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        // Length, compression scheme and data.
+        let length = 5 + raw_chunk.data().len() as u32;
+
+        if length > CHUNK_MAXIMUM_BYTES_LENGTH {
+            return Err(ChunkSaveError::LengthExceedsMaximum { length });
+        }
+
+        let mut buffer = Vec::with_capacity(length as usize + sector_padding(length));
+        buffer.extend_from_slice(&(length - 4).to_be_bytes());
+        buffer.push(raw_chunk.compression_scheme());
+        buffer.extend_from_slice(raw_chunk.data());
+        buffer.resize(buffer.len() + sector_padding(length), 0);
+
+        let sectors = sectors_required(length);
+        let old_metadata = self.get_metadata(chunk_x, chunk_z);
+
+        let sector_index = if old_metadata.sectors == sectors {
+            old_metadata.sector_index
+        } else {
+            let file_length = self.file.seek(SeekFrom::End(0)).await?;
+            let sector_length = REGION_SECTOR_BYTES_LENGTH as u64;
+
+            (file_length.div_ceil(sector_length) as u32).max(2)
+        };
+
+        let seek_offset = sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+        self.file.seek(SeekFrom::Start(seek_offset)).await?;
+        self.file.write_all(&buffer).await?;
+
+        let metadata = AnvilChunkMetadata::new(sector_index, sectors, last_modified_timestamp);
+        self.update_metadata(chunk_x, chunk_z, metadata).await?;
+
+        Ok(())
+    }
+
+    /// Removes the chunk at the specified coordinates.
+    pub async fn delete_chunk(&mut self, chunk_x: u8, chunk_z: u8) -> Result<(), io::Error> {
+        if self.get_metadata(chunk_x, chunk_z).is_empty() {
+            return Ok(());
+        }
+
+        self.update_metadata(chunk_x, chunk_z, AnvilChunkMetadata::default())
+            .await
+    }
+
+    /// Flushes the underlying file.
+    pub async fn flush(&mut self) -> Result<(), io::Error> {
+        self.file.flush().await
+    }
+
+    async fn update_metadata(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+        metadata: AnvilChunkMetadata,
+    ) -> Result<(), io::Error> {
+        let index = anvil_region::metadata_index(chunk_x, chunk_z);
+        let offset = (metadata.sector_index << 8) | metadata.sectors as u32;
+
+        self.file.seek(SeekFrom::Start(index as u64 * 4)).await?;
+        self.file.write_all(&offset.to_be_bytes()).await?;
+
+        let timestamp_position = REGION_SECTOR_BYTES_LENGTH as u64 + index as u64 * 4;
+        self.file.seek(SeekFrom::Start(timestamp_position)).await?;
+        self.file
+            .write_all(&metadata.last_modified_timestamp.to_be_bytes())
+            .await?;
+
+        self.chunks_metadata[index] = metadata;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilRegion, RegionIssue};
+    use futures::executor::block_on;
+    use futures::io::{AllowStdIo, Cursor};
+    use std::fs;
+
+    #[test]
+    fn test_async_region() {
+        let bytes = fs::read("test/region/r.0.0.mca").unwrap();
+        let mut sync_region = AnvilRegion::new(std::io::Cursor::new(bytes.clone())).unwrap();
+
+        block_on(async {
+            let file = AllowStdIo::new(fs::File::open("test/region/r.0.0.mca").unwrap());
+            let mut region = AsyncRegion::new(file).await.unwrap();
+            assert_eq!(region.header().chunks().count(), 277);
+            assert_eq!(
+                region.read_chunk_raw(4, 2).await.unwrap(),
+                sync_region.read_chunk_raw(4, 2).unwrap()
+            );
+            assert!(region.read_chunk(4, 2).await.is_ok());
+            assert!(matches!(
+                region.read_chunk(15, 14).await,
+                Err(ChunkLoadError::ChunkNotFound { .. })
+            ));
+
+            // Rewritten in place when it keeps the same number of sectors.
+            let mut region = AsyncRegion::new(Cursor::new(bytes)).await.unwrap();
+            let metadata = region.get_metadata(4, 2);
+            let raw_chunk = region.read_chunk_raw(4, 2).await.unwrap();
+            region.write_chunk_raw(4, 2, &raw_chunk, 1).await.unwrap();
+            assert_eq!(
+                region.get_metadata(4, 2).sector_index(),
+                metadata.sector_index()
+            );
+            assert_eq!(region.read_chunk_raw(4, 2).await.unwrap(), raw_chunk);
+        });
+
+        block_on(async {
+            let mut region = AsyncRegion::new(Cursor::new(Vec::new())).await.unwrap();
+            let mut chunk_compound_tag = CompoundTag::new();
+            chunk_compound_tag.insert_i8_vec("Padding", vec![1; 5000]);
+            let raw_chunk =
+                RawChunk::encode(&chunk_compound_tag, Compression::Uncompressed, 0).unwrap();
+            region.write_chunk_raw(1, 2, &raw_chunk, 7).await.unwrap();
+            region.write_chunk(3, 4, CompoundTag::new()).await.unwrap();
+            region.delete_chunk(3, 4).await.unwrap();
+
+            // Readable by the synchronous region.
+            let bytes = region.into_inner().into_inner();
+            let mut sync_region = AnvilRegion::new(std::io::Cursor::new(bytes)).unwrap();
+            assert_eq!(sync_region.read_chunk_raw(1, 2).unwrap(), raw_chunk);
+            assert_eq!(sync_region.get_metadata(1, 2).sector_index(), 2);
+            assert_eq!(sync_region.get_metadata(1, 2).sectors(), 2);
+            assert!(sync_region.read_chunk(3, 4).is_err());
+            // The sector of the deleted chunk is left at the end.
+            assert!(matches!(
+                sync_region.verify().unwrap().as_slice(),
+                [RegionIssue::TrailingGarbage {
+                    used_length: 16384,
+                    file_length: 20480
+                }]
+            ));
+        });
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{chunk_level, ChunkLoadError, ChunkReader, ChunkSelection};
+use nbt::{CompoundTag, Tag};
+
+/// Block entity found by `find_block_entities`.
+#[derive(Clone, Debug)]
+pub struct BlockEntityMatch {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    /// Id of the block entity, such as `minecraft:chest`.
+    pub id: String,
+    /// Block coordinates of the block entity.
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    /// Whole block entity tag, with its contents.
+    pub compound_tag: CompoundTag,
+}
+
+/// Finds the block entities with any of the given ids in the selected
+/// chunks, or every block entity if `ids` is empty.
+///
+/// Block entities are read from `Level.TileEntities` in chunks before 1.18
+/// and from `block_entities` in newer chunks. Block entities without id or
+/// without coordinates are skipped.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{find_block_entities, ChunkSelection, FolderChunkProvider};
+///
+/// let mut chunk_provider = FolderChunkProvider::new("test/region");
+/// let ids = ["minecraft:chest", "minecraft:mob_spawner"];
+/// let block_entities =
+///     find_block_entities(&mut chunk_provider, &ChunkSelection::All, &ids).unwrap();
+///
+/// for block_entity in block_entities {
+///     println!(
+///         "{} at {} {} {}",
+///         block_entity.id, block_entity.x, block_entity.y, block_entity.z
+///     );
+/// }
+/// ```
+pub fn find_block_entities<P: ChunkReader + ?Sized>(
+    provider: &mut P,
+    selection: &ChunkSelection,
+    ids: &[&str],
+) -> Result<Vec<BlockEntityMatch>, ChunkLoadError> {
+    let mut matches = Vec::new();
+
+    for (chunk_x, chunk_z) in provider.list_chunks_in(selection)? {
+        let chunk_compound_tag = provider.load_chunk(chunk_x, chunk_z)?;
+        let level_compound_tag = chunk_level(&chunk_compound_tag);
+
+        for name in &["TileEntities", "block_entities"] {
+            let block_entities = match level_compound_tag.get::<&Vec<Tag>>(name) {
+                Ok(block_entities) => block_entities,
+                Err(_) => continue,
+            };
+
+            for block_entity in block_entities {
+                if let Tag::Compound(compound_tag) = block_entity {
+                    if let Some(block_entity_match) =
+                        block_entity_match(chunk_x, chunk_z, compound_tag, ids)
+                    {
+                        matches.push(block_entity_match);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(matches)
+}
+
+fn block_entity_match(
+    chunk_x: i32,
+    chunk_z: i32,
+    compound_tag: &CompoundTag,
+    ids: &[&str],
+) -> Option<BlockEntityMatch> {
+    let id = compound_tag.get_str("id").ok()?;
+
+    if !ids.is_empty() && !ids.contains(&id) {
+        return None;
+    }
+
+    Some(BlockEntityMatch {
+        chunk_x,
+        chunk_z,
+        id: id.to_string(),
+        x: compound_tag.get_i32("x").ok()?,
+        y: compound_tag.get_i32("y").ok()?,
+        z: compound_tag.get_i32("z").ok()?,
+        compound_tag: compound_tag.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FolderChunkProvider;
+
+    fn block_entity(id: &str, x: i32, y: i32, z: i32) -> CompoundTag {
+        let mut block_entity = CompoundTag::new();
+        block_entity.insert_str("id", id);
+        block_entity.insert_i32("x", x);
+        block_entity.insert_i32("y", y);
+        block_entity.insert_i32("z", z);
+
+        block_entity
+    }
+
+    #[test]
+    fn test_find_block_entities_in_both_layouts() {
+        let folder = tempfile::tempdir().unwrap();
+        let mut chunk_provider = FolderChunkProvider::new(folder.path());
+
+        let mut level = CompoundTag::new();
+        level.insert_compound_tag_vec(
+            "TileEntities",
+            vec![
+                block_entity("minecraft:chest", 1, 60, 2),
+                block_entity("minecraft:furnace", 3, 60, 4),
+            ],
+        );
+        let mut legacy_chunk = CompoundTag::new();
+        legacy_chunk.insert_compound_tag("Level", level);
+        chunk_provider.save_chunk(0, 0, legacy_chunk).unwrap();
+
+        let mut chunk = CompoundTag::new();
+        chunk.insert_compound_tag_vec(
+            "block_entities",
+            vec![
+                block_entity("minecraft:mob_spawner", 17, -10, 0),
+                // Without coordinates.
+                {
+                    let mut block_entity = CompoundTag::new();
+                    block_entity.insert_str("id", "minecraft:chest");
+                    block_entity
+                },
+            ],
+        );
+        chunk_provider.save_chunk(1, 0, chunk).unwrap();
+
+        let ids = ["minecraft:chest", "minecraft:mob_spawner"];
+        let found: Vec<_> = find_block_entities(&mut chunk_provider, &ChunkSelection::All, &ids)
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.chunk_x, m.id, m.x, m.y, m.z))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (0, "minecraft:chest".to_string(), 1, 60, 2),
+                (1, "minecraft:mob_spawner".to_string(), 17, -10, 0),
+            ]
+        );
+
+        let all = find_block_entities(&mut chunk_provider, &ChunkSelection::All, &[]).unwrap();
+        assert_eq!(all.len(), 3);
+
+        let selection = ChunkSelection::rect((1, 0), (1, 0));
+        let found = find_block_entities(&mut chunk_provider, &selection, &ids).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].compound_tag.get_i32("y").unwrap(), -10);
+    }
+}
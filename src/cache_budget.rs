@@ -0,0 +1,323 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Memory limit shared by the caches of one or more providers.
+///
+/// Every cache using the budget accounts the approximate size of its entries
+/// in it. When the budget is exceeded, the cache inserting an entry drops
+/// its least recently used entries until the budget fits again, or until it
+/// is empty. Clones share the same budget.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{AnvilOptions, CacheBudget, FolderChunkProvider};
+///
+/// let budget = CacheBudget::new(64 * 1024 * 1024);
+/// let options = AnvilOptions::new()
+///     .max_open_regions(64)
+///     .cache_budget(budget.clone());
+/// let chunk_provider = FolderChunkProvider::with_options("test/region", options);
+///
+/// assert!(chunk_provider.load_chunk(4, 2).is_ok());
+/// assert_eq!(chunk_provider.cache_stats().entries, 1);
+/// assert_eq!(budget.used_bytes(), chunk_provider.cache_stats().bytes);
+/// ```
+#[derive(Clone)]
+pub struct CacheBudget {
+    inner: Arc<BudgetInner>,
+}
+
+struct BudgetInner {
+    limit_bytes: u64,
+    used_bytes: AtomicU64,
+}
+
+impl CacheBudget {
+    /// Creates a budget of the given amount of bytes.
+    pub fn new(limit_bytes: u64) -> Self {
+        CacheBudget {
+            inner: Arc::new(BudgetInner {
+                limit_bytes,
+                used_bytes: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub fn limit_bytes(&self) -> u64 {
+        self.inner.limit_bytes
+    }
+
+    /// Bytes used by the entries of every cache sharing the budget.
+    pub fn used_bytes(&self) -> u64 {
+        self.inner.used_bytes.load(Ordering::Relaxed)
+    }
+
+    fn is_exceeded(&self) -> bool {
+        self.used_bytes() > self.limit_bytes()
+    }
+
+    fn reserve(&self, bytes: u64) {
+        self.inner.used_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn release(&self, bytes: u64) {
+        self.inner.used_bytes.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for CacheBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CacheBudget")
+            .field("limit_bytes", &self.limit_bytes())
+            .field("used_bytes", &self.used_bytes())
+            .finish()
+    }
+}
+
+/// Budgets are equal when they are clones of the same budget.
+impl PartialEq for CacheBudget {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for CacheBudget {}
+
+/// Usage of a cache since it was created.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    /// Amount of entries in the cache.
+    pub entries: usize,
+    /// Approximate bytes used by the entries.
+    pub bytes: u64,
+    /// Lookups that found their entry.
+    pub hits: u64,
+    /// Lookups that didn't find their entry.
+    pub misses: u64,
+    /// Entries dropped to respect the entry limit or the budget.
+    pub evictions: u64,
+}
+
+/// Least recently used cache limited by an amount of entries and an optional
+/// shared budget.
+pub(crate) struct BudgetedCache<K, V> {
+    /// Maximum amount of entries, zero disables the cache.
+    max_entries: usize,
+    budget: Option<CacheBudget>,
+    /// Entries with their size, least recently used first.
+    entries: VecDeque<(K, V, u64)>,
+    stats: CacheStats,
+}
+
+impl<K: Eq, V> BudgetedCache<K, V> {
+    pub(crate) fn new(max_entries: usize, budget: Option<CacheBudget>) -> Self {
+        BudgetedCache {
+            max_entries,
+            budget,
+            entries: VecDeque::new(),
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn contains(&self, key: &K) -> bool {
+        self.entries.iter().any(|(other, _, _)| other == key)
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.entries.len(),
+            ..self.stats
+        }
+    }
+
+    /// Returns the entry without counting a lookup or marking it as used.
+    #[cfg_attr(not(feature = "zip"), allow(dead_code))]
+    pub(crate) fn peek(&self, key: &K) -> Option<&V> {
+        self.entries
+            .iter()
+            .find(|(other, _, _)| other == key)
+            .map(|(_, value, _)| value)
+    }
+
+    /// Same as `peek`, returning the entry mutably.
+    pub(crate) fn peek_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.entries
+            .iter_mut()
+            .find(|(other, _, _)| other == key)
+            .map(|(_, value, _)| value)
+    }
+
+    /// Returns the entry, marking it as the most recently used one.
+    #[cfg_attr(not(feature = "zip"), allow(dead_code))]
+    pub(crate) fn get(&mut self, key: &K) -> Option<&V> {
+        match self.position(key) {
+            Some(index) => {
+                self.stats.hits += 1;
+
+                let entry = self.entries.remove(index)?;
+                self.entries.push_back(entry);
+                self.entries.back().map(|(_, value, _)| value)
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Takes the entry out of the cache.
+    pub(crate) fn take(&mut self, key: &K) -> Option<V> {
+        let value = self.remove(key);
+
+        if value.is_some() {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+
+        value
+    }
+
+    /// Removes the entry without counting a lookup.
+    pub(crate) fn remove(&mut self, key: &K) -> Option<V> {
+        let index = self.position(key)?;
+        let (_, value, bytes) = self.entries.remove(index)?;
+        self.release(bytes);
+
+        Some(value)
+    }
+
+    /// Inserts the entry of the given size as the most recently used one,
+    /// returning the entries evicted to make room for it, which may include
+    /// the inserted one.
+    pub(crate) fn insert(&mut self, key: K, value: V, bytes: u64) -> Vec<V> {
+        if self.max_entries == 0 {
+            return vec![value];
+        }
+
+        let mut evicted: Vec<V> = self.remove(&key).into_iter().collect();
+
+        self.entries.push_back((key, value, bytes));
+        self.stats.bytes += bytes;
+
+        if let Some(budget) = &self.budget {
+            budget.reserve(bytes);
+        }
+
+        while self.entries.len() > self.max_entries || self.budget_exceeded() {
+            match self.entries.pop_front() {
+                Some((_, value, bytes)) => {
+                    self.release(bytes);
+                    self.stats.evictions += 1;
+                    evicted.push(value);
+                }
+                None => break,
+            }
+        }
+
+        evicted
+    }
+
+    /// Entries of the cache, least recently used first, without counting
+    /// lookups.
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.entries.iter_mut().map(|(key, value, _)| (&*key, value))
+    }
+
+    /// Takes every entry out of the cache, least recently used first.
+    pub(crate) fn drain(&mut self) -> Vec<(K, V)> {
+        let entries: Vec<_> = self.entries.drain(..).collect();
+
+        entries
+            .into_iter()
+            .map(|(key, value, bytes)| {
+                self.release(bytes);
+                (key, value)
+            })
+            .collect()
+    }
+
+    fn position(&self, key: &K) -> Option<usize> {
+        self.entries.iter().position(|(other, _, _)| other == key)
+    }
+
+    fn budget_exceeded(&self) -> bool {
+        self.budget
+            .as_ref()
+            .is_some_and(|budget| budget.is_exceeded())
+    }
+
+    fn release(&mut self, bytes: u64) {
+        self.stats.bytes -= bytes;
+
+        if let Some(budget) = &self.budget {
+            budget.release(bytes);
+        }
+    }
+}
+
+impl<K: Eq, V> fmt::Debug for BudgetedCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BudgetedCache")
+            .field("max_entries", &self.max_entries)
+            .field("budget", &self.budget)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl<K, V> Drop for BudgetedCache<K, V> {
+    fn drop(&mut self) {
+        if let Some(budget) = &self.budget {
+            budget.release(self.stats.bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budgeted_cache_eviction() {
+        let budget = CacheBudget::new(100);
+        let mut first = BudgetedCache::new(3, Some(budget.clone()));
+        let mut second = BudgetedCache::new(3, Some(budget.clone()));
+
+        assert!(first.insert(1, "a", 40).is_empty());
+        assert!(first.insert(2, "b", 40).is_empty());
+        assert_eq!(first.get(&1), Some(&"a"));
+        assert_eq!(budget.used_bytes(), 80);
+
+        // Over the budget, the least recently used entry of the inserting
+        // cache is dropped.
+        assert_eq!(second.insert(3, "c", 30), vec!["c"]);
+        assert_eq!(first.insert(4, "d", 30), vec!["b"]);
+        assert_eq!(budget.used_bytes(), 70);
+
+        assert!(first.insert(5, "e", 1).is_empty());
+        assert_eq!(first.insert(6, "f", 1), vec!["a"]);
+        assert_eq!(first.take(&7), None);
+
+        assert_eq!(
+            first.stats(),
+            CacheStats {
+                entries: 3,
+                bytes: 32,
+                hits: 1,
+                misses: 1,
+                evictions: 2,
+            }
+        );
+
+        drop(first);
+        assert_eq!(budget.used_bytes(), 0);
+    }
+}
@@ -0,0 +1,281 @@
+use crate::DataVersion;
+use nbt::{CompoundTag, Tag};
+
+/// 1.20.5, which added the namespace to the chunk status.
+const V1_20_5: DataVersion = DataVersion(3837);
+
+/// Names of the heightmaps of a fully generated chunk.
+const HEIGHTMAPS: [&str; 4] = [
+    "MOTION_BLOCKING",
+    "MOTION_BLOCKING_NO_LEAVES",
+    "OCEAN_FLOOR",
+    "WORLD_SURFACE",
+];
+
+/// Builds a fully generated chunk with the tags the given version of the
+/// game needs to load it, as an overworld chunk without blocks.
+///
+/// The light is left for the game to compute. Chunks only get sections,
+/// which the game otherwise creates as needed, with `fill_air`.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{validate_chunk, ChunkBuilder, DataVersion};
+///
+/// let chunk_compound_tag = ChunkBuilder::new(DataVersion::V1_18)
+///     .x(31)
+///     .z(16)
+///     .fill_air()
+///     .build();
+///
+/// assert_eq!(validate_chunk(&chunk_compound_tag, DataVersion::V1_18), vec![]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ChunkBuilder {
+    data_version: DataVersion,
+    x: i32,
+    z: i32,
+    last_update: i64,
+    inhabited_time: i64,
+    fill_air: bool,
+}
+
+impl ChunkBuilder {
+    pub fn new(data_version: DataVersion) -> Self {
+        ChunkBuilder {
+            data_version,
+            x: 0,
+            z: 0,
+            last_update: 0,
+            inhabited_time: 0,
+            fill_air: false,
+        }
+    }
+
+    /// Chunk coordinate on the x axis, 0 by default.
+    pub fn x(mut self, x: i32) -> Self {
+        self.x = x;
+        self
+    }
+
+    /// Chunk coordinate on the z axis, 0 by default.
+    pub fn z(mut self, z: i32) -> Self {
+        self.z = z;
+        self
+    }
+
+    /// Game tick of the last save of the chunk, 0 by default.
+    pub fn last_update(mut self, last_update: i64) -> Self {
+        self.last_update = last_update;
+        self
+    }
+
+    /// Ticks players spent in the chunk, 0 by default.
+    pub fn inhabited_time(mut self, inhabited_time: i64) -> Self {
+        self.inhabited_time = inhabited_time;
+        self
+    }
+
+    /// Adds a section of air for every section of the world height.
+    pub fn fill_air(mut self) -> Self {
+        self.fill_air = true;
+        self
+    }
+
+    pub fn build(self) -> CompoundTag {
+        let version = self.data_version;
+        let mut level_compound_tag = CompoundTag::new();
+        level_compound_tag.insert_i32("xPos", self.x);
+        level_compound_tag.insert_i32("zPos", self.z);
+        level_compound_tag.insert_i64("LastUpdate", self.last_update);
+        level_compound_tag.insert_i64("InhabitedTime", self.inhabited_time);
+
+        let sections = if self.fill_air {
+            self.air_sections()
+        } else {
+            Vec::new()
+        };
+
+        if version >= DataVersion::V1_18 {
+            level_compound_tag.insert_i32("DataVersion", version.0);
+            level_compound_tag.insert_i32("yPos", -4);
+            level_compound_tag.insert_str("Status", self.full_status());
+            level_compound_tag.insert_compound_tag_vec("sections", sections);
+            level_compound_tag.insert_compound_tag("Heightmaps", heightmaps(37));
+            level_compound_tag.insert_compound_tag_vec("block_entities", Vec::new());
+            level_compound_tag.insert("block_ticks", Tag::List(Vec::new()));
+            level_compound_tag.insert("fluid_ticks", Tag::List(Vec::new()));
+            level_compound_tag.insert("PostProcessing", empty_lists(24));
+            level_compound_tag.insert_compound_tag("structures", structures("starts"));
+
+            return level_compound_tag;
+        }
+
+        level_compound_tag.insert_compound_tag_vec("Sections", sections);
+        level_compound_tag.insert_compound_tag_vec("Entities", Vec::new());
+        level_compound_tag.insert_compound_tag_vec("TileEntities", Vec::new());
+
+        if version >= DataVersion::V1_13 {
+            let (biomes, heightmap_length) = if version >= DataVersion::V1_16 {
+                (1024, 37)
+            } else if version >= DataVersion::V1_15 {
+                (1024, 36)
+            } else {
+                (256, 36)
+            };
+
+            level_compound_tag.insert_str("Status", "full");
+            level_compound_tag.insert_i32_vec("Biomes", vec![1; biomes]);
+            level_compound_tag.insert_compound_tag("Heightmaps", heightmaps(heightmap_length));
+            level_compound_tag.insert("PostProcessing", empty_lists(16));
+            level_compound_tag.insert("TileTicks", Tag::List(Vec::new()));
+            level_compound_tag.insert("LiquidTicks", Tag::List(Vec::new()));
+            level_compound_tag.insert_compound_tag("Structures", structures("Starts"));
+        } else {
+            level_compound_tag.insert_i8("TerrainPopulated", 1);
+            level_compound_tag.insert_i8("LightPopulated", 0);
+            level_compound_tag.insert_i8_vec("Biomes", vec![1; 256]);
+            level_compound_tag.insert_i32_vec("HeightMap", vec![0; 256]);
+        }
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("DataVersion", version.0);
+        chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+
+        chunk_compound_tag
+    }
+
+    fn full_status(&self) -> &'static str {
+        if self.data_version >= V1_20_5 {
+            "minecraft:full"
+        } else {
+            "full"
+        }
+    }
+
+    fn air_sections(&self) -> Vec<CompoundTag> {
+        let version = self.data_version;
+
+        if version >= DataVersion::V1_18 {
+            return (-4..20).map(air_section_1_18).collect();
+        }
+
+        (0..16)
+            .map(|y| {
+                let mut section = CompoundTag::new();
+                section.insert_i8("Y", y);
+
+                if version >= DataVersion::V1_13 {
+                    let mut air = CompoundTag::new();
+                    air.insert_str("Name", "minecraft:air");
+                    section.insert_compound_tag_vec("Palette", vec![air]);
+                    section.insert_i64_vec("BlockStates", vec![0; 256]);
+                } else {
+                    section.insert_i8_vec("Blocks", vec![0; 4096]);
+                    section.insert_i8_vec("Data", vec![0; 2048]);
+                    section.insert_i8_vec("BlockLight", vec![0; 2048]);
+                    section.insert_i8_vec("SkyLight", vec![-1; 2048]);
+                }
+
+                section
+            })
+            .collect()
+    }
+}
+
+/// 1.18+ section with air blocks and plains biomes.
+fn air_section_1_18(y: i8) -> CompoundTag {
+    let mut air = CompoundTag::new();
+    air.insert_str("Name", "minecraft:air");
+    let mut block_states = CompoundTag::new();
+    block_states.insert_compound_tag_vec("palette", vec![air]);
+
+    let mut biomes = CompoundTag::new();
+    biomes.insert(
+        "palette",
+        Tag::List(vec![Tag::String("minecraft:plains".to_string())]),
+    );
+
+    let mut section = CompoundTag::new();
+    section.insert_i8("Y", y);
+    section.insert_compound_tag("block_states", block_states);
+    section.insert_compound_tag("biomes", biomes);
+
+    section
+}
+
+/// Heightmaps of a chunk without blocks, with 256 heights of 9 bits packed
+/// in `length` longs.
+fn heightmaps(length: usize) -> CompoundTag {
+    let mut heightmaps = CompoundTag::new();
+
+    for name in &HEIGHTMAPS {
+        heightmaps.insert_i64_vec(name, vec![0; length]);
+    }
+
+    heightmaps
+}
+
+/// List of empty lists, one per section.
+fn empty_lists(sections: usize) -> Tag {
+    Tag::List(vec![Tag::List(Vec::new()); sections])
+}
+
+/// Structures compound without structures.
+fn structures(starts_name: &str) -> CompoundTag {
+    let mut structures = CompoundTag::new();
+    structures.insert_compound_tag("References", CompoundTag::new());
+    structures.insert_compound_tag(starts_name, CompoundTag::new());
+
+    structures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chunk_level, validate_chunk, ChunkPayload};
+
+    #[test]
+    fn test_built_chunks_are_valid() {
+        let versions = [
+            DataVersion(1343),
+            DataVersion::V1_13,
+            DataVersion::V1_15,
+            DataVersion::V1_16,
+            DataVersion::V1_18,
+            DataVersion(3953),
+        ];
+
+        for &version in &versions {
+            for &fill_air in &[false, true] {
+                let mut builder = ChunkBuilder::new(version).x(-3).z(40);
+
+                if fill_air {
+                    builder = builder.fill_air();
+                }
+
+                let chunk_compound_tag = builder.build();
+                assert_eq!(validate_chunk(&chunk_compound_tag, version), vec![]);
+                assert_eq!(DataVersion::of_chunk(&chunk_compound_tag), Some(version));
+                assert_eq!(chunk_compound_tag.chunk_coordinates(), (Some(-3), Some(40)));
+
+                let sections_name = if version >= DataVersion::V1_18 {
+                    "sections"
+                } else {
+                    "Sections"
+                };
+                let sections = chunk_level(&chunk_compound_tag)
+                    .get_compound_tag_vec(sections_name)
+                    .unwrap();
+                assert_eq!(sections.is_empty(), !fill_air);
+            }
+        }
+
+        let chunk_compound_tag = ChunkBuilder::new(DataVersion(3953)).build();
+        assert_eq!(
+            chunk_compound_tag.get_str("Status").unwrap(),
+            "minecraft:full"
+        );
+    }
+}
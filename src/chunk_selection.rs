@@ -0,0 +1,241 @@
+use std::fmt;
+
+/// Set of chunk coordinates used to scope bulk operations.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::ChunkSelection;
+///
+/// // Every chunk touched by a 100 block radius around spawn, except the
+/// // chunks with negative x coordinates.
+/// let selection = ChunkSelection::circle((0, 0), 100)
+///     .difference(ChunkSelection::rect((-100, -100), (-1, 100)));
+///
+/// assert!(selection.contains(0, 0));
+/// assert!(selection.contains(6, 0));
+/// assert!(!selection.contains(7, 0));
+/// assert!(!selection.contains(-1, 0));
+/// ```
+#[derive(Default)]
+pub enum ChunkSelection<'a> {
+    /// Every chunk.
+    #[default]
+    All,
+    /// Chunks inside the rectangle, both corners included.
+    Rect { min: (i32, i32), max: (i32, i32) },
+    /// Chunks containing at least one block column within `radius` blocks of
+    /// the `center` block.
+    Circle { center: (i32, i32), radius: u32 },
+    /// Chunks in any of both selections.
+    Union(Box<ChunkSelection<'a>>, Box<ChunkSelection<'a>>),
+    /// Chunks in the first selection but not in the second one.
+    Difference(Box<ChunkSelection<'a>>, Box<ChunkSelection<'a>>),
+    /// Chunks for which the predicate returns true.
+    Predicate(Box<dyn Fn(i32, i32) -> bool + 'a>),
+}
+
+impl<'a> ChunkSelection<'a> {
+    /// Selects the chunks inside the rectangle defined by two opposite
+    /// corners in chunk coordinates, both corners included.
+    pub fn rect(corner_a: (i32, i32), corner_b: (i32, i32)) -> Self {
+        ChunkSelection::Rect {
+            min: (corner_a.0.min(corner_b.0), corner_a.1.min(corner_b.1)),
+            max: (corner_a.0.max(corner_b.0), corner_a.1.max(corner_b.1)),
+        }
+    }
+
+    /// Selects the chunks touched by a circle in block coordinates.
+    pub fn circle(center: (i32, i32), radius: u32) -> Self {
+        ChunkSelection::Circle { center, radius }
+    }
+
+    /// Selects the chunks for which the predicate returns true.
+    pub fn predicate<F: Fn(i32, i32) -> bool + 'a>(predicate: F) -> Self {
+        ChunkSelection::Predicate(Box::new(predicate))
+    }
+
+    /// Selects the chunks in `self` or in `other`.
+    pub fn union(self, other: ChunkSelection<'a>) -> Self {
+        ChunkSelection::Union(Box::new(self), Box::new(other))
+    }
+
+    /// Selects the chunks in `self` that are not in `other`.
+    pub fn difference(self, other: ChunkSelection<'a>) -> Self {
+        ChunkSelection::Difference(Box::new(self), Box::new(other))
+    }
+
+    /// Returns true if the chunk at the specified coordinates is selected.
+    pub fn contains(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        match self {
+            ChunkSelection::All => true,
+            ChunkSelection::Rect { min, max } => {
+                (min.0..=max.0).contains(&chunk_x) && (min.1..=max.1).contains(&chunk_z)
+            }
+            ChunkSelection::Circle { center, radius } => {
+                // Distance from the center to the nearest block of the chunk.
+                let min_x = i64::from(chunk_x) * 16;
+                let min_z = i64::from(chunk_z) * 16;
+                let center_x = i64::from(center.0);
+                let center_z = i64::from(center.1);
+                let dx = center_x.max(min_x).min(min_x + 15) - center_x;
+                let dz = center_z.max(min_z).min(min_z + 15) - center_z;
+                let radius = i64::from(*radius);
+
+                dx * dx + dz * dz <= radius * radius
+            }
+            ChunkSelection::Union(a, b) => a.contains(chunk_x, chunk_z) || b.contains(chunk_x, chunk_z),
+            ChunkSelection::Difference(a, b) => {
+                a.contains(chunk_x, chunk_z) && !b.contains(chunk_x, chunk_z)
+            }
+            ChunkSelection::Predicate(predicate) => predicate(chunk_x, chunk_z),
+        }
+    }
+
+    /// Bounding box of the selected chunks as (min, max), both included.
+    ///
+    /// Returns `None` if the selection is unbounded.
+    pub fn bounds(&self) -> Option<((i32, i32), (i32, i32))> {
+        match self {
+            ChunkSelection::All | ChunkSelection::Predicate(_) => None,
+            ChunkSelection::Rect { min, max } => Some((*min, *max)),
+            ChunkSelection::Circle { center, radius } => {
+                let radius = i64::from(*radius);
+                let to_chunk = |block: i64| (block >> 4) as i32;
+
+                Some((
+                    (
+                        to_chunk(i64::from(center.0) - radius),
+                        to_chunk(i64::from(center.1) - radius),
+                    ),
+                    (
+                        to_chunk(i64::from(center.0) + radius),
+                        to_chunk(i64::from(center.1) + radius),
+                    ),
+                ))
+            }
+            ChunkSelection::Union(a, b) => {
+                let (a_min, a_max) = a.bounds()?;
+                let (b_min, b_max) = b.bounds()?;
+
+                Some((
+                    (a_min.0.min(b_min.0), a_min.1.min(b_min.1)),
+                    (a_max.0.max(b_max.0), a_max.1.max(b_max.1)),
+                ))
+            }
+            ChunkSelection::Difference(a, _) => a.bounds(),
+        }
+    }
+
+    /// Bounds of the selection, or of the given selected chunks if the
+    /// selection is unbounded. Returns `None` for an unbounded selection
+    /// without chunks.
+    pub(crate) fn bounds_or_chunk_bounds(
+        &self,
+        chunks: &[(i32, i32)],
+    ) -> Option<((i32, i32), (i32, i32))> {
+        self.bounds().or_else(|| {
+            let min_x = chunks.iter().map(|&(x, _)| x).min()?;
+            let min_z = chunks.iter().map(|&(_, z)| z).min()?;
+            let max_x = chunks.iter().map(|&(x, _)| x).max()?;
+            let max_z = chunks.iter().map(|&(_, z)| z).max()?;
+
+            Some(((min_x, min_z), (max_x, max_z)))
+        })
+    }
+
+    /// Returns false if no chunk of the region can be selected, so the whole
+    /// region can be skipped.
+    pub fn may_contain_region(&self, region_x: i32, region_z: i32) -> bool {
+        match self.bounds() {
+            None => true,
+            Some((min, max)) => {
+                let region_min_x = i64::from(region_x) * 32;
+                let region_min_z = i64::from(region_z) * 32;
+
+                region_min_x <= i64::from(max.0)
+                    && region_min_x + 31 >= i64::from(min.0)
+                    && region_min_z <= i64::from(max.1)
+                    && region_min_z + 31 >= i64::from(min.1)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ChunkSelection<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkSelection::All => write!(f, "All"),
+            ChunkSelection::Rect { min, max } => f
+                .debug_struct("Rect")
+                .field("min", min)
+                .field("max", max)
+                .finish(),
+            ChunkSelection::Circle { center, radius } => f
+                .debug_struct("Circle")
+                .field("center", center)
+                .field("radius", radius)
+                .finish(),
+            ChunkSelection::Union(a, b) => f.debug_tuple("Union").field(a).field(b).finish(),
+            ChunkSelection::Difference(a, b) => {
+                f.debug_tuple("Difference").field(a).field(b).finish()
+            }
+            ChunkSelection::Predicate(_) => write!(f, "Predicate(..)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rect_contains_corners() {
+        let selection = ChunkSelection::rect((3, -2), (-1, 4));
+
+        assert!(selection.contains(-1, -2));
+        assert!(selection.contains(3, 4));
+        assert!(!selection.contains(4, 4));
+        assert!(!selection.contains(-1, -3));
+        assert_eq!(selection.bounds(), Some(((-1, -2), (3, 4))));
+    }
+
+    #[test]
+    fn circle_contains_touched_chunks() {
+        let selection = ChunkSelection::circle((8, 8), 8);
+
+        assert!(selection.contains(0, 0));
+        assert!(selection.contains(1, 0));
+        assert!(selection.contains(0, 1));
+        // Nearest block (-1, 8) is 9 blocks away from (8, 8).
+        assert!(!selection.contains(-1, 0));
+        // Nearest block (16, 16) is more than 8 blocks away from (8, 8).
+        assert!(!selection.contains(1, 1));
+        assert!(!selection.contains(2, 0));
+        assert_eq!(selection.bounds(), Some(((0, 0), (1, 1))));
+    }
+
+    #[test]
+    fn union_difference_and_predicate() {
+        let selection = ChunkSelection::rect((0, 0), (1, 1))
+            .union(ChunkSelection::rect((10, 10), (10, 10)))
+            .difference(ChunkSelection::predicate(|x, z| x == z));
+
+        assert!(selection.contains(0, 1));
+        assert!(!selection.contains(0, 0));
+        assert!(!selection.contains(10, 10));
+        assert!(!selection.contains(5, 6));
+        assert_eq!(selection.bounds(), Some(((0, 0), (10, 10))));
+    }
+
+    #[test]
+    fn may_contain_region() {
+        let selection = ChunkSelection::rect((31, 0), (32, 0));
+
+        assert!(selection.may_contain_region(0, 0));
+        assert!(selection.may_contain_region(1, 0));
+        assert!(!selection.may_contain_region(-1, 0));
+        assert!(!selection.may_contain_region(0, 1));
+        assert!(ChunkSelection::All.may_contain_region(1000, -1000));
+    }
+}
@@ -0,0 +1,140 @@
+use crate::chunk_level;
+use nbt::CompoundTag;
+
+/// Reads the `index`th value of `bits` bits from a long array of `entries`
+/// values.
+///
+/// Since 1.16 every long holds as many whole values as fit, before that
+/// values were packed across long boundaries. The layout is told apart by
+/// the array length, both layouts are the same when `bits` divides 64.
+pub(crate) fn packed_value(data: &[i64], bits: usize, entries: usize, index: usize) -> Option<u64> {
+    let mask = (1u64 << bits) - 1;
+    let values_per_long = 64 / bits;
+
+    if data.len() == entries.div_ceil(values_per_long) {
+        let long = *data.get(index / values_per_long)? as u64;
+
+        return Some((long >> ((index % values_per_long) * bits)) & mask);
+    }
+
+    let bit = index * bits;
+    let offset = bit % 64;
+    let mut value = *data.get(bit / 64)? as u64 >> offset;
+
+    if offset + bits > 64 {
+        value |= (*data.get(bit / 64 + 1)? as u64) << (64 - offset);
+    }
+
+    Some(value & mask)
+}
+
+/// Y of the highest non-air block of every column, indexed by `z * 16 + x`.
+///
+/// Read from the `WORLD_SURFACE` heightmap, or the `HeightMap` array of
+/// chunks before 1.13. Returns `None` if the chunk has no heightmap.
+pub(crate) fn surface_heights(chunk_compound_tag: &CompoundTag) -> Option<[i32; 256]> {
+    let level_compound_tag = chunk_level(chunk_compound_tag);
+    let mut heights = [0; 256];
+
+    if let Ok(height_map) = level_compound_tag.get_i32_vec("HeightMap") {
+        for (height, &value) in heights.iter_mut().zip(height_map) {
+            *height = value - 1;
+        }
+
+        return Some(heights);
+    }
+
+    let height_map = level_compound_tag
+        .get_compound_tag("Heightmaps")
+        .and_then(|heightmaps| heightmaps.get_i64_vec("WORLD_SURFACE"))
+        .ok()?;
+    let min_y = level_compound_tag.get_i32("yPos").unwrap_or(0) * 16;
+
+    for (index, height) in heights.iter_mut().enumerate() {
+        *height = packed_value(height_map, 9, 256, index)? as i32 - 1 + min_y;
+    }
+
+    Some(heights)
+}
+
+/// Name of the block at the given position, with `x` and `z` inside the
+/// chunk. Only chunks from 1.13 on store block names.
+pub(crate) fn block_name(
+    chunk_compound_tag: &CompoundTag,
+    x: usize,
+    y: i32,
+    z: usize,
+) -> Option<&str> {
+    let level_compound_tag = chunk_level(chunk_compound_tag);
+    let sections = level_compound_tag
+        .get_compound_tag_vec("sections")
+        .or_else(|_| level_compound_tag.get_compound_tag_vec("Sections"))
+        .ok()?;
+    let section = sections
+        .into_iter()
+        .find(|section| section.get_i8("Y").ok().map(i32::from) == Some(y >> 4))?;
+
+    let (palette, data) = match section.get_compound_tag("block_states") {
+        Ok(block_states) => (
+            block_states.get_compound_tag_vec("palette").ok()?,
+            block_states.get_i64_vec("data").ok(),
+        ),
+        Err(_) => (
+            section.get_compound_tag_vec("Palette").ok()?,
+            section.get_i64_vec("BlockStates").ok(),
+        ),
+    };
+
+    let palette_index = match data {
+        Some(data) if palette.len() > 1 => {
+            let bits = bits_per_block(palette.len());
+            let index = ((y & 15) as usize * 16 + z) * 16 + x;
+
+            packed_value(data, bits, 4096, index)? as usize
+        }
+        _ => 0,
+    };
+
+    palette.get(palette_index)?.get_str("Name").ok()
+}
+
+/// Bits used to store an index into a block palette of the given length.
+fn bits_per_block(palette_length: usize) -> usize {
+    let bits = (usize::BITS - (palette_length - 1).leading_zeros()) as usize;
+
+    bits.max(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packed_value() {
+        // 5 bits per value, 12 values per long.
+        let data = [(3 << 5) | 1, 7];
+        assert_eq!(packed_value(&data, 5, 24, 0), Some(1));
+        assert_eq!(packed_value(&data, 5, 24, 1), Some(3));
+        assert_eq!(packed_value(&data, 5, 24, 12), Some(7));
+
+        // 9 bits per value packed across longs, the 8th value spans two longs.
+        let mut data = vec![0; 36];
+        data[0] = 1 << 63;
+        data[1] = 3;
+        assert_eq!(packed_value(&data, 9, 256, 7), Some(0b111));
+    }
+
+    #[test]
+    fn test_surface_of_region_chunk() {
+        let chunk_provider = crate::FolderChunkProvider::new("test/region");
+        let chunk_compound_tag = chunk_provider.load_chunk(4, 2).unwrap();
+
+        let heights = surface_heights(&chunk_compound_tag).unwrap();
+        assert!(heights.iter().all(|&height| height > 0 && height < 256));
+
+        assert_ne!(
+            block_name(&chunk_compound_tag, 0, heights[0], 0).unwrap(),
+            "minecraft:air"
+        );
+    }
+}
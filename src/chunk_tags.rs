@@ -0,0 +1,445 @@
+use crate::{
+    chunk_coords_inside_region, read_chunk_data, AnvilChunkMetadata, ChunkLoadError, ChunkPayload,
+    ChunkSelection, Compression, FolderChunkProvider, RawChunk, WorldEditError,
+};
+use byteorder::{BigEndian, ReadBytesExt};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::io;
+use std::io::Read;
+
+const TAG_END: u8 = 0;
+const TAG_LONG: u8 = 4;
+const TAG_STRING: u8 = 8;
+const TAG_LIST: u8 = 9;
+const TAG_COMPOUND: u8 = 10;
+
+/// Chunk coordinates with a game tick of the chunk.
+pub type ChunkTick = ((i32, i32), i64);
+
+impl RawChunk {
+    /// Generation status of the chunk, such as `full` or `carvers`, read
+    /// from `Level.Status` or from the root `Status` of 1.18+ chunks. The
+    /// `minecraft:` prefix of 1.20.5+ chunks is removed.
+    ///
+    /// The chunk is only decompressed until the status is found, and the
+    /// other tags are skipped without being decoded.
+    pub fn status(&self) -> Result<Option<String>, ChunkLoadError> {
+        let status = self.peek_level_tag("Status", TAG_STRING, |reader| read_string(reader))?;
+
+        Ok(
+            status.map(|status| match status.strip_prefix("minecraft:") {
+                Some(status) => status.to_string(),
+                None => status,
+            }),
+        )
+    }
+
+    /// Ticks players spent in the chunk, read from `InhabitedTime` like
+    /// `status`.
+    pub fn inhabited_time(&self) -> Result<Option<i64>, ChunkLoadError> {
+        self.peek_level_tag("InhabitedTime", TAG_LONG, |reader| {
+            reader.read_i64::<BigEndian>()
+        })
+    }
+
+    /// Game tick of the last time the chunk was saved, read from
+    /// `LastUpdate` like `status`.
+    ///
+    /// Unlike the header timestamp, which tools copying chunks often
+    /// overwrite, only the game updates it.
+    pub fn last_update(&self) -> Result<Option<i64>, ChunkLoadError> {
+        self.peek_level_tag("LastUpdate", TAG_LONG, |reader| {
+            reader.read_i64::<BigEndian>()
+        })
+    }
+
+    /// Reads the tag with the given name and type in the root compound or in
+    /// its `Level` compound, decompressing only what comes before it.
+    fn peek_level_tag<T>(
+        &self,
+        name: &str,
+        tag_id: u8,
+        read_value: fn(&mut dyn Read) -> Result<T, io::Error>,
+    ) -> Result<Option<T>, ChunkLoadError> {
+        let data = self.data();
+        let mut reader: Box<dyn Read + '_> = match Compression::from_id(self.compression_scheme()) {
+            Some(Compression::Gzip) => Box::new(GzDecoder::new(data)),
+            Some(Compression::Zlib) => Box::new(ZlibDecoder::new(data)),
+            Some(Compression::Uncompressed) => Box::new(data),
+            None => {
+                return Err(ChunkLoadError::UnsupportedCompressionScheme {
+                    compression_scheme: self.compression_scheme(),
+                })
+            }
+        };
+
+        if find_level_tag(&mut reader, name, tag_id)? {
+            Ok(Some(read_value(&mut reader)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<P: ChunkPayload> FolderChunkProvider<P> {
+    /// Lists the chunks whose generation status is one of `statuses`, see
+    /// `RawChunk::status`. Chunks without a status are never listed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::FolderChunkProvider;
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("test/region");
+    ///
+    /// // Proto-chunks, which the game regenerates.
+    /// let proto_chunks = chunk_provider
+    ///     .list_chunks_with_status(&["structure_starts", "carvers", "features"])
+    ///     .unwrap();
+    /// ```
+    pub fn list_chunks_with_status(
+        &self,
+        statuses: &[&str],
+    ) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        let mut chunks = Vec::new();
+
+        for (chunk_x, chunk_z) in self.list_chunks()? {
+            let status = self.load_chunk_raw(chunk_x, chunk_z)?.status()?;
+
+            if status.is_some_and(|status| statuses.contains(&status.as_str())) {
+                chunks.push((chunk_x, chunk_z));
+            }
+        }
+
+        Ok(chunks)
+    }
+
+    /// `LastUpdate` of every selected chunk, see `RawChunk::last_update`,
+    /// most recently updated first. Chunks without it are left out.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::{ChunkSelection, FolderChunkProvider};
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("test/region");
+    /// let last_updates = chunk_provider.last_updates(&ChunkSelection::All).unwrap();
+    ///
+    /// // Chunks saved during the last 10 minutes of play.
+    /// let latest_tick = last_updates[0].1;
+    /// let recent = chunk_provider
+    ///     .list_chunks_updated_since(&ChunkSelection::All, latest_tick - 10 * 60 * 20)
+    ///     .unwrap();
+    /// assert!(!recent.is_empty());
+    /// ```
+    pub fn last_updates(
+        &self,
+        selection: &ChunkSelection,
+    ) -> Result<Vec<ChunkTick>, ChunkLoadError> {
+        let mut last_updates = Vec::new();
+
+        for (chunk_x, chunk_z) in self.list_selected_chunks(selection)? {
+            if let Some(last_update) = self.load_chunk_raw(chunk_x, chunk_z)?.last_update()? {
+                last_updates.push(((chunk_x, chunk_z), last_update));
+            }
+        }
+
+        // Stable, so chunks updated together keep the listing order.
+        last_updates.sort_by_key(|&(_, last_update)| std::cmp::Reverse(last_update));
+
+        Ok(last_updates)
+    }
+
+    /// Lists the selected chunks whose `LastUpdate` is at least `tick`, in
+    /// listing order.
+    pub fn list_chunks_updated_since(
+        &self,
+        selection: &ChunkSelection,
+        tick: i64,
+    ) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        let mut chunks = Vec::new();
+
+        for (chunk_x, chunk_z) in self.list_selected_chunks(selection)? {
+            let last_update = self.load_chunk_raw(chunk_x, chunk_z)?.last_update()?;
+
+            if last_update.is_some_and(|last_update| last_update >= tick) {
+                chunks.push((chunk_x, chunk_z));
+            }
+        }
+
+        Ok(chunks)
+    }
+
+    /// Same as `delete_chunks_where`, but the predicate also receives the
+    /// chunk as stored in the region file, so it can peek at a few tags
+    /// with `RawChunk::status` or `RawChunk::inhabited_time` without
+    /// decoding the whole chunk.
+    pub fn delete_chunks_where_raw<F>(
+        &self,
+        selection: &ChunkSelection,
+        mut predicate: F,
+    ) -> Result<usize, WorldEditError>
+    where
+        F: FnMut((i32, i32), &AnvilChunkMetadata, &RawChunk) -> Result<bool, ChunkLoadError>,
+    {
+        self.delete_chunks_in_regions(selection, |coords, metadata, region_file| {
+            let (region_chunk_x, region_chunk_z) = chunk_coords_inside_region(coords.0, coords.1);
+            let mut buffer = Vec::new();
+            let compression_scheme = read_chunk_data(
+                region_file,
+                *metadata,
+                region_chunk_x,
+                region_chunk_z,
+                &mut buffer,
+            )?;
+            let raw_chunk = RawChunk::new(compression_scheme, buffer);
+
+            Ok(predicate(coords, metadata, &raw_chunk)?)
+        })
+    }
+
+    /// Deletes the selected chunks where players spent less than
+    /// `min_inhabited_time` ticks, which the game regenerates identically
+    /// from the seed. Chunks without `InhabitedTime` are kept. Returns the
+    /// amount of deleted chunks.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use anvil_region::{ChunkSelection, FolderChunkProvider};
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("world/region");
+    ///
+    /// // Chunks visited for less than 30 seconds.
+    /// let deleted = chunk_provider
+    ///     .prune_uninhabited(&ChunkSelection::All, 30 * 20)
+    ///     .unwrap();
+    /// ```
+    pub fn prune_uninhabited(
+        &self,
+        selection: &ChunkSelection,
+        min_inhabited_time: i64,
+    ) -> Result<usize, WorldEditError> {
+        self.delete_chunks_where_raw(selection, |_, _, raw_chunk| {
+            let inhabited_time = raw_chunk.inhabited_time()?;
+
+            Ok(inhabited_time.is_some_and(|inhabited_time| inhabited_time < min_inhabited_time))
+        })
+    }
+}
+
+/// Finds the tag in the root compound or in its `Level` compound, leaving
+/// the reader at its value. Returns false if the tag is missing.
+fn find_level_tag<R: Read>(reader: &mut R, name: &str, tag_id: u8) -> Result<bool, io::Error> {
+    if reader.read_u8()? != TAG_COMPOUND {
+        return Ok(false);
+    }
+
+    skip_string(reader)?;
+
+    find_tag_in_compound(reader, name, tag_id, true)
+}
+
+fn find_tag_in_compound<R: Read>(
+    reader: &mut R,
+    name: &str,
+    tag_id: u8,
+    is_root: bool,
+) -> Result<bool, io::Error> {
+    loop {
+        let found_id = reader.read_u8()?;
+
+        if found_id == TAG_END {
+            return Ok(false);
+        }
+
+        let found_name = read_string(reader)?;
+
+        if found_id == tag_id && found_name == name {
+            return Ok(true);
+        }
+
+        if found_id == TAG_COMPOUND && found_name == "Level" && is_root {
+            return find_tag_in_compound(reader, name, tag_id, false);
+        }
+
+        skip_payload(reader, found_id)?;
+    }
+}
+
+fn read_string<R: Read + ?Sized>(reader: &mut R) -> Result<String, io::Error> {
+    let length = reader.read_u16::<BigEndian>()?;
+    let mut bytes = vec![0; length as usize];
+    reader.read_exact(&mut bytes)?;
+
+    // Modified UTF-8 only differs for characters not found in statuses.
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn skip_string<R: Read>(reader: &mut R) -> Result<(), io::Error> {
+    let length = reader.read_u16::<BigEndian>()?;
+
+    skip(reader, length as u64)
+}
+
+fn skip<R: Read>(reader: &mut R, length: u64) -> Result<(), io::Error> {
+    let skipped = io::copy(&mut reader.take(length), &mut io::sink())?;
+
+    if skipped != length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(())
+}
+
+/// Skips the array length and its elements of the given size.
+fn skip_array<R: Read>(reader: &mut R, element_size: u64) -> Result<(), io::Error> {
+    let length = reader.read_i32::<BigEndian>()?.max(0) as u64;
+
+    skip(reader, length * element_size)
+}
+
+fn skip_payload<R: Read>(reader: &mut R, tag_id: u8) -> Result<(), io::Error> {
+    match tag_id {
+        1 => skip(reader, 1),
+        2 => skip(reader, 2),
+        3 | 5 => skip(reader, 4),
+        TAG_LONG | 6 => skip(reader, 8),
+        7 => skip_array(reader, 1),
+        TAG_STRING => skip_string(reader),
+        TAG_LIST => {
+            let element_id = reader.read_u8()?;
+            let length = reader.read_i32::<BigEndian>()?;
+
+            for _ in 0..length {
+                skip_payload(reader, element_id)?;
+            }
+
+            Ok(())
+        }
+        TAG_COMPOUND => loop {
+            let tag_id = reader.read_u8()?;
+
+            if tag_id == TAG_END {
+                return Ok(());
+            }
+
+            skip_string(reader)?;
+            skip_payload(reader, tag_id)?;
+        },
+        11 => skip_array(reader, 4),
+        12 => skip_array(reader, 8),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unknown tag id {}", tag_id),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_level;
+    use nbt::CompoundTag;
+
+    #[test]
+    fn test_list_chunks_with_status() {
+        let chunk_provider = FolderChunkProvider::new("test/region");
+        let chunks = chunk_provider
+            .list_chunks_with_status(&["postprocessed"])
+            .unwrap();
+        let mut expected = Vec::new();
+
+        for (chunk_x, chunk_z) in chunk_provider.list_chunks().unwrap() {
+            let chunk_compound_tag = chunk_provider.load_chunk(chunk_x, chunk_z).unwrap();
+
+            if chunk_level(&chunk_compound_tag).get_str("Status").ok() == Some("postprocessed") {
+                expected.push((chunk_x, chunk_z));
+            }
+        }
+
+        assert!(!expected.is_empty());
+        assert_eq!(chunks, expected);
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32_vec("Skipped", vec![1, 2, 3]);
+        chunk_compound_tag.insert_compound_tag_vec("Sections", vec![CompoundTag::new()]);
+        chunk_compound_tag.insert_str("Status", "minecraft:carvers");
+
+        for compression in [
+            Compression::Gzip,
+            Compression::Zlib,
+            Compression::Uncompressed,
+        ] {
+            let raw_chunk = RawChunk::encode(&chunk_compound_tag, compression, 6).unwrap();
+            assert_eq!(raw_chunk.status().unwrap().as_deref(), Some("carvers"));
+        }
+
+        let raw_chunk = RawChunk::encode(&CompoundTag::new(), Compression::Zlib, 6).unwrap();
+        assert_eq!(raw_chunk.status().unwrap(), None);
+    }
+
+    #[test]
+    fn test_prune_uninhabited() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider = FolderChunkProvider::new(folder.path());
+
+        for chunk_x in 0..4 {
+            let mut chunk_compound_tag = CompoundTag::new();
+            chunk_compound_tag.insert_str("Status", "full");
+            chunk_compound_tag.insert_i64("InhabitedTime", chunk_x as i64 * 100);
+
+            // Pre-1.18 chunks keep their tags in `Level`.
+            if chunk_x % 2 == 1 {
+                let mut root_compound_tag = CompoundTag::new();
+                root_compound_tag.insert_i32("DataVersion", 1343);
+                root_compound_tag.insert_compound_tag("Level", chunk_compound_tag);
+                chunk_compound_tag = root_compound_tag;
+            }
+
+            chunk_provider
+                .save_chunk(chunk_x, 0, chunk_compound_tag)
+                .unwrap();
+        }
+        // Chunks without `InhabitedTime` are kept.
+        chunk_provider.save_chunk(4, 0, CompoundTag::new()).unwrap();
+
+        let raw_chunk = chunk_provider.load_chunk_raw(3, 0).unwrap();
+        assert_eq!(raw_chunk.inhabited_time().unwrap(), Some(300));
+
+        let deleted = chunk_provider
+            .prune_uninhabited(&ChunkSelection::All, 200)
+            .unwrap();
+        assert_eq!(deleted, 2);
+        assert_eq!(
+            chunk_provider.list_chunks().unwrap(),
+            vec![(2, 0), (3, 0), (4, 0)]
+        );
+    }
+
+    #[test]
+    fn test_last_updates() {
+        let chunk_provider = FolderChunkProvider::new("test/region");
+        let last_updates = chunk_provider.last_updates(&ChunkSelection::All).unwrap();
+        let mut expected = Vec::new();
+
+        for (chunk_x, chunk_z) in chunk_provider.list_chunks().unwrap() {
+            let chunk_compound_tag = chunk_provider.load_chunk(chunk_x, chunk_z).unwrap();
+
+            if let Ok(last_update) = chunk_level(&chunk_compound_tag).get_i64("LastUpdate") {
+                expected.push(((chunk_x, chunk_z), last_update));
+            }
+        }
+
+        expected.sort_by_key(|&(_, last_update)| std::cmp::Reverse(last_update));
+        assert!(!expected.is_empty());
+        assert_eq!(last_updates, expected);
+
+        let (_, last_update) = last_updates[last_updates.len() / 2];
+        let updated = chunk_provider
+            .list_chunks_updated_since(&ChunkSelection::All, last_update)
+            .unwrap();
+        let updated_since = expected.iter().filter(|&&(_, t)| t >= last_update).count();
+        assert_eq!(updated.len(), updated_since);
+    }
+}
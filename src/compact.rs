@@ -0,0 +1,475 @@
+use crate::{
+    AnvilRegion, ChunkPayload, ChunkSaveError, Compression, FolderChunkProvider, RawChunk,
+    TimestampPolicy, WorldEditError,
+};
+use std::fs;
+use std::fs::File;
+use std::io::Cursor;
+use std::sync::Mutex;
+use std::thread;
+
+/// Highest compression level, used by `CompactOptions::recompress`.
+const MAXIMUM_COMPRESSION_LEVEL: u32 = 9;
+
+/// Settings of `FolderChunkProvider::compact_all`.
+pub struct CompactOptions<'a> {
+    recompress: bool,
+    remove_empty_regions: bool,
+    threads: usize,
+    timestamp_policy: TimestampPolicy,
+    progress: Option<Box<dyn Fn(CompactProgress) + Sync + 'a>>,
+}
+
+impl Default for CompactOptions<'_> {
+    fn default() -> Self {
+        CompactOptions {
+            recompress: false,
+            remove_empty_regions: true,
+            threads: 0,
+            timestamp_policy: TimestampPolicy::Preserve,
+            progress: None,
+        }
+    }
+}
+
+impl<'a> CompactOptions<'a> {
+    /// Default options: chunks keep their compression and timestamps, empty
+    /// regions are removed and one thread per available core is used.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compresses the chunks again with the compression of the provider at
+    /// the highest level, keeping the old data when it is smaller. Chunks
+    /// with compression schemes this crate can't decode are kept as is.
+    pub fn recompress(mut self, recompress: bool) -> Self {
+        self.recompress = recompress;
+        self
+    }
+
+    /// Removes the region files without chunks.
+    pub fn remove_empty_regions(mut self, remove_empty_regions: bool) -> Self {
+        self.remove_empty_regions = remove_empty_regions;
+        self
+    }
+
+    /// Amount of regions compacted at the same time, 0 for one per
+    /// available core.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Timestamps written for the compacted chunks.
+    pub fn timestamp_policy(mut self, timestamp_policy: TimestampPolicy) -> Self {
+        self.timestamp_policy = timestamp_policy;
+        self
+    }
+
+    /// Called after every region, from the thread which compacted it.
+    pub fn progress<F: Fn(CompactProgress) + Sync + 'a>(mut self, progress: F) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+}
+
+/// Progress of `FolderChunkProvider::compact_all`, reported after every
+/// region.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CompactProgress {
+    /// Coordinates of the region just compacted.
+    pub region: (i32, i32),
+    /// Amount of regions done, including this one.
+    pub regions_done: usize,
+    pub regions_total: usize,
+}
+
+/// Result of `FolderChunkProvider::compact_all`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CompactReport {
+    /// Regions rewritten without free sectors.
+    pub regions_compacted: usize,
+    /// Region files removed because they had no chunk.
+    pub regions_removed: usize,
+    /// Chunks whose data was replaced by smaller recompressed data.
+    pub chunks_recompressed: usize,
+    /// Size of the region files before compaction, in bytes.
+    pub bytes_before: u64,
+    /// Size of the region files after compaction, in bytes.
+    pub bytes_after: u64,
+}
+
+impl CompactReport {
+    /// Disk space freed by the compaction, in bytes.
+    pub fn bytes_reclaimed(&self) -> u64 {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+
+    fn add(&mut self, other: CompactReport) {
+        self.regions_compacted += other.regions_compacted;
+        self.regions_removed += other.regions_removed;
+        self.chunks_recompressed += other.chunks_recompressed;
+        self.bytes_before += other.bytes_before;
+        self.bytes_after += other.bytes_after;
+    }
+}
+
+impl<P: ChunkPayload + Send> FolderChunkProvider<P> {
+    /// Rewrites every region file of the folder with its chunks stored one
+    /// after the other, dropping the free sectors left by deleted and moved
+    /// chunks.
+    ///
+    /// Every region is written to a temporary file first, which then
+    /// replaces the region file, so a crash keeps the old or the new file.
+    /// Regions only stored compressed, such as `r.0.0.mca.gz`, are skipped.
+    ///
+    /// With `AnvilOptions::dry_run` no file is touched and the report tells
+    /// what would be done. On error, regions handled by other threads may
+    /// already be compacted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::{CompactOptions, FolderChunkProvider};
+    /// use nbt::CompoundTag;
+    ///
+    /// # let folder = tempfile::tempdir().unwrap();
+    /// # let folder = folder.path();
+    /// let chunk_provider = FolderChunkProvider::new(folder);
+    /// chunk_provider.save_chunk(0, 0, CompoundTag::new()).unwrap();
+    /// chunk_provider.save_chunk(1, 0, CompoundTag::new()).unwrap();
+    /// chunk_provider.delete_chunk(0, 0).unwrap();
+    ///
+    /// let options = CompactOptions::new()
+    ///     .recompress(true)
+    ///     .progress(|progress| println!("{}/{}", progress.regions_done, progress.regions_total));
+    /// let report = chunk_provider.compact_all(options).unwrap();
+    ///
+    /// assert_eq!(report.regions_compacted, 1);
+    /// assert_eq!(report.bytes_reclaimed(), 4096);
+    /// ```
+    pub fn compact_all(
+        &self,
+        options: CompactOptions<'_>,
+    ) -> Result<CompactReport, WorldEditError> {
+        if self.options.read_only {
+            return Err(ChunkSaveError::ReadOnly.into());
+        }
+
+        if !self.folder_path.exists() {
+            return Ok(CompactReport::default());
+        }
+
+        let regions = self.find_all_region_mca()?;
+        let regions_total = regions.len();
+
+        if regions.is_empty() {
+            return Ok(CompactReport::default());
+        }
+
+        let threads = match options.threads {
+            0 => thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            threads => threads,
+        }
+        .min(regions.len());
+        let queue = Mutex::new(regions);
+        let report = Mutex::new((CompactReport::default(), 0));
+        let error: Mutex<Option<WorldEditError>> = Mutex::new(None);
+
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| loop {
+                    if error.lock().unwrap().is_some() {
+                        break;
+                    }
+
+                    let next = queue.lock().unwrap().pop();
+                    let region = match next {
+                        Some(region) => region,
+                        None => break,
+                    };
+
+                    let region_report = match self.compact_region(region, &options) {
+                        Ok(region_report) => region_report,
+                        Err(e) => {
+                            error.lock().unwrap().get_or_insert(e);
+                            break;
+                        }
+                    };
+
+                    let regions_done = {
+                        let mut report = report.lock().unwrap();
+                        report.0.add(region_report);
+                        report.1 += 1;
+                        report.1
+                    };
+
+                    if let Some(progress) = &options.progress {
+                        progress(CompactProgress {
+                            region,
+                            regions_done,
+                            regions_total,
+                        });
+                    }
+                });
+            }
+        });
+
+        match error.into_inner().unwrap() {
+            Some(e) => Err(e),
+            None => Ok(report.into_inner().unwrap().0),
+        }
+    }
+
+    fn compact_region(
+        &self,
+        (region_x, region_z): (i32, i32),
+        options: &CompactOptions<'_>,
+    ) -> Result<CompactReport, WorldEditError> {
+        let region_path = self.region_path(region_x, region_z);
+        let _lock = self.region_locks.lock((region_x, region_z));
+
+        if !region_path.exists() {
+            return Ok(CompactReport::default());
+        }
+
+        let bytes_before = fs::metadata(&region_path)?.len();
+        let read_chunks = |region: &mut AnvilRegion<File>| {
+            let mut chunks = Vec::new();
+
+            let chunks_metadata = region.chunks_metadata;
+
+            for (index, metadata) in chunks_metadata.iter().enumerate() {
+                if metadata.is_empty() {
+                    continue;
+                }
+
+                let (chunk_x, chunk_z) = ((index % 32) as u8, (index / 32) as u8);
+                let raw_chunk = region.read_chunk_raw(chunk_x, chunk_z)?;
+                chunks.push((
+                    metadata.sector_index,
+                    (chunk_x, chunk_z),
+                    raw_chunk,
+                    metadata.last_modified_timestamp,
+                ));
+            }
+
+            Ok::<_, WorldEditError>(chunks)
+        };
+
+        let chunks = if self.options.dry_run {
+            // Opening the region for writing would repair it.
+            read_chunks(&mut AnvilRegion::file_read_only(&region_path)?)?
+        } else {
+            // Also replays the journal and recovers torn writes.
+            let chunks = self.with_locked_region(region_x, region_z, read_chunks)?;
+            // The region file is replaced, so it must not stay open.
+            self.region_cache
+                .lock()
+                .unwrap()
+                .remove((region_x, region_z))?;
+
+            chunks
+        };
+
+        let mut report = CompactReport {
+            bytes_before,
+            ..CompactReport::default()
+        };
+
+        if chunks.is_empty() && options.remove_empty_regions {
+            if !self.options.dry_run {
+                fs::remove_file(&region_path)?;
+                self.remove_journal(region_x, region_z)?;
+            }
+            report.regions_removed = 1;
+
+            return Ok(report);
+        }
+
+        let mut compacted = AnvilRegion::new(Cursor::new(Vec::new()))?;
+        let mut chunks = chunks;
+        // Keeps the chunks in the order they had in the file.
+        chunks.sort_by_key(|(sector_index, ..)| *sector_index);
+
+        compacted.write_batch(|compacted| {
+            for (_, (chunk_x, chunk_z), raw_chunk, timestamp) in chunks {
+                let raw_chunk = if options.recompress {
+                    match self.recompress_chunk(&raw_chunk)? {
+                        Some(recompressed) => {
+                            report.chunks_recompressed += 1;
+                            recompressed
+                        }
+                        None => raw_chunk,
+                    }
+                } else {
+                    raw_chunk
+                };
+
+                let timestamp = options.timestamp_policy.timestamp(timestamp);
+                compacted.write_chunk_raw(chunk_x, chunk_z, &raw_chunk, timestamp)?;
+            }
+
+            Ok::<_, WorldEditError>(())
+        })?;
+
+        let bytes = compacted.into_inner().into_inner();
+        report.bytes_after = bytes.len() as u64;
+        report.regions_compacted = 1;
+
+        if !self.options.dry_run {
+            let mut temporary_path = region_path.clone().into_os_string();
+            temporary_path.push(".tmp");
+            fs::write(&temporary_path, bytes)?;
+            fs::File::open(&temporary_path)?.sync_all()?;
+            fs::rename(&temporary_path, &region_path)?;
+            // Its writes are already in the compacted file.
+            self.remove_journal(region_x, region_z)?;
+        }
+
+        Ok(report)
+    }
+
+    /// Compresses the chunk at the highest level, returning the new data
+    /// only if it is smaller.
+    fn recompress_chunk(&self, raw_chunk: &RawChunk) -> Result<Option<RawChunk>, WorldEditError> {
+        if Compression::from_id(raw_chunk.compression_scheme()).is_none() {
+            return Ok(None);
+        }
+
+        let chunk_compound_tag: P = raw_chunk.decode_payload()?;
+        let recompressed = RawChunk::encode(
+            &chunk_compound_tag,
+            self.options.compression,
+            MAXIMUM_COMPRESSION_LEVEL,
+        )?;
+
+        if recompressed.data().len() < raw_chunk.data().len() {
+            Ok(Some(recompressed))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn remove_journal(&self, region_x: i32, region_z: i32) -> Result<(), std::io::Error> {
+        let journal_path = self.journal_path(region_x, region_z);
+
+        if journal_path.exists() {
+            fs::remove_file(journal_path)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilOptions, ChunkReader};
+    use nbt::CompoundTag;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_compact_all() {
+        let folder = tempfile::tempdir().unwrap();
+        let region_path = folder.path().join("r.0.0.mca");
+        fs::copy("test/region/r.0.0.mca", &region_path).unwrap();
+
+        let chunk_provider = FolderChunkProvider::new(folder.path());
+        let chunks = chunk_provider.list_chunks().unwrap();
+        for &(chunk_x, chunk_z) in chunks.iter().step_by(2) {
+            chunk_provider.delete_chunk(chunk_x, chunk_z).unwrap();
+        }
+        // A region left without chunks.
+        chunk_provider
+            .save_chunk(40, 0, CompoundTag::new())
+            .unwrap();
+        chunk_provider.delete_chunk(40, 0).unwrap();
+        let size_before = fs::metadata(&region_path).unwrap().len();
+
+        // Dry runs only report.
+        let options = AnvilOptions::new().dry_run(true);
+        let dry_provider = FolderChunkProvider::with_options(folder.path(), options);
+        let dry_report = dry_provider.compact_all(CompactOptions::new()).unwrap();
+        assert_eq!(dry_report.regions_compacted, 1);
+        assert_eq!(dry_report.regions_removed, 1);
+        assert_eq!(fs::metadata(&region_path).unwrap().len(), size_before);
+
+        let calls = AtomicUsize::new(0);
+        let options = CompactOptions::new().threads(2).progress(|progress| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            assert_eq!(progress.regions_total, 2);
+        });
+        let report = chunk_provider.compact_all(options).unwrap();
+        assert_eq!(report, dry_report);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(report.bytes_reclaimed() > 0);
+        assert_eq!(
+            fs::metadata(&region_path).unwrap().len(),
+            report.bytes_after
+        );
+        assert!(report.bytes_after < size_before);
+        assert!(!folder.path().join("r.1.0.mca").exists());
+
+        let remaining = chunk_provider.list_chunks().unwrap();
+        assert_eq!(remaining.len(), chunks.len() / 2);
+        for &(chunk_x, chunk_z) in &remaining {
+            assert!(chunk_provider.load_chunk(chunk_x, chunk_z).is_ok());
+        }
+
+        let report = chunk_provider
+            .compact_all(CompactOptions::new().recompress(true))
+            .unwrap();
+        assert!(report.chunks_recompressed > 0);
+        assert!(report.bytes_after <= report.bytes_before);
+        assert_eq!(chunk_provider.list_chunks().unwrap(), remaining);
+    }
+
+    #[test]
+    fn test_compact_all_dry_run_leaves_region_untouched() {
+        let folder = tempfile::tempdir().unwrap();
+        let region_path = folder.path().join("r.0.0.mca");
+        let journal_path = folder.path().join("r.0.0.mca.journal");
+        fs::copy("test/region/r.0.0.mca", &region_path).unwrap();
+        let region_bytes = fs::read(&region_path).unwrap();
+
+        // A journal left by a crash, which writing would replay.
+        let options = AnvilOptions::new().journal(true).max_open_regions(4);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        chunk_provider.save_chunk(0, 0, CompoundTag::new()).unwrap();
+        let journal = fs::read(&journal_path).unwrap();
+        chunk_provider.close_regions().unwrap();
+        fs::write(&region_path, &region_bytes).unwrap();
+        fs::write(&journal_path, &journal).unwrap();
+
+        let options = AnvilOptions::new().dry_run(true);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        let report = chunk_provider.compact_all(CompactOptions::new()).unwrap();
+        assert_eq!(report.regions_compacted, 1);
+
+        assert_eq!(fs::read(&region_path).unwrap(), region_bytes);
+        assert_eq!(fs::read(&journal_path).unwrap(), journal);
+    }
+
+    #[test]
+    fn test_compact_all_timestamp_policy() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider = FolderChunkProvider::new(folder.path());
+        chunk_provider.save_chunk(0, 0, CompoundTag::new()).unwrap();
+        chunk_provider
+            .save_chunk_with_timestamp(1, 0, CompoundTag::new(), 1000)
+            .unwrap();
+        chunk_provider.delete_chunk(0, 0).unwrap();
+
+        chunk_provider.compact_all(CompactOptions::new()).unwrap();
+        let metadata = (&chunk_provider).load_chunk_metadata(1, 0).unwrap();
+        assert_eq!(metadata.last_modified_timestamp(), 1000);
+
+        let options = CompactOptions::new().timestamp_policy(TimestampPolicy::Refresh);
+        chunk_provider.compact_all(options).unwrap();
+        let metadata = (&chunk_provider).load_chunk_metadata(1, 0).unwrap();
+        assert!(metadata.last_modified_timestamp() > 1000);
+    }
+}
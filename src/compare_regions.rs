@@ -0,0 +1,266 @@
+use crate::{AnvilRegion, ChunkLoadError, RawChunk};
+use nbt::{CompoundTag, Tag};
+use std::io::{Read, Seek, Write};
+
+/// How `compare_regions` compares chunks that exist in both regions.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PayloadComparison {
+    /// Chunks differ if the compression scheme or the compressed bytes
+    /// differ.
+    Bytes,
+    /// Chunks with different bytes are decoded and only differ if their NBT
+    /// differs, so recompressed chunks are equal. Tags are compared by value
+    /// and compound tags ignore the order of their entries.
+    Nbt,
+}
+
+/// Chunk whose payload differs between both regions.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChunkPayloadDiff {
+    /// Chunk coordinates inside the region.
+    pub chunk: (u8, u8),
+    /// Paths of the differing tags, like `Level.Sections[2].Y`. Always empty
+    /// when comparing bytes.
+    pub tag_paths: Vec<String>,
+}
+
+/// Differences between two region files, found by `compare_regions`.
+///
+/// Chunks are listed in header order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RegionDiff {
+    /// Chunks that only exist in the first region.
+    pub only_in_a: Vec<(u8, u8)>,
+    /// Chunks that only exist in the second region.
+    pub only_in_b: Vec<(u8, u8)>,
+    /// Chunks that exist in both regions with different header timestamps.
+    pub different_timestamps: Vec<(u8, u8)>,
+    /// Chunks that exist in both regions with different payloads.
+    pub different_payloads: Vec<ChunkPayloadDiff>,
+}
+
+impl RegionDiff {
+    /// Returns true if both regions hold the same chunks, ignoring
+    /// timestamps.
+    pub fn same_chunks(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.different_payloads.is_empty()
+    }
+
+    /// Returns true if no difference was found.
+    pub fn is_empty(&self) -> bool {
+        self.same_chunks() && self.different_timestamps.is_empty()
+    }
+}
+
+/// Compares the chunks of two regions, for example to check that a backup
+/// or a conversion is lossless.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{compare_regions, AnvilRegion, PayloadComparison};
+///
+/// let mut a = AnvilRegion::file("test/region/r.0.0.mca").unwrap();
+/// let mut b = AnvilRegion::file("test/region/r.0.0.mca").unwrap();
+/// let diff = compare_regions(&mut a, &mut b, PayloadComparison::Bytes).unwrap();
+///
+/// assert!(diff.is_empty());
+/// ```
+pub fn compare_regions<A, B>(
+    a: &mut AnvilRegion<A>,
+    b: &mut AnvilRegion<B>,
+    comparison: PayloadComparison,
+) -> Result<RegionDiff, ChunkLoadError>
+where
+    A: Seek + Read + Write,
+    B: Seek + Read + Write,
+{
+    let mut diff = RegionDiff::default();
+
+    for chunk_z in 0..32 {
+        for chunk_x in 0..32 {
+            let chunk = (chunk_x, chunk_z);
+            let a_metadata = a.get_metadata(chunk_x, chunk_z);
+            let b_metadata = b.get_metadata(chunk_x, chunk_z);
+
+            match (a_metadata.is_empty(), b_metadata.is_empty()) {
+                (true, true) => continue,
+                (false, true) => {
+                    diff.only_in_a.push(chunk);
+                    continue;
+                }
+                (true, false) => {
+                    diff.only_in_b.push(chunk);
+                    continue;
+                }
+                (false, false) => {}
+            }
+
+            if a_metadata.last_modified_timestamp() != b_metadata.last_modified_timestamp() {
+                diff.different_timestamps.push(chunk);
+            }
+
+            let a_raw_chunk = a.read_chunk_raw(chunk_x, chunk_z)?;
+            let b_raw_chunk = b.read_chunk_raw(chunk_x, chunk_z)?;
+
+            if a_raw_chunk == b_raw_chunk {
+                continue;
+            }
+
+            let tag_paths = match comparison {
+                PayloadComparison::Bytes => Vec::new(),
+                PayloadComparison::Nbt => {
+                    let tag_paths = raw_chunk_tag_diff(&a_raw_chunk, &b_raw_chunk)?;
+
+                    if tag_paths.is_empty() {
+                        continue;
+                    }
+
+                    tag_paths
+                }
+            };
+
+            diff.different_payloads
+                .push(ChunkPayloadDiff { chunk, tag_paths });
+        }
+    }
+
+    Ok(diff)
+}
+
+fn raw_chunk_tag_diff(a: &RawChunk, b: &RawChunk) -> Result<Vec<String>, ChunkLoadError> {
+    let mut tag_paths = Vec::new();
+    compound_tag_diff(&a.decode()?, &b.decode()?, "", &mut tag_paths);
+
+    Ok(tag_paths)
+}
+
+/// Pushes the paths of the tags that differ between both compound tags.
+fn compound_tag_diff(a: &CompoundTag, b: &CompoundTag, path: &str, tag_paths: &mut Vec<String>) {
+    let tag_path = |name: &str| {
+        if path.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}.{}", path, name)
+        }
+    };
+
+    for (name, a_tag) in a.iter() {
+        match b.iter().find(|(b_name, _)| *b_name == name) {
+            Some((_, b_tag)) => tag_diff(a_tag, b_tag, &tag_path(name), tag_paths),
+            None => tag_paths.push(tag_path(name)),
+        }
+    }
+
+    for (name, _) in b.iter() {
+        if !a.iter().any(|(a_name, _)| a_name == name) {
+            tag_paths.push(tag_path(name));
+        }
+    }
+}
+
+fn tag_diff(a: &Tag, b: &Tag, path: &str, tag_paths: &mut Vec<String>) {
+    let equal = match (a, b) {
+        (Tag::Byte(a), Tag::Byte(b)) => a == b,
+        (Tag::Short(a), Tag::Short(b)) => a == b,
+        (Tag::Int(a), Tag::Int(b)) => a == b,
+        (Tag::Long(a), Tag::Long(b)) => a == b,
+        // Compared by bits, so NaN payloads are equal to themselves.
+        (Tag::Float(a), Tag::Float(b)) => a.to_bits() == b.to_bits(),
+        (Tag::Double(a), Tag::Double(b)) => a.to_bits() == b.to_bits(),
+        (Tag::ByteArray(a), Tag::ByteArray(b)) => a == b,
+        (Tag::String(a), Tag::String(b)) => a == b,
+        (Tag::IntArray(a), Tag::IntArray(b)) => a == b,
+        (Tag::LongArray(a), Tag::LongArray(b)) => a == b,
+        (Tag::Compound(a), Tag::Compound(b)) => {
+            compound_tag_diff(a, b, path, tag_paths);
+            return;
+        }
+        (Tag::List(a), Tag::List(b)) if a.len() == b.len() => {
+            for (index, (a, b)) in a.iter().zip(b).enumerate() {
+                tag_diff(a, b, &format!("{}[{}]", path, index), tag_paths);
+            }
+            return;
+        }
+        _ => false,
+    };
+
+    if !equal {
+        tag_paths.push(path.to_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Compression;
+    use std::io::Cursor;
+
+    fn chunk_compound_tag(x_pos: i32) -> CompoundTag {
+        let mut section_compound_tag = CompoundTag::new();
+        section_compound_tag.insert_i8("Y", 0);
+
+        let mut level_compound_tag = CompoundTag::new();
+        level_compound_tag.insert_i32("xPos", x_pos);
+        level_compound_tag.insert_compound_tag_vec("Sections", vec![section_compound_tag]);
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+
+        chunk_compound_tag
+    }
+
+    fn region() -> AnvilRegion<Cursor<Vec<u8>>> {
+        AnvilRegion::new(Cursor::new(Vec::new())).unwrap()
+    }
+
+    #[test]
+    fn test_compare_regions() {
+        let mut a = region();
+        let mut b = region();
+
+        a.write_chunk_with_timestamp(0, 0, chunk_compound_tag(0), 1)
+            .unwrap();
+        a.write_chunk_with_timestamp(1, 0, chunk_compound_tag(1), 1)
+            .unwrap();
+        a.write_chunk_with_timestamp(2, 0, chunk_compound_tag(2), 1)
+            .unwrap();
+        b.write_chunk_with_timestamp(0, 0, chunk_compound_tag(0), 2)
+            .unwrap();
+        b.write_chunk_with_timestamp(1, 0, chunk_compound_tag(9), 1)
+            .unwrap();
+        b.write_chunk_with_timestamp(3, 0, chunk_compound_tag(3), 1)
+            .unwrap();
+
+        let diff = compare_regions(&mut a, &mut b, PayloadComparison::Nbt).unwrap();
+
+        assert_eq!(diff.only_in_a, vec![(2, 0)]);
+        assert_eq!(diff.only_in_b, vec![(3, 0)]);
+        assert_eq!(diff.different_timestamps, vec![(0, 0)]);
+        assert_eq!(
+            diff.different_payloads,
+            vec![ChunkPayloadDiff {
+                chunk: (1, 0),
+                tag_paths: vec!["Level.xPos".to_owned()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_compare_regions_recompressed() {
+        let mut a = region();
+        let mut b = region().with_compression(Compression::Gzip, 6);
+
+        a.write_chunk_with_timestamp(0, 0, chunk_compound_tag(0), 1)
+            .unwrap();
+        b.write_chunk_with_timestamp(0, 0, chunk_compound_tag(0), 1)
+            .unwrap();
+
+        let diff = compare_regions(&mut a, &mut b, PayloadComparison::Bytes).unwrap();
+        assert_eq!(diff.different_payloads.len(), 1);
+        assert!(diff.different_payloads[0].tag_paths.is_empty());
+
+        let diff = compare_regions(&mut a, &mut b, PayloadComparison::Nbt).unwrap();
+        assert!(diff.is_empty());
+    }
+}
@@ -0,0 +1,314 @@
+use crate::region_cache::RegionBytes;
+use crate::{
+    read_chunk_data, AnvilRegionHeader, ChunkLoadError, ChunkPayload, FolderChunkProvider,
+    RawChunk, RegionAndOffset, RegionNaming, VanillaNaming,
+};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::fs;
+use std::io;
+use std::io::{Cursor, Read, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Compression of a whole region file, as used by archived worlds.
+///
+/// A `FolderChunkProvider` reads a compressed region file when the region
+/// has no `r.<x>.<z>.mca` file, decompressing it in memory. Saving into it
+/// replaces it with an uncompressed region file, unless
+/// `AnvilOptions::recompress_regions` is enabled.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RegionFileCompression {
+    /// `r.<x>.<z>.mca.gz` files.
+    Gzip,
+    /// `r.<x>.<z>.mca.zst` files, only with the `zstd` feature.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl RegionFileCompression {
+    /// Every supported compression, in the order the files are looked for.
+    fn all() -> Vec<RegionFileCompression> {
+        vec![
+            RegionFileCompression::Gzip,
+            #[cfg(feature = "zstd")]
+            RegionFileCompression::Zstd,
+        ]
+    }
+
+    /// Suffix added to the name of the region file.
+    pub fn extension(self) -> &'static str {
+        match self {
+            RegionFileCompression::Gzip => "gz",
+            #[cfg(feature = "zstd")]
+            RegionFileCompression::Zstd => "zst",
+        }
+    }
+
+    /// Decompresses a whole region file.
+    pub fn decompress<R: Read>(self, reader: R) -> Result<Vec<u8>, io::Error> {
+        let mut bytes = Vec::new();
+
+        match self {
+            RegionFileCompression::Gzip => {
+                GzDecoder::new(reader).read_to_end(&mut bytes)?;
+            }
+            #[cfg(feature = "zstd")]
+            RegionFileCompression::Zstd => {
+                zstd::stream::read::Decoder::new(reader)?.read_to_end(&mut bytes)?;
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    /// Compresses a whole region file.
+    pub fn compress(self, bytes: &[u8]) -> Result<Vec<u8>, io::Error> {
+        match self {
+            RegionFileCompression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "zstd")]
+            RegionFileCompression::Zstd => zstd::stream::encode_all(bytes, 0),
+        }
+    }
+}
+
+/// Parse "r.1.2.mca.gz" into (1, 2) and its compression.
+pub fn parse_compressed_region_file_name(s: &str) -> Option<((i32, i32), RegionFileCompression)> {
+    parse_compressed_name(&VanillaNaming, s)
+}
+
+/// Parse the name of a compressed region file named following `naming`.
+pub(crate) fn parse_compressed_name(
+    naming: &dyn RegionNaming,
+    s: &str,
+) -> Option<((i32, i32), RegionFileCompression)> {
+    RegionFileCompression::all()
+        .into_iter()
+        .find_map(|compression| {
+            let region_file_name = s.strip_suffix(compression.extension())?.strip_suffix('.')?;
+
+            Some((naming.parse_region_name(region_file_name)?, compression))
+        })
+}
+
+impl<P: ChunkPayload> FolderChunkProvider<P> {
+    pub(crate) fn region_path(&self, region_x: i32, region_z: i32) -> PathBuf {
+        self.folder_path.join(self.region_file_name(region_x, region_z))
+    }
+
+    /// Returns the compressed region file of the region, if it has no
+    /// uncompressed one.
+    pub(crate) fn compressed_region(
+        &self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Option<(PathBuf, RegionFileCompression)> {
+        let region_path = self.region_path(region_x, region_z);
+
+        if region_path.exists() {
+            return None;
+        }
+
+        RegionFileCompression::all()
+            .into_iter()
+            .map(|compression| {
+                let mut compressed_path = region_path.clone().into_os_string();
+                compressed_path.push(".");
+                compressed_path.push(compression.extension());
+
+                (PathBuf::from(compressed_path), compression)
+            })
+            .find(|(compressed_path, _)| compressed_path.exists())
+    }
+
+    /// Returns the decompressed region file, if the region is only stored
+    /// compressed. The last decompressed regions are kept in memory.
+    pub(crate) fn compressed_region_bytes(
+        &self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Option<Result<RegionBytes, io::Error>> {
+        let (compressed_path, compression) = self.compressed_region(region_x, region_z)?;
+
+        if let Some(bytes) = self
+            .decompressed_regions
+            .lock()
+            .unwrap()
+            .get(&(region_x, region_z))
+        {
+            return Some(Ok(bytes.clone()));
+        }
+
+        let decompressed = fs::File::open(compressed_path).and_then(|file| {
+            self.telemetry.region_opened();
+
+            compression.decompress(io::BufReader::new(file))
+        });
+
+        Some(decompressed.map(|bytes| {
+            let bytes = RegionBytes(Arc::new(bytes));
+            let length = bytes.0.len() as u64;
+            self.telemetry.decompressed(length);
+            self.decompressed_regions.lock().unwrap().insert(
+                (region_x, region_z),
+                bytes.clone(),
+                length,
+            );
+
+            bytes
+        }))
+    }
+
+    /// Reads a chunk of a region only stored compressed, `None` if the
+    /// region has an uncompressed region file.
+    pub(crate) fn load_compressed_chunk_raw(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+    ) -> Option<Result<RawChunk, ChunkLoadError>> {
+        let RegionAndOffset {
+            region_x,
+            region_z,
+            region_chunk_x,
+            region_chunk_z,
+        } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
+
+        let bytes = self.compressed_region_bytes(region_x, region_z)?;
+
+        Some(bytes.map_err(ChunkLoadError::from).and_then(|bytes| {
+            let mut region = Cursor::new(bytes);
+            let header = AnvilRegionHeader::from_reader(&mut region)?;
+            self.telemetry.header_read();
+            let metadata = header.get_metadata(region_chunk_x, region_chunk_z);
+            let mut data = Vec::new();
+            let compression_scheme = read_chunk_data(
+                &mut region,
+                metadata,
+                region_chunk_x,
+                region_chunk_z,
+                &mut data,
+            )?;
+
+            Ok(RawChunk::new(compression_scheme, data))
+        }))
+    }
+
+    /// Decompresses a region only stored compressed into its uncompressed
+    /// region file before it is modified.
+    ///
+    /// The compressed file is removed, unless regions are recompressed, in
+    /// which case it is returned so that `recompress_region` replaces it
+    /// after the modification.
+    pub(crate) fn expand_compressed_region(
+        &self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<Option<(PathBuf, RegionFileCompression)>, io::Error> {
+        let bytes = match self.compressed_region_bytes(region_x, region_z) {
+            Some(bytes) => bytes?,
+            None => return Ok(None),
+        };
+        let (compressed_path, compression) = self.compressed_region(region_x, region_z).unwrap();
+
+        fs::write(self.region_path(region_x, region_z), bytes.as_ref())?;
+        self.decompressed_regions
+            .lock()
+            .unwrap()
+            .remove(&(region_x, region_z));
+
+        if self.options.recompress_regions {
+            Ok(Some((compressed_path, compression)))
+        } else {
+            fs::remove_file(compressed_path)?;
+
+            Ok(None)
+        }
+    }
+
+    /// Compresses the uncompressed region file of a region expanded by
+    /// `expand_compressed_region` back into its compressed file.
+    pub(crate) fn recompress_region(
+        &self,
+        region_x: i32,
+        region_z: i32,
+        compressed_path: PathBuf,
+        compression: RegionFileCompression,
+    ) -> Result<(), io::Error> {
+        let region_path = self.region_path(region_x, region_z);
+        let compressed = compression.compress(&fs::read(&region_path)?)?;
+
+        // Replaced at once, so a crash keeps the old or the new file.
+        let mut temporary_path = compressed_path.clone().into_os_string();
+        temporary_path.push(".tmp");
+        fs::write(&temporary_path, compressed)?;
+        fs::rename(&temporary_path, compressed_path)?;
+
+        fs::remove_file(region_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chunk_level, AnvilOptions, ChunkReader};
+    use nbt::CompoundTag;
+
+    fn compress_region(folder: &std::path::Path, compression: RegionFileCompression) {
+        let region_path = folder.join("r.0.0.mca");
+        let compressed = compression
+            .compress(&fs::read(&region_path).unwrap())
+            .unwrap();
+        fs::write(
+            folder.join(format!("r.0.0.mca.{}", compression.extension())),
+            compressed,
+        )
+        .unwrap();
+        fs::remove_file(region_path).unwrap();
+    }
+
+    #[test]
+    fn test_compressed_region_files() {
+        assert_eq!(
+            parse_compressed_region_file_name("r.-1.2.mca.gz"),
+            Some(((-1, 2), RegionFileCompression::Gzip))
+        );
+        assert_eq!(parse_compressed_region_file_name("r.-1.2.mca"), None);
+
+        let folder = tempfile::tempdir().unwrap();
+        fs::copy("test/region/r.0.0.mca", folder.path().join("r.0.0.mca")).unwrap();
+        compress_region(folder.path(), RegionFileCompression::Gzip);
+
+        let options = AnvilOptions::new().recompress_regions(true);
+        let mut chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        let chunk_compound_tag = chunk_provider.load_chunk(4, 2).unwrap();
+        assert_eq!(chunk_level(&chunk_compound_tag).get_i32("xPos").unwrap(), 4);
+        assert_eq!(chunk_provider.list_chunks().unwrap().len(), 277);
+        assert_eq!(chunk_provider.list_regions().unwrap(), vec![(0, 0)]);
+        assert!(chunk_provider.load_region_header(0, 0).is_ok());
+
+        // Saved back compressed.
+        chunk_provider.save_chunk(0, 0, CompoundTag::new()).unwrap();
+        chunk_provider.delete_chunk(4, 2).unwrap();
+        assert!(!folder.path().join("r.0.0.mca").exists());
+        assert!(chunk_provider.try_load_chunk(0, 0).unwrap().is_some());
+        assert!(chunk_provider.try_load_chunk(4, 2).unwrap().is_none());
+        assert_eq!(chunk_provider.list_chunks().unwrap().len(), 276);
+
+        // Replaced by an uncompressed region file.
+        let chunk_provider = FolderChunkProvider::new(folder.path());
+        chunk_provider.save_chunk(1, 0, CompoundTag::new()).unwrap();
+        assert!(folder.path().join("r.0.0.mca").exists());
+        assert!(!folder.path().join("r.0.0.mca.gz").exists());
+        assert!(chunk_provider.try_load_chunk(0, 0).unwrap().is_some());
+
+        #[cfg(feature = "zstd")]
+        {
+            compress_region(folder.path(), RegionFileCompression::Zstd);
+            assert!(chunk_provider.try_load_chunk(1, 0).unwrap().is_some());
+        }
+    }
+}
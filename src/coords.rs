@@ -0,0 +1,250 @@
+//! Iterators over chunk coordinates in useful orders.
+//!
+//! # Example
+//!
+//! ```
+//! use anvil_region::coords::{load_chunks, within_radius};
+//! use anvil_region::FolderChunkProvider;
+//!
+//! # let folder = tempfile::tempdir().unwrap();
+//! # let folder = folder.path();
+//! let mut chunk_provider = FolderChunkProvider::new(folder);
+//!
+//! // Chunks around spawn, nearest first.
+//! for result in load_chunks(&mut chunk_provider, within_radius((0, 0), 8)) {
+//!     let ((chunk_x, chunk_z), chunk_compound_tag) = result.unwrap();
+//! }
+//! ```
+use crate::{ChunkLoadError, ChunkReader};
+use nbt::CompoundTag;
+use std::convert::TryFrom;
+
+/// Chunk coordinates in a square spiral around a center, counterclockwise
+/// starting east of it. Every ring is completed before the next one, so the
+/// coordinates are sorted by their Chebyshev distance to the center.
+///
+/// The iterator is endless, coordinates wrap around at the `i32` limits.
+#[derive(Clone, Debug)]
+pub struct Spiral {
+    position: (i32, i32),
+    /// Direction of the current leg of the spiral.
+    direction: (i32, i32),
+    leg_length: u32,
+    leg_position: u32,
+}
+
+/// Chunk coordinates in a spiral around `center`, see `Spiral`.
+pub fn spiral_from(center: (i32, i32)) -> Spiral {
+    Spiral {
+        position: center,
+        direction: (1, 0),
+        leg_length: 1,
+        leg_position: 0,
+    }
+}
+
+impl Iterator for Spiral {
+    type Item = (i32, i32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let position = self.position;
+
+        self.position = (
+            position.0.wrapping_add(self.direction.0),
+            position.1.wrapping_add(self.direction.1),
+        );
+        self.leg_position += 1;
+
+        if self.leg_position == self.leg_length {
+            self.leg_position = 0;
+            self.direction = (-self.direction.1, self.direction.0);
+
+            // Legs grow after every turn to the west or to the east.
+            if self.direction.1 == 0 {
+                self.leg_length += 1;
+            }
+        }
+
+        Some(position)
+    }
+}
+
+/// Chunk coordinates within `radius` chunks of `center`, in the order of
+/// `spiral_from`, so the nearest chunks come first.
+pub fn within_radius(center: (i32, i32), radius: u32) -> impl Iterator<Item = (i32, i32)> {
+    let side = 2 * u64::from(radius) + 1;
+    let radius = i64::from(radius);
+
+    spiral_from(center)
+        .take((side * side) as usize)
+        .filter(move |&(chunk_x, chunk_z)| {
+            let dx = i64::from(chunk_x.wrapping_sub(center.0));
+            let dz = i64::from(chunk_z.wrapping_sub(center.1));
+
+            dx * dx + dz * dz <= radius * radius
+        })
+}
+
+/// Chunk coordinates inside a rectangle, row by row from the north-west
+/// corner.
+#[derive(Clone, Debug)]
+pub struct Rect {
+    min_x: i32,
+    max_x: i32,
+    max_z: i32,
+    /// Next coordinates, `None` once the rectangle is exhausted.
+    next: Option<(i32, i32)>,
+}
+
+/// Chunk coordinates inside the rectangle defined by two opposite corners,
+/// both corners included.
+pub fn rect(corner_a: (i32, i32), corner_b: (i32, i32)) -> Rect {
+    let min = (corner_a.0.min(corner_b.0), corner_a.1.min(corner_b.1));
+
+    Rect {
+        min_x: min.0,
+        max_x: corner_a.0.max(corner_b.0),
+        max_z: corner_a.1.max(corner_b.1),
+        next: Some(min),
+    }
+}
+
+impl Iterator for Rect {
+    type Item = (i32, i32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (chunk_x, chunk_z) = self.next?;
+
+        self.next = if chunk_x < self.max_x {
+            Some((chunk_x + 1, chunk_z))
+        } else if chunk_z < self.max_z {
+            Some((self.min_x, chunk_z + 1))
+        } else {
+            None
+        };
+
+        Some((chunk_x, chunk_z))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = match self.next {
+            Some((chunk_x, chunk_z)) => {
+                let width = i64::from(self.max_x) - i64::from(self.min_x) + 1;
+                let rows = i64::from(self.max_z) - i64::from(chunk_z);
+
+                (rows * width + i64::from(self.max_x) - i64::from(chunk_x) + 1) as u64
+            }
+            None => 0,
+        };
+
+        match usize::try_from(remaining) {
+            Ok(remaining) => (remaining, Some(remaining)),
+            Err(_) => (usize::MAX, None),
+        }
+    }
+}
+
+/// Loads the chunks at the given coordinates in order, skipping the missing
+/// ones.
+pub fn load_chunks<P, I>(provider: &mut P, coords: I) -> LoadChunks<'_, P, I::IntoIter>
+where
+    P: ChunkReader + ?Sized,
+    I: IntoIterator<Item = (i32, i32)>,
+{
+    LoadChunks {
+        provider,
+        coords: coords.into_iter(),
+    }
+}
+
+/// Iterator returned by `load_chunks`.
+pub struct LoadChunks<'a, P: ?Sized, I> {
+    provider: &'a mut P,
+    coords: I,
+}
+
+impl<P, I> Iterator for LoadChunks<'_, P, I>
+where
+    P: ChunkReader + ?Sized,
+    I: Iterator<Item = (i32, i32)>,
+{
+    type Item = Result<((i32, i32), CompoundTag), ChunkLoadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (chunk_x, chunk_z) in &mut self.coords {
+            match self.provider.try_load_chunk(chunk_x, chunk_z) {
+                Ok(Some(chunk_compound_tag)) => {
+                    return Some(Ok(((chunk_x, chunk_z), chunk_compound_tag)))
+                }
+                Ok(None) => continue,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FolderChunkProvider;
+
+    #[test]
+    fn test_coordinate_orders() {
+        assert_eq!(
+            spiral_from((5, -5)).take(10).collect::<Vec<_>>(),
+            vec![
+                (5, -5),
+                (6, -5),
+                (6, -4),
+                (5, -4),
+                (4, -4),
+                (4, -5),
+                (4, -6),
+                (5, -6),
+                (6, -6),
+                (7, -6),
+            ]
+        );
+        // Rings are completed in order.
+        let mut last_distance = 0;
+        for (chunk_x, chunk_z) in spiral_from((0, 0)).take(81) {
+            let distance = chunk_x.abs().max(chunk_z.abs());
+            assert!(distance == last_distance || distance == last_distance + 1);
+            last_distance = distance;
+        }
+        assert_eq!(last_distance, 4);
+
+        let circle: Vec<_> = within_radius((0, 0), 1).collect();
+        assert_eq!(circle, vec![(0, 0), (1, 0), (0, 1), (-1, 0), (0, -1)]);
+        assert_eq!(within_radius((0, 0), 0).count(), 1);
+
+        let chunks = rect((1, 1), (0, 0));
+        assert_eq!(chunks.size_hint(), (4, Some(4)));
+        assert_eq!(
+            chunks.collect::<Vec<_>>(),
+            vec![(0, 0), (1, 0), (0, 1), (1, 1)]
+        );
+        let corner = (i32::MAX, i32::MAX);
+        assert_eq!(
+            rect(corner, (i32::MAX - 1, i32::MAX)).collect::<Vec<_>>(),
+            vec![(i32::MAX - 1, i32::MAX), corner]
+        );
+    }
+
+    #[test]
+    fn test_load_chunks() {
+        let folder = tempfile::tempdir().unwrap();
+        let mut chunk_provider = FolderChunkProvider::new(folder.path());
+        chunk_provider.save_chunk(1, 0, CompoundTag::new()).unwrap();
+        chunk_provider
+            .save_chunk(-1, 0, CompoundTag::new())
+            .unwrap();
+
+        let loaded: Vec<_> = load_chunks(&mut chunk_provider, within_radius((0, 0), 1))
+            .map(|result| result.unwrap().0)
+            .collect();
+        assert_eq!(loaded, vec![(1, 0), (-1, 0)]);
+    }
+}
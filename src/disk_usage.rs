@@ -0,0 +1,260 @@
+use crate::{
+    read_chunk_length, AnvilChunkMetadata, AnvilRegionHeader, ChunkLoadError, ChunkPayload,
+    FolderChunkProvider, REGION_SECTOR_BYTES_LENGTH,
+};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+
+/// Disk space used by a region file.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RegionDiskUsage {
+    /// Region coordinates.
+    pub region: (i32, i32),
+    /// Size of the region file in bytes.
+    pub file_size: u64,
+    /// Bytes of chunk data, the compression scheme and compressed data of
+    /// every chunk.
+    pub payload_bytes: u64,
+    /// Amount of chunks stored in the region.
+    pub chunks: usize,
+}
+
+impl RegionDiskUsage {
+    /// Bytes that don't hold chunk data: the header, the chunk lengths,
+    /// the padding up to sector boundaries and the free sectors.
+    pub fn overhead_bytes(&self) -> u64 {
+        self.file_size.saturating_sub(self.payload_bytes)
+    }
+
+    /// Share of the file that doesn't hold chunk data, from 0 to 100.
+    pub fn overhead_percentage(&self) -> f64 {
+        overhead_percentage(self.overhead_bytes(), self.file_size)
+    }
+}
+
+/// Disk space used by the region files of a world, see
+/// `FolderChunkProvider::disk_usage`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WorldDiskUsage {
+    /// Usage of every region file, sorted by region coordinates.
+    pub regions: Vec<RegionDiskUsage>,
+}
+
+impl WorldDiskUsage {
+    /// Size of all region files in bytes.
+    pub fn file_size(&self) -> u64 {
+        self.regions.iter().map(|region| region.file_size).sum()
+    }
+
+    /// Bytes of chunk data of all region files.
+    pub fn payload_bytes(&self) -> u64 {
+        self.regions.iter().map(|region| region.payload_bytes).sum()
+    }
+
+    /// Bytes of all region files that don't hold chunk data.
+    pub fn overhead_bytes(&self) -> u64 {
+        self.regions
+            .iter()
+            .map(|region| region.overhead_bytes())
+            .sum()
+    }
+
+    /// Share of all region files that doesn't hold chunk data, from 0 to 100.
+    pub fn overhead_percentage(&self) -> f64 {
+        overhead_percentage(self.overhead_bytes(), self.file_size())
+    }
+}
+
+/// Stored size of a chunk, see `FolderChunkProvider::largest_chunks`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ChunkSize {
+    /// Chunk coordinates.
+    pub chunk: (i32, i32),
+    /// Bytes of chunk data, the compression scheme and compressed data.
+    pub length: u32,
+    /// Amount of sectors allocated to the chunk.
+    pub sectors: u8,
+}
+
+/// Chunk coordinates, metadata and stored length of every chunk of a region.
+type ChunkLengths = Vec<((i32, i32), AnvilChunkMetadata, u32)>;
+
+fn overhead_percentage(overhead_bytes: u64, file_size: u64) -> f64 {
+    if file_size == 0 {
+        return 0.0;
+    }
+
+    overhead_bytes as f64 * 100.0 / file_size as f64
+}
+
+impl<P: ChunkPayload> FolderChunkProvider<P> {
+    /// Measures how much of every region file holds chunk data, to find the
+    /// regions worth defragmenting.
+    ///
+    /// Only the region headers and the chunk lengths are read.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::FolderChunkProvider;
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("test/region");
+    /// let usage = chunk_provider.disk_usage().unwrap();
+    ///
+    /// assert_eq!(usage.regions.len(), 1);
+    /// assert!(usage.payload_bytes() < usage.file_size());
+    /// ```
+    pub fn disk_usage(&self) -> Result<WorldDiskUsage, ChunkLoadError> {
+        let mut regions = Vec::new();
+
+        for (region_x, region_z) in self.find_all_region_mca()? {
+            let (file_size, chunk_lengths) = self.read_chunk_lengths(region_x, region_z)?;
+
+            regions.push(RegionDiskUsage {
+                region: (region_x, region_z),
+                file_size,
+                payload_bytes: chunk_lengths
+                    .iter()
+                    .map(|&(_, _, length)| length as u64)
+                    .sum(),
+                chunks: chunk_lengths.len(),
+            });
+        }
+
+        regions.sort_unstable_by_key(|usage| usage.region);
+
+        Ok(WorldDiskUsage { regions })
+    }
+
+    /// Finds the `n` chunks with the most compressed data, largest first.
+    ///
+    /// Oversized chunks are slow to load and save. Only the region headers
+    /// and the chunk lengths are read, chunks are not decoded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::FolderChunkProvider;
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("test/region");
+    /// let largest = chunk_provider.largest_chunks(3).unwrap();
+    ///
+    /// assert_eq!(largest.len(), 3);
+    /// assert!(largest[0].length >= largest[2].length);
+    /// ```
+    pub fn largest_chunks(&self, n: usize) -> Result<Vec<ChunkSize>, ChunkLoadError> {
+        // Smallest of the largest chunks found so far on top.
+        let mut largest = BinaryHeap::with_capacity(n + 1);
+
+        for (region_x, region_z) in self.find_all_region_mca()? {
+            let (_, chunk_lengths) = self.read_chunk_lengths(region_x, region_z)?;
+
+            for (chunk, metadata, length) in chunk_lengths {
+                largest.push(Reverse((length, metadata.sectors(), Reverse(chunk))));
+
+                if largest.len() > n {
+                    largest.pop();
+                }
+            }
+        }
+
+        Ok(largest
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((length, sectors, Reverse(chunk)))| ChunkSize {
+                chunk,
+                length,
+                sectors,
+            })
+            .collect())
+    }
+
+    /// Reads the size of the region file and the length of its chunks.
+    ///
+    /// Lengths past the allocated sectors only count the sectors.
+    fn read_chunk_lengths(
+        &self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<(u64, ChunkLengths), ChunkLoadError> {
+        let region_name = self.region_file_name(region_x, region_z);
+        self.write_open_header(region_x, region_z)?;
+        let mut file = File::open(self.folder_path.join(region_name))?;
+        let file_size = file.metadata()?.len();
+        let header = AnvilRegionHeader::from_reader(&mut file)?;
+        let mut chunk_lengths = Vec::new();
+
+        for ((region_chunk_x, region_chunk_z), metadata) in header.chunks() {
+            let chunk_x = (region_x * 32) + i32::from(region_chunk_x);
+            let chunk_z = (region_z * 32) + i32::from(region_chunk_z);
+            let maximum_length = metadata.sectors() as u32 * REGION_SECTOR_BYTES_LENGTH as u32 - 4;
+            let length = read_chunk_length(&mut file, metadata)?.min(maximum_length);
+
+            chunk_lengths.push(((chunk_x, chunk_z), metadata, length));
+        }
+
+        Ok((file_size, chunk_lengths))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilOptions, Compression};
+    use nbt::CompoundTag;
+
+    #[test]
+    fn test_disk_usage() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider = FolderChunkProvider::new(folder.path().to_str().unwrap());
+
+        chunk_provider.save_chunk(0, 0, CompoundTag::new()).unwrap();
+        chunk_provider.save_chunk(1, 0, CompoundTag::new()).unwrap();
+        chunk_provider
+            .save_chunk(-1, 0, CompoundTag::new())
+            .unwrap();
+        chunk_provider.delete_chunk(1, 0).unwrap();
+
+        let usage = chunk_provider.disk_usage().unwrap();
+        let regions: Vec<_> = usage.regions.iter().map(|usage| usage.region).collect();
+        assert_eq!(regions, vec![(-1, 0), (0, 0)]);
+
+        let region_usage = usage.regions[1];
+        assert_eq!(region_usage.chunks, 1);
+        assert_eq!(region_usage.file_size, 4 * 4096);
+
+        // An empty compound tag is 3 bytes, compressed with zlib.
+        assert!(region_usage.payload_bytes > 3 && region_usage.payload_bytes < 100);
+        assert!(region_usage.overhead_percentage() > 99.0);
+        assert_eq!(
+            usage.overhead_bytes(),
+            usage.file_size() - usage.payload_bytes()
+        );
+    }
+
+    #[test]
+    fn test_largest_chunks() {
+        let folder = tempfile::tempdir().unwrap();
+        let options = AnvilOptions::new().compression(Compression::Uncompressed);
+        let chunk_provider =
+            FolderChunkProvider::with_options(folder.path().to_str().unwrap(), options);
+
+        for (chunk_x, size) in [(0, 10), (1, 5000), (-40, 300), (2, 20)] {
+            let mut chunk_compound_tag = CompoundTag::new();
+            chunk_compound_tag.insert_i8_vec("Data", vec![0; size]);
+            chunk_provider
+                .save_chunk(chunk_x, 0, chunk_compound_tag)
+                .unwrap();
+        }
+
+        let largest = chunk_provider.largest_chunks(2).unwrap();
+        let chunks: Vec<_> = largest.iter().map(|size| size.chunk).collect();
+        assert_eq!(chunks, vec![(1, 0), (-40, 0)]);
+        assert_eq!(largest[0].sectors, 2);
+        assert!(largest[0].length > 5000);
+
+        assert_eq!(chunk_provider.largest_chunks(10).unwrap().len(), 4);
+        assert!(chunk_provider.largest_chunks(0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,248 @@
+use crate::{
+    sectors_required, AnvilChunkMetadata, AnvilRegionHeader, ChunkPayload, ChunkSaveError,
+    ChunkSelection, FolderChunkProvider, RawChunk, ReadAndSeek, RegionAndOffset, SectorAllocation,
+    WorldEditError, CHUNK_MAXIMUM_BYTES_LENGTH, REGION_SECTOR_BYTES_LENGTH,
+};
+use std::fs::File;
+use std::io;
+
+/// What the mutating operations of a provider in dry-run mode would have
+/// done, accumulated since the provider was created.
+///
+/// Every operation is computed against the region files on disk, which are
+/// left untouched, so operations don't see the effects of the previous ones.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{AnvilOptions, ChunkSelection, FolderChunkProvider};
+///
+/// let options = AnvilOptions::new().dry_run(true);
+/// let chunk_provider = FolderChunkProvider::with_options("test/region", options);
+///
+/// let deleted = chunk_provider
+///     .delete_chunks_where(&ChunkSelection::All, |(chunk_x, _), _| chunk_x < 8)
+///     .unwrap();
+///
+/// let report = chunk_provider.dry_run_report();
+/// assert_eq!(report.chunks_deleted, deleted as u64);
+/// assert!(report.sectors_freed > 0);
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct DryRunReport {
+    /// Chunks that would be saved.
+    pub chunks_saved: u64,
+    /// Existing chunks that would be deleted.
+    pub chunks_deleted: u64,
+    /// Bytes of saved chunks that would be written, padded to whole sectors.
+    pub bytes_written: u64,
+    /// Sectors released by saved chunks which would move elsewhere in their
+    /// region file, as they no longer fit their old sectors.
+    pub sectors_moved: u64,
+    /// Sectors released by deleted chunks.
+    pub sectors_freed: u64,
+    /// Region files that would be created.
+    pub regions_created: u64,
+    /// Region files that would be removed, as all their chunks are deleted.
+    pub regions_removed: u64,
+    /// Header timestamps that would be changed by `edit_timestamps`.
+    pub timestamps_changed: u64,
+}
+
+impl<P: ChunkPayload> FolderChunkProvider<P> {
+    /// What the mutating operations would have done, see
+    /// `AnvilOptions::dry_run`.
+    pub fn dry_run_report(&self) -> DryRunReport {
+        *self.dry_run_report.lock().unwrap()
+    }
+
+    /// Opens the region for reading only, `None` if it doesn't exist.
+    pub(crate) fn dry_run_region(
+        &self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<Option<Box<dyn ReadAndSeek>>, io::Error> {
+        if let Some(bytes) = self.compressed_region_bytes(region_x, region_z) {
+            return Ok(Some(Box::new(io::Cursor::new(bytes?))));
+        }
+
+        let region_path = self.folder_path.join(self.region_file_name(region_x, region_z));
+
+        if !region_path.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(Box::new(File::open(region_path)?)))
+    }
+
+    fn dry_run_metadata(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+    ) -> Result<Option<AnvilChunkMetadata>, io::Error> {
+        let RegionAndOffset {
+            region_x,
+            region_z,
+            region_chunk_x,
+            region_chunk_z,
+        } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
+
+        match self.dry_run_region(region_x, region_z)? {
+            Some(mut region) => {
+                let header = AnvilRegionHeader::from_reader(&mut region)?;
+
+                Ok(Some(header.get_metadata(region_chunk_x, region_chunk_z)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Reports the save of the chunk.
+    pub(crate) fn dry_run_save(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+    ) -> Result<(), ChunkSaveError> {
+        // Length, compression scheme and data.
+        let length = 5 + raw_chunk.data().len() as u32;
+
+        if length > CHUNK_MAXIMUM_BYTES_LENGTH {
+            return Err(ChunkSaveError::LengthExceedsMaximum { length });
+        }
+
+        let sectors = sectors_required(length);
+        let metadata = self.dry_run_metadata(chunk_x, chunk_z)?;
+        let mut report = self.dry_run_report.lock().unwrap();
+
+        report.chunks_saved += 1;
+        report.bytes_written += sectors as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+        match metadata {
+            Some(metadata) if metadata.is_empty() => {}
+            Some(metadata) => {
+                if metadata.sectors != sectors
+                    || self.options.sector_allocation == SectorAllocation::AppendOnly
+                {
+                    report.sectors_moved += metadata.sectors as u64;
+                }
+            }
+            None => report.regions_created += 1,
+        }
+
+        Ok(())
+    }
+
+    /// Reports the deletion of the chunk.
+    pub(crate) fn dry_run_delete(&self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError> {
+        if let Some(metadata) = self.dry_run_metadata(chunk_x, chunk_z)? {
+            if !metadata.is_empty() {
+                let mut report = self.dry_run_report.lock().unwrap();
+                report.chunks_deleted += 1;
+                report.sectors_freed += metadata.sectors as u64;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reports the deletion of every selected chunk for which
+    /// `should_delete` returns true, returning their amount.
+    pub(crate) fn dry_run_delete_chunks<F>(
+        &self,
+        regions: Vec<(i32, i32)>,
+        selection: &ChunkSelection,
+        mut should_delete: F,
+    ) -> Result<usize, WorldEditError>
+    where
+        F: FnMut(
+            (i32, i32),
+            &AnvilChunkMetadata,
+            &mut dyn ReadAndSeek,
+        ) -> Result<bool, WorldEditError>,
+    {
+        let mut deleted = 0;
+
+        for (region_x, region_z) in regions {
+            if !selection.may_contain_region(region_x, region_z) {
+                continue;
+            }
+
+            let mut region = match self.dry_run_region(region_x, region_z)? {
+                Some(region) => region,
+                None => continue,
+            };
+            let header = AnvilRegionHeader::from_reader(&mut region)?;
+            let mut remaining_chunks = header.chunks().count();
+            let mut report = DryRunReport::default();
+
+            for ((region_chunk_x, region_chunk_z), metadata) in header.chunks() {
+                let chunk_x = (region_x * 32) + i32::from(region_chunk_x);
+                let chunk_z = (region_z * 32) + i32::from(region_chunk_z);
+
+                if !selection.contains(chunk_x, chunk_z) {
+                    continue;
+                }
+
+                if should_delete((chunk_x, chunk_z), &metadata, &mut region)? {
+                    report.chunks_deleted += 1;
+                    report.sectors_freed += metadata.sectors() as u64;
+                    remaining_chunks -= 1;
+                }
+            }
+
+            if remaining_chunks == 0 {
+                report.regions_removed += 1;
+            }
+
+            let mut total_report = self.dry_run_report.lock().unwrap();
+            total_report.chunks_deleted += report.chunks_deleted;
+            total_report.sectors_freed += report.sectors_freed;
+            total_report.regions_removed += report.regions_removed;
+            deleted += report.chunks_deleted as usize;
+        }
+
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AnvilOptions;
+    use nbt::CompoundTag;
+    use std::fs;
+
+    #[test]
+    fn test_dry_run() {
+        let folder = tempfile::tempdir().unwrap();
+        let region_path = folder.path().join("r.0.0.mca");
+        fs::copy("test/region/r.0.0.mca", &region_path).unwrap();
+        let region_bytes = fs::read(&region_path).unwrap();
+
+        let options = AnvilOptions::new().dry_run(true);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        chunk_provider
+            .save_chunk(40, 0, CompoundTag::new())
+            .unwrap();
+        chunk_provider.save_chunk(4, 2, CompoundTag::new()).unwrap();
+        chunk_provider.delete_chunk(5, 2).unwrap();
+        chunk_provider.delete_chunk(15, 14).unwrap();
+        let deleted = chunk_provider
+            .delete_chunks_where_tag(&ChunkSelection::All, |_, _, _| true)
+            .unwrap();
+        assert_eq!(deleted, 277);
+
+        let report = chunk_provider.dry_run_report();
+        assert_eq!(report.chunks_saved, 2);
+        assert_eq!(report.bytes_written, 2 * 4096);
+        assert!(report.sectors_moved > 0);
+        assert_eq!(report.regions_created, 1);
+        assert_eq!(report.chunks_deleted, 1 + 277);
+        assert_eq!(report.regions_removed, 1);
+
+        // Nothing was written.
+        assert_eq!(fs::read(&region_path).unwrap(), region_bytes);
+        assert!(!folder.path().join("r.1.0.mca").exists());
+    }
+}
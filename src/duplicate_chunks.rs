@@ -0,0 +1,288 @@
+use crate::{chunk_level, ChunkLoadError, ChunkReader, ChunkSelection};
+use nbt::{CompoundTag, Tag};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ptr;
+
+/// What `find_duplicate_chunks` compares.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DuplicateKey {
+    /// The compression scheme and compressed bytes. Cheap, but only finds
+    /// chunks copied byte for byte, which keep the coordinates of the
+    /// original chunk.
+    RawBytes,
+    /// The decoded NBT, ignoring the order of compound entries and the
+    /// `xPos`/`zPos` tags, so chunks copied to other coordinates and
+    /// recompressed chunks are found too.
+    Nbt,
+}
+
+/// Group of identical chunks.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DuplicateChunks {
+    /// Hash of the compared bytes, the same for every chunk of the group.
+    pub hash: u64,
+    /// Coordinates of the identical chunks, at least two, in listing order.
+    pub chunks: Vec<(i32, i32)>,
+}
+
+/// Finds chunks of the selection that are identical to other chunks at
+/// different coordinates.
+///
+/// Chunks are hashed first and chunks with the same hash are compared again
+/// byte for byte, so every reported group is exact. Groups are sorted by
+/// their first chunk.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{find_duplicate_chunks, ChunkSelection, DuplicateKey, FolderChunkProvider};
+///
+/// let mut chunk_provider = FolderChunkProvider::new("test/region");
+/// let selection = ChunkSelection::rect((0, 0), (7, 7));
+/// let duplicates =
+///     find_duplicate_chunks(&mut chunk_provider, &selection, DuplicateKey::RawBytes).unwrap();
+///
+/// assert!(duplicates.is_empty());
+/// ```
+pub fn find_duplicate_chunks<P: ChunkReader + ?Sized>(
+    provider: &mut P,
+    selection: &ChunkSelection,
+    key: DuplicateKey,
+) -> Result<Vec<DuplicateChunks>, ChunkLoadError> {
+    let mut candidates: HashMap<u64, Vec<(i32, i32)>> = HashMap::new();
+
+    for (chunk_x, chunk_z) in provider.list_chunks_in(selection)? {
+        let bytes = compared_bytes(provider, chunk_x, chunk_z, key)?;
+
+        candidates
+            .entry(hash_bytes(&bytes))
+            .or_default()
+            .push((chunk_x, chunk_z));
+    }
+
+    let mut duplicates = Vec::new();
+
+    for (hash, chunks) in candidates {
+        if chunks.len() < 2 {
+            continue;
+        }
+
+        // Chunks with the same hash are split by their actual bytes, in case
+        // of collisions.
+        let mut group_bytes: Vec<Vec<u8>> = Vec::new();
+        let mut groups: Vec<Vec<(i32, i32)>> = Vec::new();
+
+        for (chunk_x, chunk_z) in chunks {
+            let bytes = compared_bytes(provider, chunk_x, chunk_z, key)?;
+
+            match group_bytes.iter().position(|other| *other == bytes) {
+                Some(index) => groups[index].push((chunk_x, chunk_z)),
+                None => {
+                    group_bytes.push(bytes);
+                    groups.push(vec![(chunk_x, chunk_z)]);
+                }
+            }
+        }
+
+        duplicates.extend(
+            groups
+                .into_iter()
+                .filter(|chunks| chunks.len() > 1)
+                .map(|chunks| DuplicateChunks { hash, chunks }),
+        );
+    }
+
+    duplicates.sort_unstable_by_key(|duplicate| duplicate.chunks[0]);
+
+    Ok(duplicates)
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+fn compared_bytes<P: ChunkReader + ?Sized>(
+    provider: &mut P,
+    chunk_x: i32,
+    chunk_z: i32,
+    key: DuplicateKey,
+) -> Result<Vec<u8>, ChunkLoadError> {
+    match key {
+        DuplicateKey::RawBytes => {
+            let raw_chunk = provider.load_chunk_raw(chunk_x, chunk_z)?;
+            let mut bytes = vec![raw_chunk.compression_scheme()];
+            bytes.extend_from_slice(raw_chunk.data());
+
+            Ok(bytes)
+        }
+        DuplicateKey::Nbt => {
+            let chunk_compound_tag = provider.load_chunk(chunk_x, chunk_z)?;
+            let mut bytes = Vec::new();
+            let level_compound_tag = chunk_level(&chunk_compound_tag);
+            write_canonical_compound(&chunk_compound_tag, level_compound_tag, &mut bytes);
+
+            Ok(bytes)
+        }
+    }
+}
+
+/// Writes the compound tag with its entries sorted by name, skipping the
+/// chunk coordinates of the level compound tag.
+fn write_canonical_compound(
+    compound_tag: &CompoundTag,
+    level_compound_tag: &CompoundTag,
+    bytes: &mut Vec<u8>,
+) {
+    let is_level = ptr::eq(compound_tag, level_compound_tag);
+    let mut entries: Vec<_> = compound_tag
+        .iter()
+        .filter(|(name, _)| !(is_level && (*name == "xPos" || *name == "zPos")))
+        .collect();
+    entries.sort_unstable_by_key(|(name, _)| *name);
+
+    write_length(entries.len(), bytes);
+
+    for (name, tag) in entries {
+        write_length(name.len(), bytes);
+        bytes.extend_from_slice(name.as_bytes());
+        write_canonical_tag(tag, level_compound_tag, bytes);
+    }
+}
+
+fn write_canonical_tag(tag: &Tag, level_compound_tag: &CompoundTag, bytes: &mut Vec<u8>) {
+    match tag {
+        Tag::Byte(value) => {
+            bytes.push(1);
+            bytes.push(*value as u8);
+        }
+        Tag::Short(value) => {
+            bytes.push(2);
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        Tag::Int(value) => {
+            bytes.push(3);
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        Tag::Long(value) => {
+            bytes.push(4);
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        Tag::Float(value) => {
+            bytes.push(5);
+            bytes.extend_from_slice(&value.to_bits().to_be_bytes());
+        }
+        Tag::Double(value) => {
+            bytes.push(6);
+            bytes.extend_from_slice(&value.to_bits().to_be_bytes());
+        }
+        Tag::ByteArray(values) => {
+            bytes.push(7);
+            write_length(values.len(), bytes);
+            bytes.extend(values.iter().map(|&value| value as u8));
+        }
+        Tag::String(value) => {
+            bytes.push(8);
+            write_length(value.len(), bytes);
+            bytes.extend_from_slice(value.as_bytes());
+        }
+        Tag::List(tags) => {
+            bytes.push(9);
+            write_length(tags.len(), bytes);
+
+            for tag in tags {
+                write_canonical_tag(tag, level_compound_tag, bytes);
+            }
+        }
+        Tag::Compound(compound_tag) => {
+            bytes.push(10);
+            write_canonical_compound(compound_tag, level_compound_tag, bytes);
+        }
+        Tag::IntArray(values) => {
+            bytes.push(11);
+            write_length(values.len(), bytes);
+
+            for value in values {
+                bytes.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        Tag::LongArray(values) => {
+            bytes.push(12);
+            write_length(values.len(), bytes);
+
+            for value in values {
+                bytes.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+    }
+}
+
+fn write_length(length: usize, bytes: &mut Vec<u8>) {
+    bytes.extend_from_slice(&(length as u64).to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilOptions, Compression, FolderChunkProvider};
+
+    fn chunk_compound_tag(chunk_x: i32, data: Vec<i8>) -> CompoundTag {
+        let mut level_compound_tag = CompoundTag::new();
+        level_compound_tag.insert_i32("xPos", chunk_x);
+        level_compound_tag.insert_i32("zPos", 0);
+        level_compound_tag.insert_i8_vec("Data", data);
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+
+        chunk_compound_tag
+    }
+
+    #[test]
+    fn test_find_duplicate_chunks() {
+        let folder = tempfile::tempdir().unwrap();
+        let folder = folder.path().to_str().unwrap();
+        let chunk_provider = FolderChunkProvider::new(folder);
+
+        chunk_provider
+            .save_chunk(0, 0, chunk_compound_tag(0, vec![1, 2]))
+            .unwrap();
+        chunk_provider
+            .save_chunk(1, 0, chunk_compound_tag(1, vec![1, 2]))
+            .unwrap();
+        chunk_provider
+            .save_chunk(2, 0, chunk_compound_tag(2, vec![3]))
+            .unwrap();
+        // Same bytes as chunk (0, 0), including its coordinates.
+        chunk_provider
+            .save_chunk(40, 0, chunk_compound_tag(0, vec![1, 2]))
+            .unwrap();
+
+        // Recompressed copy of chunk (1, 0).
+        let options = AnvilOptions::new().compression(Compression::Gzip);
+        FolderChunkProvider::with_options(folder, options)
+            .save_chunk(-5, 0, chunk_compound_tag(-5, vec![1, 2]))
+            .unwrap();
+
+        let mut chunk_provider = FolderChunkProvider::new(folder);
+        let duplicates = find_duplicate_chunks(
+            &mut chunk_provider,
+            &ChunkSelection::All,
+            DuplicateKey::RawBytes,
+        )
+        .unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].chunks, vec![(0, 0), (40, 0)]);
+
+        let mut duplicates =
+            find_duplicate_chunks(&mut chunk_provider, &ChunkSelection::All, DuplicateKey::Nbt)
+                .unwrap();
+        assert_eq!(duplicates.len(), 1);
+        duplicates[0].chunks.sort_unstable();
+        assert_eq!(duplicates[0].chunks, vec![(-5, 0), (0, 0), (1, 0), (40, 0)]);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{
+    chunk_coords_inside_region, missing_chunk_as_none, ChunkLoadError, ChunkPayload,
+    FolderChunkProvider, RawChunk, WorldEditError,
+};
+use std::collections::BTreeMap;
+use std::io;
+
+/// Chunks modified in an editor but not written yet, see
+/// `FolderChunkProvider::edit_session`.
+///
+/// Saved chunks are compressed right away and kept in memory, loading a
+/// chunk returns its unsaved version if any. `flush_dirty` writes the
+/// modified chunks to the region files, dropping the session discards them.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::FolderChunkProvider;
+/// use nbt::CompoundTag;
+///
+/// # let folder = tempfile::tempdir().unwrap();
+/// # let folder = folder.path();
+/// let chunk_provider = FolderChunkProvider::new(folder);
+/// let mut session = chunk_provider.edit_session();
+///
+/// session.save_chunk(0, 0, CompoundTag::new()).unwrap();
+/// session.delete_chunk(1, 0);
+/// assert_eq!(session.dirty_chunks(), vec![(0, 0), (1, 0)]);
+/// assert!(chunk_provider.try_load_chunk(0, 0).unwrap().is_none());
+///
+/// assert_eq!(session.flush_dirty().unwrap(), 2);
+/// assert!(session.dirty_chunks().is_empty());
+/// assert!(chunk_provider.load_chunk(0, 0).is_ok());
+/// ```
+pub struct EditSession<'p, P> {
+    provider: &'p FolderChunkProvider<P>,
+    /// Compressed modified chunks by chunk coordinates, `None` for deleted
+    /// chunks.
+    dirty: BTreeMap<(i32, i32), Option<RawChunk>>,
+}
+
+impl<P: ChunkPayload> FolderChunkProvider<P> {
+    /// Starts a session keeping the modified chunks in memory until they
+    /// are flushed.
+    pub fn edit_session(&self) -> EditSession<'_, P> {
+        EditSession {
+            provider: self,
+            dirty: BTreeMap::new(),
+        }
+    }
+}
+
+impl<'p, P: ChunkPayload> EditSession<'p, P> {
+    /// Loads the chunk, in its unsaved version if it was modified in the
+    /// session.
+    pub fn load_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        match self.dirty.get(&(chunk_x, chunk_z)) {
+            Some(Some(raw_chunk)) => raw_chunk.decode_payload(),
+            Some(None) => {
+                let (chunk_x, chunk_z) = chunk_coords_inside_region(chunk_x, chunk_z);
+
+                Err(ChunkLoadError::ChunkNotFound { chunk_x, chunk_z })
+            }
+            None => self.provider.load_chunk(chunk_x, chunk_z),
+        }
+    }
+
+    pub fn try_load_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<Option<P>, ChunkLoadError> {
+        missing_chunk_as_none(self.load_chunk(chunk_x, chunk_z))
+    }
+
+    /// Marks the chunk as modified, compressing it with the options of the
+    /// provider.
+    pub fn save_chunk(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+    ) -> Result<(), io::Error> {
+        let options = &self.provider.options;
+        let raw_chunk = RawChunk::encode(
+            &chunk_compound_tag,
+            options.compression,
+            options.compression_level,
+        )?;
+        self.dirty.insert((chunk_x, chunk_z), Some(raw_chunk));
+
+        Ok(())
+    }
+
+    /// Marks the chunk as deleted.
+    pub fn delete_chunk(&mut self, chunk_x: i32, chunk_z: i32) {
+        self.dirty.insert((chunk_x, chunk_z), None);
+    }
+
+    /// Whether the chunk was modified since the last flush.
+    pub fn is_dirty(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        self.dirty.contains_key(&(chunk_x, chunk_z))
+    }
+
+    /// Coordinates of the chunks modified since the last flush, sorted.
+    pub fn dirty_chunks(&self) -> Vec<(i32, i32)> {
+        self.dirty.keys().copied().collect()
+    }
+
+    /// Writes the modified chunks to the region files, in the order of
+    /// `dirty_chunks`, and returns how many were written.
+    ///
+    /// Chunks are marked clean as soon as they are written, so on error
+    /// only the chunks not written yet stay dirty.
+    pub fn flush_dirty(&mut self) -> Result<usize, WorldEditError> {
+        let mut flushed = 0;
+
+        while let Some((&(chunk_x, chunk_z), change)) = self.dirty.iter().next() {
+            match change {
+                Some(raw_chunk) => self
+                    .provider
+                    .save_chunk_raw_inner(chunk_x, chunk_z, raw_chunk, None)?,
+                None => self.provider.delete_chunk(chunk_x, chunk_z)?,
+            }
+
+            self.dirty.remove(&(chunk_x, chunk_z));
+            flushed += 1;
+        }
+
+        Ok(flushed)
+    }
+
+    /// Forgets the modifications made since the last flush.
+    pub fn discard_changes(&mut self) {
+        self.dirty.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::value_chunk_tag;
+    use crate::{AnvilOptions, ChunkReader, TimestampPolicy};
+
+    #[test]
+    fn test_edit_session_dirty_chunks() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider = FolderChunkProvider::new(folder.path());
+        chunk_provider.save_chunk(5, 5, value_chunk_tag(1)).unwrap();
+
+        let mut session = chunk_provider.edit_session();
+        assert!(!session.is_dirty(5, 5));
+        session.save_chunk(5, 5, value_chunk_tag(2)).unwrap();
+        session.save_chunk(-40, 3, value_chunk_tag(3)).unwrap();
+        session.delete_chunk(5, 5);
+        session.save_chunk(6, 5, value_chunk_tag(4)).unwrap();
+        assert!(session.is_dirty(5, 5));
+        assert_eq!(session.dirty_chunks(), vec![(-40, 3), (5, 5), (6, 5)]);
+
+        // Unsaved versions are loaded, the region files are untouched.
+        assert!(session.try_load_chunk(5, 5).unwrap().is_none());
+        let value = session.load_chunk(6, 5).unwrap().get_i32("value").unwrap();
+        assert_eq!(value, 4);
+        assert!(chunk_provider.load_chunk(5, 5).is_ok());
+        assert!(chunk_provider.try_load_chunk(6, 5).unwrap().is_none());
+
+        assert_eq!(session.flush_dirty().unwrap(), 3);
+        assert!(session.dirty_chunks().is_empty());
+        assert!(chunk_provider.try_load_chunk(5, 5).unwrap().is_none());
+        let value = chunk_provider
+            .load_chunk(-40, 3)
+            .unwrap()
+            .get_i32("value")
+            .unwrap();
+        assert_eq!(value, 3);
+        assert_eq!(session.flush_dirty().unwrap(), 0);
+
+        session.save_chunk(7, 5, value_chunk_tag(5)).unwrap();
+        session.discard_changes();
+        assert_eq!(session.flush_dirty().unwrap(), 0);
+        assert!(chunk_provider.try_load_chunk(7, 5).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_edit_session_timestamp_policy() {
+        let folder = tempfile::tempdir().unwrap();
+        let options = AnvilOptions::new().timestamp_policy(TimestampPolicy::Preserve);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        chunk_provider
+            .save_chunk_with_timestamp(5, 5, value_chunk_tag(1), 1570215508)
+            .unwrap();
+
+        let mut session = chunk_provider.edit_session();
+        session.save_chunk(5, 5, value_chunk_tag(2)).unwrap();
+        assert_eq!(session.flush_dirty().unwrap(), 1);
+
+        let metadata = (&chunk_provider).load_chunk_metadata(5, 5).unwrap();
+        assert_eq!(metadata.last_modified_timestamp(), 1570215508);
+    }
+}
@@ -0,0 +1,305 @@
+use crate::{
+    AnvilRegionHeader, ChunkDeleter, ChunkLoadError, ChunkPayload, ChunkReader, ChunkSaveError,
+    ChunkSelection, ChunkWriter, Compression, RawChunk, RegionReader, WorldEditError,
+    DEFAULT_COMPRESSION_LEVEL,
+};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use nbt::CompoundTag;
+use std::io;
+use std::marker::PhantomData;
+
+/// Compression scheme of the chunks encrypted by `EncryptedChunkProvider`,
+/// which is not used by Minecraft.
+pub const ENCRYPTED_COMPRESSION_SCHEME: u8 = 101;
+
+/// Length of the random nonce stored before every encrypted chunk.
+const NONCE_LENGTH: usize = 12;
+
+/// Provider encrypting the chunks of another provider with AES-256-GCM.
+///
+/// Every chunk is compressed, then encrypted with a random nonce and its
+/// coordinates as associated data, so chunks can't be moved to other
+/// coordinates without failing to load. The encrypted chunks are saved as
+/// raw chunks with the `ENCRYPTED_COMPRESSION_SCHEME`, so region headers
+/// stay readable: chunks and regions are listed without the key, and
+/// timestamps are kept.
+///
+/// The wrapped provider must save raw chunks as they are, which is the case
+/// of a `FolderChunkProvider` without coordinate check. Region files
+/// returned by `get_region` contain the encrypted chunks. Chunks which
+/// aren't encrypted fail to load.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{ChunkReader, ChunkWriter, EncryptedChunkProvider, FolderChunkProvider};
+/// use nbt::CompoundTag;
+///
+/// # let folder = tempfile::tempdir().unwrap();
+/// # let folder = folder.path();
+/// let key = [7; 32];
+/// let mut chunk_provider = EncryptedChunkProvider::new(FolderChunkProvider::new(folder), &key);
+///
+/// chunk_provider.save_chunk(1, 2, CompoundTag::new()).unwrap();
+/// assert!(chunk_provider.load_chunk(1, 2).is_ok());
+///
+/// // The chunk is stored encrypted.
+/// assert!(FolderChunkProvider::new(folder).load_chunk(1, 2).is_err());
+/// ```
+pub struct EncryptedChunkProvider<T, P = CompoundTag> {
+    provider: T,
+    cipher: Aes256Gcm,
+    /// Compression applied before encryption.
+    compression: Compression,
+    payload: PhantomData<fn() -> P>,
+}
+
+fn encryption_error() -> ChunkLoadError {
+    let io_error = io::Error::new(io::ErrorKind::InvalidData, "chunk decryption failed");
+
+    ChunkLoadError::ReadError { io_error }
+}
+
+/// Chunk coordinates, authenticated along with the encrypted chunk.
+fn associated_data(chunk_x: i32, chunk_z: i32) -> [u8; 8] {
+    let mut associated_data = [0; 8];
+    associated_data[..4].copy_from_slice(&chunk_x.to_be_bytes());
+    associated_data[4..].copy_from_slice(&chunk_z.to_be_bytes());
+
+    associated_data
+}
+
+impl<T, P> EncryptedChunkProvider<T, P>
+where
+    T: ChunkReader<P> + ChunkWriter<P>,
+    P: ChunkPayload,
+{
+    /// Encrypts the chunks of the provider with the given 256 bit key.
+    pub fn new(provider: T, key: &[u8; 32]) -> Self {
+        EncryptedChunkProvider {
+            provider,
+            cipher: Aes256Gcm::new(key.into()),
+            compression: Compression::Zlib,
+            payload: PhantomData,
+        }
+    }
+
+    /// Compression of the chunks before they are encrypted, zlib by
+    /// default. Raw chunks keep their compression.
+    pub fn with_compression(self, compression: Compression) -> Self {
+        EncryptedChunkProvider {
+            compression,
+            ..self
+        }
+    }
+
+    /// Returns the wrapped provider, which reads and writes encrypted
+    /// chunks.
+    pub fn provider(&self) -> &T {
+        &self.provider
+    }
+
+    /// Returns the wrapped provider.
+    pub fn into_inner(self) -> T {
+        self.provider
+    }
+
+    /// Encrypts the compression scheme and data of the chunk, stored as the
+    /// nonce followed by the ciphertext.
+    fn encrypt(&self, chunk_x: i32, chunk_z: i32, raw_chunk: &RawChunk) -> RawChunk {
+        let mut plaintext = Vec::with_capacity(1 + raw_chunk.data().len());
+        plaintext.push(raw_chunk.compression_scheme());
+        plaintext.extend_from_slice(raw_chunk.data());
+
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: &plaintext,
+            aad: &associated_data(chunk_x, chunk_z),
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .expect("chunk encryption failed");
+
+        let mut data = Vec::with_capacity(NONCE_LENGTH + ciphertext.len());
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+
+        RawChunk::new(ENCRYPTED_COMPRESSION_SCHEME, data)
+    }
+
+    fn decrypt(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+    ) -> Result<RawChunk, ChunkLoadError> {
+        let data = raw_chunk.data();
+
+        if raw_chunk.compression_scheme() != ENCRYPTED_COMPRESSION_SCHEME
+            || data.len() < NONCE_LENGTH
+        {
+            return Err(encryption_error());
+        }
+
+        let (nonce, ciphertext) = data.split_at(NONCE_LENGTH);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: &associated_data(chunk_x, chunk_z),
+        };
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| encryption_error())?;
+
+        match plaintext.split_first() {
+            Some((&compression_scheme, data)) => {
+                Ok(RawChunk::new(compression_scheme, data.to_vec()))
+            }
+            None => Err(encryption_error()),
+        }
+    }
+}
+
+impl<T, P> ChunkReader<P> for EncryptedChunkProvider<T, P>
+where
+    T: ChunkReader<P> + ChunkWriter<P>,
+    P: ChunkPayload,
+{
+    fn get_region(&mut self, region_x: i32, region_z: i32) -> Result<RegionReader, ChunkLoadError> {
+        self.provider.get_region(region_x, region_z)
+    }
+    fn load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        self.load_chunk_raw(chunk_x, chunk_z)?.decode_payload()
+    }
+    fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.provider.list_chunks()
+    }
+    fn list_regions(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.provider.list_regions()
+    }
+    fn list_chunks_in(
+        &mut self,
+        selection: &ChunkSelection,
+    ) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.provider.list_chunks_in(selection)
+    }
+    fn load_region_header(
+        &mut self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<AnvilRegionHeader, ChunkLoadError> {
+        self.provider.load_region_header(region_x, region_z)
+    }
+    fn load_chunk_raw(&mut self, chunk_x: i32, chunk_z: i32) -> Result<RawChunk, ChunkLoadError> {
+        let raw_chunk = self.provider.load_chunk_raw(chunk_x, chunk_z)?;
+
+        self.decrypt(chunk_x, chunk_z, &raw_chunk)
+    }
+}
+
+impl<T, P> ChunkWriter<P> for EncryptedChunkProvider<T, P>
+where
+    T: ChunkReader<P> + ChunkWriter<P>,
+    P: ChunkPayload,
+{
+    fn save_chunk_with_timestamp(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        let raw_chunk = RawChunk::encode(
+            &chunk_compound_tag,
+            self.compression,
+            DEFAULT_COMPRESSION_LEVEL,
+        )?;
+
+        match self.save_chunk_raw(chunk_x, chunk_z, &raw_chunk, last_modified_timestamp) {
+            Ok(()) => Ok(()),
+            Err(WorldEditError::Save(e)) => Err(e),
+            Err(WorldEditError::Load(e)) => {
+                let io_error = io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e));
+                Err(ChunkSaveError::WriteError { io_error })
+            }
+        }
+    }
+    fn save_chunk_raw(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), WorldEditError> {
+        let encrypted_chunk = self.encrypt(chunk_x, chunk_z, raw_chunk);
+
+        self.provider
+            .save_chunk_raw(chunk_x, chunk_z, &encrypted_chunk, last_modified_timestamp)
+    }
+}
+
+impl<T, P> ChunkDeleter<P> for EncryptedChunkProvider<T, P>
+where
+    T: ChunkReader<P> + ChunkDeleter<P>,
+    P: ChunkPayload,
+{
+    fn delete_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError> {
+        self.provider.delete_chunk(chunk_x, chunk_z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FolderChunkProvider;
+    use std::fs;
+
+    #[test]
+    fn test_encrypted_chunks() {
+        let folder = tempfile::tempdir().unwrap();
+        let key = [42; 32];
+        let mut chunk_provider =
+            EncryptedChunkProvider::new(FolderChunkProvider::new(folder.path()), &key)
+                .with_compression(Compression::Uncompressed);
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_str("secret", "diamonds at 0 12 0");
+        chunk_provider
+            .save_chunk_with_timestamp(1, 2, chunk_compound_tag, 1234)
+            .unwrap();
+
+        let chunk_compound_tag = chunk_provider.load_chunk(1, 2).unwrap();
+        assert_eq!(
+            chunk_compound_tag.get_str("secret").unwrap(),
+            "diamonds at 0 12 0"
+        );
+        // Headers are readable.
+        assert_eq!(chunk_provider.list_chunks().unwrap(), vec![(1, 2)]);
+        assert_eq!(
+            chunk_provider
+                .load_chunk_metadata(1, 2)
+                .unwrap()
+                .last_modified_timestamp(),
+            1234
+        );
+        let raw_chunk = chunk_provider.provider().load_chunk_raw(1, 2).unwrap();
+        assert_eq!(raw_chunk.compression_scheme(), ENCRYPTED_COMPRESSION_SCHEME);
+
+        // A chunk moved to other coordinates fails to load.
+        chunk_provider
+            .provider()
+            .save_chunk_raw(3, 2, &raw_chunk, 1234)
+            .unwrap();
+        assert!(chunk_provider.load_chunk(3, 2).is_err());
+
+        let mut wrong_key_provider =
+            EncryptedChunkProvider::new(FolderChunkProvider::new(folder.path()), &[0; 32]);
+        assert!(wrong_key_provider.load_chunk(1, 2).is_err());
+
+        // The content is not stored in clear.
+        let region_bytes = fs::read(folder.path().join("r.0.0.mca")).unwrap();
+        assert!(!region_bytes.windows(8).any(|window| window == b"diamonds"));
+    }
+}
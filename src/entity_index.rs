@@ -0,0 +1,275 @@
+use crate::{chunk_level, ChunkLoadError, ChunkReader, ChunkSelection};
+use nbt::decode::{read_gzip_compound_tag, TagDecodeError};
+use nbt::encode::write_gzip_compound_tag;
+use nbt::{CompoundTag, Tag};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::io::{Read, Write};
+
+/// Where an entity was found by `EntityIndex::scan`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EntityLocation {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    /// Value of the `Pos` tag of the entity, if valid.
+    pub position: Option<[f64; 3]>,
+}
+
+/// Locations of the entities of a world by entity id, such as
+/// `minecraft:cow`.
+///
+/// Entities are read from the `Entities` list of each chunk, at the root of
+/// the chunks in the `entities` folder of 1.17+ worlds or in the `Level`
+/// compound of older region chunks, so both folders can be scanned into the
+/// same index. Passengers are indexed too.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{ChunkSelection, EntityIndex, FolderChunkProvider};
+///
+/// let mut index = EntityIndex::new();
+/// let mut chunk_provider = FolderChunkProvider::new("test/region");
+/// index.scan(&mut chunk_provider, &ChunkSelection::All).unwrap();
+///
+/// // Chunks with the most entities first.
+/// for ((chunk_x, chunk_z), entities) in index.crowded_chunks(100) {
+///     println!("{} entities in chunk {} {}", entities, chunk_x, chunk_z);
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EntityIndex {
+    entities: BTreeMap<String, Vec<EntityLocation>>,
+}
+
+impl EntityIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the entities of every selected chunk to the index.
+    pub fn scan<P: ChunkReader + ?Sized>(
+        &mut self,
+        provider: &mut P,
+        selection: &ChunkSelection,
+    ) -> Result<(), ChunkLoadError> {
+        for (chunk_x, chunk_z) in provider.list_chunks_in(selection)? {
+            let chunk_compound_tag = provider.load_chunk(chunk_x, chunk_z)?;
+            self.add_chunk(chunk_x, chunk_z, &chunk_compound_tag);
+        }
+
+        Ok(())
+    }
+
+    /// Adds the entities of the chunk to the index.
+    pub fn add_chunk(&mut self, chunk_x: i32, chunk_z: i32, chunk_compound_tag: &CompoundTag) {
+        let level_compound_tag = chunk_level(chunk_compound_tag);
+
+        if let Ok(entities) = level_compound_tag.get::<&Vec<Tag>>("Entities") {
+            self.add_entities(chunk_x, chunk_z, entities);
+        }
+    }
+
+    fn add_entities(&mut self, chunk_x: i32, chunk_z: i32, entities: &[Tag]) {
+        for entity in entities {
+            let entity_compound_tag = match entity {
+                Tag::Compound(entity_compound_tag) => entity_compound_tag,
+                _ => continue,
+            };
+
+            if let Ok(id) = entity_compound_tag.get_str("id") {
+                self.entities
+                    .entry(id.to_string())
+                    .or_default()
+                    .push(EntityLocation {
+                        chunk_x,
+                        chunk_z,
+                        position: entity_position(entity_compound_tag),
+                    });
+            }
+
+            if let Ok(passengers) = entity_compound_tag.get::<&Vec<Tag>>("Passengers") {
+                self.add_entities(chunk_x, chunk_z, passengers);
+            }
+        }
+    }
+
+    /// Total amount of indexed entities.
+    pub fn len(&self) -> usize {
+        self.entities.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Indexed entity ids, sorted.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.entities.keys().map(String::as_str)
+    }
+
+    /// Locations of the entities with the given id, in scan order.
+    pub fn locations(&self, id: &str) -> &[EntityLocation] {
+        self.entities.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Chunks with at least `min_entities` entities of any id, with the
+    /// amount of entities, most crowded first.
+    pub fn crowded_chunks(&self, min_entities: usize) -> Vec<((i32, i32), usize)> {
+        let mut chunks: HashMap<(i32, i32), usize> = HashMap::new();
+
+        for location in self.entities.values().flatten() {
+            *chunks
+                .entry((location.chunk_x, location.chunk_z))
+                .or_default() += 1;
+        }
+
+        let mut chunks: Vec<_> = chunks
+            .into_iter()
+            .filter(|&(_, entities)| entities >= min_entities)
+            .collect();
+        chunks.sort_unstable_by(|(a_chunk, a), (b_chunk, b)| b.cmp(a).then(a_chunk.cmp(b_chunk)));
+
+        chunks
+    }
+
+    /// Writes the index as gzip compressed NBT.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        let mut entities = Vec::with_capacity(self.len());
+
+        for (id, locations) in &self.entities {
+            for location in locations {
+                let mut entity = CompoundTag::new();
+                entity.insert_str("id", id);
+                entity.insert_i32("x", location.chunk_x);
+                entity.insert_i32("z", location.chunk_z);
+
+                if let Some(position) = location.position {
+                    let pos = position.iter().map(|&value| Tag::Double(value)).collect();
+                    entity.insert("Pos", Tag::List(pos));
+                }
+
+                entities.push(entity);
+            }
+        }
+
+        let mut index_compound_tag = CompoundTag::new();
+        index_compound_tag.insert_compound_tag_vec("Entities", entities);
+
+        write_gzip_compound_tag(writer, &index_compound_tag)
+    }
+
+    /// Reads an index written by `write`.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, TagDecodeError> {
+        let index_compound_tag = read_gzip_compound_tag(reader)?;
+        let mut index = EntityIndex::new();
+
+        if let Ok(entities) = index_compound_tag.get::<&Vec<Tag>>("Entities") {
+            for entity in entities {
+                let entity_compound_tag = match entity {
+                    Tag::Compound(entity_compound_tag) => entity_compound_tag,
+                    _ => continue,
+                };
+
+                if let Ok(id) = entity_compound_tag.get_str("id") {
+                    index
+                        .entities
+                        .entry(id.to_string())
+                        .or_default()
+                        .push(EntityLocation {
+                            chunk_x: entity_compound_tag.get_i32("x").unwrap_or_default(),
+                            chunk_z: entity_compound_tag.get_i32("z").unwrap_or_default(),
+                            position: entity_position(entity_compound_tag),
+                        });
+                }
+            }
+        }
+
+        Ok(index)
+    }
+}
+
+fn entity_position(entity_compound_tag: &CompoundTag) -> Option<[f64; 3]> {
+    match entity_compound_tag.get::<&Vec<Tag>>("Pos").ok()?.as_slice() {
+        [Tag::Double(x), Tag::Double(y), Tag::Double(z)] => Some([*x, *y, *z]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FolderChunkProvider;
+
+    fn entity(id: &str, x: f64, passengers: Vec<CompoundTag>) -> CompoundTag {
+        let mut entity = CompoundTag::new();
+        entity.insert_str("id", id);
+        entity.insert(
+            "Pos",
+            Tag::List(vec![Tag::Double(x), Tag::Double(64.0), Tag::Double(0.5)]),
+        );
+
+        if !passengers.is_empty() {
+            entity.insert_compound_tag_vec("Passengers", passengers);
+        }
+
+        entity
+    }
+
+    #[test]
+    fn test_entity_index_scan_and_roundtrip() {
+        let folder = tempfile::tempdir().unwrap();
+        let mut chunk_provider = FolderChunkProvider::new(folder.path());
+
+        // 1.17+ entity chunk.
+        let mut entity_chunk = CompoundTag::new();
+        entity_chunk.insert_i32_vec("Position", vec![0, 0]);
+        entity_chunk.insert_compound_tag_vec(
+            "Entities",
+            vec![
+                entity("minecraft:cow", 1.0, vec![]),
+                entity("minecraft:cow", 2.0, vec![]),
+                entity(
+                    "minecraft:pig",
+                    3.0,
+                    vec![entity("minecraft:zombie", 3.0, vec![])],
+                ),
+            ],
+        );
+        chunk_provider.save_chunk(0, 0, entity_chunk).unwrap();
+
+        // Legacy region chunk.
+        let mut level = CompoundTag::new();
+        level.insert_compound_tag_vec("Entities", vec![entity("minecraft:cow", 20.0, vec![])]);
+        let mut legacy_chunk = CompoundTag::new();
+        legacy_chunk.insert_compound_tag("Level", level);
+        chunk_provider.save_chunk(1, 0, legacy_chunk).unwrap();
+
+        let mut index = EntityIndex::new();
+        index
+            .scan(&mut chunk_provider, &ChunkSelection::All)
+            .unwrap();
+
+        assert_eq!(index.len(), 5);
+        assert_eq!(
+            index.ids().collect::<Vec<_>>(),
+            vec!["minecraft:cow", "minecraft:pig", "minecraft:zombie"]
+        );
+        assert_eq!(index.locations("minecraft:cow").len(), 3);
+        assert_eq!(
+            index.locations("minecraft:zombie"),
+            &[EntityLocation {
+                chunk_x: 0,
+                chunk_z: 0,
+                position: Some([3.0, 64.0, 0.5]),
+            }]
+        );
+        assert!(index.locations("minecraft:creeper").is_empty());
+        assert_eq!(index.crowded_chunks(1), vec![((0, 0), 4), ((1, 0), 1)]);
+        assert_eq!(index.crowded_chunks(2), vec![((0, 0), 4)]);
+
+        let mut bytes = Vec::new();
+        index.write(&mut bytes).unwrap();
+        assert_eq!(EntityIndex::read(&mut bytes.as_slice()).unwrap(), index);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{ChunkLoadError, ChunkSaveError};
+use std::path::{Path, PathBuf};
+
+/// File, chunk and operation of an error returned by a
+/// `FolderChunkProvider`, so that errors of a scan over a whole world can be
+/// traced back to their region file.
+///
+/// With the `context` feature, errors which don't identify their chunk
+/// (I/O errors and corrupted chunk data) are wrapped in the `Context`
+/// variant of `ChunkLoadError` and `ChunkSaveError`. Errors naming their
+/// chunk, such as `RegionNotFound`, `ChunkNotFound`, `CoordinateMismatch` or
+/// `ReadOnly`, are returned as they are.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::FolderChunkProvider;
+/// use std::io::{Seek, SeekFrom, Write};
+///
+/// # let folder = tempfile::tempdir().unwrap();
+/// # let folder = folder.path();
+/// let chunk_provider = FolderChunkProvider::new(folder);
+/// chunk_provider.save_chunk(33, 1, nbt::CompoundTag::new()).unwrap();
+///
+/// // Corrupt the compression scheme of the chunk.
+/// let mut region_file = std::fs::OpenOptions::new()
+///     .write(true)
+///     .open(folder.join("r.1.0.mca"))
+///     .unwrap();
+/// region_file.seek(SeekFrom::Start(2 * 4096 + 4)).unwrap();
+/// region_file.write_all(&[42]).unwrap();
+///
+/// let error = chunk_provider.load_chunk(33, 1).unwrap_err();
+///
+/// if let Some(context) = error.context() {
+///     assert_eq!(context.operation, "load_chunk");
+///     assert_eq!(context.path, folder.join("r.1.0.mca"));
+///     assert_eq!(context.chunk, Some((33, 1)));
+/// }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ErrorContext {
+    /// Failed provider method, such as `load_chunk` or `list_chunks`.
+    pub operation: &'static str,
+    /// Region file, or region folder when listing the regions.
+    pub path: PathBuf,
+    /// Loaded, saved or deleted chunk, `None` for whole region operations.
+    pub chunk: Option<(i32, i32)>,
+}
+
+impl ErrorContext {
+    pub(crate) fn new(operation: &'static str, path: &Path, chunk: Option<(i32, i32)>) -> Self {
+        ErrorContext {
+            operation,
+            path: path.to_path_buf(),
+            chunk,
+        }
+    }
+}
+
+impl ChunkLoadError {
+    /// The error without its context.
+    pub fn root(&self) -> &ChunkLoadError {
+        match self {
+            ChunkLoadError::Context { error, .. } => error.root(),
+            error => error,
+        }
+    }
+
+    /// Where the error happened, if known.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            ChunkLoadError::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Wraps the error in the given context if the `context` feature is
+    /// enabled and the error doesn't identify its chunk.
+    #[cfg_attr(not(feature = "context"), allow(unused_variables))]
+    pub(crate) fn with_context(self, context: impl FnOnce() -> ErrorContext) -> Self {
+        match self {
+            #[cfg(feature = "context")]
+            ChunkLoadError::LengthExceedsMaximum { .. }
+            | ChunkLoadError::EmptyChunkData
+            | ChunkLoadError::UnsupportedCompressionScheme { .. }
+            | ChunkLoadError::ReadError { .. }
+            | ChunkLoadError::TagDecodeError { .. } => ChunkLoadError::Context {
+                context: Box::new(context()),
+                error: Box::new(self),
+            },
+            error => error,
+        }
+    }
+}
+
+impl ChunkSaveError {
+    /// The error without its context.
+    pub fn root(&self) -> &ChunkSaveError {
+        match self {
+            ChunkSaveError::Context { error, .. } => error.root(),
+            error => error,
+        }
+    }
+
+    /// Where the error happened, if known.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            ChunkSaveError::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Wraps the error in the given context if the `context` feature is
+    /// enabled and the error doesn't identify its chunk.
+    #[cfg_attr(not(feature = "context"), allow(unused_variables))]
+    pub(crate) fn with_context(self, context: impl FnOnce() -> ErrorContext) -> Self {
+        match self {
+            #[cfg(feature = "context")]
+            ChunkSaveError::LengthExceedsMaximum { .. } | ChunkSaveError::WriteError { .. } => {
+                ChunkSaveError::Context {
+                    context: Box::new(context()),
+                    error: Box::new(self),
+                }
+            }
+            error => error,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "context"))]
+mod tests {
+    use super::*;
+    use crate::{AnvilOptions, Compression, FolderChunkProvider};
+    use nbt::CompoundTag;
+    use std::fs;
+
+    #[test]
+    fn test_errors_with_context() {
+        let folder = tempfile::tempdir().unwrap();
+        let options = AnvilOptions::new().compression(Compression::Uncompressed);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        let region_path = folder.path().join("r.-1.0.mca");
+        chunk_provider
+            .save_chunk(-1, 2, CompoundTag::new())
+            .unwrap();
+
+        // Corrupt the length of the chunk.
+        let mut region_bytes = fs::read(&region_path).unwrap();
+        region_bytes[2 * 4096..2 * 4096 + 4].copy_from_slice(&[0, 0, 0, 0]);
+        fs::write(&region_path, region_bytes).unwrap();
+
+        let error = chunk_provider.load_chunk(-1, 2).unwrap_err();
+        assert_eq!(
+            error.context(),
+            Some(&ErrorContext::new(
+                "load_chunk",
+                &region_path,
+                Some((-1, 2))
+            ))
+        );
+        assert!(matches!(error.root(), ChunkLoadError::EmptyChunkData));
+
+        // Fits in 256 sectors, which the header can't describe.
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i8_vec("data", vec![0; 255 * 4096]);
+        let error = chunk_provider
+            .save_chunk(-1, 3, chunk_compound_tag)
+            .unwrap_err();
+        assert_eq!(error.context().unwrap().operation, "save_chunk");
+        assert_eq!(error.context().unwrap().chunk, Some((-1, 3)));
+
+        // A region file too short for its header.
+        let short_region_path = folder.path().join("r.0.0.mca");
+        fs::write(&short_region_path, [0; 100]).unwrap();
+        let error = chunk_provider.list_chunks().unwrap_err();
+        assert_eq!(
+            error.context(),
+            Some(&ErrorContext::new("list_chunks", &short_region_path, None))
+        );
+
+        // Errors naming their chunk are not wrapped.
+        assert!(matches!(
+            chunk_provider.load_chunk(0, 40),
+            Err(ChunkLoadError::RegionNotFound {
+                region_x: 0,
+                region_z: 1
+            })
+        ));
+    }
+}
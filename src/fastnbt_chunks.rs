@@ -0,0 +1,147 @@
+use crate::{decode_counted, ChunkLoadError, ChunkPayload, ChunkSaveError, FolderChunkProvider};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io;
+use std::io::{Read, Write};
+
+/// Uncompressed NBT data of a chunk, read and written as is.
+struct NbtBytes(Vec<u8>);
+
+impl ChunkPayload for NbtBytes {
+    fn read_payload<R: Read>(reader: &mut R) -> Result<Self, ChunkLoadError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        Ok(NbtBytes(bytes))
+    }
+
+    fn write_payload<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.0)
+    }
+}
+
+impl<P: ChunkPayload> FolderChunkProvider<P> {
+    /// Loads the chunk at the specified coordinates and deserializes it with
+    /// `fastnbt`, without decoding it as the payload of the provider.
+    ///
+    /// Strict loading doesn't apply, as the coordinates of the chunk are
+    /// only known to the deserialized type.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::FolderChunkProvider;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Chunk {
+    ///     #[serde(rename = "Level")]
+    ///     level: Level,
+    /// }
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Level {
+    ///     #[serde(rename = "xPos")]
+    ///     x_pos: i32,
+    ///     #[serde(rename = "zPos")]
+    ///     z_pos: i32,
+    /// }
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("test/region");
+    /// let chunk: Chunk = chunk_provider.load_chunk_fastnbt(4, 2).unwrap();
+    ///
+    /// assert_eq!((chunk.level.x_pos, chunk.level.z_pos), (4, 2));
+    /// ```
+    pub fn load_chunk_fastnbt<T: DeserializeOwned>(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+    ) -> Result<T, ChunkLoadError> {
+        let raw_chunk = self.load_chunk_raw(chunk_x, chunk_z)?;
+        let (NbtBytes(bytes), decompressed) =
+            decode_counted(raw_chunk.compression_scheme(), raw_chunk.data())?;
+        self.telemetry.decompressed(decompressed);
+
+        fastnbt::from_bytes(&bytes).map_err(|e| ChunkLoadError::ReadError {
+            io_error: io::Error::new(io::ErrorKind::InvalidData, e),
+        })
+    }
+
+    /// Serializes the chunk with `fastnbt` and saves it to the specified
+    /// coordinates, without encoding it as the payload of the provider.
+    ///
+    /// Coordinate checks don't apply, as the coordinates of the chunk are
+    /// only known to the serialized type.
+    pub fn save_chunk_fastnbt<T: Serialize>(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk: &T,
+    ) -> Result<(), ChunkSaveError> {
+        let bytes = fastnbt::to_bytes(chunk).map_err(|e| ChunkSaveError::WriteError {
+            io_error: io::Error::new(io::ErrorKind::InvalidData, e),
+        })?;
+
+        self.save_chunk_inner(
+            chunk_x,
+            chunk_z,
+            NbtBytes(bytes),
+            None,
+            self.options.compression,
+        )
+        .map_err(|e| e.with_context(|| self.chunk_context("save_chunk_fastnbt", chunk_x, chunk_z)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nbt::CompoundTag;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Chunk {
+        #[serde(rename = "xPos")]
+        x_pos: i32,
+        #[serde(rename = "zPos")]
+        z_pos: i32,
+        #[serde(rename = "Status")]
+        status: String,
+    }
+
+    #[test]
+    fn test_folder_provider_fastnbt_round_trip() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider = FolderChunkProvider::new(folder.path().to_str().unwrap());
+        let chunk = Chunk {
+            x_pos: 1,
+            z_pos: 2,
+            status: "full".to_owned(),
+        };
+
+        chunk_provider.save_chunk_fastnbt(1, 2, &chunk).unwrap();
+        assert_eq!(
+            chunk_provider.load_chunk_fastnbt::<Chunk>(1, 2).unwrap(),
+            chunk
+        );
+
+        // The chunk is plain NBT for every other payload.
+        let chunk_compound_tag: CompoundTag = chunk_provider.load_chunk(1, 2).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("xPos").unwrap(), 1);
+        assert_eq!(chunk_compound_tag.get_str("Status").unwrap(), "full");
+    }
+
+    #[test]
+    fn test_folder_provider_fastnbt_load_errors() {
+        let chunk_provider = FolderChunkProvider::new("test/region");
+
+        assert!(matches!(
+            chunk_provider.load_chunk_fastnbt::<Chunk>(4, 2),
+            Err(ChunkLoadError::ReadError { .. })
+        ));
+        assert!(matches!(
+            chunk_provider.load_chunk_fastnbt::<Chunk>(15, 14),
+            Err(ChunkLoadError::ChunkNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{
+    chunk_coords_to_region_coords, discard_prepared, replace_regions, ChunkLoadError,
+    ChunkSaveError, FolderChunkProvider, PreparedRegions, WorldFolderProvider,
+};
+use nbt::CompoundTag;
+
+/// Every part of a chunk stored by 1.17+ worlds in different folders,
+/// returned by `WorldFolderProvider::load_full_chunk`.
+#[derive(Clone, Debug)]
+pub struct FullChunk {
+    /// Chunk of the `region` folder, with the blocks.
+    pub chunk: CompoundTag,
+    /// Chunk of the `entities` folder, `None` if the chunk has none.
+    pub entities: Option<CompoundTag>,
+    /// Chunk of the `poi` folder, `None` if the chunk has none.
+    pub poi: Option<CompoundTag>,
+}
+
+impl FullChunk {
+    /// Chunk without entities nor points of interest.
+    pub fn new(chunk: CompoundTag) -> Self {
+        FullChunk {
+            chunk,
+            entities: None,
+            poi: None,
+        }
+    }
+}
+
+impl WorldFolderProvider {
+    /// Loads the chunk with its entities and points of interest. Missing
+    /// `entities` or `poi` folders, as in worlds older than 1.17, are
+    /// treated as empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::WorldFolderProvider;
+    /// use nbt::CompoundTag;
+    ///
+    /// # let world = tempfile::tempdir().unwrap();
+    /// # let world = world.path();
+    /// # let region_provider = WorldFolderProvider::new(world).region_provider();
+    /// # region_provider.save_chunk(4, 2, CompoundTag::new()).unwrap();
+    /// let world_provider = WorldFolderProvider::new(world);
+    ///
+    /// // Move the chunk along with its entities.
+    /// let full_chunk = world_provider.load_full_chunk(4, 2).unwrap();
+    /// world_provider.save_full_chunk(5, 2, full_chunk).unwrap();
+    /// world_provider.delete_full_chunk(4, 2).unwrap();
+    /// ```
+    pub fn load_full_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<FullChunk, ChunkLoadError> {
+        Ok(FullChunk {
+            chunk: self.region_provider().load_chunk(chunk_x, chunk_z)?,
+            entities: self.entities_provider().try_load_chunk(chunk_x, chunk_z)?,
+            poi: self.poi_provider().try_load_chunk(chunk_x, chunk_z)?,
+        })
+    }
+
+    /// Saves every part of the chunk to its folder. Missing entities or
+    /// points of interest delete the existing ones, so they don't end up
+    /// attached to another chunk.
+    ///
+    /// The region files of the three folders are modified all at once like
+    /// in `ChunkTransaction::commit`: if anything fails before they are
+    /// replaced, none is modified.
+    pub fn save_full_chunk(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        full_chunk: FullChunk,
+    ) -> Result<(), ChunkSaveError> {
+        let parts = vec![
+            (self.region_provider(), Some(full_chunk.chunk)),
+            (self.entities_provider(), full_chunk.entities),
+            (self.poi_provider(), full_chunk.poi),
+        ];
+
+        self.write_parts(chunk_x, chunk_z, parts)
+    }
+
+    /// Deletes every part of the chunk, all at once like `save_full_chunk`.
+    pub fn delete_full_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError> {
+        let parts = vec![
+            (self.region_provider(), None),
+            (self.entities_provider(), None),
+            (self.poi_provider(), None),
+        ];
+
+        self.write_parts(chunk_x, chunk_z, parts)
+    }
+
+    fn write_parts(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        parts: Vec<(FolderChunkProvider, Option<CompoundTag>)>,
+    ) -> Result<(), ChunkSaveError> {
+        let (region_x, region_z) = chunk_coords_to_region_coords(chunk_x, chunk_z);
+        let mut prepared = PreparedRegions::default();
+
+        for (provider, part) in &parts {
+            let mut transaction = provider.transaction();
+
+            match part {
+                Some(part) => transaction.save_chunk(chunk_x, chunk_z, part.clone()),
+                // Deleting from a missing region would create it.
+                None if provider.region_path(region_x, region_z).exists() => {
+                    transaction.delete_chunk(chunk_x, chunk_z)
+                }
+                None => continue,
+            }
+
+            match transaction.prepare() {
+                Ok(region_files) => prepared.extend(region_files),
+                Err(e) => {
+                    discard_prepared(prepared);
+
+                    return Err(e);
+                }
+            }
+        }
+
+        replace_regions(prepared)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tagged(name: &str) -> CompoundTag {
+        let mut compound_tag = CompoundTag::new();
+        compound_tag.insert_str("Part", name);
+
+        compound_tag
+    }
+
+    #[test]
+    fn test_move_full_chunk() {
+        let world = tempfile::tempdir().unwrap();
+        let world_provider = WorldFolderProvider::new(world.path());
+        let full_chunk = FullChunk {
+            chunk: tagged("chunk"),
+            entities: Some(tagged("entities")),
+            poi: None,
+        };
+        world_provider.save_full_chunk(0, 0, full_chunk).unwrap();
+        assert!(!world.path().join("poi").exists());
+
+        let full_chunk = world_provider.load_full_chunk(0, 0).unwrap();
+        assert_eq!(full_chunk.chunk.get_str("Part").unwrap(), "chunk");
+        let entities = full_chunk.entities.as_ref().unwrap();
+        assert_eq!(entities.get_str("Part").unwrap(), "entities");
+        assert!(full_chunk.poi.is_none());
+
+        world_provider.save_full_chunk(40, 0, full_chunk).unwrap();
+        world_provider.delete_full_chunk(0, 0).unwrap();
+
+        let entities_provider = world_provider.entities_provider();
+        assert!(entities_provider.try_load_chunk(0, 0).unwrap().is_none());
+        assert!(entities_provider.try_load_chunk(40, 0).unwrap().is_some());
+        assert!(world_provider.load_full_chunk(0, 0).is_err());
+
+        // The entities of the replaced chunk are deleted.
+        world_provider
+            .save_full_chunk(40, 0, FullChunk::new(tagged("chunk")))
+            .unwrap();
+        assert!(entities_provider.try_load_chunk(40, 0).unwrap().is_none());
+    }
+}
@@ -0,0 +1,145 @@
+use crate::chunk_surface::surface_heights;
+use crate::png::{write_png, GRAYSCALE_ALPHA};
+use crate::{ChunkReader, ChunkSelection, WorldEditError};
+use std::io::Write;
+
+/// Heights found by `export_heightmap_png`, mapped to the darkest and the
+/// brightest gray of the image.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct HeightRange {
+    /// Lowest surface height, drawn black.
+    pub min: i32,
+    /// Highest surface height, drawn white.
+    pub max: i32,
+}
+
+/// Writes a grayscale PNG image of the `WORLD_SURFACE` heightmaps of the
+/// selected chunks, one pixel per block column.
+///
+/// The image covers the bounds of the selection, or the bounds of the
+/// selected chunks for unbounded selections, with the north-west corner at
+/// the top left. Heights are scaled linearly from black to white across the
+/// returned range. Missing chunks and chunks without heightmap are left
+/// transparent. Returns `None` if no column was drawn.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{export_heightmap_png, ChunkSelection, FolderChunkProvider};
+///
+/// let mut chunk_provider = FolderChunkProvider::new("test/region");
+/// let mut png = Vec::new();
+/// let range = export_heightmap_png(&mut chunk_provider, &ChunkSelection::All, &mut png)
+///     .unwrap()
+///     .unwrap();
+///
+/// assert!(range.min <= range.max);
+/// assert!(png.starts_with(b"\x89PNG"));
+/// ```
+pub fn export_heightmap_png<P, W>(
+    provider: &mut P,
+    selection: &ChunkSelection,
+    mut writer: W,
+) -> Result<Option<HeightRange>, WorldEditError>
+where
+    P: ChunkReader + ?Sized,
+    W: Write,
+{
+    let chunks = provider.list_chunks_in(selection)?;
+    let ((min_x, min_z), (max_x, max_z)) = selection
+        .bounds_or_chunk_bounds(&chunks)
+        .unwrap_or(((0, 0), (-1, -1)));
+
+    let width = (max_x - min_x + 1) as usize * 16;
+    let height = (max_z - min_z + 1) as usize * 16;
+    let mut heights: Vec<Option<i32>> = vec![None; width * height];
+
+    for (chunk_x, chunk_z) in chunks {
+        let chunk_compound_tag = provider.load_chunk(chunk_x, chunk_z)?;
+
+        let chunk_heights = match surface_heights(&chunk_compound_tag) {
+            Some(chunk_heights) => chunk_heights,
+            None => continue,
+        };
+
+        let image_x = (chunk_x - min_x) as usize * 16;
+        let image_z = (chunk_z - min_z) as usize * 16;
+
+        for (index, &column_height) in chunk_heights.iter().enumerate() {
+            let x = image_x + index % 16;
+            let z = image_z + index / 16;
+            heights[z * width + x] = Some(column_height);
+        }
+    }
+
+    let range = heights
+        .iter()
+        .flatten()
+        .fold(None, |range, &column_height| {
+            Some(match range {
+                None => HeightRange {
+                    min: column_height,
+                    max: column_height,
+                },
+                Some(HeightRange { min, max }) => HeightRange {
+                    min: min.min(column_height),
+                    max: max.max(column_height),
+                },
+            })
+        });
+
+    let mut pixels = Vec::with_capacity(heights.len() * 2);
+
+    for column_height in heights {
+        match (column_height, range) {
+            (Some(column_height), Some(HeightRange { min, max })) => {
+                let gray = (column_height - min) as i64 * 255 / (max - min).max(1) as i64;
+                pixels.extend_from_slice(&[gray as u8, 255]);
+            }
+            _ => pixels.extend_from_slice(&[0, 0]),
+        }
+    }
+
+    write_png(
+        &mut writer,
+        width as u32,
+        height as u32,
+        GRAYSCALE_ALPHA,
+        2,
+        &pixels,
+    )?;
+
+    Ok(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FolderChunkProvider;
+
+    #[test]
+    fn test_export_heightmap_png() {
+        let mut chunk_provider = FolderChunkProvider::new("test/region");
+        let selection = ChunkSelection::rect((4, 2), (5, 2));
+        let mut png = Vec::new();
+
+        let range = export_heightmap_png(&mut chunk_provider, &selection, &mut png)
+            .unwrap()
+            .unwrap();
+        assert!(range.min > 0 && range.max < 256);
+
+        // Width and height from the IHDR chunk.
+        assert_eq!(&png[16..24], &[0, 0, 0, 32, 0, 0, 0, 16]);
+    }
+
+    #[test]
+    fn test_export_heightmap_png_empty_selection() {
+        let mut chunk_provider = FolderChunkProvider::new("test/region");
+        let selection = ChunkSelection::predicate(|_, _| false);
+        let mut png = Vec::new();
+
+        let range = export_heightmap_png(&mut chunk_provider, &selection, &mut png).unwrap();
+        assert_eq!(range, None);
+        assert_eq!(&png[16..24], &[0; 8]);
+    }
+}
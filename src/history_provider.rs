@@ -0,0 +1,451 @@
+use crate::{
+    chunk_coords_inside_region, chunk_coords_to_region_coords, current_timestamp,
+    missing_chunk_as_none, ChunkDeleter, ChunkLoadError, ChunkPayload, ChunkReader, ChunkSaveError,
+    ChunkWriter, FolderChunkProvider, RawChunk, RegionReader, WorldEditError,
+    CHUNK_MAXIMUM_BYTES_LENGTH,
+};
+use byteorder::{BigEndian, ReadBytesExt};
+use flate2::Crc;
+use nbt::CompoundTag;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::{fs, io};
+
+/// History entry type of a chunk which was stored.
+const STORED_ENTRY: u8 = 1;
+/// History entry type of a chunk which did not exist.
+const ABSENT_ENTRY: u8 = 2;
+
+/// Archived version with the coordinates of its chunk inside the region.
+type HistoryEntry = ((u8, u8), ChunkVersion);
+
+/// Version of a chunk archived by `HistoryChunkProvider`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChunkVersion {
+    /// Time the version was replaced, in seconds since the Unix epoch.
+    pub replaced_at: u32,
+    /// Compressed chunk data, `None` if the chunk did not exist.
+    pub raw_chunk: Option<RawChunk>,
+}
+
+impl ChunkVersion {
+    /// History entry of the version, followed by the CRC32 of its bytes so an
+    /// entry cut by a crash is recognized and ignored:
+    ///
+    /// - the entry type, 1 for stored chunks and 2 for absent chunks.
+    /// - the chunk coordinates inside the region, one byte each.
+    /// - the replacement time.
+    /// - for stored chunks, the compression scheme, the data length and the
+    ///   compressed data.
+    fn to_bytes(&self, chunk_x: u8, chunk_z: u8) -> Vec<u8> {
+        let entry_type = match self.raw_chunk {
+            Some(_) => STORED_ENTRY,
+            None => ABSENT_ENTRY,
+        };
+        let mut bytes = vec![entry_type, chunk_x, chunk_z];
+        bytes.extend_from_slice(&self.replaced_at.to_be_bytes());
+
+        if let Some(raw_chunk) = &self.raw_chunk {
+            bytes.push(raw_chunk.compression_scheme());
+            bytes.extend_from_slice(&(raw_chunk.data().len() as u32).to_be_bytes());
+            bytes.extend_from_slice(raw_chunk.data());
+        }
+
+        let mut crc = Crc::new();
+        crc.update(&bytes);
+        bytes.extend_from_slice(&crc.sum().to_be_bytes());
+
+        bytes
+    }
+
+    /// Reads the next entry with its chunk coordinates, or `None` at the end
+    /// of the history or at an incomplete or corrupted entry.
+    fn read<R: Read>(reader: &mut R) -> Result<Option<HistoryEntry>, io::Error> {
+        match Self::read_checked(reader) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            result => result,
+        }
+    }
+
+    fn read_checked<R: Read>(reader: &mut R) -> Result<Option<HistoryEntry>, io::Error> {
+        let mut head = [0; 7];
+        reader.read_exact(&mut head)?;
+
+        let [entry_type, chunk_x, chunk_z, ..] = head;
+        let replaced_at = (&head[3..]).read_u32::<BigEndian>()?;
+        let mut bytes = head.to_vec();
+
+        let raw_chunk = match entry_type {
+            STORED_ENTRY => {
+                let compression_scheme = reader.read_u8()?;
+                let length = reader.read_u32::<BigEndian>()?;
+
+                if length > CHUNK_MAXIMUM_BYTES_LENGTH {
+                    return Ok(None);
+                }
+
+                let mut data = vec![0; length as usize];
+                reader.read_exact(&mut data)?;
+
+                bytes.push(compression_scheme);
+                bytes.extend_from_slice(&length.to_be_bytes());
+                bytes.extend_from_slice(&data);
+
+                Some(RawChunk::new(compression_scheme, data))
+            }
+            ABSENT_ENTRY => None,
+            _ => return Ok(None),
+        };
+
+        let mut crc = Crc::new();
+        crc.update(&bytes);
+
+        if reader.read_u32::<BigEndian>()? != crc.sum() {
+            return Ok(None);
+        }
+
+        let version = ChunkVersion {
+            replaced_at,
+            raw_chunk,
+        };
+
+        Ok(Some(((chunk_x, chunk_z), version)))
+    }
+}
+
+/// Folder provider that archives the previous version of every chunk it
+/// saves or deletes, so chunks can be rolled back to an earlier time.
+///
+/// Versions are appended to one history file per region, named like the
+/// region file with a `.history` extension, in the history folder. History
+/// files are never compacted, old ones can be removed when they are no
+/// longer needed.
+///
+/// Only writes made through this provider are archived.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{FolderChunkProvider, HistoryChunkProvider};
+/// use nbt::CompoundTag;
+///
+/// # let folder = tempfile::tempdir().unwrap();
+/// # let folder = folder.path();
+/// let chunk_provider =
+///     HistoryChunkProvider::new(FolderChunkProvider::new(folder), folder.join("history"));
+///
+/// chunk_provider.save_chunk(1, 2, CompoundTag::new()).unwrap();
+/// assert_eq!(chunk_provider.chunk_history(1, 2).unwrap().len(), 1);
+///
+/// // Back to before the chunk was created.
+/// chunk_provider.rollback_chunk(1, 2, 0).unwrap();
+/// assert!(chunk_provider.try_load_chunk(1, 2).unwrap().is_none());
+/// ```
+pub struct HistoryChunkProvider<P = CompoundTag> {
+    /// Provider which reads and writes the region files.
+    provider: FolderChunkProvider<P>,
+    /// Folder of the history files.
+    history_path: PathBuf,
+    /// Held while archiving a chunk and writing it, so the history is in
+    /// the order of the writes.
+    write_lock: Mutex<()>,
+}
+
+impl<P: ChunkPayload> HistoryChunkProvider<P> {
+    /// Archives the chunks written through the provider into the history
+    /// folder, which is created on the first write.
+    pub fn new<F: Into<PathBuf>>(provider: FolderChunkProvider<P>, history_folder: F) -> Self {
+        HistoryChunkProvider {
+            provider,
+            history_path: history_folder.into(),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// Returns the wrapped provider. Writes made through it are not
+    /// archived.
+    pub fn provider(&self) -> &FolderChunkProvider<P> {
+        &self.provider
+    }
+
+    pub fn into_inner(self) -> FolderChunkProvider<P> {
+        self.provider
+    }
+
+    /// Archived versions of the chunk, oldest first.
+    pub fn chunk_history(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+    ) -> Result<Vec<ChunkVersion>, io::Error> {
+        let (region_x, region_z) = chunk_coords_to_region_coords(chunk_x, chunk_z);
+        let chunk = chunk_coords_inside_region(chunk_x, chunk_z);
+        let history_path = self.history_file_path(region_x, region_z);
+
+        if !history_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut reader = BufReader::new(File::open(history_path)?);
+        let mut versions = Vec::new();
+
+        while let Some((entry_chunk, version)) = ChunkVersion::read(&mut reader)? {
+            if entry_chunk == chunk {
+                versions.push(version);
+            }
+        }
+
+        Ok(versions)
+    }
+
+    /// Restores the chunk to the version it had at the given time, in
+    /// seconds since the Unix epoch. Returns false if the chunk was not
+    /// modified since then.
+    ///
+    /// The rollback is a write like any other: the replaced version is
+    /// archived and the restored chunk gets the current time as timestamp.
+    pub fn rollback_chunk(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        to_timestamp: u32,
+    ) -> Result<bool, WorldEditError> {
+        let history = self.chunk_history(chunk_x, chunk_z)?;
+        let version = match history
+            .into_iter()
+            .find(|version| version.replaced_at > to_timestamp)
+        {
+            Some(version) => version,
+            None => return Ok(false),
+        };
+
+        match version.raw_chunk {
+            Some(raw_chunk) => {
+                self.save_chunk_raw(chunk_x, chunk_z, &raw_chunk, current_timestamp())?
+            }
+            None => self.delete_chunk(chunk_x, chunk_z)?,
+        }
+
+        Ok(true)
+    }
+
+    pub fn load_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        self.provider.load_chunk(chunk_x, chunk_z)
+    }
+
+    pub fn try_load_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<Option<P>, ChunkLoadError> {
+        self.provider.try_load_chunk(chunk_x, chunk_z)
+    }
+
+    pub fn save_chunk(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        let _write_lock = self.write_lock.lock().unwrap();
+        self.archive_chunk(chunk_x, chunk_z, current_timestamp())?;
+        self.provider
+            .save_chunk(chunk_x, chunk_z, chunk_compound_tag)
+    }
+
+    pub fn save_chunk_with_timestamp(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        let _write_lock = self.write_lock.lock().unwrap();
+        self.archive_chunk(chunk_x, chunk_z, current_timestamp())?;
+        self.provider.save_chunk_with_timestamp(
+            chunk_x,
+            chunk_z,
+            chunk_compound_tag,
+            last_modified_timestamp,
+        )
+    }
+
+    pub fn save_chunk_raw(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), WorldEditError> {
+        let _write_lock = self.write_lock.lock().unwrap();
+        self.archive_chunk(chunk_x, chunk_z, current_timestamp())?;
+        self.provider
+            .save_chunk_raw(chunk_x, chunk_z, raw_chunk, last_modified_timestamp)
+    }
+
+    pub fn delete_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError> {
+        let _write_lock = self.write_lock.lock().unwrap();
+        self.archive_chunk(chunk_x, chunk_z, current_timestamp())?;
+        self.provider.delete_chunk(chunk_x, chunk_z)
+    }
+
+    /// Appends the current version of the chunk to the history of its
+    /// region.
+    fn archive_chunk(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        replaced_at: u32,
+    ) -> Result<(), ChunkSaveError> {
+        let raw_chunk = missing_chunk_as_none(self.provider.load_chunk_raw(chunk_x, chunk_z))
+            .map_err(|e| match e {
+                ChunkLoadError::ReadError { io_error } => io_error,
+                e => io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e)),
+            })?;
+        let version = ChunkVersion {
+            replaced_at,
+            raw_chunk,
+        };
+
+        let (region_x, region_z) = chunk_coords_to_region_coords(chunk_x, chunk_z);
+        let (region_chunk_x, region_chunk_z) = chunk_coords_inside_region(chunk_x, chunk_z);
+
+        if !self.history_path.exists() {
+            fs::create_dir_all(&self.history_path)?;
+        }
+
+        let mut history = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.history_file_path(region_x, region_z))?;
+        history.write_all(&version.to_bytes(region_chunk_x, region_chunk_z))?;
+        history.sync_data()?;
+
+        Ok(())
+    }
+
+    fn history_file_path(&self, region_x: i32, region_z: i32) -> PathBuf {
+        let history_name = self.provider.region_file_name(region_x, region_z) + ".history";
+
+        self.history_path.join(history_name)
+    }
+}
+
+impl<P: ChunkPayload> ChunkReader<P> for HistoryChunkProvider<P> {
+    fn get_region(&mut self, region_x: i32, region_z: i32) -> Result<RegionReader, ChunkLoadError> {
+        self.provider.get_region(region_x, region_z)
+    }
+    fn load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        HistoryChunkProvider::load_chunk(self, chunk_x, chunk_z)
+    }
+    fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.provider.list_chunks()
+    }
+    fn list_regions(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.provider.list_regions()
+    }
+    fn load_chunk_raw(&mut self, chunk_x: i32, chunk_z: i32) -> Result<RawChunk, ChunkLoadError> {
+        self.provider.load_chunk_raw(chunk_x, chunk_z)
+    }
+}
+
+impl<P: ChunkPayload> ChunkWriter<P> for HistoryChunkProvider<P> {
+    fn save_chunk_with_timestamp(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        HistoryChunkProvider::save_chunk_with_timestamp(
+            self,
+            chunk_x,
+            chunk_z,
+            chunk_compound_tag,
+            last_modified_timestamp,
+        )
+    }
+    fn save_chunk(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        HistoryChunkProvider::save_chunk(self, chunk_x, chunk_z, chunk_compound_tag)
+    }
+    fn save_chunk_raw(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), WorldEditError> {
+        HistoryChunkProvider::save_chunk_raw(
+            self,
+            chunk_x,
+            chunk_z,
+            raw_chunk,
+            last_modified_timestamp,
+        )
+    }
+}
+
+impl<P: ChunkPayload> ChunkDeleter<P> for HistoryChunkProvider<P> {
+    fn delete_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError> {
+        HistoryChunkProvider::delete_chunk(self, chunk_x, chunk_z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::value_chunk_tag;
+
+    #[test]
+    fn test_history_rollback() {
+        let folder = tempfile::tempdir().unwrap();
+        let history_path = folder.path().join("history");
+        let chunk_provider =
+            HistoryChunkProvider::new(FolderChunkProvider::new(folder.path()), &history_path);
+
+        // Versions replaced at 100, 200 and 300.
+        for (value, replaced_at) in [(1, 100), (2, 200), (3, 300)].iter() {
+            chunk_provider.archive_chunk(33, 0, *replaced_at).unwrap();
+            chunk_provider
+                .provider()
+                .save_chunk(33, 0, value_chunk_tag(*value))
+                .unwrap();
+        }
+        chunk_provider
+            .save_chunk(0, 0, value_chunk_tag(10))
+            .unwrap();
+
+        let history = chunk_provider.chunk_history(33, 0).unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].replaced_at, 100);
+        assert_eq!(history[0].raw_chunk, None);
+        let raw_chunk = history[1].raw_chunk.as_ref().unwrap();
+        assert_eq!(raw_chunk.decode().unwrap().get_i32("value").unwrap(), 1);
+        assert_eq!(chunk_provider.chunk_history(0, 0).unwrap().len(), 1);
+        assert!(chunk_provider.chunk_history(1, 0).unwrap().is_empty());
+
+        assert!(chunk_provider.rollback_chunk(33, 0, 250).unwrap());
+        let chunk_compound_tag = chunk_provider.load_chunk(33, 0).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("value").unwrap(), 2);
+        assert!(!chunk_provider.rollback_chunk(33, 0, u32::MAX).unwrap());
+
+        // The rollback was archived and can be rolled back too.
+        let history = chunk_provider.chunk_history(33, 0).unwrap();
+        assert_eq!(history.len(), 4);
+        let raw_chunk = history[3].raw_chunk.as_ref().unwrap();
+        assert_eq!(raw_chunk.decode().unwrap().get_i32("value").unwrap(), 3);
+
+        assert!(chunk_provider.rollback_chunk(33, 0, 50).unwrap());
+        assert!(chunk_provider.try_load_chunk(33, 0).unwrap().is_none());
+
+        // An entry cut by a crash is ignored.
+        let history_file = history_path.join("r.1.0.mca.history");
+        let mut bytes = fs::read(&history_file).unwrap();
+        bytes.extend_from_slice(&[STORED_ENTRY, 1, 0, 0]);
+        fs::write(&history_file, bytes).unwrap();
+        assert_eq!(chunk_provider.chunk_history(33, 0).unwrap().len(), 5);
+    }
+}
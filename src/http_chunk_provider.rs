@@ -0,0 +1,502 @@
+use crate::{
+    read_chunk_data, AnvilRegionHeader, BudgetedCache, CacheBudget, CacheStats, ChunkLoadError,
+    ChunkPayload, ChunkReader, ChunkSelection, ListingOrder, RegionAndOffset, RegionReader,
+    REGION_HEADER_BYTES_LENGTH, REGION_SECTOR_BYTES_LENGTH,
+};
+use crate::region_cache::RegionBytes;
+use nbt::CompoundTag;
+use std::fs;
+use std::io;
+use std::io::{Cursor, Read};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Read-only provider fetching region files from a base URL, such as
+/// `https://example.com/world/region/`.
+///
+/// Chunks are loaded with HTTP range requests: the header of a region is
+/// fetched once and kept in memory, and every loaded chunk only downloads
+/// its own sectors. Servers ignoring the `Range` header send the whole
+/// region file, which is then kept in memory instead. `get_region` always
+/// downloads the whole region file.
+///
+/// A web server doesn't list its files, so the regions returned by
+/// `list_regions` and `list_chunks` must be given with `with_regions`.
+///
+/// # Example
+///
+/// ```no_run
+/// use anvil_region::{ChunkReader, HttpChunkProvider};
+///
+/// let mut chunk_provider = HttpChunkProvider::new("https://example.com/world/region")
+///     .with_cache_folder("/tmp/region-cache");
+///
+/// let chunk_compound_tag = chunk_provider.load_chunk(15, 3).unwrap();
+/// ```
+pub struct HttpChunkProvider<P = CompoundTag> {
+    agent: ureq::Agent,
+    /// Always ends with "/".
+    base_url: String,
+    regions: Option<Vec<(i32, i32)>>,
+    /// Folder where downloaded region files are kept, if any.
+    cache_folder: Option<PathBuf>,
+    headers: Mutex<BudgetedCache<(i32, i32), AnvilRegionHeader>>,
+    /// Whole region files, downloaded by `get_region` or by a server
+    /// ignoring range requests.
+    region_files: Mutex<BudgetedCache<(i32, i32), RegionBytes>>,
+    listing_order: ListingOrder,
+    payload: PhantomData<fn() -> P>,
+}
+
+/// Response to a request of a part of a region file.
+enum Fetched {
+    /// Requested bytes, possibly fewer at the end of the file.
+    Range(Vec<u8>),
+    /// The server sent the whole file.
+    Whole(RegionBytes),
+}
+
+impl HttpChunkProvider {
+    /// Creates a provider reading the region files at `base_url`.
+    pub fn new(base_url: &str) -> Self {
+        Self::with_agent(base_url, ureq::Agent::new())
+    }
+}
+
+impl<P: ChunkPayload> HttpChunkProvider<P> {
+    /// Same as `new`, but uses the given agent, for example one configured
+    /// with timeouts or a proxy.
+    pub fn with_agent(base_url: &str, agent: ureq::Agent) -> Self {
+        let mut base_url = base_url.to_string();
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+
+        HttpChunkProvider {
+            agent,
+            base_url,
+            regions: None,
+            cache_folder: None,
+            headers: Mutex::new(BudgetedCache::new(usize::MAX, None)),
+            region_files: Mutex::new(BudgetedCache::new(usize::MAX, None)),
+            listing_order: ListingOrder::default(),
+            payload: PhantomData,
+        }
+    }
+
+    /// Regions available at the base URL.
+    pub fn with_regions(self, regions: Vec<(i32, i32)>) -> Self {
+        HttpChunkProvider {
+            regions: Some(regions),
+            ..self
+        }
+    }
+
+    /// Keeps the downloaded whole region files in the given folder, and
+    /// reads them from there instead of the base URL. Files in the folder
+    /// are never checked for updates.
+    pub fn with_cache_folder<F: AsRef<Path>>(self, cache_folder: F) -> Self {
+        HttpChunkProvider {
+            cache_folder: Some(cache_folder.as_ref().to_path_buf()),
+            ..self
+        }
+    }
+
+    /// Accounts the cached headers and region files in the given budget,
+    /// dropping the least recently used ones when it is exceeded. They are
+    /// kept until the provider is dropped otherwise.
+    pub fn with_cache_budget(self, budget: CacheBudget) -> Self {
+        HttpChunkProvider {
+            headers: Mutex::new(BudgetedCache::new(usize::MAX, Some(budget.clone()))),
+            region_files: Mutex::new(BudgetedCache::new(usize::MAX, Some(budget))),
+            ..self
+        }
+    }
+
+    /// Order of the listed chunks and regions. Regions are listed in the
+    /// order given to `with_regions` by default.
+    pub fn with_listing_order(self, listing_order: ListingOrder) -> Self {
+        HttpChunkProvider {
+            listing_order,
+            ..self
+        }
+    }
+
+    /// Usage of the region files cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.region_files.lock().unwrap().stats()
+    }
+
+    fn region_file_name(region_x: i32, region_z: i32) -> String {
+        format!("r.{}.{}.mca", region_x, region_z)
+    }
+
+    /// Requests `length` bytes of the region file starting at `offset`, or
+    /// the whole file if `length` is `None`.
+    fn fetch(
+        &self,
+        region_x: i32,
+        region_z: i32,
+        offset: u64,
+        length: Option<u64>,
+    ) -> Result<Fetched, ChunkLoadError> {
+        let url = format!(
+            "{}{}",
+            self.base_url,
+            Self::region_file_name(region_x, region_z)
+        );
+        let mut request = self.agent.get(&url);
+        if let Some(length) = length {
+            request = request.set(
+                "Range",
+                &format!("bytes={}-{}", offset, offset + length - 1),
+            );
+        }
+
+        let response = match request.call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) | Err(ureq::Error::Status(410, _)) => {
+                return Err(ChunkLoadError::RegionNotFound { region_x, region_z })
+            }
+            // The range starts after the end of the file.
+            Err(ureq::Error::Status(416, _)) => return Ok(Fetched::Range(Vec::new())),
+            Err(e) => {
+                let io_error = io::Error::other(e);
+                return Err(ChunkLoadError::ReadError { io_error });
+            }
+        };
+
+        let partial = response.status() == 206;
+        let mut buf = Vec::new();
+        response.into_reader().read_to_end(&mut buf)?;
+
+        if partial {
+            Ok(Fetched::Range(buf))
+        } else {
+            let bytes = RegionBytes(Arc::new(buf));
+            self.keep_region(region_x, region_z, bytes.clone());
+
+            if let Some(cache_path) = self.cache_path(region_x, region_z) {
+                fs::create_dir_all(self.cache_folder.as_ref().unwrap())?;
+                fs::write(cache_path, bytes.as_ref())?;
+            }
+
+            Ok(Fetched::Whole(bytes))
+        }
+    }
+
+    fn cache_path(&self, region_x: i32, region_z: i32) -> Option<PathBuf> {
+        self.cache_folder
+            .as_ref()
+            .map(|folder| folder.join(Self::region_file_name(region_x, region_z)))
+    }
+
+    fn keep_region(&self, region_x: i32, region_z: i32, bytes: RegionBytes) {
+        let length = bytes.0.len() as u64;
+        self.region_files
+            .lock()
+            .unwrap()
+            .insert((region_x, region_z), bytes, length);
+    }
+
+    /// Returns the whole region file if it is in memory or in the cache
+    /// folder.
+    fn cached_region(&self, region_x: i32, region_z: i32) -> Option<RegionBytes> {
+        if let Some(bytes) = self.region_files.lock().unwrap().get(&(region_x, region_z)) {
+            return Some(bytes.clone());
+        }
+
+        let buf = fs::read(self.cache_path(region_x, region_z)?).ok()?;
+        let bytes = RegionBytes(Arc::new(buf));
+        self.keep_region(region_x, region_z, bytes.clone());
+
+        Some(bytes)
+    }
+
+    /// Returns the whole region file, downloading it on first use.
+    fn load_region(&self, region_x: i32, region_z: i32) -> Result<RegionBytes, ChunkLoadError> {
+        if let Some(bytes) = self.cached_region(region_x, region_z) {
+            return Ok(bytes);
+        }
+
+        match self.fetch(region_x, region_z, 0, None)? {
+            Fetched::Whole(bytes) => Ok(bytes),
+            Fetched::Range(_) => unreachable!("no range was requested"),
+        }
+    }
+
+    /// Returns the header of the region, fetching it on first use.
+    fn load_header(
+        &self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<AnvilRegionHeader, ChunkLoadError> {
+        if let Some(header) = self.headers.lock().unwrap().get(&(region_x, region_z)) {
+            return Ok(header.clone());
+        }
+
+        let header = match self.cached_region(region_x, region_z) {
+            Some(bytes) => AnvilRegionHeader::from_reader(&mut bytes.as_ref())?,
+            None => match self.fetch(region_x, region_z, 0, Some(REGION_HEADER_BYTES_LENGTH))? {
+                Fetched::Range(buf) => AnvilRegionHeader::from_reader(&mut buf.as_slice())?,
+                Fetched::Whole(bytes) => AnvilRegionHeader::from_reader(&mut bytes.as_ref())?,
+            },
+        };
+
+        self.headers.lock().unwrap().insert(
+            (region_x, region_z),
+            header.clone(),
+            REGION_HEADER_BYTES_LENGTH,
+        );
+
+        Ok(header)
+    }
+
+    /// Reads the compressed data of a chunk, only fetching its sectors.
+    fn load_chunk_data(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        data: &mut Vec<u8>,
+    ) -> Result<u8, ChunkLoadError> {
+        let RegionAndOffset {
+            region_x,
+            region_z,
+            region_chunk_x,
+            region_chunk_z,
+        } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
+
+        let header = self.load_header(region_x, region_z)?;
+        let mut metadata = header.get_metadata(region_chunk_x, region_chunk_z);
+
+        if metadata.is_empty() {
+            return Err(ChunkLoadError::ChunkNotFound {
+                chunk_x: region_chunk_x,
+                chunk_z: region_chunk_z,
+            });
+        }
+
+        if let Some(bytes) = self.cached_region(region_x, region_z) {
+            let mut region = Cursor::new(bytes);
+            return read_chunk_data(&mut region, metadata, region_chunk_x, region_chunk_z, data);
+        }
+
+        let sector_length = u64::from(REGION_SECTOR_BYTES_LENGTH);
+        let offset = u64::from(metadata.sector_index) * sector_length;
+        let length = u64::from(metadata.sectors) * sector_length;
+
+        match self.fetch(region_x, region_z, offset, Some(length))? {
+            Fetched::Range(buf) => {
+                // The fetched sectors start at the beginning of the buffer.
+                metadata.sector_index = 0;
+                read_chunk_data(
+                    &mut Cursor::new(buf),
+                    metadata,
+                    region_chunk_x,
+                    region_chunk_z,
+                    data,
+                )
+            }
+            Fetched::Whole(bytes) => read_chunk_data(
+                &mut Cursor::new(bytes),
+                metadata,
+                region_chunk_x,
+                region_chunk_z,
+                data,
+            ),
+        }
+    }
+
+    /// Loads the chunk at the specified coordinates.
+    pub fn load_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        let mut data = Vec::new();
+        let compression_scheme = self.load_chunk_data(chunk_x, chunk_z, &mut data)?;
+
+        P::decode(compression_scheme, &data)
+    }
+
+    /// Regions given to `with_regions`.
+    fn known_regions(&self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        match &self.regions {
+            Some(regions) => {
+                let mut regions = regions.clone();
+                self.listing_order.sort_regions(&mut regions);
+
+                Ok(regions)
+            }
+            None => {
+                let io_error = io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "the regions of an HTTP provider must be given with `with_regions`",
+                );
+                Err(ChunkLoadError::ReadError { io_error })
+            }
+        }
+    }
+
+    /// Lists the chunks in the selection, without fetching the headers of
+    /// the regions outside of it.
+    fn list_selected_chunks(
+        &self,
+        selection: &ChunkSelection,
+    ) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        let mut c = vec![];
+        for (region_x, region_z) in self.known_regions()? {
+            if !selection.may_contain_region(region_x, region_z) {
+                continue;
+            }
+
+            let header = self.load_header(region_x, region_z)?;
+
+            for ((region_chunk_x, region_chunk_z), _) in header.chunks() {
+                let chunk_x = (region_x * 32) + i32::from(region_chunk_x);
+                let chunk_z = (region_z * 32) + i32::from(region_chunk_z);
+
+                if selection.contains(chunk_x, chunk_z) {
+                    c.push((chunk_x, chunk_z));
+                }
+            }
+        }
+
+        self.listing_order.sort_chunks(&mut c);
+
+        Ok(c)
+    }
+}
+
+impl<P: ChunkPayload> ChunkReader<P> for HttpChunkProvider<P> {
+    fn get_region(&mut self, region_x: i32, region_z: i32) -> Result<RegionReader, ChunkLoadError> {
+        let bytes = self.load_region(region_x, region_z)?;
+
+        Ok(Box::new(Cursor::new(bytes)))
+    }
+    fn load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        HttpChunkProvider::load_chunk(self, chunk_x, chunk_z)
+    }
+    fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.list_selected_chunks(&ChunkSelection::All)
+    }
+    fn list_regions(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.known_regions()
+    }
+    fn list_chunks_in(
+        &mut self,
+        selection: &ChunkSelection,
+    ) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.list_selected_chunks(selection)
+    }
+    fn load_region_header(
+        &mut self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<AnvilRegionHeader, ChunkLoadError> {
+        self.load_header(region_x, region_z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_level;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    /// Serves the files of `test/region`, answering range requests if
+    /// `ranges` is true. Returns the base URL and the count of requests.
+    fn serve_test_region(ranges: bool) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/region", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let served_requests = requests.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut path = String::new();
+                let mut range = None;
+                let mut line = String::new();
+
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    if line.starts_with("GET ") {
+                        path = line.split(' ').nth(1).unwrap().to_string();
+                    }
+                    if let Some(bytes) = line.to_lowercase().strip_prefix("range: bytes=") {
+                        let (start, end) = bytes.trim().split_once('-').unwrap();
+                        range = Some((start.parse().unwrap(), end.parse::<usize>().unwrap()));
+                    }
+                    line.clear();
+                }
+                served_requests.fetch_add(1, Ordering::SeqCst);
+
+                let file = path
+                    .strip_prefix("/region/")
+                    .map(|name| Path::new("test/region").join(name));
+                let response = match file.and_then(|file| fs::read(file).ok()) {
+                    Some(body) => match range {
+                        Some((start, end)) if ranges => {
+                            let end = (end + 1).min(body.len());
+                            (206, body[start..end].to_vec())
+                        }
+                        _ => (200, body),
+                    },
+                    None => (404, Vec::new()),
+                };
+
+                write!(
+                    stream,
+                    "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    response.0,
+                    response.1.len()
+                )
+                .unwrap();
+                stream.write_all(&response.1).unwrap();
+            }
+        });
+
+        (base_url, requests)
+    }
+
+    #[test]
+    fn test_http_chunk_provider() {
+        let (base_url, requests) = serve_test_region(true);
+        let mut chunk_provider = HttpChunkProvider::new(&base_url).with_regions(vec![(0, 0)]);
+        let chunk_compound_tag = chunk_provider.load_chunk(15, 3).unwrap();
+        assert_eq!(
+            chunk_level(&chunk_compound_tag).get_i32("xPos").unwrap(),
+            15
+        );
+        // The header and the sectors of the chunk.
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        chunk_provider.load_chunk(4, 2).unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert_eq!(chunk_provider.list_chunks().unwrap().len(), 277);
+        assert!(matches!(
+            chunk_provider.load_chunk(0, 40),
+            Err(ChunkLoadError::RegionNotFound {
+                region_x: 0,
+                region_z: 1
+            })
+        ));
+
+        // The whole file is sent and kept by servers without range requests.
+        let cache_folder = tempfile::tempdir().unwrap();
+        let (base_url, requests) = serve_test_region(false);
+        let mut chunk_provider =
+            HttpChunkProvider::new(&base_url).with_cache_folder(cache_folder.path());
+        let chunk_compound_tag = chunk_provider.load_chunk(15, 3).unwrap();
+        assert_eq!(
+            chunk_level(&chunk_compound_tag).get_i32("xPos").unwrap(),
+            15
+        );
+        chunk_provider.load_chunk(4, 2).unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert!(chunk_provider.list_regions().is_err());
+
+        chunk_provider.get_region(0, 0).unwrap();
+        assert!(cache_folder.path().join("r.0.0.mca").exists());
+    }
+}
@@ -0,0 +1,402 @@
+use crate::{
+    AnvilRegion, ChunkPayload, ChunkSaveError, FolderChunkProvider, OpenRegion, RawChunk,
+    RegionAndOffset, CHUNK_MAXIMUM_BYTES_LENGTH,
+};
+use byteorder::{BigEndian, ReadBytesExt};
+use flate2::Crc;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+/// Journal entry type of a written chunk.
+const WRITE_ENTRY: u8 = 1;
+/// Journal entry type of a deleted chunk.
+const DELETE_ENTRY: u8 = 2;
+
+/// Chunk change recorded in a region journal.
+///
+/// Entries are stored one after another, each one followed by the CRC32 of
+/// its bytes so an entry cut by a crash is recognized and ignored:
+///
+/// - the entry type, 1 for writes and 2 for deletions.
+/// - the chunk coordinates inside the region, one byte each.
+/// - for writes, the timestamp, the compression scheme, the data length and
+///   the compressed data.
+enum JournalEntry {
+    Write {
+        chunk: (u8, u8),
+        raw_chunk: RawChunk,
+        last_modified_timestamp: u32,
+    },
+    Delete {
+        chunk: (u8, u8),
+    },
+}
+
+impl JournalEntry {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        match self {
+            JournalEntry::Write {
+                chunk: (chunk_x, chunk_z),
+                raw_chunk,
+                last_modified_timestamp,
+            } => {
+                bytes.extend_from_slice(&[WRITE_ENTRY, *chunk_x, *chunk_z]);
+                bytes.extend_from_slice(&last_modified_timestamp.to_be_bytes());
+                bytes.push(raw_chunk.compression_scheme());
+                bytes.extend_from_slice(&(raw_chunk.data().len() as u32).to_be_bytes());
+                bytes.extend_from_slice(raw_chunk.data());
+            }
+            JournalEntry::Delete {
+                chunk: (chunk_x, chunk_z),
+            } => bytes.extend_from_slice(&[DELETE_ENTRY, *chunk_x, *chunk_z]),
+        }
+
+        let mut crc = Crc::new();
+        crc.update(&bytes);
+        bytes.extend_from_slice(&crc.sum().to_be_bytes());
+
+        bytes
+    }
+
+    /// Reads the next entry, or `None` at the end of the journal or at an
+    /// incomplete or corrupted entry.
+    fn read<R: Read>(reader: &mut R) -> Result<Option<Self>, io::Error> {
+        match Self::read_checked(reader) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            result => result,
+        }
+    }
+
+    fn read_checked<R: Read>(reader: &mut R) -> Result<Option<Self>, io::Error> {
+        let mut head = [0; 3];
+        reader.read_exact(&mut head)?;
+
+        let [entry_type, chunk_x, chunk_z] = head;
+        let mut bytes = head.to_vec();
+
+        let entry = match entry_type {
+            WRITE_ENTRY => {
+                let last_modified_timestamp = reader.read_u32::<BigEndian>()?;
+                let compression_scheme = reader.read_u8()?;
+                let length = reader.read_u32::<BigEndian>()?;
+
+                if length > CHUNK_MAXIMUM_BYTES_LENGTH {
+                    return Ok(None);
+                }
+
+                let mut data = vec![0; length as usize];
+                reader.read_exact(&mut data)?;
+
+                bytes.extend_from_slice(&last_modified_timestamp.to_be_bytes());
+                bytes.push(compression_scheme);
+                bytes.extend_from_slice(&length.to_be_bytes());
+                bytes.extend_from_slice(&data);
+
+                JournalEntry::Write {
+                    chunk: (chunk_x, chunk_z),
+                    raw_chunk: RawChunk::new(compression_scheme, data),
+                    last_modified_timestamp,
+                }
+            }
+            DELETE_ENTRY => JournalEntry::Delete {
+                chunk: (chunk_x, chunk_z),
+            },
+            _ => return Ok(None),
+        };
+
+        let mut crc = Crc::new();
+        crc.update(&bytes);
+
+        if reader.read_u32::<BigEndian>()? != crc.sum() {
+            return Ok(None);
+        }
+
+        Ok(Some(entry))
+    }
+
+    fn apply(&self, region: &mut AnvilRegion<File>) -> Result<(), ChunkSaveError> {
+        match self {
+            JournalEntry::Write {
+                chunk: (chunk_x, chunk_z),
+                raw_chunk,
+                last_modified_timestamp,
+            } => region.write_chunk_raw(*chunk_x, *chunk_z, raw_chunk, *last_modified_timestamp),
+            JournalEntry::Delete {
+                chunk: (chunk_x, chunk_z),
+            } => Ok(region.delete_chunk(*chunk_x, *chunk_z)?),
+        }
+    }
+}
+
+impl<P: ChunkPayload> FolderChunkProvider<P> {
+    pub(crate) fn journal_path(&self, region_x: i32, region_z: i32) -> PathBuf {
+        let journal_name = self.region_file_name(region_x, region_z) + ".journal";
+
+        self.folder_path.join(journal_name)
+    }
+
+    /// Records the chunk change in the journal of its region, then applies
+    /// it. `None` deletes the chunk, the timestamp of a written chunk is
+    /// chosen by the timestamp policy if `None`.
+    pub(crate) fn write_journaled(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        change: Option<(RawChunk, Option<u32>)>,
+    ) -> Result<(), ChunkSaveError> {
+        let RegionAndOffset {
+            region_x,
+            region_z,
+            region_chunk_x,
+            region_chunk_z,
+        } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
+
+        if let Some((raw_chunk, _)) = &change {
+            // Chunk length, compression scheme and data.
+            let length = 5 + raw_chunk.data().len() as u32;
+
+            if length > CHUNK_MAXIMUM_BYTES_LENGTH {
+                return Err(ChunkSaveError::LengthExceedsMaximum { length });
+            }
+        }
+
+        if !self.folder_path.exists() {
+            fs::create_dir(&self.folder_path)?;
+        }
+
+        let journal_path = self.journal_path(region_x, region_z);
+        let chunk = (region_chunk_x, region_chunk_z);
+
+        self.with_region(region_x, region_z, |region| {
+            let entry = match change {
+                Some((raw_chunk, last_modified_timestamp)) => JournalEntry::Write {
+                    chunk,
+                    last_modified_timestamp: last_modified_timestamp.unwrap_or_else(|| {
+                        self.policy_timestamp(region.get_metadata(region_chunk_x, region_chunk_z))
+                    }),
+                    raw_chunk,
+                },
+                None => JournalEntry::Delete { chunk },
+            };
+
+            self.append_journal(&journal_path, region, &[entry])
+        })
+    }
+
+    /// Records the deletion of the given chunks in the journal of the
+    /// region, then deletes them. The region must be locked by the caller.
+    pub(crate) fn delete_journaled(
+        &self,
+        region_x: i32,
+        region_z: i32,
+        region: &mut AnvilRegion<File>,
+        chunks: &[(u8, u8)],
+    ) -> Result<(), ChunkSaveError> {
+        if chunks.is_empty() {
+            return Ok(());
+        }
+
+        let entries: Vec<_> = chunks
+            .iter()
+            .map(|&chunk| JournalEntry::Delete { chunk })
+            .collect();
+
+        self.append_journal(&self.journal_path(region_x, region_z), region, &entries)
+    }
+
+    /// Appends the entries to the journal with a single sync, then applies
+    /// them to the region.
+    fn append_journal(
+        &self,
+        journal_path: &Path,
+        region: &mut AnvilRegion<File>,
+        entries: &[JournalEntry],
+    ) -> Result<(), ChunkSaveError> {
+        let mut bytes = Vec::new();
+
+        for entry in entries {
+            bytes.extend_from_slice(&entry.to_bytes());
+        }
+
+        let mut journal = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(journal_path)?;
+        journal.write_all(&bytes)?;
+        journal.sync_data()?;
+
+        for entry in entries {
+            entry.apply(region)?;
+        }
+
+        // The region is closed right away, nothing is left to replay.
+        if self.options.max_open_regions == 0 {
+            region.file.sync_data()?;
+            fs::remove_file(journal_path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies the journal of the region, if any, and removes it once the
+    /// region file is synced.
+    pub(crate) fn replay_journal(
+        &self,
+        region_x: i32,
+        region_z: i32,
+        region: &mut AnvilRegion<File>,
+    ) -> Result<(), io::Error> {
+        let journal_path = self.journal_path(region_x, region_z);
+
+        if !journal_path.exists() {
+            return Ok(());
+        }
+
+        let mut reader = BufReader::new(File::open(&journal_path)?);
+
+        while let Some(entry) = JournalEntry::read(&mut reader)? {
+            entry.apply(region).map_err(|e| match e {
+                ChunkSaveError::WriteError { io_error } => io_error,
+                e => io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e)),
+            })?;
+            self.telemetry.retried();
+        }
+
+        region.file.sync_data()?;
+        fs::remove_file(journal_path)
+    }
+
+    /// Syncs and closes every open region file, removing their journals.
+    pub(crate) fn compact_journals(&self) -> Result<(), io::Error> {
+        let regions = self.region_cache.lock().unwrap().take_all();
+        let mut result = Ok(());
+
+        for (region, open_region) in regions {
+            result = result.and_then(|()| self.compact_journal(region, open_region));
+        }
+
+        result
+    }
+
+    /// Syncs and closes the region file taken out of the cache, removing its
+    /// journal.
+    pub(crate) fn compact_journal(
+        &self,
+        (region_x, region_z): (i32, i32),
+        mut open_region: OpenRegion,
+    ) -> Result<(), io::Error> {
+        let journal_path = self.journal_path(region_x, region_z);
+
+        // Regions only read from have nothing to sync.
+        if open_region.writable {
+            open_region.region.flush()?;
+            open_region.region.file.sync_data()?;
+        }
+
+        if journal_path.exists() {
+            fs::remove_file(journal_path)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::value_chunk_tag;
+    use crate::{AnvilOptions, ChunkSelection};
+
+    #[test]
+    fn test_journal_removed_after_write() {
+        let folder = tempfile::tempdir().unwrap();
+        let options = AnvilOptions::new().journal(true);
+        let chunk_provider =
+            FolderChunkProvider::with_options(folder.path().to_str().unwrap(), options);
+
+        chunk_provider.save_chunk(0, 0, value_chunk_tag(1)).unwrap();
+        chunk_provider.delete_chunk(0, 0).unwrap();
+        chunk_provider.save_chunk(1, 0, value_chunk_tag(2)).unwrap();
+
+        assert!(!folder.path().join("r.0.0.mca.journal").exists());
+        assert!(chunk_provider.try_load_chunk(0, 0).unwrap().is_none());
+        let chunk_compound_tag = chunk_provider.load_chunk(1, 0).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("value").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_journal_replay() {
+        let folder = tempfile::tempdir().unwrap();
+        let folder_path = folder.path().to_str().unwrap();
+        let journal_path = folder.path().join("r.0.0.mca.journal");
+
+        FolderChunkProvider::new(folder_path)
+            .save_chunk(5, 5, value_chunk_tag(0))
+            .unwrap();
+        let original = fs::read(folder.path().join("r.0.0.mca")).unwrap();
+
+        let options = AnvilOptions::new().journal(true).max_open_regions(4);
+        let chunk_provider = FolderChunkProvider::with_options(folder_path, options);
+        chunk_provider.save_chunk(0, 0, value_chunk_tag(1)).unwrap();
+        chunk_provider.save_chunk(1, 0, value_chunk_tag(2)).unwrap();
+        chunk_provider.delete_chunk(5, 5).unwrap();
+        let mut journal = fs::read(&journal_path).unwrap();
+
+        chunk_provider.close_regions().unwrap();
+        assert!(!journal_path.exists());
+
+        // Crash before the region file was written, in the middle of
+        // appending another entry to the journal.
+        journal.extend_from_slice(&[WRITE_ENTRY, 2, 0, 1, 2]);
+        fs::write(folder.path().join("r.0.0.mca"), original).unwrap();
+        fs::write(&journal_path, journal).unwrap();
+
+        let chunk_provider = FolderChunkProvider::new(folder_path);
+        let chunk_compound_tag = chunk_provider.load_chunk(1, 0).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("value").unwrap(), 2);
+        assert!(chunk_provider.load_chunk(0, 0).is_ok());
+        assert!(chunk_provider.try_load_chunk(5, 5).unwrap().is_none());
+        assert!(chunk_provider.try_load_chunk(2, 0).unwrap().is_none());
+        assert!(!journal_path.exists());
+    }
+
+    #[test]
+    fn test_journal_replay_delete_chunks_where() {
+        let folder = tempfile::tempdir().unwrap();
+        let folder_path = folder.path().to_str().unwrap();
+        let journal_path = folder.path().join("r.0.0.mca.journal");
+
+        let chunk_provider = FolderChunkProvider::new(folder_path);
+        for chunk_x in 5..8 {
+            chunk_provider
+                .save_chunk(chunk_x, 5, value_chunk_tag(chunk_x))
+                .unwrap();
+        }
+        let original = fs::read(folder.path().join("r.0.0.mca")).unwrap();
+
+        let options = AnvilOptions::new().journal(true).max_open_regions(4);
+        let chunk_provider = FolderChunkProvider::with_options(folder_path, options);
+        let deleted = chunk_provider
+            .delete_chunks_where(&ChunkSelection::All, |(chunk_x, _), _| chunk_x < 7)
+            .unwrap();
+        assert_eq!(deleted, 2);
+        let journal = fs::read(&journal_path).unwrap();
+
+        chunk_provider.close_regions().unwrap();
+        assert!(!journal_path.exists());
+
+        // Crash before the region file was written.
+        fs::write(folder.path().join("r.0.0.mca"), original).unwrap();
+        fs::write(&journal_path, journal).unwrap();
+
+        let chunk_provider = FolderChunkProvider::new(folder_path);
+        assert!(chunk_provider.try_load_chunk(5, 5).unwrap().is_none());
+        assert!(chunk_provider.try_load_chunk(6, 5).unwrap().is_none());
+        assert!(chunk_provider.load_chunk(7, 5).is_ok());
+        assert!(!journal_path.exists());
+    }
+}
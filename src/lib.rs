@@ -21,40 +21,207 @@
 //! ## Write
 //!
 //! ```
-//! use anvil_region::FolderChunkProvider;
-//! use nbt::CompoundTag;
+//! use anvil_region::{ChunkBuilder, DataVersion, FolderChunkProvider};
 //!
 //! let chunk_provider = FolderChunkProvider::new("test/region");
-//! let mut chunk_compound_tag = CompoundTag::new();
-//! let mut level_compound_tag = CompoundTag::new();
-//!
-//! // To simplify example we add only coordinates.
-//! // Full list of required tags https://minecraft.gamepedia.com/Chunk_format.
-//! level_compound_tag.insert_i32("xPos", 31);
-//! level_compound_tag.insert_i32("zPos", 16);
 //!
-//! chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+//! // Chunk with the tags the game needs, without blocks.
+//! // Full list of tags https://minecraft.gamepedia.com/Chunk_format.
+//! let chunk_compound_tag = ChunkBuilder::new(DataVersion::V1_13)
+//!     .x(31)
+//!     .z(16)
+//!     .build();
 //!
 //! chunk_provider.save_chunk(31, 16, chunk_compound_tag);
 //! ```
 use bitvec::prelude::*;
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
 use nbt::decode::TagDecodeError;
-use nbt::decode::{read_gzip_compound_tag, read_zlib_compound_tag};
-use nbt::encode::write_zlib_compound_tag;
 use nbt::CompoundTag;
+use std::collections::HashSet;
 use std::fs::{File, OpenOptions};
-use std::io::{Cursor, Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::{fs, io};
+use std::{fmt, fs, io, mem};
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary_header;
+
+#[cfg(feature = "async")]
+mod async_region;
+#[cfg(feature = "async")]
+pub use async_region::*;
+
+#[cfg(feature = "encryption")]
+mod encrypted_provider;
+#[cfg(feature = "encryption")]
+pub use encrypted_provider::*;
+
+#[cfg(feature = "fastnbt")]
+mod fastnbt_chunks;
+
+#[cfg(feature = "http")]
+mod http_chunk_provider;
+#[cfg(feature = "http")]
+pub use http_chunk_provider::*;
+
+#[cfg(feature = "mmap")]
+mod mmap_region;
+#[cfg(feature = "mmap")]
+pub use mmap_region::*;
+
+#[cfg(feature = "object-store")]
+mod object_store_provider;
+#[cfg(feature = "object-store")]
+pub use object_store_provider::*;
+
+#[cfg(feature = "parallel")]
+mod parallel_save;
+#[cfg(feature = "parallel")]
+pub use parallel_save::*;
+
+#[cfg(feature = "quartz_nbt")]
+mod quartz_nbt_payload;
+#[cfg(feature = "quartz_nbt")]
+pub use quartz_nbt_payload::*;
+
+#[cfg(feature = "render")]
+mod render;
+#[cfg(feature = "render")]
+pub use render::*;
+
+#[cfg(any(test, feature = "testutil"))]
+pub mod testutil;
+
+#[cfg(feature = "upgrade")]
+pub mod upgrade;
+
+#[cfg(feature = "valence_nbt")]
+mod valence_nbt_payload;
+#[cfg(feature = "valence_nbt")]
+pub use valence_nbt_payload::*;
 
 #[cfg(feature = "zip")]
 pub mod zip_chunk_provider;
 #[cfg(feature = "zip")]
 pub use zip_chunk_provider::*;
 
+mod block_entities;
+pub use block_entities::*;
+mod cache_budget;
+pub use cache_budget::*;
+mod chunk_builder;
+pub use chunk_builder::*;
+mod chunk_selection;
+pub use chunk_selection::*;
+mod chunk_surface;
+mod chunk_tags;
+pub use chunk_tags::*;
+mod compact;
+pub use compact::*;
+mod compare_regions;
+pub use compare_regions::*;
+mod compressed_region;
+pub use compressed_region::*;
+pub mod coords;
+mod disk_usage;
+pub use disk_usage::*;
+mod dry_run;
+pub use dry_run::*;
+mod duplicate_chunks;
+pub use duplicate_chunks::*;
+mod edit_session;
+pub use edit_session::*;
+mod entity_index;
+pub use entity_index::*;
+mod error_context;
+pub use error_context::*;
+mod full_chunk;
+pub use full_chunk::*;
+mod heightmap_export;
+pub use heightmap_export::*;
+mod history_provider;
+pub use history_provider::*;
+mod journal;
+mod map_item;
+pub use map_item::*;
+mod merge_worlds;
+pub use merge_worlds::*;
+mod nbt_query;
+pub use nbt_query::*;
+mod options;
+pub use options::*;
+mod parse;
+pub use parse::*;
+mod payload;
+pub use payload::*;
+mod player_data;
+pub use player_data::*;
+mod png;
+mod poi_check;
+pub use poi_check::*;
+mod positions;
+pub use positions::*;
+mod prefetch_provider;
+pub use prefetch_provider::*;
+mod quarantine;
+pub use quarantine::*;
+mod raw_chunk;
+pub use raw_chunk::*;
+mod read_only_provider;
+pub use read_only_provider::*;
+mod region_cache;
+mod region_dump;
+mod region_handle;
+pub use region_handle::*;
+mod region_info;
+pub use region_info::*;
+mod region_naming;
+pub use region_naming::*;
+mod region_pack;
+pub use region_pack::*;
+mod region_slice;
+pub use region_slice::*;
+mod region_verify;
+pub use region_verify::*;
+mod repair_world;
+pub use repair_world::*;
+mod server_directory;
+pub use server_directory::*;
+mod shift_world;
+pub use shift_world::*;
+mod snapshot_provider;
+pub use snapshot_provider::*;
+mod sparse;
+pub use sparse::*;
 mod strict_parse_int;
+mod structure_files;
+pub use structure_files::*;
+mod structure_refs;
+pub use structure_refs::*;
+mod telemetry;
+pub use telemetry::*;
+mod timestamps;
+pub use timestamps::*;
+mod torn_writes;
+pub use torn_writes::*;
+mod transaction;
+pub use transaction::*;
+mod validate_chunk;
+pub use validate_chunk::*;
+mod world_chunks;
+pub use world_chunks::*;
+mod world_folder;
+pub use world_folder::*;
+mod world_layout;
+pub use world_layout::*;
+mod world_report;
+pub use world_report::*;
+use payload::decode_counted;
+use region_cache::{OpenRegion, RegionBytes, RegionCache, RegionLocks};
 
 /// Amount of chunks in region.
 const REGION_CHUNKS: usize = 1024;
@@ -64,12 +231,16 @@ const REGION_CHUNKS_METADATA_LENGTH: usize = 2 * REGION_CHUNKS;
 const REGION_HEADER_BYTES_LENGTH: u64 = 8 * REGION_CHUNKS as u64;
 /// Region sector length in bytes.
 const REGION_SECTOR_BYTES_LENGTH: u16 = 4096;
+/// Maximum amount of sectors of a chunk, as the header stores it in a byte.
+const CHUNK_MAXIMUM_SECTORS: u32 = 255;
 /// Maximum chunk length in bytes.
-const CHUNK_MAXIMUM_BYTES_LENGTH: u32 = REGION_SECTOR_BYTES_LENGTH as u32 * 256;
+const CHUNK_MAXIMUM_BYTES_LENGTH: u32 = REGION_SECTOR_BYTES_LENGTH as u32 * CHUNK_MAXIMUM_SECTORS;
 /// Gzip compression type value.
 const GZIP_COMPRESSION_TYPE: u8 = 1;
 /// Zlib compression type value.
 const ZLIB_COMPRESSION_TYPE: u8 = 2;
+/// Uncompressed type value.
+const UNCOMPRESSED_COMPRESSION_TYPE: u8 = 3;
 
 /// Possible errors while loading the chunk.
 #[derive(Debug)]
@@ -78,7 +249,8 @@ pub enum ChunkLoadError {
     RegionNotFound { region_x: i32, region_z: i32 },
     /// Chunk at specified coordinates inside region not found.
     ChunkNotFound { chunk_x: u8, chunk_z: u8 },
-    /// Chunk length overlaps declared maximum.
+    /// Chunk length overlaps declared maximum, which is the space of the
+    /// sectors allocated to the chunk in the header.
     ///
     /// This should not occur under normal conditions.
     ///
@@ -89,7 +261,13 @@ pub enum ChunkLoadError {
         /// Chunk maximum expected length.
         maximum_length: u32,
     },
-    /// Currently are only 2 types of compression: Gzip and Zlib.
+    /// Chunk length is zero, so not even the compression scheme is stored.
+    ///
+    /// This should not occur under normal conditions.
+    ///
+    /// Region file are corrupted.
+    EmptyChunkData,
+    /// Currently are only 3 types of compression: Gzip, Zlib and none.
     ///
     /// This should not occur under normal conditions.
     ///
@@ -106,6 +284,40 @@ pub enum ChunkLoadError {
     ///
     /// Region file are corrupted or a developer error in the NBT library.
     TagDecodeError { tag_decode_error: TagDecodeError },
+    /// Chunk `xPos`/`zPos` tags do not match the coordinates it was loaded
+    /// from.
+    ///
+    /// Only returned when strict loading is enabled, see `AnvilOptions`.
+    CoordinateMismatch {
+        /// Coordinates the chunk was loaded from.
+        chunk_x: i32,
+        chunk_z: i32,
+        /// Coordinates found in the chunk tags.
+        x_pos: i32,
+        z_pos: i32,
+    },
+    /// Error of a `FolderChunkProvider` with the file and chunk it happened
+    /// in.
+    ///
+    /// Only returned when the `context` feature is enabled, see
+    /// `ErrorContext`.
+    Context {
+        context: Box<ErrorContext>,
+        error: Box<ChunkLoadError>,
+    },
+}
+
+/// Converts a missing region or chunk into `Ok(None)`.
+fn missing_chunk_as_none<T>(
+    result: Result<T, ChunkLoadError>,
+) -> Result<Option<T>, ChunkLoadError> {
+    match result {
+        Ok(chunk_compound_tag) => Ok(Some(chunk_compound_tag)),
+        Err(ChunkLoadError::RegionNotFound { .. }) | Err(ChunkLoadError::ChunkNotFound { .. }) => {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
 }
 
 impl From<io::Error> for ChunkLoadError {
@@ -123,7 +335,8 @@ impl From<TagDecodeError> for ChunkLoadError {
 /// Possible errors while saving the chunk.
 #[derive(Debug)]
 pub enum ChunkSaveError {
-    /// Chunk length exceeds 1 MB.
+    /// Chunk needs more than 255 sectors (1020 KiB), the most the region
+    /// header can describe.
     ///
     /// This should not occur under normal conditions.
     LengthExceedsMaximum {
@@ -132,6 +345,28 @@ pub enum ChunkSaveError {
     },
     /// I/O Error which happened while were writing chunk data to region file.
     WriteError { io_error: io::Error },
+    /// Chunk `xPos`/`zPos` tags do not match the coordinates it was saved to.
+    ///
+    /// Only returned when coordinate checking is enabled, see `CoordinateCheck`.
+    CoordinateMismatch {
+        /// Coordinates the chunk was saved to.
+        chunk_x: i32,
+        chunk_z: i32,
+        /// Coordinates found in the chunk tags.
+        x_pos: i32,
+        z_pos: i32,
+    },
+    /// The provider is read-only.
+    ReadOnly,
+    /// Error of a `FolderChunkProvider` with the file and chunk it happened
+    /// in.
+    ///
+    /// Only returned when the `context` feature is enabled, see
+    /// `ErrorContext`.
+    Context {
+        context: Box<ErrorContext>,
+        error: Box<ChunkSaveError>,
+    },
 }
 
 impl From<io::Error> for ChunkSaveError {
@@ -140,6 +375,135 @@ impl From<io::Error> for ChunkSaveError {
     }
 }
 
+/// Possible errors of operations that both load and save chunks.
+#[derive(Debug)]
+pub enum WorldEditError {
+    /// Chunk could not be loaded.
+    Load(ChunkLoadError),
+    /// Chunk could not be saved.
+    Save(ChunkSaveError),
+}
+
+impl From<ChunkLoadError> for WorldEditError {
+    fn from(e: ChunkLoadError) -> Self {
+        Self::Load(e)
+    }
+}
+
+impl From<io::Error> for WorldEditError {
+    fn from(io_error: io::Error) -> Self {
+        Self::Load(ChunkLoadError::ReadError { io_error })
+    }
+}
+
+impl From<ChunkSaveError> for WorldEditError {
+    fn from(e: ChunkSaveError) -> Self {
+        Self::Save(e)
+    }
+}
+
+/// What to do when the `xPos`/`zPos` tags of a saved chunk do not match the
+/// coordinates it is saved to.
+///
+/// Both the `Level.xPos` layout and the 1.18+ root `xPos` layout are checked.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum CoordinateCheck {
+    /// Save the chunk as is.
+    #[default]
+    Disabled,
+    /// Return `ChunkSaveError::CoordinateMismatch` if the coordinates differ.
+    Verify,
+    /// Overwrite the coordinates in the chunk tags with the target ones.
+    Fix,
+}
+
+/// Compression of chunk data inside a region file.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Compression {
+    /// Gzip, unused by Minecraft in practice.
+    Gzip,
+    /// Zlib, used by Minecraft for every chunk.
+    #[default]
+    Zlib,
+    /// No compression, supported by Minecraft since 1.15.1.
+    Uncompressed,
+}
+
+impl Compression {
+    /// Compression type id stored before the chunk data.
+    pub fn id(self) -> u8 {
+        match self {
+            Compression::Gzip => GZIP_COMPRESSION_TYPE,
+            Compression::Zlib => ZLIB_COMPRESSION_TYPE,
+            Compression::Uncompressed => UNCOMPRESSED_COMPRESSION_TYPE,
+        }
+    }
+
+    /// Compression for a compression type id, if supported.
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            GZIP_COMPRESSION_TYPE => Some(Compression::Gzip),
+            ZLIB_COMPRESSION_TYPE => Some(Compression::Zlib),
+            UNCOMPRESSED_COMPRESSION_TYPE => Some(Compression::Uncompressed),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the compound holding the chunk data: `Level` for chunks before
+/// 1.18, or the root compound for newer chunks.
+pub fn chunk_level(chunk_compound_tag: &CompoundTag) -> &CompoundTag {
+    chunk_compound_tag
+        .get_compound_tag("Level")
+        .unwrap_or(chunk_compound_tag)
+}
+
+/// Mutable version of `chunk_level`.
+pub fn chunk_level_mut(chunk_compound_tag: &mut CompoundTag) -> &mut CompoundTag {
+    if chunk_compound_tag.get_compound_tag("Level").is_ok() {
+        chunk_compound_tag.get_mut("Level").unwrap()
+    } else {
+        chunk_compound_tag
+    }
+}
+
+impl CoordinateCheck {
+    /// Applies the check to a chunk that will be saved at `chunk_x`, `chunk_z`.
+    ///
+    /// Missing coordinate tags are not considered a mismatch.
+    pub fn apply<P: ChunkPayload>(
+        self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk: &mut P,
+    ) -> Result<(), ChunkSaveError> {
+        match self {
+            CoordinateCheck::Disabled => Ok(()),
+            CoordinateCheck::Verify => {
+                let (x_pos, z_pos) = chunk.chunk_coordinates();
+                let x_pos = x_pos.unwrap_or(chunk_x);
+                let z_pos = z_pos.unwrap_or(chunk_z);
+
+                if (x_pos, z_pos) != (chunk_x, chunk_z) {
+                    return Err(ChunkSaveError::CoordinateMismatch {
+                        chunk_x,
+                        chunk_z,
+                        x_pos,
+                        z_pos,
+                    });
+                }
+
+                Ok(())
+            }
+            CoordinateCheck::Fix => {
+                chunk.set_chunk_coordinates(chunk_x, chunk_z);
+
+                Ok(())
+            }
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct RegionAndOffset {
     region_x: i32,
@@ -173,103 +537,128 @@ impl RegionAndOffset {
 pub trait ReadAndSeek: Read + Seek {}
 impl<T: Read + Seek> ReadAndSeek for T {}
 
-pub trait AnvilChunkProvider {
-    fn get_region(&mut self, region_x: i32, region_z: i32) -> Result<Box<dyn ReadAndSeek + '_>, ChunkLoadError>;
-    fn load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<CompoundTag, ChunkLoadError>;
-    fn save_chunk(
+/// Region file returned by `ChunkReader::get_region`. It doesn't borrow the
+/// provider, so providers can be used as trait objects and the region can
+/// be read while the provider is used again.
+pub type RegionReader = Box<dyn ReadAndSeek + Send>;
+
+/// Storage whose length can be changed in both directions, needed to shrink
+/// region files.
+pub trait SetLen {
+    /// Truncates or extends the storage with zeroes to the given length.
+    fn set_len(&mut self, length: u64) -> Result<(), io::Error>;
+}
+
+impl SetLen for File {
+    fn set_len(&mut self, length: u64) -> Result<(), io::Error> {
+        File::set_len(self, length)
+    }
+}
+
+impl SetLen for io::Cursor<Vec<u8>> {
+    fn set_len(&mut self, length: u64) -> Result<(), io::Error> {
+        self.get_mut().resize(length as usize, 0);
+
+        Ok(())
+    }
+}
+
+/// Storage from which chunks can be read, decoded as `P`.
+pub trait ChunkReader<P: ChunkPayload = CompoundTag> {
+    fn get_region(&mut self, region_x: i32, region_z: i32) -> Result<RegionReader, ChunkLoadError>;
+    fn load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError>;
+    fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError>;
+    fn list_regions(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError>;
+
+    /// Same as `load_chunk`, but a missing region or chunk is returned as
+    /// `Ok(None)` instead of an error.
+    fn try_load_chunk(
         &mut self,
         chunk_x: i32,
         chunk_z: i32,
-        chunk_compound_tag: CompoundTag,
-    ) -> Result<(), ChunkSaveError>;
-    fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError>;
-    fn list_regions(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError>;
-}
+    ) -> Result<Option<P>, ChunkLoadError> {
+        missing_chunk_as_none(self.load_chunk(chunk_x, chunk_z))
+    }
 
-/// The chunks are saved in a folder (the default)
-pub struct FolderChunkProvider<'a> {
-    /// Folder where region files located.
-    folder_path: &'a Path,
-}
+    /// Same as `load_chunk`, with a typed position.
+    fn load_chunk_at(&mut self, chunk_pos: ChunkPos) -> Result<P, ChunkLoadError> {
+        self.load_chunk(chunk_pos.x, chunk_pos.z)
+    }
 
-impl<'a> FolderChunkProvider<'a> {
-    pub fn new(folder: &'a str) -> Self {
-        let folder_path = Path::new(folder);
+    /// Same as `try_load_chunk`, with a typed position.
+    fn try_load_chunk_at(&mut self, chunk_pos: ChunkPos) -> Result<Option<P>, ChunkLoadError> {
+        self.try_load_chunk(chunk_pos.x, chunk_pos.z)
+    }
 
-        FolderChunkProvider { folder_path }
+    /// Same as `list_chunks`, with typed positions.
+    fn list_chunk_positions(&mut self) -> Result<Vec<ChunkPos>, ChunkLoadError> {
+        Ok(self.list_chunks()?.into_iter().map(ChunkPos::from).collect())
     }
 
-    pub fn region_name(region_x: i32, region_z: i32) -> String {
-        format!("r.{}.{}.mca", region_x, region_z)
+    /// Returns the region at the specified coordinates, whose chunks are
+    /// read and written with their coordinates inside the region, without
+    /// going through `get_region`. Missing regions are created by writes.
+    fn open_region(&mut self, region_x: i32, region_z: i32) -> RegionHandle<'_, Self, P>
+    where
+        Self: Sized,
+    {
+        RegionHandle::new(self, region_x, region_z)
     }
 
-    /// Load chunks from the specified coordinates.
-    ///
-    /// # Example
-    ///
-    /// ```
-    /// use anvil_region::FolderChunkProvider;
-    ///
-    /// let chunk_provider = FolderChunkProvider::new("test/region");
-    ///
-    /// let chunk_compound_tag = chunk_provider.load_chunk(4, 2).unwrap();
-    /// let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+    /// Lists the regions with the size of their file and their amount of
+    /// chunks, reading only the region headers. Providers override it to
+    /// also report the modification time of the files.
+    fn list_regions_detailed(&mut self) -> Result<Vec<RegionInfo>, ChunkLoadError> {
+        region_info::list_regions_detailed(self)
+    }
+
+    /// Returns the coordinates of all the chunks in the selection.
     ///
-    /// assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
-    /// assert_eq!(level_compound_tag.get_i32("zPos").unwrap(), 2);
-    /// ```
-    pub fn load_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<CompoundTag, ChunkLoadError> {
-        let RegionAndOffset {
-            region_x,
-            region_z,
-            region_chunk_x,
-            region_chunk_z,
-        } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
+    /// Regions outside of the selection bounds are not opened.
+    fn list_chunks_in(
+        &mut self,
+        selection: &ChunkSelection,
+    ) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        let mut c = vec![];
 
-        let region_name = Self::region_name(region_x, region_z);
-        let region_path = self.folder_path.join(region_name);
+        for (region_x, region_z) in self.list_regions()? {
+            if !selection.may_contain_region(region_x, region_z) {
+                continue;
+            }
 
-        if !region_path.exists() {
-            return Err(ChunkLoadError::RegionNotFound { region_x, region_z });
+            let header = self.load_region_header(region_x, region_z)?;
+
+            for ((region_chunk_x, region_chunk_z), _) in header.chunks() {
+                let chunk_x = (region_x * 32) + i32::from(region_chunk_x);
+                let chunk_z = (region_z * 32) + i32::from(region_chunk_z);
+
+                if selection.contains(chunk_x, chunk_z) {
+                    c.push((chunk_x, chunk_z));
+                }
+            }
         }
 
-        // TODO: Cache region files.
-        let mut region = AnvilRegion::file(region_path)?;
+        Ok(c)
+    }
+
+    /// Reads the header of the region at the specified coordinates.
+    fn load_region_header(
+        &mut self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<AnvilRegionHeader, ChunkLoadError> {
+        let mut region = self.get_region(region_x, region_z)?;
+        region.seek(SeekFrom::Start(0))?;
 
-        region.read_chunk(region_chunk_x, region_chunk_z)
+        Ok(AnvilRegionHeader::from_reader(&mut region)?)
     }
 
-    /// Saves chunk data to the specified coordinates.
-    ///
-    /// # Example
-    ///
-    /// ```
-    /// use anvil_region::FolderChunkProvider;
-    /// use nbt::CompoundTag;
-    ///
-    /// let chunk_provider = FolderChunkProvider::new("test/region");
-    /// let mut chunk_compound_tag = CompoundTag::new();
-    /// let mut level_compound_tag = CompoundTag::new();
-    ///
-    /// // To simplify example we add only coordinates.
-    /// // Full list of required tags https://minecraft.gamepedia.com/Chunk_format.
-    /// level_compound_tag.insert_i32("xPos", 31);
-    /// level_compound_tag.insert_i32("zPos", 16);
-    ///
-    /// chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
-    ///
-    /// chunk_provider.save_chunk(31, 16, chunk_compound_tag);
-    /// ```
-    pub fn save_chunk(
-        &self,
+    /// Returns the header metadata of the chunk at the specified coordinates.
+    fn load_chunk_metadata(
+        &mut self,
         chunk_x: i32,
         chunk_z: i32,
-        chunk_compound_tag: CompoundTag,
-    ) -> Result<(), ChunkSaveError> {
-        if !self.folder_path.exists() {
-            fs::create_dir(self.folder_path)?;
-        }
-
+    ) -> Result<AnvilChunkMetadata, ChunkLoadError> {
         let RegionAndOffset {
             region_x,
             region_z,
@@ -277,639 +666,3566 @@ impl<'a> FolderChunkProvider<'a> {
             region_chunk_z,
         } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
 
-        let region_name = Self::region_name(region_x, region_z);
-        let region_path = self.folder_path.join(region_name);
-
-        // TODO: Cache region files.
-        let mut region = AnvilRegion::file(region_path)?;
-
-        region.write_chunk(region_chunk_x, region_chunk_z, chunk_compound_tag)
-    }
-
-    // Find all the region files in the current folder
-    fn find_all_region_mca(&self) -> Result<Vec<(i32, i32)>, std::io::Error> {
-        let mut r = vec![];
-
-        for entry in std::fs::read_dir(self.folder_path)? {
-            let entry = entry?;
-            let path = entry.path();
-            let filename = path.file_name().and_then(|x| x.to_str());
-            if filename.is_none() {
-                continue;
-            }
-
-            if let Some(coords) = parse_region_file_name(&filename.unwrap()) {
-                r.push(coords);
-            }
-        }
+        let header = self.load_region_header(region_x, region_z)?;
 
-        Ok(r)
+        Ok(header.get_metadata(region_chunk_x, region_chunk_z))
     }
 
-    pub fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
-        let regions = self.find_all_region_mca().map_err(|io_error| {
-            ChunkLoadError::ReadError { io_error }
-        })?;
-        let mut c = vec![];
-        for (region_x, region_z) in regions {
-            let region_name = Self::region_name(region_x, region_z);
-            let region_path = self.folder_path.join(region_name);
-
-            // TODO: Cache region files.
-            let region = AnvilRegion::file(region_path)?;
+    /// Reads the chunk at the specified coordinates without decompressing it.
+    fn load_chunk_raw(&mut self, chunk_x: i32, chunk_z: i32) -> Result<RawChunk, ChunkLoadError> {
+        let RegionAndOffset {
+            region_x,
+            region_z,
+            region_chunk_x,
+            region_chunk_z,
+        } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
 
-            // Insert all the non-empty chunks from this region
-            for region_chunk_z in 0..32 {
-                for region_chunk_x in 0..32 {
-                    let metadata = region.get_metadata(region_chunk_x, region_chunk_z);
+        let mut region = self.get_region(region_x, region_z)?;
+        region.seek(SeekFrom::Start(0))?;
 
-                    if !metadata.is_empty() {
-                        let chunk_x = (region_x * 32) + i32::from(region_chunk_x);
-                        let chunk_z = (region_z * 32) + i32::from(region_chunk_z);
-                        c.push((chunk_x, chunk_z));
-                    }
-                }
-            }
-        }
+        let header = AnvilRegionHeader::from_reader(&mut region)?;
+        let metadata = header.get_metadata(region_chunk_x, region_chunk_z);
+        let mut data = Vec::new();
+        let compression_scheme =
+            read_chunk_data(&mut region, metadata, region_chunk_x, region_chunk_z, &mut data)?;
 
-        Ok(c)
+        Ok(RawChunk::new(compression_scheme, data))
     }
 }
 
-impl<'a> AnvilChunkProvider for FolderChunkProvider<'a> {
-    fn get_region(&mut self, region_x: i32, region_z: i32) -> Result<Box<dyn ReadAndSeek + '_>, ChunkLoadError> {
-        let region_name = Self::region_name(region_x, region_z);
-        let region_path = self.folder_path.join(region_name);
-        let file = OpenOptions::new()
-            .write(true)
-            .read(true)
-            .create(true)
-            .open(region_path)?;
+/// Storage to which chunks of type `P` can be written.
+pub trait ChunkWriter<P: ChunkPayload = CompoundTag> {
+    /// Saves chunk data, stamping it with the given last modification time
+    /// in seconds since the Unix epoch.
+    fn save_chunk_with_timestamp(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError>;
 
-        Ok(Box::new(file))
-    }
-    fn load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<CompoundTag, ChunkLoadError> {
-        FolderChunkProvider::load_chunk(self, chunk_x, chunk_z)
-    }
+    /// Saves chunk data, stamping it with the current time.
     fn save_chunk(
         &mut self,
         chunk_x: i32,
         chunk_z: i32,
-        chunk_compound_tag: CompoundTag,
+        chunk_compound_tag: P,
     ) -> Result<(), ChunkSaveError> {
-        FolderChunkProvider::save_chunk(self, chunk_x, chunk_z, chunk_compound_tag)
+        self.save_chunk_with_timestamp(chunk_x, chunk_z, chunk_compound_tag, current_timestamp())
     }
-    fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
-        FolderChunkProvider::list_chunks(self)
+
+    /// Same as `save_chunk`, with a typed position.
+    fn save_chunk_at(
+        &mut self,
+        chunk_pos: ChunkPos,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        self.save_chunk(chunk_pos.x, chunk_pos.z, chunk_compound_tag)
     }
-    fn list_regions(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
-        self.find_all_region_mca().map_err(|io_error| {
-            ChunkLoadError::ReadError { io_error }
-        })
+
+    /// Saves already compressed chunk data.
+    ///
+    /// The default implementation decodes the chunk and saves it with
+    /// `save_chunk_with_timestamp`.
+    fn save_chunk_raw(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), WorldEditError> {
+        let chunk_compound_tag = raw_chunk.decode_payload()?;
+        self.save_chunk_with_timestamp(chunk_x, chunk_z, chunk_compound_tag, last_modified_timestamp)?;
+
+        Ok(())
     }
 }
 
-/// Region represents a 32x32 group of chunks.
-pub struct AnvilRegion<F> {
-    /// File in which region are stored.
-    file: F,
-    /// Array of chunks metadata.
-    chunks_metadata: [AnvilChunkMetadata; REGION_CHUNKS],
-    /// Used sectors for chunks data.
-    used_sectors: BitVec,
+/// Storage from which chunks of type `P` can also be removed.
+///
+/// Kept apart from `ChunkWriter`, so that operations which remove chunks,
+/// like `shift_world`, don't accept writers that can only add them.
+pub trait ChunkDeleter<P: ChunkPayload = CompoundTag>: ChunkWriter<P> {
+    /// Removes the chunk at the specified coordinates, if it exists.
+    fn delete_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError>;
+
+    /// Same as `delete_chunk`, with a typed position.
+    fn delete_chunk_at(&mut self, chunk_pos: ChunkPos) -> Result<(), ChunkSaveError> {
+        self.delete_chunk(chunk_pos.x, chunk_pos.z)
+    }
 }
 
-/// Chunk metadata are stored in header.
-#[derive(Copy, Clone, Default, Debug, Eq, PartialEq)]
-pub struct AnvilChunkMetadata {
-    /// Sector index from which starts chunk data.
-    sector_index: u32,
-    /// Amount of sectors used to store chunk.
-    sectors: u8,
-    /// Last time in seconds when chunk was modified.
+/// Storage from which chunks can be read and to which chunks can be written.
+///
+/// Implemented for every type that implements both `ChunkReader` and
+/// `ChunkWriter`.
+pub trait AnvilChunkProvider<P: ChunkPayload = CompoundTag>: ChunkReader<P> + ChunkWriter<P> {}
+impl<P: ChunkPayload, T: ChunkReader<P> + ChunkWriter<P> + ?Sized> AnvilChunkProvider<P> for T {}
+
+/// Implements the provider traits for a pointer to a provider, forwarding
+/// every method so the overrides of the provider are kept.
+macro_rules! forward_chunk_provider {
+    ($pointer:ty) => {
+        impl<P: ChunkPayload, T: ChunkReader<P> + ?Sized> ChunkReader<P> for $pointer {
+            fn get_region(&mut self, region_x: i32, region_z: i32) -> Result<RegionReader, ChunkLoadError> {
+                (**self).get_region(region_x, region_z)
+            }
+            fn load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+                (**self).load_chunk(chunk_x, chunk_z)
+            }
+            fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+                (**self).list_chunks()
+            }
+            fn list_regions(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+                (**self).list_regions()
+            }
+            fn try_load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<Option<P>, ChunkLoadError> {
+                (**self).try_load_chunk(chunk_x, chunk_z)
+            }
+            fn list_regions_detailed(&mut self) -> Result<Vec<RegionInfo>, ChunkLoadError> {
+                (**self).list_regions_detailed()
+            }
+            fn list_chunks_in(&mut self, selection: &ChunkSelection) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+                (**self).list_chunks_in(selection)
+            }
+            fn load_region_header(&mut self, region_x: i32, region_z: i32) -> Result<AnvilRegionHeader, ChunkLoadError> {
+                (**self).load_region_header(region_x, region_z)
+            }
+            fn load_chunk_metadata(&mut self, chunk_x: i32, chunk_z: i32) -> Result<AnvilChunkMetadata, ChunkLoadError> {
+                (**self).load_chunk_metadata(chunk_x, chunk_z)
+            }
+            fn load_chunk_raw(&mut self, chunk_x: i32, chunk_z: i32) -> Result<RawChunk, ChunkLoadError> {
+                (**self).load_chunk_raw(chunk_x, chunk_z)
+            }
+        }
+
+        impl<P: ChunkPayload, T: ChunkWriter<P> + ?Sized> ChunkWriter<P> for $pointer {
+            fn save_chunk_with_timestamp(
+                &mut self,
+                chunk_x: i32,
+                chunk_z: i32,
+                chunk_compound_tag: P,
+                last_modified_timestamp: u32,
+            ) -> Result<(), ChunkSaveError> {
+                (**self).save_chunk_with_timestamp(chunk_x, chunk_z, chunk_compound_tag, last_modified_timestamp)
+            }
+            fn save_chunk(&mut self, chunk_x: i32, chunk_z: i32, chunk_compound_tag: P) -> Result<(), ChunkSaveError> {
+                (**self).save_chunk(chunk_x, chunk_z, chunk_compound_tag)
+            }
+            fn save_chunk_raw(
+                &mut self,
+                chunk_x: i32,
+                chunk_z: i32,
+                raw_chunk: &RawChunk,
+                last_modified_timestamp: u32,
+            ) -> Result<(), WorldEditError> {
+                (**self).save_chunk_raw(chunk_x, chunk_z, raw_chunk, last_modified_timestamp)
+            }
+        }
+
+        impl<P: ChunkPayload, T: ChunkDeleter<P> + ?Sized> ChunkDeleter<P> for $pointer {
+            fn delete_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError> {
+                (**self).delete_chunk(chunk_x, chunk_z)
+            }
+        }
+    };
+}
+
+forward_chunk_provider!(&mut T);
+forward_chunk_provider!(Box<T>);
+
+/// The chunks are saved in a folder (the default)
+///
+/// Chunks are decoded as `P`, which is `nbt::CompoundTag` unless changed
+/// with `with_payload`.
+///
+/// Loading and saving only need `&self`, so the provider can be shared
+/// between threads behind an `Arc`. Each region is used by one thread at a
+/// time, chunks of different regions are loaded and saved in parallel.
+pub struct FolderChunkProvider<P = CompoundTag> {
+    /// Folder where region files located.
+    folder_path: PathBuf,
+    /// Provider configuration.
+    options: AnvilOptions,
+    /// Region files kept open between calls.
+    region_cache: Mutex<RegionCache>,
+    /// Regions in use by a thread.
+    region_locks: RegionLocks,
+    /// Last regions read from compressed region files.
+    decompressed_regions: Mutex<BudgetedCache<(i32, i32), RegionBytes>>,
+    /// I/O counters, see `telemetry`.
+    telemetry: TelemetryCounters,
+    /// What the operations would have done in dry-run mode.
+    dry_run_report: Mutex<DryRunReport>,
+    /// Type of loaded and saved chunks.
+    payload: PhantomData<fn() -> P>,
+}
+
+impl FolderChunkProvider {
+    /// Creates a provider for the given folder, which doesn't need to exist
+    /// until chunks are saved.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::FolderChunkProvider;
+    /// use std::path::PathBuf;
+    ///
+    /// let chunk_provider = FolderChunkProvider::new(PathBuf::from("test").join("region"));
+    ///
+    /// std::thread::spawn(move || {
+    ///     assert!(chunk_provider.load_chunk(4, 2).is_ok());
+    /// })
+    /// .join()
+    /// .unwrap();
+    /// ```
+    pub fn new<F: Into<PathBuf>>(folder: F) -> Self {
+        Self::with_options(folder, AnvilOptions::default())
+    }
+
+    /// Creates a provider with the given configuration.
+    pub fn with_options<F: Into<PathBuf>>(folder: F, options: AnvilOptions) -> Self {
+        let region_cache = Mutex::new(RegionCache::new(
+            options.max_open_regions,
+            options.cache_budget.clone(),
+        ));
+        let decompressed_regions = Mutex::new(BudgetedCache::new(
+            options.max_open_regions.max(1),
+            options.cache_budget.clone(),
+        ));
+
+        FolderChunkProvider {
+            folder_path: folder.into(),
+            options,
+            region_cache,
+            region_locks: RegionLocks::new(),
+            decompressed_regions,
+            telemetry: TelemetryCounters::default(),
+            dry_run_report: Mutex::new(DryRunReport::default()),
+            payload: PhantomData,
+        }
+    }
+}
+
+impl<P: ChunkPayload> FolderChunkProvider<P> {
+    /// Makes the provider load and save chunks as `Q`, for example the
+    /// compound type of another NBT library.
+    pub fn with_payload<Q: ChunkPayload>(self) -> FolderChunkProvider<Q> {
+        FolderChunkProvider {
+            folder_path: self.folder_path,
+            options: self.options,
+            region_cache: self.region_cache,
+            region_locks: self.region_locks,
+            decompressed_regions: self.decompressed_regions,
+            telemetry: self.telemetry,
+            dry_run_report: self.dry_run_report,
+            payload: PhantomData,
+        }
+    }
+
+    /// Returns the folder of the region files.
+    pub fn folder_path(&self) -> &Path {
+        &self.folder_path
+    }
+
+    /// Returns the provider configuration.
+    pub fn options(&self) -> &AnvilOptions {
+        &self.options
+    }
+
+    /// Sets the check applied to the `xPos`/`zPos` tags of saved chunks.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::{ChunkSaveError, CoordinateCheck, FolderChunkProvider};
+    /// use nbt::CompoundTag;
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("test/region")
+    ///     .with_coordinate_check(CoordinateCheck::Verify);
+    /// let mut chunk_compound_tag = CompoundTag::new();
+    /// chunk_compound_tag.insert_i32("xPos", 1);
+    /// chunk_compound_tag.insert_i32("zPos", 2);
+    ///
+    /// match chunk_provider.save_chunk(31, 16, chunk_compound_tag) {
+    ///     Err(ChunkSaveError::CoordinateMismatch { x_pos: 1, z_pos: 2, .. }) => {}
+    ///     e => panic!("Expected `CoordinateMismatch` but got `{:?}`", e),
+    /// }
+    /// ```
+    pub fn with_coordinate_check(mut self, coordinate_check: CoordinateCheck) -> Self {
+        self.options.coordinate_check = coordinate_check;
+        self
+    }
+
+    /// Reserves the given amount of free sectors in every region file this
+    /// provider creates, so that the file does not grow one sector at a time
+    /// while it is being filled.
+    ///
+    /// Existing region files are left as they are.
+    pub fn with_preallocated_sectors(mut self, sectors: u32) -> Self {
+        self.options.preallocated_sectors = sectors;
+        self
+    }
+
+    /// Sets the strategy used to find sectors for saved chunks.
+    pub fn with_sector_allocation(mut self, sector_allocation: SectorAllocation) -> Self {
+        self.options.sector_allocation = sector_allocation;
+        self
+    }
+
+    /// Keeps up to the given amount of region files open between calls,
+    /// closing the least recently used ones when more are needed.
+    ///
+    /// By default no region file is kept open. Region files kept open are
+    /// not reread, so they must not be modified by anything else than this
+    /// provider until `close_regions` is called.
+    ///
+    /// The header of an open region is written when the region is flushed,
+    /// closed or evicted, instead of after every write, unless the sync
+    /// policy is `SyncPolicy::EveryWrite`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::FolderChunkProvider;
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("test/region").with_max_open_regions(64);
+    ///
+    /// assert!(chunk_provider.load_chunk(4, 2).is_ok());
+    /// assert!(chunk_provider.load_chunk(4, 3).is_ok());
+    /// chunk_provider.close_regions().unwrap();
+    /// ```
+    pub fn with_max_open_regions(mut self, max_open_regions: usize) -> Self {
+        self.options.max_open_regions = max_open_regions;
+        self.region_cache = Mutex::new(RegionCache::new(
+            max_open_regions,
+            self.options.cache_budget.clone(),
+        ));
+        self.decompressed_regions = Mutex::new(BudgetedCache::new(
+            max_open_regions.max(1),
+            self.options.cache_budget.clone(),
+        ));
+        self
+    }
+
+    /// Usage of the open region files, see `with_max_open_regions`.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.region_cache.lock().unwrap().stats()
+    }
+
+    /// I/O counters of the provider since it was created.
+    pub fn telemetry(&self) -> Telemetry {
+        self.telemetry.snapshot()
+    }
+
+    /// Flushes and closes every region file kept open by the provider.
+    ///
+    /// With journaling enabled the region files are synced and their
+    /// journals removed.
+    pub fn close_regions(&self) -> Result<(), io::Error> {
+        if self.options.journal {
+            return self.compact_journals();
+        }
+
+        self.region_cache.lock().unwrap().clear()
+    }
+
+    /// Same as `close_regions` for a single region, which must be locked.
+    pub(crate) fn close_region(&self, region_x: i32, region_z: i32) -> Result<(), io::Error> {
+        if !self.options.journal {
+            return self.region_cache.lock().unwrap().remove((region_x, region_z));
+        }
+
+        let open_region = self.region_cache.lock().unwrap().take((region_x, region_z));
+
+        match open_region {
+            Some(open_region) => self.compact_journal((region_x, region_z), open_region),
+            None => Ok(()),
+        }
+    }
+
+    /// Syncs the region files kept open by the provider to disk, keeping
+    /// them open. Regions in use by other threads are skipped.
+    ///
+    /// With journaling enabled the journals of the synced regions are
+    /// removed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::FolderChunkProvider;
+    /// use nbt::CompoundTag;
+    ///
+    /// # let folder = tempfile::tempdir().unwrap();
+    /// # let folder = folder.path();
+    /// let chunk_provider = FolderChunkProvider::new(folder).with_max_open_regions(64);
+    ///
+    /// chunk_provider.save_chunk(4, 2, CompoundTag::new()).unwrap();
+    /// chunk_provider.flush().unwrap();
+    /// assert!(chunk_provider.load_chunk(4, 2).is_ok());
+    ///
+    /// chunk_provider.close().unwrap();
+    /// ```
+    pub fn flush(&self) -> Result<(), io::Error> {
+        let mut region_cache = self.region_cache.lock().unwrap();
+
+        for (&(region_x, region_z), open_region) in region_cache.iter_mut() {
+            // Regions only read from have nothing to sync.
+            if !open_region.writable {
+                continue;
+            }
+
+            open_region.region.flush()?;
+            open_region.region.file.sync_data()?;
+
+            let journal_path = self.journal_path(region_x, region_z);
+
+            if self.options.journal && journal_path.exists() {
+                fs::remove_file(journal_path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Consumes the provider, closing its region files like
+    /// `close_regions` and returning the errors dropping it would ignore.
+    pub fn close(self) -> Result<(), io::Error> {
+        self.close_regions()
+    }
+
+    /// Runs `f` on the region, creating the region file if needed.
+    ///
+    /// The region is taken from the open regions if possible and kept open
+    /// afterwards unless `f` fails. Other threads wait until `f` returns to
+    /// use the region, so `f` must not use the same region again.
+    fn with_region<T, E, F>(&self, region_x: i32, region_z: i32, f: F) -> Result<T, E>
+    where
+        E: From<io::Error>,
+        F: FnOnce(&mut AnvilRegion<File>) -> Result<T, E>,
+    {
+        let _lock = self.region_locks.lock((region_x, region_z));
+
+        self.with_locked_region(region_x, region_z, f)
+    }
+
+    /// Same as `with_region`, for a region already locked by the caller.
+    fn with_locked_region<T, E, F>(&self, region_x: i32, region_z: i32, f: F) -> Result<T, E>
+    where
+        E: From<io::Error>,
+        F: FnOnce(&mut AnvilRegion<File>) -> Result<T, E>,
+    {
+        let recompress = self.expand_compressed_region(region_x, region_z)?;
+        // Regions opened to load chunks are closed and opened again.
+        let cached_region = self
+            .region_cache
+            .lock()
+            .unwrap()
+            .take((region_x, region_z))
+            .filter(|open_region| open_region.writable);
+
+        let mut region = match cached_region {
+            Some(open_region) => open_region.region,
+            None => self.open_region_for_write(region_x, region_z)?,
+        };
+
+        let result = f(&mut region);
+        region.punch_freed_sectors()?;
+
+        if let Some((compressed_path, compression)) = recompress {
+            region.flush()?;
+            drop(region);
+            self.recompress_region(region_x, region_z, compressed_path, compression)?;
+
+            return result;
+        }
+
+        // The region is closed on error, with the chunks written so far.
+        if result.is_err() {
+            region.flush()?;
+        }
+
+        let result = result?;
+        let open_region = OpenRegion {
+            region,
+            writable: true,
+        };
+
+        self.region_cache
+            .lock()
+            .unwrap()
+            .put((region_x, region_z), open_region)?;
+
+        Ok(result)
+    }
+
+    /// Same as `with_region` for loading chunks, opening the region file
+    /// read-only unless it must be repaired first.
+    fn with_region_for_read<T, E, F>(&self, region_x: i32, region_z: i32, f: F) -> Result<T, E>
+    where
+        E: From<io::Error>,
+        F: FnOnce(&mut AnvilRegion<File>) -> Result<T, E>,
+    {
+        let _lock = self.region_locks.lock((region_x, region_z));
+
+        let repair = !self.options.read_only
+            && (self.options.recover_torn_writes
+                || self.journal_path(region_x, region_z).exists());
+
+        if repair {
+            return self.with_locked_region(region_x, region_z, f);
+        }
+
+        let cached_region = self.region_cache.lock().unwrap().take((region_x, region_z));
+
+        let mut open_region = match cached_region {
+            Some(open_region) => open_region,
+            None => OpenRegion {
+                region: self.open_region_for_read(region_x, region_z)?,
+                writable: false,
+            },
+        };
+
+        // Failed reads keep the region open, as it may hold header updates
+        // not written yet.
+        let result = f(&mut open_region.region);
+
+        self.region_cache
+            .lock()
+            .unwrap()
+            .put((region_x, region_z), open_region)?;
+
+        result
+    }
+
+    /// Opens the region file for loading chunks, so that worlds on read-only
+    /// file systems or without write permission can be loaded.
+    fn open_region_for_read(
+        &self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<AnvilRegion<File>, io::Error> {
+        let region = AnvilRegion::file_read_only(self.region_path(region_x, region_z))?;
+
+        self.telemetry.region_opened();
+        self.telemetry.header_read();
+
+        Ok(region)
+    }
+
+    /// Opens the region file for writing, creating it if needed.
+    fn open_region_for_write(
+        &self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<AnvilRegion<File>, io::Error> {
+        let region_name = self.region_file_name(region_x, region_z);
+        let region_path = self.folder_path.join(region_name);
+        let created = !region_path.exists();
+
+        // Open regions write their header when flushed or closed, unless
+        // every write must reach the disk.
+        let defer_header =
+            self.options.max_open_regions > 0 && self.options.sync_policy != SyncPolicy::EveryWrite;
+        let mut region = AnvilRegion::file(region_path)?
+            .with_sector_allocation(self.options.sector_allocation)
+            .with_compression(self.options.compression, self.options.compression_level)
+            .with_punch_holes(self.options.punch_holes)
+            .with_deferred_header(defer_header);
+
+        self.telemetry.region_opened();
+        self.telemetry.header_read();
+
+        if created {
+            region.reserve_sectors(self.options.preallocated_sectors)?;
+        }
+
+        // Redo the writes of a journal left by a crash.
+        if !self.options.read_only {
+            self.replay_journal(region_x, region_z, &mut region)?;
+        }
+
+        if self.options.recover_torn_writes && !self.options.read_only {
+            for torn_chunk in region.find_torn_chunks()? {
+                region.restore_previous_chunk(torn_chunk.chunk_x, torn_chunk.chunk_z)?;
+            }
+        }
+
+        Ok(region)
+    }
+
+    /// Vanilla name of the region file, see `region_file_name` for the name
+    /// used by the provider.
+    pub fn region_name(region_x: i32, region_z: i32) -> String {
+        VanillaNaming.region_name(region_x, region_z)
+    }
+
+    /// Name of the region file following `AnvilOptions::region_naming`.
+    pub fn region_file_name(&self, region_x: i32, region_z: i32) -> String {
+        self.region_naming().region_name(region_x, region_z)
+    }
+
+    pub(crate) fn region_naming(&self) -> &dyn RegionNaming {
+        match &self.options.region_naming {
+            Some(naming) => naming.0.as_ref(),
+            None => &VanillaNaming,
+        }
+    }
+
+    /// Load chunks from the specified coordinates.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::FolderChunkProvider;
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("test/region");
+    ///
+    /// let chunk_compound_tag = chunk_provider.load_chunk(4, 2).unwrap();
+    /// let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+    ///
+    /// assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+    /// assert_eq!(level_compound_tag.get_i32("zPos").unwrap(), 2);
+    /// ```
+    pub fn load_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        let RegionAndOffset {
+            region_x,
+            region_z,
+            region_chunk_x,
+            region_chunk_z,
+        } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
+
+        let region_name = self.region_file_name(region_x, region_z);
+        let region_path = self.folder_path.join(region_name);
+        self.telemetry.chunk_read();
+
+        if let Some(raw_chunk) = self.load_compressed_chunk_raw(chunk_x, chunk_z) {
+            let raw_chunk = raw_chunk?;
+            let (chunk_compound_tag, decompressed) =
+                decode_counted(raw_chunk.compression_scheme(), raw_chunk.data())?;
+            self.telemetry.decompressed(decompressed);
+
+            return self.check_loaded_chunk(chunk_x, chunk_z, chunk_compound_tag);
+        }
+
+        if !region_path.exists() {
+            return Err(ChunkLoadError::RegionNotFound { region_x, region_z });
+        }
+
+        let chunk_compound_tag: P = match self.with_region_for_read(region_x, region_z, |region| {
+            let metadata = region.get_metadata(region_chunk_x, region_chunk_z);
+            let compression_scheme = read_chunk_data(
+                &mut region.file,
+                metadata,
+                region_chunk_x,
+                region_chunk_z,
+                &mut region.buffer,
+            )?;
+            let (chunk_compound_tag, decompressed) =
+                decode_counted(compression_scheme, &region.buffer)?;
+            self.telemetry.decompressed(decompressed);
+
+            Ok(chunk_compound_tag)
+        }) {
+            Err(e)
+                if self.options.quarantine
+                    && !self.options.read_only
+                    && is_corrupted_chunk_error(&e) =>
+            {
+                self.quarantine_chunk(chunk_x, chunk_z, &e)?;
+
+                return Err(ChunkLoadError::ChunkNotFound {
+                    chunk_x: region_chunk_x,
+                    chunk_z: region_chunk_z,
+                });
+            }
+            result => result.map_err(|e| {
+                e.with_context(|| {
+                    ErrorContext::new("load_chunk", &region_path, Some((chunk_x, chunk_z)))
+                })
+            })?,
+        };
+
+        self.check_loaded_chunk(chunk_x, chunk_z, chunk_compound_tag)
+    }
+
+    /// Applies strict loading to a chunk loaded from the given coordinates.
+    fn check_loaded_chunk(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+    ) -> Result<P, ChunkLoadError> {
+        if self.options.strict_loading {
+            let (x_pos, z_pos) = chunk_compound_tag.chunk_coordinates();
+            let x_pos = x_pos.unwrap_or(chunk_x);
+            let z_pos = z_pos.unwrap_or(chunk_z);
+
+            if (x_pos, z_pos) != (chunk_x, chunk_z) {
+                return Err(ChunkLoadError::CoordinateMismatch {
+                    chunk_x,
+                    chunk_z,
+                    x_pos,
+                    z_pos,
+                });
+            }
+        }
+
+        Ok(chunk_compound_tag)
+    }
+
+    /// Load chunks from the specified coordinates, returning `None` if the
+    /// region or the chunk does not exist.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::FolderChunkProvider;
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("test/region");
+    ///
+    /// assert!(chunk_provider.try_load_chunk(4, 2).unwrap().is_some());
+    /// assert!(chunk_provider.try_load_chunk(15, 14).unwrap().is_none());
+    /// assert!(chunk_provider.try_load_chunk(100, 100).unwrap().is_none());
+    /// ```
+    pub fn try_load_chunk(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+    ) -> Result<Option<P>, ChunkLoadError> {
+        missing_chunk_as_none(self.load_chunk(chunk_x, chunk_z))
+    }
+
+    /// Loads the chunk at the specified coordinates without decompressing
+    /// it. Strict loading doesn't apply, as the chunk tags are not read.
+    pub fn load_chunk_raw(&self, chunk_x: i32, chunk_z: i32) -> Result<RawChunk, ChunkLoadError> {
+        let RegionAndOffset {
+            region_x,
+            region_z,
+            region_chunk_x,
+            region_chunk_z,
+        } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
+
+        let region_name = self.region_file_name(region_x, region_z);
+        let region_path = self.folder_path.join(region_name);
+        self.telemetry.chunk_read();
+
+        if let Some(raw_chunk) = self.load_compressed_chunk_raw(chunk_x, chunk_z) {
+            return raw_chunk;
+        }
+
+        if !region_path.exists() {
+            return Err(ChunkLoadError::RegionNotFound { region_x, region_z });
+        }
+
+        self.with_region_for_read(region_x, region_z, |region| {
+            region.read_chunk_raw(region_chunk_x, region_chunk_z)
+        })
+        .map_err(|e: ChunkLoadError| {
+            e.with_context(|| {
+                ErrorContext::new("load_chunk_raw", &region_path, Some((chunk_x, chunk_z)))
+            })
+        })
+    }
+
+    /// Saves chunk data to the specified coordinates.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::FolderChunkProvider;
+    /// use nbt::CompoundTag;
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("test/region");
+    /// let mut chunk_compound_tag = CompoundTag::new();
+    /// let mut level_compound_tag = CompoundTag::new();
+    ///
+    /// // To simplify example we add only coordinates.
+    /// // Full list of required tags https://minecraft.gamepedia.com/Chunk_format.
+    /// level_compound_tag.insert_i32("xPos", 31);
+    /// level_compound_tag.insert_i32("zPos", 16);
+    ///
+    /// chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+    ///
+    /// chunk_provider.save_chunk(31, 16, chunk_compound_tag);
+    /// ```
+    pub fn save_chunk(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        self.save_chunk_inner(
+            chunk_x,
+            chunk_z,
+            chunk_compound_tag,
+            None,
+            self.options.compression,
+        )
+        .map_err(|e| e.with_context(|| self.chunk_context("save_chunk", chunk_x, chunk_z)))
+    }
+
+    /// Saves chunk data to the specified coordinates with the given
+    /// compression instead of the one of the options.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::{Compression, FolderChunkProvider};
+    /// use nbt::CompoundTag;
+    ///
+    /// # let folder = tempfile::tempdir().unwrap();
+    /// # let folder = folder.path();
+    /// let chunk_provider = FolderChunkProvider::new(folder);
+    ///
+    /// chunk_provider
+    ///     .save_chunk_with(1, 2, CompoundTag::new(), Compression::Gzip)
+    ///     .unwrap();
+    ///
+    /// let raw_chunk = chunk_provider.load_chunk_raw(1, 2).unwrap();
+    /// assert_eq!(raw_chunk.compression_scheme(), Compression::Gzip.id());
+    /// ```
+    pub fn save_chunk_with(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+        compression: Compression,
+    ) -> Result<(), ChunkSaveError> {
+        self.save_chunk_inner(chunk_x, chunk_z, chunk_compound_tag, None, compression)
+            .map_err(|e| e.with_context(|| self.chunk_context("save_chunk", chunk_x, chunk_z)))
+    }
+
+    /// Saves chunk data to the specified coordinates, using the given last
+    /// modification time in seconds since the Unix epoch instead of the
+    /// current time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::FolderChunkProvider;
+    /// use nbt::CompoundTag;
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("test/region");
+    /// let mut chunk_compound_tag = CompoundTag::new();
+    /// let mut level_compound_tag = CompoundTag::new();
+    ///
+    /// level_compound_tag.insert_i32("xPos", 31);
+    /// level_compound_tag.insert_i32("zPos", 16);
+    ///
+    /// chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+    ///
+    /// chunk_provider.save_chunk_with_timestamp(31, 16, chunk_compound_tag, 1570215508);
+    /// ```
+    pub fn save_chunk_with_timestamp(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        self.save_chunk_inner(
+            chunk_x,
+            chunk_z,
+            chunk_compound_tag,
+            Some(last_modified_timestamp),
+            self.options.compression,
+        )
+        .map_err(|e| e.with_context(|| self.chunk_context("save_chunk", chunk_x, chunk_z)))
+    }
+
+    /// Context of an error of the given operation on a chunk.
+    fn chunk_context(&self, operation: &'static str, chunk_x: i32, chunk_z: i32) -> ErrorContext {
+        let RegionAndOffset {
+            region_x, region_z, ..
+        } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
+        let region_path = self.folder_path.join(self.region_file_name(region_x, region_z));
+
+        ErrorContext::new(operation, &region_path, Some((chunk_x, chunk_z)))
+    }
+
+    /// Saves the chunk with the given timestamp, or the one chosen by the
+    /// timestamp policy if `None`, and the given compression.
+    fn save_chunk_inner<Q: ChunkPayload>(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        mut chunk_compound_tag: Q,
+        last_modified_timestamp: Option<u32>,
+        compression: Compression,
+    ) -> Result<(), ChunkSaveError> {
+        if self.options.read_only {
+            return Err(ChunkSaveError::ReadOnly);
+        }
+
+        self.options
+            .coordinate_check
+            .apply(chunk_x, chunk_z, &mut chunk_compound_tag)?;
+
+        if self.options.dry_run {
+            let raw_chunk = RawChunk::encode(
+                &chunk_compound_tag,
+                compression,
+                self.options.compression_level,
+            )?;
+
+            return self.dry_run_save(chunk_x, chunk_z, &raw_chunk);
+        }
+
+        self.telemetry.chunk_written();
+
+        if self.options.journal {
+            let raw_chunk = RawChunk::encode(
+                &chunk_compound_tag,
+                compression,
+                self.options.compression_level,
+            )?;
+
+            return self.write_journaled(
+                chunk_x,
+                chunk_z,
+                Some((raw_chunk, last_modified_timestamp)),
+            );
+        }
+
+        self.write_to_region(chunk_x, chunk_z, |region, region_chunk_x, region_chunk_z| {
+            let last_modified_timestamp = last_modified_timestamp.unwrap_or_else(|| {
+                self.policy_timestamp(region.get_metadata(region_chunk_x, region_chunk_z))
+            });
+
+            region.write_chunk_with_compression(
+                region_chunk_x,
+                region_chunk_z,
+                chunk_compound_tag,
+                compression,
+                last_modified_timestamp,
+            )
+        })
+    }
+
+    /// Timestamp of a chunk saved over the chunk with the given metadata,
+    /// chosen by the timestamp policy. New chunks get the current time.
+    fn policy_timestamp(&self, metadata: AnvilChunkMetadata) -> u32 {
+        if metadata.is_empty() {
+            current_timestamp()
+        } else {
+            self.options
+                .timestamp_policy
+                .timestamp(metadata.last_modified_timestamp)
+        }
+    }
+
+    /// Saves already compressed chunk data as is.
+    ///
+    /// When a coordinate check is configured the chunk is decoded, checked
+    /// and saved with the configured compression instead.
+    pub fn save_chunk_raw(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), WorldEditError> {
+        self.save_chunk_raw_inner(chunk_x, chunk_z, raw_chunk, Some(last_modified_timestamp))
+    }
+
+    /// Same as `save_chunk_raw` with the given timestamp, or the one chosen
+    /// by the timestamp policy if `None`.
+    pub(crate) fn save_chunk_raw_inner(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: Option<u32>,
+    ) -> Result<(), WorldEditError> {
+        if self.options.read_only {
+            return Err(ChunkSaveError::ReadOnly.into());
+        }
+
+        let context = || self.chunk_context("save_chunk_raw", chunk_x, chunk_z);
+
+        if self.options.coordinate_check != CoordinateCheck::Disabled {
+            let chunk_compound_tag: P = raw_chunk.decode_payload()?;
+            self.save_chunk_inner(
+                chunk_x,
+                chunk_z,
+                chunk_compound_tag,
+                last_modified_timestamp,
+                self.options.compression,
+            )
+            .map_err(|e| e.with_context(context))?;
+
+            return Ok(());
+        }
+
+        if self.options.dry_run {
+            self.dry_run_save(chunk_x, chunk_z, raw_chunk)
+                .map_err(|e| e.with_context(context))?;
+
+            return Ok(());
+        }
+
+        self.telemetry.chunk_written();
+
+        if self.options.journal {
+            self.write_journaled(
+                chunk_x,
+                chunk_z,
+                Some((raw_chunk.clone(), last_modified_timestamp)),
+            )
+            .map_err(|e| e.with_context(context))?;
+
+            return Ok(());
+        }
+
+        self.write_to_region(chunk_x, chunk_z, |region, region_chunk_x, region_chunk_z| {
+            let last_modified_timestamp = last_modified_timestamp.unwrap_or_else(|| {
+                self.policy_timestamp(region.get_metadata(region_chunk_x, region_chunk_z))
+            });
+
+            region.write_chunk_raw(
+                region_chunk_x,
+                region_chunk_z,
+                raw_chunk,
+                last_modified_timestamp,
+            )
+        })
+        .map_err(|e| e.with_context(context))?;
+
+        Ok(())
+    }
+
+    /// Runs `write` on the region of the chunk with the chunk coordinates
+    /// inside the region, creating the folder and the region file if needed.
+    fn write_to_region<F>(&self, chunk_x: i32, chunk_z: i32, write: F) -> Result<(), ChunkSaveError>
+    where
+        F: FnOnce(&mut AnvilRegion<File>, u8, u8) -> Result<(), ChunkSaveError>,
+    {
+        if !self.folder_path.exists() {
+            fs::create_dir(&self.folder_path)?;
+        }
+
+        let RegionAndOffset {
+            region_x,
+            region_z,
+            region_chunk_x,
+            region_chunk_z,
+        } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
+
+        self.with_region(region_x, region_z, |region| {
+            write(region, region_chunk_x, region_chunk_z)?;
+
+            self.sync_after_write(region)
+        })
+    }
+
+    /// Syncs the region file if required by the sync policy.
+    fn sync_after_write(&self, region: &mut AnvilRegion<File>) -> Result<(), ChunkSaveError> {
+        if self.options.sync_policy == SyncPolicy::EveryWrite {
+            region.file.sync_data()?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the chunk at the specified coordinates, if it exists.
+    ///
+    /// The sectors used by the chunk are marked as free in the region header,
+    /// the region file itself is not truncated unless
+    /// `AnvilOptions::shrink_regions` is enabled.
+    pub fn delete_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError> {
+        if self.options.read_only {
+            return Err(ChunkSaveError::ReadOnly);
+        }
+
+        let RegionAndOffset {
+            region_x,
+            region_z,
+            region_chunk_x,
+            region_chunk_z,
+        } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
+
+        let region_name = self.region_file_name(region_x, region_z);
+        let region_path = self.folder_path.join(region_name);
+
+        if !region_path.exists() && self.compressed_region(region_x, region_z).is_none() {
+            return Ok(());
+        }
+
+        let context = || ErrorContext::new("delete_chunk", &region_path, Some((chunk_x, chunk_z)));
+
+        if self.options.dry_run {
+            return self
+                .dry_run_delete(chunk_x, chunk_z)
+                .map_err(|e| e.with_context(context));
+        }
+
+        if self.options.journal {
+            self.write_journaled(chunk_x, chunk_z, None)
+                .map_err(|e| e.with_context(context))?;
+        } else {
+            self.with_region(region_x, region_z, |region| {
+                region.delete_chunk(region_chunk_x, region_chunk_z)?;
+
+                self.sync_after_write(region)
+            })
+            .map_err(|e: ChunkSaveError| e.with_context(context))?;
+        }
+
+        if self.options.shrink_regions {
+            self.shrink_region(region_x, region_z)
+                .map_err(|e| e.with_context(context))?;
+        }
+
+        Ok(())
+    }
+
+    /// Truncates the free sectors at the end of the region file, removing
+    /// the file if it has no chunk left.
+    fn shrink_region(&self, region_x: i32, region_z: i32) -> Result<(), ChunkSaveError> {
+        let _lock = self.region_locks.lock((region_x, region_z));
+        let is_empty = self.with_locked_region(region_x, region_z, |region| {
+            region.truncate_garbage()?;
+
+            Ok::<_, ChunkSaveError>(region.chunks_metadata.iter().all(|m| m.is_empty()))
+        })?;
+
+        if is_empty {
+            self.remove_empty_region(region_x, region_z)?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the file of a region without chunks, which must be locked.
+    fn remove_empty_region(&self, region_x: i32, region_z: i32) -> Result<(), io::Error> {
+        let region_name = self.region_file_name(region_x, region_z);
+        let region_path = self.folder_path.join(region_name);
+
+        self.region_cache
+            .lock()
+            .unwrap()
+            .remove((region_x, region_z))?;
+
+        // A removed region has nothing left to replay.
+        let journal_path = self.journal_path(region_x, region_z);
+
+        if journal_path.exists() {
+            fs::remove_file(journal_path)?;
+        }
+
+        // A recompressed region only has its compressed file left.
+        match self.compressed_region(region_x, region_z) {
+            Some((compressed_path, _)) => fs::remove_file(compressed_path),
+            None => fs::remove_file(region_path),
+        }
+    }
+
+    /// Deletes every selected chunk for which the predicate returns true,
+    /// using only the region headers. Returns the amount of deleted chunks.
+    ///
+    /// Region files left without chunks are removed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use anvil_region::{ChunkSelection, FolderChunkProvider};
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("world/region");
+    ///
+    /// // Delete every chunk that was not modified since 2020.
+    /// chunk_provider
+    ///     .delete_chunks_where(&ChunkSelection::All, |_, metadata| {
+    ///         metadata.last_modified_timestamp() < 1577836800
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn delete_chunks_where<F>(
+        &self,
+        selection: &ChunkSelection,
+        mut predicate: F,
+    ) -> Result<usize, WorldEditError>
+    where
+        F: FnMut((i32, i32), &AnvilChunkMetadata) -> bool,
+    {
+        self.delete_chunks_in_regions(selection, |coords, metadata, _| {
+            Ok(predicate(coords, metadata))
+        })
+    }
+
+    /// Same as `delete_chunks_where`, but every selected chunk is decoded so
+    /// the predicate can also inspect the chunk tags, for example its
+    /// `InhabitedTime` or `Status`.
+    pub fn delete_chunks_where_tag<F>(
+        &self,
+        selection: &ChunkSelection,
+        mut predicate: F,
+    ) -> Result<usize, WorldEditError>
+    where
+        F: FnMut((i32, i32), &AnvilChunkMetadata, &P) -> bool,
+    {
+        self.delete_chunks_in_regions(selection, |coords, metadata, region_file| {
+            let (region_chunk_x, region_chunk_z) = chunk_coords_inside_region(coords.0, coords.1);
+            let mut buffer = Vec::new();
+            let compression_scheme = read_chunk_data(
+                region_file,
+                *metadata,
+                region_chunk_x,
+                region_chunk_z,
+                &mut buffer,
+            )?;
+            let chunk_compound_tag = P::decode(compression_scheme, &buffer)?;
+
+            Ok(predicate(coords, metadata, &chunk_compound_tag))
+        })
+    }
+
+    fn delete_chunks_in_regions<F>(
+        &self,
+        selection: &ChunkSelection,
+        mut should_delete: F,
+    ) -> Result<usize, WorldEditError>
+    where
+        F: FnMut(
+            (i32, i32),
+            &AnvilChunkMetadata,
+            &mut dyn ReadAndSeek,
+        ) -> Result<bool, WorldEditError>,
+    {
+        if self.options.read_only {
+            return Err(ChunkSaveError::ReadOnly.into());
+        }
+
+        let regions = self.find_all_region_mca()?;
+
+        if self.options.dry_run {
+            return self.dry_run_delete_chunks(regions, selection, should_delete);
+        }
+
+        let mut deleted = 0;
+
+        for (region_x, region_z) in regions {
+            if !selection.may_contain_region(region_x, region_z) {
+                continue;
+            }
+
+            // Locked until the empty region file is removed.
+            let _lock = self.region_locks.lock((region_x, region_z));
+            let is_empty = self.with_locked_region(region_x, region_z, |region| {
+                let mut matching = Vec::new();
+
+                for region_chunk_z in 0..32 {
+                    for region_chunk_x in 0..32 {
+                        let metadata = region.get_metadata(region_chunk_x, region_chunk_z);
+                        let chunk_x = (region_x * 32) + i32::from(region_chunk_x);
+                        let chunk_z = (region_z * 32) + i32::from(region_chunk_z);
+
+                        if metadata.is_empty() || !selection.contains(chunk_x, chunk_z) {
+                            continue;
+                        }
+
+                        if should_delete((chunk_x, chunk_z), &metadata, &mut region.file)? {
+                            matching.push((region_chunk_x, region_chunk_z));
+                        }
+                    }
+                }
+
+                if self.options.journal {
+                    self.delete_journaled(region_x, region_z, region, &matching)?;
+                } else {
+                    for &(region_chunk_x, region_chunk_z) in &matching {
+                        region
+                            .delete_chunk(region_chunk_x, region_chunk_z)
+                            .map_err(ChunkSaveError::from)?;
+                    }
+                }
+
+                deleted += matching.len();
+
+                if self.options.shrink_regions {
+                    region.truncate_garbage().map_err(ChunkSaveError::from)?;
+                }
+
+                self.sync_after_write(region)?;
+
+                Ok::<_, WorldEditError>(region.chunks_metadata.iter().all(|m| m.is_empty()))
+            })?;
+
+            if is_empty {
+                self.remove_empty_region(region_x, region_z)
+                    .map_err(ChunkSaveError::from)?;
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    // Find all the region files in the current folder
+    fn find_all_region_mca(&self) -> Result<Vec<(i32, i32)>, std::io::Error> {
+        let mut r = vec![];
+        let mut compressed = HashSet::new();
+
+        for entry in std::fs::read_dir(&self.folder_path)? {
+            let entry = entry?;
+            let path = entry.path();
+            let filename = path.file_name().and_then(|x| x.to_str());
+            if filename.is_none() {
+                continue;
+            }
+
+            let naming = self.region_naming();
+
+            if let Some(coords) = naming.parse_region_name(filename.unwrap()) {
+                r.push(coords);
+            } else if let Some((coords, _)) = parse_compressed_name(naming, filename.unwrap()) {
+                compressed.insert(coords);
+            }
+        }
+
+        // Regions only stored compressed.
+        for coords in &r {
+            compressed.remove(coords);
+        }
+        r.extend(compressed);
+
+        self.options.listing_order.sort_regions(&mut r);
+
+        Ok(r)
+    }
+
+    /// Writes the header updates kept in memory by the region, if it is
+    /// open, before its file is read directly.
+    pub(crate) fn write_open_header(&self, region_x: i32, region_z: i32) -> Result<(), io::Error> {
+        self.region_cache
+            .lock()
+            .unwrap()
+            .write_header((region_x, region_z))
+    }
+
+    /// Opens the region file for `ChunkReader::get_region`.
+    fn region_reader(&self, region_x: i32, region_z: i32) -> Result<RegionReader, ChunkLoadError> {
+        let region_name = self.region_file_name(region_x, region_z);
+        let region_path = self.folder_path.join(region_name);
+        self.write_open_header(region_x, region_z)?;
+
+        if let Some(bytes) = self.compressed_region_bytes(region_x, region_z) {
+            return Ok(Box::new(io::Cursor::new(bytes?)));
+        }
+
+        if !region_path.exists() {
+            return Err(ChunkLoadError::RegionNotFound { region_x, region_z });
+        }
+
+        let file = File::open(region_path)?;
+        self.telemetry.region_opened();
+
+        Ok(Box::new(file))
+    }
+
+    pub fn list_chunks(&self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.list_selected_chunks(&ChunkSelection::All)
+    }
+
+    /// Same as `ChunkReader::list_chunks_in`, without borrowing the
+    /// provider mutably.
+    pub fn list_chunks_in(
+        &self,
+        selection: &ChunkSelection,
+    ) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.list_selected_chunks(selection)
+    }
+
+    /// Same as `ChunkReader::list_regions`, without borrowing the provider
+    /// mutably.
+    pub fn list_regions(&self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.find_all_region_mca().map_err(|io_error| {
+            ChunkLoadError::ReadError { io_error }
+                .with_context(|| ErrorContext::new("list_regions", &self.folder_path, None))
+        })
+    }
+
+    /// Whether the chunk is stored, read from the header of its region
+    /// without loading the chunk.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::FolderChunkProvider;
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("test/region");
+    ///
+    /// assert!(chunk_provider.chunk_exists(4, 2).unwrap());
+    /// assert!(!chunk_provider.chunk_exists(28, 0).unwrap());
+    /// assert!(!chunk_provider.chunk_exists(1000, 0).unwrap());
+    /// ```
+    pub fn chunk_exists(&self, chunk_x: i32, chunk_z: i32) -> Result<bool, ChunkLoadError> {
+        let RegionAndOffset {
+            region_x,
+            region_z,
+            region_chunk_x,
+            region_chunk_z,
+        } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
+
+        let mut region = match self.region_reader(region_x, region_z) {
+            Ok(region) => region,
+            Err(ChunkLoadError::RegionNotFound { .. }) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        let header = AnvilRegionHeader::from_reader(&mut region)?;
+        self.telemetry.header_read();
+
+        Ok(header.chunk_exists(region_chunk_x, region_chunk_z))
+    }
+
+    /// Lists the chunks in the selection, in the listing order of the
+    /// options.
+    fn list_selected_chunks(
+        &self,
+        selection: &ChunkSelection,
+    ) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        let regions = self.find_all_region_mca().map_err(|io_error| {
+            ChunkLoadError::ReadError { io_error }
+                .with_context(|| ErrorContext::new("list_chunks", &self.folder_path, None))
+        })?;
+        let mut c = vec![];
+        for (region_x, region_z) in regions {
+            if !selection.may_contain_region(region_x, region_z) {
+                continue;
+            }
+
+            let region_name = self.region_file_name(region_x, region_z);
+            let region_path = self.folder_path.join(region_name);
+            self.write_open_header(region_x, region_z)?;
+
+            let header = match self.compressed_region_bytes(region_x, region_z) {
+                Some(bytes) => {
+                    bytes.and_then(|bytes| AnvilRegionHeader::from_reader(&mut bytes.as_ref()))
+                }
+                None => AnvilRegionHeader::read(&region_path),
+            };
+            self.telemetry.header_read();
+            let header = header.map_err(|io_error| {
+                ChunkLoadError::ReadError { io_error }
+                    .with_context(|| ErrorContext::new("list_chunks", &region_path, None))
+            })?;
+
+            // Insert all the non-empty chunks from this region
+            for ((region_chunk_x, region_chunk_z), _) in header.chunks() {
+                let chunk_x = (region_x * 32) + i32::from(region_chunk_x);
+                let chunk_z = (region_z * 32) + i32::from(region_chunk_z);
+
+                if selection.contains(chunk_x, chunk_z) {
+                    c.push((chunk_x, chunk_z));
+                }
+            }
+        }
+
+        self.options.listing_order.sort_chunks(&mut c);
+
+        Ok(c)
+    }
+}
+
+impl<P: ChunkPayload> ChunkReader<P> for FolderChunkProvider<P> {
+    fn get_region(&mut self, region_x: i32, region_z: i32) -> Result<RegionReader, ChunkLoadError> {
+        self.region_reader(region_x, region_z)
+    }
+    fn load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        FolderChunkProvider::load_chunk(self, chunk_x, chunk_z)
+    }
+    fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        FolderChunkProvider::list_chunks(self)
+    }
+    fn list_regions(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        FolderChunkProvider::list_regions(self)
+    }
+    fn list_regions_detailed(&mut self) -> Result<Vec<RegionInfo>, ChunkLoadError> {
+        FolderChunkProvider::list_regions_detailed(self)
+    }
+    fn list_chunks_in(
+        &mut self,
+        selection: &ChunkSelection,
+    ) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        FolderChunkProvider::list_chunks_in(self, selection)
+    }
+    fn load_chunk_raw(&mut self, chunk_x: i32, chunk_z: i32) -> Result<RawChunk, ChunkLoadError> {
+        FolderChunkProvider::load_chunk_raw(self, chunk_x, chunk_z)
+    }
+}
+
+/// Reads chunks through a shared reference, so that several threads can use
+/// the same provider with functions taking a `ChunkReader`. Loads are
+/// synchronized by the provider.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{world_report, FolderChunkProvider};
+/// use std::thread;
+///
+/// let chunk_provider = FolderChunkProvider::new("test/region").with_max_open_regions(8);
+///
+/// thread::scope(|scope| {
+///     for _ in 0..2 {
+///         scope.spawn(|| {
+///             let report = world_report(&mut &chunk_provider).unwrap();
+///             assert_eq!(report.chunks, 277);
+///         });
+///     }
+/// });
+/// ```
+impl<P: ChunkPayload> ChunkReader<P> for &FolderChunkProvider<P> {
+    fn get_region(&mut self, region_x: i32, region_z: i32) -> Result<RegionReader, ChunkLoadError> {
+        self.region_reader(region_x, region_z)
+    }
+    fn load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        FolderChunkProvider::load_chunk(self, chunk_x, chunk_z)
+    }
+    fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        FolderChunkProvider::list_chunks(self)
+    }
+    fn list_regions(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        FolderChunkProvider::list_regions(self)
+    }
+    fn list_regions_detailed(&mut self) -> Result<Vec<RegionInfo>, ChunkLoadError> {
+        FolderChunkProvider::list_regions_detailed(self)
+    }
+    fn list_chunks_in(
+        &mut self,
+        selection: &ChunkSelection,
+    ) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        FolderChunkProvider::list_chunks_in(self, selection)
+    }
+    fn load_chunk_raw(&mut self, chunk_x: i32, chunk_z: i32) -> Result<RawChunk, ChunkLoadError> {
+        FolderChunkProvider::load_chunk_raw(self, chunk_x, chunk_z)
+    }
+}
+
+impl<P: ChunkPayload> ChunkWriter<P> for FolderChunkProvider<P> {
+    fn save_chunk_with_timestamp(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        FolderChunkProvider::save_chunk_with_timestamp(
+            self,
+            chunk_x,
+            chunk_z,
+            chunk_compound_tag,
+            last_modified_timestamp,
+        )
+    }
+    fn save_chunk(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        FolderChunkProvider::save_chunk(self, chunk_x, chunk_z, chunk_compound_tag)
+    }
+    fn save_chunk_raw(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), WorldEditError> {
+        FolderChunkProvider::save_chunk_raw(
+            self,
+            chunk_x,
+            chunk_z,
+            raw_chunk,
+            last_modified_timestamp,
+        )
+    }
+}
+
+impl<P: ChunkPayload> ChunkDeleter<P> for FolderChunkProvider<P> {
+    fn delete_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError> {
+        FolderChunkProvider::delete_chunk(self, chunk_x, chunk_z)
+    }
+}
+
+/// Region represents a 32x32 group of chunks.
+pub struct AnvilRegion<F> {
+    /// File in which region are stored.
+    file: F,
+    /// Array of chunks metadata.
+    chunks_metadata: [AnvilChunkMetadata; REGION_CHUNKS],
+    /// Used sectors for chunks data.
+    used_sectors: BitVec,
+    /// Scratch buffer for compressed chunk data, reused across reads and
+    /// writes so bulk operations don't allocate for every chunk.
+    buffer: Vec<u8>,
+    /// Strategy used to find sectors for written chunks.
+    sector_allocation: SectorAllocation,
+    /// Compression of written chunks.
+    compression: Compression,
+    /// Compression level of written chunks.
+    compression_level: u32,
+    /// Whether freed sectors are recorded to punch holes over them.
+    punch_holes: bool,
+    /// Runs of sectors freed since holes were last punched, as
+    /// `(first sector index, amount of sectors)`.
+    freed_sectors: Vec<(u32, u32)>,
+    /// Whether header updates are kept in memory until the end of a
+    /// `write_batch`.
+    batching: bool,
+    /// Whether header updates are kept in memory until `flush`.
+    defer_header: bool,
+    /// Whether the header in memory differs from the file.
+    header_dirty: bool,
+    /// Chunk sectors released while the header was not written, which the
+    /// header in the file may still point at. They stay used until the
+    /// header is written.
+    pending_sectors: Vec<AnvilChunkMetadata>,
+}
+
+/// Header of a region file, read without opening the region for writing.
+///
+/// Cheaper than `AnvilRegion` when only the chunk occupancy or the
+/// timestamps are needed.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::AnvilRegionHeader;
+///
+/// let header = AnvilRegionHeader::read("test/region/r.0.0.mca").unwrap();
+///
+/// assert!(header.chunk_exists(4, 2));
+/// assert!(!header.chunk_exists(15, 14));
+/// assert_eq!(header.chunks().count(), 277);
+/// ```
+#[derive(Clone)]
+pub struct AnvilRegionHeader {
+    /// Array of chunks metadata.
+    chunks_metadata: [AnvilChunkMetadata; REGION_CHUNKS],
+}
+
+impl AnvilRegionHeader {
+    /// Reads the header of the region file at the given path.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let mut file = File::open(path)?;
+
+        Self::from_reader(&mut file)
+    }
+
+    /// Reads a region header from the current position of the reader.
+    pub fn from_reader<R: Read + ?Sized>(reader: &mut R) -> Result<Self, io::Error> {
+        let chunks_metadata = anvil_region::read_header(reader)?;
+
+        Ok(AnvilRegionHeader { chunks_metadata })
+    }
+
+    /// Returns chunk metadata at specified coordinates.
+    pub fn get_metadata(&self, chunk_x: u8, chunk_z: u8) -> AnvilChunkMetadata {
+        self.chunks_metadata[anvil_region::metadata_index(chunk_x, chunk_z)]
+    }
+
+    /// Returns true if a chunk is stored at the specified coordinates.
+    pub fn chunk_exists(&self, chunk_x: u8, chunk_z: u8) -> bool {
+        !self.get_metadata(chunk_x, chunk_z).is_empty()
+    }
+
+    /// Last time in seconds since the Unix epoch when the chunk at the
+    /// specified coordinates was modified.
+    pub fn last_modified_timestamp(&self, chunk_x: u8, chunk_z: u8) -> u32 {
+        self.get_metadata(chunk_x, chunk_z).last_modified_timestamp
+    }
+
+    /// Iterates over the coordinates inside the region and the metadata of
+    /// all the non-empty chunks.
+    pub fn chunks(&self) -> impl Iterator<Item = ((u8, u8), AnvilChunkMetadata)> + '_ {
+        self.chunks_metadata
+            .iter()
+            .enumerate()
+            .filter(|(_, metadata)| !metadata.is_empty())
+            .map(|(index, metadata)| (((index % 32) as u8, (index / 32) as u8), *metadata))
+    }
+}
+
+impl fmt::Debug for AnvilRegionHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnvilRegionHeader")
+            .field("chunks", &self.chunks().count())
+            .finish()
+    }
+}
+
+/// Chunk metadata are stored in header.
+#[derive(Copy, Clone, Default, Debug, Eq, PartialEq)]
+pub struct AnvilChunkMetadata {
+    /// Sector index from which starts chunk data.
+    sector_index: u32,
+    /// Amount of sectors used to store chunk.
+    sectors: u8,
+    /// Last time in seconds when chunk was modified.
     last_modified_timestamp: u32,
 }
 
-impl AnvilChunkMetadata {
-    fn new(sector_index: u32, sectors: u8, last_modified_timestamp: u32) -> Self {
-        AnvilChunkMetadata {
-            sector_index,
-            sectors,
-            last_modified_timestamp,
+impl AnvilChunkMetadata {
+    fn new(sector_index: u32, sectors: u8, last_modified_timestamp: u32) -> Self {
+        AnvilChunkMetadata {
+            sector_index,
+            sectors,
+            last_modified_timestamp,
+        }
+    }
+
+    /// Sector index from which starts chunk data.
+    pub fn sector_index(&self) -> u32 {
+        self.sector_index
+    }
+
+    /// Amount of sectors used to store chunk.
+    pub fn sectors(&self) -> u8 {
+        self.sectors
+    }
+
+    /// Last time in seconds since the Unix epoch when chunk was modified.
+    pub fn last_modified_timestamp(&self) -> u32 {
+        self.last_modified_timestamp
+    }
+
+    /// Returns true if no chunk is stored at these coordinates.
+    pub fn is_empty(&self) -> bool {
+        self.sectors == 0
+    }
+}
+
+/// How the last modification time of written chunks is set.
+///
+/// Applies to the chunks saved by a provider, see
+/// `AnvilOptions::timestamp_policy`, and to the chunks copied by operations
+/// taking a policy of their own, like `shift_world` or `compact_all`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum TimestampPolicy {
+    /// Keep the timestamp from the header of the source chunk, or of the
+    /// chunk being overwritten.
+    Preserve,
+    /// Stamp every chunk with the current time.
+    #[default]
+    Refresh,
+}
+
+impl TimestampPolicy {
+    /// Timestamp to write for a chunk whose source timestamp is `original`.
+    pub fn timestamp(self, original: u32) -> u32 {
+        match self {
+            TimestampPolicy::Preserve => original,
+            TimestampPolicy::Refresh => current_timestamp(),
+        }
+    }
+}
+
+/// Where `AnvilRegion` puts chunk data that doesn't fit in its old sectors.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum SectorAllocation {
+    /// Use the first gap large enough. Chunks that still fit in the same
+    /// amount of sectors are rewritten in place.
+    #[default]
+    FirstFit,
+    /// Use the smallest gap large enough, which keeps large gaps available
+    /// and the file smaller. Chunks that still fit in the same amount of
+    /// sectors are rewritten in place.
+    BestFit,
+    /// Always write after the last used sector, never filling gaps nor
+    /// overwriting the old data in place. Writes are sequential at the cost
+    /// of a growing file, which can be compacted afterwards.
+    AppendOnly,
+}
+
+/// Current time in seconds since the Unix epoch, as stored in the region header.
+fn current_timestamp() -> u32 {
+    let system_time = SystemTime::now();
+    let time = system_time.duration_since(UNIX_EPOCH).unwrap();
+
+    time.as_secs() as u32
+}
+
+pub mod anvil_region {
+    use crate::{AnvilChunkMetadata, REGION_CHUNKS, REGION_CHUNKS_METADATA_LENGTH};
+    use bitvec::prelude::*;
+    use byteorder::{BigEndian, ByteOrder};
+    use std::io;
+    use std::io::Read;
+
+    /// First 8KB of file are header of 1024 offsets and 1024 timestamps.
+    ///
+    /// The whole header is read with a single call to the reader.
+    pub fn read_header<R: Read + ?Sized>(
+        reader: &mut R,
+    ) -> Result<[AnvilChunkMetadata; REGION_CHUNKS], io::Error> {
+        let mut chunks_metadata = [Default::default(); REGION_CHUNKS];
+        let mut buffer = [0u8; REGION_CHUNKS_METADATA_LENGTH * 4];
+
+        // The whole header in a single read.
+        reader.read_exact(&mut buffer)?;
+        let (offsets, timestamps) = buffer.split_at(REGION_CHUNKS * 4);
+
+        for index in 0..REGION_CHUNKS {
+            let last_modified_timestamp = BigEndian::read_u32(&timestamps[index * 4..]);
+            let offset = BigEndian::read_u32(&offsets[index * 4..]);
+
+            let sector_index = offset >> 8;
+            let sectors = (offset & 0xFF) as u8;
+
+            let metadata = AnvilChunkMetadata::new(sector_index, sectors, last_modified_timestamp);
+            chunks_metadata[index] = metadata;
+        }
+
+        Ok(chunks_metadata)
+    }
+
+    pub fn metadata_index(chunk_x: u8, chunk_z: u8) -> usize {
+        assert!(32 > chunk_x, "Region chunk x coordinate out of bounds");
+        assert!(32 > chunk_z, "Region chunk y coordinate out of bounds");
+
+        chunk_x as usize + chunk_z as usize * 32
+    }
+
+    /// Calculates used sectors.
+    pub fn used_sectors(total_sectors: u32, chunks_metadata: &[AnvilChunkMetadata]) -> BitVec {
+        let mut used_sectors = bitvec![0; total_sectors as usize];
+
+        used_sectors.set(0, true);
+        used_sectors.set(1, true);
+
+        for metadata in chunks_metadata {
+            if metadata.is_empty() {
+                continue;
+            }
+
+            // Sectors past the end of the file are ignored, reading such a
+            // chunk fails instead.
+            let start_index = (metadata.sector_index as usize).min(used_sectors.len());
+            let end_index = (start_index + metadata.sectors as usize).min(used_sectors.len());
+
+            for index in start_index..end_index {
+                used_sectors.set(index, true);
+            }
+        }
+
+        used_sectors
+    }
+}
+
+/// Amount of sectors needed to store chunk data of a given length.
+///
+/// Lengths are checked against `CHUNK_MAXIMUM_BYTES_LENGTH` first, so the
+/// result always fits in the header byte.
+fn sectors_required(chunk_length: u32) -> u8 {
+    let sector_length = REGION_SECTOR_BYTES_LENGTH as u32;
+
+    chunk_length.div_ceil(sector_length) as u8
+}
+
+/// Amount of zeroes needed after chunk data of a given length to reach the
+/// sector boundary.
+fn sector_padding(chunk_length: u32) -> usize {
+    let sector_length = REGION_SECTOR_BYTES_LENGTH as u32;
+
+    ((sector_length - chunk_length % sector_length) % sector_length) as usize
+}
+
+/// Reads the length stored before the data of the chunk described by the
+/// metadata, which counts the compression scheme and the compressed data.
+///
+/// The length is not checked against the allocated sectors.
+fn read_chunk_length<R: Read + Seek + ?Sized>(
+    reader: &mut R,
+    metadata: AnvilChunkMetadata,
+) -> Result<u32, io::Error> {
+    let seek_offset = metadata.sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+    let mut length = [0u8; 4];
+
+    reader.seek(SeekFrom::Start(seek_offset))?;
+    reader.read_exact(&mut length)?;
+
+    Ok(BigEndian::read_u32(&length))
+}
+
+/// Reads the data of the chunk described by the metadata into the buffer,
+/// returning its compression scheme.
+fn read_chunk_data<R: Read + Seek + ?Sized>(
+    reader: &mut R,
+    metadata: AnvilChunkMetadata,
+    chunk_x: u8,
+    chunk_z: u8,
+    buffer: &mut Vec<u8>,
+) -> Result<u8, ChunkLoadError> {
+    if metadata.is_empty() {
+        return Err(ChunkLoadError::ChunkNotFound { chunk_x, chunk_z });
+    }
+
+    let seek_offset = metadata.sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+    // Length doesn't count its own 4 bytes.
+    let maximum_length = metadata.sectors as u32 * REGION_SECTOR_BYTES_LENGTH as u32 - 4;
+
+    // Length and compression scheme are read together.
+    let mut chunk_header = [0u8; 5];
+    reader.seek(SeekFrom::Start(seek_offset))?;
+    reader.read_exact(&mut chunk_header)?;
+
+    let length = BigEndian::read_u32(&chunk_header[..4]);
+    let compression_scheme = chunk_header[4];
+
+    if length > maximum_length {
+        return Err(ChunkLoadError::LengthExceedsMaximum {
+            length,
+            maximum_length,
+        });
+    }
+
+    if length == 0 {
+        return Err(ChunkLoadError::EmptyChunkData);
+    }
+
+    buffer.clear();
+    buffer.resize((length - 1) as usize, 0);
+    reader.read_exact(buffer)?;
+
+    Ok(compression_scheme)
+}
+
+fn stream_len<S: Seek>(file: &mut S) -> Result<u64, io::Error> {
+    let old_pos = file.stream_position()?;
+    let len = file.seek(SeekFrom::End(0))?;
+
+    // Avoid seeking a third time when we were already at the end of the
+    // stream. The branch is usually way cheaper than a seek operation.
+    if old_pos != len {
+        file.seek(SeekFrom::Start(old_pos))?;
+    }
+
+    Ok(len)
+}
+
+fn stream_set_len<S: Seek + Write>(file: &mut S, new_len: u64) -> Result<u64, io::Error> {
+    let old_pos = file.stream_position()?;
+    let len = file.seek(SeekFrom::Start(new_len - 1))? + 1;
+
+    // Actually write so the stream len changes
+    file.write_all(&[0])?;
+    // Avoid seeking a third time when we were already at the end of the
+    // stream. The branch is usually way cheaper than a seek operation.
+    if old_pos != len {
+        file.seek(SeekFrom::Start(old_pos))?;
+    }
+
+    Ok(len)
+}
+
+impl AnvilRegion<File> {
+    pub fn file<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let file = OpenOptions::new()
+            .write(true)
+            .read(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        Self::new(file)
+    }
+
+    /// Opens the region file for reading only, writing chunks fails.
+    ///
+    /// Unlike `file`, the file is not created if missing nor extended to
+    /// the length of the header: files shorter than the header are read as
+    /// regions without chunks.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::AnvilRegion;
+    ///
+    /// let mut region = AnvilRegion::file_read_only("test/region/r.0.0.mca").unwrap();
+    ///
+    /// assert!(region.read_chunk(4, 2).is_ok());
+    /// assert!(AnvilRegion::file_read_only("test/region/r.9.9.mca").is_err());
+    /// ```
+    pub fn file_read_only<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let mut file = File::open(path)?;
+
+        let chunks_metadata = if file.metadata()?.len() < REGION_HEADER_BYTES_LENGTH {
+            [AnvilChunkMetadata::default(); REGION_CHUNKS]
+        } else {
+            anvil_region::read_header(&mut file)?
+        };
+
+        Ok(Self::with_header(file, chunks_metadata))
+    }
+}
+
+impl<F> AnvilRegion<F> {
+    /// Region over the file with the given header, with default settings.
+    fn with_header(file: F, chunks_metadata: [AnvilChunkMetadata; REGION_CHUNKS]) -> Self {
+        AnvilRegion {
+            file,
+            chunks_metadata,
+            // Built by the first write, reads don't need it.
+            used_sectors: BitVec::new(),
+            buffer: Vec::new(),
+            sector_allocation: SectorAllocation::default(),
+            compression: Compression::default(),
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            punch_holes: false,
+            freed_sectors: Vec::new(),
+            batching: false,
+            defer_header: false,
+            header_dirty: false,
+            pending_sectors: Vec::new(),
+        }
+    }
+}
+
+impl<F: Seek + Read + Write> AnvilRegion<F> {
+    pub fn new(mut file: F) -> Result<Self, io::Error> {
+        // If necessary, extend the file length to the length of the header.
+        if REGION_HEADER_BYTES_LENGTH > stream_len(&mut file)? {
+            stream_set_len(&mut file, REGION_HEADER_BYTES_LENGTH)?;
+        }
+
+        let chunks_metadata = anvil_region::read_header(&mut file)?;
+
+        Ok(Self::with_header(file, chunks_metadata))
+    }
+
+    /// Sets the compression and compression level, from 0 to 9, of written
+    /// chunks.
+    pub fn with_compression(mut self, compression: Compression, compression_level: u32) -> Self {
+        self.compression = compression;
+        self.compression_level = compression_level.min(9);
+        self
+    }
+
+    /// Sets the strategy used to find sectors for written chunks.
+    pub fn with_sector_allocation(mut self, sector_allocation: SectorAllocation) -> Self {
+        self.sector_allocation = sector_allocation;
+        self
+    }
+
+    /// Records the sectors freed by deleted and moved chunks, so that
+    /// `punch_freed_sectors` releases their disk space.
+    pub fn with_punch_holes(mut self, punch_holes: bool) -> Self {
+        self.punch_holes = punch_holes;
+        self
+    }
+
+    /// Keeps header updates in memory until `flush`, like in a
+    /// `write_batch` that lasts until the region is flushed.
+    pub fn with_deferred_header(mut self, defer_header: bool) -> Self {
+        self.defer_header = defer_header;
+        self
+    }
+
+    /// Approximate memory used by the region, without the file.
+    pub(crate) fn memory_bytes(&self) -> u64 {
+        (mem::size_of::<Self>() + self.used_sectors.capacity() / 8 + self.buffer.capacity()) as u64
+    }
+
+    /// Consumes the region, returning the underlying file.
+    pub fn into_inner(self) -> F {
+        self.file
+    }
+
+    /// Writes the header updates pending in a `write_batch` or deferred by
+    /// `with_deferred_header`, then flushes the underlying file.
+    pub fn flush(&mut self) -> Result<(), io::Error> {
+        self.write_pending_header()?;
+
+        self.file.flush()
+    }
+
+    /// Runs `f` with header updates kept in memory, then writes the offset
+    /// and timestamp tables at once, so that saving many chunks doesn't
+    /// write the header for every chunk.
+    ///
+    /// The header is written even if `f` fails, so it always matches the
+    /// chunks written to the file. Until then, chunks are never rewritten
+    /// in place and the sectors they are moved from are not reused, so a
+    /// crash during the batch leaves the previous header pointing at the
+    /// previous chunks. Nested batches are part of the outer one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::{AnvilRegion, ChunkSaveError};
+    /// use nbt::CompoundTag;
+    /// use std::io::Cursor;
+    ///
+    /// let mut region = AnvilRegion::new(Cursor::new(Vec::new())).unwrap();
+    ///
+    /// region
+    ///     .write_batch(|region| {
+    ///         for chunk_x in 0..32 {
+    ///             region.write_chunk(chunk_x, 0, CompoundTag::new())?;
+    ///         }
+    ///
+    ///         Ok::<_, ChunkSaveError>(())
+    ///     })
+    ///     .unwrap();
+    ///
+    /// let bytes = region.into_inner().into_inner();
+    /// let mut region = AnvilRegion::new(Cursor::new(bytes)).unwrap();
+    /// assert!(region.read_chunk(31, 0).is_ok());
+    /// ```
+    pub fn write_batch<T, E, B>(&mut self, f: B) -> Result<T, E>
+    where
+        E: From<io::Error>,
+        B: FnOnce(&mut Self) -> Result<T, E>,
+    {
+        if self.batching {
+            return f(self);
+        }
+
+        self.batching = true;
+        let result = f(self);
+        self.batching = false;
+
+        if !self.defer_header {
+            self.write_pending_header()?;
+        }
+
+        result
+    }
+
+    /// Whether header updates are kept in memory instead of being written.
+    fn header_deferred(&self) -> bool {
+        self.batching || self.defer_header
+    }
+
+    /// Writes the header if it differs from the file.
+    fn write_pending_header(&mut self) -> Result<(), io::Error> {
+        if self.header_dirty {
+            self.write_header()?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the offset and timestamp tables of the header in one write,
+    /// then releases the sectors the previous header pointed at.
+    fn write_header(&mut self) -> Result<(), io::Error> {
+        let mut header = Vec::with_capacity(REGION_HEADER_BYTES_LENGTH as usize);
+
+        for metadata in self.chunks_metadata.iter() {
+            let offset = (metadata.sector_index << 8) | metadata.sectors as u32;
+            header.extend_from_slice(&offset.to_be_bytes());
+        }
+
+        for metadata in self.chunks_metadata.iter() {
+            header.extend_from_slice(&metadata.last_modified_timestamp.to_be_bytes());
+        }
+
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header)?;
+        self.header_dirty = false;
+
+        for metadata in mem::take(&mut self.pending_sectors) {
+            self.free_sectors(metadata);
+        }
+
+        Ok(())
+    }
+
+    pub fn read_chunk(&mut self, chunk_x: u8, chunk_z: u8) -> Result<CompoundTag, ChunkLoadError> {
+        self.read_chunk_payload(chunk_x, chunk_z)
+    }
+
+    /// Reads the chunk decoded as `P`.
+    pub fn read_chunk_payload<P: ChunkPayload>(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+    ) -> Result<P, ChunkLoadError> {
+        let metadata = self.get_metadata(chunk_x, chunk_z);
+        let compression_scheme =
+            read_chunk_data(&mut self.file, metadata, chunk_x, chunk_z, &mut self.buffer)?;
+
+        P::decode(compression_scheme, &self.buffer)
+    }
+
+    /// Reads the chunk data without decompressing it.
+    pub fn read_chunk_raw(&mut self, chunk_x: u8, chunk_z: u8) -> Result<RawChunk, ChunkLoadError> {
+        let metadata = self.get_metadata(chunk_x, chunk_z);
+        let compression_scheme =
+            read_chunk_data(&mut self.file, metadata, chunk_x, chunk_z, &mut self.buffer)?;
+
+        Ok(RawChunk::new(compression_scheme, self.buffer.clone()))
+    }
+
+    /// Writes chunk data, stamping it with the current time.
+    pub fn write_chunk<P: ChunkPayload>(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        self.write_chunk_with_timestamp(chunk_x, chunk_z, chunk_compound_tag, current_timestamp())
+    }
+
+    /// Writes chunk data, stamping it with the given last modification time
+    /// in seconds since the Unix epoch.
+    pub fn write_chunk_with_timestamp<P: ChunkPayload>(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+        chunk_compound_tag: P,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        self.write_chunk_with_compression(
+            chunk_x,
+            chunk_z,
+            chunk_compound_tag,
+            self.compression,
+            last_modified_timestamp,
+        )
+    }
+
+    /// Writes chunk data with the given compression instead of the one of
+    /// the region, stamping it with the given last modification time.
+    pub fn write_chunk_with_compression<P: ChunkPayload>(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+        chunk_compound_tag: P,
+        compression: Compression,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        // Taken so the region can be borrowed mutably while the buffer is
+        // in use, and put back even if the write fails.
+        let mut buffer = std::mem::take(&mut self.buffer);
+
+        // 4 bytes for data length, filled in once the data is compressed.
+        buffer.clear();
+        buffer.extend_from_slice(&[0; 4]);
+        buffer.push(compression.id());
+
+        let result = match chunk_compound_tag.encode(
+            &mut buffer,
+            compression,
+            self.compression_level,
+        ) {
+            Ok(()) => self.write_chunk_buffer(&mut buffer, chunk_x, chunk_z, last_modified_timestamp),
+            Err(e) => Err(e.into()),
+        };
+        self.buffer = buffer;
+
+        result
+    }
+
+    /// Writes already compressed chunk data as is, stamping it with the given
+    /// last modification time in seconds since the Unix epoch.
+    pub fn write_chunk_raw(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+
+        buffer.clear();
+        buffer.extend_from_slice(&[0; 4]);
+        buffer.push(raw_chunk.compression_scheme());
+        buffer.extend_from_slice(raw_chunk.data());
+
+        let result = self.write_chunk_buffer(&mut buffer, chunk_x, chunk_z, last_modified_timestamp);
+        self.buffer = buffer;
+
+        result
+    }
+
+    /// Writes a buffer holding a 4 bytes placeholder for the length, the
+    /// compression scheme and the chunk data.
+    fn write_chunk_buffer(
+        &mut self,
+        buffer: &mut Vec<u8>,
+        chunk_x: u8,
+        chunk_z: u8,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        let length = buffer.len() as u32;
+
+        if length > CHUNK_MAXIMUM_BYTES_LENGTH {
+            return Err(ChunkSaveError::LengthExceedsMaximum { length });
+        }
+
+        BigEndian::write_u32(&mut buffer[..4], length - 4);
+
+        // Padding to align sector.
+        buffer.resize(buffer.len() + sector_padding(length), 0);
+
+        let mut metadata = self.find_place(chunk_x, chunk_z, length)?;
+        let seek_offset = metadata.sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+        // Write the whole sectors at once.
+        self.file.seek(SeekFrom::Start(seek_offset))?;
+        self.file.write_all(buffer)?;
+
+        metadata.last_modified_timestamp = last_modified_timestamp;
+        self.update_metadata(chunk_x, chunk_z, metadata)?;
+
+        Ok(())
+    }
+
+    /// Makes sure at least the given amount of free sectors follows the last
+    /// used sector, extending the file at once if needed.
+    ///
+    /// Chunks written afterwards are placed in the reserved sectors instead
+    /// of growing the file for every write. Reserved sectors which are never
+    /// used stay in the file as free space.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::AnvilRegion;
+    /// use std::io::Cursor;
+    ///
+    /// let mut region = AnvilRegion::new(Cursor::new(Vec::new())).unwrap();
+    /// region.reserve_sectors(16).unwrap();
+    ///
+    /// assert_eq!(region.sector_count().unwrap(), 2 + 16);
+    /// ```
+    pub fn reserve_sectors(&mut self, sectors: u32) -> Result<(), io::Error> {
+        self.build_used_sectors()?;
+
+        let trailing_free_sectors = self
+            .used_sectors
+            .iter()
+            .rev()
+            .take_while(|used| !**used)
+            .count() as u32;
+
+        if trailing_free_sectors >= sectors {
+            return Ok(());
+        }
+
+        let extend_sectors = sectors - trailing_free_sectors;
+        let file_length = self.stream_len()?;
+        let extend_length = extend_sectors as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+        self.stream_set_len(file_length + extend_length)?;
+
+        for _ in 0..extend_sectors {
+            self.used_sectors.push(false);
+        }
+
+        Ok(())
+    }
+
+    /// Amount of complete sectors in the region file, including the two
+    /// header sectors.
+    pub fn sector_count(&mut self) -> Result<u32, io::Error> {
+        Ok((self.stream_len()? / REGION_SECTOR_BYTES_LENGTH as u64) as u32)
+    }
+
+    /// Reads the raw bytes of the sector at the specified index.
+    ///
+    /// Sectors 0 and 1 are the region header.
+    pub fn read_sector(
+        &mut self,
+        sector_index: u32,
+    ) -> Result<[u8; REGION_SECTOR_BYTES_LENGTH as usize], io::Error> {
+        self.check_sector_index(sector_index)?;
+
+        let mut sector = [0u8; REGION_SECTOR_BYTES_LENGTH as usize];
+        let seek_offset = sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+        self.file.seek(SeekFrom::Start(seek_offset))?;
+        self.file.read_exact(&mut sector)?;
+
+        Ok(sector)
+    }
+
+    /// Overwrites the raw bytes of the sector at the specified index.
+    ///
+    /// No validation of the written bytes is done. When writing one of the
+    /// header sectors, the header is read again so the region stays
+    /// consistent with the file.
+    pub fn write_sector(
+        &mut self,
+        sector_index: u32,
+        sector: &[u8; REGION_SECTOR_BYTES_LENGTH as usize],
+    ) -> Result<(), io::Error> {
+        self.check_sector_index(sector_index)?;
+
+        let seek_offset = sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+        self.file.seek(SeekFrom::Start(seek_offset))?;
+        self.file.write_all(sector)?;
+
+        if u64::from(sector_index) * (REGION_SECTOR_BYTES_LENGTH as u64) < REGION_HEADER_BYTES_LENGTH {
+            self.file.seek(SeekFrom::Start(0))?;
+            self.chunks_metadata = anvil_region::read_header(&mut self.file)?;
+
+            let total_sectors = self.sector_count()?;
+            self.used_sectors = anvil_region::used_sectors(total_sectors, &self.chunks_metadata);
+        }
+
+        Ok(())
+    }
+
+    fn check_sector_index(&mut self, sector_index: u32) -> Result<(), io::Error> {
+        let sector_count = self.sector_count()?;
+
+        if sector_index >= sector_count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Sector index {} out of bounds, region has {} sectors",
+                    sector_index, sector_count
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Removes the chunk at the specified coordinates and releases its sectors.
+    pub fn delete_chunk(&mut self, chunk_x: u8, chunk_z: u8) -> Result<(), io::Error> {
+        let metadata = self.get_metadata(chunk_x, chunk_z);
+
+        if metadata.is_empty() {
+            return Ok(());
+        }
+
+        self.build_used_sectors()?;
+        self.release_sectors(metadata);
+
+        self.update_metadata(chunk_x, chunk_z, AnvilChunkMetadata::default())
+    }
+
+    /// Returns chunk metadata at specified coordinates.
+    pub fn get_metadata(&self, chunk_x: u8, chunk_z: u8) -> AnvilChunkMetadata {
+        self.chunks_metadata[anvil_region::metadata_index(chunk_x, chunk_z)]
+    }
+
+    /// Finds a place where chunk data of a given length can be put.
+    ///
+    /// If cannot find a place to put chunk data will extend file.
+    fn find_place(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+        chunk_length: u32,
+    ) -> Result<AnvilChunkMetadata, io::Error> {
+        let sectors_required = sectors_required(chunk_length);
+        let metadata = self.get_metadata(chunk_x, chunk_z);
+
+        // Can place chunk in the old sectors, unless the header in the file
+        // still points at them.
+        if metadata.sectors == sectors_required
+            && self.sector_allocation != SectorAllocation::AppendOnly
+            && !self.header_deferred()
+        {
+            return Ok(metadata);
+        }
+
+        self.build_used_sectors()?;
+        self.release_sectors(metadata);
+
+        let file_length = self.stream_len()?;
+        let total_sectors = (file_length / REGION_SECTOR_BYTES_LENGTH as u64) as u32;
+        let free_runs = self.free_runs(total_sectors);
+
+        // Free sectors at the end of the file.
+        let trailing_run = free_runs
+            .last()
+            .filter(|(start, length)| start + length == total_sectors)
+            .copied();
+
+        let fits = |(_, length): &(u32, u32)| *length >= sectors_required as u32;
+        let gap = match self.sector_allocation {
+            SectorAllocation::FirstFit => free_runs.iter().copied().find(fits),
+            SectorAllocation::BestFit => free_runs
+                .iter()
+                .copied()
+                .filter(fits)
+                .min_by_key(|(_, length)| *length),
+            SectorAllocation::AppendOnly => trailing_run.filter(fits),
+        };
+
+        let put_sector_index = match gap {
+            // Can put chunk in gap.
+            Some((start, _)) => start,
+            // Extending file because cannot find a place to put chunk data.
+            None => {
+                let sectors_free = trailing_run.map_or(0, |(_, length)| length);
+                let extend_sectors = sectors_required as u32 - sectors_free;
+                let extend_length = extend_sectors as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+                self.stream_set_len(file_length + extend_length)?;
+
+                for _ in 0..extend_sectors {
+                    self.used_sectors.push(false);
+                }
+
+                total_sectors - sectors_free
+            }
+        };
+
+        // Acquire used sectors.
+        for i in 0..sectors_required {
+            let sector_index = put_sector_index as usize + i as usize;
+            self.used_sectors.set(sector_index, true);
         }
+
+        Ok(AnvilChunkMetadata::new(put_sector_index, sectors_required, 0))
     }
 
-    fn update_last_modified_timestamp(&mut self) {
-        let system_time = SystemTime::now();
-        let time = system_time.duration_since(UNIX_EPOCH).unwrap();
+    /// Computes the used sectors from the header if they weren't yet, which
+    /// is left to the first operation needing them so that regions only
+    /// read from don't pay for it.
+    fn build_used_sectors(&mut self) -> Result<(), io::Error> {
+        // Built sectors always include the header.
+        if self.used_sectors.is_empty() {
+            let total_sectors = self.sector_count()?;
+            self.used_sectors = anvil_region::used_sectors(total_sectors, &self.chunks_metadata);
+        }
 
-        self.last_modified_timestamp = time.as_secs() as u32
+        Ok(())
     }
 
-    fn is_empty(&self) -> bool {
-        self.sectors == 0
+    /// Marks the sectors of the chunk as free, or as free once the header
+    /// is written while header updates are kept in memory.
+    fn release_sectors(&mut self, metadata: AnvilChunkMetadata) {
+        if self.header_deferred() {
+            if metadata.sectors > 0 {
+                self.pending_sectors.push(metadata);
+            }
+
+            return;
+        }
+
+        self.free_sectors(metadata);
+    }
+
+    fn free_sectors(&mut self, metadata: AnvilChunkMetadata) {
+        for i in 0..metadata.sectors {
+            let sector_index = metadata.sector_index as usize + i as usize;
+            self.used_sectors.set(sector_index, false);
+        }
+
+        if self.punch_holes && metadata.sectors > 0 {
+            self.freed_sectors
+                .push((metadata.sector_index, metadata.sectors as u32));
+        }
+    }
+
+    /// Runs of free sectors as `(first sector index, amount of sectors)`, in
+    /// file order.
+    fn free_runs(&self, total_sectors: u32) -> Vec<(u32, u32)> {
+        let mut runs = Vec::new();
+        let mut start = 0;
+        let mut length = 0;
+
+        for sector_index in 0..total_sectors {
+            if self.used_sectors[sector_index as usize] {
+                if length > 0 {
+                    runs.push((start, length));
+                }
+
+                length = 0;
+                continue;
+            }
+
+            if length == 0 {
+                start = sector_index;
+            }
+
+            length += 1;
+        }
+
+        if length > 0 {
+            runs.push((start, length));
+        }
+
+        runs
+    }
+
+    /// Updates chunk metadata.
+    fn update_metadata(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+        metadata: AnvilChunkMetadata,
+    ) -> Result<(), io::Error> {
+        let metadata_index = anvil_region::metadata_index(chunk_x, chunk_z);
+        self.chunks_metadata[metadata_index] = metadata;
+
+        if self.header_deferred() {
+            self.header_dirty = true;
+
+            return Ok(());
+        }
+
+        let start_seek_offset = SeekFrom::Start((metadata_index * 4) as u64);
+        let offset = (metadata.sector_index << 8) | metadata.sectors as u32;
+
+        self.file.seek(start_seek_offset)?;
+        self.file.write_u32::<BigEndian>(offset)?;
+
+        let next_seek_offset = SeekFrom::Current(REGION_SECTOR_BYTES_LENGTH as i64 - 4);
+        let last_modified_timestamp = metadata.last_modified_timestamp;
+
+        self.file.seek(next_seek_offset)?;
+        self.file.write_u32::<BigEndian>(last_modified_timestamp)?;
+
+        Ok(())
+    }
+
+    fn stream_len(&mut self) -> Result<u64, io::Error> {
+        stream_len(&mut self.file)
+    }
+
+    fn stream_set_len(&mut self, new_len: u64) -> Result<u64, io::Error> {
+        stream_set_len(&mut self.file, new_len)
     }
 }
 
-pub mod anvil_region {
-    use crate::AnvilChunkMetadata;
-    use bitvec::prelude::*;
+/// Parse "r.1.2.mca" into (1, 2)
+pub fn parse_region_file_name(s: &str) -> Option<(i32, i32)> {
+    let mut iter = s.as_bytes().split(|x| *x == b'.');
+    if iter.next() != Some(b"r") {
+        return None;
+    }
+    let x = strict_parse_int::strict_parse_i32(iter.next()?)?;
+    let z = strict_parse_int::strict_parse_i32(iter.next()?)?;
+    if iter.next() != Some(b"mca") {
+        return None;
+    }
+
+    if iter.next().is_some() {
+        // Trailing dots
+        return None;
+    }
+
+    Some((x, z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AnvilChunkMetadata, AnvilRegion, ChunkLoadError, FolderChunkProvider,
+        REGION_HEADER_BYTES_LENGTH, REGION_SECTOR_BYTES_LENGTH,
+    };
+    use nbt::CompoundTag;
+    use std::io::{Cursor, Read};
+    use std::path::Path;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_empty_header_write_cursor() {
+        let mut region = AnvilRegion::new(Cursor::new(vec![])).unwrap();
+        let file_length = region.stream_len().unwrap();
+
+        assert_eq!(file_length, REGION_HEADER_BYTES_LENGTH);
+    }
+
+    #[test]
+    fn test_empty_header_write() {
+        let file = NamedTempFile::new().unwrap();
+        let mut region = AnvilRegion::file(file.path()).unwrap();
+        let file_length = region.stream_len().unwrap();
+
+        assert_eq!(file_length, REGION_HEADER_BYTES_LENGTH);
+    }
+
+    #[test]
+    fn test_empty_region_init() {
+        let mut file = NamedTempFile::new().unwrap();
+        AnvilRegion::file(file.path()).unwrap();
+
+        let mut vec = Vec::new();
+        file.read_to_end(&mut vec).unwrap();
+
+        assert_eq!(vec, include_bytes!("../test/empty_region.mca").to_vec());
+    }
+
+    #[test]
+    fn test_header_read() {
+        let expected_data = [
+            AnvilChunkMetadata::new(61, 2, 1570215508),
+            AnvilChunkMetadata::new(102, 2, 1570215511),
+            AnvilChunkMetadata::new(177, 2, 1570215515),
+            AnvilChunkMetadata::new(265, 2, 1570215519),
+            AnvilChunkMetadata::new(56, 2, 1570215508),
+        ];
+
+        let path = Path::new("test/region/r.0.0.mca");
+        assert!(path.exists());
+
+        let region = AnvilRegion::file(path).unwrap();
+
+        for (index, expected_chunk_metadata) in expected_data.iter().enumerate() {
+            let chunk_metadata = region.chunks_metadata[256 + index];
+
+            assert_eq!(&chunk_metadata, expected_chunk_metadata);
+        }
+    }
+
+    #[test]
+    fn test_region_header_read() {
+        let path = Path::new("test/region/r.0.0.mca");
+        let region = AnvilRegion::file(path).unwrap();
+        let header = AnvilRegionHeader::read(path).unwrap();
+
+        assert_eq!(&header.chunks_metadata[..], &region.chunks_metadata[..]);
+        assert_eq!(header.get_metadata(0, 8), AnvilChunkMetadata::new(61, 2, 1570215508));
+        assert_eq!(header.last_modified_timestamp(0, 8), 1570215508);
+        assert_eq!(header.chunks().next().unwrap().0, (0, 0));
+
+        let empty_header = AnvilRegionHeader::read("test/empty_region.mca").unwrap();
+        assert_eq!(empty_header.chunks().count(), 0);
+    }
+
+    #[test]
+    fn test_read_chunk_data() {
+        let path = Path::new("test/region/r.0.0.mca");
+        assert!(path.exists());
+
+        let mut region = AnvilRegion::file(path).unwrap();
+        let compound_tag = region.read_chunk(15, 3).unwrap();
+        let level_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(level_tag.get_i32("xPos").unwrap(), 15);
+        assert_eq!(level_tag.get_i32("zPos").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_read_chunk_empty() {
+        let path = Path::new("test/empty_region.mca");
+        assert!(path.exists());
+
+        let mut region = AnvilRegion::file(path).unwrap();
+        let load_error = region.read_chunk(0, 0).err().unwrap();
+
+        match load_error {
+            ChunkLoadError::ChunkNotFound { chunk_x, chunk_z } => {
+                assert_eq!(chunk_x, 0);
+                assert_eq!(chunk_z, 0);
+            }
+            _ => panic!("Expected `ChunkNotFound` but got `{:?}`", load_error),
+        }
+    }
+
+    #[test]
+    fn test_load_chunk_no_folder() {
+        let chunk_provider = FolderChunkProvider::new("no-folder");
+        let load_error = chunk_provider.load_chunk(4, 4).err().unwrap();
+
+        match load_error {
+            ChunkLoadError::RegionNotFound { region_x, region_z } => {
+                assert_eq!(region_x, 0);
+                assert_eq!(region_z, 0);
+            }
+            _ => panic!("Expected `RegionNotFound` but got `{:?}", load_error),
+        }
+    }
+
+    #[test]
+    fn test_load_chunk_no_region() {
+        let chunk_provider = FolderChunkProvider::new("test/region");
+        let load_error = chunk_provider.load_chunk(100, 100).err().unwrap();
+
+        match load_error {
+            ChunkLoadError::RegionNotFound { region_x, region_z } => {
+                assert_eq!(region_x, 3);
+                assert_eq!(region_z, 3);
+            }
+            _ => panic!("Expected `RegionNotFound` but got `{:?}", load_error),
+        }
+    }
+
+    #[test]
+    fn test_load_chunk_chunk_not_found() {
+        let chunk_provider = FolderChunkProvider::new("test/region");
+        let load_error = chunk_provider.load_chunk(15, 14).err().unwrap();
+
+        match load_error {
+            ChunkLoadError::ChunkNotFound { chunk_x, chunk_z } => {
+                assert_eq!(chunk_x, 15);
+                assert_eq!(chunk_z, 14);
+            }
+            _ => panic!("Expected `ChunkNotFound` but got `{:?}", load_error),
+        }
+    }
+
+    #[test]
+    fn test_try_load_chunk() {
+        let mut chunk_provider = FolderChunkProvider::new("test/region");
+
+        assert!(chunk_provider.try_load_chunk(15, 3).unwrap().is_some());
+        assert!(chunk_provider.try_load_chunk(15, 14).unwrap().is_none());
+        assert!(chunk_provider.try_load_chunk(100, 100).unwrap().is_none());
 
-    pub fn metadata_index(chunk_x: u8, chunk_z: u8) -> usize {
-        assert!(32 > chunk_x, "Region chunk x coordinate out of bounds");
-        assert!(32 > chunk_z, "Region chunk y coordinate out of bounds");
+        let reader: &mut dyn ChunkReader = &mut chunk_provider;
 
-        chunk_x as usize + chunk_z as usize * 32
+        assert!(reader.try_load_chunk(15, 3).unwrap().is_some());
+        assert!(reader.try_load_chunk(15, 14).unwrap().is_none());
     }
 
-    /// Calculates used sectors.
-    pub fn used_sectors(total_sectors: u32, chunks_metadata: &[AnvilChunkMetadata]) -> BitVec {
-        let mut used_sectors = bitvec![0; total_sectors as usize];
-
-        used_sectors.set(0, true);
-        used_sectors.set(1, true);
+    #[test]
+    fn test_load_chunk_metadata() {
+        let mut chunk_provider = FolderChunkProvider::new("test/region");
 
-        for metadata in chunks_metadata {
-            if metadata.is_empty() {
-                continue;
-            }
+        let metadata = chunk_provider.load_chunk_metadata(0, 8).unwrap();
+        assert_eq!(metadata, AnvilChunkMetadata::new(61, 2, 1570215508));
 
-            let start_index = metadata.sector_index as usize;
-            let end_index = start_index + metadata.sectors as usize;
+        let metadata = chunk_provider.load_chunk_metadata(15, 14).unwrap();
+        assert!(metadata.is_empty());
 
-            for index in start_index..end_index {
-                used_sectors.set(index, true);
-            }
+        match chunk_provider.load_chunk_metadata(100, 100) {
+            Err(ChunkLoadError::RegionNotFound {
+                region_x: 3,
+                region_z: 3,
+            }) => {}
+            e => panic!("Expected `RegionNotFound` but got `{:?}`", e),
         }
-
-        used_sectors
     }
-}
-
-fn stream_len<S: Seek>(file: &mut S) -> Result<u64, io::Error> {
-    let old_pos = file.seek(SeekFrom::Current(0))?;
-    let len = file.seek(SeekFrom::End(0))?;
 
-    // Avoid seeking a third time when we were already at the end of the
-    // stream. The branch is usually way cheaper than a seek operation.
-    if old_pos != len {
-        file.seek(SeekFrom::Start(old_pos))?;
+    #[test]
+    fn test_timestamp_policy() {
+        assert_eq!(TimestampPolicy::Preserve.timestamp(1570215508), 1570215508);
+        assert!(TimestampPolicy::Refresh.timestamp(1570215508) > 1570215508);
+        assert_eq!(
+            AnvilOptions::default().timestamp_policy,
+            TimestampPolicy::default()
+        );
     }
 
-    Ok(len)
-}
+    #[test]
+    fn test_list_chunks_in_selection() {
+        let chunk_provider = FolderChunkProvider::new("test/region");
 
-fn stream_set_len<S: Seek + Write>(file: &mut S, new_len: u64) -> Result<u64, io::Error> {
-    let old_pos = file.seek(SeekFrom::Current(0))?;
-    let len = file.seek(SeekFrom::Start(new_len - 1))? + 1;
+        let all = chunk_provider.list_chunks_in(&ChunkSelection::All).unwrap();
+        assert_eq!(all.len(), 277);
 
-    // Actually write so the stream len changes
-    file.write_all(&[0])?;
-    // Avoid seeking a third time when we were already at the end of the
-    // stream. The branch is usually way cheaper than a seek operation.
-    if old_pos != len {
-        file.seek(SeekFrom::Start(old_pos))?;
-    }
+        let selection = ChunkSelection::rect((15, 3), (16, 3));
+        let mut some = chunk_provider.list_chunks_in(&selection).unwrap();
+        some.sort_unstable();
+        assert_eq!(some, vec![(15, 3), (16, 3)]);
 
-    Ok(len)
-}
+        let outside = ChunkSelection::rect((-10, -10), (-1, -1));
+        assert!(chunk_provider.list_chunks_in(&outside).unwrap().is_empty());
+    }
 
-impl AnvilRegion<File> {
-    pub fn file<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
-        let file = OpenOptions::new()
-            .write(true)
-            .read(true)
-            .create(true)
-            .open(path)?;
+    #[test]
+    fn test_list_chunks_in_folder() {
+        let chunk_provider = FolderChunkProvider::new("test/region");
+        let x = chunk_provider.list_chunks().unwrap();
 
-        Self::new(file)
+        assert_eq!(x.len(), 277);
     }
-}
 
-impl<F: Seek + Read + Write> AnvilRegion<F> {
-    pub fn new(mut file: F) -> Result<Self, io::Error> {
-        // If necessary, extend the file length to the length of the header.
-        if REGION_HEADER_BYTES_LENGTH > stream_len(&mut file)? {
-            stream_set_len(&mut file, REGION_HEADER_BYTES_LENGTH)?;
+    #[test]
+    fn test_listing_order() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunks = [(33, 0), (0, 1), (-1, 0), (1, 0), (0, 0), (1, 1), (0, 32)];
+        let chunk_provider = FolderChunkProvider::new(folder.path());
+        for &(chunk_x, chunk_z) in &chunks {
+            chunk_provider
+                .save_chunk(chunk_x, chunk_z, CompoundTag::new())
+                .unwrap();
         }
 
-        let chunks_metadata = Self::read_header(&mut file)?;
-        let total_sectors = stream_len(&mut file)? as u32 / REGION_SECTOR_BYTES_LENGTH as u32;
-        let free_sectors = anvil_region::used_sectors(total_sectors, &chunks_metadata);
-
-        let region = AnvilRegion {
-            file,
-            chunks_metadata,
-            used_sectors: free_sectors,
-        };
+        let options = AnvilOptions::new().listing_order(ListingOrder::RegionMajor);
+        let mut chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        assert_eq!(
+            ChunkReader::list_regions(&mut chunk_provider).unwrap(),
+            vec![(-1, 0), (0, 0), (1, 0), (0, 1)]
+        );
+        assert_eq!(
+            chunk_provider.list_chunks().unwrap(),
+            vec![(-1, 0), (0, 0), (1, 0), (0, 1), (1, 1), (33, 0), (0, 32)]
+        );
 
-        Ok(region)
+        let options = AnvilOptions::new().listing_order(ListingOrder::ZOrder);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        assert_eq!(
+            chunk_provider
+                .list_chunks_in(&ChunkSelection::rect((-1, 0), (1, 32)))
+                .unwrap(),
+            vec![(-1, 0), (0, 0), (1, 0), (0, 1), (1, 1), (0, 32)]
+        );
     }
 
-    /// First 8KB of file are header of 1024 offsets and 1024 timestamps.
-    fn read_header(file: &mut F) -> Result<[AnvilChunkMetadata; REGION_CHUNKS], io::Error> {
-        let mut chunks_metadata = [Default::default(); REGION_CHUNKS];
-        let mut values = [0u32; REGION_CHUNKS_METADATA_LENGTH];
-
-        for index in 0..REGION_CHUNKS_METADATA_LENGTH {
-            values[index] = file.read_u32::<BigEndian>()?;
+    #[test]
+    fn test_folder_provider_is_reader_and_writer() {
+        fn list_regions<P: AnvilChunkProvider + ?Sized>(provider: &mut P) -> Vec<(i32, i32)> {
+            provider.list_regions().unwrap()
         }
 
-        for index in 0..REGION_CHUNKS {
-            let last_modified_timestamp = values[REGION_CHUNKS + index];
-            let offset = values[index];
+        let mut chunk_provider = FolderChunkProvider::new("test/region");
+        let provider: &mut dyn AnvilChunkProvider = &mut chunk_provider;
 
-            let sector_index = offset >> 8;
-            let sectors = (offset & 0xFF) as u8;
+        assert_eq!(list_regions(provider), vec![(0, 0)]);
+    }
 
-            let metadata = AnvilChunkMetadata::new(sector_index, sectors, last_modified_timestamp);
-            chunks_metadata[index] = metadata;
-        }
+    #[test]
+    fn test_folder_provider_shared_between_threads() {
+        let folder = tempfile::tempdir().unwrap();
+
+        for max_open_regions in [0, 1] {
+            let options = AnvilOptions::new().max_open_regions(max_open_regions);
+            let chunk_provider = std::sync::Arc::new(FolderChunkProvider::with_options(
+                folder.path().join(max_open_regions.to_string()),
+                options,
+            ));
+
+            let threads: Vec<_> = (0..4)
+                .map(|chunk_x| {
+                    let chunk_provider = std::sync::Arc::clone(&chunk_provider);
+                    std::thread::spawn(move || {
+                        for chunk_z in 0..16 {
+                            chunk_provider
+                                .save_chunk(chunk_x, chunk_z, CompoundTag::new())
+                                .unwrap();
+                        }
+                    })
+                })
+                .collect();
+
+            for thread in threads {
+                thread.join().unwrap();
+            }
 
-        return Ok(chunks_metadata);
+            chunk_provider.close_regions().unwrap();
+            assert_eq!(chunk_provider.list_chunks().unwrap().len(), 64);
+        }
     }
 
-    pub fn read_chunk(&mut self, chunk_x: u8, chunk_z: u8) -> Result<CompoundTag, ChunkLoadError> {
-        let metadata = self.get_metadata(chunk_x, chunk_z);
+    #[test]
+    fn test_boxed_provider() {
+        struct Engine {
+            provider: Box<dyn AnvilChunkProvider + Send>,
+        }
 
-        if metadata.is_empty() {
-            return Err(ChunkLoadError::ChunkNotFound { chunk_x, chunk_z });
+        fn load_header<P: ChunkReader>(mut provider: P) -> AnvilRegionHeader {
+            provider.load_region_header(0, 0).unwrap()
         }
 
-        let seek_offset = metadata.sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
-        let maximum_length = (metadata.sectors as u32 * REGION_SECTOR_BYTES_LENGTH as u32)
-            .min(CHUNK_MAXIMUM_BYTES_LENGTH);
+        let mut engine = Engine {
+            provider: Box::new(FolderChunkProvider::new("test/region")),
+        };
+        let mut region = engine.provider.get_region(0, 0).unwrap();
 
-        self.file.seek(SeekFrom::Start(seek_offset))?;
-        let length = self.file.read_u32::<BigEndian>()?;
+        engine = std::thread::spawn(move || {
+            assert!(engine.provider.load_chunk(4, 2).is_ok());
+            engine
+        })
+        .join()
+        .unwrap();
 
-        if length > maximum_length {
-            return Err(ChunkLoadError::LengthExceedsMaximum {
-                length,
-                maximum_length,
-            });
-        }
+        let header = AnvilRegionHeader::from_reader(&mut region).unwrap();
+        assert!(header.chunk_exists(4, 2));
+        assert!(load_header(&mut engine.provider).chunk_exists(4, 2));
+        assert!(load_header(engine.provider).chunk_exists(4, 2));
+    }
 
-        let compression_scheme = self.file.read_u8()?;
-        let mut compressed_buffer = vec![0u8; (length - 1) as usize];
-        self.file.read_exact(&mut compressed_buffer)?;
+    #[test]
+    fn test_coordinate_check() {
+        let mut level_compound_tag = CompoundTag::new();
+        level_compound_tag.insert_i32("xPos", 31);
+        level_compound_tag.insert_i32("zPos", 16);
+        let mut old_layout = CompoundTag::new();
+        old_layout.insert_compound_tag("Level", level_compound_tag);
+
+        let mut new_layout = CompoundTag::new();
+        new_layout.insert_i32("xPos", 31);
+        new_layout.insert_i32("zPos", 16);
+
+        for chunk_compound_tag in &mut [old_layout, new_layout] {
+            assert!(CoordinateCheck::Verify
+                .apply(31, 16, chunk_compound_tag)
+                .is_ok());
+            assert!(CoordinateCheck::Disabled
+                .apply(0, 0, chunk_compound_tag)
+                .is_ok());
+
+            match CoordinateCheck::Verify.apply(-1, 16, chunk_compound_tag) {
+                Err(ChunkSaveError::CoordinateMismatch {
+                    chunk_x: -1,
+                    chunk_z: 16,
+                    x_pos: 31,
+                    z_pos: 16,
+                }) => {}
+                e => panic!("Expected `CoordinateMismatch` but got `{:?}`", e),
+            }
 
-        let mut cursor = Cursor::new(&compressed_buffer);
+            CoordinateCheck::Fix
+                .apply(-1, 16, chunk_compound_tag)
+                .unwrap();
+            let level_compound_tag = chunk_level(chunk_compound_tag);
 
-        match compression_scheme {
-            GZIP_COMPRESSION_TYPE => Ok(read_gzip_compound_tag(&mut cursor)?),
-            ZLIB_COMPRESSION_TYPE => Ok(read_zlib_compound_tag(&mut cursor)?),
-            _ => Err(ChunkLoadError::UnsupportedCompressionScheme { compression_scheme }),
+            assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), -1);
+            assert_eq!(level_compound_tag.get_i32("zPos").unwrap(), 16);
         }
     }
 
-    fn write_chunk(
-        &mut self,
-        chunk_x: u8,
-        chunk_z: u8,
-        chunk_compound_tag: CompoundTag,
-    ) -> Result<(), ChunkSaveError> {
-        let mut buffer = Vec::new();
+    #[test]
+    fn test_save_chunk_fixes_coordinates() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider = FolderChunkProvider::new(folder.path().to_str().unwrap())
+            .with_coordinate_check(CoordinateCheck::Fix);
 
-        buffer.write_u8(ZLIB_COMPRESSION_TYPE)?;
-        write_zlib_compound_tag(&mut buffer, &chunk_compound_tag)?;
+        chunk_provider.save_chunk(-3, 40, CompoundTag::new()).unwrap();
+        let chunk_compound_tag = chunk_provider.load_chunk(-3, 40).unwrap();
 
-        // 4 bytes for data length.
-        let length = (buffer.len() + 4) as u32;
+        assert_eq!(chunk_compound_tag.get_i32("xPos").unwrap(), -3);
+        assert_eq!(chunk_compound_tag.get_i32("zPos").unwrap(), 40);
+    }
 
-        if length > CHUNK_MAXIMUM_BYTES_LENGTH {
-            return Err(ChunkSaveError::LengthExceedsMaximum { length });
+    #[test]
+    fn test_delete_chunks_where() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider = FolderChunkProvider::new(folder.path().to_str().unwrap());
+
+        for chunk_x in 0..4 {
+            let mut chunk_compound_tag = CompoundTag::new();
+            chunk_compound_tag.insert_i64("InhabitedTime", i64::from(chunk_x) * 100);
+            chunk_provider
+                .save_chunk_with_timestamp(chunk_x, 0, chunk_compound_tag, 100 + chunk_x as u32)
+                .unwrap();
         }
+        chunk_provider.save_chunk(40, 0, CompoundTag::new()).unwrap();
 
-        let mut metadata = self.find_place(chunk_x, chunk_z, length)?;
-        let seek_offset = metadata.sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+        // Only header timestamps.
+        let deleted = chunk_provider
+            .delete_chunks_where(&ChunkSelection::All, |_, metadata| {
+                metadata.last_modified_timestamp() == 100
+            })
+            .unwrap();
+        assert_eq!(deleted, 1);
+
+        // Decoding the chunks, limited to a selection.
+        let selection = ChunkSelection::rect((0, 0), (2, 0));
+        let deleted = chunk_provider
+            .delete_chunks_where_tag(&selection, |_, _, chunk_compound_tag| {
+                chunk_compound_tag.get_i64("InhabitedTime").unwrap() < 250
+            })
+            .unwrap();
+        assert_eq!(deleted, 2);
 
-        self.file.seek(SeekFrom::Start(seek_offset))?;
-        self.file.write_u32::<BigEndian>(buffer.len() as u32)?;
-        self.file.write_all(&buffer)?;
+        assert_eq!(chunk_provider.list_chunks().unwrap().len(), 2);
 
-        // Padding to align sector.
-        let padding = REGION_SECTOR_BYTES_LENGTH - length as u16 % REGION_SECTOR_BYTES_LENGTH;
+        // Deleting the last chunk of a region removes the region file.
+        let deleted = chunk_provider
+            .delete_chunks_where(&ChunkSelection::rect((32, 0), (63, 31)), |_, _| true)
+            .unwrap();
+        assert_eq!(deleted, 1);
+        assert_eq!(chunk_provider.list_regions().unwrap(), vec![(0, 0)]);
+    }
 
-        for _ in 0..padding {
-            self.file.write_u8(0)?;
-        }
+    #[test]
+    fn test_update_metadata() {
+        let mut file = NamedTempFile::new().unwrap();
+        let mut region = AnvilRegion::file(file.path()).unwrap();
 
-        metadata.update_last_modified_timestamp();
-        self.update_metadata(chunk_x, chunk_z, metadata)?;
+        let metadata = AnvilChunkMetadata::new(500, 10, current_timestamp());
 
-        Ok(())
-    }
+        region.update_metadata(15, 15, metadata).unwrap();
+        let chunks_metadata = anvil_region::read_header(file.as_file_mut()).unwrap();
+        let metadata_index = anvil_region::metadata_index(15, 15);
 
-    /// Returns chunk metadata at specified coordinates.
-    fn get_metadata(&self, chunk_x: u8, chunk_z: u8) -> AnvilChunkMetadata {
-        self.chunks_metadata[anvil_region::metadata_index(chunk_x, chunk_z)]
+        // In memory metadata.
+        assert_eq!(region.get_metadata(15, 15), metadata);
+        // Written to file metadata.
+        assert_eq!(chunks_metadata[metadata_index], metadata);
     }
 
-    /// Finds a place where chunk data of a given length can be put.
-    ///
-    /// If cannot find a place to put chunk data will extend file.
-    fn find_place(
-        &mut self,
-        chunk_x: u8,
-        chunk_z: u8,
-        chunk_length: u32,
-    ) -> Result<AnvilChunkMetadata, io::Error> {
-        let sectors_required = (chunk_length / REGION_SECTOR_BYTES_LENGTH as u32) as u8 + 1;
-        let metadata = self.get_metadata(chunk_x, chunk_z);
+    #[test]
+    fn test_write_batch_header() {
+        let file = NamedTempFile::new().unwrap();
+        let mut region = AnvilRegion::file(file.path()).unwrap();
 
-        // Can place chunk in the old sectors.
-        if metadata.sectors == sectors_required {
-            return Ok(metadata);
-        }
+        region
+            .write_batch(|region| {
+                region.write_chunk_with_timestamp(15, 15, CompoundTag::new(), 7)?;
+                region.write_chunk_with_timestamp(0, 1, CompoundTag::new(), 8)?;
+                region.delete_chunk(15, 15)?;
+
+                // Only updated in memory during the batch.
+                let header = AnvilRegionHeader::read(file.path()).unwrap();
+                assert_eq!(header.chunks().count(), 0);
+                assert_eq!(region.get_metadata(0, 1).last_modified_timestamp(), 8);
+
+                Ok::<_, ChunkSaveError>(())
+            })
+            .unwrap();
 
-        // Release used sectors.
-        for i in 0..metadata.sectors {
-            let sector_index = metadata.sector_index as usize + i as usize;
-            self.used_sectors.set(sector_index, false);
+        let header = AnvilRegionHeader::read(file.path()).unwrap();
+        assert_eq!(header.get_metadata(0, 1), region.get_metadata(0, 1));
+        assert!(!header.chunk_exists(15, 15));
+
+        // The header is written even if the batch fails.
+        let result = region.write_batch(|region| {
+            region.write_chunk_with_timestamp(2, 2, CompoundTag::new(), 9)?;
+
+            Err::<(), _>(ChunkSaveError::ReadOnly)
+        });
+        assert!(result.is_err());
+        let header = AnvilRegionHeader::read(file.path()).unwrap();
+        assert_eq!(header.last_modified_timestamp(2, 2), 9);
+    }
+
+    #[test]
+    fn test_write_batch_crash_keeps_previous_chunks() {
+        fn value_chunk(value: i32) -> CompoundTag {
+            let mut chunk_compound_tag = CompoundTag::new();
+            chunk_compound_tag.insert_i32("value", value);
+
+            chunk_compound_tag
         }
 
-        let file_length = self.stream_len()?;
-        let total_sectors = file_length / REGION_SECTOR_BYTES_LENGTH as u64;
+        let file = NamedTempFile::new().unwrap();
+        let mut region = AnvilRegion::file(file.path()).unwrap();
+        region.write_chunk(0, 0, value_chunk(1)).unwrap();
+        region.write_chunk(1, 0, value_chunk(2)).unwrap();
 
-        // Trying to find enough big gap between sectors to put chunk.
-        let mut sectors_free = 0;
+        let mut crashed = Vec::new();
+        region
+            .write_batch(|region| {
+                // Would reuse the sector of (0, 0) or rewrite (1, 0) in place
+                // if sectors were released before the header is written.
+                region.delete_chunk(0, 0)?;
+                region.write_chunk(2, 0, value_chunk(3))?;
+                region.write_chunk(1, 0, value_chunk(4))?;
+
+                // Crash before the header is written.
+                crashed = fs::read(file.path())?;
+
+                Ok::<_, ChunkSaveError>(())
+            })
+            .unwrap();
 
-        for sector_index in 0..total_sectors {
-            if self.used_sectors[sector_index as usize] {
-                sectors_free = 0;
-                continue;
-            }
+        let mut crashed = AnvilRegion::new(Cursor::new(crashed)).unwrap();
+        let chunk_compound_tag = crashed.read_chunk(0, 0).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("value").unwrap(), 1);
+        let chunk_compound_tag = crashed.read_chunk(1, 0).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("value").unwrap(), 2);
+        assert!(crashed.get_metadata(2, 0).is_empty());
+
+        // Once the header is written the old sectors are reused.
+        assert!(region.get_metadata(0, 0).is_empty());
+        let chunk_compound_tag = region.read_chunk(1, 0).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("value").unwrap(), 4);
+        region.write_chunk(3, 0, value_chunk(5)).unwrap();
+        assert_eq!(region.get_metadata(3, 0).sector_index, 2);
+    }
 
-            sectors_free += 1;
+    #[test]
+    fn test_write_chunk_with_file_extend() {
+        let file = NamedTempFile::new().unwrap();
+        let mut region = AnvilRegion::file(file.path()).unwrap();
 
-            // Can put chunk in gap.
-            if sectors_free == sectors_required {
-                let put_sector_index = sector_index as u32 - sectors_free as u32;
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_bool("test_bool", true);
+        write_compound_tag.insert_str("test_str", "test");
 
-                // Acquire used sectors.
-                for i in 0..sectors_free {
-                    let sector_index = put_sector_index as usize + i as usize;
-                    self.used_sectors.set(sector_index, true);
-                }
+        region.write_chunk(15, 15, write_compound_tag).unwrap();
 
-                return Ok(AnvilChunkMetadata::new(put_sector_index, sectors_free, 0));
-            }
-        }
+        assert_eq!(
+            file.as_file().metadata().unwrap().len(),
+            REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64
+        );
 
-        // Extending file because cannot find a place to put chunk data.
-        let extend_sectors = sectors_required - sectors_free;
-        let extend_length = (REGION_SECTOR_BYTES_LENGTH * extend_sectors as u16) as u64;
-        self.stream_set_len(file_length + extend_length)?;
+        assert_eq!(region.used_sectors.len(), 3);
 
-        // Mark new sectors as used.
-        for _ in 0..extend_sectors {
-            self.used_sectors.push(true);
-        }
+        let read_compound_tag = region.read_chunk(15, 15).unwrap();
 
-        return Ok(AnvilChunkMetadata::new(
-            total_sectors as u32 - sectors_free as u32,
-            sectors_required,
-            0,
-        ));
+        assert!(read_compound_tag.get_bool("test_bool").unwrap());
+        assert_eq!(read_compound_tag.get_str("test_str").unwrap(), "test");
     }
 
-    /// Updates chunk metadata.
-    fn update_metadata(
-        &mut self,
-        chunk_x: u8,
-        chunk_z: u8,
-        metadata: AnvilChunkMetadata,
-    ) -> Result<(), io::Error> {
-        let metadata_index = anvil_region::metadata_index(chunk_x, chunk_z);
-        self.chunks_metadata[metadata_index] = metadata;
-
-        let start_seek_offset = SeekFrom::Start((metadata_index * 4) as u64);
-        let offset = (metadata.sector_index << 8) | metadata.sectors as u32;
+    #[test]
+    fn test_write_chunk_with_timestamp() {
+        let mut file = NamedTempFile::new().unwrap();
+        let mut region = AnvilRegion::file(file.path()).unwrap();
 
-        self.file.seek(start_seek_offset)?;
-        self.file.write_u32::<BigEndian>(offset)?;
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_bool("test_bool", true);
 
-        let next_seek_offset = SeekFrom::Current(REGION_SECTOR_BYTES_LENGTH as i64 - 4);
-        let last_modified_timestamp = metadata.last_modified_timestamp;
+        region
+            .write_chunk_with_timestamp(15, 15, write_compound_tag, 1570215508)
+            .unwrap();
 
-        self.file.seek(next_seek_offset)?;
-        self.file.write_u32::<BigEndian>(last_modified_timestamp)?;
+        let chunks_metadata = anvil_region::read_header(file.as_file_mut()).unwrap();
+        let metadata_index = anvil_region::metadata_index(15, 15);
 
-        Ok(())
+        assert_eq!(region.get_metadata(15, 15).last_modified_timestamp, 1570215508);
+        assert_eq!(chunks_metadata[metadata_index].last_modified_timestamp, 1570215508);
     }
 
-    fn stream_len(&mut self) -> Result<u64, io::Error> {
-        stream_len(&mut self.file)
-    }
+    #[test]
+    fn test_write_chunk_pads_to_sector_boundary() {
+        let file = NamedTempFile::new().unwrap();
+        let mut region = AnvilRegion::file(file.path()).unwrap();
 
-    fn stream_set_len(&mut self, new_len: u64) -> Result<u64, io::Error> {
-        stream_set_len(&mut self.file, new_len)
-    }
-}
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_str("test_str", "test");
 
-/// Parse "r.1.2.mca" into (1, 2)
-pub fn parse_region_file_name(s: &str) -> Option<(i32, i32)> {
-    let mut iter = s.as_bytes().split(|x| *x == b'.');
-    if iter.next() != Some(b"r") {
-        return None;
-    }
-    let x = strict_parse_int::strict_parse_i32(iter.next()?)?;
-    let z = strict_parse_int::strict_parse_i32(iter.next()?)?;
-    if iter.next() != Some(b"mca") {
-        return None;
-    }
+        region.write_chunk(3, 7, write_compound_tag).unwrap();
 
-    if iter.next() != None {
-        // Trailing dots
-        return None;
+        let file_length = file.as_file().metadata().unwrap().len();
+        assert_eq!(file_length, REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64);
+
+        let sector = region.read_sector(2).unwrap();
+        let length = BigEndian::read_u32(&sector[..4]) as usize;
+
+        assert_eq!(sector[4], ZLIB_COMPRESSION_TYPE);
+        assert!(sector[4 + length..].iter().all(|&b| b == 0));
+        let read_compound_tag = region.read_chunk(3, 7).unwrap();
+        assert_eq!(read_compound_tag.get_str("test_str").unwrap(), "test");
     }
 
-    Some((x, z))
-}
+    #[test]
+    fn test_buffer_reuse_does_not_leak_previous_chunk() {
+        let file = NamedTempFile::new().unwrap();
+        let mut region = AnvilRegion::file(file.path()).unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        AnvilChunkMetadata, AnvilRegion, ChunkLoadError, FolderChunkProvider,
-        REGION_HEADER_BYTES_LENGTH, REGION_SECTOR_BYTES_LENGTH,
-    };
-    use nbt::CompoundTag;
-    use std::io::Read;
-    use std::path::Path;
-    use tempfile::NamedTempFile;
+        let mut large_compound_tag = CompoundTag::new();
+        large_compound_tag.insert_i64_vec("values", (0..4000).map(|i| i * 7919).collect());
+        let mut small_compound_tag = CompoundTag::new();
+        small_compound_tag.insert_str("test_str", "test");
 
-    #[test]
-    fn test_empty_header_write_cursor() {
-        let mut region = AnvilRegion::new(Cursor::new(vec![])).unwrap();
-        let file_length = region.stream_len().unwrap();
+        region.write_chunk(0, 0, large_compound_tag).unwrap();
+        region.write_chunk(1, 0, small_compound_tag).unwrap();
 
-        assert_eq!(file_length, REGION_HEADER_BYTES_LENGTH);
+        let sector_index = region.get_metadata(1, 0).sector_index;
+        let sector = region.read_sector(sector_index).unwrap();
+        let length = BigEndian::read_u32(&sector[..4]) as usize;
+
+        assert!(sector[4 + length..].iter().all(|&b| b == 0));
+
+        let large = region.read_chunk(0, 0).unwrap();
+        let small = region.read_chunk(1, 0).unwrap();
+
+        assert_eq!(large.get_i64_vec("values").unwrap().len(), 4000);
+        assert_eq!(small.get_str("test_str").unwrap(), "test");
     }
 
     #[test]
-    fn test_empty_header_write() {
+    fn test_reserve_sectors() {
         let file = NamedTempFile::new().unwrap();
         let mut region = AnvilRegion::file(file.path()).unwrap();
-        let file_length = region.stream_len().unwrap();
 
-        assert_eq!(file_length, REGION_HEADER_BYTES_LENGTH);
+        region.reserve_sectors(8).unwrap();
+        assert_eq!(region.sector_count().unwrap(), 10);
+
+        for chunk_x in 0..8 {
+            region.write_chunk(chunk_x, 0, CompoundTag::new()).unwrap();
+        }
+
+        // Chunks went into the reserved sectors.
+        assert_eq!(region.sector_count().unwrap(), 10);
+        assert_eq!(region.get_metadata(7, 0).sector_index, 9);
+
+        // Only the missing free sectors are added.
+        region.reserve_sectors(2).unwrap();
+        region.write_chunk(8, 0, CompoundTag::new()).unwrap();
+        region.reserve_sectors(2).unwrap();
+        assert_eq!(region.sector_count().unwrap(), 13);
     }
 
     #[test]
-    fn test_empty_region_init() {
-        let mut file = NamedTempFile::new().unwrap();
-        AnvilRegion::file(file.path()).unwrap();
+    fn test_folder_provider_preallocated_sectors() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider = FolderChunkProvider::new(folder.path().to_str().unwrap())
+            .with_preallocated_sectors(64);
+
+        chunk_provider.save_chunk(0, 0, CompoundTag::new()).unwrap();
+        chunk_provider.save_chunk(1, 0, CompoundTag::new()).unwrap();
+
+        let region_path = folder.path().join("r.0.0.mca");
+        let file_length = fs::metadata(region_path).unwrap().len();
+        assert_eq!(file_length, REGION_HEADER_BYTES_LENGTH + 64 * REGION_SECTOR_BYTES_LENGTH as u64);
+        assert!(chunk_provider.load_chunk(1, 0).is_ok());
+    }
 
-        let mut vec = Vec::new();
-        file.read_to_end(&mut vec).unwrap();
+    /// Region with chunks (0, 0), (1, 0) and (2, 0) of 1, 2 and 1 sectors
+    /// followed by 2 free sectors, after removing the first two chunks.
+    ///
+    /// Free sectors are 2..5 and 6..8.
+    fn region_with_gaps(sector_allocation: SectorAllocation) -> AnvilRegion<Cursor<Vec<u8>>> {
+        let mut region = AnvilRegion::new(Cursor::new(Vec::new()))
+            .unwrap()
+            .with_sector_allocation(sector_allocation);
+
+        // Pseudorandom bytes so the chunk doesn't compress into one sector.
+        let mut seed = 1u32;
+        let data = (0..6000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                (seed >> 16) as i8
+            })
+            .collect();
+        let mut large_compound_tag = CompoundTag::new();
+        large_compound_tag.insert_i8_vec("data", data);
+
+        region.write_chunk(0, 0, CompoundTag::new()).unwrap();
+        region.write_chunk(1, 0, large_compound_tag).unwrap();
+        region.write_chunk(2, 0, CompoundTag::new()).unwrap();
+        region.reserve_sectors(2).unwrap();
+        region.delete_chunk(0, 0).unwrap();
+        region.delete_chunk(1, 0).unwrap();
+
+        assert_eq!(region.get_metadata(2, 0).sector_index, 5);
+        assert_eq!(region.sector_count().unwrap(), 8);
 
-        assert_eq!(vec, include_bytes!("../test/empty_region.mca").to_vec());
+        region
     }
 
     #[test]
-    fn test_header_read() {
-        let expected_data = vec![
-            AnvilChunkMetadata::new(61, 2, 1570215508),
-            AnvilChunkMetadata::new(102, 2, 1570215511),
-            AnvilChunkMetadata::new(177, 2, 1570215515),
-            AnvilChunkMetadata::new(265, 2, 1570215519),
-            AnvilChunkMetadata::new(56, 2, 1570215508),
-        ];
-
-        let path = Path::new("test/region/r.0.0.mca");
-        assert!(path.exists());
+    fn test_sector_allocation_first_fit() {
+        let mut region = region_with_gaps(SectorAllocation::FirstFit);
 
-        let region = AnvilRegion::file(path).unwrap();
+        region.write_chunk(3, 0, CompoundTag::new()).unwrap();
+        assert_eq!(region.get_metadata(3, 0).sector_index, 2);
+    }
 
-        for (index, expected_chunk_metadata) in expected_data.iter().enumerate() {
-            let chunk_metadata = region.chunks_metadata[256 + index];
+    #[test]
+    fn test_sector_allocation_best_fit() {
+        let mut region = region_with_gaps(SectorAllocation::BestFit);
 
-            assert_eq!(&chunk_metadata, expected_chunk_metadata);
-        }
+        region.write_chunk(3, 0, CompoundTag::new()).unwrap();
+        assert_eq!(region.get_metadata(3, 0).sector_index, 6);
     }
 
     #[test]
-    fn test_read_chunk_data() {
-        let path = Path::new("test/region/r.0.0.mca");
-        assert!(path.exists());
+    fn test_sector_allocation_append_only() {
+        let mut region = region_with_gaps(SectorAllocation::AppendOnly);
 
-        let mut region = AnvilRegion::file(path).unwrap();
-        let compound_tag = region.read_chunk(15, 3).unwrap();
-        let level_tag = compound_tag.get_compound_tag("Level").unwrap();
+        region.write_chunk(3, 0, CompoundTag::new()).unwrap();
+        assert_eq!(region.get_metadata(3, 0).sector_index, 6);
 
-        assert_eq!(level_tag.get_i32("xPos").unwrap(), 15);
-        assert_eq!(level_tag.get_i32("zPos").unwrap(), 3);
+        // Rewriting a chunk moves it even when it has the same size.
+        region.write_chunk(2, 0, CompoundTag::new()).unwrap();
+        assert_eq!(region.get_metadata(2, 0).sector_index, 7);
+
+        region.write_chunk(4, 0, CompoundTag::new()).unwrap();
+        assert_eq!(region.get_metadata(4, 0).sector_index, 8);
+        assert_eq!(region.sector_count().unwrap(), 9);
     }
 
     #[test]
-    fn test_read_chunk_empty() {
-        let path = Path::new("test/empty_region.mca");
-        assert!(path.exists());
+    fn test_folder_provider_max_open_regions() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider =
+            FolderChunkProvider::new(folder.path().to_str().unwrap()).with_max_open_regions(2);
+        let open_regions = || chunk_provider.region_cache.lock().unwrap().open_regions();
 
-        let mut region = AnvilRegion::file(path).unwrap();
-        let load_error = region.read_chunk(0, 0).err().unwrap();
+        for region_x in 0..3 {
+            let mut chunk_compound_tag = CompoundTag::new();
+            chunk_compound_tag.insert_i32("region_x", region_x);
 
-        match load_error {
-            ChunkLoadError::ChunkNotFound { chunk_x, chunk_z } => {
-                assert_eq!(chunk_x, 0);
-                assert_eq!(chunk_z, 0);
-            }
-            _ => panic!("Expected `ChunkNotFound` but got `{:?}`", load_error),
+            chunk_provider.save_chunk(region_x * 32, 0, chunk_compound_tag).unwrap();
         }
+
+        assert_eq!(open_regions(), 2);
+
+        // Evicted region is opened again.
+        let chunk_compound_tag = chunk_provider.load_chunk(0, 0).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("region_x").unwrap(), 0);
+        assert_eq!(open_regions(), 2);
+
+        // Region files removed by the provider are closed first.
+        let deleted = chunk_provider
+            .delete_chunks_where(&ChunkSelection::All, |_, _| true)
+            .unwrap();
+        assert_eq!(deleted, 3);
+        assert_eq!(open_regions(), 0);
+        assert_eq!(fs::read_dir(folder.path()).unwrap().count(), 0);
     }
 
     #[test]
-    fn test_load_chunk_no_folder() {
-        let chunk_provider = FolderChunkProvider::new("no-folder");
-        let load_error = chunk_provider.load_chunk(4, 4).err().unwrap();
+    fn test_folder_provider_close_regions() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider =
+            FolderChunkProvider::new(folder.path().to_str().unwrap()).with_max_open_regions(8);
 
-        match load_error {
-            ChunkLoadError::RegionNotFound { region_x, region_z } => {
-                assert_eq!(region_x, 0);
-                assert_eq!(region_z, 0);
-            }
-            _ => panic!("Expected `RegionNotFound` but got `{:?}", load_error),
-        }
+        chunk_provider.save_chunk(1, 2, CompoundTag::new()).unwrap();
+        assert_eq!(chunk_provider.region_cache.lock().unwrap().open_regions(), 1);
+
+        chunk_provider.close_regions().unwrap();
+        assert_eq!(chunk_provider.region_cache.lock().unwrap().open_regions(), 0);
+        assert!(chunk_provider.load_chunk(1, 2).is_ok());
     }
 
     #[test]
-    fn test_load_chunk_no_region() {
-        let chunk_provider = FolderChunkProvider::new("test/region");
-        let load_error = chunk_provider.load_chunk(100, 100).err().unwrap();
+    fn test_folder_provider_flush() {
+        let folder = tempfile::tempdir().unwrap();
+        let journal_path = folder.path().join("r.0.0.mca.journal");
+        let options = AnvilOptions::new().journal(true).max_open_regions(8);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+
+        chunk_provider.save_chunk(1, 2, CompoundTag::new()).unwrap();
+        assert!(journal_path.exists());
+
+        // Synced regions stay open.
+        chunk_provider.flush().unwrap();
+        assert!(!journal_path.exists());
+        assert_eq!(chunk_provider.region_cache.lock().unwrap().open_regions(), 1);
+        assert!(chunk_provider.load_chunk(1, 2).is_ok());
+
+        chunk_provider.save_chunk(1, 3, CompoundTag::new()).unwrap();
+        chunk_provider.close().unwrap();
+        assert!(!journal_path.exists());
+        assert!(FolderChunkProvider::new(folder.path())
+            .load_chunk(1, 3)
+            .is_ok());
+    }
 
-        match load_error {
-            ChunkLoadError::RegionNotFound { region_x, region_z } => {
-                assert_eq!(region_x, 3);
-                assert_eq!(region_z, 3);
-            }
-            _ => panic!("Expected `RegionNotFound` but got `{:?}", load_error),
-        }
+    #[test]
+    fn test_folder_provider_defers_open_region_header() {
+        let folder = tempfile::tempdir().unwrap();
+        let region_path = folder.path().join("r.0.0.mca");
+        let chunk_provider = FolderChunkProvider::new(folder.path()).with_max_open_regions(8);
+
+        chunk_provider.save_chunk(1, 2, CompoundTag::new()).unwrap();
+        chunk_provider.save_chunk(1, 3, CompoundTag::new()).unwrap();
+
+        // The header stays in memory while the region is open.
+        let header = AnvilRegionHeader::read(&region_path).unwrap();
+        assert!(header.get_metadata(1, 2).is_empty());
+        assert!(header.get_metadata(1, 3).is_empty());
+        assert!(chunk_provider.load_chunk(1, 3).is_ok());
+
+        chunk_provider.flush().unwrap();
+        let header = AnvilRegionHeader::read(&region_path).unwrap();
+        assert!(!header.get_metadata(1, 2).is_empty());
+        assert!(!header.get_metadata(1, 3).is_empty());
     }
 
     #[test]
-    fn test_load_chunk_chunk_not_found() {
-        let chunk_provider = FolderChunkProvider::new("test/region");
-        let load_error = chunk_provider.load_chunk(15, 14).err().unwrap();
+    fn test_folder_provider_missing_chunk_keeps_deferred_header() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider = FolderChunkProvider::new(folder.path()).with_max_open_regions(8);
+
+        chunk_provider.save_chunk(0, 0, CompoundTag::new()).unwrap();
+        assert!(matches!(
+            chunk_provider.load_chunk(1, 1),
+            Err(ChunkLoadError::ChunkNotFound { .. })
+        ));
+        chunk_provider.close().unwrap();
 
-        match load_error {
-            ChunkLoadError::ChunkNotFound { chunk_x, chunk_z } => {
-                assert_eq!(chunk_x, 15);
-                assert_eq!(chunk_z, 14);
-            }
-            _ => panic!("Expected `ChunkNotFound` but got `{:?}", load_error),
-        }
+        assert!(FolderChunkProvider::new(folder.path())
+            .load_chunk(0, 0)
+            .is_ok());
     }
 
     #[test]
-    fn test_list_chunks_in_folder() {
-        let mut chunk_provider = FolderChunkProvider::new("test/region");
-        let x = chunk_provider.list_chunks().unwrap();
-
-        assert_eq!(x.len(), 277);
+    fn test_folder_provider_loads_read_only() {
+        let folder = tempfile::tempdir().unwrap();
+        let region_path = folder.path().join("r.0.0.mca");
+        fs::copy("test/region/r.0.0.mca", &region_path).unwrap();
+        let empty_region_path = folder.path().join("r.1.0.mca");
+        fs::write(&empty_region_path, []).unwrap();
+
+        let chunk_provider =
+            FolderChunkProvider::new(folder.path().to_str().unwrap()).with_max_open_regions(8);
+        assert!(chunk_provider.load_chunk(4, 2).is_ok());
+        assert!(chunk_provider.load_chunk_raw(5, 2).is_ok());
+        assert!(matches!(
+            chunk_provider.load_chunk(32, 0),
+            Err(ChunkLoadError::ChunkNotFound { .. })
+        ));
+        // Loading doesn't extend the file, which stays open like the others.
+        assert_eq!(fs::metadata(&empty_region_path).unwrap().len(), 0);
+        assert_eq!(chunk_provider.region_cache.lock().unwrap().open_regions(), 2);
+
+        // Regions opened read-only are reopened to write.
+        chunk_provider.save_chunk(15, 14, CompoundTag::new()).unwrap();
+        chunk_provider.close_regions().unwrap();
+        assert!(chunk_provider.load_chunk(15, 14).is_ok());
     }
 
     #[test]
-    fn test_update_metadata() {
-        let mut file = NamedTempFile::new().unwrap();
-        let mut region = AnvilRegion::file(file.path()).unwrap();
+    fn test_write_chunk_compression() {
+        for &compression in &[Compression::Gzip, Compression::Zlib, Compression::Uncompressed] {
+            let mut region = AnvilRegion::new(Cursor::new(Vec::new()))
+                .unwrap()
+                .with_compression(compression, 9);
 
-        let mut metadata = AnvilChunkMetadata::new(500, 10, 0);
-        metadata.update_last_modified_timestamp();
+            let mut write_compound_tag = CompoundTag::new();
+            write_compound_tag.insert_str("test_str", "test");
+            region.write_chunk(0, 0, write_compound_tag).unwrap();
 
-        region.update_metadata(15, 15, metadata).unwrap();
-        let chunks_metadata = AnvilRegion::read_header(file.as_file_mut()).unwrap();
-        let metadata_index = anvil_region::metadata_index(15, 15);
+            let sector = region.read_sector(2).unwrap();
+            assert_eq!(Compression::from_id(sector[4]), Some(compression));
 
-        // In memory metadata.
-        assert_eq!(region.get_metadata(15, 15), metadata);
-        // Written to file metadata.
-        assert_eq!(chunks_metadata[metadata_index], metadata);
+            let read_compound_tag = region.read_chunk(0, 0).unwrap();
+            assert_eq!(read_compound_tag.get_str("test_str").unwrap(), "test");
+        }
     }
 
     #[test]
-    fn test_write_chunk_with_file_extend() {
-        let file = NamedTempFile::new().unwrap();
-        let mut region = AnvilRegion::file(file.path()).unwrap();
+    fn test_folder_provider_options() {
+        let folder = tempfile::tempdir().unwrap();
+        let options = AnvilOptions::new()
+            .compression(Compression::Gzip)
+            .timestamp_policy(TimestampPolicy::Preserve)
+            .sync_policy(SyncPolicy::EveryWrite);
+        let chunk_provider =
+            FolderChunkProvider::with_options(folder.path().to_str().unwrap(), options);
+
+        chunk_provider
+            .save_chunk_with_timestamp(1, 1, CompoundTag::new(), 1000)
+            .unwrap();
+        chunk_provider.save_chunk(1, 1, CompoundTag::new()).unwrap();
+        chunk_provider.save_chunk(2, 2, CompoundTag::new()).unwrap();
 
-        let mut write_compound_tag = CompoundTag::new();
-        write_compound_tag.insert_bool("test_bool", true);
-        write_compound_tag.insert_str("test_str", "test");
+        let header = AnvilRegionHeader::read(folder.path().join("r.0.0.mca")).unwrap();
+        assert_eq!(header.last_modified_timestamp(1, 1), 1000);
+        assert!(header.last_modified_timestamp(2, 2) > 1000);
 
-        region.write_chunk(15, 15, write_compound_tag).unwrap();
+        let mut region = AnvilRegion::file(folder.path().join("r.0.0.mca")).unwrap();
+        let sector_index = region.get_metadata(1, 1).sector_index;
+        assert_eq!(region.read_sector(sector_index).unwrap()[4], GZIP_COMPRESSION_TYPE);
+    }
 
-        assert_eq!(
-            file.as_file().metadata().unwrap().len(),
-            REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64
-        );
+    #[test]
+    fn test_folder_provider_read_only() {
+        let options = AnvilOptions::new().read_only(true);
+        let chunk_provider = FolderChunkProvider::with_options("test/region", options);
 
-        assert_eq!(region.used_sectors.len(), 3);
+        match chunk_provider.save_chunk(4, 2, CompoundTag::new()) {
+            Err(ChunkSaveError::ReadOnly) => {}
+            e => panic!("Expected `ReadOnly` but got `{:?}`", e),
+        }
 
-        let read_compound_tag = region.read_chunk(15, 15).unwrap();
+        match chunk_provider.delete_chunk(4, 2) {
+            Err(ChunkSaveError::ReadOnly) => {}
+            e => panic!("Expected `ReadOnly` but got `{:?}`", e),
+        }
 
-        assert!(read_compound_tag.get_bool("test_bool").unwrap());
-        assert_eq!(read_compound_tag.get_str("test_str").unwrap(), "test");
+        assert!(chunk_provider.load_chunk(4, 2).is_ok());
+    }
+
+    #[test]
+    fn test_folder_provider_strict_loading() {
+        let folder = tempfile::tempdir().unwrap();
+        let folder_path = folder.path().to_str().unwrap();
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("xPos", 7);
+        chunk_compound_tag.insert_i32("zPos", 3);
+        FolderChunkProvider::new(folder_path)
+            .save_chunk(0, 0, chunk_compound_tag)
+            .unwrap();
+
+        let chunk_provider =
+            FolderChunkProvider::with_options(folder_path, AnvilOptions::new().strict_loading(true));
+
+        match chunk_provider.load_chunk(0, 0) {
+            Err(ChunkLoadError::CoordinateMismatch { x_pos: 7, z_pos: 3, .. }) => {}
+            e => panic!("Expected `CoordinateMismatch` but got `{:?}`", e),
+        }
     }
 
     #[test]
@@ -985,6 +4301,7 @@ mod tests {
         write_compound_tag.insert_bool("test_bool", true);
         write_compound_tag.insert_str("test_str", "test");
 
+        region.build_used_sectors().unwrap();
         for _ in 3..6 {
             region.used_sectors.push(true);
         }
@@ -996,11 +4313,43 @@ mod tests {
 
         region.write_chunk(15, 15, write_compound_tag).unwrap();
 
+        assert!(region.used_sectors.get(3).unwrap());
         assert!(region.used_sectors.get(4).unwrap());
+        assert_eq!(region.get_metadata(15, 15).sector_index, 3);
         assert_eq!(file.as_file().metadata().unwrap().len(), length);
         assert_eq!(region.used_sectors.len(), 5);
     }
 
+    #[test]
+    fn test_write_chunk_in_gap_keeps_neighbours() {
+        let file = NamedTempFile::new().unwrap();
+        let mut region = AnvilRegion::file(file.path()).unwrap();
+
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_bool("test_bool", true);
+
+        region.write_chunk(0, 0, write_compound_tag.clone()).unwrap();
+        region.write_chunk(1, 0, write_compound_tag.clone()).unwrap();
+        region.write_chunk(2, 0, write_compound_tag.clone()).unwrap();
+
+        // Free the middle sector, leaving a one sector gap between two chunks.
+        region.used_sectors.set(3, false);
+        region
+            .update_metadata(1, 0, AnvilChunkMetadata::default())
+            .unwrap();
+
+        region.write_chunk(3, 0, write_compound_tag).unwrap();
+
+        assert_eq!(region.get_metadata(3, 0).sector_index, 3);
+        assert_eq!(region.used_sectors.len(), 5);
+
+        let mut region = AnvilRegion::file(file.path()).unwrap();
+
+        assert!(region.read_chunk(0, 0).is_ok());
+        assert!(region.read_chunk(2, 0).is_ok());
+        assert!(region.read_chunk(3, 0).is_ok());
+    }
+
     #[test]
     fn test_write_chunk_not_enough_gap() {
         let file = NamedTempFile::new().unwrap();
@@ -1037,6 +4386,184 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_delete_chunk() {
+        let file = NamedTempFile::new().unwrap();
+        let mut region = AnvilRegion::file(file.path()).unwrap();
+
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_bool("test_bool", true);
+
+        region.write_chunk(15, 15, write_compound_tag.clone()).unwrap();
+        region.write_chunk(0, 0, write_compound_tag.clone()).unwrap();
+        region.delete_chunk(15, 15).unwrap();
+
+        assert!(region.get_metadata(15, 15).is_empty());
+        assert_eq!(region.used_sectors.clone().into_vec()[0], 0b00001011);
+
+        // Deleted sectors are reused by the next write.
+        region.write_chunk(1, 1, write_compound_tag).unwrap();
+
+        assert_eq!(region.get_metadata(1, 1).sector_index, 2);
+
+        let region = AnvilRegion::file(file.path()).unwrap();
+
+        assert!(region.get_metadata(15, 15).is_empty());
+        assert!(!region.get_metadata(0, 0).is_empty());
+        assert!(!region.get_metadata(1, 1).is_empty());
+    }
+
+    #[test]
+    fn test_save_chunk_with_compression() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider = FolderChunkProvider::new(folder.path());
+        let compressions = [
+            Compression::Gzip,
+            Compression::Zlib,
+            Compression::Uncompressed,
+        ];
+
+        for (chunk_x, &compression) in compressions.iter().enumerate() {
+            let mut chunk_compound_tag = CompoundTag::new();
+            chunk_compound_tag.insert_bool("test_bool", true);
+            chunk_provider
+                .save_chunk_with(chunk_x as i32, 0, chunk_compound_tag, compression)
+                .unwrap();
+        }
+
+        for (chunk_x, &compression) in compressions.iter().enumerate() {
+            let raw_chunk = chunk_provider.load_chunk_raw(chunk_x as i32, 0).unwrap();
+            assert_eq!(raw_chunk.compression_scheme(), compression.id());
+
+            let chunk_compound_tag = chunk_provider.load_chunk(chunk_x as i32, 0).unwrap();
+            assert!(chunk_compound_tag.get_bool("test_bool").unwrap());
+        }
+
+        // The default compression is kept for other saves.
+        chunk_provider.save_chunk(3, 0, CompoundTag::new()).unwrap();
+        let raw_chunk = chunk_provider.load_chunk_raw(3, 0).unwrap();
+        assert_eq!(raw_chunk.compression_scheme(), Compression::Zlib.id());
+    }
+
+    #[test]
+    fn test_delete_chunk_shrinks_region() {
+        let folder = tempfile::tempdir().unwrap();
+        let options = AnvilOptions::new().shrink_regions(true);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        let region_path = folder.path().join("r.0.0.mca");
+
+        chunk_provider.save_chunk(0, 0, CompoundTag::new()).unwrap();
+        chunk_provider.save_chunk(1, 0, CompoundTag::new()).unwrap();
+        chunk_provider.save_chunk(2, 0, CompoundTag::new()).unwrap();
+        assert_eq!(fs::metadata(&region_path).unwrap().len(), 5 * 4096);
+
+        // Only the free sectors at the end are truncated.
+        chunk_provider.delete_chunk(0, 0).unwrap();
+        assert_eq!(fs::metadata(&region_path).unwrap().len(), 5 * 4096);
+        chunk_provider.delete_chunk(2, 0).unwrap();
+        assert_eq!(fs::metadata(&region_path).unwrap().len(), 4 * 4096);
+        assert!(chunk_provider.load_chunk(1, 0).is_ok());
+
+        chunk_provider.delete_chunk(1, 0).unwrap();
+        assert!(!region_path.exists());
+    }
+
+    #[test]
+    fn test_read_write_sector() {
+        let file = NamedTempFile::new().unwrap();
+        let mut region = AnvilRegion::file(file.path()).unwrap();
+
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_bool("test_bool", true);
+        region.write_chunk(15, 15, write_compound_tag).unwrap();
+
+        assert_eq!(region.sector_count().unwrap(), 3);
+
+        // Chunk data starts with the length and the compression scheme.
+        let sector = region.read_sector(2).unwrap();
+        let length = u32::from_be_bytes([sector[0], sector[1], sector[2], sector[3]]);
+        assert!(length > 1 && length < 4096);
+        assert_eq!(sector[4], ZLIB_COMPRESSION_TYPE);
+
+        assert!(region.read_sector(3).is_err());
+        assert!(region.write_sector(3, &sector).is_err());
+
+        // Clearing the header removes the chunk.
+        let empty_sector = [0u8; REGION_SECTOR_BYTES_LENGTH as usize];
+        region.write_sector(0, &empty_sector).unwrap();
+
+        assert!(region.get_metadata(15, 15).is_empty());
+        assert_eq!(region.used_sectors.clone().into_vec()[0], 0b00000011);
+    }
+
+    #[test]
+    fn test_write_chunk_over_255_sectors() {
+        let mut region = AnvilRegion::new(Cursor::new(Vec::new()))
+            .unwrap()
+            .with_compression(Compression::Uncompressed, 0);
+
+        // Fits in 256 sectors, which the header can't describe.
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_i8_vec("data", vec![0; 255 * 4096]);
+
+        match region.write_chunk(0, 0, write_compound_tag) {
+            Err(ChunkSaveError::LengthExceedsMaximum { length }) => {
+                assert!(length > 255 * 4096 && length <= 256 * 4096)
+            }
+            e => panic!("Expected `LengthExceedsMaximum` but got `{:?}`", e),
+        }
+
+        assert!(region.get_metadata(0, 0).is_empty());
+
+        // The largest chunk which fits in 255 sectors is still written.
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_i8_vec("data", vec![0; 255 * 4096 - 64]);
+        region.write_chunk(0, 0, write_compound_tag).unwrap();
+
+        assert_eq!(region.get_metadata(0, 0).sectors, 255);
+        assert!(region.read_chunk(0, 0).is_ok());
+    }
+
+    #[test]
+    fn test_read_chunk_inconsistent_length() {
+        let mut region = AnvilRegion::new(Cursor::new(Vec::new())).unwrap();
+        region.write_chunk(0, 0, CompoundTag::new()).unwrap();
+
+        let mut sector = region.read_sector(2).unwrap();
+
+        sector[..4].copy_from_slice(&4093u32.to_be_bytes());
+        region.write_sector(2, &sector).unwrap();
+
+        match region.read_chunk(0, 0) {
+            Err(ChunkLoadError::LengthExceedsMaximum {
+                length: 4093,
+                maximum_length: 4092,
+            }) => {}
+            e => panic!("Expected `LengthExceedsMaximum` but got `{:?}`", e),
+        }
+
+        sector[..4].copy_from_slice(&0u32.to_be_bytes());
+        region.write_sector(2, &sector).unwrap();
+
+        match region.read_chunk(0, 0) {
+            Err(ChunkLoadError::EmptyChunkData) => {}
+            e => panic!("Expected `EmptyChunkData` but got `{:?}`", e),
+        }
+    }
+
+    #[test]
+    fn test_sectors_required_and_padding() {
+        assert_eq!(sectors_required(1), 1);
+        assert_eq!(sectors_required(4095), 1);
+        assert_eq!(sectors_required(4096), 1);
+        assert_eq!(sectors_required(4097), 2);
+
+        assert_eq!(sector_padding(1), 4095);
+        assert_eq!(sector_padding(4095), 1);
+        assert_eq!(sector_padding(4096), 0);
+        assert_eq!(sector_padding(4097), 4095);
+    }
+
     #[test]
     fn test_used_sectors_only_header() {
         let empty_chunks_metadata = Vec::new();
@@ -1067,6 +4594,45 @@ mod tests {
         assert_eq!(used_vec[0], 0b100111011);
     }
 
+    #[test]
+    fn test_used_sectors_past_end() {
+        let chunks_metadata = vec![
+            AnvilChunkMetadata::new(3, 3, 0),
+            AnvilChunkMetadata::new(60, 1, 0),
+        ];
+
+        let used_sectors = anvil_region::used_sectors(4, &chunks_metadata);
+        let used_vec = used_sectors.into_vec();
+
+        assert_eq!(used_vec[0], 0b1011);
+    }
+
+    #[test]
+    fn test_used_sectors_built_on_write() {
+        let bytes = std::fs::read("test/region/r.0.0.mca").unwrap();
+        let mut region = AnvilRegion::new(Cursor::new(bytes)).unwrap();
+
+        region.read_chunk(4, 2).unwrap();
+        assert!(region.used_sectors.is_empty());
+
+        let total_sectors = region.sector_count().unwrap();
+        let expected = anvil_region::used_sectors(total_sectors, &region.chunks_metadata);
+        region.delete_chunk(15, 14).unwrap();
+        assert!(region.used_sectors.is_empty());
+
+        region.delete_chunk(4, 2).unwrap();
+        let metadata = AnvilRegionHeader::read("test/region/r.0.0.mca")
+            .unwrap()
+            .get_metadata(4, 2);
+        assert_eq!(region.used_sectors.len(), expected.len());
+        for index in 0..expected.len() {
+            let released = (metadata.sector_index as usize..)
+                .take(metadata.sectors as usize)
+                .any(|sector_index| sector_index == index);
+            assert_eq!(region.used_sectors[index], expected[index] && !released);
+        }
+    }
+
     #[test]
     fn test_chunk_to_region() {
         // Chunk (0, 0) is in region (0, 0) at offset (0, 0)
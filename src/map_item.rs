@@ -0,0 +1,271 @@
+use crate::png::{write_png, RGBA};
+use crate::WorldFolderProvider;
+use nbt::decode::TagDecodeError;
+use nbt::CompoundTag;
+use std::io;
+use std::io::Write;
+
+/// Width and height of a map in pixels.
+pub const MAP_SIZE: u32 = 128;
+
+/// Brightness of the 4 shades of every base color, out of 255.
+const SHADE_MULTIPLIERS: [u32; 4] = [180, 220, 255, 135];
+
+/// Base colors of the map color ids as of 1.17, id 0 is transparent.
+const BASE_COLORS: [[u8; 3]; 62] = [
+    [0, 0, 0],
+    [127, 178, 56],
+    [247, 233, 163],
+    [199, 199, 199],
+    [255, 0, 0],
+    [160, 160, 255],
+    [167, 167, 167],
+    [0, 124, 0],
+    [255, 255, 255],
+    [164, 168, 184],
+    [151, 109, 77],
+    [112, 112, 112],
+    [64, 64, 255],
+    [143, 119, 72],
+    [255, 252, 245],
+    [216, 127, 51],
+    [178, 76, 216],
+    [102, 153, 216],
+    [229, 229, 51],
+    [127, 204, 25],
+    [242, 127, 165],
+    [76, 76, 76],
+    [153, 153, 153],
+    [76, 127, 153],
+    [127, 63, 178],
+    [51, 76, 178],
+    [102, 76, 51],
+    [102, 127, 51],
+    [153, 51, 51],
+    [25, 25, 25],
+    [250, 238, 77],
+    [92, 219, 213],
+    [74, 128, 255],
+    [0, 217, 58],
+    [129, 86, 49],
+    [112, 2, 0],
+    [209, 177, 161],
+    [159, 82, 36],
+    [149, 87, 108],
+    [112, 108, 138],
+    [186, 133, 36],
+    [103, 117, 53],
+    [160, 77, 78],
+    [57, 41, 35],
+    [135, 107, 98],
+    [87, 92, 92],
+    [122, 73, 88],
+    [76, 62, 92],
+    [76, 50, 35],
+    [76, 82, 42],
+    [142, 60, 46],
+    [37, 22, 16],
+    [189, 48, 49],
+    [148, 63, 97],
+    [92, 25, 29],
+    [22, 126, 134],
+    [58, 142, 140],
+    [86, 44, 62],
+    [20, 180, 133],
+    [100, 100, 100],
+    [216, 175, 147],
+    [127, 167, 150],
+];
+
+/// In-game map stored in `data/map_<id>.dat`.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::WorldFolderProvider;
+///
+/// # let world = tempfile::tempdir().unwrap();
+/// # let world = world.path();
+/// let world_provider = WorldFolderProvider::new(world);
+///
+/// for id in world_provider.list_maps().unwrap() {
+///     let map_item = world_provider.load_map(id).unwrap();
+///     let mut png = Vec::new();
+///     map_item.write_png(&mut png).unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MapItem {
+    /// Every pixel covers `2^scale` blocks in each direction, from 0 to 4.
+    pub scale: u8,
+    /// Block coordinates of the center of the map.
+    pub x_center: i32,
+    pub z_center: i32,
+    /// Dimension of the map, such as `minecraft:overworld`, if known.
+    pub dimension: Option<String>,
+    pub locked: bool,
+    /// Color ids of the 128x128 pixels, row by row from north to south.
+    pub colors: Vec<u8>,
+}
+
+impl MapItem {
+    /// Reads the map from the root compound of a map file. Returns `None`
+    /// if the colors are missing or don't have the size of a map.
+    pub fn from_compound_tag(map_compound_tag: &CompoundTag) -> Option<Self> {
+        let data = map_compound_tag.get_compound_tag("data").ok()?;
+        let colors = data.get_i8_vec("colors").ok()?;
+
+        if colors.len() != (MAP_SIZE * MAP_SIZE) as usize {
+            return None;
+        }
+
+        // Stored as a byte before 1.16 and as a name since.
+        let dimension = match data.get_str("dimension") {
+            Ok(dimension) => Some(dimension.to_string()),
+            Err(_) => match data
+                .get_i8("dimension")
+                .or_else(|_| data.get_i32("dimension").map(|dimension| dimension as i8))
+            {
+                Ok(-1) => Some("minecraft:the_nether".to_string()),
+                Ok(0) => Some("minecraft:overworld".to_string()),
+                Ok(1) => Some("minecraft:the_end".to_string()),
+                _ => None,
+            },
+        };
+
+        Some(MapItem {
+            scale: data.get_i8("scale").unwrap_or_default() as u8,
+            x_center: data.get_i32("xCenter").unwrap_or_default(),
+            z_center: data.get_i32("zCenter").unwrap_or_default(),
+            dimension,
+            locked: data.get_bool("locked").unwrap_or_default(),
+            colors: colors.iter().map(|&color| color as u8).collect(),
+        })
+    }
+
+    /// Amount of blocks covered by every pixel in each direction.
+    pub fn blocks_per_pixel(&self) -> u32 {
+        1 << self.scale.min(4)
+    }
+
+    /// Block coordinates of the north-west and south-east corners of the
+    /// area covered by the map, both included.
+    pub fn bounds(&self) -> ((i32, i32), (i32, i32)) {
+        let half_size = (MAP_SIZE * self.blocks_per_pixel() / 2) as i32;
+
+        (
+            (self.x_center - half_size, self.z_center - half_size),
+            (self.x_center + half_size - 1, self.z_center + half_size - 1),
+        )
+    }
+
+    /// Colors of the pixels with 4 bytes per pixel (red, green, blue,
+    /// alpha), row by row. Unexplored pixels and unknown color ids are
+    /// transparent.
+    pub fn rgba(&self) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(self.colors.len() * 4);
+
+        for &color in &self.colors {
+            let base = (color / 4) as usize;
+            let shade = SHADE_MULTIPLIERS[(color % 4) as usize];
+
+            match BASE_COLORS.get(base) {
+                Some(rgb) if base != 0 => {
+                    for &channel in rgb {
+                        pixels.push((u32::from(channel) * shade / 255) as u8);
+                    }
+
+                    pixels.push(255);
+                }
+                _ => pixels.extend_from_slice(&[0, 0, 0, 0]),
+            }
+        }
+
+        pixels
+    }
+
+    /// Writes the map as a 128x128 PNG image.
+    pub fn write_png<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        write_png(writer, MAP_SIZE, MAP_SIZE, RGBA, 4, &self.rgba())
+    }
+}
+
+impl WorldFolderProvider {
+    /// Ids of the maps in the `data` folder, sorted.
+    pub fn list_maps(&self) -> Result<Vec<u32>, io::Error> {
+        let mut ids: Vec<u32> = self
+            .list_data()?
+            .iter()
+            .filter_map(|name| name.strip_prefix("map_")?.parse().ok())
+            .collect();
+        ids.sort_unstable();
+
+        Ok(ids)
+    }
+
+    pub fn load_map(&self, id: u32) -> Result<MapItem, TagDecodeError> {
+        let map_compound_tag = self.load_data(&format!("map_{}", id))?;
+
+        MapItem::from_compound_tag(&map_compound_tag).ok_or_else(|| TagDecodeError::IOError {
+            io_error: io::Error::new(io::ErrorKind::InvalidData, "invalid map data"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_and_render_map() {
+        let world = tempfile::tempdir().unwrap();
+        let world_provider = WorldFolderProvider::new(world.path());
+
+        let mut colors = vec![0i8; (MAP_SIZE * MAP_SIZE) as usize];
+        // Grass in its brightest and darkest shades, then an unknown id.
+        colors[0] = 4 + 2;
+        colors[1] = 4 + 3;
+        colors[2] = -1;
+        let mut data = CompoundTag::new();
+        data.insert_i8("scale", 1);
+        data.insert_i32("xCenter", 64);
+        data.insert_i32("zCenter", -64);
+        data.insert_i8("dimension", -1);
+        data.insert_i8_vec("colors", colors);
+        let mut map_compound_tag = CompoundTag::new();
+        map_compound_tag.insert_compound_tag("data", data);
+
+        world_provider
+            .save_data("map_10", &map_compound_tag)
+            .unwrap();
+        world_provider
+            .save_data("map_2", &map_compound_tag)
+            .unwrap();
+        world_provider
+            .save_data("idcounts", &CompoundTag::new())
+            .unwrap();
+        assert_eq!(world_provider.list_maps().unwrap(), vec![2, 10]);
+
+        let map_item = world_provider.load_map(10).unwrap();
+        assert_eq!(map_item.dimension.as_deref(), Some("minecraft:the_nether"));
+        assert_eq!(map_item.bounds(), ((-64, -192), (191, 63)));
+
+        let pixels = map_item.rgba();
+        assert_eq!(
+            &pixels[..12],
+            &[127, 178, 56, 255, 67, 94, 29, 255, 0, 0, 0, 0]
+        );
+        assert_eq!(&pixels[12..16], &[0, 0, 0, 0]);
+
+        let mut png = Vec::new();
+        map_item.write_png(&mut png).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+
+        match world_provider.load_map(3) {
+            Err(TagDecodeError::IOError { io_error }) => {
+                assert_eq!(io_error.kind(), io::ErrorKind::NotFound)
+            }
+            e => panic!("Expected `IOError` but got `{:?}`", e),
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use crate::{
+    AnvilChunkMetadata, AnvilChunkProvider, ChunkLoadError, ChunkReader, ChunkSelection, Recode,
+    TimestampPolicy, WorldEditError,
+};
+use nbt::CompoundTag;
+
+/// Callback used by `MergePolicy::Custom`.
+pub type MergeCallback<'a> = dyn FnMut((i32, i32), CompoundTag, CompoundTag) -> CompoundTag + 'a;
+
+/// How `merge_worlds` resolves chunks that exist in both worlds.
+pub enum MergePolicy<'a> {
+    /// Keep the chunk with the most recent header timestamp. The destination
+    /// chunk is kept if both timestamps are equal.
+    KeepNewest,
+    /// Always replace the destination chunk with the source chunk.
+    PreferSource,
+    /// Never replace the destination chunk.
+    PreferDestination,
+    /// Save the chunk returned by the callback, which receives the chunk
+    /// coordinates, the destination chunk and the source chunk.
+    Custom(Box<MergeCallback<'a>>),
+}
+
+/// Amount of chunks affected by `merge_worlds`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct MergeSummary {
+    /// Chunks that only existed in the source world.
+    pub added: usize,
+    /// Conflicting chunks that were overwritten.
+    pub replaced: usize,
+    /// Conflicting chunks where the destination chunk was kept.
+    pub kept: usize,
+}
+
+/// Copies every selected chunk of `src` into `dst`.
+///
+/// Chunks that only exist in the source world are always copied, conflicts
+/// are resolved using the merge policy. The timestamp policy decides the
+/// header timestamp of written chunks; when preserving timestamps, a chunk
+/// returned by a custom callback gets the newest of both timestamps.
+///
+/// Copied chunks are compressed as requested by `recode`. Chunks returned by
+/// a custom callback are saved with the compression of the destination.
+pub fn merge_worlds<D, S>(
+    dst: &mut D,
+    src: &mut S,
+    selection: &ChunkSelection,
+    mut policy: MergePolicy,
+    timestamp_policy: TimestampPolicy,
+    recode: Recode,
+) -> Result<MergeSummary, WorldEditError>
+where
+    D: AnvilChunkProvider + ?Sized,
+    S: ChunkReader + ?Sized,
+{
+    let mut summary = MergeSummary::default();
+
+    for (chunk_x, chunk_z) in src.list_chunks_in(selection)? {
+        let src_metadata = src.load_chunk_metadata(chunk_x, chunk_z)?;
+        let src_timestamp = src_metadata.last_modified_timestamp();
+        let src_raw_chunk = src.load_chunk_raw(chunk_x, chunk_z)?;
+
+        let dst_metadata = match existing_metadata(dst, chunk_x, chunk_z)? {
+            Some(dst_metadata) => dst_metadata,
+            None => {
+                dst.save_chunk_raw(
+                    chunk_x,
+                    chunk_z,
+                    &src_raw_chunk.recode(recode)?,
+                    timestamp_policy.timestamp(src_timestamp),
+                )?;
+                summary.added += 1;
+                continue;
+            }
+        };
+        let dst_timestamp = dst_metadata.last_modified_timestamp();
+
+        let replace = match &mut policy {
+            MergePolicy::KeepNewest => src_timestamp > dst_timestamp,
+            MergePolicy::PreferSource => true,
+            MergePolicy::PreferDestination => false,
+            MergePolicy::Custom(callback) => {
+                let src_chunk_compound_tag = src_raw_chunk.decode()?;
+                let dst_chunk_compound_tag = dst.load_chunk(chunk_x, chunk_z)?;
+                let chunk_compound_tag = callback(
+                    (chunk_x, chunk_z),
+                    dst_chunk_compound_tag,
+                    src_chunk_compound_tag,
+                );
+
+                dst.save_chunk_with_timestamp(
+                    chunk_x,
+                    chunk_z,
+                    chunk_compound_tag,
+                    timestamp_policy.timestamp(src_timestamp.max(dst_timestamp)),
+                )?;
+                summary.replaced += 1;
+                continue;
+            }
+        };
+
+        if !replace {
+            summary.kept += 1;
+            continue;
+        }
+
+        dst.save_chunk_raw(
+            chunk_x,
+            chunk_z,
+            &src_raw_chunk.recode(recode)?,
+            timestamp_policy.timestamp(src_timestamp),
+        )?;
+        summary.replaced += 1;
+    }
+
+    Ok(summary)
+}
+
+/// Metadata of the chunk, or `None` if the region or the chunk does not exist.
+fn existing_metadata<P: ChunkReader + ?Sized>(
+    provider: &mut P,
+    chunk_x: i32,
+    chunk_z: i32,
+) -> Result<Option<AnvilChunkMetadata>, ChunkLoadError> {
+    match provider.load_chunk_metadata(chunk_x, chunk_z) {
+        Ok(metadata) if metadata.is_empty() => Ok(None),
+        Ok(metadata) => Ok(Some(metadata)),
+        Err(ChunkLoadError::RegionNotFound { .. }) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilOptions, Compression, FolderChunkProvider};
+    use tempfile::TempDir;
+
+    fn chunk(value: i32) -> CompoundTag {
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("value", value);
+
+        chunk_compound_tag
+    }
+
+    // Destination has chunks (0, 0) and (1, 0), source has (1, 0) and (40, 0).
+    fn worlds(dst_folder: &TempDir, src_folder: &TempDir) {
+        let dst = FolderChunkProvider::new(dst_folder.path().to_str().unwrap());
+        dst.save_chunk_with_timestamp(0, 0, chunk(1), 100).unwrap();
+        dst.save_chunk_with_timestamp(1, 0, chunk(2), 100).unwrap();
+
+        let src = FolderChunkProvider::new(src_folder.path().to_str().unwrap());
+        src.save_chunk_with_timestamp(1, 0, chunk(3), 200).unwrap();
+        src.save_chunk_with_timestamp(40, 0, chunk(4), 200).unwrap();
+    }
+
+    fn merge(policy: MergePolicy) -> (MergeSummary, Vec<i32>) {
+        let dst_folder = tempfile::tempdir().unwrap();
+        let src_folder = tempfile::tempdir().unwrap();
+        worlds(&dst_folder, &src_folder);
+
+        let mut dst = FolderChunkProvider::new(dst_folder.path().to_str().unwrap());
+        let mut src = FolderChunkProvider::new(src_folder.path().to_str().unwrap());
+        let summary = merge_worlds(
+            &mut dst,
+            &mut src,
+            &ChunkSelection::All,
+            policy,
+            TimestampPolicy::Preserve,
+            Recode::default(),
+        )
+        .unwrap();
+
+        let values = [(0, 0), (1, 0), (40, 0)]
+            .iter()
+            .map(|&(chunk_x, chunk_z)| {
+                let chunk_compound_tag = dst.load_chunk(chunk_x, chunk_z).unwrap();
+                chunk_compound_tag.get_i32("value").unwrap()
+            })
+            .collect();
+
+        (summary, values)
+    }
+
+    #[test]
+    fn merge_keep_newest() {
+        let (summary, values) = merge(MergePolicy::KeepNewest);
+
+        assert_eq!(summary, MergeSummary { added: 1, replaced: 1, kept: 0 });
+        assert_eq!(values, vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn merge_prefer_destination() {
+        let (summary, values) = merge(MergePolicy::PreferDestination);
+
+        assert_eq!(summary, MergeSummary { added: 1, replaced: 0, kept: 1 });
+        assert_eq!(values, vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn merge_prefer_source() {
+        let (summary, values) = merge(MergePolicy::PreferSource);
+
+        assert_eq!(summary, MergeSummary { added: 1, replaced: 1, kept: 0 });
+        assert_eq!(values, vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn merge_custom() {
+        let policy = MergePolicy::Custom(Box::new(|coords, dst, src| {
+            assert_eq!(coords, (1, 0));
+            chunk(dst.get_i32("value").unwrap() + src.get_i32("value").unwrap())
+        }));
+        let (summary, values) = merge(policy);
+
+        assert_eq!(summary, MergeSummary { added: 1, replaced: 1, kept: 0 });
+        assert_eq!(values, vec![1, 5, 4]);
+    }
+
+    #[test]
+    fn merge_selection() {
+        let dst_folder = tempfile::tempdir().unwrap();
+        let src_folder = tempfile::tempdir().unwrap();
+        worlds(&dst_folder, &src_folder);
+
+        let mut dst = FolderChunkProvider::new(dst_folder.path().to_str().unwrap());
+        let mut src = FolderChunkProvider::new(src_folder.path().to_str().unwrap());
+        let summary = merge_worlds(
+            &mut dst,
+            &mut src,
+            &ChunkSelection::rect((0, 0), (31, 31)),
+            MergePolicy::PreferSource,
+            TimestampPolicy::Preserve,
+            Recode::default(),
+        )
+        .unwrap();
+
+        assert_eq!(summary, MergeSummary { added: 0, replaced: 1, kept: 0 });
+        assert!(dst.try_load_chunk(40, 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn merge_recode_preserve() {
+        let dst_folder = tempfile::tempdir().unwrap();
+        let src_folder = tempfile::tempdir().unwrap();
+
+        let mut dst = FolderChunkProvider::new(dst_folder.path().to_str().unwrap());
+        let mut src = FolderChunkProvider::with_options(
+            src_folder.path().to_str().unwrap(),
+            AnvilOptions::new().compression(Compression::Gzip),
+        );
+        src.save_chunk_with_timestamp(0, 0, chunk(1), 100).unwrap();
+        src.save_chunk_with_timestamp(1, 0, chunk(2), 100).unwrap();
+
+        merge_worlds(
+            &mut dst,
+            &mut src,
+            &ChunkSelection::rect((0, 0), (0, 0)),
+            MergePolicy::PreferSource,
+            TimestampPolicy::Preserve,
+            Recode::Preserve,
+        )
+        .unwrap();
+        merge_worlds(
+            &mut dst,
+            &mut src,
+            &ChunkSelection::rect((1, 0), (1, 0)),
+            MergePolicy::PreferSource,
+            TimestampPolicy::Preserve,
+            Recode::ForceZlib,
+        )
+        .unwrap();
+
+        let preserved = dst.load_chunk_raw(0, 0).unwrap();
+        assert_eq!(preserved, src.load_chunk_raw(0, 0).unwrap());
+        assert_eq!(preserved.compression_scheme(), Compression::Gzip.id());
+
+        let recoded = dst.load_chunk_raw(1, 0).unwrap();
+        assert_eq!(recoded.compression_scheme(), Compression::Zlib.id());
+        assert_eq!(recoded.decode().unwrap().get_i32("value").unwrap(), 2);
+    }
+}
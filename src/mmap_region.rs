@@ -0,0 +1,211 @@
+use crate::{AnvilRegion, SetLen};
+use memmap2::MmapMut;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Region file written through a memory mapping.
+///
+/// Reads, writes and seeks are memory copies instead of system calls, which
+/// is cheaper for the many small header updates of workloads writing many
+/// chunks of one region. The file is remapped when it grows. Writes reach
+/// the disk when `flush` is called, or whenever the system writes the
+/// mapped pages back.
+///
+/// The file must not be truncated by another process while it is mapped.
+pub struct MmapRegionFile {
+    file: File,
+    map: Option<MmapMut>,
+    length: u64,
+    position: u64,
+}
+
+impl MmapRegionFile {
+    /// Maps the file, which must be open for reading and writing.
+    pub fn new(file: File) -> Result<Self, io::Error> {
+        let length = file.metadata()?.len();
+        let mut mmap_file = MmapRegionFile {
+            file,
+            map: None,
+            length,
+            position: 0,
+        };
+        mmap_file.remap()?;
+
+        Ok(mmap_file)
+    }
+
+    /// Opens and maps the file at the path, creating it if needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let file = OpenOptions::new()
+            .write(true)
+            .read(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        Self::new(file)
+    }
+
+    /// Flushes the mapping and returns the file.
+    pub fn into_inner(mut self) -> Result<File, io::Error> {
+        self.flush()?;
+
+        Ok(self.file)
+    }
+
+    fn remap(&mut self) -> Result<(), io::Error> {
+        self.map = None;
+
+        // Empty files can't be mapped.
+        if self.length > 0 {
+            // Safe as long as the file is not truncated elsewhere, as
+            // documented on the type.
+            self.map = Some(unsafe { MmapMut::map_mut(&self.file)? });
+        }
+
+        Ok(())
+    }
+
+    fn mapped_bytes(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or(&[])
+    }
+}
+
+impl Read for MmapRegionFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes = self.mapped_bytes();
+        let start = (self.position as usize).min(bytes.len());
+        let length = buf.len().min(bytes.len() - start);
+
+        buf[..length].copy_from_slice(&bytes[start..start + length]);
+        self.position += length as u64;
+
+        Ok(length)
+    }
+}
+
+impl Write for MmapRegionFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let end = self.position + buf.len() as u64;
+
+        if end > self.length {
+            self.set_len(end)?;
+        }
+
+        if let Some(map) = self.map.as_mut() {
+            let start = self.position as usize;
+            map[start..start + buf.len()].copy_from_slice(buf);
+        }
+        self.position = end;
+
+        Ok(buf.len())
+    }
+
+    /// Writes the modified pages of the mapping to the disk.
+    fn flush(&mut self) -> io::Result<()> {
+        match &self.map {
+            Some(map) => map.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Seek for MmapRegionFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )),
+        }
+    }
+}
+
+impl SetLen for MmapRegionFile {
+    fn set_len(&mut self, length: u64) -> Result<(), io::Error> {
+        // Flushed first, so shrinking doesn't lose pages still mapped.
+        self.flush()?;
+        self.map = None;
+        self.file.set_len(length)?;
+        self.length = length;
+
+        self.remap()
+    }
+}
+
+impl AnvilRegion<MmapRegionFile> {
+    /// Opens the region file for writing through a memory mapping, creating
+    /// it if needed. Call `flush` to write the modifications to the disk.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::AnvilRegion;
+    /// use nbt::CompoundTag;
+    ///
+    /// # let folder = tempfile::tempdir().unwrap();
+    /// # let path = folder.path().join("r.0.0.mca");
+    /// let mut region = AnvilRegion::mmap(&path).unwrap();
+    /// region.write_chunk(1, 2, CompoundTag::new()).unwrap();
+    /// region.flush().unwrap();
+    ///
+    /// assert!(AnvilRegion::file(&path).unwrap().read_chunk(1, 2).is_ok());
+    /// ```
+    pub fn mmap<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        Self::new(MmapRegionFile::open(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nbt::CompoundTag;
+    use std::fs;
+
+    #[test]
+    fn test_mmap_region_writes() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("r.0.0.mca");
+        fs::copy("test/region/r.0.0.mca", &path).unwrap();
+
+        let mut region = AnvilRegion::mmap(&path).unwrap();
+        let chunk_compound_tag = region.read_chunk(4, 2).unwrap();
+        let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+
+        for chunk_x in 0..32 {
+            let mut chunk_compound_tag = CompoundTag::new();
+            chunk_compound_tag.insert_i32("x", chunk_x as i32);
+            region.write_chunk(chunk_x, 31, chunk_compound_tag).unwrap();
+        }
+        region.delete_chunk(4, 2).unwrap();
+        region.flush().unwrap();
+        drop(region);
+
+        let mut region = AnvilRegion::file(&path).unwrap();
+        assert!(region.read_chunk(4, 2).is_err());
+        let chunk_compound_tag = region.read_chunk(17, 31).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("x").unwrap(), 17);
+        assert!(region.verify().unwrap().is_empty());
+
+        // Empty files are mapped once they grow.
+        let path = folder.path().join("r.1.0.mca");
+        let mut region = AnvilRegion::mmap(&path).unwrap();
+        region.write_chunk(0, 0, CompoundTag::new()).unwrap();
+        region.truncate_garbage().unwrap();
+        region.flush().unwrap();
+        assert!(AnvilRegion::file(&path).unwrap().read_chunk(0, 0).is_ok());
+    }
+}
@@ -0,0 +1,301 @@
+use crate::{ChunkLoadError, ChunkReader, ChunkSelection};
+use nbt::{CompoundTag, Tag};
+use std::str::FromStr;
+
+/// Path to values inside the NBT of a chunk, such as
+/// `Level.Entities[*].id`.
+///
+/// A path is a list of keys separated by dots, each key optionally followed
+/// by one or more indexes:
+///
+/// - `key` selects the tag with this name inside a compound.
+/// - `[n]` selects the element `n` of a list or an array, starting at 0.
+/// - `[*]` selects every element of a list or an array.
+///
+/// Parts of the path which don't exist or don't have the expected type
+/// select nothing.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::NbtPath;
+/// use nbt::{CompoundTag, Tag};
+///
+/// let mut entity = CompoundTag::new();
+/// entity.insert_str("id", "minecraft:cow");
+/// let mut level = CompoundTag::new();
+/// level.insert_compound_tag_vec("Entities", vec![entity]);
+/// let mut chunk_compound_tag = CompoundTag::new();
+/// chunk_compound_tag.insert_compound_tag("Level", level);
+///
+/// let path: NbtPath = "Level.Entities[*].id".parse().unwrap();
+/// let values = path.select(&chunk_compound_tag);
+///
+/// assert_eq!(values.len(), 1);
+/// assert!(matches!(&values[0], Tag::String(id) if id == "minecraft:cow"));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NbtPath {
+    segments: Vec<PathSegment>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+    AllElements,
+}
+
+/// Error parsing a `NbtPath`, at the given byte position of the path.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NbtPathError {
+    /// The path, or a key between two dots, is empty.
+    EmptyKey { position: usize },
+    /// An index is not closed by `]`, or is neither a number nor `*`.
+    InvalidIndex { position: usize },
+    /// An index is followed by something other than `.`, `[` or the end of
+    /// the path.
+    UnexpectedCharacter { position: usize },
+}
+
+impl NbtPath {
+    pub fn parse(path: &str) -> Result<Self, NbtPathError> {
+        let mut segments = Vec::new();
+        let mut position = 0;
+
+        loop {
+            let key_end = path[position..]
+                .find(&['.', '['][..])
+                .map_or(path.len(), |offset| position + offset);
+
+            if key_end == position {
+                return Err(NbtPathError::EmptyKey { position });
+            }
+
+            segments.push(PathSegment::Key(path[position..key_end].to_string()));
+            position = key_end;
+
+            while path[position..].starts_with('[') {
+                let index_end = path[position..]
+                    .find(']')
+                    .map(|offset| position + offset)
+                    .ok_or(NbtPathError::InvalidIndex { position })?;
+
+                let segment = match &path[position + 1..index_end] {
+                    "*" => PathSegment::AllElements,
+                    index => PathSegment::Index(
+                        index
+                            .parse()
+                            .map_err(|_| NbtPathError::InvalidIndex { position })?,
+                    ),
+                };
+
+                segments.push(segment);
+                position = index_end + 1;
+            }
+
+            if position == path.len() {
+                return Ok(NbtPath { segments });
+            }
+
+            if !path[position..].starts_with('.') {
+                return Err(NbtPathError::UnexpectedCharacter { position });
+            }
+
+            position += 1;
+        }
+    }
+
+    /// Returns the values selected by the path, in the order they are
+    /// stored.
+    ///
+    /// Elements of arrays are returned as the tag of their type, for example
+    /// `Tag::Long` for the elements of a `Tag::LongArray`.
+    pub fn select(&self, compound_tag: &CompoundTag) -> Vec<Tag> {
+        let mut values = Vec::new();
+
+        if let Some((PathSegment::Key(key), rest)) = self.segments.split_first() {
+            if let Ok(tag) = compound_tag.get::<&Tag>(key) {
+                select_values(tag, rest, &mut values);
+            }
+        }
+
+        values
+    }
+}
+
+impl FromStr for NbtPath {
+    type Err = NbtPathError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        NbtPath::parse(path)
+    }
+}
+
+fn select_values(tag: &Tag, segments: &[PathSegment], values: &mut Vec<Tag>) {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => return values.push(tag.clone()),
+    };
+
+    match (segment, tag) {
+        (PathSegment::Key(key), Tag::Compound(compound_tag)) => {
+            if let Ok(tag) = compound_tag.get::<&Tag>(key) {
+                select_values(tag, rest, values);
+            }
+        }
+        (PathSegment::Index(index), Tag::List(tags)) => {
+            if let Some(tag) = tags.get(*index) {
+                select_values(tag, rest, values);
+            }
+        }
+        (PathSegment::AllElements, Tag::List(tags)) => {
+            for tag in tags {
+                select_values(tag, rest, values);
+            }
+        }
+        (PathSegment::Index(index), _) => {
+            if let Some(element) = array_element(tag, *index) {
+                select_values(&element, rest, values);
+            }
+        }
+        (PathSegment::AllElements, _) => {
+            for index in 0.. {
+                match array_element(tag, index) {
+                    Some(element) => select_values(&element, rest, values),
+                    None => break,
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Element of a byte, int or long array.
+fn array_element(tag: &Tag, index: usize) -> Option<Tag> {
+    match tag {
+        Tag::ByteArray(array) => array.get(index).map(|&value| Tag::Byte(value)),
+        Tag::IntArray(array) => array.get(index).map(|&value| Tag::Int(value)),
+        Tag::LongArray(array) => array.get(index).map(|&value| Tag::Long(value)),
+        _ => None,
+    }
+}
+
+/// Value selected by `query`, with the coordinates of its chunk.
+#[derive(Clone, Debug)]
+pub struct QueryMatch {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    pub value: Tag,
+}
+
+/// Evaluates the path on every selected chunk, returning the selected
+/// values ordered by chunk, like a grep for worlds.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{query, ChunkSelection, FolderChunkProvider};
+/// use nbt::Tag;
+///
+/// let mut chunk_provider = FolderChunkProvider::new("test/region");
+/// let selection = ChunkSelection::rect((4, 2), (5, 2));
+/// let path = "Level.xPos".parse().unwrap();
+///
+/// let matches = query(&mut chunk_provider, &selection, &path).unwrap();
+///
+/// assert_eq!(matches.len(), 2);
+/// assert!(matches
+///     .iter()
+///     .all(|m| matches!(m.value, Tag::Int(x_pos) if x_pos == m.chunk_x)));
+/// ```
+pub fn query<P: ChunkReader + ?Sized>(
+    provider: &mut P,
+    selection: &ChunkSelection,
+    path: &NbtPath,
+) -> Result<Vec<QueryMatch>, ChunkLoadError> {
+    let mut matches = Vec::new();
+
+    for (chunk_x, chunk_z) in provider.list_chunks_in(selection)? {
+        let chunk_compound_tag = provider.load_chunk(chunk_x, chunk_z)?;
+
+        for value in path.select(&chunk_compound_tag) {
+            matches.push(QueryMatch {
+                chunk_x,
+                chunk_z,
+                value,
+            });
+        }
+    }
+
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nbt_path() {
+        assert_eq!(
+            NbtPath::parse("Level.Sections[2].BlockStates[*]").unwrap(),
+            NbtPath {
+                segments: vec![
+                    PathSegment::Key("Level".to_string()),
+                    PathSegment::Key("Sections".to_string()),
+                    PathSegment::Index(2),
+                    PathSegment::Key("BlockStates".to_string()),
+                    PathSegment::AllElements,
+                ]
+            }
+        );
+        assert_eq!(
+            NbtPath::parse(""),
+            Err(NbtPathError::EmptyKey { position: 0 })
+        );
+        assert_eq!(
+            NbtPath::parse("Level..xPos"),
+            Err(NbtPathError::EmptyKey { position: 6 })
+        );
+        assert_eq!(
+            NbtPath::parse("Level.Sections[x]"),
+            Err(NbtPathError::InvalidIndex { position: 14 })
+        );
+        assert_eq!(
+            NbtPath::parse("Level.Sections[0"),
+            Err(NbtPathError::InvalidIndex { position: 14 })
+        );
+        assert_eq!(
+            NbtPath::parse("Level.Sections[0]Y"),
+            Err(NbtPathError::UnexpectedCharacter { position: 17 })
+        );
+    }
+
+    #[test]
+    fn test_select_lists_and_arrays() {
+        let mut section = CompoundTag::new();
+        section.insert_i64_vec("BlockStates", vec![1, 2, 3]);
+        let mut level = CompoundTag::new();
+        level.insert_compound_tag_vec("Sections", vec![CompoundTag::new(), section]);
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_compound_tag("Level", level);
+
+        let select = |path: &str| -> Vec<i64> {
+            NbtPath::parse(path)
+                .unwrap()
+                .select(&chunk_compound_tag)
+                .into_iter()
+                .map(|value| match value {
+                    Tag::Long(value) => value,
+                    value => panic!("Expected `Long` but got `{:?}`", value),
+                })
+                .collect()
+        };
+
+        assert_eq!(select("Level.Sections[*].BlockStates[*]"), vec![1, 2, 3]);
+        assert_eq!(select("Level.Sections[1].BlockStates[2]"), vec![3]);
+        assert!(select("Level.Sections[1].BlockStates[3]").is_empty());
+        assert!(select("Level.Sections[2].BlockStates[0]").is_empty());
+        assert!(select("Level.Sections.BlockStates").is_empty());
+    }
+}
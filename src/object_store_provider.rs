@@ -0,0 +1,439 @@
+use crate::{
+    chunk_coords_to_region_coords, parse_region_file_name, AnvilRegionHeader, ChunkDeleter,
+    ChunkLoadError, ChunkPayload, ChunkReader, ChunkSaveError, ChunkWriter, FolderChunkProvider,
+    RawChunk, RegionReader, WorldEditError,
+};
+use futures::executor::block_on;
+use nbt::CompoundTag;
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, PutPayload};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Provider reading and writing the region files of an `object_store`, such
+/// as an S3, GCS or Azure bucket.
+///
+/// A region file is downloaded into a local staging folder the first time
+/// one of its chunks is used, and chunks are then loaded and saved there by
+/// a `FolderChunkProvider`. Modified region files are only uploaded by
+/// `flush`, dropping the provider without flushing loses the changes.
+///
+/// The requests are made by blocking on the futures of the store, so stores
+/// which need a tokio runtime must be used from inside one, for example
+/// after `Handle::enter`.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::ObjectStoreChunkProvider;
+/// use nbt::CompoundTag;
+/// use object_store::memory::InMemory;
+/// use std::sync::Arc;
+///
+/// # let staging = tempfile::tempdir().unwrap();
+/// # let staging = staging.path();
+/// let store = Arc::new(InMemory::new());
+/// let chunk_provider = ObjectStoreChunkProvider::new(store, "world/region", staging);
+///
+/// chunk_provider.save_chunk(1, 2, CompoundTag::new()).unwrap();
+/// chunk_provider.flush().unwrap();
+/// ```
+pub struct ObjectStoreChunkProvider<P = CompoundTag> {
+    store: Arc<dyn ObjectStore>,
+    /// Location of the region files in the store.
+    prefix: ObjectPath,
+    /// Provider over the staging folder.
+    provider: FolderChunkProvider<P>,
+    staged: Mutex<StagedRegions>,
+}
+
+/// Regions of the staging folder.
+#[derive(Default)]
+struct StagedRegions {
+    /// Regions downloaded into the staging folder, or known to be missing
+    /// from the store.
+    downloaded: HashSet<(i32, i32)>,
+    /// Regions modified since the last flush.
+    modified: HashSet<(i32, i32)>,
+}
+
+fn store_error(e: object_store::Error) -> io::Error {
+    match e {
+        object_store::Error::NotFound { .. } => io::Error::new(io::ErrorKind::NotFound, e),
+        e => io::Error::other(e),
+    }
+}
+
+impl ObjectStoreChunkProvider {
+    /// Creates a provider for the region files stored under `prefix`, for
+    /// example `"world/region"`, staging them in the given local folder.
+    pub fn new<F: Into<PathBuf>>(store: Arc<dyn ObjectStore>, prefix: &str, staging: F) -> Self {
+        Self::with_provider(store, prefix, FolderChunkProvider::new(staging))
+    }
+}
+
+impl<P: ChunkPayload> ObjectStoreChunkProvider<P> {
+    /// Same as `new`, but stages the region files with the given provider,
+    /// so its options are used to load and save chunks. The region files
+    /// already in its folder are replaced by the ones of the store.
+    pub fn with_provider(
+        store: Arc<dyn ObjectStore>,
+        prefix: &str,
+        provider: FolderChunkProvider<P>,
+    ) -> Self {
+        ObjectStoreChunkProvider {
+            store,
+            prefix: ObjectPath::from(prefix),
+            provider,
+            staged: Mutex::new(StagedRegions::default()),
+        }
+    }
+
+    /// Returns the provider over the staging folder. Writes made through
+    /// it are not uploaded.
+    pub fn provider(&self) -> &FolderChunkProvider<P> {
+        &self.provider
+    }
+
+    /// Coordinates of the regions modified since the last flush, sorted.
+    pub fn modified_regions(&self) -> Vec<(i32, i32)> {
+        let mut regions: Vec<_> = self
+            .staged
+            .lock()
+            .unwrap()
+            .modified
+            .iter()
+            .copied()
+            .collect();
+        regions.sort_unstable();
+
+        regions
+    }
+
+    fn object_path(&self, region_x: i32, region_z: i32) -> ObjectPath {
+        self.prefix.child(self.provider.region_file_name(region_x, region_z))
+    }
+
+    fn staging_path(&self, region_x: i32, region_z: i32) -> PathBuf {
+        let region_name = self.provider.region_file_name(region_x, region_z);
+
+        self.provider.folder_path.join(region_name)
+    }
+
+    /// Downloads the region file into the staging folder, unless it was
+    /// already downloaded.
+    fn stage_region(&self, region_x: i32, region_z: i32) -> Result<(), io::Error> {
+        let mut staged = self.staged.lock().unwrap();
+
+        if staged.downloaded.contains(&(region_x, region_z)) {
+            return Ok(());
+        }
+
+        let staging_path = self.staging_path(region_x, region_z);
+        let object_path = self.object_path(region_x, region_z);
+        let bytes = block_on(async {
+            match self.store.get(&object_path).await {
+                Ok(result) => result.bytes().await.map(Some),
+                Err(object_store::Error::NotFound { .. }) => Ok(None),
+                Err(e) => Err(e),
+            }
+        })
+        .map_err(store_error)?;
+
+        // A region file may still be open from a previous session.
+        self.provider.close_regions()?;
+
+        match bytes {
+            Some(bytes) => {
+                fs::create_dir_all(&self.provider.folder_path)?;
+                fs::write(staging_path, bytes)?;
+            }
+            None if staging_path.exists() => fs::remove_file(staging_path)?,
+            None => {}
+        }
+
+        staged.downloaded.insert((region_x, region_z));
+
+        Ok(())
+    }
+
+    fn stage_chunk_region(&self, chunk_x: i32, chunk_z: i32) -> Result<(), io::Error> {
+        let (region_x, region_z) = chunk_coords_to_region_coords(chunk_x, chunk_z);
+
+        self.stage_region(region_x, region_z)
+    }
+
+    fn mark_modified(&self, chunk_x: i32, chunk_z: i32) {
+        let region = chunk_coords_to_region_coords(chunk_x, chunk_z);
+
+        self.staged.lock().unwrap().modified.insert(region);
+    }
+
+    /// Uploads the region files modified since the last flush. Region files
+    /// removed from the staging folder are deleted from the store.
+    pub fn flush(&self) -> Result<(), io::Error> {
+        let mut staged = self.staged.lock().unwrap();
+
+        // Open regions may keep writes in memory.
+        self.provider.close_regions()?;
+
+        let mut modified: Vec<_> = staged.modified.iter().copied().collect();
+        modified.sort_unstable();
+
+        for (region_x, region_z) in modified {
+            let staging_path = self.staging_path(region_x, region_z);
+            let object_path = self.object_path(region_x, region_z);
+
+            let result = if staging_path.exists() {
+                let payload = PutPayload::from(fs::read(staging_path)?);
+                block_on(self.store.put(&object_path, payload)).map(|_| ())
+            } else {
+                match block_on(self.store.delete(&object_path)) {
+                    Err(object_store::Error::NotFound { .. }) => Ok(()),
+                    result => result,
+                }
+            };
+
+            result.map_err(store_error)?;
+            staged.modified.remove(&(region_x, region_z));
+        }
+
+        Ok(())
+    }
+
+    pub fn load_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        self.stage_chunk_region(chunk_x, chunk_z)?;
+        self.provider.load_chunk(chunk_x, chunk_z)
+    }
+
+    pub fn try_load_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<Option<P>, ChunkLoadError> {
+        self.stage_chunk_region(chunk_x, chunk_z)?;
+        self.provider.try_load_chunk(chunk_x, chunk_z)
+    }
+
+    pub fn save_chunk(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        self.stage_chunk_region(chunk_x, chunk_z)?;
+        self.mark_modified(chunk_x, chunk_z);
+        self.provider
+            .save_chunk(chunk_x, chunk_z, chunk_compound_tag)
+    }
+
+    pub fn save_chunk_with_timestamp(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        self.stage_chunk_region(chunk_x, chunk_z)?;
+        self.mark_modified(chunk_x, chunk_z);
+        self.provider.save_chunk_with_timestamp(
+            chunk_x,
+            chunk_z,
+            chunk_compound_tag,
+            last_modified_timestamp,
+        )
+    }
+
+    pub fn save_chunk_raw(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), WorldEditError> {
+        self.stage_chunk_region(chunk_x, chunk_z)?;
+        self.mark_modified(chunk_x, chunk_z);
+        self.provider
+            .save_chunk_raw(chunk_x, chunk_z, raw_chunk, last_modified_timestamp)
+    }
+
+    pub fn delete_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError> {
+        self.stage_chunk_region(chunk_x, chunk_z)?;
+        self.mark_modified(chunk_x, chunk_z);
+        self.provider.delete_chunk(chunk_x, chunk_z)
+    }
+
+    /// Lists the regions of the store, along with the regions only saved in
+    /// the staging folder, sorted.
+    pub fn list_regions(&self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        let list_result =
+            block_on(self.store.list_with_delimiter(Some(&self.prefix))).map_err(store_error)?;
+        let mut regions: HashSet<_> = list_result
+            .objects
+            .iter()
+            .filter_map(|object| parse_region_file_name(object.location.filename()?))
+            .collect();
+
+        let staged = self.staged.lock().unwrap();
+        for region in &staged.modified {
+            let (region_x, region_z) = *region;
+            if self.staging_path(region_x, region_z).exists() {
+                regions.insert(*region);
+            } else {
+                regions.remove(region);
+            }
+        }
+
+        let mut regions: Vec<_> = regions.into_iter().collect();
+        regions.sort_unstable();
+
+        Ok(regions)
+    }
+
+    pub fn list_chunks(&self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        let mut c = vec![];
+
+        for (region_x, region_z) in self.list_regions()? {
+            let header = self.load_header(region_x, region_z)?;
+
+            for ((region_chunk_x, region_chunk_z), _) in header.chunks() {
+                c.push((
+                    (region_x * 32) + i32::from(region_chunk_x),
+                    (region_z * 32) + i32::from(region_chunk_z),
+                ));
+            }
+        }
+
+        Ok(c)
+    }
+
+    fn load_header(
+        &self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<AnvilRegionHeader, ChunkLoadError> {
+        self.stage_region(region_x, region_z)?;
+
+        let mut region = self.provider.region_reader(region_x, region_z)?;
+
+        Ok(AnvilRegionHeader::from_reader(&mut region)?)
+    }
+}
+
+impl<P: ChunkPayload> ChunkReader<P> for ObjectStoreChunkProvider<P> {
+    fn get_region(&mut self, region_x: i32, region_z: i32) -> Result<RegionReader, ChunkLoadError> {
+        self.stage_region(region_x, region_z)?;
+        self.provider.get_region(region_x, region_z)
+    }
+    fn load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        ObjectStoreChunkProvider::load_chunk(self, chunk_x, chunk_z)
+    }
+    fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        ObjectStoreChunkProvider::list_chunks(self)
+    }
+    fn list_regions(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        ObjectStoreChunkProvider::list_regions(self)
+    }
+    fn load_region_header(
+        &mut self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<AnvilRegionHeader, ChunkLoadError> {
+        self.load_header(region_x, region_z)
+    }
+    fn load_chunk_raw(&mut self, chunk_x: i32, chunk_z: i32) -> Result<RawChunk, ChunkLoadError> {
+        self.stage_chunk_region(chunk_x, chunk_z)?;
+        self.provider.load_chunk_raw(chunk_x, chunk_z)
+    }
+}
+
+impl<P: ChunkPayload> ChunkWriter<P> for ObjectStoreChunkProvider<P> {
+    fn save_chunk_with_timestamp(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        ObjectStoreChunkProvider::save_chunk_with_timestamp(
+            self,
+            chunk_x,
+            chunk_z,
+            chunk_compound_tag,
+            last_modified_timestamp,
+        )
+    }
+    fn save_chunk(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        ObjectStoreChunkProvider::save_chunk(self, chunk_x, chunk_z, chunk_compound_tag)
+    }
+    fn save_chunk_raw(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), WorldEditError> {
+        ObjectStoreChunkProvider::save_chunk_raw(
+            self,
+            chunk_x,
+            chunk_z,
+            raw_chunk,
+            last_modified_timestamp,
+        )
+    }
+}
+
+impl<P: ChunkPayload> ChunkDeleter<P> for ObjectStoreChunkProvider<P> {
+    fn delete_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError> {
+        ObjectStoreChunkProvider::delete_chunk(self, chunk_x, chunk_z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_store::memory::InMemory;
+
+    #[test]
+    fn test_object_store_flush() {
+        let store = Arc::new(InMemory::new());
+        let region_bytes = fs::read("test/region/r.0.0.mca").unwrap();
+        block_on(store.put(
+            &ObjectPath::from("world/region/r.0.0.mca"),
+            PutPayload::from(region_bytes),
+        ))
+        .unwrap();
+
+        let staging = tempfile::tempdir().unwrap();
+        let chunk_provider =
+            ObjectStoreChunkProvider::new(store.clone(), "world/region", staging.path());
+        assert!(chunk_provider.load_chunk(4, 2).is_ok());
+        assert_eq!(chunk_provider.list_chunks().unwrap().len(), 277);
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("value", 7);
+        chunk_provider
+            .save_chunk(-1, 0, chunk_compound_tag)
+            .unwrap();
+        assert_eq!(
+            chunk_provider.list_regions().unwrap(),
+            vec![(-1, 0), (0, 0)]
+        );
+        assert_eq!(chunk_provider.modified_regions(), vec![(-1, 0)]);
+        assert!(block_on(store.head(&ObjectPath::from("world/region/r.-1.0.mca"))).is_err());
+
+        chunk_provider.flush().unwrap();
+        assert!(chunk_provider.modified_regions().is_empty());
+
+        // Another provider only sees the store.
+        let staging = tempfile::tempdir().unwrap();
+        let chunk_provider = ObjectStoreChunkProvider::new(store, "world/region", staging.path());
+        let chunk_compound_tag = chunk_provider.load_chunk(-1, 0).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("value").unwrap(), 7);
+        assert!(chunk_provider.try_load_chunk(-1, 1).unwrap().is_none());
+    }
+}
@@ -0,0 +1,312 @@
+use crate::{
+    CacheBudget, Compression, CoordinateCheck, RegionNaming, SectorAllocation, SharedNaming,
+    TimestampPolicy,
+};
+use std::sync::Arc;
+
+/// Default zlib/gzip compression level, same as the one used by Minecraft.
+pub const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
+
+/// When written region files are synced to disk.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum SyncPolicy {
+    /// Leave it to the operating system.
+    #[default]
+    Never,
+    /// Sync the region file data after every saved or deleted chunk.
+    EveryWrite,
+}
+
+/// Order of the coordinates returned by `list_chunks`, `list_chunks_in` and
+/// `list_regions`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ListingOrder {
+    /// Order in which the region files are found, which depends on the
+    /// platform and the file system.
+    #[default]
+    Unsorted,
+    /// Regions sorted by z then x, the chunks of each region sorted by z
+    /// then x.
+    RegionMajor,
+    /// Z-order curve, so that coordinates close in the listing are close in
+    /// the world. Chunks of the same region stay together.
+    ZOrder,
+}
+
+impl ListingOrder {
+    /// Sorts chunk coordinates in this order.
+    pub fn sort_chunks(self, chunks: &mut [(i32, i32)]) {
+        match self {
+            ListingOrder::Unsorted => {}
+            ListingOrder::RegionMajor => chunks.sort_unstable_by_key(|&(chunk_x, chunk_z)| {
+                (chunk_z >> 5, chunk_x >> 5, chunk_z, chunk_x)
+            }),
+            ListingOrder::ZOrder => chunks.sort_unstable_by_key(|&(x, z)| z_order_index(x, z)),
+        }
+    }
+
+    /// Sorts region coordinates in this order.
+    pub fn sort_regions(self, regions: &mut [(i32, i32)]) {
+        match self {
+            ListingOrder::Unsorted => {}
+            ListingOrder::RegionMajor => {
+                regions.sort_unstable_by_key(|&(region_x, region_z)| (region_z, region_x))
+            }
+            ListingOrder::ZOrder => regions.sort_unstable_by_key(|&(x, z)| z_order_index(x, z)),
+        }
+    }
+}
+
+/// Position of the coordinates on the Z-order curve. The sign bits are
+/// flipped so that negative coordinates come first, which keeps every
+/// aligned square of coordinates together.
+fn z_order_index(x: i32, z: i32) -> u64 {
+    let spread = |value: i32| {
+        let mut value = u64::from(value as u32 ^ 0x8000_0000);
+        value = (value | (value << 16)) & 0x0000_ffff_0000_ffff;
+        value = (value | (value << 8)) & 0x00ff_00ff_00ff_00ff;
+        value = (value | (value << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+        value = (value | (value << 2)) & 0x3333_3333_3333_3333;
+        (value | (value << 1)) & 0x5555_5555_5555_5555
+    };
+
+    spread(x) | (spread(z) << 1)
+}
+
+/// Configuration of a `FolderChunkProvider`.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{AnvilOptions, Compression, FolderChunkProvider, SyncPolicy};
+///
+/// let options = AnvilOptions::new()
+///     .compression(Compression::Gzip)
+///     .compression_level(9)
+///     .max_open_regions(64)
+///     .sync_policy(SyncPolicy::EveryWrite);
+///
+/// let chunk_provider = FolderChunkProvider::with_options("test/region", options);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AnvilOptions {
+    pub(crate) compression: Compression,
+    pub(crate) compression_level: u32,
+    pub(crate) read_only: bool,
+    pub(crate) max_open_regions: usize,
+    pub(crate) timestamp_policy: TimestampPolicy,
+    pub(crate) coordinate_check: CoordinateCheck,
+    pub(crate) strict_loading: bool,
+    pub(crate) sync_policy: SyncPolicy,
+    pub(crate) sector_allocation: SectorAllocation,
+    pub(crate) preallocated_sectors: u32,
+    pub(crate) journal: bool,
+    pub(crate) cache_budget: Option<CacheBudget>,
+    pub(crate) listing_order: ListingOrder,
+    pub(crate) quarantine: bool,
+    pub(crate) recompress_regions: bool,
+    pub(crate) punch_holes: bool,
+    pub(crate) dry_run: bool,
+    pub(crate) shrink_regions: bool,
+    pub(crate) recover_torn_writes: bool,
+    pub(crate) region_naming: Option<SharedNaming>,
+}
+
+impl Default for AnvilOptions {
+    fn default() -> Self {
+        AnvilOptions {
+            compression: Compression::default(),
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            read_only: false,
+            max_open_regions: 0,
+            timestamp_policy: TimestampPolicy::default(),
+            coordinate_check: CoordinateCheck::default(),
+            strict_loading: false,
+            sync_policy: SyncPolicy::default(),
+            sector_allocation: SectorAllocation::default(),
+            preallocated_sectors: 0,
+            journal: false,
+            cache_budget: None,
+            listing_order: ListingOrder::default(),
+            quarantine: false,
+            recompress_regions: false,
+            punch_holes: false,
+            dry_run: false,
+            shrink_regions: false,
+            recover_torn_writes: false,
+            region_naming: None,
+        }
+    }
+}
+
+impl AnvilOptions {
+    /// Options matching the behavior of `FolderChunkProvider::new`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compression of saved chunks. Zlib by default.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Compression level of saved chunks, from 0 to 9. Ignored for
+    /// uncompressed chunks.
+    pub fn compression_level(mut self, compression_level: u32) -> Self {
+        self.compression_level = compression_level.min(9);
+        self
+    }
+
+    /// Rejects every save and delete with `ChunkSaveError::ReadOnly`.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Amount of region files kept open between calls, see
+    /// `FolderChunkProvider::with_max_open_regions`. None by default.
+    pub fn max_open_regions(mut self, max_open_regions: usize) -> Self {
+        self.max_open_regions = max_open_regions;
+        self
+    }
+
+    /// Timestamp given to chunks saved with `save_chunk`, and by the other
+    /// saves without a timestamp of their own, like
+    /// `EditSession::flush_dirty`.
+    ///
+    /// `Refresh` (the default) stamps chunks with the current time,
+    /// `Preserve` keeps the timestamp of the chunk being overwritten.
+    pub fn timestamp_policy(mut self, timestamp_policy: TimestampPolicy) -> Self {
+        self.timestamp_policy = timestamp_policy;
+        self
+    }
+
+    /// Check applied to the `xPos`/`zPos` tags of saved chunks.
+    pub fn coordinate_check(mut self, coordinate_check: CoordinateCheck) -> Self {
+        self.coordinate_check = coordinate_check;
+        self
+    }
+
+    /// Makes loading fail with `ChunkLoadError::CoordinateMismatch` when the
+    /// `xPos`/`zPos` tags of a loaded chunk are not the requested ones.
+    pub fn strict_loading(mut self, strict_loading: bool) -> Self {
+        self.strict_loading = strict_loading;
+        self
+    }
+
+    /// When written region files are synced to disk.
+    pub fn sync_policy(mut self, sync_policy: SyncPolicy) -> Self {
+        self.sync_policy = sync_policy;
+        self
+    }
+
+    /// Strategy used to find sectors for saved chunks.
+    pub fn sector_allocation(mut self, sector_allocation: SectorAllocation) -> Self {
+        self.sector_allocation = sector_allocation;
+        self
+    }
+
+    /// Free sectors reserved in newly created region files.
+    pub fn preallocated_sectors(mut self, preallocated_sectors: u32) -> Self {
+        self.preallocated_sectors = preallocated_sectors;
+        self
+    }
+
+    /// Appends every saved and deleted chunk to a journal next to the region
+    /// file before modifying the region, so a write interrupted by a crash
+    /// or a power loss is redone when the region is opened again.
+    ///
+    /// Journals are removed once the region file is synced, after every
+    /// write when no region file is kept open, otherwise when the regions
+    /// are closed with `FolderChunkProvider::close_regions`.
+    pub fn journal(mut self, journal: bool) -> Self {
+        self.journal = journal;
+        self
+    }
+
+    /// Memory budget of the caches of the provider, which can be shared
+    /// with other providers. Caches are only limited by their amount of
+    /// entries by default.
+    pub fn cache_budget(mut self, cache_budget: CacheBudget) -> Self {
+        self.cache_budget = Some(cache_budget);
+        self
+    }
+
+    /// Order of the listed chunks and regions. Unsorted by default.
+    pub fn listing_order(mut self, listing_order: ListingOrder) -> Self {
+        self.listing_order = listing_order;
+        self
+    }
+
+    /// Moves chunks whose data is corrupted out of their region into the
+    /// `quarantine` folder next to the region files, and loads them as
+    /// missing chunks instead of returning an error, so the world keeps
+    /// loading without them.
+    ///
+    /// Ignored by read-only providers.
+    pub fn quarantine(mut self, quarantine: bool) -> Self {
+        self.quarantine = quarantine;
+        self
+    }
+
+    /// Writes the regions stored in compressed region files, such as
+    /// `r.0.0.mca.gz`, back compressed after every modification. They are
+    /// replaced by uncompressed region files by default.
+    pub fn recompress_regions(mut self, recompress_regions: bool) -> Self {
+        self.recompress_regions = recompress_regions;
+        self
+    }
+
+    /// Punches holes over the sectors freed by deleted and moved chunks, so
+    /// the disk usage of region files shrinks without rewriting them. Only
+    /// done on Linux file systems supporting it, such as ext4, XFS or btrfs.
+    pub fn punch_holes(mut self, punch_holes: bool) -> Self {
+        self.punch_holes = punch_holes;
+        self
+    }
+
+    /// Makes saves and deletions, including `delete_chunks_where`, leave the
+    /// region files untouched and only report what they would have done in
+    /// `FolderChunkProvider::dry_run_report`.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Truncates the free sectors at the end of region files after chunks
+    /// are deleted, and removes the region files left without chunks. By
+    /// default deletions only mark the sectors as free.
+    pub fn shrink_regions(mut self, shrink_regions: bool) -> Self {
+        self.shrink_regions = shrink_regions;
+        self
+    }
+
+    /// Checks the chunks of every region file opened for writing with
+    /// `AnvilRegion::find_torn_chunks`, and restores the previous version of
+    /// the chunks whose write didn't complete when it is still in the free
+    /// sectors. Other torn chunks are left as they are.
+    pub fn recover_torn_writes(mut self, recover_torn_writes: bool) -> Self {
+        self.recover_torn_writes = recover_torn_writes;
+        self
+    }
+
+    /// Names of the region files, `VanillaNaming` by default. Files named
+    /// otherwise are ignored by the provider.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::{AnvilOptions, FolderChunkProvider, PatternNaming};
+    ///
+    /// let options = AnvilOptions::new().region_naming(PatternNaming::with_extension("mca.bak"));
+    /// let chunk_provider = FolderChunkProvider::with_options("test/region", options);
+    ///
+    /// assert_eq!(chunk_provider.region_file_name(0, 0), "r.0.0.mca.bak");
+    /// assert!(chunk_provider.list_chunks().unwrap().is_empty());
+    /// ```
+    pub fn region_naming<N: RegionNaming + 'static>(mut self, region_naming: N) -> Self {
+        self.region_naming = Some(SharedNaming(Arc::new(region_naming)));
+        self
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{
+    chunk_coords_inside_region, chunk_coords_to_region_coords, ChunkPayload, ChunkSaveError,
+    FolderChunkProvider,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+use std::thread;
+
+/// Chunks of one region, keyed by their position inside the region.
+type RegionChunks<P> = Vec<((u8, u8), P)>;
+
+/// Saves chunks to the folder of the provider, writing different regions
+/// on different threads.
+///
+/// Chunks are grouped by region and every region file is written by a
+/// single thread, so compression runs in parallel while each file sees the
+/// same sequence of writes as with `save_chunk`. When the same chunk is
+/// given more than once the last one wins. Timestamps follow the
+/// provider's `TimestampPolicy` like `save_chunk`, and the provider's
+/// coordinate check is applied before any file is touched.
+///
+/// Returns the amount of saved chunks. On error, regions handled by other
+/// threads may already be written.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{save_chunks_parallel, FolderChunkProvider};
+/// use nbt::CompoundTag;
+///
+/// let folder = tempfile::tempdir().unwrap();
+/// let chunk_provider = FolderChunkProvider::new(folder.path().to_str().unwrap());
+///
+/// let chunks = (0..64).map(|chunk_x| ((chunk_x, 0), CompoundTag::new()));
+/// let saved = save_chunks_parallel(&chunk_provider, chunks).unwrap();
+///
+/// assert_eq!(saved, 64);
+/// assert!(chunk_provider.load_chunk(63, 0).is_ok());
+/// ```
+pub fn save_chunks_parallel<P, I>(
+    provider: &FolderChunkProvider<P>,
+    chunks: I,
+) -> Result<usize, ChunkSaveError>
+where
+    P: ChunkPayload + Send,
+    I: IntoIterator<Item = ((i32, i32), P)>,
+{
+    if provider.options.read_only {
+        return Err(ChunkSaveError::ReadOnly);
+    }
+
+    let mut regions: HashMap<(i32, i32), RegionChunks<P>> = HashMap::new();
+    let mut saved = 0;
+
+    for ((chunk_x, chunk_z), mut chunk_compound_tag) in chunks {
+        provider
+            .options
+            .coordinate_check
+            .apply(chunk_x, chunk_z, &mut chunk_compound_tag)?;
+
+        let region = chunk_coords_to_region_coords(chunk_x, chunk_z);
+        let region_chunk = chunk_coords_inside_region(chunk_x, chunk_z);
+
+        regions
+            .entry(region)
+            .or_default()
+            .push((region_chunk, chunk_compound_tag));
+        saved += 1;
+    }
+
+    if regions.is_empty() {
+        return Ok(0);
+    }
+
+    if !provider.folder_path.exists() {
+        fs::create_dir(&provider.folder_path)?;
+    }
+
+    let threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(regions.len());
+    let queue = Mutex::new(regions.into_iter().collect::<Vec<_>>());
+    let error: Mutex<Option<ChunkSaveError>> = Mutex::new(None);
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                if error.lock().unwrap().is_some() {
+                    break;
+                }
+
+                let next = queue.lock().unwrap().pop();
+                let (region, region_chunks) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+
+                if let Err(e) = save_region(provider, region, region_chunks) {
+                    error.lock().unwrap().get_or_insert(e);
+                    break;
+                }
+            });
+        }
+    });
+
+    match error.into_inner().unwrap() {
+        Some(e) => Err(e),
+        None => Ok(saved),
+    }
+}
+
+fn save_region<P: ChunkPayload>(
+    provider: &FolderChunkProvider<P>,
+    (region_x, region_z): (i32, i32),
+    region_chunks: RegionChunks<P>,
+) -> Result<(), ChunkSaveError> {
+    provider.with_region(region_x, region_z, |region| {
+        region.write_batch(|region| {
+            for ((region_chunk_x, region_chunk_z), chunk_compound_tag) in region_chunks {
+                let last_modified_timestamp =
+                    provider.policy_timestamp(region.get_metadata(region_chunk_x, region_chunk_z));
+
+                region.write_chunk_with_timestamp(
+                    region_chunk_x,
+                    region_chunk_z,
+                    chunk_compound_tag,
+                    last_modified_timestamp,
+                )?;
+            }
+
+            Ok(())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilOptions, ChunkReader, CoordinateCheck, TimestampPolicy};
+    use nbt::CompoundTag;
+
+    #[test]
+    fn test_save_chunks_parallel_many_regions() {
+        let folder = tempfile::tempdir().unwrap();
+        let provider = FolderChunkProvider::new(folder.path().to_str().unwrap());
+
+        let chunks = (-40..40).flat_map(|chunk_x| {
+            (-3..3).map(move |chunk_z| {
+                let mut chunk_compound_tag = CompoundTag::new();
+                chunk_compound_tag.insert_i32("xPos", chunk_x);
+                chunk_compound_tag.insert_i32("zPos", chunk_z);
+
+                ((chunk_x, chunk_z), chunk_compound_tag)
+            })
+        });
+
+        assert_eq!(save_chunks_parallel(&provider, chunks).unwrap(), 480);
+        assert_eq!(provider.list_regions().unwrap().len(), 8);
+
+        for chunk_x in -40..40 {
+            for chunk_z in -3..3 {
+                let chunk_compound_tag = provider.load_chunk(chunk_x, chunk_z).unwrap();
+
+                assert_eq!(chunk_compound_tag.get_i32("xPos").unwrap(), chunk_x);
+                assert_eq!(chunk_compound_tag.get_i32("zPos").unwrap(), chunk_z);
+            }
+        }
+    }
+
+    #[test]
+    fn test_save_chunks_parallel_last_duplicate_wins() {
+        let folder = tempfile::tempdir().unwrap();
+        let provider = FolderChunkProvider::new(folder.path().to_str().unwrap());
+
+        let chunks = (0..3).map(|i| {
+            let mut chunk_compound_tag = CompoundTag::new();
+            chunk_compound_tag.insert_i32("i", i);
+
+            ((5, 5), chunk_compound_tag)
+        });
+
+        save_chunks_parallel(&provider, chunks).unwrap();
+
+        let chunk_compound_tag = provider.load_chunk(5, 5).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("i").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_save_chunks_parallel_coordinate_check() {
+        let folder = tempfile::tempdir().unwrap();
+        let provider = FolderChunkProvider::new(folder.path().to_str().unwrap())
+            .with_coordinate_check(CoordinateCheck::Verify);
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("xPos", 1);
+        chunk_compound_tag.insert_i32("zPos", 1);
+
+        let chunks = vec![((0, 0), CompoundTag::new()), ((2, 2), chunk_compound_tag)];
+
+        match save_chunks_parallel(&provider, chunks) {
+            Err(ChunkSaveError::CoordinateMismatch { .. }) => {}
+            e => panic!("Expected `CoordinateMismatch` but got `{:?}`", e),
+        }
+
+        // Nothing is written when a chunk fails the check.
+        assert!(provider.list_regions().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_save_chunks_parallel_timestamp_policy() {
+        let folder = tempfile::tempdir().unwrap();
+        let options = AnvilOptions::new().timestamp_policy(TimestampPolicy::Preserve);
+        let provider = FolderChunkProvider::with_options(folder.path(), options);
+
+        provider
+            .save_chunk_with_timestamp(0, 0, CompoundTag::new(), 1000)
+            .unwrap();
+
+        let chunks = vec![((0, 0), CompoundTag::new()), ((1, 0), CompoundTag::new())];
+        save_chunks_parallel(&provider, chunks).unwrap();
+
+        let preserved = (&provider).load_chunk_metadata(0, 0).unwrap();
+        let created = (&provider).load_chunk_metadata(1, 0).unwrap();
+
+        assert_eq!(preserved.last_modified_timestamp(), 1000);
+        assert!(created.last_modified_timestamp() > 1000);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{read_chunk_data, AnvilRegionHeader, ChunkLoadError, ChunkPayload, RawChunk};
+use byteorder::{BigEndian, ByteOrder};
+use nbt::CompoundTag;
+use std::io;
+use std::io::Cursor;
+
+/// Chunk coordinates inside the region and the chunk data.
+pub type ParsedChunk = ((u8, u8), RawChunk);
+
+/// Parses a region file held in memory, returning every chunk without
+/// decompressing it, in header order.
+///
+/// Doesn't touch the filesystem, so it can be used as a fuzz target for the
+/// header parser and the sector math.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::parse_region_bytes;
+///
+/// let bytes = std::fs::read("test/region/r.0.0.mca").unwrap();
+/// let chunks = parse_region_bytes(&bytes).unwrap();
+///
+/// assert_eq!(chunks.len(), 277);
+/// assert!(chunks[0].1.decode().is_ok());
+/// ```
+pub fn parse_region_bytes(bytes: &[u8]) -> Result<Vec<ParsedChunk>, ChunkLoadError> {
+    let mut reader = Cursor::new(bytes);
+    let header = AnvilRegionHeader::from_reader(&mut reader)?;
+    let mut buffer = Vec::new();
+    let mut chunks = Vec::new();
+
+    for ((chunk_x, chunk_z), metadata) in header.chunks() {
+        let compression_scheme =
+            read_chunk_data(&mut reader, metadata, chunk_x, chunk_z, &mut buffer)?;
+
+        chunks.push((
+            (chunk_x, chunk_z),
+            RawChunk::new(compression_scheme, buffer.clone()),
+        ));
+    }
+
+    Ok(chunks)
+}
+
+/// Parses the data of a single chunk as stored in its sectors: the length,
+/// the compression scheme and the compressed NBT.
+///
+/// Bytes after the declared length are ignored, like the padding up to the
+/// sector boundary.
+pub fn parse_chunk_payload(bytes: &[u8]) -> Result<CompoundTag, ChunkLoadError> {
+    if bytes.len() < 4 {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    let length = BigEndian::read_u32(&bytes[..4]);
+    let maximum_length = (bytes.len() - 4).min(u32::MAX as usize) as u32;
+
+    if length > maximum_length {
+        return Err(ChunkLoadError::LengthExceedsMaximum {
+            length,
+            maximum_length,
+        });
+    }
+
+    if length == 0 {
+        return Err(ChunkLoadError::EmptyChunkData);
+    }
+
+    let data = &bytes[5..4 + length as usize];
+
+    CompoundTag::decode(bytes[4], data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilRegion, Compression};
+
+    #[test]
+    fn test_parse_region_bytes_invalid() {
+        match parse_region_bytes(&[0; 100]) {
+            Err(ChunkLoadError::ReadError { io_error }) => {
+                assert_eq!(io_error.kind(), io::ErrorKind::UnexpectedEof)
+            }
+            e => panic!("Expected `ReadError` but got `{:?}`", e),
+        }
+
+        // Chunk (0, 0) at sector 2, past the end of the file.
+        let mut bytes = vec![0; 8192];
+        bytes[..4].copy_from_slice(&0x201u32.to_be_bytes());
+        assert!(parse_region_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_parse_chunk_payload() {
+        let mut region = AnvilRegion::new(Cursor::new(Vec::new()))
+            .unwrap()
+            .with_compression(Compression::Zlib, 6);
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("xPos", 3);
+        region.write_chunk(0, 0, chunk_compound_tag).unwrap();
+
+        let sector = region.read_sector(2).unwrap();
+        let parsed = parse_chunk_payload(&sector).unwrap();
+        assert_eq!(parsed.get_i32("xPos").unwrap(), 3);
+
+        match parse_chunk_payload(&sector[..10]) {
+            Err(ChunkLoadError::LengthExceedsMaximum {
+                maximum_length: 6, ..
+            }) => {}
+            e => panic!("Expected `LengthExceedsMaximum` but got `{:?}`", e),
+        }
+
+        match parse_chunk_payload(&[0, 0, 0, 0]) {
+            Err(ChunkLoadError::EmptyChunkData) => {}
+            e => panic!("Expected `EmptyChunkData` but got `{:?}`", e),
+        }
+    }
+}
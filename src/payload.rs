@@ -0,0 +1,189 @@
+use crate::{chunk_level, chunk_level_mut, ChunkLoadError, Compression};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use nbt::decode::read_compound_tag;
+use nbt::encode::write_compound_tag;
+use nbt::CompoundTag;
+use std::io;
+use std::io::{Read, Write};
+
+/// Chunk data stored in region files.
+///
+/// Providers and regions are generic over the payload, so chunks can be
+/// decoded with any NBT library by implementing this trait for its compound
+/// type. `nbt::CompoundTag` is the default payload everywhere.
+///
+/// Only `read_payload` and `write_payload` are required: compression is
+/// handled by `decode` and `encode`, which can be overridden by payloads that
+/// prefer to handle compressed streams themselves.
+pub trait ChunkPayload: Sized {
+    /// Reads the payload from decompressed chunk data.
+    fn read_payload<R: Read>(reader: &mut R) -> Result<Self, ChunkLoadError>;
+
+    /// Writes the payload as uncompressed chunk data.
+    fn write_payload<W: Write>(&self, writer: &mut W) -> Result<(), io::Error>;
+
+    /// Decodes the payload from chunk data compressed with the given scheme.
+    fn decode(compression_scheme: u8, data: &[u8]) -> Result<Self, ChunkLoadError> {
+        let mut data = data;
+
+        match Compression::from_id(compression_scheme) {
+            Some(Compression::Gzip) => Self::read_payload(&mut GzDecoder::new(data)),
+            Some(Compression::Zlib) => Self::read_payload(&mut ZlibDecoder::new(data)),
+            Some(Compression::Uncompressed) => Self::read_payload(&mut data),
+            None => Err(ChunkLoadError::UnsupportedCompressionScheme { compression_scheme }),
+        }
+    }
+
+    /// Writes the payload compressed with the given compression and level,
+    /// from 0 to 9.
+    fn encode<W: Write>(
+        &self,
+        writer: &mut W,
+        compression: Compression,
+        compression_level: u32,
+    ) -> Result<(), io::Error> {
+        let level = flate2::Compression::new(compression_level);
+
+        match compression {
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(writer, level);
+                self.write_payload(&mut encoder)?;
+                encoder.finish()?;
+            }
+            Compression::Zlib => {
+                let mut encoder = ZlibEncoder::new(writer, level);
+                self.write_payload(&mut encoder)?;
+                encoder.finish()?;
+            }
+            Compression::Uncompressed => self.write_payload(writer)?,
+        }
+
+        Ok(())
+    }
+
+    /// The `xPos` and `zPos` stored in the chunk, used by coordinate checks
+    /// and strict loading. Missing coordinates are never a mismatch, so
+    /// payloads which don't expose them can keep the default.
+    fn chunk_coordinates(&self) -> (Option<i32>, Option<i32>) {
+        (None, None)
+    }
+
+    /// Overwrites the coordinates stored in the chunk, used by
+    /// `CoordinateCheck::Fix`. Does nothing by default.
+    fn set_chunk_coordinates(&mut self, _chunk_x: i32, _chunk_z: i32) {}
+}
+
+impl ChunkPayload for CompoundTag {
+    fn read_payload<R: Read>(reader: &mut R) -> Result<Self, ChunkLoadError> {
+        Ok(read_compound_tag(reader)?)
+    }
+
+    fn write_payload<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        write_compound_tag(writer, self)
+    }
+
+    fn chunk_coordinates(&self) -> (Option<i32>, Option<i32>) {
+        let level_compound_tag = chunk_level(self);
+
+        (
+            level_compound_tag.get_i32("xPos").ok(),
+            level_compound_tag.get_i32("zPos").ok(),
+        )
+    }
+
+    fn set_chunk_coordinates(&mut self, chunk_x: i32, chunk_z: i32) {
+        let level_compound_tag = chunk_level_mut(self);
+
+        level_compound_tag.insert_i32("xPos", chunk_x);
+        level_compound_tag.insert_i32("zPos", chunk_z);
+    }
+}
+
+/// Same as `ChunkPayload::decode`, also returning the length of the
+/// decompressed chunk data.
+pub(crate) fn decode_counted<P: ChunkPayload>(
+    compression_scheme: u8,
+    data: &[u8],
+) -> Result<(P, u64), ChunkLoadError> {
+    let mut decompressed = Vec::new();
+
+    match Compression::from_id(compression_scheme) {
+        Some(Compression::Gzip) => GzDecoder::new(data).read_to_end(&mut decompressed)?,
+        Some(Compression::Zlib) => ZlibDecoder::new(data).read_to_end(&mut decompressed)?,
+        Some(Compression::Uncompressed) => {
+            return Ok((P::decode(compression_scheme, data)?, data.len() as u64))
+        }
+        None => return Err(ChunkLoadError::UnsupportedCompressionScheme { compression_scheme }),
+    };
+
+    let payload = P::decode(Compression::Uncompressed.id(), &decompressed)?;
+
+    Ok((payload, decompressed.len() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Payload holding the uncompressed bytes, as a stand-in for another NBT
+    /// library.
+    #[derive(Debug, PartialEq)]
+    struct Bytes(Vec<u8>);
+
+    impl ChunkPayload for Bytes {
+        fn read_payload<R: Read>(reader: &mut R) -> Result<Self, ChunkLoadError> {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes)?;
+
+            Ok(Bytes(bytes))
+        }
+
+        fn write_payload<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+            writer.write_all(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_payload_encode_decode() {
+        for &compression in &[Compression::Gzip, Compression::Zlib, Compression::Uncompressed] {
+            let mut data = Vec::new();
+            Bytes(vec![1, 2, 3]).encode(&mut data, compression, 6).unwrap();
+
+            let payload = Bytes::decode(compression.id(), &data).unwrap();
+            assert_eq!(payload, Bytes(vec![1, 2, 3]));
+        }
+    }
+
+    #[test]
+    fn test_compound_tag_coordinates() {
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("xPos", 3);
+        assert_eq!(chunk_compound_tag.chunk_coordinates(), (Some(3), None));
+
+        let mut level_compound_tag = CompoundTag::new();
+        level_compound_tag.insert_i32("zPos", 1);
+        chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+        chunk_compound_tag.set_chunk_coordinates(4, 2);
+
+        let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+        assert_eq!(level_compound_tag.get_i32("zPos").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_folder_provider_with_payload() {
+        use crate::{AnvilChunkProvider, FolderChunkProvider};
+
+        fn round_trip<P: AnvilChunkProvider<Bytes>>(provider: &mut P) -> Bytes {
+            provider.save_chunk(1, 2, Bytes(vec![4, 5, 6])).unwrap();
+            provider.load_chunk(1, 2).unwrap()
+        }
+
+        let folder = tempfile::tempdir().unwrap();
+        let mut provider =
+            FolderChunkProvider::new(folder.path().to_str().unwrap()).with_payload::<Bytes>();
+
+        assert_eq!(round_trip(&mut provider), Bytes(vec![4, 5, 6]));
+    }
+}
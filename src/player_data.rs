@@ -0,0 +1,218 @@
+use crate::world_folder::{load_gzip_nbt, save_gzip_nbt};
+use nbt::decode::TagDecodeError;
+use nbt::CompoundTag;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Player data folder of worlds since 1.7.6, files named by UUID.
+const PLAYERDATA_FOLDER: &str = "playerdata";
+/// Player data folder of older worlds, files named by player name.
+const LEGACY_PLAYERS_FOLDER: &str = "players";
+
+/// Key of a player data file.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum PlayerId {
+    /// Player stored in `playerdata/<uuid>.dat`.
+    Uuid(u128),
+    /// Player stored in the legacy `players/<name>.dat`.
+    Name(String),
+}
+
+impl PlayerId {
+    /// Parses a UUID in its hyphenated form, such as
+    /// `069a79f4-44e9-4726-a5be-fca90e38aaf5`.
+    pub fn parse_uuid(uuid: &str) -> Option<Self> {
+        let bytes = uuid.as_bytes();
+
+        if bytes.len() != 36 || [8, 13, 18, 23].iter().any(|&index| bytes[index] != b'-') {
+            return None;
+        }
+
+        let digits: String = uuid.split('-').collect();
+
+        if !digits.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        u128::from_str_radix(&digits, 16).ok().map(PlayerId::Uuid)
+    }
+
+    /// Path of the player data file inside the world folder.
+    fn path(&self, world_path: &Path) -> PathBuf {
+        match self {
+            PlayerId::Uuid(uuid) => {
+                let digits = format!("{:032x}", uuid);
+                let file_name = format!(
+                    "{}-{}-{}-{}-{}.dat",
+                    &digits[..8],
+                    &digits[8..12],
+                    &digits[12..16],
+                    &digits[16..20],
+                    &digits[20..]
+                );
+
+                world_path.join(PLAYERDATA_FOLDER).join(file_name)
+            }
+            PlayerId::Name(name) => world_path
+                .join(LEGACY_PLAYERS_FOLDER)
+                .join(format!("{}.dat", name)),
+        }
+    }
+}
+
+/// Lists, loads and saves the gzip compressed NBT player files of a world.
+///
+/// Players are read from the `playerdata` folder, keyed by UUID, and from
+/// the legacy `players` folder, keyed by name.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{PlayerDataProvider, PlayerId};
+/// use nbt::CompoundTag;
+///
+/// # let world = tempfile::tempdir().unwrap();
+/// # let world = world.path();
+/// let player_data_provider = PlayerDataProvider::new(world);
+/// let player = PlayerId::parse_uuid("069a79f4-44e9-4726-a5be-fca90e38aaf5").unwrap();
+///
+/// let mut player_compound_tag = CompoundTag::new();
+/// player_compound_tag.insert_i32("XpLevel", 30);
+/// player_data_provider.save_player(&player, &player_compound_tag).unwrap();
+///
+/// assert_eq!(player_data_provider.list_players().unwrap(), vec![player.clone()]);
+/// let player_compound_tag = player_data_provider.load_player(&player).unwrap();
+/// assert_eq!(player_compound_tag.get_i32("XpLevel").unwrap(), 30);
+/// ```
+#[derive(Debug)]
+pub struct PlayerDataProvider {
+    /// World folder, containing the player data folders.
+    world_path: PathBuf,
+}
+
+impl PlayerDataProvider {
+    pub fn new<F: Into<PathBuf>>(world_folder: F) -> Self {
+        PlayerDataProvider {
+            world_path: world_folder.into(),
+        }
+    }
+
+    pub fn world_path(&self) -> &Path {
+        &self.world_path
+    }
+
+    /// Lists the players of both player data folders, sorted with the
+    /// UUIDs first. Missing folders are treated as empty.
+    pub fn list_players(&self) -> Result<Vec<PlayerId>, io::Error> {
+        let mut players = Vec::new();
+
+        for folder in &[PLAYERDATA_FOLDER, LEGACY_PLAYERS_FOLDER] {
+            let folder_path = self.world_path.join(folder);
+
+            if !folder_path.exists() {
+                continue;
+            }
+
+            for entry in fs::read_dir(folder_path)? {
+                let file_name = entry?.file_name();
+                let stem = match file_name
+                    .to_str()
+                    .and_then(|name| name.strip_suffix(".dat"))
+                {
+                    Some(stem) => stem,
+                    None => continue,
+                };
+
+                let player = if *folder == PLAYERDATA_FOLDER {
+                    match PlayerId::parse_uuid(stem) {
+                        Some(player) => player,
+                        None => continue,
+                    }
+                } else {
+                    PlayerId::Name(stem.to_string())
+                };
+
+                players.push(player);
+            }
+        }
+
+        players.sort();
+
+        Ok(players)
+    }
+
+    pub fn load_player(&self, player: &PlayerId) -> Result<CompoundTag, TagDecodeError> {
+        load_gzip_nbt(&player.path(&self.world_path))
+    }
+
+    /// Saves the player data the way the game does: the new data is written
+    /// to a temporary file first, and the previous data is kept as
+    /// `<file>.dat_old`.
+    pub fn save_player(
+        &self,
+        player: &PlayerId,
+        player_compound_tag: &CompoundTag,
+    ) -> Result<(), io::Error> {
+        save_gzip_nbt(&player.path(&self.world_path), player_compound_tag, true)
+    }
+
+    /// Deletes the player data file, keeping any `<file>.dat_old` backup.
+    pub fn delete_player(&self, player: &PlayerId) -> Result<(), io::Error> {
+        fs::remove_file(player.path(&self.world_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_player_data_folders() {
+        let world = tempfile::tempdir().unwrap();
+        let player_data_provider = PlayerDataProvider::new(world.path());
+        assert!(player_data_provider.list_players().unwrap().is_empty());
+
+        let uuid = PlayerId::parse_uuid("069A79F4-44E9-4726-A5BE-FCA90E38AAF5").unwrap();
+        let name = PlayerId::Name("Notch".to_string());
+        assert_eq!(uuid, PlayerId::Uuid(0x069a79f444e94726a5befca90e38aaf5));
+        assert_eq!(
+            PlayerId::parse_uuid("069a79f444e94726a5befca90e38aaf5"),
+            None
+        );
+        assert_eq!(
+            PlayerId::parse_uuid("069a79f4-44e9-4726-a5be-fca90e38aafg"),
+            None
+        );
+
+        let mut player_compound_tag = CompoundTag::new();
+        for (player, health) in &[(&uuid, 20.0), (&name, 10.0), (&uuid, 15.0)] {
+            player_compound_tag.insert_f32("Health", *health);
+            player_data_provider
+                .save_player(player, &player_compound_tag)
+                .unwrap();
+        }
+
+        assert!(world
+            .path()
+            .join("playerdata/069a79f4-44e9-4726-a5be-fca90e38aaf5.dat_old")
+            .exists());
+        assert!(world.path().join("players/Notch.dat").exists());
+        assert_eq!(
+            player_data_provider.list_players().unwrap(),
+            vec![uuid.clone(), name.clone()]
+        );
+
+        let player_compound_tag = player_data_provider.load_player(&uuid).unwrap();
+        assert_eq!(player_compound_tag.get_f32("Health").unwrap(), 15.0);
+
+        player_data_provider.delete_player(&name).unwrap();
+        assert_eq!(player_data_provider.list_players().unwrap(), vec![uuid]);
+        match player_data_provider.load_player(&name) {
+            Err(TagDecodeError::IOError { io_error }) => {
+                assert_eq!(io_error.kind(), io::ErrorKind::NotFound)
+            }
+            e => panic!("Expected `IOError` but got `{:?}`", e),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+use std::io;
+use std::io::Write;
+
+/// Grayscale with alpha, 8 bits per channel.
+pub(crate) const GRAYSCALE_ALPHA: u8 = 4;
+/// Red, green, blue and alpha, 8 bits per channel.
+pub(crate) const RGBA: u8 = 6;
+
+/// Writes a non-interlaced PNG image with 8 bits per channel.
+///
+/// `pixels` holds the rows from top to bottom, with `channels` bytes per
+/// pixel as required by the color type.
+pub(crate) fn write_png<W: Write>(
+    writer: &mut W,
+    width: u32,
+    height: u32,
+    color_type: u8,
+    channels: usize,
+    pixels: &[u8],
+) -> Result<(), io::Error> {
+    writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Bit depth, color type, compression, filter and interlace methods.
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+    write_chunk(writer, b"IHDR", &header)?;
+
+    let row_length = width as usize * channels;
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+
+    for row in pixels.chunks(row_length.max(1)).take(height as usize) {
+        // Rows are stored without filtering.
+        encoder.write_all(&[0])?;
+        encoder.write_all(row)?;
+    }
+
+    write_chunk(writer, b"IDAT", &encoder.finish()?)?;
+    write_chunk(writer, b"IEND", &[])
+}
+
+fn write_chunk<W: Write>(
+    writer: &mut W,
+    chunk_type: &[u8; 4],
+    data: &[u8],
+) -> Result<(), io::Error> {
+    let mut crc = Crc::new();
+    crc.update(chunk_type);
+    crc.update(data);
+
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(chunk_type)?;
+    writer.write_all(data)?;
+    writer.write_all(&crc.sum().to_be_bytes())
+}
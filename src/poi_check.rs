@@ -0,0 +1,352 @@
+use crate::chunk_surface::block_name;
+use crate::{ChunkLoadError, ChunkReader, ChunkSelection, ChunkWriter, WorldEditError};
+use nbt::{CompoundTag, Tag};
+use std::collections::BTreeMap;
+
+/// Block coordinates and type of a point of interest record.
+type PoiRecord<'a> = ([i32; 3], &'a str);
+
+/// Why a point of interest record was found stale by `find_stale_poi`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StalePoiReason {
+    /// The chunk holding the position doesn't exist.
+    ChunkMissing,
+    /// The block at the position is air.
+    Air,
+    /// The block at the position can't be used by this type of point of
+    /// interest, such as a `minecraft:home` without a bed.
+    UnexpectedBlock { block: String },
+}
+
+/// Point of interest record of the `poi` folder without its block.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StalePoi {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    /// Block coordinates of the record.
+    pub position: [i32; 3],
+    /// Type of the record, such as `minecraft:home`.
+    pub poi_type: String,
+    pub reason: StalePoiReason,
+}
+
+/// Cross-references the point of interest records of the selected chunks
+/// against the blocks of the world, returning the records whose block is
+/// missing.
+///
+/// `poi_provider` reads the `poi` folder of the world and `chunk_provider`
+/// its `region` folder. Records are stale when their chunk doesn't exist,
+/// when their block is air, or when the block doesn't match a known type of
+/// point of interest. Chunks from before 1.13, which don't store block
+/// names, are never stale.
+///
+/// # Example
+///
+/// ```no_run
+/// use anvil_region::{find_stale_poi, remove_stale_poi, ChunkSelection, FolderChunkProvider};
+///
+/// let mut poi_provider = FolderChunkProvider::new("world/poi");
+/// let mut chunk_provider = FolderChunkProvider::new("world/region");
+///
+/// let stale = find_stale_poi(&mut poi_provider, &mut chunk_provider, &ChunkSelection::All)
+///     .unwrap();
+/// let removed = remove_stale_poi(&mut poi_provider, &stale).unwrap();
+///
+/// assert_eq!(removed, stale.len());
+/// ```
+pub fn find_stale_poi<Q, P>(
+    poi_provider: &mut Q,
+    chunk_provider: &mut P,
+    selection: &ChunkSelection,
+) -> Result<Vec<StalePoi>, ChunkLoadError>
+where
+    Q: ChunkReader + ?Sized,
+    P: ChunkReader + ?Sized,
+{
+    let mut stale = Vec::new();
+
+    for (chunk_x, chunk_z) in poi_provider.list_chunks_in(selection)? {
+        let poi_compound_tag = poi_provider.load_chunk(chunk_x, chunk_z)?;
+        let records = poi_records(&poi_compound_tag);
+
+        if records.is_empty() {
+            continue;
+        }
+
+        let chunk_compound_tag = chunk_provider.try_load_chunk(chunk_x, chunk_z)?;
+
+        for (position, poi_type) in records {
+            let [x, y, z] = position;
+
+            let reason = match &chunk_compound_tag {
+                None => Some(StalePoiReason::ChunkMissing),
+                Some(chunk_compound_tag) => {
+                    let block =
+                        block_name(chunk_compound_tag, (x & 15) as usize, y, (z & 15) as usize);
+
+                    block.and_then(|block| stale_reason(poi_type, block))
+                }
+            };
+
+            if let Some(reason) = reason {
+                stale.push(StalePoi {
+                    chunk_x,
+                    chunk_z,
+                    position,
+                    poi_type: poi_type.to_string(),
+                    reason,
+                });
+            }
+        }
+    }
+
+    Ok(stale)
+}
+
+/// Removes the given records from the `poi` folder, returning the amount of
+/// removed records.
+pub fn remove_stale_poi<Q>(
+    poi_provider: &mut Q,
+    stale: &[StalePoi],
+) -> Result<usize, WorldEditError>
+where
+    Q: ChunkReader + ChunkWriter + ?Sized,
+{
+    let mut chunks: BTreeMap<(i32, i32), Vec<PoiRecord<'_>>> = BTreeMap::new();
+
+    for stale_poi in stale {
+        chunks
+            .entry((stale_poi.chunk_x, stale_poi.chunk_z))
+            .or_default()
+            .push((stale_poi.position, &stale_poi.poi_type));
+    }
+
+    let mut removed = 0;
+
+    for ((chunk_x, chunk_z), chunk_stale) in chunks {
+        let mut poi_compound_tag = match poi_provider.try_load_chunk(chunk_x, chunk_z)? {
+            Some(poi_compound_tag) => poi_compound_tag,
+            None => continue,
+        };
+
+        let sections = match poi_compound_tag.get_mut::<&mut CompoundTag>("Sections") {
+            Ok(sections) => sections,
+            Err(_) => continue,
+        };
+
+        let mut chunk_removed = 0;
+
+        for (_, section) in sections.iter_mut() {
+            let records = match section {
+                Tag::Compound(section) => match section.get_mut::<&mut Vec<Tag>>("Records") {
+                    Ok(records) => records,
+                    Err(_) => continue,
+                },
+                _ => continue,
+            };
+
+            let records_before = records.len();
+            records.retain(|record| match record {
+                Tag::Compound(record) => !chunk_stale.iter().any(|&(position, poi_type)| {
+                    record.get_i32_vec("pos").ok().map(Vec::as_slice) == Some(&position[..])
+                        && record.get_str("type").ok() == Some(poi_type)
+                }),
+                _ => true,
+            });
+            chunk_removed += records_before - records.len();
+        }
+
+        if chunk_removed > 0 {
+            removed += chunk_removed;
+            poi_provider.save_chunk(chunk_x, chunk_z, poi_compound_tag)?;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Position and type of every record of a `poi` chunk.
+fn poi_records(poi_compound_tag: &CompoundTag) -> Vec<PoiRecord<'_>> {
+    let mut records = Vec::new();
+
+    let sections = match poi_compound_tag.get_compound_tag("Sections") {
+        Ok(sections) => sections,
+        Err(_) => return records,
+    };
+
+    for (_, section) in sections.iter() {
+        let section_records = match section {
+            Tag::Compound(section) => match section.get_compound_tag_vec("Records") {
+                Ok(section_records) => section_records,
+                Err(_) => continue,
+            },
+            _ => continue,
+        };
+
+        for record in section_records {
+            let position = match record.get_i32_vec("pos").map(Vec::as_slice) {
+                Ok(&[x, y, z]) => [x, y, z],
+                _ => continue,
+            };
+
+            if let Ok(poi_type) = record.get_str("type") {
+                records.push((position, poi_type));
+            }
+        }
+    }
+
+    records
+}
+
+/// Returns why the block can't hold the point of interest, if it can't.
+fn stale_reason(poi_type: &str, block: &str) -> Option<StalePoiReason> {
+    if matches!(
+        block,
+        "minecraft:air" | "minecraft:cave_air" | "minecraft:void_air"
+    ) {
+        return Some(StalePoiReason::Air);
+    }
+
+    let expected_blocks: &[&str] = match poi_type {
+        "minecraft:armorer" => &["minecraft:blast_furnace"],
+        "minecraft:butcher" => &["minecraft:smoker"],
+        "minecraft:cartographer" => &["minecraft:cartography_table"],
+        "minecraft:cleric" => &["minecraft:brewing_stand"],
+        "minecraft:farmer" => &["minecraft:composter"],
+        "minecraft:fisherman" => &["minecraft:barrel"],
+        "minecraft:fletcher" => &["minecraft:fletching_table"],
+        "minecraft:librarian" => &["minecraft:lectern"],
+        "minecraft:mason" => &["minecraft:stonecutter"],
+        "minecraft:shepherd" => &["minecraft:loom"],
+        "minecraft:toolsmith" => &["minecraft:smithing_table"],
+        "minecraft:weaponsmith" => &["minecraft:grindstone"],
+        "minecraft:meeting" => &["minecraft:bell"],
+        "minecraft:beehive" => &["minecraft:beehive"],
+        "minecraft:bee_nest" => &["minecraft:bee_nest"],
+        "minecraft:nether_portal" => &["minecraft:nether_portal"],
+        "minecraft:lodestone" => &["minecraft:lodestone"],
+        "minecraft:lightning_rod" => &["minecraft:lightning_rod"],
+        "minecraft:home" if block.ends_with("_bed") => return None,
+        "minecraft:home" => &[],
+        "minecraft:leatherworker" if block.ends_with("cauldron") => return None,
+        "minecraft:leatherworker" => &[],
+        // Unknown types are only checked against air.
+        _ => return None,
+    };
+
+    if expected_blocks.contains(&block) {
+        None
+    } else {
+        Some(StalePoiReason::UnexpectedBlock {
+            block: block.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FolderChunkProvider;
+
+    fn poi_record(position: [i32; 3], poi_type: &str) -> CompoundTag {
+        let mut record = CompoundTag::new();
+        record.insert_i32_vec("pos", position.to_vec());
+        record.insert_str("type", poi_type);
+        record.insert_i32("free_tickets", 0);
+
+        record
+    }
+
+    /// 1.18 chunk with a single section at Y 4 holding two blocks.
+    fn chunk_with_blocks() -> CompoundTag {
+        let palette: Vec<_> = ["minecraft:air", "minecraft:red_bed", "minecraft:lectern"]
+            .iter()
+            .map(|name| {
+                let mut block_state = CompoundTag::new();
+                block_state.insert_str("Name", *name);
+                block_state
+            })
+            .collect();
+
+        // 4 bits per block, block (1, 64, 0) is a bed and (2, 64, 0) a
+        // lectern.
+        let mut data = vec![0i64; 256];
+        data[0] = (1 << 4) | (2 << 8);
+
+        let mut block_states = CompoundTag::new();
+        block_states.insert_compound_tag_vec("palette", palette);
+        block_states.insert_i64_vec("data", data);
+        let mut section = CompoundTag::new();
+        section.insert_i8("Y", 4);
+        section.insert_compound_tag("block_states", block_states);
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_compound_tag_vec("sections", vec![section]);
+
+        chunk_compound_tag
+    }
+
+    #[test]
+    fn test_find_and_remove_stale_poi() {
+        let world = tempfile::tempdir().unwrap();
+        let mut poi_provider = FolderChunkProvider::new(world.path().join("poi"));
+        let mut chunk_provider = FolderChunkProvider::new(world.path().join("region"));
+        chunk_provider
+            .save_chunk(0, 0, chunk_with_blocks())
+            .unwrap();
+
+        let mut section = CompoundTag::new();
+        section.insert_i8("Valid", 1);
+        section.insert_compound_tag_vec(
+            "Records",
+            vec![
+                poi_record([1, 64, 0], "minecraft:home"),
+                poi_record([2, 64, 0], "minecraft:librarian"),
+                poi_record([2, 64, 0], "minecraft:home"),
+                poi_record([3, 64, 0], "minecraft:meeting"),
+                poi_record([3, 64, 0], "minecraft:unknown"),
+            ],
+        );
+        let mut sections = CompoundTag::new();
+        sections.insert_compound_tag("4", section);
+        let mut poi_compound_tag = CompoundTag::new();
+        poi_compound_tag.insert_compound_tag("Sections", sections.clone());
+        poi_provider
+            .save_chunk(0, 0, poi_compound_tag.clone())
+            .unwrap();
+        poi_provider.save_chunk(1, 0, poi_compound_tag).unwrap();
+
+        let selection = ChunkSelection::rect((0, 0), (0, 0));
+        let stale = find_stale_poi(&mut poi_provider, &mut chunk_provider, &selection).unwrap();
+        let reasons: Vec<_> = stale
+            .iter()
+            .map(|stale_poi| (stale_poi.poi_type.as_str(), stale_poi.reason.clone()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (
+                    "minecraft:home",
+                    StalePoiReason::UnexpectedBlock {
+                        block: "minecraft:lectern".to_string()
+                    }
+                ),
+                ("minecraft:meeting", StalePoiReason::Air),
+                ("minecraft:unknown", StalePoiReason::Air),
+            ]
+        );
+
+        let missing =
+            find_stale_poi(&mut poi_provider, &mut chunk_provider, &ChunkSelection::All).unwrap();
+        assert_eq!(missing.len(), 8);
+        assert_eq!(missing[3].reason, StalePoiReason::ChunkMissing);
+
+        assert_eq!(remove_stale_poi(&mut poi_provider, &stale).unwrap(), 3);
+        assert!(
+            find_stale_poi(&mut poi_provider, &mut chunk_provider, &selection)
+                .unwrap()
+                .is_empty()
+        );
+        let poi_compound_tag = poi_provider.load_chunk(0, 0).unwrap();
+        assert_eq!(poi_records(&poi_compound_tag).len(), 2);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::{
+    chunk_coords_inside_region, chunk_coords_to_region_coords, AnvilChunkMetadata, AnvilRegion,
+    ChunkLoadError, ChunkPayload, ChunkSaveError,
+};
+use nbt::CompoundTag;
+use std::fmt;
+use std::io;
+use std::io::{Read, Seek, Write};
+
+/// Coordinates of a chunk in the world, in chunks.
+///
+/// The typed positions avoid mixing up chunk, region and in-region
+/// coordinates, which the `i32` pairs used by most of the API don't. The
+/// providers accept them through the `_at` methods, such as
+/// `ChunkReader::load_chunk_at`.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{ChunkPos, RegionLocalPos, RegionPos};
+///
+/// let chunk_pos = ChunkPos::new(-1, 40);
+///
+/// assert_eq!(chunk_pos.region(), RegionPos::new(-1, 1));
+/// assert_eq!(chunk_pos.local(), RegionLocalPos::new(31, 8).unwrap());
+/// assert_eq!(ChunkPos::from_block(-1, 647), chunk_pos);
+/// assert_eq!(chunk_pos.region().chunk(chunk_pos.local()), chunk_pos);
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ChunkPos {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkPos {
+    pub fn new(x: i32, z: i32) -> Self {
+        ChunkPos { x, z }
+    }
+
+    /// Chunk containing the block at the given block coordinates.
+    pub fn from_block(block_x: i32, block_z: i32) -> Self {
+        ChunkPos::new(block_x >> 4, block_z >> 4)
+    }
+
+    /// Region containing the chunk.
+    pub fn region(self) -> RegionPos {
+        let (region_x, region_z) = chunk_coords_to_region_coords(self.x, self.z);
+
+        RegionPos::new(region_x, region_z)
+    }
+
+    /// Coordinates of the chunk inside its region.
+    pub fn local(self) -> RegionLocalPos {
+        let (x, z) = chunk_coords_inside_region(self.x, self.z);
+
+        RegionLocalPos { x, z }
+    }
+
+    /// Block coordinates of the north-west corner of the chunk.
+    pub fn min_block(self) -> (i32, i32) {
+        (self.x * 16, self.z * 16)
+    }
+}
+
+impl From<(i32, i32)> for ChunkPos {
+    fn from((x, z): (i32, i32)) -> Self {
+        ChunkPos::new(x, z)
+    }
+}
+
+impl From<ChunkPos> for (i32, i32) {
+    fn from(chunk_pos: ChunkPos) -> Self {
+        (chunk_pos.x, chunk_pos.z)
+    }
+}
+
+impl fmt::Display for ChunkPos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "chunk {} {}", self.x, self.z)
+    }
+}
+
+/// Coordinates of a region in the world, in regions of 32 by 32 chunks.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct RegionPos {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl RegionPos {
+    pub fn new(x: i32, z: i32) -> Self {
+        RegionPos { x, z }
+    }
+
+    /// Chunk at the given coordinates inside the region.
+    pub fn chunk(self, local_pos: RegionLocalPos) -> ChunkPos {
+        ChunkPos::new(
+            self.x * 32 + i32::from(local_pos.x),
+            self.z * 32 + i32::from(local_pos.z),
+        )
+    }
+
+    /// Every chunk of the region, in the order of the region header.
+    pub fn chunks(self) -> impl Iterator<Item = ChunkPos> {
+        RegionLocalPos::all().map(move |local_pos| self.chunk(local_pos))
+    }
+}
+
+impl From<(i32, i32)> for RegionPos {
+    fn from((x, z): (i32, i32)) -> Self {
+        RegionPos::new(x, z)
+    }
+}
+
+impl From<RegionPos> for (i32, i32) {
+    fn from(region_pos: RegionPos) -> Self {
+        (region_pos.x, region_pos.z)
+    }
+}
+
+impl fmt::Display for RegionPos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "region {} {}", self.x, self.z)
+    }
+}
+
+/// Coordinates of a chunk inside its region, from 0 to 31.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct RegionLocalPos {
+    x: u8,
+    z: u8,
+}
+
+impl RegionLocalPos {
+    /// Returns `None` if a coordinate is 32 or more.
+    pub fn new(x: u8, z: u8) -> Option<Self> {
+        if x < 32 && z < 32 {
+            Some(RegionLocalPos { x, z })
+        } else {
+            None
+        }
+    }
+
+    pub fn x(self) -> u8 {
+        self.x
+    }
+
+    pub fn z(self) -> u8 {
+        self.z
+    }
+
+    /// Index of the chunk in the region header.
+    pub fn index(self) -> usize {
+        self.x as usize + self.z as usize * 32
+    }
+
+    /// Every position inside a region, in the order of the region header.
+    pub fn all() -> impl Iterator<Item = RegionLocalPos> {
+        (0..32).flat_map(|z| (0..32).map(move |x| RegionLocalPos { x, z }))
+    }
+}
+
+impl<F: Seek + Read + Write> AnvilRegion<F> {
+    /// Same as `read_chunk`, with a typed position.
+    pub fn read_chunk_at(
+        &mut self,
+        local_pos: RegionLocalPos,
+    ) -> Result<CompoundTag, ChunkLoadError> {
+        self.read_chunk(local_pos.x, local_pos.z)
+    }
+
+    /// Same as `write_chunk`, with a typed position.
+    pub fn write_chunk_at<P: ChunkPayload>(
+        &mut self,
+        local_pos: RegionLocalPos,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        self.write_chunk(local_pos.x, local_pos.z, chunk_compound_tag)
+    }
+
+    /// Same as `delete_chunk`, with a typed position.
+    pub fn delete_chunk_at(&mut self, local_pos: RegionLocalPos) -> Result<(), io::Error> {
+        self.delete_chunk(local_pos.x, local_pos.z)
+    }
+
+    /// Same as `get_metadata`, with a typed position.
+    pub fn get_metadata_at(&self, local_pos: RegionLocalPos) -> AnvilChunkMetadata {
+        self.get_metadata(local_pos.x, local_pos.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChunkDeleter, ChunkReader, ChunkWriter, FolderChunkProvider};
+
+    #[test]
+    fn test_positions() {
+        for &(chunk_x, chunk_z) in &[(0, 0), (31, 32), (-1, -33), (i32::MIN, i32::MAX)] {
+            let chunk_pos = ChunkPos::new(chunk_x, chunk_z);
+            let region_pos = chunk_pos.region();
+            assert_eq!(
+                (region_pos.x, region_pos.z),
+                chunk_coords_to_region_coords(chunk_x, chunk_z)
+            );
+            assert_eq!(region_pos.chunk(chunk_pos.local()), chunk_pos);
+        }
+
+        let chunks: Vec<_> = RegionPos::new(-1, 2).chunks().collect();
+        assert_eq!(chunks.len(), 1024);
+        assert_eq!(chunks[0], ChunkPos::new(-32, 64));
+        assert_eq!(chunks[1], ChunkPos::new(-31, 64));
+        assert_eq!(chunks[1023], ChunkPos::new(-1, 95));
+        assert!(chunks
+            .iter()
+            .all(|chunk_pos| chunk_pos.region() == RegionPos::new(-1, 2)));
+
+        let local_pos = RegionLocalPos::new(3, 2).unwrap();
+        assert_eq!(local_pos.index(), 67);
+        assert_eq!(RegionLocalPos::all().nth(67), Some(local_pos));
+        assert_eq!(RegionLocalPos::new(32, 0), None);
+        assert_eq!(ChunkPos::from((4, 2)).to_string(), "chunk 4 2");
+    }
+
+    #[test]
+    fn test_provider_positions() {
+        let folder = tempfile::tempdir().unwrap();
+        let mut chunk_provider = FolderChunkProvider::new(folder.path());
+        let chunk_pos = ChunkPos::new(-1, 40);
+        chunk_provider
+            .save_chunk_at(chunk_pos, CompoundTag::new())
+            .unwrap();
+
+        assert_eq!(
+            chunk_provider.list_chunk_positions().unwrap(),
+            vec![chunk_pos]
+        );
+        assert!(chunk_provider.load_chunk_at(chunk_pos).is_ok());
+
+        let region_path = folder.path().join("r.-1.1.mca");
+        let mut region = AnvilRegion::file(region_path).unwrap();
+        assert!(region.read_chunk_at(chunk_pos.local()).is_ok());
+        region.delete_chunk_at(chunk_pos.local()).unwrap();
+        assert!(region.get_metadata_at(chunk_pos.local()).is_empty());
+
+        chunk_provider.delete_chunk_at(chunk_pos).unwrap();
+        assert!(chunk_provider
+            .try_load_chunk_at(chunk_pos)
+            .unwrap()
+            .is_none());
+    }
+}
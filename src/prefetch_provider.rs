@@ -0,0 +1,392 @@
+use crate::{
+    chunk_coords_to_region_coords, BudgetedCache, CacheStats, ChunkDeleter, ChunkLoadError,
+    ChunkPayload, ChunkReader, ChunkSaveError, ChunkWriter, FolderChunkProvider, RawChunk,
+    RegionReader, WorldEditError,
+};
+use nbt::CompoundTag;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// Default amount of prefetched chunks kept in memory.
+const DEFAULT_PREFETCH_CAPACITY: usize = 256;
+
+/// Folder provider that reads the neighbors of every loaded chunk in the
+/// background, for access patterns that move through the world in spatial
+/// order such as rendering or scanning for structures.
+///
+/// When a chunk is loaded, its 8 neighbors in the same region are read by a
+/// background thread and kept as raw chunks, so loading them afterwards only
+/// decompresses them. Chunks saved or deleted through this provider are
+/// dropped from the prefetched chunks, chunks modified by anything else
+/// while prefetched are returned as they were read.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{FolderChunkProvider, PrefetchChunkProvider};
+///
+/// let chunk_provider = PrefetchChunkProvider::new(FolderChunkProvider::new("test/region"));
+///
+/// for chunk_x in 4..8 {
+///     assert!(chunk_provider.load_chunk(chunk_x, 2).is_ok());
+/// }
+/// ```
+pub struct PrefetchChunkProvider<P = CompoundTag> {
+    /// Provider shared with the prefetching thread.
+    provider: Arc<FolderChunkProvider<P>>,
+    prefetched: Arc<Mutex<PrefetchedChunks>>,
+    /// Chunks whose neighbors are prefetched, `None` once dropped.
+    requests: Option<Sender<(i32, i32)>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+/// Raw chunks read in advance.
+struct PrefetchedChunks {
+    cache: BudgetedCache<(i32, i32), RawChunk>,
+    /// Incremented on every write, so chunks read before a write are not
+    /// kept after it.
+    version: u64,
+}
+
+impl PrefetchedChunks {
+    fn insert(&mut self, chunk: (i32, i32), raw_chunk: RawChunk) {
+        // Compression scheme and data.
+        let bytes = 1 + raw_chunk.data().len() as u64;
+        self.cache.insert(chunk, raw_chunk, bytes);
+    }
+
+    fn invalidate(&mut self, chunk: (i32, i32)) {
+        self.cache.remove(&chunk);
+        self.version += 1;
+    }
+}
+
+impl<P: ChunkPayload + 'static> PrefetchChunkProvider<P> {
+    /// Starts prefetching for the given provider, keeping up to 256
+    /// prefetched chunks.
+    pub fn new(provider: FolderChunkProvider<P>) -> Self {
+        Self::with_capacity(provider, DEFAULT_PREFETCH_CAPACITY)
+    }
+
+    /// Starts prefetching for the given provider, keeping up to `capacity`
+    /// prefetched chunks. The least recently prefetched ones are dropped
+    /// first, also when the cache budget of the provider is exceeded.
+    pub fn with_capacity(provider: FolderChunkProvider<P>, capacity: usize) -> Self {
+        let budget = provider.options.cache_budget.clone();
+        let provider = Arc::new(provider);
+        let prefetched = Arc::new(Mutex::new(PrefetchedChunks {
+            cache: BudgetedCache::new(capacity, budget),
+            version: 0,
+        }));
+        let (requests, received_requests) = mpsc::channel();
+
+        let worker = {
+            let provider = Arc::clone(&provider);
+            let prefetched = Arc::clone(&prefetched);
+
+            thread::spawn(move || {
+                for (chunk_x, chunk_z) in received_requests {
+                    prefetch_neighbors(&provider, &prefetched, chunk_x, chunk_z);
+                }
+            })
+        };
+
+        PrefetchChunkProvider {
+            provider,
+            prefetched,
+            requests: Some(requests),
+            worker: Some(worker),
+        }
+    }
+}
+
+impl<P: ChunkPayload> PrefetchChunkProvider<P> {
+    /// Returns the wrapped provider. Writes made through it don't update
+    /// the prefetched chunks.
+    pub fn provider(&self) -> &FolderChunkProvider<P> {
+        &self.provider
+    }
+
+    /// Amount of prefetched chunks kept in memory.
+    pub fn prefetched_chunks(&self) -> usize {
+        self.prefetched.lock().unwrap().cache.len()
+    }
+
+    /// Usage of the prefetched chunks, a hit is a chunk loaded without
+    /// reading it.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.prefetched.lock().unwrap().cache.stats()
+    }
+
+    /// Loads the chunk, from the prefetched chunks if possible, and starts
+    /// prefetching its neighbors.
+    pub fn load_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        let raw_chunk = self
+            .prefetched
+            .lock()
+            .unwrap()
+            .cache
+            .take(&(chunk_x, chunk_z));
+
+        let result = match raw_chunk {
+            Some(raw_chunk) => {
+                let chunk_compound_tag = raw_chunk.decode_payload()?;
+                self.provider
+                    .check_loaded_chunk(chunk_x, chunk_z, chunk_compound_tag)
+            }
+            None => self.provider.load_chunk(chunk_x, chunk_z),
+        };
+
+        if let Some(requests) = &self.requests {
+            // The worker only stops once the provider is dropped.
+            let _ = requests.send((chunk_x, chunk_z));
+        }
+
+        result
+    }
+
+    pub fn try_load_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<Option<P>, ChunkLoadError> {
+        crate::missing_chunk_as_none(self.load_chunk(chunk_x, chunk_z))
+    }
+
+    pub fn save_chunk(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        let result = self
+            .provider
+            .save_chunk(chunk_x, chunk_z, chunk_compound_tag);
+        self.invalidate(chunk_x, chunk_z);
+
+        result
+    }
+
+    pub fn save_chunk_with_timestamp(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        let result = self.provider.save_chunk_with_timestamp(
+            chunk_x,
+            chunk_z,
+            chunk_compound_tag,
+            last_modified_timestamp,
+        );
+        self.invalidate(chunk_x, chunk_z);
+
+        result
+    }
+
+    pub fn save_chunk_raw(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), WorldEditError> {
+        let result =
+            self.provider
+                .save_chunk_raw(chunk_x, chunk_z, raw_chunk, last_modified_timestamp);
+        self.invalidate(chunk_x, chunk_z);
+
+        result
+    }
+
+    pub fn delete_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError> {
+        let result = self.provider.delete_chunk(chunk_x, chunk_z);
+        self.invalidate(chunk_x, chunk_z);
+
+        result
+    }
+
+    fn invalidate(&self, chunk_x: i32, chunk_z: i32) {
+        self.prefetched
+            .lock()
+            .unwrap()
+            .invalidate((chunk_x, chunk_z));
+    }
+}
+
+/// Reads the neighbors of the chunk in the same region which are not
+/// prefetched yet. Missing and unreadable chunks are skipped.
+fn prefetch_neighbors<P: ChunkPayload>(
+    provider: &FolderChunkProvider<P>,
+    prefetched: &Mutex<PrefetchedChunks>,
+    chunk_x: i32,
+    chunk_z: i32,
+) {
+    let region = chunk_coords_to_region_coords(chunk_x, chunk_z);
+
+    for neighbor_z in chunk_z - 1..=chunk_z + 1 {
+        for neighbor_x in chunk_x - 1..=chunk_x + 1 {
+            let neighbor = (neighbor_x, neighbor_z);
+
+            if neighbor == (chunk_x, chunk_z)
+                || chunk_coords_to_region_coords(neighbor_x, neighbor_z) != region
+            {
+                continue;
+            }
+
+            let version = {
+                let prefetched = prefetched.lock().unwrap();
+
+                if prefetched.cache.contains(&neighbor) {
+                    continue;
+                }
+
+                prefetched.version
+            };
+
+            if let Ok(raw_chunk) = provider.load_chunk_raw(neighbor_x, neighbor_z) {
+                let mut prefetched = prefetched.lock().unwrap();
+
+                if prefetched.version == version {
+                    prefetched.insert(neighbor, raw_chunk);
+                }
+            }
+        }
+    }
+}
+
+impl<P> Drop for PrefetchChunkProvider<P> {
+    fn drop(&mut self) {
+        // Closing the channel stops the worker.
+        self.requests = None;
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl<P: ChunkPayload> ChunkReader<P> for PrefetchChunkProvider<P> {
+    fn get_region(&mut self, region_x: i32, region_z: i32) -> Result<RegionReader, ChunkLoadError> {
+        self.provider.region_reader(region_x, region_z)
+    }
+    fn load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        PrefetchChunkProvider::load_chunk(self, chunk_x, chunk_z)
+    }
+    fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.provider.list_chunks()
+    }
+    fn list_regions(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        Ok(self.provider.find_all_region_mca()?)
+    }
+}
+
+impl<P: ChunkPayload> ChunkWriter<P> for PrefetchChunkProvider<P> {
+    fn save_chunk_with_timestamp(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        PrefetchChunkProvider::save_chunk_with_timestamp(
+            self,
+            chunk_x,
+            chunk_z,
+            chunk_compound_tag,
+            last_modified_timestamp,
+        )
+    }
+    fn save_chunk(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        PrefetchChunkProvider::save_chunk(self, chunk_x, chunk_z, chunk_compound_tag)
+    }
+    fn save_chunk_raw(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), WorldEditError> {
+        PrefetchChunkProvider::save_chunk_raw(
+            self,
+            chunk_x,
+            chunk_z,
+            raw_chunk,
+            last_modified_timestamp,
+        )
+    }
+}
+
+impl<P: ChunkPayload> ChunkDeleter<P> for PrefetchChunkProvider<P> {
+    fn delete_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError> {
+        PrefetchChunkProvider::delete_chunk(self, chunk_x, chunk_z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::value_chunk_tag;
+    use std::fs;
+    use std::time::{Duration, Instant};
+
+    fn wait_prefetched<P: ChunkPayload>(chunk_provider: &PrefetchChunkProvider<P>, chunks: usize) {
+        let start = Instant::now();
+
+        while chunk_provider.prefetched_chunks() < chunks {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_prefetch_neighbors() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider = PrefetchChunkProvider::new(FolderChunkProvider::new(folder.path()));
+
+        // (-1, 0) is in another region.
+        for chunk_x in -1..2 {
+            for chunk_z in 0..2 {
+                chunk_provider
+                    .save_chunk(chunk_x, chunk_z, value_chunk_tag(chunk_x))
+                    .unwrap();
+            }
+        }
+
+        chunk_provider.load_chunk(0, 0).unwrap();
+        wait_prefetched(&chunk_provider, 3);
+        assert_eq!(chunk_provider.prefetched_chunks(), 3);
+
+        chunk_provider.save_chunk(1, 1, value_chunk_tag(5)).unwrap();
+        assert_eq!(chunk_provider.prefetched_chunks(), 2);
+
+        // The prefetched chunks are not read again.
+        fs::remove_file(folder.path().join("r.0.0.mca")).unwrap();
+        let chunk_compound_tag = chunk_provider.load_chunk(1, 0).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("value").unwrap(), 1);
+        assert!(chunk_provider.load_chunk(1, 1).is_err());
+    }
+
+    #[test]
+    fn test_prefetch_capacity() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider =
+            PrefetchChunkProvider::with_capacity(FolderChunkProvider::new(folder.path()), 4);
+
+        for chunk_x in 0..3 {
+            for chunk_z in 0..3 {
+                chunk_provider
+                    .save_chunk(chunk_x, chunk_z, value_chunk_tag(0))
+                    .unwrap();
+            }
+        }
+
+        chunk_provider.load_chunk(1, 1).unwrap();
+        wait_prefetched(&chunk_provider, 4);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(chunk_provider.prefetched_chunks(), 4);
+    }
+}
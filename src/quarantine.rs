@@ -0,0 +1,141 @@
+use crate::world_folder::save_gzip_nbt;
+use crate::{
+    current_timestamp, ChunkLoadError, ChunkPayload, FolderChunkProvider, RegionAndOffset,
+    SyncPolicy,
+};
+use nbt::CompoundTag;
+use std::io;
+
+/// Folder, inside the region folder, where corrupted chunks are moved by
+/// the quarantine mode, see `AnvilOptions::quarantine`.
+pub const QUARANTINE_FOLDER: &str = "quarantine";
+
+/// Returns true if the error means the stored chunk data is corrupted, as
+/// opposed to missing or unreadable because of the file system.
+pub(crate) fn is_corrupted_chunk_error(e: &ChunkLoadError) -> bool {
+    match e {
+        ChunkLoadError::LengthExceedsMaximum { .. }
+        | ChunkLoadError::EmptyChunkData
+        | ChunkLoadError::UnsupportedCompressionScheme { .. }
+        | ChunkLoadError::TagDecodeError { .. } => true,
+        // Invalid compressed data is reported by the decoders as I/O errors.
+        ChunkLoadError::ReadError { io_error } => matches!(
+            io_error.kind(),
+            io::ErrorKind::InvalidData | io::ErrorKind::InvalidInput
+        ),
+        _ => false,
+    }
+}
+
+impl<P: ChunkPayload> FolderChunkProvider<P> {
+    /// Moves the chunk at the specified coordinates out of its region into
+    /// `quarantine/c.<x>.<z>.<time>.dat`, a gzip compressed NBT file with
+    /// the raw sectors of the chunk in `Data`, its header entry (`Region`,
+    /// `SectorIndex`, `Sectors`, `LastModified`) and the `Error` which made
+    /// it fail to load. The header entry of the chunk is then cleared.
+    pub(crate) fn quarantine_chunk(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        error: &ChunkLoadError,
+    ) -> Result<(), ChunkLoadError> {
+        let RegionAndOffset {
+            region_x,
+            region_z,
+            region_chunk_x,
+            region_chunk_z,
+        } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
+
+        self.with_region(region_x, region_z, |region| {
+            let metadata = region.get_metadata(region_chunk_x, region_chunk_z);
+
+            if metadata.is_empty() {
+                return Ok(());
+            }
+
+            // Sectors past the end of the file are left out.
+            let mut data = Vec::new();
+            for sector_index in
+                metadata.sector_index..metadata.sector_index + u32::from(metadata.sectors)
+            {
+                match region.read_sector(sector_index) {
+                    Ok(sector) => data.extend(sector.iter().map(|&byte| byte as i8)),
+                    Err(_) => break,
+                }
+            }
+
+            let now = current_timestamp();
+            let mut quarantine_compound_tag = CompoundTag::new();
+            quarantine_compound_tag.insert_i32("xPos", chunk_x);
+            quarantine_compound_tag.insert_i32("zPos", chunk_z);
+            quarantine_compound_tag.insert_str("Region", self.region_file_name(region_x, region_z));
+            quarantine_compound_tag.insert_i64("SectorIndex", i64::from(metadata.sector_index));
+            quarantine_compound_tag.insert_i32("Sectors", i32::from(metadata.sectors));
+            quarantine_compound_tag
+                .insert_i64("LastModified", i64::from(metadata.last_modified_timestamp));
+            quarantine_compound_tag.insert_i64("QuarantineTime", i64::from(now));
+            quarantine_compound_tag.insert_str("Error", format!("{:?}", error));
+            quarantine_compound_tag.insert_i8_vec("Data", data);
+
+            let quarantine_path = self
+                .folder_path
+                .join(QUARANTINE_FOLDER)
+                .join(format!("c.{}.{}.{}.dat", chunk_x, chunk_z, now));
+            save_gzip_nbt(&quarantine_path, &quarantine_compound_tag, false)?;
+
+            region.delete_chunk(region_chunk_x, region_chunk_z)?;
+
+            if self.options.sync_policy == SyncPolicy::EveryWrite {
+                region.file.sync_data()?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::world_folder::load_gzip_nbt;
+    use crate::{AnvilOptions, ChunkLoadError, FolderChunkProvider, QUARANTINE_FOLDER};
+    use nbt::CompoundTag;
+    use std::fs;
+
+    #[test]
+    fn test_quarantine_corrupted_chunk() {
+        let folder = tempfile::tempdir().unwrap();
+        let options = AnvilOptions::new().quarantine(true);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        chunk_provider.save_chunk(1, 2, CompoundTag::new()).unwrap();
+        chunk_provider.save_chunk(3, 4, CompoundTag::new()).unwrap();
+
+        // Corrupt the compression scheme of the chunk at (1, 2).
+        let region_path = folder.path().join("r.0.0.mca");
+        let mut region_bytes = fs::read(&region_path).unwrap();
+        region_bytes[2 * 4096 + 4] = 42;
+        fs::write(&region_path, region_bytes).unwrap();
+
+        match chunk_provider.load_chunk(1, 2) {
+            Err(ChunkLoadError::ChunkNotFound {
+                chunk_x: 1,
+                chunk_z: 2,
+            }) => {}
+            e => panic!("Expected `ChunkNotFound` but got `{:?}`", e),
+        }
+        assert!(chunk_provider.try_load_chunk(1, 2).unwrap().is_none());
+        assert!(chunk_provider.load_chunk(3, 4).is_ok());
+        assert_eq!(chunk_provider.list_chunks().unwrap(), vec![(3, 4)]);
+
+        let quarantined: Vec<_> = fs::read_dir(folder.path().join(QUARANTINE_FOLDER))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(quarantined.len(), 1);
+        let quarantine_compound_tag = load_gzip_nbt(&quarantined[0]).unwrap();
+        assert_eq!(quarantine_compound_tag.get_i32("xPos").unwrap(), 1);
+        assert_eq!(quarantine_compound_tag.get_i64("SectorIndex").unwrap(), 2);
+        let data = quarantine_compound_tag.get_i8_vec("Data").unwrap();
+        assert_eq!(data.len(), 4096);
+        assert_eq!(data[4], 42);
+    }
+}
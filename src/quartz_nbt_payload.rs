@@ -0,0 +1,145 @@
+use crate::{ChunkLoadError, ChunkPayload};
+use nbt::{CompoundTag, Tag};
+use quartz_nbt::io::{read_nbt, write_nbt, Flavor, NbtIoError};
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use std::io;
+use std::io::{Read, Write};
+
+/// Chunks can be loaded and saved as `quartz_nbt` compounds, see
+/// `FolderChunkProvider::with_payload`.
+impl ChunkPayload for NbtCompound {
+    fn read_payload<R: Read>(reader: &mut R) -> Result<Self, ChunkLoadError> {
+        match read_nbt(reader, Flavor::Uncompressed) {
+            Ok((compound, _)) => Ok(compound),
+            Err(e) => Err(ChunkLoadError::ReadError {
+                io_error: nbt_io_error_to_io_error(e),
+            }),
+        }
+    }
+
+    fn write_payload<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        write_nbt(writer, None, self, Flavor::Uncompressed).map_err(nbt_io_error_to_io_error)
+    }
+
+    fn chunk_coordinates(&self) -> (Option<i32>, Option<i32>) {
+        let level = self.get::<_, &NbtCompound>("Level").unwrap_or(self);
+
+        (level.get("xPos").ok(), level.get("zPos").ok())
+    }
+
+    fn set_chunk_coordinates(&mut self, chunk_x: i32, chunk_z: i32) {
+        let level = if self.contains_key("Level") {
+            match self.get_mut::<_, &mut NbtCompound>("Level") {
+                Ok(level) => level,
+                Err(_) => return,
+            }
+        } else {
+            self
+        };
+
+        level.insert("xPos", chunk_x);
+        level.insert("zPos", chunk_z);
+    }
+}
+
+fn nbt_io_error_to_io_error(e: NbtIoError) -> io::Error {
+    match e {
+        NbtIoError::StdIo(io_error) => io_error,
+        e => io::Error::new(io::ErrorKind::InvalidData, e),
+    }
+}
+
+/// Converts a compound tag into a `quartz_nbt` compound without re-encoding.
+pub fn compound_tag_to_quartz(compound_tag: &CompoundTag) -> NbtCompound {
+    compound_tag
+        .iter()
+        .map(|(name, tag)| (name.clone(), tag_to_quartz(tag)))
+        .collect()
+}
+
+/// Converts a `quartz_nbt` compound into a compound tag without re-encoding.
+pub fn compound_tag_from_quartz(compound: &NbtCompound) -> CompoundTag {
+    let mut compound_tag = CompoundTag::new();
+
+    for (name, tag) in compound {
+        compound_tag.insert(name.as_str(), tag_from_quartz(tag));
+    }
+
+    compound_tag
+}
+
+fn tag_to_quartz(tag: &Tag) -> NbtTag {
+    match tag {
+        Tag::Byte(value) => NbtTag::Byte(*value),
+        Tag::Short(value) => NbtTag::Short(*value),
+        Tag::Int(value) => NbtTag::Int(*value),
+        Tag::Long(value) => NbtTag::Long(*value),
+        Tag::Float(value) => NbtTag::Float(*value),
+        Tag::Double(value) => NbtTag::Double(*value),
+        Tag::ByteArray(value) => NbtTag::ByteArray(value.clone()),
+        Tag::String(value) => NbtTag::String(value.clone()),
+        Tag::List(value) => NbtTag::List(value.iter().map(tag_to_quartz).collect::<NbtList>()),
+        Tag::Compound(value) => NbtTag::Compound(compound_tag_to_quartz(value)),
+        Tag::IntArray(value) => NbtTag::IntArray(value.clone()),
+        Tag::LongArray(value) => NbtTag::LongArray(value.clone()),
+    }
+}
+
+fn tag_from_quartz(tag: &NbtTag) -> Tag {
+    match tag {
+        NbtTag::Byte(value) => Tag::Byte(*value),
+        NbtTag::Short(value) => Tag::Short(*value),
+        NbtTag::Int(value) => Tag::Int(*value),
+        NbtTag::Long(value) => Tag::Long(*value),
+        NbtTag::Float(value) => Tag::Float(*value),
+        NbtTag::Double(value) => Tag::Double(*value),
+        NbtTag::ByteArray(value) => Tag::ByteArray(value.clone()),
+        NbtTag::String(value) => Tag::String(value.clone()),
+        NbtTag::List(value) => Tag::List(value.iter().map(tag_from_quartz).collect()),
+        NbtTag::Compound(value) => Tag::Compound(compound_tag_from_quartz(value)),
+        NbtTag::IntArray(value) => Tag::IntArray(value.clone()),
+        NbtTag::LongArray(value) => Tag::LongArray(value.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FolderChunkProvider;
+
+    #[test]
+    fn test_compound_tag_quartz_round_trip() {
+        let mut level_compound_tag = CompoundTag::new();
+        level_compound_tag.insert_i32("xPos", 4);
+        level_compound_tag.insert_i64_vec("Heightmap", vec![1, 2, 3]);
+        level_compound_tag.insert_str_vec("Names", vec!["a", "b"]);
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+
+        let compound = compound_tag_to_quartz(&chunk_compound_tag);
+        let level = compound.get::<_, &NbtCompound>("Level").unwrap();
+        assert_eq!(level.get::<_, i32>("xPos").unwrap(), 4);
+
+        let converted = compound_tag_from_quartz(&compound);
+        let level_compound_tag = converted.get_compound_tag("Level").unwrap();
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+        assert_eq!(level_compound_tag.get_i64_vec("Heightmap").unwrap(), &vec![1, 2, 3]);
+        assert_eq!(level_compound_tag.get_str_vec("Names").unwrap(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_folder_provider_quartz_payload() {
+        let chunk_provider = FolderChunkProvider::new("test/region").with_payload::<NbtCompound>();
+
+        let compound = chunk_provider.load_chunk(4, 2).unwrap();
+        assert_eq!(compound.chunk_coordinates(), (Some(4), Some(2)));
+
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider =
+            FolderChunkProvider::new(folder.path().to_str().unwrap()).with_payload::<NbtCompound>();
+        chunk_provider.save_chunk(1, 2, compound.clone()).unwrap();
+
+        assert_eq!(chunk_provider.load_chunk(1, 2).unwrap(), compound);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{ChunkLoadError, ChunkPayload, Compression, WorldEditError, DEFAULT_COMPRESSION_LEVEL};
+use nbt::CompoundTag;
+use std::io;
+
+/// Chunk data as stored in a region file: the compression scheme id followed
+/// by the compressed bytes.
+///
+/// Copying raw chunks moves them between region files without decoding, and
+/// keeps chunks with compression schemes this crate can't decode intact.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RawChunk {
+    compression_scheme: u8,
+    data: Vec<u8>,
+}
+
+impl RawChunk {
+    pub fn new(compression_scheme: u8, data: Vec<u8>) -> Self {
+        RawChunk {
+            compression_scheme,
+            data,
+        }
+    }
+
+    /// Compresses the chunk with the given compression and level, from 0 to 9.
+    pub fn encode<P: ChunkPayload>(
+        chunk_compound_tag: &P,
+        compression: Compression,
+        compression_level: u32,
+    ) -> Result<Self, io::Error> {
+        let mut data = Vec::new();
+        chunk_compound_tag.encode(&mut data, compression, compression_level)?;
+
+        Ok(RawChunk::new(compression.id(), data))
+    }
+
+    /// Compression scheme type id of the data.
+    pub fn compression_scheme(&self) -> u8 {
+        self.compression_scheme
+    }
+
+    /// Compressed chunk data.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Decompresses and decodes the chunk.
+    pub fn decode(&self) -> Result<CompoundTag, ChunkLoadError> {
+        self.decode_payload()
+    }
+
+    /// Decompresses and decodes the chunk as `P`.
+    pub fn decode_payload<P: ChunkPayload>(&self) -> Result<P, ChunkLoadError> {
+        P::decode(self.compression_scheme, &self.data)
+    }
+
+    /// Converts the chunk to the compression requested by `recode`.
+    ///
+    /// Chunks which already use the target compression are returned as is.
+    pub fn recode(self, recode: Recode) -> Result<Self, WorldEditError> {
+        let compression = match recode {
+            Recode::Preserve => return Ok(self),
+            Recode::ForceZlib => Compression::Zlib,
+            Recode::Force(compression) => compression,
+        };
+
+        if self.compression_scheme == compression.id() {
+            return Ok(self);
+        }
+
+        let chunk_compound_tag = self.decode()?;
+        let raw_chunk = RawChunk::encode(
+            &chunk_compound_tag,
+            compression,
+            DEFAULT_COMPRESSION_LEVEL,
+        )
+        .map_err(crate::ChunkSaveError::from)?;
+
+        Ok(raw_chunk)
+    }
+}
+
+/// How operations copying chunks between providers compress them.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Recode {
+    /// Keep the original compression and bytes of every chunk.
+    Preserve,
+    /// Convert every chunk to zlib, which is what Minecraft writes.
+    #[default]
+    ForceZlib,
+    /// Convert every chunk to the given compression.
+    Force(Compression),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_chunk_encode_decode() {
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_str("test_str", "test");
+
+        let raw_chunk = RawChunk::encode(&chunk_compound_tag, Compression::Gzip, 6).unwrap();
+        assert_eq!(raw_chunk.compression_scheme(), 1);
+
+        let decoded_compound_tag = raw_chunk.decode().unwrap();
+        assert_eq!(decoded_compound_tag.get_str("test_str").unwrap(), "test");
+    }
+
+    #[test]
+    fn test_raw_chunk_recode() {
+        let raw_chunk = RawChunk::encode(&CompoundTag::new(), Compression::Gzip, 6).unwrap();
+
+        let preserved = raw_chunk.clone().recode(Recode::Preserve).unwrap();
+        assert_eq!(preserved, raw_chunk);
+
+        let zlib = raw_chunk.clone().recode(Recode::ForceZlib).unwrap();
+        assert_eq!(zlib.compression_scheme(), 2);
+        assert!(zlib.decode().is_ok());
+
+        let gzip = raw_chunk.clone().recode(Recode::Force(Compression::Gzip)).unwrap();
+        assert_eq!(gzip, raw_chunk);
+    }
+
+    #[test]
+    fn test_raw_chunk_unknown_compression() {
+        // LZ4, used by Minecraft since 1.20.5.
+        let raw_chunk = RawChunk::new(4, vec![1, 2, 3]);
+
+        assert_eq!(raw_chunk.clone().recode(Recode::Preserve).unwrap(), raw_chunk);
+
+        match raw_chunk.recode(Recode::ForceZlib) {
+            Err(WorldEditError::Load(ChunkLoadError::UnsupportedCompressionScheme {
+                compression_scheme: 4,
+            })) => {}
+            e => panic!("Expected `UnsupportedCompressionScheme` but got `{:?}`", e),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    AnvilChunkMetadata, AnvilRegionHeader, ChunkDeleter, ChunkLoadError, ChunkPayload, ChunkReader,
+    ChunkSaveError, ChunkSelection, ChunkWriter, RawChunk, RegionInfo, RegionReader,
+    WorldEditError,
+};
+use nbt::CompoundTag;
+use std::marker::PhantomData;
+
+/// Provider rejecting every save and delete with `ChunkSaveError::ReadOnly`,
+/// whatever the wrapped provider allows.
+///
+/// Unlike `AnvilOptions::read_only`, the wrapped provider can't be
+/// reached back, so the guard can be handed to code that must not modify
+/// the world. Wrap `&mut provider` to keep using the provider afterwards.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{
+///     ChunkReader, ChunkSaveError, ChunkWriter, FolderChunkProvider, ReadOnlyChunkProvider,
+/// };
+/// use nbt::CompoundTag;
+///
+/// let mut folder_provider = FolderChunkProvider::new("test/region");
+/// let mut chunk_provider = ReadOnlyChunkProvider::new(&mut folder_provider);
+///
+/// assert!(chunk_provider.load_chunk(4, 2).is_ok());
+/// assert!(matches!(
+///     chunk_provider.save_chunk(4, 2, CompoundTag::new()),
+///     Err(ChunkSaveError::ReadOnly)
+/// ));
+/// ```
+pub struct ReadOnlyChunkProvider<T, P = CompoundTag> {
+    provider: T,
+    payload: PhantomData<fn() -> P>,
+}
+
+impl<T: ChunkReader<P>, P: ChunkPayload> ReadOnlyChunkProvider<T, P> {
+    /// Guards the provider against modifications.
+    pub fn new(provider: T) -> Self {
+        ReadOnlyChunkProvider {
+            provider,
+            payload: PhantomData,
+        }
+    }
+}
+
+impl<T: ChunkReader<P>, P: ChunkPayload> ChunkReader<P> for ReadOnlyChunkProvider<T, P> {
+    fn get_region(&mut self, region_x: i32, region_z: i32) -> Result<RegionReader, ChunkLoadError> {
+        self.provider.get_region(region_x, region_z)
+    }
+    fn load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        self.provider.load_chunk(chunk_x, chunk_z)
+    }
+    fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.provider.list_chunks()
+    }
+    fn list_regions(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.provider.list_regions()
+    }
+    fn try_load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<Option<P>, ChunkLoadError> {
+        self.provider.try_load_chunk(chunk_x, chunk_z)
+    }
+    fn list_regions_detailed(&mut self) -> Result<Vec<RegionInfo>, ChunkLoadError> {
+        self.provider.list_regions_detailed()
+    }
+    fn list_chunks_in(
+        &mut self,
+        selection: &ChunkSelection,
+    ) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.provider.list_chunks_in(selection)
+    }
+    fn load_region_header(
+        &mut self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<AnvilRegionHeader, ChunkLoadError> {
+        self.provider.load_region_header(region_x, region_z)
+    }
+    fn load_chunk_metadata(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+    ) -> Result<AnvilChunkMetadata, ChunkLoadError> {
+        self.provider.load_chunk_metadata(chunk_x, chunk_z)
+    }
+    fn load_chunk_raw(&mut self, chunk_x: i32, chunk_z: i32) -> Result<RawChunk, ChunkLoadError> {
+        self.provider.load_chunk_raw(chunk_x, chunk_z)
+    }
+}
+
+impl<T: ChunkReader<P>, P: ChunkPayload> ChunkWriter<P> for ReadOnlyChunkProvider<T, P> {
+    fn save_chunk_with_timestamp(
+        &mut self,
+        _chunk_x: i32,
+        _chunk_z: i32,
+        _chunk_compound_tag: P,
+        _last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        Err(ChunkSaveError::ReadOnly)
+    }
+    fn save_chunk_raw(
+        &mut self,
+        _chunk_x: i32,
+        _chunk_z: i32,
+        _raw_chunk: &RawChunk,
+        _last_modified_timestamp: u32,
+    ) -> Result<(), WorldEditError> {
+        Err(ChunkSaveError::ReadOnly.into())
+    }
+}
+
+impl<T: ChunkReader<P>, P: ChunkPayload> ChunkDeleter<P> for ReadOnlyChunkProvider<T, P> {
+    fn delete_chunk(&mut self, _chunk_x: i32, _chunk_z: i32) -> Result<(), ChunkSaveError> {
+        Err(ChunkSaveError::ReadOnly)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilChunkProvider, FolderChunkProvider};
+    use std::fs;
+
+    #[test]
+    fn test_read_only_provider() {
+        let folder = tempfile::tempdir().unwrap();
+        let region_path = folder.path().join("r.0.0.mca");
+        fs::copy("test/region/r.0.0.mca", &region_path).unwrap();
+        let region_bytes = fs::read(&region_path).unwrap();
+
+        let mut folder_provider = FolderChunkProvider::new(folder.path());
+        {
+            let mut chunk_provider: Box<dyn AnvilChunkProvider> =
+                Box::new(ReadOnlyChunkProvider::new(&mut folder_provider));
+
+            assert_eq!(chunk_provider.list_chunks().unwrap().len(), 277);
+            let raw_chunk = chunk_provider.load_chunk_raw(4, 2).unwrap();
+
+            assert!(matches!(
+                chunk_provider.save_chunk(0, 0, CompoundTag::new()),
+                Err(ChunkSaveError::ReadOnly)
+            ));
+            assert!(matches!(
+                chunk_provider.save_chunk_raw(5, 2, &raw_chunk, 0),
+                Err(WorldEditError::Save(ChunkSaveError::ReadOnly))
+            ));
+        }
+        {
+            let mut chunk_provider = ReadOnlyChunkProvider::new(&mut folder_provider);
+
+            assert!(matches!(
+                chunk_provider.delete_chunk(4, 2),
+                Err(ChunkSaveError::ReadOnly)
+            ));
+        }
+
+        assert_eq!(fs::read(&region_path).unwrap(), region_bytes);
+        folder_provider.delete_chunk(4, 2).unwrap();
+    }
+}
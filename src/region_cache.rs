@@ -0,0 +1,160 @@
+use crate::{AnvilRegion, BudgetedCache, CacheBudget, CacheStats};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Region file held in memory, shared by a cache and the readers returned
+/// by `get_region`.
+#[derive(Clone, Debug)]
+pub(crate) struct RegionBytes(pub(crate) Arc<Vec<u8>>);
+
+impl AsRef<[u8]> for RegionBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Region file kept open by a cache.
+pub(crate) struct OpenRegion {
+    pub(crate) region: AnvilRegion<File>,
+    /// Whether the file was opened for writing. Regions opened to load
+    /// chunks are reopened before writing to them.
+    pub(crate) writable: bool,
+}
+
+/// Open region files kept by a provider, least recently used first.
+///
+/// Regions are taken out of the cache while they are in use, so a region is
+/// never shared and callbacks may use the provider again without deadlocks.
+pub(crate) struct RegionCache {
+    /// Open regions, at most `max_open_regions`. Zero disables the cache.
+    regions: BudgetedCache<(i32, i32), OpenRegion>,
+}
+
+impl RegionCache {
+    pub(crate) fn new(max_open_regions: usize, budget: Option<CacheBudget>) -> Self {
+        RegionCache {
+            regions: BudgetedCache::new(max_open_regions, budget),
+        }
+    }
+
+    /// Takes the region out of the cache, if it is open.
+    pub(crate) fn take(&mut self, region: (i32, i32)) -> Option<OpenRegion> {
+        self.regions.take(&region)
+    }
+
+    /// Flushes and closes the region, if it is open.
+    pub(crate) fn remove(&mut self, region: (i32, i32)) -> io::Result<()> {
+        match self.regions.remove(&region) {
+            Some(mut open_region) => open_region.region.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Puts a region back as the most recently used one, closing the least
+    /// recently used regions over the capacity or the budget.
+    pub(crate) fn put(
+        &mut self,
+        region: (i32, i32),
+        open_region: OpenRegion,
+    ) -> io::Result<()> {
+        let bytes = open_region.region.memory_bytes();
+        let mut result = Ok(());
+
+        for mut evicted in self.regions.insert(region, open_region, bytes) {
+            result = result.and(evicted.region.flush());
+        }
+
+        result
+    }
+
+    /// Flushes and closes every open region.
+    pub(crate) fn clear(&mut self) -> io::Result<()> {
+        let mut result = Ok(());
+
+        for (_, mut open_region) in self.regions.drain() {
+            result = result.and(open_region.region.flush());
+        }
+
+        result
+    }
+
+    /// Open regions, least recently used first.
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (&(i32, i32), &mut OpenRegion)> {
+        self.regions.iter_mut()
+    }
+
+    /// Writes the header updates kept in memory by the region, if it is
+    /// open, so that its file can be read directly.
+    pub(crate) fn write_header(&mut self, region: (i32, i32)) -> io::Result<()> {
+        match self.regions.peek_mut(&region) {
+            Some(open_region) => open_region.region.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Takes every open region out of the cache, without flushing them.
+    pub(crate) fn take_all(&mut self) -> Vec<((i32, i32), OpenRegion)> {
+        self.regions.drain()
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        self.regions.stats()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn open_regions(&self) -> usize {
+        self.regions.len()
+    }
+}
+
+impl Drop for RegionCache {
+    fn drop(&mut self) {
+        // Header updates kept in memory would be lost otherwise.
+        let _ = self.clear();
+    }
+}
+
+/// Regions in use by a provider, so every region is used by one thread at a
+/// time while different regions are used in parallel.
+pub(crate) struct RegionLocks {
+    locked: Mutex<HashSet<(i32, i32)>>,
+    unlocked: Condvar,
+}
+
+impl RegionLocks {
+    pub(crate) fn new() -> Self {
+        RegionLocks {
+            locked: Mutex::new(HashSet::new()),
+            unlocked: Condvar::new(),
+        }
+    }
+
+    /// Waits until the region is not in use and marks it as used until the
+    /// returned guard is dropped.
+    pub(crate) fn lock(&self, region: (i32, i32)) -> RegionLockGuard<'_> {
+        let mut locked = self.locked.lock().unwrap();
+
+        while !locked.insert(region) {
+            locked = self.unlocked.wait(locked).unwrap();
+        }
+
+        RegionLockGuard {
+            locks: self,
+            region,
+        }
+    }
+}
+
+pub(crate) struct RegionLockGuard<'a> {
+    locks: &'a RegionLocks,
+    region: (i32, i32),
+}
+
+impl Drop for RegionLockGuard<'_> {
+    fn drop(&mut self) {
+        self.locks.locked.lock().unwrap().remove(&self.region);
+        self.locks.unlocked.notify_all();
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{read_chunk_length, AnvilRegion, REGION_SECTOR_BYTES_LENGTH};
+use std::io;
+use std::io::{Read, Seek, Write};
+
+/// Amount of sectors in a row of the sector map.
+const SECTOR_MAP_ROW: usize = 64;
+
+impl<F: Seek + Read + Write> AnvilRegion<F> {
+    /// Writes a human readable description of the region header, for
+    /// debugging corrupted regions by hand.
+    ///
+    /// The first part is a table with a row for every stored chunk: its
+    /// coordinates inside the region, first sector, amount of sectors,
+    /// timestamp and the length stored before its data, or `-` if the
+    /// length can't be read.
+    ///
+    /// The second part is a map of the sectors of the file, 64 per row:
+    ///
+    /// - `H` is a header sector.
+    /// - `#` is a sector used by one chunk.
+    /// - `X` is a sector used by more than one chunk.
+    /// - `.` is a free sector.
+    ///
+    /// Sectors of chunks past the end of the file are not shown in the map.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::AnvilRegion;
+    /// use nbt::CompoundTag;
+    /// use std::io::Cursor;
+    ///
+    /// let mut region = AnvilRegion::new(Cursor::new(Vec::new())).unwrap();
+    /// region.write_chunk_with_timestamp(3, 1, CompoundTag::new(), 1234).unwrap();
+    ///
+    /// let mut dump = Vec::new();
+    /// region.dump_header(&mut dump).unwrap();
+    ///
+    /// assert_eq!(
+    ///     String::from_utf8(dump).unwrap(),
+    ///     "  x  z  sector sectors  timestamp  length\n\
+    ///     \x20 3  1       2       1       1234      13\n\
+    ///     \n\
+    ///     sectors: 3, free: 0\n\
+    ///     \x20    0 HH#\n"
+    /// );
+    /// ```
+    pub fn dump_header<W: Write>(&mut self, writer: &mut W) -> Result<(), io::Error> {
+        let file_length = self.stream_len()?;
+        let file_sectors = file_length.div_ceil(REGION_SECTOR_BYTES_LENGTH as u64) as usize;
+        // Amount of chunks using every sector, headers included.
+        let mut sector_users = vec![0u32; file_sectors];
+
+        for sector_users in sector_users.iter_mut().take(2) {
+            *sector_users += 1;
+        }
+
+        writeln!(
+            writer,
+            "{:>3}{:>3} {:>7} {:>7} {:>10} {:>7}",
+            "x", "z", "sector", "sectors", "timestamp", "length"
+        )?;
+
+        for (index, metadata) in self.chunks_metadata.iter().enumerate() {
+            if metadata.is_empty() {
+                continue;
+            }
+
+            let start_index = metadata.sector_index as usize;
+            let end_index = start_index + metadata.sectors as usize;
+
+            let length = if end_index <= file_sectors {
+                read_chunk_length(&mut self.file, *metadata).ok()
+            } else {
+                None
+            };
+
+            for sector_users in sector_users.iter_mut().take(end_index).skip(start_index) {
+                *sector_users += 1;
+            }
+
+            writeln!(
+                writer,
+                "{:>3}{:>3} {:>7} {:>7} {:>10} {:>7}",
+                index % 32,
+                index / 32,
+                metadata.sector_index,
+                metadata.sectors,
+                metadata.last_modified_timestamp,
+                length.map_or("-".to_string(), |length| length.to_string())
+            )?;
+        }
+
+        let free_sectors = sector_users.iter().filter(|&&users| users == 0).count();
+        writeln!(writer)?;
+        writeln!(writer, "sectors: {}, free: {}", file_sectors, free_sectors)?;
+
+        for (row, row_users) in sector_users.chunks(SECTOR_MAP_ROW).enumerate() {
+            let row_start = row * SECTOR_MAP_ROW;
+            let map: String = row_users
+                .iter()
+                .enumerate()
+                .map(|(offset, &users)| match (row_start + offset, users) {
+                    (0..=1, _) => 'H',
+                    (_, 0) => '.',
+                    (_, 1) => '#',
+                    _ => 'X',
+                })
+                .collect();
+
+            writeln!(writer, "{:>6} {}", row_start, map)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nbt::CompoundTag;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_dump_header_overlapping_chunks() {
+        let mut region = AnvilRegion::new(Cursor::new(Vec::new())).unwrap();
+        region
+            .write_chunk_with_timestamp(0, 0, CompoundTag::new(), 1)
+            .unwrap();
+        region
+            .write_chunk_with_timestamp(1, 0, CompoundTag::new(), 2)
+            .unwrap();
+
+        // Point the second chunk at the sector of the first one, and add a
+        // chunk past the end of the file.
+        let mut header = region.read_sector(0).unwrap();
+        header[4..8].copy_from_slice(&[0, 0, 2, 1]);
+        header[8..12].copy_from_slice(&[0, 0, 9, 2]);
+        region.write_sector(0, &header).unwrap();
+
+        let mut dump = Vec::new();
+        region.dump_header(&mut dump).unwrap();
+
+        let expected = [
+            "  x  z  sector sectors  timestamp  length",
+            "  0  0       2       1          1      13",
+            "  1  0       2       1          2      13",
+            "  2  0       9       2          0       -",
+            "",
+            "sectors: 4, free: 1",
+            "     0 HHX.",
+            "",
+        ];
+        assert_eq!(String::from_utf8(dump).unwrap(), expected.join("\n"));
+    }
+}
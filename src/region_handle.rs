@@ -0,0 +1,208 @@
+use crate::{
+    AnvilChunkMetadata, AnvilRegionHeader, ChunkDeleter, ChunkLoadError, ChunkPayload, ChunkReader,
+    ChunkSaveError, ChunkWriter, RawChunk, WorldEditError,
+};
+use nbt::CompoundTag;
+use std::marker::PhantomData;
+
+/// Region of a provider returned by `ChunkReader::open_region`, whose chunks
+/// are read and written with their coordinates inside the region, like with
+/// `AnvilRegion`.
+///
+/// Chunks can be written when the provider is a `ChunkWriter`, and removed
+/// when it is a `ChunkDeleter`.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{ChunkReader, FolderChunkProvider};
+///
+/// let mut chunk_provider = FolderChunkProvider::new("test/region");
+/// let mut region = chunk_provider.open_region(0, 0);
+///
+/// assert_eq!(region.header().unwrap().chunks().count(), 277);
+/// assert!(region.read_chunk(4, 2).is_ok());
+/// assert!(region.try_read_chunk(15, 14).unwrap().is_none());
+/// ```
+pub struct RegionHandle<'a, T: ?Sized, P = CompoundTag> {
+    provider: &'a mut T,
+    region_x: i32,
+    region_z: i32,
+    payload: PhantomData<fn() -> P>,
+}
+
+impl<'a, T: ?Sized, P> RegionHandle<'a, T, P> {
+    pub(crate) fn new(provider: &'a mut T, region_x: i32, region_z: i32) -> Self {
+        RegionHandle {
+            provider,
+            region_x,
+            region_z,
+            payload: PhantomData,
+        }
+    }
+
+    pub fn region_x(&self) -> i32 {
+        self.region_x
+    }
+
+    pub fn region_z(&self) -> i32 {
+        self.region_z
+    }
+
+    /// World coordinates of the chunk at the coordinates inside the region.
+    fn chunk_coords(&self, chunk_x: u8, chunk_z: u8) -> (i32, i32) {
+        assert!(32 > chunk_x, "Region chunk x coordinate out of bounds");
+        assert!(32 > chunk_z, "Region chunk z coordinate out of bounds");
+
+        (
+            self.region_x * 32 + i32::from(chunk_x),
+            self.region_z * 32 + i32::from(chunk_z),
+        )
+    }
+}
+
+impl<'a, T: ChunkReader<P> + ?Sized, P: ChunkPayload> RegionHandle<'a, T, P> {
+    /// Reads the header of the region.
+    pub fn header(&mut self) -> Result<AnvilRegionHeader, ChunkLoadError> {
+        self.provider
+            .load_region_header(self.region_x, self.region_z)
+    }
+
+    /// Returns chunk metadata at specified coordinates.
+    pub fn get_metadata(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+    ) -> Result<AnvilChunkMetadata, ChunkLoadError> {
+        let (chunk_x, chunk_z) = self.chunk_coords(chunk_x, chunk_z);
+
+        self.provider.load_chunk_metadata(chunk_x, chunk_z)
+    }
+
+    pub fn read_chunk(&mut self, chunk_x: u8, chunk_z: u8) -> Result<P, ChunkLoadError> {
+        let (chunk_x, chunk_z) = self.chunk_coords(chunk_x, chunk_z);
+
+        self.provider.load_chunk(chunk_x, chunk_z)
+    }
+
+    /// Same as `read_chunk`, but a missing region or chunk is returned as
+    /// `Ok(None)` instead of an error.
+    pub fn try_read_chunk(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+    ) -> Result<Option<P>, ChunkLoadError> {
+        let (chunk_x, chunk_z) = self.chunk_coords(chunk_x, chunk_z);
+
+        self.provider.try_load_chunk(chunk_x, chunk_z)
+    }
+
+    /// Reads the chunk data without decompressing it.
+    pub fn read_chunk_raw(&mut self, chunk_x: u8, chunk_z: u8) -> Result<RawChunk, ChunkLoadError> {
+        let (chunk_x, chunk_z) = self.chunk_coords(chunk_x, chunk_z);
+
+        self.provider.load_chunk_raw(chunk_x, chunk_z)
+    }
+}
+
+impl<'a, T: ChunkWriter<P> + ?Sized, P: ChunkPayload> RegionHandle<'a, T, P> {
+    /// Writes chunk data, stamping it with the current time.
+    pub fn write_chunk(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        let (chunk_x, chunk_z) = self.chunk_coords(chunk_x, chunk_z);
+
+        self.provider
+            .save_chunk(chunk_x, chunk_z, chunk_compound_tag)
+    }
+
+    /// Writes chunk data, stamping it with the given last modification time
+    /// in seconds since the Unix epoch.
+    pub fn write_chunk_with_timestamp(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+        chunk_compound_tag: P,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        let (chunk_x, chunk_z) = self.chunk_coords(chunk_x, chunk_z);
+
+        self.provider.save_chunk_with_timestamp(
+            chunk_x,
+            chunk_z,
+            chunk_compound_tag,
+            last_modified_timestamp,
+        )
+    }
+
+    /// Writes already compressed chunk data.
+    pub fn write_chunk_raw(
+        &mut self,
+        chunk_x: u8,
+        chunk_z: u8,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), WorldEditError> {
+        let (chunk_x, chunk_z) = self.chunk_coords(chunk_x, chunk_z);
+
+        self.provider
+            .save_chunk_raw(chunk_x, chunk_z, raw_chunk, last_modified_timestamp)
+    }
+}
+
+impl<'a, T: ChunkDeleter<P> + ?Sized, P: ChunkPayload> RegionHandle<'a, T, P> {
+    /// Removes the chunk at the specified coordinates, if it exists.
+    pub fn delete_chunk(&mut self, chunk_x: u8, chunk_z: u8) -> Result<(), ChunkSaveError> {
+        let (chunk_x, chunk_z) = self.chunk_coords(chunk_x, chunk_z);
+
+        self.provider.delete_chunk(chunk_x, chunk_z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FolderChunkProvider, ReadOnlyChunkProvider};
+    use std::fs;
+
+    #[test]
+    fn test_region_handle() {
+        let folder = tempfile::tempdir().unwrap();
+        fs::copy("test/region/r.0.0.mca", folder.path().join("r.0.0.mca")).unwrap();
+
+        let mut chunk_provider = FolderChunkProvider::new(folder.path());
+        let mut region = chunk_provider.open_region(-1, 0);
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("xPos", -31);
+        region
+            .write_chunk_with_timestamp(1, 2, chunk_compound_tag, 77)
+            .unwrap();
+
+        assert_eq!(
+            region.get_metadata(1, 2).unwrap().last_modified_timestamp(),
+            77
+        );
+        let chunk_compound_tag = region.read_chunk(1, 2).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("xPos").unwrap(), -31);
+        assert!(chunk_provider.load_chunk(-31, 2).is_ok());
+
+        let mut region = chunk_provider.open_region(0, 0);
+        let raw_chunk = region.read_chunk_raw(4, 2).unwrap();
+        region.write_chunk_raw(15, 14, &raw_chunk, 0).unwrap();
+        region.delete_chunk(4, 2).unwrap();
+        assert!(region.try_read_chunk(4, 2).unwrap().is_none());
+        assert_eq!(region.read_chunk_raw(15, 14).unwrap(), raw_chunk);
+
+        // Writes are rejected by read-only providers.
+        let mut read_only_provider = ReadOnlyChunkProvider::new(&mut chunk_provider);
+        let mut region = read_only_provider.open_region(0, 0);
+        assert!(region.read_chunk(15, 14).is_ok());
+        assert!(matches!(
+            region.delete_chunk(15, 14),
+            Err(ChunkSaveError::ReadOnly)
+        ));
+    }
+}
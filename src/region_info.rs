@@ -0,0 +1,163 @@
+use crate::{
+    AnvilRegionHeader, ChunkLoadError, ChunkPayload, ChunkReader, FolderChunkProvider,
+    REGION_HEADER_BYTES_LENGTH,
+};
+use std::fs;
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::time::SystemTime;
+
+/// Summary of a region file, see `ChunkReader::list_regions_detailed`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RegionInfo {
+    /// Region coordinates.
+    pub region: (i32, i32),
+    /// Size of the stored region file in bytes.
+    pub file_size: u64,
+    /// Last modification time of the file, `None` when the storage doesn't
+    /// keep it.
+    pub modified: Option<SystemTime>,
+    /// Amount of chunks stored in the region, according to its header.
+    pub chunks: usize,
+}
+
+/// Amount of chunks in the header, regions shorter than the header have no
+/// chunks.
+pub(crate) fn count_header_chunks<R: std::io::Read + ?Sized>(
+    reader: &mut R,
+    file_size: u64,
+) -> Result<usize, ChunkLoadError> {
+    if file_size < REGION_HEADER_BYTES_LENGTH {
+        return Ok(0);
+    }
+
+    Ok(AnvilRegionHeader::from_reader(reader)?.chunks().count())
+}
+
+/// Default `ChunkReader::list_regions_detailed`, measuring the regions
+/// returned by `get_region`.
+pub(crate) fn list_regions_detailed<P, T>(
+    provider: &mut T,
+) -> Result<Vec<RegionInfo>, ChunkLoadError>
+where
+    P: ChunkPayload,
+    T: ChunkReader<P> + ?Sized,
+{
+    let mut regions = Vec::new();
+
+    for (region_x, region_z) in provider.list_regions()? {
+        let mut region = provider.get_region(region_x, region_z)?;
+        let file_size = region.seek(SeekFrom::End(0))?;
+        region.seek(SeekFrom::Start(0))?;
+
+        regions.push(RegionInfo {
+            region: (region_x, region_z),
+            file_size,
+            modified: None,
+            chunks: count_header_chunks(&mut region, file_size)?,
+        });
+    }
+
+    Ok(regions)
+}
+
+impl<P: ChunkPayload> FolderChunkProvider<P> {
+    /// Lists the regions with the size and modification time of their file
+    /// and their amount of chunks, in the listing order of the options.
+    ///
+    /// Only the region headers are read. Regions only stored compressed
+    /// report the size of the compressed file.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::FolderChunkProvider;
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("test/region");
+    /// let regions = chunk_provider.list_regions_detailed().unwrap();
+    ///
+    /// assert_eq!(regions[0].region, (0, 0));
+    /// assert_eq!(regions[0].chunks, 277);
+    /// assert!(regions[0].modified.is_some());
+    /// ```
+    pub fn list_regions_detailed(&self) -> Result<Vec<RegionInfo>, ChunkLoadError> {
+        let mut regions = Vec::new();
+
+        for (region_x, region_z) in self.find_all_region_mca()? {
+            let (metadata, chunks) = match self.compressed_region(region_x, region_z) {
+                Some((compressed_path, _)) => {
+                    let metadata = fs::metadata(compressed_path)?;
+                    let mut region = self.region_reader(region_x, region_z)?;
+                    let length = region.seek(SeekFrom::End(0))?;
+                    region.seek(SeekFrom::Start(0))?;
+
+                    (metadata, count_header_chunks(&mut region, length)?)
+                }
+                None => {
+                    self.write_open_header(region_x, region_z)?;
+                    let mut file = File::open(self.region_path(region_x, region_z))?;
+                    let metadata = file.metadata()?;
+                    let chunks = count_header_chunks(&mut file, metadata.len())?;
+
+                    (metadata, chunks)
+                }
+            };
+
+            regions.push(RegionInfo {
+                region: (region_x, region_z),
+                file_size: metadata.len(),
+                modified: metadata.modified().ok(),
+                chunks,
+            });
+        }
+
+        Ok(regions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilChunkProvider, ReadOnlyChunkProvider};
+    use nbt::CompoundTag;
+
+    #[test]
+    fn test_list_regions_detailed() {
+        let folder = tempfile::tempdir().unwrap();
+        fs::copy("test/region/r.0.0.mca", folder.path().join("r.0.0.mca")).unwrap();
+        let chunk_provider = FolderChunkProvider::new(folder.path());
+        chunk_provider
+            .save_chunk(-1, -1, CompoundTag::new())
+            .unwrap();
+        // Empty region file.
+        File::create(folder.path().join("r.5.5.mca")).unwrap();
+
+        let mut regions = chunk_provider.list_regions_detailed().unwrap();
+        regions.sort_unstable_by_key(|info| info.region);
+        let file_size = fs::metadata("test/region/r.0.0.mca").unwrap().len();
+
+        assert_eq!(regions.len(), 3);
+        assert_eq!(regions[0].region, (-1, -1));
+        assert_eq!(regions[0].chunks, 1);
+        assert_eq!(regions[0].file_size, 3 * 4096);
+        assert_eq!(regions[1].region, (0, 0));
+        assert_eq!(regions[1].chunks, 277);
+        assert_eq!(regions[1].file_size, file_size);
+        assert_eq!(regions[2].chunks, 0);
+        assert_eq!(regions[2].file_size, 0);
+
+        // Default implementation, without modification times.
+        let mut chunk_provider: Box<dyn AnvilChunkProvider> = Box::new(chunk_provider);
+        let mut read_only_provider = ReadOnlyChunkProvider::new(&mut chunk_provider);
+        let mut default_regions =
+            list_regions_detailed::<CompoundTag, _>(&mut read_only_provider).unwrap();
+        default_regions.sort_unstable_by_key(|info| info.region);
+
+        for (info, default_info) in regions.iter().zip(&default_regions) {
+            assert_eq!(default_info.region, info.region);
+            assert_eq!(default_info.file_size, info.file_size);
+            assert_eq!(default_info.chunks, info.chunks);
+            assert_eq!(default_info.modified, None);
+        }
+    }
+}
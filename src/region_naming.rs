@@ -0,0 +1,203 @@
+use crate::{parse_region_file_name, strict_parse_int};
+use std::fmt;
+use std::sync::Arc;
+
+/// Naming scheme of the region files of a `FolderChunkProvider`, set with
+/// `AnvilOptions::region_naming`.
+///
+/// Compressed region files keep their extension after the name, for
+/// example "region_1_2.dat.gz".
+pub trait RegionNaming: Send + Sync {
+    /// Name of the region file of the region.
+    fn region_name(&self, region_x: i32, region_z: i32) -> String;
+
+    /// Coordinates of the region stored in the file, `None` if the name
+    /// isn't the name of a region file.
+    fn parse_region_name(&self, name: &str) -> Option<(i32, i32)>;
+}
+
+/// Names used by Minecraft, "r.1.2.mca".
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct VanillaNaming;
+
+impl RegionNaming for VanillaNaming {
+    fn region_name(&self, region_x: i32, region_z: i32) -> String {
+        format!("r.{}.{}.mca", region_x, region_z)
+    }
+
+    fn parse_region_name(&self, name: &str) -> Option<(i32, i32)> {
+        parse_region_file_name(name)
+    }
+}
+
+/// Names following a pattern where `{x}` and `{z}` are replaced by the
+/// coordinates of the region.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{PatternNaming, RegionNaming};
+///
+/// let naming = PatternNaming::new("region_{x}_{z}.dat").unwrap();
+///
+/// assert_eq!(naming.region_name(-1, 2), "region_-1_2.dat");
+/// assert_eq!(naming.parse_region_name("region_-1_2.dat"), Some((-1, 2)));
+/// assert_eq!(naming.parse_region_name("r.-1.2.mca"), None);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PatternNaming {
+    prefix: String,
+    separator: String,
+    suffix: String,
+    z_first: bool,
+}
+
+impl PatternNaming {
+    /// Pattern containing `{x}` and `{z}` once each, separated by at least
+    /// one character. Returns `None` for other patterns.
+    pub fn new(pattern: &str) -> Option<Self> {
+        let x_index = pattern.find("{x}")?;
+        let z_index = pattern.find("{z}")?;
+
+        if pattern.matches("{x}").count() != 1 || pattern.matches("{z}").count() != 1 {
+            return None;
+        }
+
+        let z_first = z_index < x_index;
+        let (first, second) = if z_first {
+            (z_index, x_index)
+        } else {
+            (x_index, z_index)
+        };
+        let separator = &pattern[first + 3..second];
+
+        if separator.is_empty() {
+            return None;
+        }
+
+        Some(PatternNaming {
+            prefix: pattern[..first].to_string(),
+            separator: separator.to_string(),
+            suffix: pattern[second + 3..].to_string(),
+            z_first,
+        })
+    }
+
+    /// Same as the vanilla names but with another extension, for example
+    /// "mca.bak".
+    pub fn with_extension(extension: &str) -> Self {
+        PatternNaming {
+            prefix: "r.".to_string(),
+            separator: ".".to_string(),
+            suffix: format!(".{}", extension),
+            z_first: false,
+        }
+    }
+}
+
+impl RegionNaming for PatternNaming {
+    fn region_name(&self, region_x: i32, region_z: i32) -> String {
+        let (first, second) = if self.z_first {
+            (region_z, region_x)
+        } else {
+            (region_x, region_z)
+        };
+
+        format!(
+            "{}{}{}{}{}",
+            self.prefix, first, self.separator, second, self.suffix
+        )
+    }
+
+    fn parse_region_name(&self, name: &str) -> Option<(i32, i32)> {
+        let coordinates = name
+            .strip_prefix(self.prefix.as_str())?
+            .strip_suffix(self.suffix.as_str())?;
+
+        // The separator may also appear in the coordinates, as in "{x}-{z}".
+        let bytes = coordinates.as_bytes();
+        let (first, second) = coordinates
+            .match_indices(self.separator.as_str())
+            .find_map(|(index, _)| {
+                let first = strict_parse_int::strict_parse_i32(&bytes[..index])?;
+                let second =
+                    strict_parse_int::strict_parse_i32(&bytes[index + self.separator.len()..])?;
+
+                Some((first, second))
+            })?;
+
+        if self.z_first {
+            Some((second, first))
+        } else {
+            Some((first, second))
+        }
+    }
+}
+
+/// Naming set in the options, compared by identity as trait objects can't
+/// be compared.
+#[derive(Clone)]
+pub(crate) struct SharedNaming(pub(crate) Arc<dyn RegionNaming>);
+
+impl fmt::Debug for SharedNaming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SharedNaming")
+            .field(&self.0.region_name(0, 0))
+            .finish()
+    }
+}
+
+impl PartialEq for SharedNaming {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedNaming {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilOptions, FolderChunkProvider};
+    use nbt::CompoundTag;
+
+    #[test]
+    fn test_pattern_naming() {
+        assert!(PatternNaming::new("region_{x}{z}").is_none());
+        assert!(PatternNaming::new("region_{x}.dat").is_none());
+        assert!(PatternNaming::new("{x}_{x}_{z}").is_none());
+
+        let naming = PatternNaming::new("{z}-{x}").unwrap();
+        assert_eq!(naming.region_name(-3, -4), "-4--3");
+        assert_eq!(naming.parse_region_name("-4--3"), Some((-3, -4)));
+        assert_eq!(naming.parse_region_name("-4-03"), None);
+
+        let naming = PatternNaming::with_extension("mca.bak");
+        assert_eq!(naming.region_name(1, 2), "r.1.2.mca.bak");
+        assert_eq!(naming.parse_region_name("r.1.2.mca.bak"), Some((1, 2)));
+        assert_eq!(naming.parse_region_name("r.1.2.mca"), None);
+    }
+
+    #[test]
+    fn test_provider_region_naming() {
+        let folder = tempfile::tempdir().unwrap();
+        let options =
+            AnvilOptions::new().region_naming(PatternNaming::new("region_{x}_{z}.dat").unwrap());
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        chunk_provider
+            .save_chunk(-1, 40, CompoundTag::new())
+            .unwrap();
+
+        assert!(folder.path().join("region_-1_1.dat").exists());
+        assert_eq!(chunk_provider.region_file_name(-1, 1), "region_-1_1.dat");
+        assert_eq!(chunk_provider.list_chunks().unwrap(), vec![(-1, 40)]);
+        assert!(chunk_provider.load_chunk(-1, 40).is_ok());
+
+        chunk_provider.delete_chunk(-1, 40).unwrap();
+        assert!(chunk_provider.try_load_chunk(-1, 40).unwrap().is_none());
+
+        // Vanilla names are not regions of this provider.
+        std::fs::copy("test/region/r.0.0.mca", folder.path().join("r.0.0.mca")).unwrap();
+        assert!(chunk_provider.list_chunks().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{
+    AnvilRegion, ChunkLoadError, ChunkPayload, Compression, RawChunk, WorldEditError,
+    DEFAULT_COMPRESSION_LEVEL,
+};
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Name of the manifest written by `unpack_region`.
+pub const PACK_MANIFEST: &str = "manifest.txt";
+
+/// Uncompressed chunk data, kept as bytes so chunks are unpacked exactly as
+/// they are stored.
+struct UncompressedChunk(Vec<u8>);
+
+impl ChunkPayload for UncompressedChunk {
+    fn read_payload<R: Read>(reader: &mut R) -> Result<Self, ChunkLoadError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        Ok(UncompressedChunk(data))
+    }
+
+    fn write_payload<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_all(&self.0)
+    }
+}
+
+fn chunk_file_name(chunk_x: u8, chunk_z: u8) -> String {
+    format!("c.{}.{}.nbt", chunk_x, chunk_z)
+}
+
+/// Writes every chunk of the region file as an uncompressed NBT file named
+/// `c.<x>.<z>.nbt` in the given folder, using the coordinates of the chunk
+/// inside the region, and returns the amount of unpacked chunks.
+///
+/// The folder also gets a `manifest.txt` with one line per chunk, sorted
+/// by coordinates: `<x> <z> <timestamp> <compression scheme>`, so that
+/// `pack_region` rebuilds the region with the same timestamps and
+/// compression. Chunks with a compression scheme this crate can't
+/// decompress are written as stored.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{pack_region, unpack_region};
+///
+/// # let folder = tempfile::tempdir().unwrap();
+/// # let folder = folder.path();
+/// let chunks = unpack_region("test/region/r.0.0.mca", folder.join("r.0.0")).unwrap();
+/// assert_eq!(chunks, 277);
+///
+/// pack_region(folder.join("r.0.0"), folder.join("r.0.0.mca")).unwrap();
+/// ```
+pub fn unpack_region<R, F>(region_path: R, folder: F) -> Result<usize, WorldEditError>
+where
+    R: AsRef<Path>,
+    F: AsRef<Path>,
+{
+    let folder = folder.as_ref();
+    let mut region = AnvilRegion::file(region_path)?;
+    let mut manifest = String::new();
+    let mut unpacked = 0;
+
+    fs::create_dir_all(folder)?;
+
+    for chunk_z in 0..32 {
+        for chunk_x in 0..32 {
+            let metadata = region.get_metadata(chunk_x, chunk_z);
+
+            if metadata.is_empty() {
+                continue;
+            }
+
+            let raw_chunk = region.read_chunk_raw(chunk_x, chunk_z)?;
+            let data = match raw_chunk.decode_payload::<UncompressedChunk>() {
+                Ok(UncompressedChunk(data)) => data,
+                Err(ChunkLoadError::UnsupportedCompressionScheme { .. }) => {
+                    raw_chunk.data().to_vec()
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            fs::write(folder.join(chunk_file_name(chunk_x, chunk_z)), data)?;
+            manifest.push_str(&format!(
+                "{} {} {} {}\n",
+                chunk_x,
+                chunk_z,
+                metadata.last_modified_timestamp(),
+                raw_chunk.compression_scheme()
+            ));
+            unpacked += 1;
+        }
+    }
+
+    fs::write(folder.join(PACK_MANIFEST), manifest)?;
+
+    Ok(unpacked)
+}
+
+/// Rebuilds a region file from a folder written by `unpack_region`,
+/// replacing the region file if it exists. Returns the amount of packed
+/// chunks.
+///
+/// Only the chunks listed in the manifest are packed.
+pub fn pack_region<F, R>(folder: F, region_path: R) -> Result<usize, WorldEditError>
+where
+    F: AsRef<Path>,
+    R: AsRef<Path>,
+{
+    let folder = folder.as_ref();
+    let manifest = fs::read_to_string(folder.join(PACK_MANIFEST))?;
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(region_path)?;
+    let mut region = AnvilRegion::new(file)?;
+    let mut packed = 0;
+
+    for (line_index, line) in manifest.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (chunk_x, chunk_z, timestamp, compression_scheme) = parse_manifest_line(line)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid manifest line {}", line_index + 1),
+                )
+            })?;
+
+        let data = fs::read(folder.join(chunk_file_name(chunk_x, chunk_z)))?;
+        let raw_chunk = match Compression::from_id(compression_scheme) {
+            Some(compression) => RawChunk::encode(
+                &UncompressedChunk(data),
+                compression,
+                DEFAULT_COMPRESSION_LEVEL,
+            )?,
+            None => RawChunk::new(compression_scheme, data),
+        };
+
+        region.write_chunk_raw(chunk_x, chunk_z, &raw_chunk, timestamp)?;
+        packed += 1;
+    }
+
+    Ok(packed)
+}
+
+/// Parses `<x> <z> <timestamp> <compression scheme>`.
+fn parse_manifest_line(line: &str) -> Option<(u8, u8, u32, u8)> {
+    let mut fields = line.split_whitespace();
+    let chunk_x: u8 = fields.next()?.parse().ok()?;
+    let chunk_z: u8 = fields.next()?.parse().ok()?;
+    let timestamp = fields.next()?.parse().ok()?;
+    let compression_scheme = fields.next()?.parse().ok()?;
+
+    if chunk_x >= 32 || chunk_z >= 32 || fields.next().is_some() {
+        return None;
+    }
+
+    Some((chunk_x, chunk_z, timestamp, compression_scheme))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compare_regions, PayloadComparison};
+
+    #[test]
+    fn test_unpack_and_pack_region() {
+        let folder = tempfile::tempdir().unwrap();
+        let unpacked_folder = folder.path().join("r.0.0");
+        let region_path = folder.path().join("r.0.0.mca");
+
+        assert_eq!(
+            unpack_region("test/region/r.0.0.mca", &unpacked_folder).unwrap(),
+            277
+        );
+        let manifest = fs::read_to_string(unpacked_folder.join(PACK_MANIFEST)).unwrap();
+        assert_eq!(manifest.lines().count(), 277);
+        let chunk = fs::read(unpacked_folder.join("c.4.2.nbt")).unwrap();
+        // Uncompressed NBT starting with the root compound tag.
+        assert_eq!(chunk[0], 10);
+
+        assert_eq!(pack_region(&unpacked_folder, &region_path).unwrap(), 277);
+
+        let mut original = AnvilRegion::file("test/region/r.0.0.mca").unwrap();
+        let mut packed = AnvilRegion::file(&region_path).unwrap();
+        let diff = compare_regions(&mut original, &mut packed, PayloadComparison::Nbt).unwrap();
+        assert!(diff.is_empty(), "{:?}", diff);
+
+        fs::write(unpacked_folder.join(PACK_MANIFEST), "4 2 0\n").unwrap();
+        assert!(pack_region(&unpacked_folder, &region_path).is_err());
+    }
+}
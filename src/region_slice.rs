@@ -0,0 +1,195 @@
+use crate::{
+    anvil_region, AnvilChunkMetadata, AnvilRegion, ChunkLoadError, ChunkPayload, RawChunk,
+    REGION_HEADER_BYTES_LENGTH, REGION_SECTOR_BYTES_LENGTH,
+};
+use byteorder::{BigEndian, ByteOrder};
+use nbt::CompoundTag;
+use std::io;
+
+/// Region file bytes borrowed by a region opened with
+/// `AnvilRegion::from_slice`.
+#[derive(Copy, Clone, Debug)]
+pub struct RegionSlice<'a> {
+    bytes: &'a [u8],
+}
+
+/// Chunk data borrowed from the bytes of a region file, see `RawChunk`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RawChunkRef<'a> {
+    compression_scheme: u8,
+    data: &'a [u8],
+}
+
+impl<'a> RawChunkRef<'a> {
+    /// Compression scheme type id of the data.
+    pub fn compression_scheme(&self) -> u8 {
+        self.compression_scheme
+    }
+
+    /// Compressed chunk data.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Decompresses and decodes the chunk.
+    pub fn decode(&self) -> Result<CompoundTag, ChunkLoadError> {
+        self.decode_payload()
+    }
+
+    /// Decompresses and decodes the chunk as `P`.
+    pub fn decode_payload<P: ChunkPayload>(&self) -> Result<P, ChunkLoadError> {
+        P::decode(self.compression_scheme, self.data)
+    }
+
+    /// Copies the data into an owned `RawChunk`.
+    pub fn to_raw_chunk(&self) -> RawChunk {
+        RawChunk::new(self.compression_scheme, self.data.to_vec())
+    }
+}
+
+impl<'a> AnvilRegion<RegionSlice<'a>> {
+    /// Opens the region stored in the bytes for reading only, for example a
+    /// memory mapped file or a file embedded in the program.
+    ///
+    /// Chunks are read without copying their data, only decoding them
+    /// allocates.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::AnvilRegion;
+    ///
+    /// let bytes = std::fs::read("test/region/r.0.0.mca").unwrap();
+    /// let region = AnvilRegion::from_slice(&bytes).unwrap();
+    ///
+    /// let raw_chunk = region.read_chunk_raw(4, 2).unwrap();
+    /// // Zlib.
+    /// assert_eq!(raw_chunk.compression_scheme(), 2);
+    /// assert!(raw_chunk.decode().is_ok());
+    /// ```
+    pub fn from_slice(bytes: &'a [u8]) -> Result<Self, io::Error> {
+        if (bytes.len() as u64) < REGION_HEADER_BYTES_LENGTH {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let chunks_metadata = anvil_region::read_header(&mut &bytes[..])?;
+
+        Ok(AnvilRegion::with_header(
+            RegionSlice { bytes },
+            chunks_metadata,
+        ))
+    }
+
+    /// Bytes the region was opened from.
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.file.bytes
+    }
+
+    /// Returns chunk metadata at specified coordinates.
+    pub fn get_metadata(&self, chunk_x: u8, chunk_z: u8) -> AnvilChunkMetadata {
+        self.chunks_metadata[anvil_region::metadata_index(chunk_x, chunk_z)]
+    }
+
+    /// Returns the chunk data as a sub-slice of the region bytes, without
+    /// decompressing it.
+    pub fn read_chunk_raw(
+        &self,
+        chunk_x: u8,
+        chunk_z: u8,
+    ) -> Result<RawChunkRef<'a>, ChunkLoadError> {
+        let metadata = self.get_metadata(chunk_x, chunk_z);
+
+        if metadata.is_empty() {
+            return Err(ChunkLoadError::ChunkNotFound { chunk_x, chunk_z });
+        }
+
+        let bytes = self.file.bytes;
+        let start = metadata.sector_index as usize * REGION_SECTOR_BYTES_LENGTH as usize;
+        // Length doesn't count its own 4 bytes.
+        let maximum_length = metadata.sectors as u32 * REGION_SECTOR_BYTES_LENGTH as u32 - 4;
+
+        let chunk_header = bytes
+            .get(start..start + 5)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        let length = BigEndian::read_u32(&chunk_header[..4]);
+
+        if length > maximum_length {
+            return Err(ChunkLoadError::LengthExceedsMaximum {
+                length,
+                maximum_length,
+            });
+        }
+
+        if length == 0 {
+            return Err(ChunkLoadError::EmptyChunkData);
+        }
+
+        let data = bytes
+            .get(start + 5..start + 4 + length as usize)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+
+        Ok(RawChunkRef {
+            compression_scheme: chunk_header[4],
+            data,
+        })
+    }
+
+    pub fn read_chunk(&self, chunk_x: u8, chunk_z: u8) -> Result<CompoundTag, ChunkLoadError> {
+        self.read_chunk_raw(chunk_x, chunk_z)?.decode()
+    }
+
+    /// Reads the chunk decoded as `P`.
+    pub fn read_chunk_payload<P: ChunkPayload>(
+        &self,
+        chunk_x: u8,
+        chunk_z: u8,
+    ) -> Result<P, ChunkLoadError> {
+        self.read_chunk_raw(chunk_x, chunk_z)?.decode_payload()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AnvilRegionHeader;
+    use std::fs;
+
+    #[test]
+    fn test_region_from_slice() {
+        let bytes = fs::read("test/region/r.0.0.mca").unwrap();
+        let slice_region = AnvilRegion::from_slice(&bytes).unwrap();
+        let mut file_region = AnvilRegion::file("test/region/r.0.0.mca").unwrap();
+        let header = AnvilRegionHeader::read("test/region/r.0.0.mca").unwrap();
+
+        for ((chunk_x, chunk_z), metadata) in header.chunks() {
+            assert_eq!(slice_region.get_metadata(chunk_x, chunk_z), metadata);
+
+            let raw_chunk = slice_region.read_chunk_raw(chunk_x, chunk_z).unwrap();
+            assert_eq!(
+                raw_chunk.to_raw_chunk(),
+                file_region.read_chunk_raw(chunk_x, chunk_z).unwrap()
+            );
+            // Borrowed from the bytes.
+            let offset = raw_chunk.data().as_ptr() as usize - bytes.as_ptr() as usize;
+            assert_eq!(offset, metadata.sector_index() as usize * 4096 + 5);
+        }
+
+        assert!(matches!(
+            slice_region.read_chunk(15, 14),
+            Err(ChunkLoadError::ChunkNotFound {
+                chunk_x: 15,
+                chunk_z: 14
+            })
+        ));
+
+        // Truncated in the middle of a chunk.
+        let metadata = slice_region.get_metadata(4, 2);
+        let truncated = &bytes[..metadata.sector_index() as usize * 4096 + 100];
+        let slice_region = AnvilRegion::from_slice(truncated).unwrap();
+        assert!(matches!(
+            slice_region.read_chunk_raw(4, 2),
+            Err(ChunkLoadError::ReadError { .. })
+        ));
+        assert!(AnvilRegion::from_slice(&bytes[..100]).is_err());
+    }
+}
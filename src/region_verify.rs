@@ -0,0 +1,215 @@
+use crate::{
+    anvil_region, AnvilChunkMetadata, AnvilRegion, SetLen, REGION_HEADER_BYTES_LENGTH,
+    REGION_SECTOR_BYTES_LENGTH,
+};
+use std::io;
+use std::io::{Read, Seek, Write};
+
+/// Problem in the layout of a region file, found by `AnvilRegion::verify`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RegionIssue {
+    /// Bytes after the last sector used by a chunk, left by tools that
+    /// don't shrink region files. Free sectors reserved with
+    /// `AnvilRegion::reserve_sectors` are reported too.
+    TrailingGarbage {
+        /// End of the last used sector.
+        used_length: u64,
+        file_length: u64,
+    },
+    /// The file length is not a multiple of the sector size.
+    UnalignedLength { file_length: u64 },
+    /// The sectors of the chunk start or end past the last sector of the
+    /// file, so the chunk can't be read.
+    ChunkPastEnd { chunk_x: u8, chunk_z: u8 },
+}
+
+impl<F: Seek + Read + Write> AnvilRegion<F> {
+    /// Checks the layout of the region file.
+    ///
+    /// Only the header and the file length are inspected, chunk data is not
+    /// read.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::{AnvilRegion, RegionIssue};
+    /// use std::io::Cursor;
+    ///
+    /// let mut region = AnvilRegion::new(Cursor::new(Vec::new())).unwrap();
+    /// region.reserve_sectors(2).unwrap();
+    ///
+    /// assert_eq!(
+    ///     region.verify().unwrap(),
+    ///     vec![RegionIssue::TrailingGarbage { used_length: 8192, file_length: 16384 }]
+    /// );
+    /// assert_eq!(region.truncate_garbage().unwrap(), 8192);
+    /// assert!(region.verify().unwrap().is_empty());
+    /// ```
+    pub fn verify(&mut self) -> Result<Vec<RegionIssue>, io::Error> {
+        let file_length = self.stream_len()?;
+        let sector_length = REGION_SECTOR_BYTES_LENGTH as u64;
+        let file_sectors = file_length.div_ceil(sector_length);
+        let mut issues = Vec::new();
+
+        for (index, metadata) in self.chunks_metadata.iter().enumerate() {
+            let sector_end = metadata.sector_index as u64 + metadata.sectors as u64;
+
+            if !metadata.is_empty() && sector_end > file_sectors {
+                issues.push(RegionIssue::ChunkPastEnd {
+                    chunk_x: (index % 32) as u8,
+                    chunk_z: (index / 32) as u8,
+                });
+            }
+        }
+
+        if file_length % sector_length != 0 {
+            issues.push(RegionIssue::UnalignedLength { file_length });
+        }
+
+        let used_length = self.used_length(file_length);
+
+        if file_length > used_length {
+            issues.push(RegionIssue::TrailingGarbage {
+                used_length,
+                file_length,
+            });
+        }
+
+        Ok(issues)
+    }
+
+    /// End of the last sector used by a chunk that fits in the file, or of
+    /// the header if no chunk is stored.
+    fn used_length(&self, file_length: u64) -> u64 {
+        let sector_length = REGION_SECTOR_BYTES_LENGTH as u64;
+        let file_sectors = file_length.div_ceil(sector_length);
+
+        let used_sectors = self
+            .chunks_metadata
+            .iter()
+            .filter(|metadata| !metadata.is_empty())
+            .map(|metadata| metadata.sector_index as u64 + metadata.sectors as u64)
+            .filter(|&sector_end| sector_end <= file_sectors)
+            .max()
+            .unwrap_or(0);
+
+        (used_sectors * sector_length).max(REGION_HEADER_BYTES_LENGTH)
+    }
+
+    /// Empties the header entries of the chunks extending past the end of
+    /// the file, which can't be read, and returns their coordinates inside
+    /// the region.
+    pub fn drop_chunks_past_end(&mut self) -> Result<Vec<(u8, u8)>, io::Error> {
+        let mut dropped = Vec::new();
+
+        for issue in self.verify()? {
+            if let RegionIssue::ChunkPastEnd { chunk_x, chunk_z } = issue {
+                self.update_metadata(chunk_x, chunk_z, AnvilChunkMetadata::default())?;
+                dropped.push((chunk_x, chunk_z));
+            }
+        }
+
+        if !dropped.is_empty() {
+            let total_sectors = self.sector_count()?;
+            self.used_sectors = anvil_region::used_sectors(total_sectors, &self.chunks_metadata);
+        }
+
+        Ok(dropped)
+    }
+}
+
+impl<F: Seek + Read + Write + SetLen> AnvilRegion<F> {
+    /// Removes everything after the last sector used by a chunk, returning
+    /// the new file length.
+    ///
+    /// Unaligned files are padded with zeroes up to the sector boundary when
+    /// the last sector is used. Chunks extending past the end of the file are
+    /// ignored, the file is not extended to fit them.
+    pub fn truncate_garbage(&mut self) -> Result<u64, io::Error> {
+        // Sectors the header in the file still points at are not garbage.
+        self.write_pending_header()?;
+
+        let file_length = self.stream_len()?;
+        let used_length = self.used_length(file_length);
+
+        if used_length != file_length {
+            self.file.set_len(used_length)?;
+
+            let total_sectors = (used_length / REGION_SECTOR_BYTES_LENGTH as u64) as u32;
+            self.used_sectors = anvil_region::used_sectors(total_sectors, &self.chunks_metadata);
+        }
+
+        Ok(used_length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nbt::CompoundTag;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_verify_and_truncate_garbage() {
+        let mut region = AnvilRegion::new(Cursor::new(Vec::new())).unwrap();
+        region.write_chunk(0, 0, CompoundTag::new()).unwrap();
+        region.write_chunk(1, 0, CompoundTag::new()).unwrap();
+        assert!(region.verify().unwrap().is_empty());
+
+        let mut bytes = region.into_inner().into_inner();
+        bytes.extend_from_slice(&[0xAB; 5000]);
+        let mut region = AnvilRegion::new(Cursor::new(bytes)).unwrap();
+
+        assert_eq!(
+            region.verify().unwrap(),
+            vec![
+                RegionIssue::UnalignedLength { file_length: 21384 },
+                RegionIssue::TrailingGarbage {
+                    used_length: 16384,
+                    file_length: 21384,
+                },
+            ]
+        );
+
+        assert_eq!(region.truncate_garbage().unwrap(), 16384);
+        assert!(region.verify().unwrap().is_empty());
+        assert!(region.read_chunk(1, 0).is_ok());
+
+        // The freed sectors are reused.
+        region.write_chunk(2, 0, CompoundTag::new()).unwrap();
+        assert_eq!(region.sector_count().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_verify_chunk_past_end() {
+        let mut region = AnvilRegion::new(Cursor::new(Vec::new())).unwrap();
+        region.write_chunk(3, 1, CompoundTag::new()).unwrap();
+
+        let bytes = region.into_inner().into_inner();
+
+        // The last sector is cut, but still holds the chunk data.
+        let mut region = AnvilRegion::new(Cursor::new(bytes[..10000].to_vec())).unwrap();
+        assert_eq!(
+            region.verify().unwrap(),
+            vec![RegionIssue::UnalignedLength { file_length: 10000 }]
+        );
+        assert_eq!(region.truncate_garbage().unwrap(), 12288);
+        assert!(region.read_chunk(3, 1).is_ok());
+
+        let mut region = AnvilRegion::new(Cursor::new(bytes[..8192].to_vec())).unwrap();
+        assert_eq!(
+            region.verify().unwrap(),
+            vec![RegionIssue::ChunkPastEnd {
+                chunk_x: 3,
+                chunk_z: 1
+            }]
+        );
+
+        // The chunk doesn't fit, the file is not extended.
+        assert_eq!(region.truncate_garbage().unwrap(), 8192);
+
+        assert_eq!(region.drop_chunks_past_end().unwrap(), vec![(3, 1)]);
+        assert!(region.verify().unwrap().is_empty());
+        assert!(region.get_metadata(3, 1).is_empty());
+    }
+}
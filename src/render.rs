@@ -0,0 +1,253 @@
+use crate::chunk_surface::{block_name, surface_heights};
+use crate::{ChunkLoadError, ChunkReader, ChunkSelection};
+use nbt::CompoundTag;
+
+/// Area covered by every pixel of a rendered map.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum RenderScale {
+    /// One pixel per block column.
+    #[default]
+    Block,
+    /// One pixel per chunk, the average color of its columns.
+    Chunk,
+}
+
+/// Image with 4 bytes per pixel (red, green, blue, alpha), stored row by
+/// row from north to south.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RgbaImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Creates a fully transparent image.
+    pub fn new(width: u32, height: u32) -> Self {
+        RgbaImage {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Pixel data, 4 bytes per pixel.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn into_pixels(self) -> Vec<u8> {
+        self.pixels
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let index = self.pixel_index(x, y);
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.pixels[index..index + 4]);
+
+        pixel
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: [u8; 4]) {
+        let index = self.pixel_index(x, y);
+        self.pixels[index..index + 4].copy_from_slice(&pixel);
+    }
+
+    fn pixel_index(&self, x: u32, y: u32) -> usize {
+        assert!(x < self.width && y < self.height, "pixel out of bounds");
+
+        (y as usize * self.width as usize + x as usize) * 4
+    }
+}
+
+/// Renders the selected chunks seen from above.
+///
+/// The image covers the bounds of the selection, or the bounds of the
+/// selected chunks for unbounded selections, with the north-west corner at
+/// the top left. Missing chunks are left transparent. Columns are colored
+/// by their surface block, found using the chunk heightmaps, and shaded by
+/// the height difference with the column to the north.
+///
+/// Block colors come from a small built-in table, so most blocks get a
+/// generic color. Chunks before 1.13 don't store block names and are drawn
+/// in gray.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{render_map, ChunkSelection, FolderChunkProvider, RenderScale};
+///
+/// let mut chunk_provider = FolderChunkProvider::new("test/region");
+/// let selection = ChunkSelection::rect((0, 0), (31, 31));
+/// let image = render_map(&mut chunk_provider, &selection, RenderScale::Block).unwrap();
+///
+/// assert_eq!((image.width(), image.height()), (512, 512));
+/// ```
+pub fn render_map<P: ChunkReader + ?Sized>(
+    provider: &mut P,
+    selection: &ChunkSelection,
+    scale: RenderScale,
+) -> Result<RgbaImage, ChunkLoadError> {
+    let chunks = provider.list_chunks_in(selection)?;
+
+    let bounds = selection.bounds_or_chunk_bounds(&chunks);
+
+    let ((min_x, min_z), (max_x, max_z)) = match bounds {
+        Some(bounds) => bounds,
+        None => return Ok(RgbaImage::new(0, 0)),
+    };
+
+    let pixels_per_chunk = match scale {
+        RenderScale::Block => 16,
+        RenderScale::Chunk => 1,
+    };
+    let width = (max_x - min_x + 1) as u32 * pixels_per_chunk;
+    let height = (max_z - min_z + 1) as u32 * pixels_per_chunk;
+    let mut image = RgbaImage::new(width, height);
+
+    for (chunk_x, chunk_z) in chunks {
+        let chunk_compound_tag = provider.load_chunk(chunk_x, chunk_z)?;
+        let colors = chunk_colors(&chunk_compound_tag);
+        let image_x = (chunk_x - min_x) as u32 * pixels_per_chunk;
+        let image_z = (chunk_z - min_z) as u32 * pixels_per_chunk;
+
+        match scale {
+            RenderScale::Block => {
+                for (index, &color) in colors.iter().enumerate() {
+                    let x = image_x + (index % 16) as u32;
+                    let z = image_z + (index / 16) as u32;
+                    image.set_pixel(x, z, color);
+                }
+            }
+            RenderScale::Chunk => image.set_pixel(image_x, image_z, average_color(&colors)),
+        }
+    }
+
+    Ok(image)
+}
+
+/// Shaded color of every column of the chunk, indexed by `z * 16 + x`.
+fn chunk_colors(chunk_compound_tag: &CompoundTag) -> [[u8; 4]; 256] {
+    let mut colors = [[0; 4]; 256];
+
+    let heights = match surface_heights(chunk_compound_tag) {
+        Some(heights) => heights,
+        None => return colors,
+    };
+
+    for (index, color) in colors.iter_mut().enumerate() {
+        let (x, z) = (index % 16, index / 16);
+        let height = heights[index];
+
+        let [red, green, blue] = match block_name(chunk_compound_tag, x, height, z) {
+            Some(name) => block_color(name.trim_start_matches("minecraft:")),
+            None => DEFAULT_COLOR,
+        };
+
+        let north_height = if z > 0 { heights[index - 16] } else { height };
+        let shade = 1.0 + ((height - north_height) as f32 * 0.08).clamp(-0.3, 0.3);
+        let shaded = |channel: u8| (channel as f32 * shade).min(255.0) as u8;
+
+        *color = [shaded(red), shaded(green), shaded(blue), 255];
+    }
+
+    colors
+}
+
+fn average_color(colors: &[[u8; 4]]) -> [u8; 4] {
+    let opaque: Vec<_> = colors.iter().filter(|color| color[3] != 0).collect();
+
+    if opaque.is_empty() {
+        return [0; 4];
+    }
+
+    let mut sum = [0usize; 3];
+
+    for color in &opaque {
+        for channel in 0..3 {
+            sum[channel] += color[channel] as usize;
+        }
+    }
+
+    let average = |channel: usize| (sum[channel] / opaque.len()) as u8;
+
+    [average(0), average(1), average(2), 255]
+}
+
+/// Color of blocks missing from the table.
+const DEFAULT_COLOR: [u8; 3] = [128, 128, 128];
+
+/// Approximate top color of a block, given its name without namespace.
+fn block_color(name: &str) -> [u8; 3] {
+    match name {
+        "grass_block" | "grass" | "tall_grass" => [106, 170, 64],
+        "water" | "bubble_column" | "kelp" | "kelp_plant" | "seagrass" | "tall_seagrass" => {
+            [56, 92, 220]
+        }
+        "lava" => [210, 90, 20],
+        "sand" | "sandstone" => [218, 210, 158],
+        "red_sand" | "red_sandstone" => [190, 102, 33],
+        "gravel" => [136, 126, 126],
+        "dirt" | "coarse_dirt" | "farmland" | "dirt_path" | "grass_path" => [134, 96, 67],
+        "podzol" => [91, 63, 24],
+        "mycelium" => [111, 99, 105],
+        "snow" | "snow_block" | "powder_snow" => [250, 250, 250],
+        "ice" | "packed_ice" | "frosted_ice" => [160, 188, 255],
+        "blue_ice" => [116, 167, 253],
+        "stone" | "cobblestone" | "andesite" | "mossy_cobblestone" => [125, 125, 125],
+        "granite" => [149, 103, 85],
+        "diorite" => [188, 188, 188],
+        "deepslate" => [80, 80, 82],
+        "clay" => [160, 166, 179],
+        "terracotta" => [152, 94, 67],
+        "netherrack" => [97, 38, 38],
+        "end_stone" => [219, 222, 158],
+        "bedrock" => [85, 85, 85],
+        "obsidian" => [20, 18, 29],
+        "cactus" => [85, 127, 43],
+        "pumpkin" => [198, 118, 24],
+        "melon" => [111, 145, 30],
+        "lily_pad" => [32, 128, 48],
+        _ if name.ends_with("_leaves") => [60, 120, 40],
+        _ if name.ends_with("_log") || name.ends_with("_wood") => [102, 81, 51],
+        _ if name.ends_with("_planks") => [162, 130, 78],
+        _ if name.ends_with("_flower") || name.ends_with("_tulip") => [180, 60, 60],
+        _ => DEFAULT_COLOR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FolderChunkProvider;
+
+    #[test]
+    fn test_render_map_chunk_scale() {
+        let mut chunk_provider = FolderChunkProvider::new("test/region");
+        let selection = ChunkSelection::rect((0, 0), (31, 31));
+        let image = render_map(&mut chunk_provider, &selection, RenderScale::Chunk).unwrap();
+
+        assert_eq!((image.width(), image.height()), (32, 32));
+        assert_eq!(image.get_pixel(4, 2)[3], 255);
+        // Chunk 15, 14 does not exist.
+        assert_eq!(image.get_pixel(15, 14), [0; 4]);
+    }
+
+    #[test]
+    fn test_render_map_unbounded_selection() {
+        let mut chunk_provider = FolderChunkProvider::new("test/region");
+        let selection = ChunkSelection::predicate(|chunk_x, chunk_z| chunk_x == 4 && chunk_z == 2);
+        let image = render_map(&mut chunk_provider, &selection, RenderScale::Block).unwrap();
+
+        assert_eq!((image.width(), image.height()), (16, 16));
+        assert!(image.pixels().chunks(4).all(|pixel| pixel[3] == 255));
+    }
+}
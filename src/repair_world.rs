@@ -0,0 +1,385 @@
+use crate::{
+    find_stale_poi, remove_stale_poi, AnvilRegion, ChunkSaveError, ChunkSelection,
+    FolderChunkProvider, RegionIssue, StalePoi, TornChunk, WorldEditError,
+};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+/// Settings of `repair_world`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RepairOptions {
+    drop_unrecoverable: bool,
+    recover_orphans: bool,
+    keep_garbage: bool,
+    poi_folder: Option<PathBuf>,
+}
+
+impl RepairOptions {
+    /// Default options: torn chunks are restored when possible, garbage is
+    /// truncated, orphan chunks and points of interest are left alone.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deletes the torn chunks without a previous version to restore, so
+    /// that the game generates them again. They are only reported by
+    /// default.
+    pub fn drop_unrecoverable(mut self, drop_unrecoverable: bool) -> Self {
+        self.drop_unrecoverable = drop_unrecoverable;
+        self
+    }
+
+    /// Adds the chunks found in the free sectors to the empty header
+    /// entries, see `AnvilRegion::recover_orphan_chunks`. Deleted chunks
+    /// may come back.
+    pub fn recover_orphans(mut self, recover_orphans: bool) -> Self {
+        self.recover_orphans = recover_orphans;
+        self
+    }
+
+    /// Leaves the bytes after the last used sector of the region files.
+    pub fn keep_garbage(mut self, keep_garbage: bool) -> Self {
+        self.keep_garbage = keep_garbage;
+        self
+    }
+
+    /// Checks the point of interest records of the given `poi` folder
+    /// against the repaired chunks, removing the stale ones, see
+    /// `find_stale_poi`.
+    pub fn poi_folder<F: Into<PathBuf>>(mut self, poi_folder: F) -> Self {
+        self.poi_folder = Some(poi_folder.into());
+        self
+    }
+}
+
+/// Change made to a region file by `repair_world`, with chunk coordinates
+/// inside the region.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RepairFix {
+    /// The header entry of a chunk extending past the end of the file was
+    /// emptied.
+    DroppedChunkPastEnd { chunk_x: u8, chunk_z: u8 },
+    /// A torn chunk was replaced by its previous version.
+    RestoredPreviousChunk { chunk_x: u8, chunk_z: u8 },
+    /// A torn chunk without a previous version was deleted.
+    DroppedTornChunk { chunk_x: u8, chunk_z: u8 },
+    /// A chunk found in the free sectors was added to the header.
+    RecoveredOrphanChunk { chunk_x: u8, chunk_z: u8 },
+    /// The bytes after the last used sector were removed.
+    TruncatedGarbage { file_length: u64, used_length: u64 },
+}
+
+impl fmt::Display for RepairFix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RepairFix::DroppedChunkPastEnd { chunk_x, chunk_z } => {
+                write!(f, "dropped_chunk_past_end {} {}", chunk_x, chunk_z)
+            }
+            RepairFix::RestoredPreviousChunk { chunk_x, chunk_z } => {
+                write!(f, "restored_previous_chunk {} {}", chunk_x, chunk_z)
+            }
+            RepairFix::DroppedTornChunk { chunk_x, chunk_z } => {
+                write!(f, "dropped_torn_chunk {} {}", chunk_x, chunk_z)
+            }
+            RepairFix::RecoveredOrphanChunk { chunk_x, chunk_z } => {
+                write!(f, "recovered_orphan_chunk {} {}", chunk_x, chunk_z)
+            }
+            RepairFix::TruncatedGarbage {
+                file_length,
+                used_length,
+            } => write!(f, "truncated_garbage {} {}", file_length, used_length),
+        }
+    }
+}
+
+/// Problems found in a region file by `repair_world` and what was done
+/// about them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RegionRepair {
+    pub region_x: i32,
+    pub region_z: i32,
+    /// Layout problems found before the repair, see `AnvilRegion::verify`.
+    pub issues: Vec<RegionIssue>,
+    /// Torn chunks found before the repair, see
+    /// `AnvilRegion::find_torn_chunks`.
+    pub torn_chunks: Vec<TornChunk>,
+    pub fixes: Vec<RepairFix>,
+}
+
+/// Result of `repair_world`.
+///
+/// Its `Display` implementation writes one line per fix, which is easy to
+/// parse: `region <region x> <region z> <fix> <values>` with the names of
+/// the `RepairFix` variants in snake case followed by their fields, and
+/// `poi <x> <y> <z> <type>` for every stale point of interest.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RepairReport {
+    /// Amount of region files checked.
+    pub regions_checked: usize,
+    /// Regions with problems, sorted by coordinates.
+    pub regions: Vec<RegionRepair>,
+    /// Stale point of interest records, only checked with
+    /// `RepairOptions::poi_folder`.
+    pub stale_poi: Vec<StalePoi>,
+    /// Amount of removed point of interest records.
+    pub removed_poi: usize,
+}
+
+impl RepairReport {
+    /// Amount of changes made to the region files.
+    pub fn fixes(&self) -> usize {
+        self.regions.iter().map(|region| region.fixes.len()).sum()
+    }
+}
+
+impl fmt::Display for RepairReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for region in &self.regions {
+            for fix in &region.fixes {
+                writeln!(f, "region {} {} {}", region.region_x, region.region_z, fix)?;
+            }
+        }
+
+        for stale_poi in &self.stale_poi {
+            let [x, y, z] = stale_poi.position;
+            writeln!(f, "poi {} {} {} {}", x, y, z, stale_poi.poi_type)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks and repairs every region file of the provider, then the point of
+/// interest records if a `poi` folder is given.
+///
+/// For every region, in this order: the header entries of the chunks past
+/// the end of the file are emptied, torn chunks are restored from their
+/// previous version or optionally dropped, orphan chunks are optionally
+/// recovered and the garbage at the end of the file is truncated. A
+/// journal left by a crash is replayed first. Repaired regions are written
+/// to a temporary file which replaces the region file, so a crash keeps the
+/// old or the new file. Regions only stored compressed are skipped.
+///
+/// With `AnvilOptions::dry_run` no file is touched and the report tells
+/// what would be done.
+///
+/// # Example
+///
+/// ```no_run
+/// use anvil_region::{repair_world, FolderChunkProvider, RepairOptions};
+///
+/// let mut chunk_provider = FolderChunkProvider::new("world/region");
+/// let options = RepairOptions::new().poi_folder("world/poi");
+/// let report = repair_world(&mut chunk_provider, options).unwrap();
+///
+/// print!("{}", report);
+/// ```
+pub fn repair_world(
+    provider: &mut FolderChunkProvider,
+    options: RepairOptions,
+) -> Result<RepairReport, WorldEditError> {
+    if provider.options.read_only {
+        return Err(ChunkSaveError::ReadOnly.into());
+    }
+
+    let mut report = RepairReport::default();
+
+    if provider.folder_path.exists() {
+        let mut regions = provider.find_all_region_mca()?;
+        regions.sort();
+
+        for (region_x, region_z) in regions {
+            let region_repair = match provider.repair_region(region_x, region_z, &options)? {
+                Some(region_repair) => region_repair,
+                None => continue,
+            };
+            report.regions_checked += 1;
+
+            if !region_repair.issues.is_empty()
+                || !region_repair.torn_chunks.is_empty()
+                || !region_repair.fixes.is_empty()
+            {
+                report.regions.push(region_repair);
+            }
+        }
+    }
+
+    if let Some(poi_folder) = &options.poi_folder {
+        let mut poi_provider =
+            FolderChunkProvider::with_options(poi_folder, provider.options.clone());
+        report.stale_poi = find_stale_poi(&mut poi_provider, &mut *provider, &ChunkSelection::All)?;
+
+        if !provider.options.dry_run {
+            report.removed_poi = remove_stale_poi(&mut poi_provider, &report.stale_poi)?;
+        }
+    }
+
+    Ok(report)
+}
+
+impl FolderChunkProvider {
+    /// Repairs a copy of the region file in memory, replacing the file if
+    /// anything was fixed. Returns `None` for regions only stored
+    /// compressed.
+    fn repair_region(
+        &self,
+        region_x: i32,
+        region_z: i32,
+        options: &RepairOptions,
+    ) -> Result<Option<RegionRepair>, io::Error> {
+        let region_path = self.region_path(region_x, region_z);
+        let _lock = self.region_locks.lock((region_x, region_z));
+
+        if !region_path.exists() {
+            return Ok(None);
+        }
+
+        if self.journal_path(region_x, region_z).exists() {
+            self.with_locked_region(region_x, region_z, |_| Ok::<_, io::Error>(()))?;
+        }
+
+        // The region file is replaced, so it must not stay open.
+        self.region_cache
+            .lock()
+            .unwrap()
+            .remove((region_x, region_z))?;
+
+        let mut region = AnvilRegion::new(Cursor::new(fs::read(&region_path)?))?;
+        let issues = region.verify()?;
+        let mut fixes = Vec::new();
+
+        for (chunk_x, chunk_z) in region.drop_chunks_past_end()? {
+            fixes.push(RepairFix::DroppedChunkPastEnd { chunk_x, chunk_z });
+        }
+
+        let torn_chunks = region.find_torn_chunks()?;
+
+        for torn_chunk in &torn_chunks {
+            let (chunk_x, chunk_z) = (torn_chunk.chunk_x, torn_chunk.chunk_z);
+
+            if region.restore_previous_chunk(chunk_x, chunk_z)? {
+                fixes.push(RepairFix::RestoredPreviousChunk { chunk_x, chunk_z });
+            } else if options.drop_unrecoverable {
+                region.delete_chunk(chunk_x, chunk_z)?;
+                fixes.push(RepairFix::DroppedTornChunk { chunk_x, chunk_z });
+            }
+        }
+
+        if options.recover_orphans {
+            for (chunk_x, chunk_z) in region.recover_orphan_chunks()? {
+                fixes.push(RepairFix::RecoveredOrphanChunk { chunk_x, chunk_z });
+            }
+        }
+
+        if !options.keep_garbage {
+            let file_length = region.stream_len()?;
+            let used_length = region.truncate_garbage()?;
+
+            if used_length != file_length {
+                fixes.push(RepairFix::TruncatedGarbage {
+                    file_length,
+                    used_length,
+                });
+            }
+        }
+
+        if !fixes.is_empty() && !self.options.dry_run {
+            let mut temporary_path = region_path.clone().into_os_string();
+            temporary_path.push(".tmp");
+            fs::write(&temporary_path, region.into_inner().into_inner())?;
+            fs::File::open(&temporary_path)?.sync_all()?;
+            fs::rename(&temporary_path, &region_path)?;
+        }
+
+        Ok(Some(RegionRepair {
+            region_x,
+            region_z,
+            issues,
+            torn_chunks,
+            fixes,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilOptions, Compression};
+    use nbt::CompoundTag;
+
+    fn chunk(chunk_x: i32, chunk_z: i32, padding: usize) -> CompoundTag {
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("xPos", chunk_x);
+        chunk_compound_tag.insert_i32("zPos", chunk_z);
+        chunk_compound_tag.insert_i8_vec("Padding", vec![7; padding]);
+
+        chunk_compound_tag
+    }
+
+    #[test]
+    fn test_repair_world() {
+        let folder = tempfile::tempdir().unwrap();
+        let region_path = folder.path().join("r.0.0.mca");
+        let options = AnvilOptions::new().compression(Compression::Uncompressed);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        chunk_provider.save_chunk(1, 2, chunk(1, 2, 10)).unwrap();
+        chunk_provider.save_chunk(3, 0, chunk(3, 0, 10)).unwrap();
+        chunk_provider.save_chunk(4, 0, chunk(4, 0, 10)).unwrap();
+        chunk_provider.save_chunk(1, 2, chunk(1, 2, 6000)).unwrap();
+        chunk_provider.close_regions().unwrap();
+
+        // The new version of 1, 2 is torn, 4, 0 lost its header entry and
+        // the file ends with garbage.
+        let mut region = AnvilRegion::file(&region_path).unwrap();
+        let metadata = region.get_metadata(1, 2);
+        region
+            .write_sector(metadata.sector_index(), &[0; 4096])
+            .unwrap();
+        region.delete_chunk(4, 0).unwrap();
+        drop(region);
+        let mut bytes = fs::read(&region_path).unwrap();
+        bytes.extend_from_slice(&[0xAB; 100]);
+        fs::write(&region_path, &bytes).unwrap();
+
+        let repair_options = RepairOptions::new().recover_orphans(true);
+        let options = AnvilOptions::new().dry_run(true);
+        let mut dry_provider = FolderChunkProvider::with_options(folder.path(), options);
+        let dry_report = repair_world(&mut dry_provider, repair_options.clone()).unwrap();
+        assert_eq!(fs::read(&region_path).unwrap(), bytes);
+
+        let mut chunk_provider = FolderChunkProvider::new(folder.path());
+        let report = repair_world(&mut chunk_provider, repair_options).unwrap();
+        assert_eq!(report, dry_report);
+        assert_eq!(report.regions_checked, 1);
+        let region_repair = &report.regions[0];
+        assert_eq!(region_repair.torn_chunks.len(), 1);
+        assert_eq!(
+            region_repair.fixes[..2],
+            [
+                RepairFix::RestoredPreviousChunk {
+                    chunk_x: 1,
+                    chunk_z: 2
+                },
+                RepairFix::RecoveredOrphanChunk {
+                    chunk_x: 4,
+                    chunk_z: 0
+                },
+            ]
+        );
+        assert!(report
+            .to_string()
+            .starts_with("region 0 0 restored_previous_chunk 1 2\n"));
+
+        let chunk_compound_tag = chunk_provider.load_chunk(1, 2).unwrap();
+        assert_eq!(chunk_compound_tag.get_i8_vec("Padding").unwrap().len(), 10);
+        assert!(chunk_provider.load_chunk(4, 0).is_ok());
+        assert_eq!(fs::metadata(&region_path).unwrap().len() % 4096, 0);
+
+        let report = repair_world(&mut chunk_provider, RepairOptions::new()).unwrap();
+        assert_eq!(report.fixes(), 0);
+        assert!(report.regions.is_empty());
+    }
+}
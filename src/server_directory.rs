@@ -0,0 +1,193 @@
+use crate::{DimensionLayout, FolderChunkProvider, WorldLayout};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Suffixes of the world folders of the vanilla dimensions on Bukkit
+/// servers, such as Spigot or Paper.
+const BUKKIT_SUFFIXES: [(&str, &str); 2] = [
+    ("minecraft:the_nether", "_nether"),
+    ("minecraft:the_end", "_the_end"),
+];
+
+/// Dimension of a world found by `ServerDirectoryProvider::worlds`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ServerWorld {
+    /// Name of the world, the name of the world folder for the overworld,
+    /// with a `_nether` or `_the_end` suffix for the other vanilla
+    /// dimensions, and the dimension id for custom dimensions.
+    pub name: String,
+    /// Name of the world folder inside the server folder.
+    pub world_folder: String,
+    /// Folders of the dimension.
+    pub dimension: DimensionLayout,
+}
+
+impl ServerWorld {
+    /// Provider of the chunks of the world.
+    pub fn region_provider(&self) -> Option<FolderChunkProvider> {
+        self.dimension.region_provider()
+    }
+}
+
+/// Finds the worlds of a server folder, the folders with a `level.dat`
+/// file, and names them the same for vanilla and Bukkit servers.
+///
+/// Vanilla servers store every dimension in the `world` folder, Bukkit
+/// servers store the nether and the end in `world_nether/DIM-1` and
+/// `world_the_end/DIM1`. Both are named `world`, `world_nether` and
+/// `world_the_end`.
+///
+/// # Example
+///
+/// ```no_run
+/// use anvil_region::ServerDirectoryProvider;
+///
+/// let server_provider = ServerDirectoryProvider::new("server");
+///
+/// for world in server_provider.worlds().unwrap() {
+///     println!("{}: {}", world.name, world.dimension.path.display());
+/// }
+///
+/// let nether_provider = server_provider.provider("world_nether").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ServerDirectoryProvider {
+    server_path: PathBuf,
+}
+
+impl ServerDirectoryProvider {
+    pub fn new<F: Into<PathBuf>>(server_folder: F) -> Self {
+        ServerDirectoryProvider {
+            server_path: server_folder.into(),
+        }
+    }
+
+    pub fn server_path(&self) -> &Path {
+        &self.server_path
+    }
+
+    /// Dimensions with a region folder of every world, sorted by world
+    /// folder and then in the order of `WorldLayout::dimensions`.
+    ///
+    /// The unused overworld folders of the Bukkit nether and end worlds are
+    /// skipped.
+    pub fn worlds(&self) -> Result<Vec<ServerWorld>, io::Error> {
+        let mut world_folders = Vec::new();
+
+        for entry in fs::read_dir(&self.server_path)? {
+            let world_path = entry?.path();
+
+            if !world_path.join("level.dat").is_file() {
+                continue;
+            }
+
+            if let Some(world_folder) = world_path.file_name().and_then(|name| name.to_str()) {
+                world_folders.push(world_folder.to_string());
+            }
+        }
+
+        world_folders.sort();
+        let mut worlds = Vec::new();
+
+        for world_folder in world_folders {
+            let layout = WorldLayout::detect(self.server_path.join(&world_folder))?;
+            let bukkit_world = BUKKIT_SUFFIXES.iter().any(|(id, suffix)| {
+                world_folder.ends_with(suffix) && layout.dimension(id).is_some()
+            });
+
+            for dimension in layout.dimensions {
+                if dimension.region_path.is_none() {
+                    continue;
+                }
+
+                let name = match dimension.id.as_deref() {
+                    Some("minecraft:overworld") if bukkit_world => continue,
+                    Some("minecraft:overworld") | None => world_folder.clone(),
+                    Some(id) => match BUKKIT_SUFFIXES.iter().find(|(other, _)| *other == id) {
+                        Some((_, suffix)) if world_folder.ends_with(suffix) => world_folder.clone(),
+                        Some((_, suffix)) => format!("{}{}", world_folder, suffix),
+                        None => id.to_string(),
+                    },
+                };
+
+                worlds.push(ServerWorld {
+                    name,
+                    world_folder: world_folder.clone(),
+                    dimension,
+                });
+            }
+        }
+
+        Ok(worlds)
+    }
+
+    /// Provider of the chunks of the world with the given name, see
+    /// `ServerWorld::name`.
+    pub fn provider(&self, name: &str) -> Result<Option<FolderChunkProvider>, io::Error> {
+        Ok(self
+            .worlds()?
+            .into_iter()
+            .find(|world| world.name == name)
+            .and_then(|world| world.region_provider()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nbt::CompoundTag;
+
+    fn create_world(server_path: &Path, world_folder: &str, dimension_folder: &str) {
+        let world_path = server_path.join(world_folder);
+        let region_path = world_path.join(dimension_folder).join("region");
+        fs::create_dir_all(world_path.join("region")).unwrap();
+        fs::create_dir_all(&region_path).unwrap();
+        fs::write(world_path.join("level.dat"), []).unwrap();
+        FolderChunkProvider::new(region_path)
+            .save_chunk(0, 0, CompoundTag::new())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_server_worlds() {
+        let server = tempfile::tempdir().unwrap();
+        create_world(server.path(), "world", ".");
+        create_world(server.path(), "world_nether", "DIM-1");
+        create_world(server.path(), "world_the_end", "DIM1");
+        fs::create_dir(server.path().join("plugins")).unwrap();
+
+        let server_provider = ServerDirectoryProvider::new(server.path());
+        let worlds = server_provider.worlds().unwrap();
+        let names: Vec<_> = worlds.iter().map(|world| world.name.as_str()).collect();
+        assert_eq!(names, vec!["world", "world_nether", "world_the_end"]);
+        assert_eq!(
+            worlds[1].dimension.id.as_deref(),
+            Some("minecraft:the_nether")
+        );
+
+        let nether_provider = server_provider.provider("world_nether").unwrap().unwrap();
+        assert_eq!(
+            nether_provider.folder_path(),
+            server
+                .path()
+                .join("world_nether")
+                .join("DIM-1")
+                .join("region")
+        );
+        assert!(nether_provider.load_chunk(0, 0).is_ok());
+        assert!(server_provider.provider("lobby").unwrap().is_none());
+
+        // Vanilla layout, with every dimension in the same world folder.
+        let server = tempfile::tempdir().unwrap();
+        create_world(server.path(), "lobby", ".");
+        create_world(server.path(), "lobby", "DIM1");
+
+        let worlds = ServerDirectoryProvider::new(server.path())
+            .worlds()
+            .unwrap();
+        let names: Vec<_> = worlds.iter().map(|world| world.name.as_str()).collect();
+        assert_eq!(names, vec!["lobby", "lobby_the_end"]);
+        assert_eq!(worlds[1].world_folder, "lobby");
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{chunk_level_mut, AnvilChunkProvider, ChunkDeleter, TimestampPolicy, WorldEditError};
+use nbt::{CompoundTag, Tag};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+/// Moves every chunk of the provider by the given amount of chunks.
+///
+/// Chunks are saved at their new coordinates and the coordinates stored
+/// inside the chunk tags are updated, see `shift_chunk_tag`. Chunks whose old
+/// position is not overwritten by another moved chunk are deleted.
+///
+/// Chunks are processed in an order where every chunk is read before another
+/// chunk is moved on top of it, so no temporary copy of the world is needed.
+pub fn shift_world<P: AnvilChunkProvider + ChunkDeleter + ?Sized>(
+    provider: &mut P,
+    dx_chunks: i32,
+    dz_chunks: i32,
+    timestamp_policy: TimestampPolicy,
+) -> Result<(), WorldEditError> {
+    if dx_chunks == 0 && dz_chunks == 0 {
+        return Ok(());
+    }
+
+    let mut chunks = provider.list_chunks()?;
+
+    // The destination of a chunk always has a greater key than the chunk
+    // itself, so processing in descending order reads every destination
+    // before overwriting it.
+    let sign_x = i64::from(dx_chunks.signum());
+    let sign_z = i64::from(dz_chunks.signum());
+    chunks.sort_unstable_by_key(|&(chunk_x, chunk_z)| {
+        Reverse(i64::from(chunk_x) * sign_x + i64::from(chunk_z) * sign_z)
+    });
+
+    let destinations: HashSet<(i32, i32)> = chunks
+        .iter()
+        .map(|&(chunk_x, chunk_z)| (chunk_x + dx_chunks, chunk_z + dz_chunks))
+        .collect();
+
+    for &(chunk_x, chunk_z) in &chunks {
+        let metadata = provider.load_chunk_metadata(chunk_x, chunk_z)?;
+        let mut chunk_compound_tag = provider.load_chunk(chunk_x, chunk_z)?;
+        let last_modified_timestamp =
+            timestamp_policy.timestamp(metadata.last_modified_timestamp());
+
+        shift_chunk_tag(&mut chunk_compound_tag, dx_chunks, dz_chunks);
+        provider.save_chunk_with_timestamp(
+            chunk_x + dx_chunks,
+            chunk_z + dz_chunks,
+            chunk_compound_tag,
+            last_modified_timestamp,
+        )?;
+    }
+
+    for &(chunk_x, chunk_z) in &chunks {
+        if !destinations.contains(&(chunk_x, chunk_z)) {
+            provider.delete_chunk(chunk_x, chunk_z)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Updates the coordinates stored inside chunk tags after moving the chunk by
+/// the given amount of chunks.
+///
+/// Handles both the `Level` layout and the 1.18+ root layout:
+///
+/// * `xPos` and `zPos`.
+/// * `Pos` of entities in `Entities`, including their passengers.
+/// * `x` and `z` of block entities in `TileEntities` or `block_entities`.
+/// * `x` and `z` of scheduled ticks in `TileTicks`, `LiquidTicks`,
+///   `block_ticks` or `fluid_ticks`.
+/// * `Position` of 1.17+ entity chunks.
+pub fn shift_chunk_tag(chunk_compound_tag: &mut CompoundTag, dx_chunks: i32, dz_chunks: i32) {
+    let dx_blocks = dx_chunks * 16;
+    let dz_blocks = dz_chunks * 16;
+
+    // Entity chunks store their coordinates as [x, z] instead of xPos/zPos.
+    if let Ok(position) = chunk_compound_tag.get_mut::<&mut Vec<i32>>("Position") {
+        if let [x, z] = position.as_mut_slice() {
+            *x += dx_chunks;
+            *z += dz_chunks;
+        }
+    }
+
+    let level_compound_tag = chunk_level_mut(chunk_compound_tag);
+
+    if let Ok(x_pos) = level_compound_tag.get_mut::<&mut i32>("xPos") {
+        *x_pos += dx_chunks;
+    }
+    if let Ok(z_pos) = level_compound_tag.get_mut::<&mut i32>("zPos") {
+        *z_pos += dz_chunks;
+    }
+
+    if let Ok(entities) = level_compound_tag.get_mut::<&mut Vec<Tag>>("Entities") {
+        shift_entities(entities, dx_blocks, dz_blocks);
+    }
+
+    for name in &[
+        "TileEntities",
+        "block_entities",
+        "TileTicks",
+        "LiquidTicks",
+        "block_ticks",
+        "fluid_ticks",
+    ] {
+        if let Ok(tags) = level_compound_tag.get_mut::<&mut Vec<Tag>>(name) {
+            for tag in tags {
+                if let Tag::Compound(compound_tag) = tag {
+                    shift_block_position(compound_tag, dx_blocks, dz_blocks);
+                }
+            }
+        }
+    }
+}
+
+fn shift_entities(entities: &mut [Tag], dx_blocks: i32, dz_blocks: i32) {
+    for entity in entities {
+        if let Tag::Compound(entity_compound_tag) = entity {
+            if let Ok(pos) = entity_compound_tag.get_mut::<&mut Vec<Tag>>("Pos") {
+                if let [Tag::Double(x), _, Tag::Double(z)] = pos.as_mut_slice() {
+                    *x += f64::from(dx_blocks);
+                    *z += f64::from(dz_blocks);
+                }
+            }
+
+            if let Ok(passengers) = entity_compound_tag.get_mut::<&mut Vec<Tag>>("Passengers") {
+                shift_entities(passengers, dx_blocks, dz_blocks);
+            }
+        }
+    }
+}
+
+fn shift_block_position(compound_tag: &mut CompoundTag, dx_blocks: i32, dz_blocks: i32) {
+    if let Ok(x) = compound_tag.get_mut::<&mut i32>("x") {
+        *x += dx_blocks;
+    }
+    if let Ok(z) = compound_tag.get_mut::<&mut i32>("z") {
+        *z += dz_blocks;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chunk_level, ChunkReader, FolderChunkProvider};
+
+    fn chunk_with_entity(chunk_x: i32, chunk_z: i32) -> CompoundTag {
+        let mut entity = CompoundTag::new();
+        entity.insert_f64_vec(
+            "Pos",
+            vec![f64::from(chunk_x * 16) + 0.5, 64.0, f64::from(chunk_z * 16) + 0.5],
+        );
+        let mut tile_entity = CompoundTag::new();
+        tile_entity.insert_i32("x", chunk_x * 16 + 1);
+        tile_entity.insert_i32("y", 64);
+        tile_entity.insert_i32("z", chunk_z * 16 + 2);
+
+        let mut level_compound_tag = CompoundTag::new();
+        level_compound_tag.insert_i32("xPos", chunk_x);
+        level_compound_tag.insert_i32("zPos", chunk_z);
+        level_compound_tag.insert_compound_tag_vec("Entities", vec![entity]);
+        level_compound_tag.insert_compound_tag_vec("TileEntities", vec![tile_entity]);
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+
+        chunk_compound_tag
+    }
+
+    #[test]
+    fn shift_chunk_tag_positions() {
+        let mut chunk_compound_tag = chunk_with_entity(1, 2);
+        shift_chunk_tag(&mut chunk_compound_tag, 3, -4);
+
+        let level_compound_tag = chunk_level(&chunk_compound_tag);
+        let entity = level_compound_tag.get_compound_tag_vec("Entities").unwrap()[0];
+        let tile_entity = level_compound_tag.get_compound_tag_vec("TileEntities").unwrap()[0];
+
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+        assert_eq!(level_compound_tag.get_i32("zPos").unwrap(), -2);
+        assert_eq!(entity.get_f64_vec("Pos").unwrap(), vec![64.5, 64.0, -31.5]);
+        assert_eq!(tile_entity.get_i32("x").unwrap(), 65);
+        assert_eq!(tile_entity.get_i32("y").unwrap(), 64);
+        assert_eq!(tile_entity.get_i32("z").unwrap(), -30);
+    }
+
+    #[test]
+    fn shift_entity_chunk_position() {
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32_vec("Position", vec![1, 2]);
+        shift_chunk_tag(&mut chunk_compound_tag, -1, 30);
+
+        assert_eq!(chunk_compound_tag.get_i32_vec("Position").unwrap(), &vec![0, 32]);
+    }
+
+    #[test]
+    fn shift_overlapping_chunks() {
+        let folder = tempfile::tempdir().unwrap();
+        let mut provider = FolderChunkProvider::new(folder.path().to_str().unwrap());
+
+        // A row of chunks crossing a region border, moved by less than its length.
+        for chunk_x in 30..34 {
+            provider
+                .save_chunk_with_timestamp(chunk_x, 0, chunk_with_entity(chunk_x, 0), 1000)
+                .unwrap();
+        }
+
+        shift_world(&mut provider, 2, -1, TimestampPolicy::Preserve).unwrap();
+
+        let mut chunks = provider.list_chunks().unwrap();
+        chunks.sort_unstable();
+        assert_eq!(chunks, vec![(32, -1), (33, -1), (34, -1), (35, -1)]);
+
+        for (chunk_x, chunk_z) in chunks {
+            let chunk_compound_tag = provider.load_chunk(chunk_x, chunk_z).unwrap();
+            let level_compound_tag = chunk_level(&chunk_compound_tag);
+            let metadata = provider.load_chunk_metadata(chunk_x, chunk_z).unwrap();
+
+            assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), chunk_x);
+            assert_eq!(level_compound_tag.get_i32("zPos").unwrap(), chunk_z);
+            assert_eq!(metadata.last_modified_timestamp(), 1000);
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use crate::{
+    chunk_coords_to_region_coords, ChunkDeleter, ChunkLoadError, ChunkPayload, ChunkReader,
+    ChunkSaveError, ChunkWriter, FolderChunkProvider, RawChunk, RegionReader, WorldEditError,
+};
+use nbt::CompoundTag;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::{fs, mem};
+
+/// Folder provider that keeps the original content of every region file it
+/// modifies, so a whole editing session can be undone.
+///
+/// Before the first write to a region file in a session, the whole file is
+/// read into memory. `rollback` writes these copies back and `commit` drops
+/// them, both start a new session. Dropping the provider keeps the changes.
+///
+/// Only regions modified through this provider are tracked, the region
+/// files must not be modified by anything else during a session.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{FolderChunkProvider, SnapshotChunkProvider};
+/// use nbt::CompoundTag;
+///
+/// # let folder = tempfile::tempdir().unwrap();
+/// # let folder = folder.path().to_str().unwrap();
+/// let chunk_provider = SnapshotChunkProvider::new(FolderChunkProvider::new(folder));
+///
+/// chunk_provider.save_chunk(1, 2, CompoundTag::new()).unwrap();
+/// assert!(chunk_provider.load_chunk(1, 2).is_ok());
+///
+/// chunk_provider.rollback().unwrap();
+/// assert!(chunk_provider.try_load_chunk(1, 2).unwrap().is_none());
+/// ```
+pub struct SnapshotChunkProvider<P = CompoundTag> {
+    /// Provider which reads and writes the region files.
+    provider: FolderChunkProvider<P>,
+    /// Content of the region files before their first modification, or
+    /// `None` if the file did not exist.
+    originals: Mutex<RegionOriginals>,
+}
+
+/// Original content of region files by region coordinates.
+type RegionOriginals = HashMap<(i32, i32), Option<Vec<u8>>>;
+
+impl<P: ChunkPayload> SnapshotChunkProvider<P> {
+    /// Starts a session on the region folder of the provider.
+    pub fn new(provider: FolderChunkProvider<P>) -> Self {
+        SnapshotChunkProvider {
+            provider,
+            originals: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the wrapped provider. Writes made through it are not tracked.
+    pub fn provider(&self) -> &FolderChunkProvider<P> {
+        &self.provider
+    }
+
+    /// Returns the wrapped provider, keeping the changes of the session.
+    pub fn into_inner(self) -> FolderChunkProvider<P> {
+        self.provider
+    }
+
+    /// Coordinates of the regions modified in this session, sorted.
+    pub fn modified_regions(&self) -> Vec<(i32, i32)> {
+        let mut regions: Vec<_> = self.originals.lock().unwrap().keys().copied().collect();
+        regions.sort_unstable();
+
+        regions
+    }
+
+    /// Keeps the changes made in this session and starts a new one.
+    pub fn commit(&self) {
+        self.originals.lock().unwrap().clear();
+    }
+
+    /// Restores every region file modified in this session to its original
+    /// content, removing the region files created in this session, and
+    /// starts a new session.
+    pub fn rollback(&self) -> Result<(), io::Error> {
+        let originals = mem::take(&mut *self.originals.lock().unwrap());
+
+        // Open regions keep the modified header in memory.
+        self.provider.close_regions()?;
+
+        for ((region_x, region_z), original) in originals {
+            let region_path = self.region_path(region_x, region_z);
+
+            match original {
+                Some(bytes) => fs::write(region_path, bytes)?,
+                None if region_path.exists() => fs::remove_file(region_path)?,
+                None => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn load_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        self.provider.load_chunk(chunk_x, chunk_z)
+    }
+
+    pub fn try_load_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<Option<P>, ChunkLoadError> {
+        self.provider.try_load_chunk(chunk_x, chunk_z)
+    }
+
+    pub fn save_chunk(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        self.record_original(chunk_x, chunk_z)?;
+        self.provider
+            .save_chunk(chunk_x, chunk_z, chunk_compound_tag)
+    }
+
+    pub fn save_chunk_with_timestamp(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        self.record_original(chunk_x, chunk_z)?;
+        self.provider.save_chunk_with_timestamp(
+            chunk_x,
+            chunk_z,
+            chunk_compound_tag,
+            last_modified_timestamp,
+        )
+    }
+
+    pub fn save_chunk_raw(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), WorldEditError> {
+        self.record_original(chunk_x, chunk_z)?;
+        self.provider
+            .save_chunk_raw(chunk_x, chunk_z, raw_chunk, last_modified_timestamp)
+    }
+
+    pub fn delete_chunk(&self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError> {
+        self.record_original(chunk_x, chunk_z)?;
+        self.provider.delete_chunk(chunk_x, chunk_z)
+    }
+
+    /// Reads the region file of the chunk into memory, unless it was already
+    /// modified in this session.
+    fn record_original(&self, chunk_x: i32, chunk_z: i32) -> Result<(), io::Error> {
+        let (region_x, region_z) = chunk_coords_to_region_coords(chunk_x, chunk_z);
+        let mut originals = self.originals.lock().unwrap();
+
+        if originals.contains_key(&(region_x, region_z)) {
+            return Ok(());
+        }
+
+        let region_path = self.region_path(region_x, region_z);
+        self.provider.write_open_header(region_x, region_z)?;
+        let original = if region_path.exists() {
+            Some(fs::read(region_path)?)
+        } else {
+            None
+        };
+
+        originals.insert((region_x, region_z), original);
+
+        Ok(())
+    }
+
+    fn region_path(&self, region_x: i32, region_z: i32) -> PathBuf {
+        let region_name = self.provider.region_file_name(region_x, region_z);
+
+        self.provider.folder_path.join(region_name)
+    }
+}
+
+impl<P: ChunkPayload> ChunkReader<P> for SnapshotChunkProvider<P> {
+    fn get_region(
+        &mut self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<RegionReader, ChunkLoadError> {
+        self.provider.get_region(region_x, region_z)
+    }
+    fn load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<P, ChunkLoadError> {
+        SnapshotChunkProvider::load_chunk(self, chunk_x, chunk_z)
+    }
+    fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.provider.list_chunks()
+    }
+    fn list_regions(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.provider.list_regions()
+    }
+    fn load_chunk_raw(&mut self, chunk_x: i32, chunk_z: i32) -> Result<RawChunk, ChunkLoadError> {
+        self.provider.load_chunk_raw(chunk_x, chunk_z)
+    }
+}
+
+impl<P: ChunkPayload> ChunkWriter<P> for SnapshotChunkProvider<P> {
+    fn save_chunk_with_timestamp(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkSaveError> {
+        SnapshotChunkProvider::save_chunk_with_timestamp(
+            self,
+            chunk_x,
+            chunk_z,
+            chunk_compound_tag,
+            last_modified_timestamp,
+        )
+    }
+    fn save_chunk(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        chunk_compound_tag: P,
+    ) -> Result<(), ChunkSaveError> {
+        SnapshotChunkProvider::save_chunk(self, chunk_x, chunk_z, chunk_compound_tag)
+    }
+    fn save_chunk_raw(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        raw_chunk: &RawChunk,
+        last_modified_timestamp: u32,
+    ) -> Result<(), WorldEditError> {
+        SnapshotChunkProvider::save_chunk_raw(
+            self,
+            chunk_x,
+            chunk_z,
+            raw_chunk,
+            last_modified_timestamp,
+        )
+    }
+}
+
+impl<P: ChunkPayload> ChunkDeleter<P> for SnapshotChunkProvider<P> {
+    fn delete_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<(), ChunkSaveError> {
+        SnapshotChunkProvider::delete_chunk(self, chunk_x, chunk_z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::value_chunk_tag;
+
+    #[test]
+    fn test_snapshot_rollback() {
+        let folder = tempfile::tempdir().unwrap();
+        let folder = folder.path().to_str().unwrap();
+        FolderChunkProvider::new(folder)
+            .save_chunk(0, 0, value_chunk_tag(1))
+            .unwrap();
+        let original = fs::read(folder.to_owned() + "/r.0.0.mca").unwrap();
+
+        let chunk_provider =
+            SnapshotChunkProvider::new(FolderChunkProvider::new(folder).with_max_open_regions(4));
+        chunk_provider.save_chunk(0, 0, value_chunk_tag(2)).unwrap();
+        chunk_provider.save_chunk(1, 0, value_chunk_tag(3)).unwrap();
+        chunk_provider.delete_chunk(0, 0).unwrap();
+        chunk_provider
+            .save_chunk(-1, 0, value_chunk_tag(4))
+            .unwrap();
+        assert_eq!(chunk_provider.modified_regions(), vec![(-1, 0), (0, 0)]);
+
+        chunk_provider.rollback().unwrap();
+        assert!(chunk_provider.modified_regions().is_empty());
+        assert_eq!(
+            fs::read(folder.to_owned() + "/r.0.0.mca").unwrap(),
+            original
+        );
+        assert!(!std::path::Path::new(&(folder.to_owned() + "/r.-1.0.mca")).exists());
+
+        let chunk_compound_tag = chunk_provider.load_chunk(0, 0).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("value").unwrap(), 1);
+        assert!(chunk_provider.try_load_chunk(1, 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_snapshot_commit() {
+        let folder = tempfile::tempdir().unwrap();
+        let folder = folder.path().to_str().unwrap();
+        let chunk_provider = SnapshotChunkProvider::new(FolderChunkProvider::new(folder));
+
+        chunk_provider.save_chunk(0, 0, value_chunk_tag(1)).unwrap();
+        chunk_provider.commit();
+        chunk_provider.save_chunk(0, 0, value_chunk_tag(2)).unwrap();
+        chunk_provider.rollback().unwrap();
+
+        let chunk_compound_tag = chunk_provider.load_chunk(0, 0).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("value").unwrap(), 1);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{AnvilRegion, REGION_SECTOR_BYTES_LENGTH};
+use std::fs::File;
+use std::io;
+use std::io::{Read, Seek, Write};
+
+/// Storage able to release the space of a byte range without changing its
+/// length, reading zeroes afterwards.
+pub trait PunchHole {
+    /// Deallocates the given byte range, returning `false` when the storage
+    /// doesn't support it, in which case it is left unchanged.
+    fn punch_hole(&mut self, offset: u64, length: u64) -> Result<bool, io::Error>;
+}
+
+impl PunchHole for File {
+    /// Uses `fallocate` with `FALLOC_FL_PUNCH_HOLE` on Linux. Not supported
+    /// on other systems and file systems.
+    #[cfg(target_os = "linux")]
+    fn punch_hole(&mut self, offset: u64, length: u64) -> Result<bool, io::Error> {
+        use std::os::unix::io::AsRawFd;
+
+        let mode = libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE;
+        // Safe because the file descriptor is owned by the file.
+        let result = unsafe {
+            libc::fallocate(
+                self.as_raw_fd(),
+                mode,
+                offset as libc::off_t,
+                length as libc::off_t,
+            )
+        };
+
+        if result == 0 {
+            return Ok(true);
+        }
+
+        let error = io::Error::last_os_error();
+
+        match error.raw_os_error() {
+            Some(libc::EOPNOTSUPP) | Some(libc::ENOSYS) => Ok(false),
+            _ => Err(error),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn punch_hole(&mut self, _offset: u64, _length: u64) -> Result<bool, io::Error> {
+        Ok(false)
+    }
+}
+
+impl PunchHole for io::Cursor<Vec<u8>> {
+    fn punch_hole(&mut self, offset: u64, length: u64) -> Result<bool, io::Error> {
+        let bytes = self.get_mut();
+        let start = (offset as usize).min(bytes.len());
+        let end = (offset + length).min(bytes.len() as u64) as usize;
+
+        for byte in &mut bytes[start..end] {
+            *byte = 0;
+        }
+
+        Ok(true)
+    }
+}
+
+impl<F: Seek + Read + Write + PunchHole> AnvilRegion<F> {
+    /// Punches holes over the sectors freed since the last call, when
+    /// enabled by `with_punch_holes`, returning the amount of bytes
+    /// released.
+    ///
+    /// Sectors used again by other chunks in the meantime are kept.
+    pub fn punch_freed_sectors(&mut self) -> Result<u64, io::Error> {
+        let freed_sectors = std::mem::take(&mut self.freed_sectors);
+        let mut punched_length = 0;
+
+        for (start, length) in freed_sectors {
+            for sector_index in start..start + length {
+                let free = self
+                    .used_sectors
+                    .get(sector_index as usize)
+                    .is_some_and(|used| !*used);
+
+                if free && self.punch_sectors(sector_index, 1)? {
+                    punched_length += REGION_SECTOR_BYTES_LENGTH as u64;
+                }
+            }
+        }
+
+        Ok(punched_length)
+    }
+
+    /// Punches holes over every free sector of the region, returning the
+    /// amount of bytes released. Useful for regions written without
+    /// `with_punch_holes`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::AnvilRegion;
+    /// use nbt::CompoundTag;
+    /// use std::io::Cursor;
+    ///
+    /// let mut region = AnvilRegion::new(Cursor::new(Vec::new())).unwrap();
+    /// region.write_chunk(0, 0, CompoundTag::new()).unwrap();
+    /// region.write_chunk(1, 0, CompoundTag::new()).unwrap();
+    /// region.delete_chunk(0, 0).unwrap();
+    ///
+    /// assert_eq!(region.punch_free_sectors().unwrap(), 4096);
+    /// ```
+    pub fn punch_free_sectors(&mut self) -> Result<u64, io::Error> {
+        self.freed_sectors.clear();
+        self.build_used_sectors()?;
+
+        let total_sectors = self.sector_count()?;
+        let mut punched_length = 0;
+
+        for (start, length) in self.free_runs(total_sectors) {
+            if self.punch_sectors(start, length)? {
+                punched_length += length as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+            }
+        }
+
+        Ok(punched_length)
+    }
+
+    fn punch_sectors(&mut self, sector_index: u32, sectors: u32) -> Result<bool, io::Error> {
+        let sector_length = REGION_SECTOR_BYTES_LENGTH as u64;
+
+        self.file.punch_hole(
+            sector_index as u64 * sector_length,
+            sectors as u64 * sector_length,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilOptions, FolderChunkProvider};
+    use nbt::CompoundTag;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_punch_freed_sectors() {
+        let mut region = AnvilRegion::new(Cursor::new(Vec::new()))
+            .unwrap()
+            .with_punch_holes(true);
+        region.write_chunk(0, 0, CompoundTag::new()).unwrap();
+        region.write_chunk(1, 0, CompoundTag::new()).unwrap();
+        region.delete_chunk(0, 0).unwrap();
+
+        assert_eq!(region.punch_freed_sectors().unwrap(), 4096);
+        assert_eq!(region.punch_freed_sectors().unwrap(), 0);
+        assert!(region.read_chunk(1, 0).is_ok());
+
+        let bytes = region.into_inner().into_inner();
+        assert_eq!(bytes.len(), 4 * 4096);
+        assert!(bytes[2 * 4096..3 * 4096].iter().all(|&byte| byte == 0));
+
+        // Files keep their length, deleted chunks read as zeroes if the file
+        // system supports holes.
+        let folder = tempfile::tempdir().unwrap();
+        let options = AnvilOptions::new().punch_holes(true);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        chunk_provider.save_chunk(0, 0, CompoundTag::new()).unwrap();
+        chunk_provider.save_chunk(1, 0, CompoundTag::new()).unwrap();
+        chunk_provider.delete_chunk(0, 0).unwrap();
+
+        let region_path = folder.path().join("r.0.0.mca");
+        assert_eq!(std::fs::metadata(&region_path).unwrap().len(), 4 * 4096);
+        assert!(chunk_provider.load_chunk(1, 0).is_ok());
+
+        let bytes = std::fs::read(&region_path).unwrap();
+        let zeroed = bytes[2 * 4096..3 * 4096].iter().all(|&byte| byte == 0);
+        let mut region = AnvilRegion::file(&region_path).unwrap();
+        assert_eq!(region.punch_free_sectors().unwrap() > 0, zeroed);
+    }
+}
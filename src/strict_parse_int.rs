@@ -18,7 +18,7 @@ use std::convert::TryFrom;
 /// * Hexadecimal or other bases: "0x0"
 /// * Floating point syntax: dots or exponents: "0.", "0.0", "0e0"
 pub fn strict_parse_i32(s: &[u8]) -> Option<i32> {
-    let negative = *s.get(0)? == b'-';
+    let negative = *s.first()? == b'-';
 
     if negative {
         // Parse positive value
@@ -54,7 +54,7 @@ pub fn strict_parse_i32(s: &[u8]) -> Option<i32> {
 /// * Hexadecimal or other bases: "0x0"
 /// * Floating point syntax: dots or exponents: "0.", "0.0", "0e0"
 pub fn strict_parse_u32(s: &[u8]) -> Option<u32> {
-    if s.len() == 0 {
+    if s.is_empty() {
         // Empty string
         return None;
     }
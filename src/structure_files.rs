@@ -0,0 +1,275 @@
+use crate::world_folder::{load_gzip_nbt, save_gzip_nbt};
+use nbt::decode::TagDecodeError;
+use nbt::CompoundTag;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Folder of the structure templates saved by structure blocks.
+const GENERATED_FOLDER: &str = "generated";
+/// Structure folder of a namespace before 1.21.
+const STRUCTURES_FOLDER: &str = "structures";
+/// Structure folder of a namespace since 1.21.
+const STRUCTURE_FOLDER: &str = "structure";
+
+/// Id of a structure template, such as `minecraft:house/roof`.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct StructureId {
+    pub namespace: String,
+    /// Path of the template inside the namespace, with `/` separators and
+    /// without the `.nbt` extension.
+    pub path: String,
+}
+
+impl StructureId {
+    pub fn new(namespace: &str, path: &str) -> Self {
+        StructureId {
+            namespace: namespace.to_string(),
+            path: path.to_string(),
+        }
+    }
+
+    /// Parses an id like `namespace:path`, with `minecraft` as the default
+    /// namespace.
+    pub fn parse(id: &str) -> Option<Self> {
+        let (namespace, path) = match id.split_once(':') {
+            Some((namespace, path)) => (namespace, path),
+            None => ("minecraft", id),
+        };
+
+        if namespace.is_empty() || path.is_empty() {
+            return None;
+        }
+
+        Some(StructureId::new(namespace, path))
+    }
+
+    /// Path of the template file in the given structure folder.
+    fn file_path(&self, world_path: &Path, folder: &str) -> PathBuf {
+        let mut file_path = world_path
+            .join(GENERATED_FOLDER)
+            .join(&self.namespace)
+            .join(folder);
+
+        for part in self.path.split('/') {
+            file_path.push(part);
+        }
+
+        file_path.set_extension("nbt");
+
+        file_path
+    }
+}
+
+impl fmt::Display for StructureId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.path)
+    }
+}
+
+/// Lists, loads and saves the gzip compressed NBT structure templates of a
+/// world, saved by structure blocks in
+/// `generated/<namespace>/structures/<path>.nbt`, or in the `structure`
+/// folder since 1.21.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{StructureId, StructureProvider};
+/// use nbt::CompoundTag;
+///
+/// # let world = tempfile::tempdir().unwrap();
+/// # let world = world.path();
+/// let structure_provider = StructureProvider::new(world);
+/// let house = StructureId::parse("minecraft:house/roof").unwrap();
+///
+/// let mut structure_compound_tag = CompoundTag::new();
+/// structure_compound_tag.insert_i32_vec("size", vec![5, 3, 5]);
+/// structure_provider.save_structure(&house, &structure_compound_tag).unwrap();
+///
+/// assert_eq!(structure_provider.list_structures().unwrap(), vec![house.clone()]);
+/// let structure_compound_tag = structure_provider.load_structure(&house).unwrap();
+/// assert_eq!(structure_compound_tag.get_i32_vec("size").unwrap(), &[5, 3, 5]);
+/// ```
+#[derive(Debug)]
+pub struct StructureProvider {
+    /// World folder, containing the `generated` folder.
+    world_path: PathBuf,
+}
+
+impl StructureProvider {
+    pub fn new<F: Into<PathBuf>>(world_folder: F) -> Self {
+        StructureProvider {
+            world_path: world_folder.into(),
+        }
+    }
+
+    pub fn world_path(&self) -> &Path {
+        &self.world_path
+    }
+
+    /// Lists the templates of every namespace, sorted. A missing
+    /// `generated` folder is treated as empty.
+    pub fn list_structures(&self) -> Result<Vec<StructureId>, io::Error> {
+        let generated_path = self.world_path.join(GENERATED_FOLDER);
+        let mut structures = Vec::new();
+
+        if !generated_path.is_dir() {
+            return Ok(structures);
+        }
+
+        for entry in fs::read_dir(generated_path)? {
+            let namespace_path = entry?.path();
+            let namespace = match namespace_path.file_name().and_then(|name| name.to_str()) {
+                Some(namespace) if namespace_path.is_dir() => namespace.to_string(),
+                _ => continue,
+            };
+
+            for folder in &[STRUCTURES_FOLDER, STRUCTURE_FOLDER] {
+                let folder_path = namespace_path.join(folder);
+
+                if folder_path.is_dir() {
+                    find_templates(&namespace, &folder_path, "", &mut structures)?;
+                }
+            }
+        }
+
+        structures.sort();
+        structures.dedup();
+
+        Ok(structures)
+    }
+
+    /// Loads the template, from the 1.21 `structure` folder if it is in
+    /// both folders.
+    pub fn load_structure(&self, structure: &StructureId) -> Result<CompoundTag, TagDecodeError> {
+        load_gzip_nbt(&self.existing_path(structure))
+    }
+
+    /// Saves the template over the existing file, in the `structures`
+    /// folder for new templates.
+    pub fn save_structure(
+        &self,
+        structure: &StructureId,
+        structure_compound_tag: &CompoundTag,
+    ) -> Result<(), io::Error> {
+        save_gzip_nbt(
+            &self.existing_path(structure),
+            structure_compound_tag,
+            false,
+        )
+    }
+
+    /// Deletes the template from both folders.
+    pub fn delete_structure(&self, structure: &StructureId) -> Result<(), io::Error> {
+        let mut deleted = false;
+
+        for folder in &[STRUCTURE_FOLDER, STRUCTURES_FOLDER] {
+            match fs::remove_file(structure.file_path(&self.world_path, folder)) {
+                Ok(()) => deleted = true,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        if deleted {
+            Ok(())
+        } else {
+            Err(io::ErrorKind::NotFound.into())
+        }
+    }
+
+    /// Path of the existing template file, or of the file in the
+    /// `structures` folder if there is none.
+    fn existing_path(&self, structure: &StructureId) -> PathBuf {
+        let file_path = structure.file_path(&self.world_path, STRUCTURE_FOLDER);
+
+        if file_path.exists() {
+            file_path
+        } else {
+            structure.file_path(&self.world_path, STRUCTURES_FOLDER)
+        }
+    }
+}
+
+/// Adds the templates below the folder, whose paths start with `prefix`.
+fn find_templates(
+    namespace: &str,
+    folder_path: &Path,
+    prefix: &str,
+    structures: &mut Vec<StructureId>,
+) -> Result<(), io::Error> {
+    for entry in fs::read_dir(folder_path)? {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+
+        if path.is_dir() {
+            find_templates(
+                namespace,
+                &path,
+                &format!("{}{}/", prefix, name),
+                structures,
+            )?;
+        } else if let Some(stem) = name.strip_suffix(".nbt") {
+            let path = format!("{}{}", prefix, stem);
+            structures.push(StructureId::new(namespace, &path));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structure_folders() {
+        let world = tempfile::tempdir().unwrap();
+        let structure_provider = StructureProvider::new(world.path());
+        assert!(structure_provider.list_structures().unwrap().is_empty());
+
+        assert_eq!(
+            StructureId::parse("tower"),
+            Some(StructureId::new("minecraft", "tower"))
+        );
+        assert_eq!(StructureId::parse("mod:"), None);
+
+        let tower = StructureId::parse("mod:towers/tall").unwrap();
+        let house = StructureId::parse("house").unwrap();
+        let mut structure_compound_tag = CompoundTag::new();
+        structure_compound_tag.insert_i32("DataVersion", 1343);
+        structure_provider
+            .save_structure(&tower, &structure_compound_tag)
+            .unwrap();
+        assert!(world
+            .path()
+            .join("generated/mod/structures/towers/tall.nbt")
+            .exists());
+
+        // 1.21 folder.
+        let house_path = house.file_path(world.path(), STRUCTURE_FOLDER);
+        fs::create_dir_all(house_path.parent().unwrap()).unwrap();
+        save_gzip_nbt(&house_path, &structure_compound_tag, false).unwrap();
+        structure_compound_tag.insert_i32("DataVersion", 3953);
+        structure_provider
+            .save_structure(&house, &structure_compound_tag)
+            .unwrap();
+
+        assert_eq!(
+            structure_provider.list_structures().unwrap(),
+            vec![house.clone(), tower.clone()]
+        );
+        let structure_compound_tag = structure_provider.load_structure(&house).unwrap();
+        assert_eq!(structure_compound_tag.get_i32("DataVersion").unwrap(), 3953);
+
+        structure_provider.delete_structure(&house).unwrap();
+        assert_eq!(structure_provider.list_structures().unwrap(), vec![tower]);
+        let error = structure_provider.delete_structure(&house).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+}
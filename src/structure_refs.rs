@@ -0,0 +1,271 @@
+use crate::{chunk_level, ChunkLoadError, ChunkReader, ChunkSelection, FolderChunkProvider};
+use nbt::{CompoundTag, Tag};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Generated structure touching a chunk, found by `structure_refs`.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct StructureRef {
+    /// Structure name as stored in the chunk, such as `Mineshaft` before
+    /// 1.14, `village` before 1.18 or `minecraft:village_plains`.
+    pub name: String,
+    /// Chunk holding the start of the structure, which the game needs to
+    /// place the structure pieces in the other chunks.
+    pub start: (i32, i32),
+}
+
+/// Structures touching the chunk at the given coordinates, read from the
+/// `Structures` compound of pre-1.18 chunks or the `structures` compound of
+/// newer ones, sorted. Both the structures referenced by the chunk and the
+/// ones starting in it are returned.
+pub fn structure_refs(
+    chunk_compound_tag: &CompoundTag,
+    chunk_x: i32,
+    chunk_z: i32,
+) -> Vec<StructureRef> {
+    let level_compound_tag = chunk_level(chunk_compound_tag);
+    let (structures, starts_name) = match level_compound_tag.get_compound_tag("structures") {
+        Ok(structures) => (structures, "starts"),
+        Err(_) => match level_compound_tag.get_compound_tag("Structures") {
+            Ok(structures) => (structures, "Starts"),
+            Err(_) => return Vec::new(),
+        },
+    };
+
+    let mut refs = BTreeSet::new();
+
+    if let Ok(references) = structures.get_compound_tag("References") {
+        for (name, tag) in references.iter() {
+            if let Tag::LongArray(positions) = tag {
+                for &position in positions {
+                    refs.insert(StructureRef {
+                        name: name.clone(),
+                        start: unpack_chunk_position(position),
+                    });
+                }
+            }
+        }
+    }
+
+    if let Ok(starts) = structures.get_compound_tag(starts_name) {
+        for (name, tag) in starts.iter() {
+            let is_start = match tag {
+                // Chunks from 1.13 to 1.17 list every structure type, with
+                // the `INVALID` id when nothing starts in the chunk.
+                Tag::Compound(start) => start.get_str("id").ok() != Some("INVALID"),
+                _ => false,
+            };
+
+            if is_start {
+                refs.insert(StructureRef {
+                    name: name.clone(),
+                    start: (chunk_x, chunk_z),
+                });
+            }
+        }
+    }
+
+    refs.into_iter().collect()
+}
+
+/// Chunk coordinates packed in a long by structure references, `x` in the
+/// low bits.
+fn unpack_chunk_position(position: i64) -> (i32, i32) {
+    (position as i32, (position >> 32) as i32)
+}
+
+impl FolderChunkProvider {
+    /// Structures touching the chunk, see `structure_refs`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::FolderChunkProvider;
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("test/region");
+    ///
+    /// for structure_ref in chunk_provider.chunk_structure_refs(4, 2).unwrap() {
+    ///     println!("{} starting in chunk {:?}", structure_ref.name, structure_ref.start);
+    /// }
+    /// ```
+    pub fn chunk_structure_refs(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+    ) -> Result<Vec<StructureRef>, ChunkLoadError> {
+        let chunk_compound_tag = self.load_chunk(chunk_x, chunk_z)?;
+
+        Ok(structure_refs(&chunk_compound_tag, chunk_x, chunk_z))
+    }
+}
+
+/// Generated structure found by `StructureIndex::find_structures`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IndexedStructure {
+    /// Structure name as stored in the chunks.
+    pub name: String,
+    /// Chunk holding the start of the structure.
+    pub start: (i32, i32),
+    /// Scanned chunks touched by the structure, sorted.
+    pub chunks: Vec<(i32, i32)>,
+}
+
+/// Chunks touched by the structures of a name, by start chunk.
+type StructureStarts = BTreeMap<(i32, i32), BTreeSet<(i32, i32)>>;
+
+/// Generated structures of a world with the chunks they touch.
+///
+/// Deleting a chunk holding a structure start breaks the structure in every
+/// other chunk it touches, so pruning tools should keep the chunks of
+/// `starts_selection`.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{ChunkSelection, FolderChunkProvider, StructureIndex};
+///
+/// let mut chunk_provider = FolderChunkProvider::new("test/region");
+/// let mut index = StructureIndex::new();
+/// index.scan(&mut chunk_provider, &ChunkSelection::All).unwrap();
+///
+/// let mineshafts = index.find_structures("minecraft:mineshaft");
+/// assert!(mineshafts.iter().any(|mineshaft| mineshaft.start == (3, 2)));
+///
+/// // Chunks that may be pruned.
+/// let prunable = ChunkSelection::All.difference(index.starts_selection());
+/// assert!(!prunable.contains(3, 2));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StructureIndex {
+    /// Chunks touched by each structure, by name and start chunk.
+    structures: BTreeMap<String, StructureStarts>,
+}
+
+impl StructureIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the structures of every selected chunk to the index.
+    pub fn scan<P: ChunkReader + ?Sized>(
+        &mut self,
+        provider: &mut P,
+        selection: &ChunkSelection,
+    ) -> Result<(), ChunkLoadError> {
+        for (chunk_x, chunk_z) in provider.list_chunks_in(selection)? {
+            let chunk_compound_tag = provider.load_chunk(chunk_x, chunk_z)?;
+            self.add_chunk(chunk_x, chunk_z, &chunk_compound_tag);
+        }
+
+        Ok(())
+    }
+
+    /// Adds the structures of the chunk to the index.
+    pub fn add_chunk(&mut self, chunk_x: i32, chunk_z: i32, chunk_compound_tag: &CompoundTag) {
+        for structure_ref in structure_refs(chunk_compound_tag, chunk_x, chunk_z) {
+            self.structures
+                .entry(structure_ref.name)
+                .or_default()
+                .entry(structure_ref.start)
+                .or_default()
+                .insert((chunk_x, chunk_z));
+        }
+    }
+
+    /// Indexed structure names, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.structures.keys().map(String::as_str)
+    }
+
+    /// Structures with the given name, sorted by start chunk.
+    ///
+    /// Names are compared ignoring case and the `minecraft:` namespace, so
+    /// `minecraft:mineshaft` also finds the `Mineshaft` of 1.13 chunks.
+    /// Since 1.18 structures have variants with their own names, such as
+    /// `minecraft:village_plains`.
+    pub fn find_structures(&self, name: &str) -> Vec<IndexedStructure> {
+        let name = normalize_name(name);
+
+        self.structures
+            .iter()
+            .filter(|(indexed_name, _)| normalize_name(indexed_name) == name)
+            .flat_map(|(indexed_name, starts)| {
+                starts.iter().map(move |(&start, chunks)| IndexedStructure {
+                    name: indexed_name.clone(),
+                    start,
+                    chunks: chunks.iter().copied().collect(),
+                })
+            })
+            .collect()
+    }
+
+    /// Whether a structure starts in the chunk.
+    pub fn is_start(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        self.structures
+            .values()
+            .any(|starts| starts.contains_key(&(chunk_x, chunk_z)))
+    }
+
+    /// Selection of the chunks holding a structure start.
+    pub fn starts_selection(&self) -> ChunkSelection<'_> {
+        ChunkSelection::predicate(move |chunk_x, chunk_z| self.is_start(chunk_x, chunk_z))
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.strip_prefix("minecraft:")
+        .unwrap_or(name)
+        .to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structure_refs() {
+        let mut structures = CompoundTag::new();
+        let mut references = CompoundTag::new();
+        references.insert_i64_vec("minecraft:village_plains", vec![(-2 << 32) | 0xFFFF_FFFD]);
+        structures.insert_compound_tag("References", references);
+        let mut starts = CompoundTag::new();
+        let mut start = CompoundTag::new();
+        start.insert_str("id", "minecraft:stronghold");
+        starts.insert_compound_tag("minecraft:stronghold", start);
+        structures.insert_compound_tag("starts", starts);
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_compound_tag("structures", structures);
+
+        assert_eq!(
+            structure_refs(&chunk_compound_tag, 7, 8),
+            vec![
+                StructureRef {
+                    name: "minecraft:stronghold".to_string(),
+                    start: (7, 8),
+                },
+                StructureRef {
+                    name: "minecraft:village_plains".to_string(),
+                    start: (-3, -2),
+                },
+            ]
+        );
+
+        let mut index = StructureIndex::new();
+        index.add_chunk(7, 8, &chunk_compound_tag);
+        index.add_chunk(-3, -2, &chunk_compound_tag);
+        assert!(index.is_start(7, 8));
+        assert!(index.is_start(-3, -2));
+        assert!(!index.is_start(0, 0));
+
+        let villages = index.find_structures("Village_Plains");
+        assert_eq!(villages.len(), 1);
+        assert_eq!(villages[0].start, (-3, -2));
+        assert_eq!(villages[0].chunks, vec![(-3, -2), (7, 8)]);
+
+        let chunk_provider = FolderChunkProvider::new("test/region");
+        let refs = chunk_provider.chunk_structure_refs(3, 2).unwrap();
+        assert!(refs.contains(&StructureRef {
+            name: "Mineshaft".to_string(),
+            start: (3, 2),
+        }));
+    }
+}
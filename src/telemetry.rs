@@ -0,0 +1,122 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// I/O counters of a provider since it was created.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::FolderChunkProvider;
+///
+/// let chunk_provider = FolderChunkProvider::new("test/region");
+/// chunk_provider.load_chunk(4, 2).unwrap();
+/// chunk_provider.load_chunk(5, 2).unwrap();
+///
+/// let telemetry = chunk_provider.telemetry();
+/// assert_eq!(telemetry.chunk_reads, 2);
+/// // Region files are not kept open by default.
+/// assert_eq!(telemetry.region_opens, 2);
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Telemetry {
+    /// Region files opened, or decompressed for compressed region files.
+    pub region_opens: u64,
+    /// Region headers read, including the ones read to open a region.
+    pub header_reads: u64,
+    /// Chunks loaded, decoded or raw.
+    pub chunk_reads: u64,
+    /// Chunks saved.
+    pub chunk_writes: u64,
+    /// Bytes produced by decompressing chunks and compressed region files.
+    pub bytes_decompressed: u64,
+    /// Chunk writes redone from the journal left by a crash.
+    pub retries: u64,
+}
+
+/// Counters shared by the threads using a provider.
+#[derive(Default)]
+pub(crate) struct TelemetryCounters {
+    region_opens: AtomicU64,
+    header_reads: AtomicU64,
+    chunk_reads: AtomicU64,
+    chunk_writes: AtomicU64,
+    bytes_decompressed: AtomicU64,
+    retries: AtomicU64,
+}
+
+impl TelemetryCounters {
+    pub(crate) fn region_opened(&self) {
+        self.region_opens.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn header_read(&self) {
+        self.header_reads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn chunk_read(&self) {
+        self.chunk_reads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn chunk_written(&self) {
+        self.chunk_writes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn decompressed(&self, bytes: u64) {
+        self.bytes_decompressed.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn retried(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> Telemetry {
+        Telemetry {
+            region_opens: self.region_opens.load(Ordering::Relaxed),
+            header_reads: self.header_reads.load(Ordering::Relaxed),
+            chunk_reads: self.chunk_reads.load(Ordering::Relaxed),
+            chunk_writes: self.chunk_writes.load(Ordering::Relaxed),
+            bytes_decompressed: self.bytes_decompressed.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AnvilOptions, FolderChunkProvider};
+    use flate2::read::ZlibDecoder;
+    use nbt::CompoundTag;
+    use std::fs;
+    use std::io::Read;
+
+    #[test]
+    fn test_provider_telemetry() {
+        let folder = tempfile::tempdir().unwrap();
+        fs::copy("test/region/r.0.0.mca", folder.path().join("r.0.0.mca")).unwrap();
+
+        let options = AnvilOptions::new().max_open_regions(4);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        chunk_provider.load_chunk(4, 2).unwrap();
+        chunk_provider.load_chunk_raw(5, 2).unwrap();
+        chunk_provider
+            .save_chunk(40, 2, CompoundTag::new())
+            .unwrap();
+        assert_eq!(chunk_provider.list_chunks().unwrap().len(), 278);
+
+        let telemetry = chunk_provider.telemetry();
+        assert_eq!(telemetry.region_opens, 2);
+        assert_eq!(telemetry.header_reads, 4);
+        assert_eq!(telemetry.chunk_reads, 2);
+        assert_eq!(telemetry.chunk_writes, 1);
+        assert_eq!(telemetry.retries, 0);
+
+        // Only the decoded chunk counts, with its decompressed length.
+        let raw_chunk = chunk_provider.load_chunk_raw(4, 2).unwrap();
+        let mut decompressed = Vec::new();
+        ZlibDecoder::new(raw_chunk.data())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+
+        assert_ne!(decompressed.len(), raw_chunk.data().len());
+        assert_eq!(telemetry.bytes_decompressed, decompressed.len() as u64);
+    }
+}
@@ -0,0 +1,244 @@
+//! Helpers to generate fixture regions and chunks for tests.
+//!
+//! Generated regions are returned as the bytes of a region file, they can be
+//! written to disk or opened with `AnvilRegion::new(Cursor::new(bytes))`.
+//! Chunks are stamped with `FIXTURE_TIMESTAMP`, so the same input always
+//! gives the same bytes.
+//!
+//! # Example
+//!
+//! ```
+//! use anvil_region::testutil::{region_with_bad_length, region_with_chunks};
+//! use anvil_region::{AnvilRegion, ChunkLoadError};
+//! use std::io::Cursor;
+//!
+//! let mut region = AnvilRegion::new(Cursor::new(region_with_chunks(&[(4, 2)]))).unwrap();
+//! assert!(region.read_chunk(4, 2).is_ok());
+//!
+//! let mut region = AnvilRegion::new(Cursor::new(region_with_bad_length((4, 2), 0))).unwrap();
+//! assert!(matches!(region.read_chunk(4, 2), Err(ChunkLoadError::EmptyChunkData)));
+//! ```
+use crate::{
+    anvil_region, AnvilRegion, ChunkSaveError, FolderChunkProvider, REGION_SECTOR_BYTES_LENGTH,
+};
+use byteorder::{BigEndian, ByteOrder};
+use nbt::CompoundTag;
+use std::io::Cursor;
+
+/// Header timestamp of generated chunks.
+pub const FIXTURE_TIMESTAMP: u32 = 1_600_000_000;
+
+/// Minimal chunk with only the `xPos` and `zPos` tags.
+pub fn chunk_tag(chunk_x: i32, chunk_z: i32) -> CompoundTag {
+    let mut level_compound_tag = CompoundTag::new();
+    level_compound_tag.insert_i32("xPos", chunk_x);
+    level_compound_tag.insert_i32("zPos", chunk_z);
+
+    let mut chunk_compound_tag = CompoundTag::new();
+    chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+
+    chunk_compound_tag
+}
+
+/// Chunk with only a `value` tag, to tell apart the versions of a chunk.
+pub fn value_chunk_tag(value: i32) -> CompoundTag {
+    let mut chunk_compound_tag = CompoundTag::new();
+    chunk_compound_tag.insert_i32("value", value);
+
+    chunk_compound_tag
+}
+
+/// Chunk filled with pseudo-random tags, the same seed always gives the same
+/// chunk.
+///
+/// The chunk has the layout of a pre-1.18 chunk, with coordinates, a
+/// heightmap and a few sections of block data, but the values are
+/// meaningless to the game.
+pub fn random_chunk_tag(seed: u64) -> CompoundTag {
+    let mut random = SplitMix64(seed);
+
+    let sections: Vec<CompoundTag> = (0..random.below(8) + 1)
+        .map(|y| {
+            let mut section_compound_tag = CompoundTag::new();
+            section_compound_tag.insert_i8("Y", y as i8);
+            section_compound_tag.insert_i8_vec(
+                "BlockLight",
+                (0..2048).map(|_| random.next() as i8).collect(),
+            );
+            section_compound_tag.insert_i64_vec(
+                "BlockStates",
+                (0..256).map(|_| random.next() as i64).collect(),
+            );
+
+            section_compound_tag
+        })
+        .collect();
+
+    let mut level_compound_tag = CompoundTag::new();
+    level_compound_tag.insert_i32("xPos", random.below(64) as i32 - 32);
+    level_compound_tag.insert_i32("zPos", random.below(64) as i32 - 32);
+    level_compound_tag.insert_i64("LastUpdate", random.below(1 << 32) as i64);
+    level_compound_tag.insert_i64("InhabitedTime", random.below(1 << 20) as i64);
+    level_compound_tag.insert_str("Status", "full");
+    level_compound_tag.insert_i32_vec(
+        "HeightMap",
+        (0..256).map(|_| random.below(256) as i32).collect(),
+    );
+    level_compound_tag.insert_compound_tag_vec("Sections", sections);
+
+    let mut chunk_compound_tag = CompoundTag::new();
+    chunk_compound_tag.insert_i32("DataVersion", 1343);
+    chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+
+    chunk_compound_tag
+}
+
+/// Region file with a `chunk_tag` at each of the given coordinates, as if
+/// the region was `r.0.0.mca`.
+pub fn region_with_chunks(chunks: &[(u8, u8)]) -> Vec<u8> {
+    let mut region = AnvilRegion::new(Cursor::new(Vec::new())).unwrap();
+
+    for &(chunk_x, chunk_z) in chunks {
+        let chunk_compound_tag = chunk_tag(chunk_x as i32, chunk_z as i32);
+
+        region
+            .write_chunk_with_timestamp(chunk_x, chunk_z, chunk_compound_tag, FIXTURE_TIMESTAMP)
+            .unwrap();
+    }
+
+    region.into_inner().into_inner()
+}
+
+/// Region file with a single chunk whose data declares the given length
+/// instead of its real length.
+///
+/// A length of 0 or larger than the allocated sectors can't be read, see
+/// `ChunkLoadError::EmptyChunkData` and
+/// `ChunkLoadError::LengthExceedsMaximum`.
+pub fn region_with_bad_length(chunk: (u8, u8), length: u32) -> Vec<u8> {
+    let mut bytes = region_with_chunks(&[chunk]);
+    let offset = sector_offset(&bytes, chunk);
+
+    BigEndian::write_u32(&mut bytes[offset..offset + 4], length);
+
+    bytes
+}
+
+/// Region file with two chunks whose header entries point at the same
+/// sectors, so both read as the first chunk.
+pub fn region_with_overlapping_sectors(first: (u8, u8), second: (u8, u8)) -> Vec<u8> {
+    let mut bytes = region_with_chunks(&[first, second]);
+    let first_index = anvil_region::metadata_index(first.0, first.1) * 4;
+    let second_index = anvil_region::metadata_index(second.0, second.1) * 4;
+
+    let location = BigEndian::read_u32(&bytes[first_index..first_index + 4]);
+    BigEndian::write_u32(&mut bytes[second_index..second_index + 4], location);
+
+    bytes
+}
+
+/// Saves a `chunk_tag` at each of the given coordinates in the region
+/// folder, creating the region files as needed.
+pub fn folder_with_chunks(folder: &str, chunks: &[(i32, i32)]) -> Result<(), ChunkSaveError> {
+    let chunk_provider = FolderChunkProvider::new(folder);
+
+    for &(chunk_x, chunk_z) in chunks {
+        chunk_provider.save_chunk_with_timestamp(
+            chunk_x,
+            chunk_z,
+            chunk_tag(chunk_x, chunk_z),
+            FIXTURE_TIMESTAMP,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Offset in bytes of the data of the chunk, read from the header.
+fn sector_offset(bytes: &[u8], (chunk_x, chunk_z): (u8, u8)) -> usize {
+    let index = anvil_region::metadata_index(chunk_x, chunk_z) * 4;
+    let sector_index = BigEndian::read_u32(&bytes[index..index + 4]) >> 8;
+
+    sector_index as usize * REGION_SECTOR_BYTES_LENGTH as usize
+}
+
+/// Small deterministic random number generator, fixtures don't need more.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        z ^ (z >> 31)
+    }
+
+    /// Random number below `bound`.
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChunkLoadError;
+
+    #[test]
+    fn test_random_chunk_tag_is_deterministic() {
+        let a = random_chunk_tag(7);
+        let b = random_chunk_tag(7);
+        let c = random_chunk_tag(8);
+
+        let level = |chunk_compound_tag: &CompoundTag| {
+            let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+            level_compound_tag.get_i32_vec("HeightMap").unwrap().clone()
+        };
+
+        assert_eq!(level(&a), level(&b));
+        assert_ne!(level(&a), level(&c));
+    }
+
+    #[test]
+    fn test_region_with_overlapping_sectors() {
+        let bytes = region_with_overlapping_sectors((0, 0), (1, 0));
+        let mut region = AnvilRegion::new(Cursor::new(bytes)).unwrap();
+
+        assert_eq!(
+            region.get_metadata(0, 0).sector_index(),
+            region.get_metadata(1, 0).sector_index()
+        );
+
+        let chunk_compound_tag = region.read_chunk(1, 0).unwrap();
+        let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_region_with_bad_length() {
+        let bytes = region_with_bad_length((3, 4), 5000);
+        let mut region = AnvilRegion::new(Cursor::new(bytes)).unwrap();
+
+        match region.read_chunk(3, 4) {
+            Err(ChunkLoadError::LengthExceedsMaximum { length: 5000, .. }) => {}
+            e => panic!("Expected `LengthExceedsMaximum` but got `{:?}`", e),
+        }
+    }
+
+    #[test]
+    fn test_folder_with_chunks() {
+        let folder = tempfile::tempdir().unwrap();
+        let folder = folder.path().to_str().unwrap();
+        folder_with_chunks(folder, &[(0, 0), (-1, 40)]).unwrap();
+
+        let chunk_provider = FolderChunkProvider::new(folder);
+        let mut chunks = chunk_provider.list_chunks().unwrap();
+        chunks.sort_unstable();
+
+        assert_eq!(chunks, vec![(-1, 40), (0, 0)]);
+        assert!(chunk_provider.load_chunk(-1, 40).is_ok());
+    }
+}
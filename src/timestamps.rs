@@ -0,0 +1,454 @@
+use crate::quarantine::is_corrupted_chunk_error;
+use crate::{
+    AnvilRegion, AnvilRegionHeader, ChunkLoadError, ChunkPayload, ChunkSaveError, ChunkSelection,
+    FolderChunkProvider, WorldEditError, REGION_CHUNKS, REGION_HEADER_BYTES_LENGTH,
+    REGION_SECTOR_BYTES_LENGTH,
+};
+use std::collections::HashMap;
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Change applied to the header timestamps by `edit_timestamps`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TimestampEdit {
+    /// Sets the timestamps to the given time, in seconds since the Unix
+    /// epoch.
+    Set(u32),
+    /// Sets the timestamps to zero, as if the chunks were never modified.
+    Clear,
+    /// Moves the timestamps by the given amount of seconds, saturating at
+    /// zero and `u32::MAX`.
+    Offset(i64),
+}
+
+impl TimestampEdit {
+    /// New timestamp of a chunk whose timestamp is `original`.
+    pub fn apply(self, original: u32) -> u32 {
+        match self {
+            TimestampEdit::Set(timestamp) => timestamp,
+            TimestampEdit::Clear => 0,
+            TimestampEdit::Offset(seconds) => {
+                (i64::from(original) + seconds).clamp(0, i64::from(u32::MAX)) as u32
+            }
+        }
+    }
+}
+
+/// Game tick reached at a known time, to convert the `LastUpdate` of the
+/// chunks to header timestamps. The `Time` and `LastPlayed` tags of
+/// `level.dat` are such a pair.
+///
+/// The game time only advances while the world is running, so the converted
+/// timestamps are estimates, but they keep the order of the updates.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TickAnchor {
+    /// Game tick.
+    pub tick: i64,
+    /// Time of the tick, in seconds since the Unix epoch.
+    pub timestamp: u32,
+}
+
+impl TickAnchor {
+    /// Ticks per second of a running world.
+    pub const TICKS_PER_SECOND: i64 = 20;
+
+    /// Estimated time of the given tick, in seconds since the Unix epoch.
+    pub fn timestamp(self, tick: i64) -> u32 {
+        let seconds = (self.tick.saturating_sub(tick)) / Self::TICKS_PER_SECOND;
+
+        i64::from(self.timestamp)
+            .saturating_sub(seconds)
+            .clamp(0, i64::from(u32::MAX)) as u32
+    }
+}
+
+impl<F: Seek + Read + Write> AnvilRegion<F> {
+    /// Applies the edit to the timestamps of the chunks for which `filter`
+    /// returns true, given their coordinates inside the region. Returns the
+    /// amount of timestamps changed.
+    ///
+    /// Only the timestamp table of the header is written, in a single write,
+    /// chunk data and offsets are left untouched.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::{AnvilRegion, TimestampEdit};
+    /// use std::io::Cursor;
+    ///
+    /// let bytes = std::fs::read("test/region/r.0.0.mca").unwrap();
+    /// let mut region = AnvilRegion::new(Cursor::new(bytes)).unwrap();
+    ///
+    /// let changed = region
+    ///     .edit_timestamps(|chunk_x, _| chunk_x < 4, TimestampEdit::Clear)
+    ///     .unwrap();
+    ///
+    /// assert!(changed > 0);
+    /// assert_eq!(region.get_metadata(0, 8).last_modified_timestamp(), 0);
+    /// ```
+    pub fn edit_timestamps<S>(
+        &mut self,
+        mut filter: S,
+        edit: TimestampEdit,
+    ) -> Result<usize, io::Error>
+    where
+        S: FnMut(u8, u8) -> bool,
+    {
+        self.map_timestamps(|chunk_x, chunk_z, timestamp| {
+            if filter(chunk_x, chunk_z) {
+                edit.apply(timestamp)
+            } else {
+                timestamp
+            }
+        })
+    }
+
+    /// Replaces the timestamp of every stored chunk with the one returned by
+    /// `map`, writing the timestamp table if any changed.
+    fn map_timestamps<M>(&mut self, mut map: M) -> Result<usize, io::Error>
+    where
+        M: FnMut(u8, u8, u32) -> u32,
+    {
+        let mut changed = 0;
+
+        for (index, metadata) in self.chunks_metadata.iter_mut().enumerate() {
+            let (chunk_x, chunk_z) = ((index % 32) as u8, (index / 32) as u8);
+
+            if metadata.is_empty() {
+                continue;
+            }
+
+            let timestamp = map(chunk_x, chunk_z, metadata.last_modified_timestamp);
+
+            if timestamp != metadata.last_modified_timestamp {
+                metadata.last_modified_timestamp = timestamp;
+                changed += 1;
+            }
+        }
+
+        if changed > 0 {
+            let mut timestamps = Vec::with_capacity(REGION_CHUNKS * 4);
+
+            for metadata in self.chunks_metadata.iter() {
+                timestamps.extend_from_slice(&metadata.last_modified_timestamp.to_be_bytes());
+            }
+
+            self.file
+                .seek(SeekFrom::Start(REGION_SECTOR_BYTES_LENGTH as u64))?;
+            self.file.write_all(&timestamps)?;
+        }
+
+        Ok(changed)
+    }
+}
+
+impl<P: ChunkPayload> FolderChunkProvider<P> {
+    /// Applies the edit to the header timestamps of the chunks in the
+    /// selection, for example to reset them after a migration. Returns the
+    /// amount of timestamps changed.
+    ///
+    /// Only the timestamp tables of the region files are written, see
+    /// `AnvilRegion::edit_timestamps`. In dry-run mode the region files are
+    /// only read and the changes are counted in the report.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use anvil_region::{ChunkSelection, FolderChunkProvider, TimestampEdit};
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("world/region");
+    ///
+    /// // Make every chunk one day older.
+    /// chunk_provider
+    ///     .edit_timestamps(&ChunkSelection::All, TimestampEdit::Offset(-86400))
+    ///     .unwrap();
+    /// ```
+    pub fn edit_timestamps(
+        &self,
+        selection: &ChunkSelection,
+        edit: TimestampEdit,
+    ) -> Result<usize, ChunkSaveError> {
+        if self.options.read_only {
+            return Err(ChunkSaveError::ReadOnly);
+        }
+
+        let mut changed = 0;
+
+        for (region_x, region_z) in self.find_all_region_mca()? {
+            if !selection.may_contain_region(region_x, region_z) {
+                continue;
+            }
+
+            let filter = |region_chunk_x: u8, region_chunk_z: u8| {
+                let chunk_x = (region_x * 32) + i32::from(region_chunk_x);
+                let chunk_z = (region_z * 32) + i32::from(region_chunk_z);
+
+                selection.contains(chunk_x, chunk_z)
+            };
+
+            if self.options.dry_run {
+                if let Some(mut region) = self.dry_run_region(region_x, region_z)? {
+                    let header = AnvilRegionHeader::from_reader(&mut region)?;
+                    let region_changed = header
+                        .chunks()
+                        .filter(|&((region_chunk_x, region_chunk_z), metadata)| {
+                            let timestamp = metadata.last_modified_timestamp();
+
+                            filter(region_chunk_x, region_chunk_z)
+                                && edit.apply(timestamp) != timestamp
+                        })
+                        .count();
+
+                    self.dry_run_report.lock().unwrap().timestamps_changed += region_changed as u64;
+                    changed += region_changed;
+                }
+
+                continue;
+            }
+
+            changed += self.with_region(region_x, region_z, |region| {
+                let region_changed = region.edit_timestamps(filter, edit)?;
+                self.sync_after_write(region)?;
+
+                Ok::<_, ChunkSaveError>(region_changed)
+            })?;
+        }
+
+        Ok(changed)
+    }
+
+    /// Sets the zero header timestamps of the chunks in the selection from
+    /// the `LastUpdate` of the chunks, converted with the anchor. Returns the
+    /// amount of timestamps changed.
+    ///
+    /// Some editors write zero timestamps, which makes the chunks look
+    /// unmodified to incremental backups. Chunks with a timestamp, without
+    /// `LastUpdate` or whose data is corrupted are left untouched. Only the
+    /// timestamp tables are written, like `edit_timestamps`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use anvil_region::{ChunkSelection, FolderChunkProvider, TickAnchor};
+    ///
+    /// let chunk_provider = FolderChunkProvider::new("world/region");
+    /// // `Time` and `LastPlayed` of level.dat.
+    /// let anchor = TickAnchor {
+    ///     tick: 1_728_000,
+    ///     timestamp: 1_600_000_000,
+    /// };
+    ///
+    /// chunk_provider
+    ///     .timestamps_from_last_update(&ChunkSelection::All, anchor)
+    ///     .unwrap();
+    /// ```
+    pub fn timestamps_from_last_update(
+        &self,
+        selection: &ChunkSelection,
+        anchor: TickAnchor,
+    ) -> Result<usize, WorldEditError> {
+        if self.options.read_only {
+            return Err(ChunkSaveError::ReadOnly.into());
+        }
+
+        let mut changed = 0;
+
+        for (region_x, region_z) in self.find_all_region_mca()? {
+            if !selection.may_contain_region(region_x, region_z) {
+                continue;
+            }
+
+            let timestamps = self.last_update_timestamps(region_x, region_z, selection, anchor)?;
+
+            if timestamps.is_empty() {
+                continue;
+            }
+
+            if self.options.dry_run {
+                self.dry_run_report.lock().unwrap().timestamps_changed += timestamps.len() as u64;
+                changed += timestamps.len();
+                continue;
+            }
+
+            changed += self.with_region(region_x, region_z, |region| {
+                let region_changed = region.map_timestamps(|chunk_x, chunk_z, timestamp| {
+                    match timestamps.get(&(chunk_x, chunk_z)) {
+                        Some(&new_timestamp) if timestamp == 0 => new_timestamp,
+                        _ => timestamp,
+                    }
+                })?;
+                self.sync_after_write(region)?;
+
+                Ok::<_, ChunkSaveError>(region_changed)
+            })?;
+        }
+
+        Ok(changed)
+    }
+
+    /// New timestamps of the selected chunks of the region with a zero
+    /// timestamp, by coordinates inside the region.
+    fn last_update_timestamps(
+        &self,
+        region_x: i32,
+        region_z: i32,
+        selection: &ChunkSelection,
+        anchor: TickAnchor,
+    ) -> Result<HashMap<(u8, u8), u32>, ChunkLoadError> {
+        let mut region = self.region_reader(region_x, region_z)?;
+
+        if region.seek(SeekFrom::End(0))? < REGION_HEADER_BYTES_LENGTH {
+            return Ok(HashMap::new());
+        }
+
+        region.seek(SeekFrom::Start(0))?;
+        let header = AnvilRegionHeader::from_reader(&mut region)?;
+        let mut timestamps = HashMap::new();
+
+        for ((region_chunk_x, region_chunk_z), metadata) in header.chunks() {
+            let chunk_x = (region_x * 32) + i32::from(region_chunk_x);
+            let chunk_z = (region_z * 32) + i32::from(region_chunk_z);
+
+            if metadata.last_modified_timestamp() != 0 || !selection.contains(chunk_x, chunk_z) {
+                continue;
+            }
+
+            let last_update = self
+                .load_chunk_raw(chunk_x, chunk_z)
+                .and_then(|raw_chunk| raw_chunk.last_update());
+
+            match last_update {
+                Ok(Some(last_update)) => {
+                    let timestamp = anchor.timestamp(last_update);
+
+                    if timestamp != 0 {
+                        timestamps.insert((region_chunk_x, region_chunk_z), timestamp);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) if is_corrupted_chunk_error(&e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(timestamps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilOptions, ChunkReader};
+    use nbt::CompoundTag;
+    use std::fs;
+
+    #[test]
+    fn test_edit_timestamps() {
+        assert_eq!(TimestampEdit::Offset(-10).apply(5), 0);
+        assert_eq!(TimestampEdit::Offset(10).apply(u32::MAX - 5), u32::MAX);
+        assert_eq!(TimestampEdit::Offset(-10).apply(15), 5);
+
+        let folder = tempfile::tempdir().unwrap();
+        let region_path = folder.path().join("r.0.0.mca");
+        fs::copy("test/region/r.0.0.mca", &region_path).unwrap();
+        let original = fs::read(&region_path).unwrap();
+        let mut chunk_provider = FolderChunkProvider::new(folder.path());
+        chunk_provider
+            .save_chunk_with_timestamp(-1, -1, CompoundTag::new(), 100)
+            .unwrap();
+
+        let selection = ChunkSelection::rect((-1, -1), (3, 31));
+        let changed = chunk_provider
+            .edit_timestamps(&selection, TimestampEdit::Set(1234))
+            .unwrap();
+        let metadata = chunk_provider.load_chunk_metadata(0, 8).unwrap();
+        assert_eq!(metadata.last_modified_timestamp(), 1234);
+        let metadata = chunk_provider.load_chunk_metadata(-1, -1).unwrap();
+        assert_eq!(metadata.last_modified_timestamp(), 1234);
+        assert_eq!(
+            chunk_provider.load_chunk_metadata(15, 3).unwrap(),
+            AnvilRegion::file("test/region/r.0.0.mca")
+                .unwrap()
+                .get_metadata(15, 3)
+        );
+
+        // Only the timestamp table changed.
+        let edited = fs::read(&region_path).unwrap();
+        assert_eq!(edited.len(), original.len());
+        assert_eq!(edited[..4096], original[..4096]);
+        assert_eq!(edited[8192..], original[8192..]);
+
+        // Unchanged timestamps are not counted.
+        let options = AnvilOptions::new().dry_run(true);
+        let dry_run_provider = FolderChunkProvider::with_options(folder.path(), options);
+        assert_eq!(
+            dry_run_provider
+                .edit_timestamps(&selection, TimestampEdit::Set(1234))
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            dry_run_provider
+                .edit_timestamps(&selection, TimestampEdit::Clear)
+                .unwrap(),
+            changed
+        );
+        assert_eq!(
+            dry_run_provider.dry_run_report().timestamps_changed,
+            changed as u64
+        );
+        assert_eq!(fs::read(&region_path).unwrap(), edited);
+    }
+
+    #[test]
+    fn test_timestamps_from_last_update() {
+        let anchor = TickAnchor {
+            tick: 2000,
+            timestamp: 1000,
+        };
+        assert_eq!(anchor.timestamp(2000), 1000);
+        assert_eq!(anchor.timestamp(1000), 950);
+        assert_eq!(anchor.timestamp(-100_000), 0);
+
+        let folder = tempfile::tempdir().unwrap();
+        fs::copy("test/region/r.0.0.mca", folder.path().join("r.0.0.mca")).unwrap();
+        let mut chunk_provider = FolderChunkProvider::new(folder.path());
+        let zeroed = chunk_provider
+            .edit_timestamps(
+                &ChunkSelection::rect((0, 0), (15, 31)),
+                TimestampEdit::Clear,
+            )
+            .unwrap();
+
+        let anchor = TickAnchor {
+            tick: 1 << 32,
+            timestamp: 1_600_000_000,
+        };
+        let changed = chunk_provider
+            .timestamps_from_last_update(&ChunkSelection::All, anchor)
+            .unwrap();
+        assert!(changed > 0 && changed <= zeroed);
+
+        for chunk_z in 0..3 {
+            let raw_chunk = chunk_provider.load_chunk_raw(15, chunk_z).unwrap();
+            let metadata = chunk_provider.load_chunk_metadata(15, chunk_z).unwrap();
+            let expected = anchor.timestamp(raw_chunk.last_update().unwrap().unwrap());
+            assert_eq!(metadata.last_modified_timestamp(), expected);
+        }
+        // Chunks with a timestamp keep it.
+        assert_eq!(
+            chunk_provider.load_chunk_metadata(16, 0).unwrap(),
+            AnvilRegion::file("test/region/r.0.0.mca")
+                .unwrap()
+                .get_metadata(16, 0)
+        );
+
+        // Nothing left to repair.
+        assert_eq!(
+            chunk_provider
+                .timestamps_from_last_update(&ChunkSelection::All, anchor)
+                .unwrap(),
+            0
+        );
+    }
+}
@@ -0,0 +1,394 @@
+use crate::{
+    sectors_required, AnvilChunkMetadata, AnvilRegion, ChunkPayload, Compression,
+    REGION_SECTOR_BYTES_LENGTH,
+};
+use byteorder::{BigEndian, ByteOrder};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use nbt::CompoundTag;
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// NBT id of the compound tag every chunk starts with.
+const COMPOUND_TAG_ID: u8 = 10;
+
+/// Coordinates stored in a chunk found in the free sectors and its amount
+/// of sectors.
+type ChunkVersionAt = ((i32, i32), u8);
+
+/// Sign that the write of a chunk didn't complete, found by
+/// `AnvilRegion::find_torn_chunks`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TornChunkIssue {
+    /// The chunk data length is zero, usually sectors that were never
+    /// written.
+    EmptyData,
+    /// The chunk data length is larger than the sectors allocated to the
+    /// chunk. Chunks may be allocated more sectors than they need, as older
+    /// versions of the game did.
+    LengthMismatch { length: u32, sectors: u8 },
+    /// The chunk data doesn't start like a compressed chunk.
+    UndecodableData,
+}
+
+/// Chunk of a region whose last write evidently didn't complete.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TornChunk {
+    pub chunk_x: u8,
+    pub chunk_z: u8,
+    pub issue: TornChunkIssue,
+}
+
+/// Whether the first bytes of the data decompress to the start of a
+/// compound tag. Unknown compression schemes are not checked.
+fn starts_like_chunk(compression_scheme: u8, mut data: &[u8]) -> bool {
+    let mut first_byte = [0; 1];
+    let read = match Compression::from_id(compression_scheme) {
+        Some(Compression::Gzip) => GzDecoder::new(data).read_exact(&mut first_byte),
+        Some(Compression::Zlib) => ZlibDecoder::new(data).read_exact(&mut first_byte),
+        Some(Compression::Uncompressed) => data.read_exact(&mut first_byte),
+        None => return true,
+    };
+
+    read.is_ok() && first_byte[0] == COMPOUND_TAG_ID
+}
+
+impl<F: Seek + Read + Write> AnvilRegion<F> {
+    /// Finds the chunks whose write evidently didn't complete, for example
+    /// because of a power loss, by checking their length against their
+    /// sectors and decompressing their first bytes.
+    ///
+    /// Chunks extending past the end of the file are left to
+    /// `AnvilRegion::verify`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::AnvilRegion;
+    /// use nbt::CompoundTag;
+    /// use std::io::Cursor;
+    ///
+    /// let mut region = AnvilRegion::new(Cursor::new(Vec::new())).unwrap();
+    /// region.write_chunk(0, 0, CompoundTag::new()).unwrap();
+    ///
+    /// assert!(region.find_torn_chunks().unwrap().is_empty());
+    /// ```
+    pub fn find_torn_chunks(&mut self) -> Result<Vec<TornChunk>, io::Error> {
+        let total_sectors = self.sector_count()? as u64;
+        let mut torn_chunks = Vec::new();
+
+        for index in 0..self.chunks_metadata.len() {
+            let metadata = self.chunks_metadata[index];
+            let sector_end = metadata.sector_index as u64 + metadata.sectors as u64;
+
+            if metadata.is_empty() || sector_end > total_sectors {
+                continue;
+            }
+
+            if let Some(issue) = self.check_chunk_data(metadata)? {
+                torn_chunks.push(TornChunk {
+                    chunk_x: (index % 32) as u8,
+                    chunk_z: (index / 32) as u8,
+                    issue,
+                });
+            }
+        }
+
+        Ok(torn_chunks)
+    }
+
+    fn check_chunk_data(
+        &mut self,
+        metadata: AnvilChunkMetadata,
+    ) -> Result<Option<TornChunkIssue>, io::Error> {
+        let seek_offset = metadata.sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+        let mut sectors = vec![0; metadata.sectors as usize * REGION_SECTOR_BYTES_LENGTH as usize];
+        self.file.seek(SeekFrom::Start(seek_offset))?;
+        self.file.read_exact(&mut sectors)?;
+
+        let length = BigEndian::read_u32(&sectors[..4]);
+
+        if length == 0 {
+            return Ok(Some(TornChunkIssue::EmptyData));
+        }
+
+        if length > sectors.len() as u32 - 4 {
+            return Ok(Some(TornChunkIssue::LengthMismatch {
+                length,
+                sectors: metadata.sectors,
+            }));
+        }
+
+        if !starts_like_chunk(sectors[4], &sectors[5..length as usize + 4]) {
+            return Ok(Some(TornChunkIssue::UndecodableData));
+        }
+
+        Ok(None)
+    }
+
+    /// Looks for a previous version of the chunk in the free sectors and
+    /// makes the header point to it again, keeping the timestamp. Returns
+    /// `false` if no version is found, or if the chunk still decodes, in
+    /// which case it is left as is.
+    ///
+    /// Chunks moved to other sectors when they grow or shrink leave their
+    /// previous version behind until the sectors are reused. A version is
+    /// recognized by decoding it and comparing its `xPos` and `zPos` with
+    /// the coordinates of the chunk, the first one in file order is used.
+    pub fn restore_previous_chunk(&mut self, chunk_x: u8, chunk_z: u8) -> Result<bool, io::Error> {
+        if self.read_chunk(chunk_x, chunk_z).is_ok() {
+            return Ok(false);
+        }
+
+        self.build_used_sectors()?;
+        let total_sectors = self.sector_count()?;
+
+        for (start, length) in self.free_runs(total_sectors) {
+            for sector_index in start..start + length {
+                let sectors = start + length - sector_index;
+
+                let found = match self.chunk_version_at(sector_index, sectors)? {
+                    Some(((x_pos, z_pos), found))
+                        if x_pos.rem_euclid(32) == chunk_x as i32
+                            && z_pos.rem_euclid(32) == chunk_z as i32 =>
+                    {
+                        found
+                    }
+                    _ => continue,
+                };
+
+                let metadata = self.get_metadata(chunk_x, chunk_z);
+                self.release_sectors(metadata);
+
+                for i in 0..found {
+                    self.used_sectors
+                        .set(sector_index as usize + i as usize, true);
+                }
+
+                let restored =
+                    AnvilChunkMetadata::new(sector_index, found, metadata.last_modified_timestamp);
+                self.update_metadata(chunk_x, chunk_z, restored)?;
+
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Makes the empty header entries point to the chunks found in the free
+    /// sectors, such as chunks lost when the header is overwritten, and
+    /// returns their coordinates inside the region.
+    ///
+    /// Chunks are recognized by decoding them and reading their `xPos` and
+    /// `zPos`, the first one in file order is used for every entry. Their
+    /// timestamp is zero. Chunks deleted without `AnvilOptions::shrink_regions`
+    /// or `AnvilOptions::punch_holes` leave their data behind, so they are
+    /// recovered too.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::AnvilRegion;
+    /// use nbt::CompoundTag;
+    /// use std::io::Cursor;
+    ///
+    /// let mut chunk_compound_tag = CompoundTag::new();
+    /// chunk_compound_tag.insert_i32("xPos", 33);
+    /// chunk_compound_tag.insert_i32("zPos", 2);
+    ///
+    /// let mut region = AnvilRegion::new(Cursor::new(Vec::new())).unwrap();
+    /// region.write_chunk(1, 2, chunk_compound_tag).unwrap();
+    /// region.delete_chunk(1, 2).unwrap();
+    ///
+    /// assert_eq!(region.recover_orphan_chunks().unwrap(), vec![(1, 2)]);
+    /// assert!(region.read_chunk(1, 2).is_ok());
+    /// ```
+    pub fn recover_orphan_chunks(&mut self) -> Result<Vec<(u8, u8)>, io::Error> {
+        self.build_used_sectors()?;
+        let total_sectors = self.sector_count()?;
+        let mut recovered = Vec::new();
+
+        for (start, length) in self.free_runs(total_sectors) {
+            let mut sector_index = start;
+
+            while sector_index < start + length {
+                let free_sectors = start + length - sector_index;
+                let ((x_pos, z_pos), sectors) =
+                    match self.chunk_version_at(sector_index, free_sectors)? {
+                        Some(found) => found,
+                        None => {
+                            sector_index += 1;
+                            continue;
+                        }
+                    };
+                let (chunk_x, chunk_z) = (x_pos.rem_euclid(32) as u8, z_pos.rem_euclid(32) as u8);
+
+                if self.get_metadata(chunk_x, chunk_z).is_empty() {
+                    for i in 0..sectors {
+                        self.used_sectors
+                            .set(sector_index as usize + i as usize, true);
+                    }
+
+                    let metadata = AnvilChunkMetadata::new(sector_index, sectors, 0);
+                    self.update_metadata(chunk_x, chunk_z, metadata)?;
+                    recovered.push((chunk_x, chunk_z));
+                }
+
+                sector_index += sectors as u32;
+            }
+        }
+
+        Ok(recovered)
+    }
+
+    /// Coordinates stored in the chunk starting at the given free sector and
+    /// its amount of sectors, if one fits in the following free sectors.
+    fn chunk_version_at(
+        &mut self,
+        sector_index: u32,
+        free_sectors: u32,
+    ) -> Result<Option<ChunkVersionAt>, io::Error> {
+        let seek_offset = sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+        let mut chunk_header = [0; 5];
+        self.file.seek(SeekFrom::Start(seek_offset))?;
+        self.file.read_exact(&mut chunk_header)?;
+
+        let length = BigEndian::read_u32(&chunk_header[..4]);
+        let maximum_length = free_sectors as u64 * REGION_SECTOR_BYTES_LENGTH as u64 - 4;
+
+        if length < 2 || length as u64 > maximum_length {
+            return Ok(None);
+        }
+
+        let mut data = vec![0; length as usize - 1];
+        self.file.read_exact(&mut data)?;
+
+        let coordinates = CompoundTag::decode(chunk_header[4], &data)
+            .ok()
+            .map(|chunk_compound_tag| chunk_compound_tag.chunk_coordinates());
+
+        match coordinates {
+            Some((Some(x_pos), Some(z_pos))) => {
+                Ok(Some(((x_pos, z_pos), sectors_required(length + 4))))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilOptions, FolderChunkProvider};
+    use std::fs;
+    use std::io::Cursor;
+
+    fn chunk(chunk_x: i32, chunk_z: i32, padding: usize) -> CompoundTag {
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("xPos", chunk_x);
+        chunk_compound_tag.insert_i32("zPos", chunk_z);
+        chunk_compound_tag.insert_i8_vec("Padding", vec![7; padding]);
+
+        chunk_compound_tag
+    }
+
+    #[test]
+    fn test_find_and_restore_torn_chunks() {
+        let mut region = AnvilRegion::new(Cursor::new(Vec::new()))
+            .unwrap()
+            .with_compression(Compression::Uncompressed, 0);
+        region.write_chunk(1, 2, chunk(33, 2, 10)).unwrap();
+        region.write_chunk(3, 0, chunk(3, 0, 10)).unwrap();
+        // The previous version is left in the freed sector.
+        region.write_chunk(1, 2, chunk(33, 2, 6000)).unwrap();
+        assert!(region.find_torn_chunks().unwrap().is_empty());
+
+        // Power loss: the header was written, but not the data.
+        let metadata = region.get_metadata(1, 2);
+        let offset = metadata.sector_index() as usize * 4096;
+        let mut bytes = region.into_inner().into_inner();
+        for byte in &mut bytes[offset..offset + 4096] {
+            *byte = 0;
+        }
+
+        let mut region = AnvilRegion::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(
+            region.find_torn_chunks().unwrap(),
+            vec![TornChunk {
+                chunk_x: 1,
+                chunk_z: 2,
+                issue: TornChunkIssue::EmptyData,
+            }]
+        );
+        assert!(!region.restore_previous_chunk(3, 0).unwrap());
+        assert!(region.restore_previous_chunk(1, 2).unwrap());
+        assert!(region.find_torn_chunks().unwrap().is_empty());
+
+        let chunk_compound_tag = region.read_chunk(1, 2).unwrap();
+        assert_eq!(chunk_compound_tag.get_i8_vec("Padding").unwrap().len(), 10);
+        assert_eq!(region.get_metadata(1, 2).sectors(), 1);
+
+        // Garbage data.
+        let metadata = region.get_metadata(3, 0);
+        let offset = metadata.sector_index() as usize * 4096;
+        let mut bytes = region.into_inner().into_inner();
+        bytes[offset + 5..offset + 4096].copy_from_slice(&[0xFF; 4091]);
+        let mut region = AnvilRegion::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(
+            region.find_torn_chunks().unwrap()[0].issue,
+            TornChunkIssue::UndecodableData
+        );
+    }
+
+    #[test]
+    fn test_provider_recovers_torn_writes() {
+        let folder = tempfile::tempdir().unwrap();
+        let region_path = folder.path().join("r.0.0.mca");
+        let options = AnvilOptions::new().compression(Compression::Uncompressed);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        chunk_provider.save_chunk(1, 2, chunk(1, 2, 10)).unwrap();
+        chunk_provider.save_chunk(3, 0, chunk(3, 0, 10)).unwrap();
+        chunk_provider.save_chunk(1, 2, chunk(1, 2, 6000)).unwrap();
+
+        let mut region = AnvilRegion::file(&region_path).unwrap();
+        let metadata = region.get_metadata(1, 2);
+        region
+            .write_sector(metadata.sector_index(), &[0; 4096])
+            .unwrap();
+        drop(region);
+
+        let options = AnvilOptions::new().recover_torn_writes(true);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        let chunk_compound_tag = chunk_provider.load_chunk(1, 2).unwrap();
+        assert_eq!(chunk_compound_tag.get_i8_vec("Padding").unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_provider_keeps_chunks_with_extra_sectors() {
+        let folder = tempfile::tempdir().unwrap();
+        let region_path = folder.path().join("r.0.0.mca");
+        let options = AnvilOptions::new().compression(Compression::Uncompressed);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        chunk_provider.save_chunk(1, 2, chunk(1, 2, 10)).unwrap();
+        chunk_provider.save_chunk(3, 0, chunk(3, 0, 10)).unwrap();
+        chunk_provider.save_chunk(1, 2, chunk(1, 2, 6000)).unwrap();
+
+        // Allocate one more sector than needed to the latest version.
+        let mut bytes = fs::read(&region_path).unwrap();
+        let sectors_offset = (1 + 2 * 32) * 4 + 3;
+        assert_eq!(bytes[sectors_offset], 2);
+        bytes[sectors_offset] = 3;
+        bytes.extend_from_slice(&[0; 4096]);
+        fs::write(&region_path, &bytes).unwrap();
+
+        let mut region = AnvilRegion::file(&region_path).unwrap();
+        assert!(region.find_torn_chunks().unwrap().is_empty());
+        assert!(!region.restore_previous_chunk(1, 2).unwrap());
+        drop(region);
+
+        let options = AnvilOptions::new().recover_torn_writes(true);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        let chunk_compound_tag = chunk_provider.load_chunk(1, 2).unwrap();
+        assert_eq!(chunk_compound_tag.get_i8_vec("Padding").unwrap().len(), 6000);
+    }
+}
@@ -0,0 +1,383 @@
+use crate::region_cache::RegionLockGuard;
+use crate::{
+    chunk_coords_inside_region, chunk_coords_to_region_coords, AnvilRegion, ChunkPayload,
+    ChunkSaveError, FolderChunkProvider, RawChunk,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Chunk saves and deletions applied together by `commit`, see
+/// `FolderChunkProvider::transaction`.
+///
+/// Every modified region is first written to a temporary copy next to the
+/// region file, and the copies replace the region files only once all of
+/// them were written. If anything fails before that, no region file is
+/// modified. Dropping the transaction discards the staged changes.
+///
+/// The modified regions are locked from their copy until their replacement,
+/// so other threads using them wait for the commit.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::FolderChunkProvider;
+/// use nbt::CompoundTag;
+///
+/// # let folder = tempfile::tempdir().unwrap();
+/// # let folder = folder.path().to_str().unwrap();
+/// let chunk_provider = FolderChunkProvider::new(folder);
+/// let mut transaction = chunk_provider.transaction();
+///
+/// transaction.save_chunk(0, 0, CompoundTag::new());
+/// transaction.save_chunk(40, 40, CompoundTag::new());
+/// transaction.delete_chunk(1, 0);
+/// transaction.commit().unwrap();
+///
+/// assert!(chunk_provider.load_chunk(40, 40).is_ok());
+/// ```
+pub struct ChunkTransaction<'p, P> {
+    provider: &'p FolderChunkProvider<P>,
+    /// Staged chunks by chunk coordinates, `None` for deleted chunks.
+    staged: BTreeMap<(i32, i32), Option<P>>,
+}
+
+/// Staged changes of a region by chunk coordinates.
+type StagedChanges<P> = Vec<((i32, i32), Option<P>)>;
+
+/// Temporary copies written by `ChunkTransaction::prepare`, whose regions
+/// stay locked until the copies replace the region files or are discarded.
+#[derive(Default)]
+pub(crate) struct PreparedRegions<'p> {
+    /// Paths of the temporary copies with the paths of the region files they
+    /// replace.
+    files: Vec<(PathBuf, PathBuf)>,
+    locks: Vec<RegionLockGuard<'p>>,
+}
+
+impl<'p> PreparedRegions<'p> {
+    pub(crate) fn extend(&mut self, other: PreparedRegions<'p>) {
+        self.files.extend(other.files);
+        self.locks.extend(other.locks);
+    }
+}
+
+impl<P: ChunkPayload> FolderChunkProvider<P> {
+    /// Starts a transaction to save and delete chunks all at once.
+    pub fn transaction(&self) -> ChunkTransaction<'_, P> {
+        ChunkTransaction {
+            provider: self,
+            staged: BTreeMap::new(),
+        }
+    }
+}
+
+impl<'p, P: ChunkPayload> ChunkTransaction<'p, P> {
+    /// Stages saving the chunk, replacing any change staged for it.
+    pub fn save_chunk(&mut self, chunk_x: i32, chunk_z: i32, chunk_compound_tag: P) {
+        self.staged
+            .insert((chunk_x, chunk_z), Some(chunk_compound_tag));
+    }
+
+    /// Stages deleting the chunk, replacing any change staged for it.
+    pub fn delete_chunk(&mut self, chunk_x: i32, chunk_z: i32) {
+        self.staged.insert((chunk_x, chunk_z), None);
+    }
+
+    /// Amount of chunks with staged changes.
+    pub fn len(&self) -> usize {
+        self.staged.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.staged.is_empty()
+    }
+
+    /// Applies every staged change.
+    ///
+    /// On error no region file is modified, unless the error happens while
+    /// the temporary copies replace the region files, the only step that
+    /// is not all-or-nothing.
+    pub fn commit(self) -> Result<(), ChunkSaveError> {
+        let prepared = self.prepare()?;
+
+        replace_regions(prepared)
+    }
+
+    /// Writes the temporary copies of the modified regions. On error the
+    /// copies already written are removed.
+    pub(crate) fn prepare(self) -> Result<PreparedRegions<'p>, ChunkSaveError> {
+        let provider = self.provider;
+
+        if provider.options.read_only {
+            return Err(ChunkSaveError::ReadOnly);
+        }
+
+        if provider.options.dry_run {
+            for ((chunk_x, chunk_z), change) in self.staged {
+                match change {
+                    Some(mut chunk_compound_tag) => {
+                        provider.options.coordinate_check.apply(
+                            chunk_x,
+                            chunk_z,
+                            &mut chunk_compound_tag,
+                        )?;
+
+                        let raw_chunk = RawChunk::encode(
+                            &chunk_compound_tag,
+                            provider.options.compression,
+                            provider.options.compression_level,
+                        )?;
+                        provider.dry_run_save(chunk_x, chunk_z, &raw_chunk)?;
+                    }
+                    None => provider.dry_run_delete(chunk_x, chunk_z)?,
+                }
+            }
+
+            return Ok(PreparedRegions::default());
+        }
+
+        let mut regions: BTreeMap<(i32, i32), StagedChanges<P>> = BTreeMap::new();
+
+        for ((chunk_x, chunk_z), change) in self.staged {
+            regions
+                .entry(chunk_coords_to_region_coords(chunk_x, chunk_z))
+                .or_default()
+                .push(((chunk_x, chunk_z), change));
+        }
+
+        if !provider.folder_path.exists() {
+            fs::create_dir(&provider.folder_path)?;
+        }
+
+        let mut prepared = PreparedRegions::default();
+
+        for ((region_x, region_z), changes) in regions {
+            // Writes to the region between the copy and the replacement would
+            // be lost.
+            prepared
+                .locks
+                .push(provider.region_locks.lock((region_x, region_z)));
+
+            let region_path = provider
+                .folder_path
+                .join(provider.region_file_name(region_x, region_z));
+            let temporary_path = temporary_path(&region_path);
+
+            let result = provider
+                .close_region(region_x, region_z)
+                .map_err(ChunkSaveError::from)
+                .and_then(|()| {
+                    write_temporary_region(provider, &region_path, &temporary_path, changes)
+                });
+            prepared.files.push((temporary_path, region_path));
+
+            if let Err(e) = result {
+                discard_prepared(prepared);
+
+                return Err(e);
+            }
+        }
+
+        Ok(prepared)
+    }
+}
+
+/// Replaces the region files with their prepared temporary copies.
+pub(crate) fn replace_regions(prepared: PreparedRegions<'_>) -> Result<(), ChunkSaveError> {
+    for (temporary_path, region_path) in prepared.files {
+        fs::rename(temporary_path, region_path)?;
+    }
+
+    Ok(())
+}
+
+/// Removes the prepared temporary copies.
+pub(crate) fn discard_prepared(prepared: PreparedRegions<'_>) {
+    for (temporary_path, _) in prepared.files {
+        let _ = fs::remove_file(temporary_path);
+    }
+}
+
+fn temporary_path(region_path: &Path) -> PathBuf {
+    let mut temporary_path = region_path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+
+    temporary_path.into()
+}
+
+/// Copies the region file, if any, and applies the changes to the copy.
+fn write_temporary_region<P: ChunkPayload>(
+    provider: &FolderChunkProvider<P>,
+    region_path: &Path,
+    temporary_path: &Path,
+    changes: StagedChanges<P>,
+) -> Result<(), ChunkSaveError> {
+    if region_path.exists() {
+        fs::copy(region_path, temporary_path)?;
+    } else if temporary_path.exists() {
+        // Left by an interrupted commit.
+        fs::remove_file(temporary_path)?;
+    }
+
+    let options = &provider.options;
+    let mut region = AnvilRegion::file(temporary_path)?
+        .with_sector_allocation(options.sector_allocation)
+        .with_compression(options.compression, options.compression_level);
+
+    region.write_batch(|region| {
+        for ((chunk_x, chunk_z), change) in changes {
+            let (region_chunk_x, region_chunk_z) = chunk_coords_inside_region(chunk_x, chunk_z);
+
+            match change {
+                Some(mut chunk_compound_tag) => {
+                    options
+                        .coordinate_check
+                        .apply(chunk_x, chunk_z, &mut chunk_compound_tag)?;
+
+                    let metadata = region.get_metadata(region_chunk_x, region_chunk_z);
+                    region.write_chunk_with_timestamp(
+                        region_chunk_x,
+                        region_chunk_z,
+                        chunk_compound_tag,
+                        provider.policy_timestamp(metadata),
+                    )?;
+                }
+                None => region.delete_chunk(region_chunk_x, region_chunk_z)?,
+            }
+        }
+
+        Ok::<_, ChunkSaveError>(())
+    })?;
+
+    region.into_inner().sync_all()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilOptions, CoordinateCheck};
+    use nbt::CompoundTag;
+    use std::thread;
+
+    fn chunk_compound_tag(chunk_x: i32, chunk_z: i32) -> CompoundTag {
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("xPos", chunk_x);
+        chunk_compound_tag.insert_i32("zPos", chunk_z);
+
+        chunk_compound_tag
+    }
+
+    #[test]
+    fn test_transaction_commit() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider = FolderChunkProvider::new(folder.path().to_str().unwrap());
+        chunk_provider
+            .save_chunk(1, 0, chunk_compound_tag(1, 0))
+            .unwrap();
+
+        let mut transaction = chunk_provider.transaction();
+        transaction.save_chunk(0, 0, chunk_compound_tag(0, 0));
+        transaction.save_chunk(-1, 0, chunk_compound_tag(-1, 0));
+        transaction.delete_chunk(1, 0);
+        transaction.save_chunk(2, 0, chunk_compound_tag(2, 0));
+        transaction.delete_chunk(2, 0);
+        assert_eq!(transaction.len(), 4);
+        transaction.commit().unwrap();
+
+        assert!(chunk_provider.load_chunk(0, 0).is_ok());
+        assert!(chunk_provider.load_chunk(-1, 0).is_ok());
+        assert!(chunk_provider.try_load_chunk(1, 0).unwrap().is_none());
+        assert!(chunk_provider.try_load_chunk(2, 0).unwrap().is_none());
+
+        let leftovers = fs::read_dir(folder.path())
+            .unwrap()
+            .filter(|entry| {
+                let path = entry.as_ref().unwrap().path();
+                path.extension().unwrap() == "tmp"
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn test_transaction_failure_changes_nothing() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider = FolderChunkProvider::new(folder.path().to_str().unwrap())
+            .with_coordinate_check(CoordinateCheck::Verify);
+        chunk_provider
+            .save_chunk(0, 0, chunk_compound_tag(0, 0))
+            .unwrap();
+        let original = fs::read(folder.path().join("r.0.0.mca")).unwrap();
+
+        let mut transaction = chunk_provider.transaction();
+        transaction.delete_chunk(0, 0);
+        transaction.save_chunk(-1, 0, chunk_compound_tag(-1, 0));
+        // Wrong coordinates, fails the commit.
+        transaction.save_chunk(40, 0, chunk_compound_tag(0, 0));
+
+        match transaction.commit() {
+            Err(ChunkSaveError::CoordinateMismatch { .. }) => {}
+            e => panic!("Expected `CoordinateMismatch` but got `{:?}`", e),
+        }
+
+        assert_eq!(fs::read(folder.path().join("r.0.0.mca")).unwrap(), original);
+        assert!(!folder.path().join("r.-1.0.mca").exists());
+        assert_eq!(fs::read_dir(folder.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_transaction_keeps_concurrent_saves() {
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider =
+            FolderChunkProvider::new(folder.path().to_str().unwrap()).with_max_open_regions(4);
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                for _ in 0..50 {
+                    let mut transaction = chunk_provider.transaction();
+                    transaction.save_chunk(0, 0, chunk_compound_tag(0, 0));
+                    transaction.commit().unwrap();
+                }
+            });
+
+            for chunk_x in 1..50 {
+                chunk_provider
+                    .save_chunk(chunk_x, 0, chunk_compound_tag(chunk_x, 0))
+                    .unwrap();
+            }
+        });
+
+        for chunk_x in 0..50 {
+            assert!(chunk_provider.load_chunk(chunk_x, 0).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_transaction_dry_run() {
+        let folder = tempfile::tempdir().unwrap();
+        let region_path = folder.path().join("r.0.0.mca");
+        fs::copy("test/region/r.0.0.mca", &region_path).unwrap();
+        let region_bytes = fs::read(&region_path).unwrap();
+
+        let options = AnvilOptions::new().dry_run(true);
+        let chunk_provider = FolderChunkProvider::with_options(folder.path(), options);
+        let mut transaction = chunk_provider.transaction();
+        transaction.save_chunk(40, 0, chunk_compound_tag(40, 0));
+        transaction.save_chunk(0, 0, chunk_compound_tag(0, 0));
+        transaction.delete_chunk(4, 2);
+        transaction.commit().unwrap();
+
+        let report = chunk_provider.dry_run_report();
+        assert_eq!(report.chunks_saved, 2);
+        assert_eq!(report.chunks_deleted, 1);
+        assert_eq!(report.regions_created, 1);
+        assert!(report.sectors_freed > 0);
+
+        assert_eq!(fs::read(&region_path).unwrap(), region_bytes);
+        assert!(!folder.path().join("r.1.0.mca").exists());
+        assert_eq!(fs::read_dir(folder.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,547 @@
+//! Conversion of chunks from before 1.13, which store blocks as numeric ids
+//! and data values, to the paletted block states used since.
+//!
+//! The conversion uses a bundled table covering the common blocks of the
+//! legacy id space. Only the block sections are converted: the chunk keeps
+//! its `DataVersion` and the rest of its tags, so it can be read by tools
+//! expecting block names but still needs the game to upgrade the rest of
+//! it.
+//!
+//! # Example
+//!
+//! ```
+//! use anvil_region::upgrade::upgrade_chunk;
+//! use nbt::CompoundTag;
+//!
+//! let mut section = CompoundTag::new();
+//! section.insert_i8("Y", 0);
+//! section.insert_i8_vec("Blocks", vec![1; 4096]);
+//! section.insert_i8_vec("Data", vec![0; 2048]);
+//! let mut level = CompoundTag::new();
+//! level.insert_compound_tag_vec("Sections", vec![section]);
+//! let mut chunk_compound_tag = CompoundTag::new();
+//! chunk_compound_tag.insert_compound_tag("Level", level);
+//!
+//! let report = upgrade_chunk(&mut chunk_compound_tag);
+//!
+//! assert_eq!(report.sections, 1);
+//! assert_eq!(report.unknown_blocks, 0);
+//! ```
+use crate::{chunk_level_mut, ChunkReader, ChunkSelection, ChunkWriter, WorldEditError};
+use nbt::{CompoundTag, Tag};
+use std::collections::HashMap;
+
+/// Block states of legacy ids and data values, as `name[property=value]`.
+const LEGACY_BLOCKS: &[(u16, u8, &str)] = &[
+    (0, 0, "minecraft:air"),
+    (1, 0, "minecraft:stone"),
+    (1, 1, "minecraft:granite"),
+    (1, 2, "minecraft:polished_granite"),
+    (1, 3, "minecraft:diorite"),
+    (1, 4, "minecraft:polished_diorite"),
+    (1, 5, "minecraft:andesite"),
+    (1, 6, "minecraft:polished_andesite"),
+    (2, 0, "minecraft:grass_block[snowy=false]"),
+    (3, 0, "minecraft:dirt"),
+    (3, 1, "minecraft:coarse_dirt"),
+    (3, 2, "minecraft:podzol[snowy=false]"),
+    (4, 0, "minecraft:cobblestone"),
+    (5, 0, "minecraft:oak_planks"),
+    (5, 1, "minecraft:spruce_planks"),
+    (5, 2, "minecraft:birch_planks"),
+    (5, 3, "minecraft:jungle_planks"),
+    (5, 4, "minecraft:acacia_planks"),
+    (5, 5, "minecraft:dark_oak_planks"),
+    (6, 0, "minecraft:oak_sapling[stage=0]"),
+    (6, 1, "minecraft:spruce_sapling[stage=0]"),
+    (6, 2, "minecraft:birch_sapling[stage=0]"),
+    (6, 3, "minecraft:jungle_sapling[stage=0]"),
+    (6, 4, "minecraft:acacia_sapling[stage=0]"),
+    (6, 5, "minecraft:dark_oak_sapling[stage=0]"),
+    (7, 0, "minecraft:bedrock"),
+    (8, 0, "minecraft:water[level=0]"),
+    (9, 0, "minecraft:water[level=0]"),
+    (10, 0, "minecraft:lava[level=0]"),
+    (11, 0, "minecraft:lava[level=0]"),
+    (12, 0, "minecraft:sand"),
+    (12, 1, "minecraft:red_sand"),
+    (13, 0, "minecraft:gravel"),
+    (14, 0, "minecraft:gold_ore"),
+    (15, 0, "minecraft:iron_ore"),
+    (16, 0, "minecraft:coal_ore"),
+    (17, 0, "minecraft:oak_log[axis=y]"),
+    (17, 1, "minecraft:spruce_log[axis=y]"),
+    (17, 2, "minecraft:birch_log[axis=y]"),
+    (17, 3, "minecraft:jungle_log[axis=y]"),
+    (17, 4, "minecraft:oak_log[axis=x]"),
+    (17, 5, "minecraft:spruce_log[axis=x]"),
+    (17, 6, "minecraft:birch_log[axis=x]"),
+    (17, 7, "minecraft:jungle_log[axis=x]"),
+    (17, 8, "minecraft:oak_log[axis=z]"),
+    (17, 9, "minecraft:spruce_log[axis=z]"),
+    (17, 10, "minecraft:birch_log[axis=z]"),
+    (17, 11, "minecraft:jungle_log[axis=z]"),
+    (18, 0, "minecraft:oak_leaves[distance=7,persistent=false]"),
+    (
+        18,
+        1,
+        "minecraft:spruce_leaves[distance=7,persistent=false]",
+    ),
+    (18, 2, "minecraft:birch_leaves[distance=7,persistent=false]"),
+    (
+        18,
+        3,
+        "minecraft:jungle_leaves[distance=7,persistent=false]",
+    ),
+    (19, 0, "minecraft:sponge"),
+    (20, 0, "minecraft:glass"),
+    (21, 0, "minecraft:lapis_ore"),
+    (22, 0, "minecraft:lapis_block"),
+    (24, 0, "minecraft:sandstone"),
+    (24, 1, "minecraft:chiseled_sandstone"),
+    (24, 2, "minecraft:cut_sandstone"),
+    (31, 0, "minecraft:dead_bush"),
+    (31, 1, "minecraft:grass"),
+    (31, 2, "minecraft:fern"),
+    (32, 0, "minecraft:dead_bush"),
+    (37, 0, "minecraft:dandelion"),
+    (38, 0, "minecraft:poppy"),
+    (38, 1, "minecraft:blue_orchid"),
+    (38, 2, "minecraft:allium"),
+    (38, 3, "minecraft:azure_bluet"),
+    (38, 4, "minecraft:red_tulip"),
+    (38, 5, "minecraft:orange_tulip"),
+    (38, 6, "minecraft:white_tulip"),
+    (38, 7, "minecraft:pink_tulip"),
+    (38, 8, "minecraft:oxeye_daisy"),
+    (39, 0, "minecraft:brown_mushroom"),
+    (40, 0, "minecraft:red_mushroom"),
+    (41, 0, "minecraft:gold_block"),
+    (42, 0, "minecraft:iron_block"),
+    (45, 0, "minecraft:bricks"),
+    (46, 0, "minecraft:tnt"),
+    (47, 0, "minecraft:bookshelf"),
+    (48, 0, "minecraft:mossy_cobblestone"),
+    (49, 0, "minecraft:obsidian"),
+    (50, 5, "minecraft:torch"),
+    (50, 1, "minecraft:wall_torch[facing=east]"),
+    (50, 2, "minecraft:wall_torch[facing=west]"),
+    (50, 3, "minecraft:wall_torch[facing=south]"),
+    (50, 4, "minecraft:wall_torch[facing=north]"),
+    (52, 0, "minecraft:spawner"),
+    (
+        54,
+        2,
+        "minecraft:chest[facing=north,type=single,waterlogged=false]",
+    ),
+    (
+        54,
+        3,
+        "minecraft:chest[facing=south,type=single,waterlogged=false]",
+    ),
+    (
+        54,
+        4,
+        "minecraft:chest[facing=west,type=single,waterlogged=false]",
+    ),
+    (
+        54,
+        5,
+        "minecraft:chest[facing=east,type=single,waterlogged=false]",
+    ),
+    (56, 0, "minecraft:diamond_ore"),
+    (57, 0, "minecraft:diamond_block"),
+    (58, 0, "minecraft:crafting_table"),
+    (60, 0, "minecraft:farmland[moisture=0]"),
+    (61, 2, "minecraft:furnace[facing=north,lit=false]"),
+    (61, 3, "minecraft:furnace[facing=south,lit=false]"),
+    (61, 4, "minecraft:furnace[facing=west,lit=false]"),
+    (61, 5, "minecraft:furnace[facing=east,lit=false]"),
+    (73, 0, "minecraft:redstone_ore[lit=false]"),
+    (74, 0, "minecraft:redstone_ore[lit=true]"),
+    (78, 0, "minecraft:snow[layers=1]"),
+    (79, 0, "minecraft:ice"),
+    (80, 0, "minecraft:snow_block"),
+    (81, 0, "minecraft:cactus[age=0]"),
+    (82, 0, "minecraft:clay"),
+    (83, 0, "minecraft:sugar_cane[age=0]"),
+    (85, 0, "minecraft:oak_fence"),
+    (86, 0, "minecraft:carved_pumpkin[facing=south]"),
+    (87, 0, "minecraft:netherrack"),
+    (88, 0, "minecraft:soul_sand"),
+    (89, 0, "minecraft:glowstone"),
+    (91, 0, "minecraft:jack_o_lantern[facing=south]"),
+    (98, 0, "minecraft:stone_bricks"),
+    (98, 1, "minecraft:mossy_stone_bricks"),
+    (98, 2, "minecraft:cracked_stone_bricks"),
+    (98, 3, "minecraft:chiseled_stone_bricks"),
+    (103, 0, "minecraft:melon"),
+    (110, 0, "minecraft:mycelium[snowy=false]"),
+    (112, 0, "minecraft:nether_bricks"),
+    (121, 0, "minecraft:end_stone"),
+    (129, 0, "minecraft:emerald_ore"),
+    (133, 0, "minecraft:emerald_block"),
+    (152, 0, "minecraft:redstone_block"),
+    (153, 0, "minecraft:nether_quartz_ore"),
+    (155, 0, "minecraft:quartz_block"),
+    (155, 1, "minecraft:chiseled_quartz_block"),
+    (155, 2, "minecraft:quartz_pillar[axis=y]"),
+    (
+        161,
+        0,
+        "minecraft:acacia_leaves[distance=7,persistent=false]",
+    ),
+    (
+        161,
+        1,
+        "minecraft:dark_oak_leaves[distance=7,persistent=false]",
+    ),
+    (162, 0, "minecraft:acacia_log[axis=y]"),
+    (162, 1, "minecraft:dark_oak_log[axis=y]"),
+    (168, 0, "minecraft:prismarine"),
+    (168, 1, "minecraft:prismarine_bricks"),
+    (168, 2, "minecraft:dark_prismarine"),
+    (169, 0, "minecraft:sea_lantern"),
+    (170, 0, "minecraft:hay_block[axis=y]"),
+    (172, 0, "minecraft:terracotta"),
+    (173, 0, "minecraft:coal_block"),
+    (174, 0, "minecraft:packed_ice"),
+    (179, 0, "minecraft:red_sandstone"),
+    (179, 1, "minecraft:chiseled_red_sandstone"),
+    (179, 2, "minecraft:cut_red_sandstone"),
+];
+
+/// Legacy ids of blocks with one block per color, the data value being the
+/// color, with the suffix of their names.
+const COLORED_BLOCKS: [(u16, &str); 4] = [
+    (35, "wool"),
+    (95, "stained_glass"),
+    (159, "terracotta"),
+    (171, "carpet"),
+];
+
+/// Colors in data value order.
+const COLORS: [&str; 16] = [
+    "white",
+    "orange",
+    "magenta",
+    "light_blue",
+    "yellow",
+    "lime",
+    "pink",
+    "gray",
+    "light_gray",
+    "cyan",
+    "purple",
+    "blue",
+    "brown",
+    "green",
+    "red",
+    "black",
+];
+
+/// Blocks converted by `upgrade_chunk` or `upgrade_chunks`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct UpgradeReport {
+    /// Amount of converted chunks, only counted by `upgrade_chunks`.
+    pub chunks: usize,
+    /// Amount of converted sections.
+    pub sections: usize,
+    /// Blocks whose data value is not in the table, converted to the block
+    /// state of another data value of the same id, usually losing their
+    /// orientation.
+    pub approximated_blocks: usize,
+    /// Blocks whose id is not in the table, converted to air.
+    pub unknown_blocks: usize,
+}
+
+/// Block state of a legacy id and data value.
+enum LegacyBlock {
+    Exact(String),
+    Approximated(String),
+    Unknown,
+}
+
+fn legacy_block(id: u16, data: u8) -> LegacyBlock {
+    if let Some((_, suffix)) = COLORED_BLOCKS
+        .iter()
+        .find(|(colored_id, _)| *colored_id == id)
+    {
+        let name = format!("minecraft:{}_{}", COLORS[data as usize & 15], suffix);
+
+        return LegacyBlock::Exact(name);
+    }
+
+    let mut same_id = LEGACY_BLOCKS
+        .iter()
+        .filter(|(legacy_id, _, _)| *legacy_id == id)
+        .peekable();
+
+    let first = match same_id.peek() {
+        Some((_, _, block_state)) => *block_state,
+        None => return LegacyBlock::Unknown,
+    };
+
+    match same_id.find(|(_, legacy_data, _)| *legacy_data == data) {
+        Some((_, _, block_state)) => LegacyBlock::Exact(block_state.to_string()),
+        None => LegacyBlock::Approximated(first.to_string()),
+    }
+}
+
+/// Palette entry of a block state written as `name[property=value,...]`.
+fn palette_entry(block_state: &str) -> CompoundTag {
+    let mut palette_entry = CompoundTag::new();
+
+    let (name, properties) = match block_state.find('[') {
+        Some(start) => (
+            &block_state[..start],
+            Some(&block_state[start + 1..block_state.len() - 1]),
+        ),
+        None => (block_state, None),
+    };
+
+    palette_entry.insert_str("Name", name);
+
+    if let Some(properties) = properties {
+        let mut properties_compound_tag = CompoundTag::new();
+
+        for property in properties.split(',') {
+            if let Some((key, value)) = property.split_once('=') {
+                properties_compound_tag.insert_str(key, value);
+            }
+        }
+
+        palette_entry.insert_compound_tag("Properties", properties_compound_tag);
+    }
+
+    palette_entry
+}
+
+/// Packs the values with `bits` bits each, values spanning long boundaries
+/// as in chunks from 1.13 to 1.15.
+fn pack_values(values: &[u16], bits: usize) -> Vec<i64> {
+    let mut data = vec![0u64; (values.len() * bits).div_ceil(64)];
+
+    for (index, &value) in values.iter().enumerate() {
+        let bit = index * bits;
+        let offset = bit % 64;
+        data[bit / 64] |= u64::from(value) << offset;
+
+        if offset + bits > 64 {
+            data[bit / 64 + 1] |= u64::from(value) >> (64 - offset);
+        }
+    }
+
+    data.into_iter().map(|long| long as i64).collect()
+}
+
+/// Converts the `Blocks`, `Data` and `Add` arrays of every section of the
+/// chunk into a `Palette` and `BlockStates`. Sections already converted are
+/// left as they are.
+pub fn upgrade_chunk(chunk_compound_tag: &mut CompoundTag) -> UpgradeReport {
+    let mut report = UpgradeReport::default();
+    let level_compound_tag = chunk_level_mut(chunk_compound_tag);
+
+    let sections = match level_compound_tag.get_mut::<&mut Vec<Tag>>("Sections") {
+        Ok(sections) => sections,
+        Err(_) => return report,
+    };
+
+    for section in sections {
+        if let Tag::Compound(section) = section {
+            if upgrade_section(section, &mut report) {
+                report.sections += 1;
+            }
+        }
+    }
+
+    report
+}
+
+/// Converts the section, returning false if it has no legacy blocks.
+fn upgrade_section(section: &mut CompoundTag, report: &mut UpgradeReport) -> bool {
+    let blocks = match section.get_i8_vec("Blocks") {
+        Ok(blocks) if blocks.len() == 4096 => blocks,
+        _ => return false,
+    };
+
+    let nibble = |array: Option<&Vec<i8>>, index: usize| -> u8 {
+        match array.and_then(|array| array.get(index / 2)) {
+            Some(&byte) => (byte as u8 >> (index % 2 * 4)) & 15,
+            None => 0,
+        }
+    };
+
+    let data = section.get_i8_vec("Data").ok();
+    let add = section.get_i8_vec("Add").ok();
+    let mut palette = vec!["minecraft:air".to_string()];
+    let mut palette_indexes: HashMap<String, u16> = HashMap::new();
+    palette_indexes.insert(palette[0].clone(), 0);
+    let mut values = Vec::with_capacity(4096);
+
+    for (index, &block) in blocks.iter().enumerate() {
+        let id = u16::from(block as u8) | (u16::from(nibble(add, index)) << 8);
+
+        let block_state = match legacy_block(id, nibble(data, index)) {
+            LegacyBlock::Exact(block_state) => block_state,
+            LegacyBlock::Approximated(block_state) => {
+                report.approximated_blocks += 1;
+                block_state
+            }
+            LegacyBlock::Unknown => {
+                report.unknown_blocks += 1;
+                palette[0].clone()
+            }
+        };
+
+        let palette_index = *palette_indexes
+            .entry(block_state)
+            .or_insert_with_key(|block_state| {
+                palette.push(block_state.clone());
+                (palette.len() - 1) as u16
+            });
+
+        values.push(palette_index);
+    }
+
+    let bits = ((usize::BITS - (palette.len() - 1).leading_zeros()) as usize).max(4);
+    let palette: Vec<CompoundTag> = palette
+        .iter()
+        .map(|block_state| palette_entry(block_state))
+        .collect();
+
+    // The legacy arrays are dropped by copying every other tag.
+    let mut upgraded_section = CompoundTag::new();
+
+    for (name, tag) in section.iter() {
+        if !["Blocks", "Data", "Add"].contains(&name.as_str()) {
+            upgraded_section.insert(name, tag.clone());
+        }
+    }
+
+    upgraded_section.insert_compound_tag_vec("Palette", palette);
+    upgraded_section.insert_i64_vec("BlockStates", pack_values(&values, bits));
+    *section = upgraded_section;
+
+    true
+}
+
+/// Converts the legacy blocks of every selected chunk with `upgrade_chunk`,
+/// saving the chunks with legacy sections.
+pub fn upgrade_chunks<P>(
+    provider: &mut P,
+    selection: &ChunkSelection,
+) -> Result<UpgradeReport, WorldEditError>
+where
+    P: ChunkReader + ChunkWriter + ?Sized,
+{
+    let mut report = UpgradeReport::default();
+
+    for (chunk_x, chunk_z) in provider.list_chunks_in(selection)? {
+        let mut chunk_compound_tag = provider.load_chunk(chunk_x, chunk_z)?;
+        let chunk_report = upgrade_chunk(&mut chunk_compound_tag);
+
+        if chunk_report.sections == 0 {
+            continue;
+        }
+
+        provider.save_chunk(chunk_x, chunk_z, chunk_compound_tag)?;
+
+        report.chunks += 1;
+        report.sections += chunk_report.sections;
+        report.approximated_blocks += chunk_report.approximated_blocks;
+        report.unknown_blocks += chunk_report.unknown_blocks;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_surface::block_name;
+    use crate::FolderChunkProvider;
+
+    fn legacy_chunk() -> CompoundTag {
+        let mut blocks = vec![0i8; 4096];
+        let mut data = vec![0i8; 2048];
+
+        // The 16 wool colors on the first row, at x 0 to 15.
+        for x in 0..16 {
+            blocks[x] = 35;
+            data[x / 2] |= ((x as i8) & 15) << ((x % 2) * 4);
+        }
+
+        // Stone, an x axis oak log, a torch with an unknown data value and an
+        // unknown block on the second row.
+        blocks[16] = 1;
+        blocks[17] = 17;
+        data[17 / 2] |= 4 << 4;
+        blocks[18] = 50;
+        data[18 / 2] |= 9;
+        blocks[19] = -6;
+
+        let mut section = CompoundTag::new();
+        section.insert_i8("Y", 1);
+        section.insert_i8_vec("Blocks", blocks);
+        section.insert_i8_vec("Data", data);
+        section.insert_i8_vec("SkyLight", vec![0; 2048]);
+        let mut level = CompoundTag::new();
+        level.insert_compound_tag_vec("Sections", vec![section]);
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_compound_tag("Level", level);
+
+        chunk_compound_tag
+    }
+
+    #[test]
+    fn test_upgrade_chunks() {
+        let folder = tempfile::tempdir().unwrap();
+        let mut chunk_provider = FolderChunkProvider::new(folder.path());
+        chunk_provider.save_chunk(0, 0, legacy_chunk()).unwrap();
+        chunk_provider.save_chunk(1, 0, CompoundTag::new()).unwrap();
+
+        let report = upgrade_chunks(&mut chunk_provider, &ChunkSelection::All).unwrap();
+        assert_eq!(
+            report,
+            UpgradeReport {
+                chunks: 1,
+                sections: 1,
+                approximated_blocks: 1,
+                unknown_blocks: 1,
+            }
+        );
+
+        let chunk_compound_tag = chunk_provider.load_chunk(0, 0).unwrap();
+        let block = |x, z| block_name(&chunk_compound_tag, x, 16, z).unwrap();
+        assert_eq!(block(0, 0), "minecraft:white_wool");
+        assert_eq!(block(15, 0), "minecraft:black_wool");
+        assert_eq!(block(0, 1), "minecraft:stone");
+        assert_eq!(block(1, 1), "minecraft:oak_log");
+        assert_eq!(block(2, 1), "minecraft:torch");
+        assert_eq!(block(3, 1), "minecraft:air");
+        assert_eq!(block(3, 3), "minecraft:air");
+
+        let section = &chunk_compound_tag
+            .get_compound_tag("Level")
+            .unwrap()
+            .get_compound_tag_vec("Sections")
+            .unwrap()[0];
+        let log = &section.get_compound_tag_vec("Palette").unwrap()[18];
+        assert_eq!(log.get_str("Name").unwrap(), "minecraft:oak_log");
+        assert_eq!(
+            log.get_compound_tag("Properties")
+                .unwrap()
+                .get_str("axis")
+                .unwrap(),
+            "x"
+        );
+        // 20 block states need 5 bits per block.
+        assert_eq!(section.get_i64_vec("BlockStates").unwrap().len(), 320);
+        assert!(!section.contains_key("Blocks"));
+        assert!(section.contains_key("SkyLight"));
+
+        let report = upgrade_chunks(&mut chunk_provider, &ChunkSelection::All).unwrap();
+        assert_eq!(report, UpgradeReport::default());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{ChunkLoadError, ChunkPayload};
+use nbt::{CompoundTag, Tag};
+use std::io;
+use std::io::{Read, Write};
+use valence_nbt::value::ValueRef;
+use valence_nbt::{from_binary, to_binary, Compound, List, Value};
+
+/// Chunks can be loaded and saved as `valence_nbt` compounds, see
+/// `FolderChunkProvider::with_payload`.
+impl ChunkPayload for Compound {
+    fn read_payload<R: Read>(reader: &mut R) -> Result<Self, ChunkLoadError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        match from_binary::<String>(&mut bytes.as_slice()) {
+            Ok((compound, _)) => Ok(compound),
+            Err(e) => Err(ChunkLoadError::ReadError {
+                io_error: nbt_error_to_io_error(e),
+            }),
+        }
+    }
+
+    fn write_payload<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        to_binary(self, writer, "").map_err(nbt_error_to_io_error)
+    }
+
+    fn chunk_coordinates(&self) -> (Option<i32>, Option<i32>) {
+        let level = match self.get("Level") {
+            Some(Value::Compound(level)) => level,
+            _ => self,
+        };
+        let coordinate = |name| match level.get(name) {
+            Some(Value::Int(value)) => Some(*value),
+            _ => None,
+        };
+
+        (coordinate("xPos"), coordinate("zPos"))
+    }
+
+    fn set_chunk_coordinates(&mut self, chunk_x: i32, chunk_z: i32) {
+        let level = if self.contains_key("Level") {
+            match self.get_mut("Level") {
+                Some(Value::Compound(level)) => level,
+                _ => return,
+            }
+        } else {
+            self
+        };
+
+        level.insert("xPos", chunk_x);
+        level.insert("zPos", chunk_z);
+    }
+}
+
+fn nbt_error_to_io_error(e: valence_nbt::binary::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Converts a compound tag into a `valence_nbt` compound without re-encoding.
+///
+/// List elements of a different type than the first element are skipped,
+/// as they could not be saved either.
+pub fn compound_tag_to_valence(compound_tag: &CompoundTag) -> Compound {
+    compound_tag
+        .iter()
+        .map(|(name, tag)| (name.clone(), tag_to_valence(tag)))
+        .collect()
+}
+
+/// Converts a `valence_nbt` compound into a compound tag without re-encoding.
+pub fn compound_tag_from_valence(compound: &Compound) -> CompoundTag {
+    let mut compound_tag = CompoundTag::new();
+
+    for (name, value) in compound {
+        compound_tag.insert(name.as_str(), tag_from_valence(value.as_value_ref()));
+    }
+
+    compound_tag
+}
+
+fn tag_to_valence(tag: &Tag) -> Value {
+    match tag {
+        Tag::Byte(value) => Value::Byte(*value),
+        Tag::Short(value) => Value::Short(*value),
+        Tag::Int(value) => Value::Int(*value),
+        Tag::Long(value) => Value::Long(*value),
+        Tag::Float(value) => Value::Float(*value),
+        Tag::Double(value) => Value::Double(*value),
+        Tag::ByteArray(value) => Value::ByteArray(value.clone()),
+        Tag::String(value) => Value::String(value.clone()),
+        Tag::List(value) => {
+            let mut list = List::new();
+
+            for tag in value {
+                let _ = list.try_push(tag_to_valence(tag));
+            }
+
+            Value::List(list)
+        }
+        Tag::Compound(value) => Value::Compound(compound_tag_to_valence(value)),
+        Tag::IntArray(value) => Value::IntArray(value.clone()),
+        Tag::LongArray(value) => Value::LongArray(value.clone()),
+    }
+}
+
+fn tag_from_valence(value: ValueRef) -> Tag {
+    match value {
+        ValueRef::Byte(value) => Tag::Byte(*value),
+        ValueRef::Short(value) => Tag::Short(*value),
+        ValueRef::Int(value) => Tag::Int(*value),
+        ValueRef::Long(value) => Tag::Long(*value),
+        ValueRef::Float(value) => Tag::Float(*value),
+        ValueRef::Double(value) => Tag::Double(*value),
+        ValueRef::ByteArray(value) => Tag::ByteArray(value.to_vec()),
+        ValueRef::String(value) => Tag::String(value.clone()),
+        ValueRef::List(value) => Tag::List(value.iter().map(tag_from_valence).collect()),
+        ValueRef::Compound(value) => Tag::Compound(compound_tag_from_valence(value)),
+        ValueRef::IntArray(value) => Tag::IntArray(value.to_vec()),
+        ValueRef::LongArray(value) => Tag::LongArray(value.to_vec()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FolderChunkProvider;
+
+    #[test]
+    fn test_compound_tag_valence_round_trip() {
+        let mut level_compound_tag = CompoundTag::new();
+        level_compound_tag.insert_i32("xPos", 4);
+        level_compound_tag.insert_i64_vec("Heightmap", vec![1, 2, 3]);
+        level_compound_tag.insert_str_vec("Names", vec!["a", "b"]);
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+
+        let compound = compound_tag_to_valence(&chunk_compound_tag);
+        assert_eq!(compound.chunk_coordinates(), (Some(4), None));
+
+        let converted = compound_tag_from_valence(&compound);
+        let level_compound_tag = converted.get_compound_tag("Level").unwrap();
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+        assert_eq!(level_compound_tag.get_i64_vec("Heightmap").unwrap(), &vec![1, 2, 3]);
+        assert_eq!(level_compound_tag.get_str_vec("Names").unwrap(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_folder_provider_valence_payload() {
+        let chunk_provider = FolderChunkProvider::new("test/region").with_payload::<Compound>();
+
+        let compound = chunk_provider.load_chunk(4, 2).unwrap();
+        assert_eq!(compound.chunk_coordinates(), (Some(4), Some(2)));
+
+        let folder = tempfile::tempdir().unwrap();
+        let chunk_provider =
+            FolderChunkProvider::new(folder.path().to_str().unwrap()).with_payload::<Compound>();
+        chunk_provider.save_chunk(1, 2, compound.clone()).unwrap();
+
+        assert_eq!(chunk_provider.load_chunk(1, 2).unwrap(), compound);
+    }
+}
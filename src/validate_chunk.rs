@@ -0,0 +1,377 @@
+use nbt::{CompoundTag, CompoundTagError, Tag};
+
+/// Version of the Minecraft data format, stored in the `DataVersion` tag of
+/// chunks since 1.9.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DataVersion(pub i32);
+
+impl DataVersion {
+    /// 1.13, which introduced block palettes.
+    pub const V1_13: DataVersion = DataVersion(1519);
+    /// 1.15, which made biomes three dimensional.
+    pub const V1_15: DataVersion = DataVersion(2225);
+    /// 1.16, which stopped packing block states across longs.
+    pub const V1_16: DataVersion = DataVersion(2566);
+    /// 1.18, which removed the `Level` compound and added paletted biomes.
+    pub const V1_18: DataVersion = DataVersion(2860);
+
+    /// Data version of the chunk, or `None` if it has no `DataVersion` tag.
+    pub fn of_chunk(chunk_compound_tag: &CompoundTag) -> Option<Self> {
+        chunk_compound_tag
+            .get_i32("DataVersion")
+            .ok()
+            .map(DataVersion)
+    }
+}
+
+/// Problem found by `validate_chunk`. Paths are dot separated tag names,
+/// with list indices in brackets, like `Level.Sections[2].BlockStates`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SchemaIssue {
+    /// A required tag is missing.
+    MissingTag { path: String },
+    /// A tag has an unexpected type.
+    WrongType {
+        path: String,
+        /// Name of the expected NBT type, like `Int` or `LongArray`.
+        expected: &'static str,
+    },
+    /// The chunk `DataVersion` is not the validated one.
+    DataVersionMismatch { data_version: i32 },
+    /// An array does not have the length required by its section.
+    WrongLength {
+        path: String,
+        expected: usize,
+        length: usize,
+    },
+}
+
+/// Checks that the chunk has the tags, tag types and section shape the
+/// given version of the game requires.
+///
+/// Only the parts of the format the game can't load without are checked,
+/// an empty result does not mean every tag has a sensible value.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{validate_chunk, DataVersion, FolderChunkProvider, SchemaIssue};
+///
+/// let chunk_provider = FolderChunkProvider::new("test/region");
+/// let chunk_compound_tag = chunk_provider.load_chunk(4, 2).unwrap();
+/// let data_version = DataVersion::of_chunk(&chunk_compound_tag).unwrap();
+///
+/// assert_eq!(validate_chunk(&chunk_compound_tag, data_version), vec![]);
+/// ```
+pub fn validate_chunk(chunk_compound_tag: &CompoundTag, version: DataVersion) -> Vec<SchemaIssue> {
+    let mut validator = Validator { issues: Vec::new() };
+
+    if let Some(data_version) = validator.required(
+        chunk_compound_tag.get_i32("DataVersion"),
+        "",
+        "DataVersion",
+        "Int",
+    ) {
+        if data_version != version.0 {
+            validator
+                .issues
+                .push(SchemaIssue::DataVersionMismatch { data_version });
+        }
+    }
+
+    if version >= DataVersion::V1_18 {
+        validator.validate_level(chunk_compound_tag, "", version);
+    } else if let Some(level_compound_tag) = validator.required(
+        chunk_compound_tag.get_compound_tag("Level"),
+        "",
+        "Level",
+        "Compound",
+    ) {
+        validator.validate_level(level_compound_tag, "Level", version);
+    }
+
+    validator.issues
+}
+
+struct Validator {
+    issues: Vec<SchemaIssue>,
+}
+
+impl Validator {
+    /// Records the issue if the tag is missing or has the wrong type.
+    fn required<T>(
+        &mut self,
+        result: Result<T, CompoundTagError>,
+        path: &str,
+        name: &str,
+        expected: &'static str,
+    ) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(CompoundTagError::TagNotFound { .. }) => {
+                let path = join(path, name);
+                self.issues.push(SchemaIssue::MissingTag { path });
+                None
+            }
+            Err(CompoundTagError::TagWrongType { .. }) => {
+                let path = join(path, name);
+                self.issues.push(SchemaIssue::WrongType { path, expected });
+                None
+            }
+        }
+    }
+
+    /// Same as `required`, but a missing tag is not an issue.
+    fn optional<T>(
+        &mut self,
+        result: Result<T, CompoundTagError>,
+        path: &str,
+        name: &str,
+        expected: &'static str,
+    ) -> Option<T> {
+        match result {
+            Err(CompoundTagError::TagNotFound { .. }) => None,
+            result => self.required(result, path, name, expected),
+        }
+    }
+
+    fn check_length(&mut self, path: String, expected: usize, length: usize) {
+        if length != expected {
+            self.issues.push(SchemaIssue::WrongLength {
+                path,
+                expected,
+                length,
+            });
+        }
+    }
+
+    /// Validates the compound holding the chunk data, `Level` before 1.18.
+    fn validate_level(&mut self, level: &CompoundTag, path: &str, version: DataVersion) {
+        self.required(level.get_i32("xPos"), path, "xPos", "Int");
+        self.required(level.get_i32("zPos"), path, "zPos", "Int");
+        self.required(level.get_i64("LastUpdate"), path, "LastUpdate", "Long");
+
+        if version >= DataVersion::V1_13 {
+            self.required(level.get_str("Status"), path, "Status", "String");
+        }
+
+        if version >= DataVersion::V1_18 {
+            self.required(level.get_i32("yPos"), path, "yPos", "Int");
+        } else if version >= DataVersion::V1_13 {
+            let length = if version >= DataVersion::V1_15 {
+                1024
+            } else {
+                256
+            };
+
+            if let Some(biomes) =
+                self.optional(level.get_i32_vec("Biomes"), path, "Biomes", "IntArray")
+            {
+                self.check_length(join(path, "Biomes"), length, biomes.len());
+            }
+        }
+
+        let sections_name = if version >= DataVersion::V1_18 {
+            "sections"
+        } else {
+            "Sections"
+        };
+        let sections = level.get_compound_tag_vec(sections_name);
+
+        if let Some(sections) = self.required(sections, path, sections_name, "List") {
+            for (index, section) in sections.into_iter().enumerate() {
+                let path = format!("{}[{}]", join(path, sections_name), index);
+                self.validate_section(section, &path, version);
+            }
+        }
+    }
+
+    fn validate_section(&mut self, section: &CompoundTag, path: &str, version: DataVersion) {
+        self.required(section.get_i8("Y"), path, "Y", "Byte");
+
+        for name in &["BlockLight", "SkyLight"] {
+            if let Some(light) = self.optional(section.get_i8_vec(name), path, name, "ByteArray") {
+                self.check_length(join(path, name), 2048, light.len());
+            }
+        }
+
+        if version >= DataVersion::V1_18 {
+            let block_states = section.get_compound_tag("block_states");
+
+            if let Some(block_states) =
+                self.optional(block_states, path, "block_states", "Compound")
+            {
+                let path = join(path, "block_states");
+                self.validate_palette(block_states, &path, 4096, 4);
+            }
+
+            let biomes = section.get_compound_tag("biomes");
+
+            if let Some(biomes) = self.optional(biomes, path, "biomes", "Compound") {
+                let path = join(path, "biomes");
+                self.validate_palette(biomes, &path, 64, 1);
+            }
+        } else if version >= DataVersion::V1_13 {
+            let palette = section.get_compound_tag_vec("Palette");
+            let palette = self.optional(palette, path, "Palette", "List");
+            let block_states = section.get_i64_vec("BlockStates");
+            let block_states = self.optional(block_states, path, "BlockStates", "LongArray");
+
+            match (palette, block_states) {
+                (Some(palette), Some(block_states)) => {
+                    let expected = packed_length(
+                        4096,
+                        bits_per_entry(palette.len(), 4),
+                        version < DataVersion::V1_16,
+                    );
+                    self.check_length(join(path, "BlockStates"), expected, block_states.len());
+                }
+                (Some(_), None) => self.issues.push(SchemaIssue::MissingTag {
+                    path: join(path, "BlockStates"),
+                }),
+                (None, Some(_)) => self.issues.push(SchemaIssue::MissingTag {
+                    path: join(path, "Palette"),
+                }),
+                (None, None) => {}
+            }
+        } else {
+            if let Some(blocks) =
+                self.required(section.get_i8_vec("Blocks"), path, "Blocks", "ByteArray")
+            {
+                self.check_length(join(path, "Blocks"), 4096, blocks.len());
+            }
+
+            if let Some(data) = self.required(section.get_i8_vec("Data"), path, "Data", "ByteArray")
+            {
+                self.check_length(join(path, "Data"), 2048, data.len());
+            }
+        }
+    }
+
+    /// Validates a 1.18+ paletted container of `entries` values. The
+    /// `data` array is only required when the palette has several values.
+    fn validate_palette(
+        &mut self,
+        container: &CompoundTag,
+        path: &str,
+        entries: usize,
+        minimum_bits: usize,
+    ) {
+        let palette = container.get::<&Vec<Tag>>("palette");
+        let palette_length = match self.required(palette, path, "palette", "List") {
+            Some(palette) => palette.len(),
+            None => return,
+        };
+
+        let data = self.optional(container.get_i64_vec("data"), path, "data", "LongArray");
+
+        match data {
+            Some(data) if palette_length > 1 => {
+                let bits = bits_per_entry(palette_length, minimum_bits);
+                let expected = packed_length(entries, bits, false);
+                self.check_length(join(path, "data"), expected, data.len());
+            }
+            None if palette_length > 1 => self.issues.push(SchemaIssue::MissingTag {
+                path: join(path, "data"),
+            }),
+            _ => {}
+        }
+    }
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+/// Bits used to store an index into a palette of the given length.
+fn bits_per_entry(palette_length: usize, minimum_bits: usize) -> usize {
+    let bits = (usize::BITS - palette_length.saturating_sub(1).leading_zeros()) as usize;
+
+    bits.max(minimum_bits)
+}
+
+/// Amount of longs needed to store the entries. Before 1.16 entries were
+/// packed across long boundaries.
+fn packed_length(entries: usize, bits: usize, packed: bool) -> usize {
+    if packed {
+        (entries * bits).div_ceil(64)
+    } else {
+        entries.div_ceil(64 / bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk_1_18() -> CompoundTag {
+        let mut block_states = CompoundTag::new();
+        block_states.insert_compound_tag_vec("palette", vec![CompoundTag::new(); 5]);
+        block_states.insert_i64_vec("data", vec![0; 256]);
+
+        let mut section = CompoundTag::new();
+        section.insert_i8("Y", -4);
+        section.insert_compound_tag("block_states", block_states);
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("DataVersion", 2860);
+        chunk_compound_tag.insert_i32("xPos", 0);
+        chunk_compound_tag.insert_i32("yPos", -4);
+        chunk_compound_tag.insert_i32("zPos", 0);
+        chunk_compound_tag.insert_i64("LastUpdate", 0);
+        chunk_compound_tag.insert_str("Status", "full");
+        chunk_compound_tag.insert_compound_tag_vec("sections", vec![section]);
+
+        chunk_compound_tag
+    }
+
+    #[test]
+    fn validate_valid_chunk() {
+        assert_eq!(validate_chunk(&chunk_1_18(), DataVersion::V1_18), vec![]);
+    }
+
+    #[test]
+    fn validate_chunk_issues() {
+        let mut chunk_compound_tag = chunk_1_18();
+        chunk_compound_tag.insert_str("xPos", "0");
+        chunk_compound_tag.insert_i32("DataVersion", 2730);
+
+        let mut block_states = CompoundTag::new();
+        block_states.insert_compound_tag_vec("palette", vec![CompoundTag::new(); 33]);
+        block_states.insert_i64_vec("data", vec![0; 342]);
+        let mut section = CompoundTag::new();
+        section.insert_compound_tag("block_states", block_states);
+        chunk_compound_tag.insert_compound_tag_vec("sections", vec![section]);
+
+        assert_eq!(
+            validate_chunk(&chunk_compound_tag, DataVersion::V1_18),
+            vec![
+                SchemaIssue::DataVersionMismatch { data_version: 2730 },
+                SchemaIssue::WrongType {
+                    path: String::from("xPos"),
+                    expected: "Int",
+                },
+                SchemaIssue::MissingTag {
+                    path: String::from("sections[0].Y"),
+                },
+                SchemaIssue::WrongLength {
+                    path: String::from("sections[0].block_states.data"),
+                    expected: 410,
+                    length: 342,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn block_states_length() {
+        assert_eq!(packed_length(4096, bits_per_entry(2, 4), true), 256);
+        assert_eq!(packed_length(4096, bits_per_entry(17, 4), true), 320);
+        assert_eq!(packed_length(4096, bits_per_entry(17, 4), false), 342);
+        assert_eq!(packed_length(64, bits_per_entry(3, 1), false), 2);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{ChunkLoadError, ChunkPayload, ChunkReader, FolderChunkProvider};
+use nbt::CompoundTag;
+use std::io;
+use std::marker::PhantomData;
+use std::vec;
+
+/// Iterator over every chunk of a provider, returned by `iter_chunks` and by
+/// iterating over `&mut FolderChunkProvider`.
+///
+/// Regions are visited one at a time, their header being read when the
+/// iterator reaches them, and their chunks are loaded in the order of their
+/// sectors in the file. Errors are returned with the coordinates of the
+/// chunk and the iteration continues with the next chunk. Errors reading a
+/// region header are returned at the coordinates of the north-west chunk of
+/// the region, and errors listing the regions at `(0, 0)`.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::FolderChunkProvider;
+///
+/// let mut chunk_provider = FolderChunkProvider::new("test/region");
+/// let mut loaded = 0;
+///
+/// for ((chunk_x, chunk_z), chunk_compound_tag) in &mut chunk_provider {
+///     match chunk_compound_tag {
+///         Ok(_) => loaded += 1,
+///         Err(e) => eprintln!("Chunk {} {}: {:?}", chunk_x, chunk_z, e),
+///     }
+/// }
+///
+/// assert_eq!(loaded, 277);
+/// ```
+pub struct WorldChunks<'a, T: ?Sized, P = CompoundTag> {
+    provider: &'a mut T,
+    /// Regions not visited yet, `None` until they are listed.
+    regions: Option<vec::IntoIter<(i32, i32)>>,
+    /// Chunks of the current region not loaded yet.
+    chunks: vec::IntoIter<(i32, i32)>,
+    payload: PhantomData<fn() -> P>,
+}
+
+/// Iterates over every chunk of the provider, see `WorldChunks`.
+pub fn iter_chunks<P, T>(provider: &mut T) -> WorldChunks<'_, T, P>
+where
+    P: ChunkPayload,
+    T: ChunkReader<P> + ?Sized,
+{
+    WorldChunks {
+        provider,
+        regions: None,
+        chunks: Vec::new().into_iter(),
+        payload: PhantomData,
+    }
+}
+
+impl<P, T> Iterator for WorldChunks<'_, T, P>
+where
+    P: ChunkPayload,
+    T: ChunkReader<P> + ?Sized,
+{
+    type Item = ((i32, i32), Result<P, ChunkLoadError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((chunk_x, chunk_z)) = self.chunks.next() {
+                return Some((
+                    (chunk_x, chunk_z),
+                    self.provider.load_chunk(chunk_x, chunk_z),
+                ));
+            }
+
+            let regions = match &mut self.regions {
+                Some(regions) => regions,
+                None => match self.provider.list_regions() {
+                    Ok(regions) => self.regions.get_or_insert(regions.into_iter()),
+                    // A missing region folder has no chunks.
+                    Err(e) if is_not_found(&e) => {
+                        self.regions.get_or_insert(Vec::new().into_iter())
+                    }
+                    Err(e) => {
+                        self.regions = Some(Vec::new().into_iter());
+                        return Some(((0, 0), Err(e)));
+                    }
+                },
+            };
+
+            let (region_x, region_z) = regions.next()?;
+
+            let header = match self.provider.load_region_header(region_x, region_z) {
+                Ok(header) => header,
+                Err(e) => return Some(((region_x * 32, region_z * 32), Err(e))),
+            };
+
+            let mut chunks: Vec<_> = header.chunks().collect();
+            chunks.sort_unstable_by_key(|(_, metadata)| metadata.sector_index());
+
+            self.chunks = chunks
+                .into_iter()
+                .map(|((region_chunk_x, region_chunk_z), _)| {
+                    (
+                        region_x * 32 + i32::from(region_chunk_x),
+                        region_z * 32 + i32::from(region_chunk_z),
+                    )
+                })
+                .collect::<Vec<_>>()
+                .into_iter();
+        }
+    }
+}
+
+fn is_not_found(e: &ChunkLoadError) -> bool {
+    match e.root() {
+        ChunkLoadError::ReadError { io_error } => io_error.kind() == io::ErrorKind::NotFound,
+        _ => false,
+    }
+}
+
+impl<'a, P: ChunkPayload> IntoIterator for &'a mut FolderChunkProvider<P> {
+    type Item = ((i32, i32), Result<P, ChunkLoadError>);
+    type IntoIter = WorldChunks<'a, FolderChunkProvider<P>, P>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        iter_chunks(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::{Seek, SeekFrom, Write};
+
+    #[test]
+    fn test_iter_chunks_continues_after_errors() {
+        let folder = tempfile::tempdir().unwrap();
+        let mut chunk_provider = FolderChunkProvider::new(folder.path().join("region"));
+        assert_eq!((&mut chunk_provider).into_iter().count(), 0);
+
+        // Saved in reverse order, so the sector order is the opposite of the
+        // header order.
+        for chunk_x in (0..3).rev() {
+            let mut chunk_compound_tag = CompoundTag::new();
+            chunk_compound_tag.insert_i32("xPos", chunk_x);
+            chunk_provider
+                .save_chunk(chunk_x, 0, chunk_compound_tag)
+                .unwrap();
+        }
+
+        // Corrupt the compression scheme of the chunk at (1, 0), saved in
+        // the second sector after the header.
+        let mut region_file = OpenOptions::new()
+            .write(true)
+            .open(folder.path().join("region/r.0.0.mca"))
+            .unwrap();
+        region_file.seek(SeekFrom::Start(3 * 4096 + 4)).unwrap();
+        region_file.write_all(&[42]).unwrap();
+
+        let chunks: Vec<_> = iter_chunks(&mut chunk_provider)
+            .map(|(coords, chunk_compound_tag)| (coords, chunk_compound_tag.is_ok()))
+            .collect();
+        assert_eq!(
+            chunks,
+            vec![((2, 0), true), ((1, 0), false), ((0, 0), true)]
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use crate::{ChunkSelection, FolderChunkProvider, PlayerDataProvider, StructureProvider};
+use nbt::decode::{read_gzip_compound_tag, TagDecodeError};
+use nbt::encode::write_gzip_compound_tag;
+use nbt::{CompoundTag, Tag};
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+/// Folder of the miscellaneous world data files.
+const DATA_FOLDER: &str = "data";
+
+/// Data files with a list of records centered on a block, with the name of
+/// the list inside the `data` compound.
+const CENTERED_RECORDS: [(&str, &str); 2] = [("raids", "Raids"), ("villages", "Villages")];
+
+/// Provider of the files of a whole world folder: the region folder, the
+/// player data and the gzip compressed NBT files of the `data` folder, such
+/// as `raids.dat`, `idcounts.dat` or `scoreboard.dat`.
+///
+/// Data files are named without the `.dat` extension.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::WorldFolderProvider;
+/// use nbt::CompoundTag;
+///
+/// # let world = tempfile::tempdir().unwrap();
+/// # let world = world.path();
+/// let world_provider = WorldFolderProvider::new(world);
+///
+/// let mut data = CompoundTag::new();
+/// data.insert_i32("map", 3);
+/// let mut idcounts = CompoundTag::new();
+/// idcounts.insert_compound_tag("data", data);
+/// world_provider.save_data("idcounts", &idcounts).unwrap();
+///
+/// assert_eq!(world_provider.list_data().unwrap(), vec!["idcounts"]);
+/// assert!(world_provider.try_load_data("raids").unwrap().is_none());
+/// ```
+#[derive(Debug)]
+pub struct WorldFolderProvider {
+    world_path: PathBuf,
+}
+
+impl WorldFolderProvider {
+    pub fn new<F: Into<PathBuf>>(world_folder: F) -> Self {
+        WorldFolderProvider {
+            world_path: world_folder.into(),
+        }
+    }
+
+    pub fn world_path(&self) -> &Path {
+        &self.world_path
+    }
+
+    /// Provider of the chunks in the `region` folder of the world.
+    pub fn region_provider(&self) -> FolderChunkProvider {
+        FolderChunkProvider::new(self.world_path.join("region"))
+    }
+
+    /// Provider of the entity chunks in the `entities` folder of 1.17+
+    /// worlds.
+    pub fn entities_provider(&self) -> FolderChunkProvider {
+        FolderChunkProvider::new(self.world_path.join("entities"))
+    }
+
+    /// Provider of the point of interest chunks in the `poi` folder of 1.14+
+    /// worlds.
+    pub fn poi_provider(&self) -> FolderChunkProvider {
+        FolderChunkProvider::new(self.world_path.join("poi"))
+    }
+
+    pub fn player_data_provider(&self) -> PlayerDataProvider {
+        PlayerDataProvider::new(&self.world_path)
+    }
+
+    pub fn structure_provider(&self) -> StructureProvider {
+        StructureProvider::new(&self.world_path)
+    }
+
+    /// Names of the files in the `data` folder, sorted. A missing folder is
+    /// treated as empty.
+    pub fn list_data(&self) -> Result<Vec<String>, io::Error> {
+        let data_path = self.world_path.join(DATA_FOLDER);
+        let mut names = Vec::new();
+
+        if !data_path.exists() {
+            return Ok(names);
+        }
+
+        for entry in fs::read_dir(data_path)? {
+            let file_name = entry?.file_name();
+
+            if let Some(name) = file_name
+                .to_str()
+                .and_then(|name| name.strip_suffix(".dat"))
+            {
+                names.push(name.to_string());
+            }
+        }
+
+        names.sort();
+
+        Ok(names)
+    }
+
+    pub fn load_data(&self, name: &str) -> Result<CompoundTag, TagDecodeError> {
+        load_gzip_nbt(&self.data_path(name))
+    }
+
+    /// Same as `load_data`, but returns `None` if the file doesn't exist.
+    pub fn try_load_data(&self, name: &str) -> Result<Option<CompoundTag>, TagDecodeError> {
+        match self.load_data(name) {
+            Err(TagDecodeError::IOError { io_error })
+                if io_error.kind() == io::ErrorKind::NotFound =>
+            {
+                Ok(None)
+            }
+            result => result.map(Some),
+        }
+    }
+
+    pub fn save_data(&self, name: &str, data_compound_tag: &CompoundTag) -> Result<(), io::Error> {
+        save_gzip_nbt(&self.data_path(name), data_compound_tag, false)
+    }
+
+    pub fn delete_data(&self, name: &str) -> Result<(), io::Error> {
+        fs::remove_file(self.data_path(name))
+    }
+
+    /// Removes the raids of `raids.dat` and the villages of the pre-1.14
+    /// `villages.dat` centered in the selected chunks, returning the amount
+    /// of removed records.
+    ///
+    /// Meant to be called after deleting or resetting the selected chunks,
+    /// so the game doesn't keep raids or villages without their blocks.
+    pub fn prune_data_in(&self, selection: &ChunkSelection) -> Result<usize, TagDecodeError> {
+        let mut removed = 0;
+
+        for (name, list_name) in &CENTERED_RECORDS {
+            let mut data_compound_tag = match self.try_load_data(name)? {
+                Some(data_compound_tag) => data_compound_tag,
+                None => continue,
+            };
+
+            let records = match data_compound_tag
+                .get_mut::<&mut CompoundTag>("data")
+                .ok()
+                .and_then(|data| data.get_mut::<&mut Vec<Tag>>(list_name).ok())
+            {
+                Some(records) => records,
+                None => continue,
+            };
+
+            let records_before = records.len();
+            records.retain(|record| !record_centered_in(record, selection));
+
+            if records.len() != records_before {
+                removed += records_before - records.len();
+                self.save_data(name, &data_compound_tag)
+                    .map_err(|io_error| TagDecodeError::IOError { io_error })?;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    fn data_path(&self, name: &str) -> PathBuf {
+        self.world_path
+            .join(DATA_FOLDER)
+            .join(format!("{}.dat", name))
+    }
+}
+
+/// Returns true if the record has a `CX` and `CZ` center inside a selected
+/// chunk.
+fn record_centered_in(record: &Tag, selection: &ChunkSelection) -> bool {
+    let record_compound_tag = match record {
+        Tag::Compound(record_compound_tag) => record_compound_tag,
+        _ => return false,
+    };
+
+    match (
+        record_compound_tag.get_i32("CX"),
+        record_compound_tag.get_i32("CZ"),
+    ) {
+        (Ok(x), Ok(z)) => selection.contains(x >> 4, z >> 4),
+        _ => false,
+    }
+}
+
+/// Reads a gzip compressed NBT file.
+pub(crate) fn load_gzip_nbt(path: &Path) -> Result<CompoundTag, TagDecodeError> {
+    let file = File::open(path).map_err(|io_error| TagDecodeError::IOError { io_error })?;
+
+    read_gzip_compound_tag(&mut BufReader::new(file))
+}
+
+/// Writes a gzip compressed NBT file to a temporary file first, then moves
+/// it over the previous file, which is kept as `<file>.dat_old` if `backup`
+/// is set. Missing folders are created.
+pub(crate) fn save_gzip_nbt(
+    path: &Path,
+    compound_tag: &CompoundTag,
+    backup: bool,
+) -> Result<(), io::Error> {
+    if let Some(folder_path) = path.parent() {
+        fs::create_dir_all(folder_path)?;
+    }
+
+    let temporary_path = path.with_extension("dat.tmp");
+    let mut file = File::create(&temporary_path)?;
+    write_gzip_compound_tag(&mut file, compound_tag)?;
+    file.sync_all()?;
+
+    if backup && path.exists() {
+        fs::rename(path, path.with_extension("dat_old"))?;
+    }
+
+    fs::rename(temporary_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raid(center_x: i32, center_z: i32) -> CompoundTag {
+        let mut raid = CompoundTag::new();
+        raid.insert_i32("CX", center_x);
+        raid.insert_i32("CY", 64);
+        raid.insert_i32("CZ", center_z);
+
+        raid
+    }
+
+    #[test]
+    fn test_prune_data_in() {
+        let world = tempfile::tempdir().unwrap();
+        let world_provider = WorldFolderProvider::new(world.path());
+        assert_eq!(
+            world_provider.prune_data_in(&ChunkSelection::All).unwrap(),
+            0
+        );
+
+        let mut data = CompoundTag::new();
+        data.insert_compound_tag_vec("Raids", vec![raid(8, 8), raid(-1, 8), raid(40, 8)]);
+        let mut raids = CompoundTag::new();
+        raids.insert_compound_tag("data", data);
+        world_provider.save_data("raids", &raids).unwrap();
+
+        let selection = ChunkSelection::rect((-1, 0), (0, 0));
+        assert_eq!(world_provider.prune_data_in(&selection).unwrap(), 2);
+
+        let raids = world_provider.load_data("raids").unwrap();
+        let raids = raids
+            .get_compound_tag("data")
+            .unwrap()
+            .get_compound_tag_vec("Raids")
+            .unwrap();
+        assert_eq!(raids.len(), 1);
+        assert_eq!(raids[0].get_i32("CX").unwrap(), 40);
+    }
+}
@@ -0,0 +1,386 @@
+use crate::{
+    parse_compressed_region_file_name, parse_region_file_name, ChunkReader, DataVersion,
+    FolderChunkProvider,
+};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Amount of chunks loaded per dimension to find the data version.
+const SAMPLED_CHUNKS: usize = 8;
+
+/// Kind of folder found by `WorldLayout::detect`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WorldKind {
+    /// A full save, with a `level.dat` file.
+    FullSave,
+    /// The folder of a single dimension, with `region`, `entities` or `poi`
+    /// folders but no `level.dat`.
+    Dimension,
+    /// A folder of region files only.
+    RegionDump,
+}
+
+/// Generation of the chunk format, from the data version of the chunks.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ChunkFormat {
+    /// Before 1.13, with numeric block ids.
+    PreFlattening,
+    /// From 1.13 to 1.17, with block palettes inside a `Level` compound.
+    Flattened,
+    /// Since 1.18, without the `Level` compound.
+    Modern,
+}
+
+impl ChunkFormat {
+    /// Format of chunks with the given data version, `None` for chunks
+    /// without one, which are older than 1.9.
+    pub fn of_data_version(data_version: Option<DataVersion>) -> Self {
+        match data_version {
+            Some(data_version) if data_version >= DataVersion::V1_18 => ChunkFormat::Modern,
+            Some(data_version) if data_version >= DataVersion::V1_13 => ChunkFormat::Flattened,
+            _ => ChunkFormat::PreFlattening,
+        }
+    }
+}
+
+/// Folders of one dimension found by `WorldLayout::detect`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DimensionLayout {
+    /// Dimension id like `minecraft:the_nether`, `None` when the folder is
+    /// not part of a full save.
+    pub id: Option<String>,
+    /// Folder of the dimension.
+    pub path: PathBuf,
+    /// Folder of the region files of the chunks.
+    pub region_path: Option<PathBuf>,
+    /// Folder of the entity region files, since 1.17.
+    pub entities_path: Option<PathBuf>,
+    /// Folder of the point of interest region files, since 1.14.
+    pub poi_path: Option<PathBuf>,
+    /// Highest data version of a sample of the chunks, `None` if no sampled
+    /// chunk has one.
+    pub data_version: Option<DataVersion>,
+    /// Whether chunks could be sampled, `false` for dimensions without
+    /// chunks.
+    pub has_chunks: bool,
+}
+
+impl DimensionLayout {
+    /// Format of the chunks, `None` for dimensions without chunks.
+    pub fn chunk_format(&self) -> Option<ChunkFormat> {
+        if self.has_chunks {
+            Some(ChunkFormat::of_data_version(self.data_version))
+        } else {
+            None
+        }
+    }
+
+    /// Provider of the chunks of the dimension.
+    pub fn region_provider(&self) -> Option<FolderChunkProvider> {
+        self.region_path.as_ref().map(FolderChunkProvider::new)
+    }
+
+    fn detect(id: Option<String>, path: &Path) -> Result<Self, io::Error> {
+        let existing = |name: &str| Some(path.join(name)).filter(|folder| folder.is_dir());
+        let region_path = existing("region");
+        let mut dimension = DimensionLayout {
+            id,
+            path: path.to_path_buf(),
+            entities_path: existing("entities"),
+            poi_path: existing("poi"),
+            region_path: region_path.clone(),
+            data_version: None,
+            has_chunks: false,
+        };
+
+        if let Some(region_path) = region_path {
+            dimension.sample_chunks(&region_path)?;
+        }
+
+        Ok(dimension)
+    }
+
+    fn is_dimension(path: &Path) -> bool {
+        ["region", "entities", "poi"]
+            .iter()
+            .any(|name| path.join(name).is_dir())
+    }
+
+    /// Finds the data version of the first chunks of the region folder.
+    fn sample_chunks(&mut self, region_path: &Path) -> Result<(), io::Error> {
+        let mut chunk_provider = FolderChunkProvider::new(region_path);
+        let mut sampled = 0;
+        let mut regions = match chunk_provider.list_regions() {
+            Ok(regions) => regions,
+            Err(_) => return Ok(()),
+        };
+        regions.sort_unstable();
+
+        for (region_x, region_z) in regions {
+            let header = match chunk_provider.load_region_header(region_x, region_z) {
+                Ok(header) => header,
+                Err(_) => continue,
+            };
+
+            for ((region_chunk_x, region_chunk_z), _) in header.chunks() {
+                if sampled == SAMPLED_CHUNKS {
+                    return Ok(());
+                }
+
+                let chunk_x = region_x * 32 + i32::from(region_chunk_x);
+                let chunk_z = region_z * 32 + i32::from(region_chunk_z);
+
+                // Chunks failing to load are not counted.
+                if let Ok(chunk_compound_tag) = chunk_provider.load_chunk(chunk_x, chunk_z) {
+                    sampled += 1;
+                    self.has_chunks = true;
+                    self.data_version = self
+                        .data_version
+                        .max(DataVersion::of_chunk(&chunk_compound_tag));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Layout of a world folder: its kind and where the folders of every
+/// dimension are, as guessed from the files it contains.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{WorldKind, WorldLayout};
+///
+/// let layout = WorldLayout::detect("test/region").unwrap();
+///
+/// assert_eq!(layout.kind, WorldKind::RegionDump);
+/// assert!(layout.dimensions[0].has_chunks);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WorldLayout {
+    pub kind: WorldKind,
+    /// Dimensions found, the overworld, the nether and the end first for
+    /// full saves, then custom dimensions sorted by id.
+    pub dimensions: Vec<DimensionLayout>,
+}
+
+impl WorldLayout {
+    /// Inspects the folder, which can be a full save, the folder of a
+    /// dimension or a folder of region files. Other folders return an error
+    /// of kind `NotFound`.
+    pub fn detect<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let path = path.as_ref();
+
+        if path.join("level.dat").is_file() {
+            return Self::detect_save(path);
+        }
+
+        if DimensionLayout::is_dimension(path) {
+            return Ok(WorldLayout {
+                kind: WorldKind::Dimension,
+                dimensions: vec![DimensionLayout::detect(None, path)?],
+            });
+        }
+
+        if has_region_files(path)? {
+            let mut dimension = DimensionLayout {
+                id: None,
+                path: path.to_path_buf(),
+                region_path: Some(path.to_path_buf()),
+                entities_path: None,
+                poi_path: None,
+                data_version: None,
+                has_chunks: false,
+            };
+            dimension.sample_chunks(path)?;
+
+            return Ok(WorldLayout {
+                kind: WorldKind::RegionDump,
+                dimensions: vec![dimension],
+            });
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no world found in {}", path.display()),
+        ))
+    }
+
+    fn detect_save(path: &Path) -> Result<Self, io::Error> {
+        let mut dimensions = Vec::new();
+        let vanilla_dimensions = [
+            ("minecraft:overworld", path.to_path_buf()),
+            ("minecraft:the_nether", path.join("DIM-1")),
+            ("minecraft:the_end", path.join("DIM1")),
+        ];
+
+        for (id, dimension_path) in &vanilla_dimensions {
+            if DimensionLayout::is_dimension(dimension_path) {
+                dimensions.push(DimensionLayout::detect(
+                    Some(id.to_string()),
+                    dimension_path,
+                )?);
+            }
+        }
+
+        // Custom dimensions are in `dimensions/<namespace>/<path>`, where
+        // the path can have several folders.
+        let mut custom_dimensions = Vec::new();
+        let dimensions_path = path.join("dimensions");
+
+        if dimensions_path.is_dir() {
+            for entry in fs::read_dir(&dimensions_path)? {
+                let namespace_path = entry?.path();
+
+                if let Some(namespace) = folder_name(&namespace_path) {
+                    find_custom_dimensions(
+                        &format!("{}:", namespace),
+                        &namespace_path,
+                        &mut custom_dimensions,
+                    )?;
+                }
+            }
+        }
+
+        custom_dimensions.sort();
+
+        for (id, dimension_path) in custom_dimensions {
+            dimensions.push(DimensionLayout::detect(Some(id), &dimension_path)?);
+        }
+
+        Ok(WorldLayout {
+            kind: WorldKind::FullSave,
+            dimensions,
+        })
+    }
+
+    /// Returns the dimension with the given id.
+    pub fn dimension(&self, id: &str) -> Option<&DimensionLayout> {
+        self.dimensions
+            .iter()
+            .find(|dimension| dimension.id.as_deref() == Some(id))
+    }
+}
+
+fn folder_name(path: &Path) -> Option<String> {
+    if !path.is_dir() {
+        return None;
+    }
+
+    path.file_name()?.to_str().map(str::to_string)
+}
+
+/// Adds the dimension folders below the folder, with ids starting with
+/// `id_prefix`.
+fn find_custom_dimensions(
+    id_prefix: &str,
+    path: &Path,
+    dimensions: &mut Vec<(String, PathBuf)>,
+) -> Result<(), io::Error> {
+    for entry in fs::read_dir(path)? {
+        let dimension_path = entry?.path();
+        let name = match folder_name(&dimension_path) {
+            Some(name) => name,
+            None => continue,
+        };
+        let id = format!("{}{}", id_prefix, name);
+
+        if DimensionLayout::is_dimension(&dimension_path) {
+            dimensions.push((id, dimension_path));
+        } else {
+            find_custom_dimensions(&format!("{}/", id), &dimension_path, dimensions)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the folder contains region files, compressed or not.
+fn has_region_files(path: &Path) -> Result<bool, io::Error> {
+    if !path.is_dir() {
+        return Ok(false);
+    }
+
+    for entry in fs::read_dir(path)? {
+        let file_name = entry?.file_name();
+
+        if let Some(file_name) = file_name.to_str() {
+            if parse_region_file_name(file_name).is_some()
+                || parse_compressed_region_file_name(file_name).is_some()
+            {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nbt::CompoundTag;
+
+    #[test]
+    fn test_detect_world_layout() {
+        let world = tempfile::tempdir().unwrap();
+        let world_path = world.path();
+        fs::write(world_path.join("level.dat"), []).unwrap();
+        fs::create_dir(world_path.join("region")).unwrap();
+        fs::copy(
+            "test/region/r.0.0.mca",
+            world_path.join("region").join("r.0.0.mca"),
+        )
+        .unwrap();
+        fs::create_dir_all(world_path.join("DIM-1").join("poi")).unwrap();
+        let custom_path = world_path
+            .join("dimensions")
+            .join("mod")
+            .join("sky")
+            .join("top");
+        fs::create_dir_all(&custom_path).unwrap();
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("DataVersion", 3465);
+        FolderChunkProvider::new(custom_path.join("region"))
+            .save_chunk(0, 0, chunk_compound_tag)
+            .unwrap();
+
+        let layout = WorldLayout::detect(world_path).unwrap();
+        assert_eq!(layout.kind, WorldKind::FullSave);
+        let ids: Vec<_> = layout
+            .dimensions
+            .iter()
+            .map(|dimension| dimension.id.clone().unwrap())
+            .collect();
+        assert_eq!(
+            ids,
+            vec!["minecraft:overworld", "minecraft:the_nether", "mod:sky/top"]
+        );
+
+        let overworld = layout.dimension("minecraft:overworld").unwrap();
+        assert_eq!(overworld.region_path, Some(world_path.join("region")));
+        assert_eq!(overworld.chunk_format(), Some(ChunkFormat::Flattened));
+
+        let nether = layout.dimension("minecraft:the_nether").unwrap();
+        assert_eq!(nether.region_path, None);
+        assert_eq!(nether.poi_path, Some(world_path.join("DIM-1").join("poi")));
+        assert_eq!(nether.chunk_format(), None);
+
+        let custom = layout.dimension("mod:sky/top").unwrap();
+        assert_eq!(custom.data_version, Some(DataVersion(3465)));
+        assert_eq!(custom.chunk_format(), Some(ChunkFormat::Modern));
+
+        let layout = WorldLayout::detect(world_path.join("DIM-1")).unwrap();
+        assert_eq!(layout.kind, WorldKind::Dimension);
+
+        let layout = WorldLayout::detect(world_path.join("region")).unwrap();
+        assert_eq!(layout.kind, WorldKind::RegionDump);
+
+        let error = WorldLayout::detect(world_path.join("dimensions")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::quarantine::is_corrupted_chunk_error;
+use crate::{ChunkLoadError, ChunkReader, DataVersion, WorldLayout};
+use std::collections::BTreeMap;
+
+/// Summary of the chunks of a world, see `world_report`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WorldReport {
+    /// Amount of chunks stored, including the corrupted ones.
+    pub chunks: usize,
+    /// Amount of chunks per data version, `None` for chunks without a
+    /// `DataVersion` tag. Corrupted chunks are not counted.
+    pub data_versions: BTreeMap<Option<DataVersion>, usize>,
+    /// Chunks and extent of every dimension.
+    pub dimensions: Vec<DimensionReport>,
+    /// Bytes of compressed chunk data, without the region headers and the
+    /// padding of the sectors.
+    pub compressed_bytes: u64,
+    /// Chunks whose data could not be decoded, in listing order.
+    pub corrupted_chunks: Vec<CorruptedChunk>,
+}
+
+/// Chunks of a dimension in a `WorldReport`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DimensionReport {
+    /// Dimension id like `minecraft:the_nether`, `None` for the dimension
+    /// of a provider passed to `world_report`.
+    pub id: Option<String>,
+    /// Amount of chunks stored in the dimension.
+    pub chunks: usize,
+    /// Smallest and largest chunk coordinates, `None` for dimensions
+    /// without chunks.
+    pub bounds: Option<((i32, i32), (i32, i32))>,
+}
+
+/// Chunk of a `WorldReport` which failed to load.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CorruptedChunk {
+    /// Id of the dimension of the chunk, see `DimensionReport::id`.
+    pub dimension: Option<String>,
+    /// Chunk coordinates.
+    pub chunk: (i32, i32),
+    /// Error returned when loading the chunk.
+    pub error: String,
+}
+
+/// Reads every chunk of the provider once and summarizes them.
+///
+/// Chunks whose data is corrupted are listed in the report, other errors,
+/// such as I/O errors, are returned.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{world_report, DataVersion, FolderChunkProvider};
+///
+/// let mut chunk_provider = FolderChunkProvider::new("test/region");
+/// let report = world_report(&mut chunk_provider).unwrap();
+///
+/// assert_eq!(report.chunks, 277);
+/// // 1.13.2, and a chunk without a data version.
+/// assert_eq!(report.data_versions[&Some(DataVersion(1631))], 276);
+/// assert_eq!(report.data_versions[&None], 1);
+/// assert_eq!(report.dimensions[0].bounds, Some(((0, 0), (31, 16))));
+/// assert!(report.corrupted_chunks.is_empty());
+/// ```
+pub fn world_report<P: ChunkReader + ?Sized>(
+    provider: &mut P,
+) -> Result<WorldReport, ChunkLoadError> {
+    let mut report = WorldReport::default();
+    report.add_dimension(None, provider)?;
+
+    Ok(report)
+}
+
+impl WorldReport {
+    /// Reads the chunks of the provider into a new dimension of the report.
+    fn add_dimension<P: ChunkReader + ?Sized>(
+        &mut self,
+        id: Option<String>,
+        provider: &mut P,
+    ) -> Result<(), ChunkLoadError> {
+        let mut dimension = DimensionReport {
+            id,
+            chunks: 0,
+            bounds: None,
+        };
+
+        for (region_x, region_z) in provider.list_regions()? {
+            let header = provider.load_region_header(region_x, region_z)?;
+
+            for ((region_chunk_x, region_chunk_z), _) in header.chunks() {
+                let chunk_x = (region_x * 32) + i32::from(region_chunk_x);
+                let chunk_z = (region_z * 32) + i32::from(region_chunk_z);
+
+                dimension.chunks += 1;
+                dimension.bounds = Some(match dimension.bounds {
+                    Some(((min_x, min_z), (max_x, max_z))) => (
+                        (min_x.min(chunk_x), min_z.min(chunk_z)),
+                        (max_x.max(chunk_x), max_z.max(chunk_z)),
+                    ),
+                    None => ((chunk_x, chunk_z), (chunk_x, chunk_z)),
+                });
+
+                let decoded = provider
+                    .load_chunk_raw(chunk_x, chunk_z)
+                    .and_then(|raw_chunk| {
+                        self.compressed_bytes += raw_chunk.data().len() as u64;
+                        raw_chunk.decode()
+                    });
+
+                match decoded {
+                    Ok(chunk_compound_tag) => {
+                        *self
+                            .data_versions
+                            .entry(DataVersion::of_chunk(&chunk_compound_tag))
+                            .or_default() += 1;
+                    }
+                    Err(e) if is_corrupted_chunk_error(&e) => {
+                        self.corrupted_chunks.push(CorruptedChunk {
+                            dimension: dimension.id.clone(),
+                            chunk: (chunk_x, chunk_z),
+                            error: format!("{:?}", e),
+                        });
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        self.chunks += dimension.chunks;
+        self.dimensions.push(dimension);
+
+        Ok(())
+    }
+}
+
+impl WorldLayout {
+    /// Same as `world_report`, for the chunks of every dimension of the
+    /// world, in the order of `dimensions`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::WorldLayout;
+    ///
+    /// let layout = WorldLayout::detect("test/region").unwrap();
+    /// let report = layout.report().unwrap();
+    ///
+    /// assert_eq!(report.dimensions.len(), 1);
+    /// assert_eq!(report.dimensions[0].chunks, 277);
+    /// ```
+    pub fn report(&self) -> Result<WorldReport, ChunkLoadError> {
+        let mut report = WorldReport::default();
+
+        for dimension in &self.dimensions {
+            if let Some(mut chunk_provider) = dimension.region_provider() {
+                report.add_dimension(dimension.id.clone(), &mut chunk_provider)?;
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnvilRegion, ChunkBuilder, FolderChunkProvider};
+    use nbt::CompoundTag;
+    use std::fs;
+
+    #[test]
+    fn test_world_report() {
+        let world = tempfile::tempdir().unwrap();
+        fs::write(world.path().join("level.dat"), []).unwrap();
+        fs::create_dir_all(world.path().join("DIM-1")).unwrap();
+        let overworld = FolderChunkProvider::new(world.path().join("region"));
+        let nether = FolderChunkProvider::new(world.path().join("DIM-1").join("region"));
+
+        for &(chunk_x, chunk_z) in &[(-40, 3), (5, -2), (7, 7)] {
+            let chunk_compound_tag = ChunkBuilder::new(DataVersion::V1_18).build();
+            overworld
+                .save_chunk(chunk_x, chunk_z, chunk_compound_tag)
+                .unwrap();
+        }
+        let chunk_compound_tag = ChunkBuilder::new(DataVersion::V1_16).build();
+        nether.save_chunk(1, 1, chunk_compound_tag).unwrap();
+        nether.save_chunk(2, 1, CompoundTag::new()).unwrap();
+
+        // Corrupt the data of a chunk, keeping its length.
+        let region_path = world.path().join("region").join("r.0.-1.mca");
+        let sector_index = AnvilRegion::file(&region_path)
+            .unwrap()
+            .get_metadata(5, 30)
+            .sector_index();
+        let mut bytes = fs::read(&region_path).unwrap();
+        bytes[sector_index as usize * 4096 + 5] ^= 0xFF;
+        fs::write(&region_path, bytes).unwrap();
+
+        let report = WorldLayout::detect(world.path()).unwrap().report().unwrap();
+        assert_eq!(report.chunks, 5);
+        assert_eq!(report.data_versions.len(), 3);
+        assert_eq!(report.data_versions[&Some(DataVersion::V1_18)], 2);
+        assert_eq!(report.data_versions[&Some(DataVersion::V1_16)], 1);
+        assert_eq!(report.data_versions[&None], 1);
+        assert!(report.compressed_bytes > 0);
+
+        let dimension = &report.dimensions[0];
+        assert_eq!(dimension.id.as_deref(), Some("minecraft:overworld"));
+        assert_eq!(dimension.chunks, 3);
+        assert_eq!(dimension.bounds, Some(((-40, -2), (7, 7))));
+        let dimension = &report.dimensions[1];
+        assert_eq!(dimension.id.as_deref(), Some("minecraft:the_nether"));
+        assert_eq!(dimension.bounds, Some(((1, 1), (2, 1))));
+
+        assert_eq!(report.corrupted_chunks.len(), 1);
+        let corrupted_chunk = &report.corrupted_chunks[0];
+        assert_eq!(
+            corrupted_chunk.dimension.as_deref(),
+            Some("minecraft:overworld")
+        );
+        assert_eq!(corrupted_chunk.chunk, (5, -2));
+    }
+}
@@ -1,26 +1,69 @@
-use crate::{AnvilChunkProvider, AnvilRegion, ChunkLoadError, ChunkSaveError, RegionAndOffset, ReadAndSeek};
+use crate::{
+    read_chunk_data, AnvilRegion, AnvilRegionHeader, BudgetedCache, CacheBudget, CacheStats,
+    ChunkLoadError, ChunkPayload, ChunkReader, ChunkSaveError, ChunkSelection, ListingOrder,
+    Recode, RegionAndOffset, RegionInfo, RegionReader, TimestampPolicy, WorldEditError,
+};
+use crate::region_info::count_header_chunks;
 use crate::parse_region_file_name;
+use crate::region_cache::RegionBytes;
 use nbt::CompoundTag;
-use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs::{File, OpenOptions};
 use std::io;
-use std::io::{Cursor, Read, Seek};
+use std::io::{Cursor, Read, Seek, Write};
 use std::path::Path;
-use zip::ZipArchive;
+use std::sync::{Arc, Mutex};
+use zip::read::ZipFile;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
 
 pub use zip::result::ZipError;
 
 /// The chunks are read from a zip file
+///
+/// Any `Read + Seek` source can be used, so archives that are already in
+/// memory do not need to be written to a temporary file first.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::ZipChunkProvider;
+/// use std::io::Cursor;
+///
+/// let bytes = std::fs::read("test/region.zip").unwrap();
+/// let chunk_provider = ZipChunkProvider::new(Cursor::new(bytes)).unwrap();
+///
+/// let chunk_compound_tag = chunk_provider.load_chunk(15, 3).unwrap();
+/// let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+///
+/// assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 15);
+/// ```
+///
+/// Loading only needs `&self`, so a provider over a `Send` source can be
+/// shared between threads behind an `Arc`.
+///
+/// Region files are uncompressed the first time one of their chunks is
+/// needed, and kept with their parsed header until the cache evicts them,
+/// see `with_cache_budget`.
 #[derive(Debug)]
 pub struct ZipChunkProvider<R: Read + Seek> {
-    zip_archive: ZipArchive<R>,
+    zip_archive: Mutex<ZipArchive<R>>,
     // Prefix for the region folder. Must end with "/".
     // For example: "region/", "world/region/" or "saves/world/region/"
     region_prefix: String,
     // Cache (region_x, region_z) to uncompressed file, so each region file is
     // only uncompressed once
-    cache: HashMap<(i32, i32), Vec<u8>>,
+    cache: Mutex<BudgetedCache<(i32, i32), Arc<ExtractedRegion>>>,
+    // Regions in the region folder, found the first time they are listed
+    regions: Mutex<Option<Vec<(i32, i32)>>>,
+    listing_order: ListingOrder,
+}
+
+/// Uncompressed region file, with its header parsed once.
+#[derive(Debug)]
+struct ExtractedRegion {
+    bytes: RegionBytes,
+    header: AnvilRegionHeader,
 }
 
 #[derive(Debug)]
@@ -111,7 +154,7 @@ fn find_all_region_mca<R: Read + Seek>(
         if mca_name.is_none() {
             continue;
         }
-        if let Some(coords) = parse_region_file_name(&mca_name.unwrap()) {
+        if let Some(coords) = parse_region_file_name(mca_name.unwrap()) {
             r.push(coords);
         }
     }
@@ -119,57 +162,166 @@ fn find_all_region_mca<R: Read + Seek>(
     r
 }
 
+// Opens the entry of the region file, reporting a missing entry as a missing
+// region.
+fn region_entry<'a, R: Read + Seek>(
+    zip_archive: &'a mut ZipArchive<R>,
+    region_path: &str,
+    region_x: i32,
+    region_z: i32,
+) -> Result<ZipFile<'a>, ChunkLoadError> {
+    match zip_archive.by_name(region_path) {
+        Ok(x) => Ok(x),
+        Err(ZipError::FileNotFound) => Err(ChunkLoadError::RegionNotFound { region_x, region_z }),
+        Err(ZipError::Io(io_error)) => Err(ChunkLoadError::ReadError { io_error }),
+        Err(e) => {
+            let io_error = io::Error::new(io::ErrorKind::InvalidData, e);
+            Err(ChunkLoadError::ReadError { io_error })
+        }
+    }
+}
+
 impl<R: Read + Seek> ZipChunkProvider<R> {
+    /// Creates a provider reading the overworld region folder from any
+    /// `Read + Seek` source, for example a `File` or a `Cursor<Vec<u8>>`.
     pub fn new(reader: R) -> Result<Self, ZipProviderError> {
         Self::new_with_dimension(reader, None)
     }
 
+    /// Same as `new`, but reads the region folder of the given dimension
+    /// folder, such as `"DIM-1"` or `"DIM1"`.
     pub fn new_with_dimension(reader: R, dimension: Option<&str>) -> Result<Self, ZipProviderError> {
         let mut zip_archive = ZipArchive::new(reader)?;
         let region_prefix = find_region_folder_path(&mut zip_archive, dimension)?;
-        let cache = HashMap::new();
 
-        Ok(ZipChunkProvider {
-            zip_archive,
+        Ok(Self::with_archive(zip_archive, region_prefix))
+    }
+
+    /// Same as `new`, but reads the region files in the given folder of the
+    /// archive, such as `"world/region"`, instead of searching the archive
+    /// for it. An empty folder is the root of the archive.
+    ///
+    /// Only the central directory of the archive is read, entries are not
+    /// looked at until a chunk or a list of chunks is needed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::ZipChunkProvider;
+    /// use std::fs::File;
+    ///
+    /// let file = File::open("test/region.zip").unwrap();
+    /// let chunk_provider = ZipChunkProvider::with_region_folder(file, "region").unwrap();
+    ///
+    /// assert!(chunk_provider.load_chunk(15, 3).is_ok());
+    /// ```
+    pub fn with_region_folder(reader: R, region_folder: &str) -> Result<Self, ZipProviderError> {
+        let zip_archive = ZipArchive::new(reader)?;
+        let region_folder = region_folder.trim_matches('/');
+        let region_prefix = if region_folder.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", region_folder)
+        };
+
+        Ok(Self::with_archive(zip_archive, region_prefix))
+    }
+
+    fn with_archive(zip_archive: ZipArchive<R>, region_prefix: String) -> Self {
+        ZipChunkProvider {
+            zip_archive: Mutex::new(zip_archive),
             region_prefix,
-            cache,
-        })
+            cache: Mutex::new(BudgetedCache::new(usize::MAX, None)),
+            regions: Mutex::new(None),
+            listing_order: ListingOrder::default(),
+        }
+    }
+
+    /// Accounts the uncompressed region files in the given budget, dropping
+    /// the least recently used ones when it is exceeded. They are kept until
+    /// the provider is dropped otherwise.
+    pub fn with_cache_budget(self, budget: CacheBudget) -> Self {
+        ZipChunkProvider {
+            cache: Mutex::new(BudgetedCache::new(usize::MAX, Some(budget))),
+            ..self
+        }
+    }
+
+    /// Keeps at most the given amount of bytes of uncompressed region files,
+    /// same as `with_cache_budget` with a budget used only by this provider.
+    pub fn with_cache_bytes(self, limit_bytes: u64) -> Self {
+        self.with_cache_budget(CacheBudget::new(limit_bytes))
+    }
+
+    /// Order of the listed chunks and regions. Regions are listed in the
+    /// order of the archive by default.
+    pub fn with_listing_order(self, listing_order: ListingOrder) -> Self {
+        ZipChunkProvider {
+            listing_order,
+            ..self
+        }
+    }
+
+    /// Usage of the uncompressed region files cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.lock().unwrap().stats()
     }
 
     fn region_path(&self, region_x: i32, region_z: i32) -> String {
         format!("{}r.{}.{}.mca", self.region_prefix, region_x, region_z)
     }
 
-    fn load_region_into_cache(
-        &mut self,
+    /// Returns the uncompressed region file, uncompressing it on first use.
+    fn load_region(
+        &self,
         region_x: i32,
         region_z: i32,
-    ) -> Result<(), ChunkLoadError> {
-        if !self.cache.contains_key(&(region_x, region_z)) {
-            let region_path = self.region_path(region_x, region_z);
-
-            let mut region_file = match self.zip_archive.by_name(&region_path) {
-                Ok(x) => x,
-                Err(ZipError::FileNotFound) => {
-                    return Err(ChunkLoadError::RegionNotFound { region_x, region_z })
-                }
-                Err(ZipError::Io(io_error)) => return Err(ChunkLoadError::ReadError { io_error }),
-                Err(e) => panic!("Unhandled zip error: {}", e),
-            };
+    ) -> Result<Arc<ExtractedRegion>, ChunkLoadError> {
+        if let Some(region) = self.cache.lock().unwrap().get(&(region_x, region_z)) {
+            return Ok(Arc::clone(region));
+        }
 
-            let uncompressed_size = region_file.size();
-            let mut buf = Vec::with_capacity(uncompressed_size as usize);
-            region_file.read_to_end(&mut buf)?;
+        let region_path = self.region_path(region_x, region_z);
+        let mut zip_archive = self.zip_archive.lock().unwrap();
 
-            // Insert into cache
-            self.cache.insert((region_x, region_z), buf.clone());
-        };
+        let mut region_file = region_entry(&mut zip_archive, &region_path, region_x, region_z)?;
+
+        let uncompressed_size = region_file.size();
+        let mut buf = Vec::with_capacity(uncompressed_size as usize);
+        region_file.read_to_end(&mut buf)?;
+
+        let header = AnvilRegionHeader::from_reader(&mut buf.as_slice())?;
+        let length = buf.len() as u64;
+        let region = Arc::new(ExtractedRegion {
+            bytes: RegionBytes(Arc::new(buf)),
+            header,
+        });
+
+        // Insert into cache
+        self.cache
+            .lock()
+            .unwrap()
+            .insert((region_x, region_z), Arc::clone(&region), length);
+
+        Ok(region)
+    }
+
+    /// Reads the header of a region file. Regions that are not cached are
+    /// not uncompressed, only the beginning of their entry is read.
+    fn load_header(&self, region_x: i32, region_z: i32) -> Result<AnvilRegionHeader, ChunkLoadError> {
+        if let Some(region) = self.cache.lock().unwrap().peek(&(region_x, region_z)) {
+            return Ok(region.header.clone());
+        }
+
+        let region_path = self.region_path(region_x, region_z);
+        let mut zip_archive = self.zip_archive.lock().unwrap();
+        let mut region_file = region_entry(&mut zip_archive, &region_path, region_x, region_z)?;
 
-        Ok(())
+        Ok(AnvilRegionHeader::from_reader(&mut region_file)?)
     }
 
     pub fn load_chunk(
-        &mut self,
+        &self,
         chunk_x: i32,
         chunk_z: i32,
     ) -> Result<CompoundTag, ChunkLoadError> {
@@ -180,57 +332,112 @@ impl<R: Read + Seek> ZipChunkProvider<R> {
             region_chunk_z,
         } = RegionAndOffset::from_chunk(chunk_x, chunk_z);
 
-        // Warning: the zip archive will not be updated with any writes!
-        // Any writes made by AnvilRegion will only affect the in-memory cache
-        // AnvilRegion needs Read+Seek+Write access to the reader
-        // But ZipArchive only provides Read access to the compressed files
-        // So we uncompress the file into memory, and pass the in-memory buffer
-        // to AnvilRegion
-        self.load_region_into_cache(region_x, region_z)?;
+        // The zip archive is never written, regions are only read from the
+        // in-memory uncompressed copy.
+        let region = self.load_region(region_x, region_z)?;
+        let metadata = region.header.get_metadata(region_chunk_x, region_chunk_z);
+        let mut data = Vec::new();
+        let compression_scheme = read_chunk_data(
+            &mut Cursor::new(region.bytes.as_ref()),
+            metadata,
+            region_chunk_x,
+            region_chunk_z,
+            &mut data,
+        )?;
 
-        let buf = self.cache.get_mut(&(region_x, region_z)).unwrap();
-        let mut region = AnvilRegion::new(Cursor::new(buf))?;
+        CompoundTag::decode(compression_scheme, &data)
+    }
 
-        region.read_chunk(region_chunk_x, region_chunk_z)
+    pub fn list_chunks(&self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.list_selected_chunks(&ChunkSelection::All)
     }
 
-    pub fn save_chunk(
-        &mut self,
-        _chunk_x: i32,
-        _chunk_z: i32,
-        _chunk_compound_tag: CompoundTag,
-    ) -> Result<(), ChunkSaveError> {
-        panic!("Writing to ZIP archives is not supported");
+    /// Lists the regions with the uncompressed size of their file and their
+    /// amount of chunks. Only the header of the regions is uncompressed,
+    /// unless they are already cached.
+    ///
+    /// Modification times are not reported, as zip archives store them
+    /// without a time zone.
+    pub fn list_regions_detailed(&self) -> Result<Vec<RegionInfo>, ChunkLoadError> {
+        let mut regions = Vec::new();
+
+        for (region_x, region_z) in self.find_regions() {
+            let cached = self.cache.lock().unwrap().peek(&(region_x, region_z)).cloned();
+            let (file_size, chunks) = match cached {
+                Some(region) => (
+                    region.bytes.0.len() as u64,
+                    region.header.chunks().count(),
+                ),
+                None => {
+                    let region_path = self.region_path(region_x, region_z);
+                    let mut zip_archive = self.zip_archive.lock().unwrap();
+                    let mut region_file =
+                        region_entry(&mut zip_archive, &region_path, region_x, region_z)?;
+                    let file_size = region_file.size();
+
+                    (file_size, count_header_chunks(&mut region_file, file_size)?)
+                }
+            };
+
+            regions.push(RegionInfo {
+                region: (region_x, region_z),
+                file_size,
+                modified: None,
+                chunks,
+            });
+        }
+
+        Ok(regions)
     }
 
-    pub fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
-        let regions = find_all_region_mca(&mut self.zip_archive, &self.region_prefix);
+    /// Lists the chunks in the selection, reading only the headers of the
+    /// regions, see `load_header`.
+    fn list_selected_chunks(
+        &self,
+        selection: &ChunkSelection,
+    ) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
         let mut c = vec![];
-        for (region_x, region_z) in regions {
-            self.load_region_into_cache(region_x, region_z)?;
+        for (region_x, region_z) in self.find_regions() {
+            if !selection.may_contain_region(region_x, region_z) {
+                continue;
+            }
 
-            let buf = self.cache.get_mut(&(region_x, region_z)).unwrap();
-            let region = AnvilRegion::new(Cursor::new(buf))?;
+            let header = self.load_header(region_x, region_z)?;
 
             // Insert all the non-empty chunks from this region
-            for region_chunk_z in 0..32 {
-                for region_chunk_x in 0..32 {
-                    let metadata = region.get_metadata(region_chunk_x, region_chunk_z);
-
-                    if !metadata.is_empty() {
-                        let chunk_x = (region_x * 32) + i32::from(region_chunk_x);
-                        let chunk_z = (region_z * 32) + i32::from(region_chunk_z);
-                        c.push((chunk_x, chunk_z));
-                    }
+            for ((region_chunk_x, region_chunk_z), _) in header.chunks() {
+                let chunk_x = (region_x * 32) + i32::from(region_chunk_x);
+                let chunk_z = (region_z * 32) + i32::from(region_chunk_z);
+
+                if selection.contains(chunk_x, chunk_z) {
+                    c.push((chunk_x, chunk_z));
                 }
             }
         }
 
+        self.listing_order.sort_chunks(&mut c);
+
         Ok(c)
     }
+
+    fn find_regions(&self) -> Vec<(i32, i32)> {
+        let mut regions = self
+            .regions
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| {
+                let mut zip_archive = self.zip_archive.lock().unwrap();
+                find_all_region_mca(&mut zip_archive, &self.region_prefix)
+            })
+            .clone();
+        self.listing_order.sort_regions(&mut regions);
+
+        regions
+    }
 }
 
 impl ZipChunkProvider<File> {
+    /// Opens the zip file at the given path.
     pub fn file<P: AsRef<Path>>(path: P) -> Result<Self, ZipProviderError> {
         let file = OpenOptions::new()
             .write(false)
@@ -242,35 +449,205 @@ impl ZipChunkProvider<File> {
     }
 }
 
-impl<R: Read + Seek> AnvilChunkProvider for ZipChunkProvider<R> {
-    fn get_region(&mut self, region_x: i32, region_z: i32) -> Result<Box<dyn ReadAndSeek + '_>, ChunkLoadError> {
-		self.load_region_into_cache(region_x, region_z)?;
+impl<R: Read + Seek> ChunkReader for ZipChunkProvider<R> {
+    fn get_region(&mut self, region_x: i32, region_z: i32) -> Result<RegionReader, ChunkLoadError> {
+        let region = self.load_region(region_x, region_z)?;
 
-		if let Some(bytes) = self.cache.get(&(region_x, region_z)) {
-			Ok(Box::new(Cursor::new(bytes)))
-		} else {
-			Err(ChunkLoadError::RegionNotFound { region_x, region_z })
-		}
+        Ok(Box::new(Cursor::new(region.bytes.clone())))
     }
     fn load_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<CompoundTag, ChunkLoadError> {
-        self.load_chunk(chunk_x, chunk_z)
-    }
-    fn save_chunk(
-        &mut self,
-        chunk_x: i32,
-        chunk_z: i32,
-        chunk_compound_tag: CompoundTag,
-    ) -> Result<(), ChunkSaveError> {
-        self.save_chunk(chunk_x, chunk_z, chunk_compound_tag)
+        ZipChunkProvider::load_chunk(self, chunk_x, chunk_z)
     }
     fn list_chunks(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
-        self.list_chunks()
+        ZipChunkProvider::list_chunks(self)
     }
     fn list_regions(&mut self) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
-        let regions = find_all_region_mca(&mut self.zip_archive, &self.region_prefix);
-        Ok(regions)
+        Ok(self.find_regions())
+    }
+    fn list_regions_detailed(&mut self) -> Result<Vec<RegionInfo>, ChunkLoadError> {
+        ZipChunkProvider::list_regions_detailed(self)
+    }
+    fn list_chunks_in(
+        &mut self,
+        selection: &ChunkSelection,
+    ) -> Result<Vec<(i32, i32)>, ChunkLoadError> {
+        self.list_selected_chunks(selection)
+    }
+    fn load_region_header(
+        &mut self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<AnvilRegionHeader, ChunkLoadError> {
+        self.load_header(region_x, region_z)
+    }
+}
+
+/// Folder layout used when exporting region files into a zip archive.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ZipLayout {
+    /// Region files are stored in `region/`.
+    Region,
+    /// Region files are stored in `<world name>/region/`, which is the layout
+    /// of a zipped world save.
+    World(String),
+}
+
+impl ZipLayout {
+    fn folders(&self) -> Vec<String> {
+        match self {
+            ZipLayout::Region => vec![String::from("region/")],
+            ZipLayout::World(world_name) => vec![
+                format!("{}/", world_name),
+                format!("{}/region/", world_name),
+            ],
+        }
+    }
+}
+
+/// Possible errors while exporting chunks into a zip archive.
+#[derive(Debug)]
+pub enum ZipExportError {
+    /// Chunk could not be loaded from the source provider.
+    Load(ChunkLoadError),
+    /// Chunk could not be written into the new region file.
+    Save(ChunkSaveError),
+    Zip(ZipError),
+}
+
+impl From<ChunkLoadError> for ZipExportError {
+    fn from(e: ChunkLoadError) -> Self {
+        Self::Load(e)
+    }
+}
+
+impl From<ChunkSaveError> for ZipExportError {
+    fn from(e: ChunkSaveError) -> Self {
+        Self::Save(e)
+    }
+}
+
+impl From<WorldEditError> for ZipExportError {
+    fn from(e: WorldEditError) -> Self {
+        match e {
+            WorldEditError::Load(e) => Self::Load(e),
+            WorldEditError::Save(e) => Self::Save(e),
+        }
+    }
+}
+
+impl From<ZipError> for ZipExportError {
+    fn from(e: ZipError) -> Self {
+        Self::Zip(e)
+    }
+}
+
+impl From<io::Error> for ZipExportError {
+    fn from(e: io::Error) -> Self {
+        Self::Zip(ZipError::Io(e))
+    }
+}
+
+/// Writes every selected chunk of the reader into a new zip archive.
+///
+/// Region files are regenerated from scratch, so they do not contain any of
+/// the unused sectors that the source region files may have. Regions without
+/// selected chunks are left out. Chunks are copied without decoding them
+/// unless `recode` asks for a different compression. Returns the writer once
+/// the archive is finished.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::{
+///     export_zip, ChunkSelection, FolderChunkProvider, Recode, TimestampPolicy,
+///     ZipChunkProvider, ZipLayout,
+/// };
+/// use std::io::Cursor;
+///
+/// let mut chunk_provider = FolderChunkProvider::new("test/region");
+/// let layout = ZipLayout::World(String::from("world"));
+/// let cursor = export_zip(
+///     &mut chunk_provider,
+///     Cursor::new(Vec::new()),
+///     &layout,
+///     &ChunkSelection::All,
+///     TimestampPolicy::Preserve,
+///     Recode::Preserve,
+/// )
+/// .unwrap();
+///
+/// let mut zip_chunk_provider = ZipChunkProvider::new(cursor).unwrap();
+/// assert!(zip_chunk_provider.load_chunk(4, 2).is_ok());
+/// ```
+pub fn export_zip<P, W>(
+    provider: &mut P,
+    writer: W,
+    layout: &ZipLayout,
+    selection: &ChunkSelection,
+    timestamp_policy: TimestampPolicy,
+    recode: Recode,
+) -> Result<W, ZipExportError>
+where
+    P: ChunkReader + ?Sized,
+    W: Write + Seek,
+{
+    let mut zip_writer = ZipWriter::new(writer);
+    let folders = layout.folders();
+
+    for folder in &folders {
+        zip_writer.add_directory(folder.as_str(), FileOptions::default())?;
+    }
+
+    // Sort regions so the same world always results in the same archive
+    let mut regions = provider.list_regions()?;
+    regions.sort_unstable();
+
+    let region_folder = folders.last().unwrap();
+
+    for (region_x, region_z) in regions {
+        if !selection.may_contain_region(region_x, region_z) {
+            continue;
+        }
+
+        let header = provider.load_region_header(region_x, region_z)?;
+        let mut region = AnvilRegion::new(Cursor::new(Vec::new()))?;
+        let mut region_chunks = 0;
+
+        region.write_batch(|region| {
+            for ((region_chunk_x, region_chunk_z), metadata) in header.chunks() {
+                let chunk_x = (region_x * 32) + i32::from(region_chunk_x);
+                let chunk_z = (region_z * 32) + i32::from(region_chunk_z);
+
+                if !selection.contains(chunk_x, chunk_z) {
+                    continue;
+                }
+
+                let raw_chunk = provider.load_chunk_raw(chunk_x, chunk_z)?.recode(recode)?;
+                let last_modified_timestamp =
+                    timestamp_policy.timestamp(metadata.last_modified_timestamp());
+
+                region.write_chunk_raw(
+                    region_chunk_x,
+                    region_chunk_z,
+                    &raw_chunk,
+                    last_modified_timestamp,
+                )?;
+                region_chunks += 1;
+            }
+
+            Ok::<_, ZipExportError>(())
+        })?;
+
+        if region_chunks == 0 {
+            continue;
+        }
+
+        let region_path = format!("{}r.{}.{}.mca", region_folder, region_x, region_z);
+        zip_writer.start_file(region_path, FileOptions::default())?;
+        zip_writer.write_all(region.into_inner().get_ref())?;
     }
 
+    Ok(zip_writer.finish()?)
 }
 
 #[cfg(test)]
@@ -310,7 +687,7 @@ mod tests {
         let zip_file = Path::new("test/empty_region.zip");
         assert!(zip_file.exists());
 
-        let mut z = ZipChunkProvider::file(zip_file).unwrap();
+        let z = ZipChunkProvider::file(zip_file).unwrap();
         let err = z.load_chunk(0, 0).unwrap_err();
 
         match err {
@@ -327,11 +704,160 @@ mod tests {
         let zip_file = Path::new("test/region.zip");
         assert!(zip_file.exists());
 
-        let mut z = ZipChunkProvider::file(zip_file).unwrap();
+        let z = ZipChunkProvider::file(zip_file).unwrap();
         let compound_tag = z.load_chunk(15, 3).unwrap();
         let level_tag = compound_tag.get_compound_tag("Level").unwrap();
 
         assert_eq!(level_tag.get_i32("xPos").unwrap(), 15);
         assert_eq!(level_tag.get_i32("zPos").unwrap(), 3);
     }
+
+    #[test]
+    fn read_zip_from_threads() {
+        let z = Arc::new(ZipChunkProvider::file("test/region.zip").unwrap());
+
+        let threads: Vec<_> = (0..4)
+            .map(|chunk_z| {
+                let z = Arc::clone(&z);
+                std::thread::spawn(move || z.load_chunk(15, chunk_z).is_ok())
+            })
+            .collect();
+
+        for thread in threads {
+            assert!(thread.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn read_zip_from_memory() {
+        let bytes = std::fs::read("test/region.zip").unwrap();
+
+        let z = ZipChunkProvider::new(Cursor::new(bytes)).unwrap();
+        let compound_tag = z.load_chunk(15, 3).unwrap();
+        let level_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(level_tag.get_i32("xPos").unwrap(), 15);
+        assert_eq!(level_tag.get_i32("zPos").unwrap(), 3);
+        assert_eq!(z.list_chunks().unwrap().len(), 277);
+    }
+
+    #[test]
+    fn read_zip_region_once() {
+        let file = File::open("test/region.zip").unwrap();
+        let z = ZipChunkProvider::with_region_folder(file, "/region/").unwrap();
+        assert_eq!(z.region_prefix, "region/");
+
+        for chunk_z in 0..4 {
+            assert!(z.load_chunk(15, chunk_z).is_ok());
+        }
+        assert_eq!(z.list_chunks().unwrap().len(), 277);
+
+        let stats = z.cache_stats();
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.misses, 1);
+
+        // Too small for the region, so it is uncompressed on every access.
+        let z = ZipChunkProvider::file("test/region.zip")
+            .unwrap()
+            .with_cache_bytes(1024);
+        assert!(z.load_chunk(15, 3).is_ok());
+        assert_eq!(z.cache_stats().entries, 0);
+
+        // Without uncompressing the region.
+        let regions = z.list_regions_detailed().unwrap();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].chunks, 277);
+        assert_eq!(regions[0].file_size, 1871872);
+        assert_eq!(z.list_chunks().unwrap().len(), 277);
+        assert_eq!(z.cache_stats().entries, 0);
+
+        let file = File::open("test/region.zip").unwrap();
+        let z = ZipChunkProvider::with_region_folder(file, "world/region").unwrap();
+        assert!(matches!(
+            z.load_chunk(15, 3),
+            Err(ChunkLoadError::RegionNotFound { .. })
+        ));
+        assert!(z.list_chunks().unwrap().is_empty());
+    }
+
+    #[test]
+    fn export_folder_to_zip() {
+        let mut folder_provider = crate::FolderChunkProvider::new("test/region");
+        let layout = ZipLayout::World(String::from("world"));
+        let cursor = export_zip(
+            &mut folder_provider,
+            Cursor::new(Vec::new()),
+            &layout,
+            &ChunkSelection::All,
+            TimestampPolicy::Preserve,
+            Recode::Preserve,
+        )
+        .unwrap();
+
+        let mut z = ZipChunkProvider::new(cursor).unwrap();
+        assert_eq!(z.region_prefix, "world/region/");
+        assert_eq!(z.list_regions().unwrap(), vec![(0, 0)]);
+        assert_eq!(z.list_chunks().unwrap().len(), 277);
+        assert_eq!(
+            z.load_chunk_metadata(0, 8).unwrap().last_modified_timestamp(),
+            1570215508
+        );
+
+        let compound_tag = z.load_chunk(15, 3).unwrap();
+        let level_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(level_tag.get_i32("xPos").unwrap(), 15);
+        assert_eq!(level_tag.get_i32("zPos").unwrap(), 3);
+    }
+
+    #[test]
+    fn export_selection_to_zip() {
+        let mut folder_provider = crate::FolderChunkProvider::new("test/region");
+        let cursor = export_zip(
+            &mut folder_provider,
+            Cursor::new(Vec::new()),
+            &ZipLayout::Region,
+            &ChunkSelection::rect((15, 3), (16, 3)),
+            TimestampPolicy::Preserve,
+            Recode::Preserve,
+        )
+        .unwrap();
+
+        let z = ZipChunkProvider::new(cursor).unwrap();
+        let mut chunks = z.list_chunks().unwrap();
+        chunks.sort_unstable();
+        assert_eq!(chunks, vec![(15, 3), (16, 3)]);
+
+        let cursor = export_zip(
+            &mut folder_provider,
+            Cursor::new(Vec::new()),
+            &ZipLayout::Region,
+            &ChunkSelection::rect((-10, -10), (-1, -1)),
+            TimestampPolicy::Preserve,
+            Recode::Preserve,
+        )
+        .unwrap();
+
+        let mut z = ZipChunkProvider::new(cursor).unwrap();
+        assert!(z.list_regions().unwrap().is_empty());
+    }
+
+    #[test]
+    fn export_empty_provider_to_zip() {
+        let bytes = std::fs::read("test/empty_region.zip").unwrap();
+        let mut empty_provider = ZipChunkProvider::new(Cursor::new(bytes)).unwrap();
+        let cursor = export_zip(
+            &mut empty_provider,
+            Cursor::new(Vec::new()),
+            &ZipLayout::Region,
+            &ChunkSelection::All,
+            TimestampPolicy::Refresh,
+            Recode::Preserve,
+        )
+        .unwrap();
+
+        let z = ZipChunkProvider::new(cursor).unwrap();
+        assert_eq!(z.region_prefix, "region/");
+        assert!(z.list_chunks().unwrap().is_empty());
+    }
 }